@@ -0,0 +1,187 @@
+use actix_web::{web, HttpResponse, HttpRequest, HttpMessage};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{models::{AssignPricingPlanRequest, UpdatePricingPlanRequest, UpdateRollingReserveRequest, UpdateMfaPolicyRequest, UpdateRateLimitTierRequest}, errors::DefiantError, AppState, services::{pricing::PricingService, reserve_service::ReserveService, mfa_service::MfaService, security_service::SecurityService}, custom_middleware::auth::Claims};
+
+/// Admin routes authenticate with the operator's JWT rather than a merchant API key.
+fn require_admin(req: &HttpRequest) -> Result<(), DefiantError> {
+    match req.extensions().get::<Claims>() {
+        Some(claims) if claims.role == "admin" => Ok(()),
+        _ => Err(DefiantError::AuthorizationError("Admin role required".into())),
+    }
+}
+
+pub async fn get_merchant_pricing(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    
+    let pricing_service = PricingService::new(state.db.clone(), state.config.clone());
+    let pricing = pricing_service.effective_pricing(path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(pricing))
+}
+
+pub async fn assign_pricing_plan(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Json<AssignPricingPlanRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    data.validate()?;
+    
+    let pricing_service = PricingService::new(state.db.clone(), state.config.clone());
+    let pricing = pricing_service.assign_plan(path.into_inner(), data.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(pricing))
+}
+
+pub async fn update_pricing_plan(
+    req: HttpRequest,
+    path: web::Path<String>,
+    data: web::Json<UpdatePricingPlanRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    data.validate()?;
+    
+    let code = path.into_inner();
+    if code.is_empty() || code.len() > 50 {
+        return Err(DefiantError::ValidationError("Plan code must be 1 to 50 characters".into()));
+    }
+    
+    let pricing_service = PricingService::new(state.db.clone(), state.config.clone());
+    let plan = pricing_service.update_plan(&code, data.into_inner()).await?;
+    
+    info!("Pricing plan updated: {}", plan.plan.code);
+    
+    Ok(HttpResponse::Ok().json(plan))
+}
+
+pub async fn get_rolling_reserve(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    
+    let reserve_service = ReserveService::new(state.db.clone());
+    let reserve = reserve_service
+        .get_reserve(path.into_inner())
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("No rolling reserve configured".into()))?;
+    
+    Ok(HttpResponse::Ok().json(reserve))
+}
+
+pub async fn update_rolling_reserve(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Json<UpdateRollingReserveRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    data.validate()?;
+    
+    let reserve_service = ReserveService::new(state.db.clone());
+    let reserve = reserve_service.update_reserve(path.into_inner(), data.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(reserve))
+}
+
+pub async fn remove_rolling_reserve(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    
+    let reserve_service = ReserveService::new(state.db.clone());
+    reserve_service.remove_reserve(path.into_inner()).await?;
+    
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_mfa_policy(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    
+    let mfa_service = MfaService::new(state.db.clone(), state.config.clone());
+    let policy = mfa_service.policy(path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+/// Once MFA is required, the merchant's users cannot sign in to the dashboard until
+/// they enroll. Existing sessions are left running.
+pub async fn update_mfa_policy(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Json<UpdateMfaPolicyRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    data.validate()?;
+    
+    let mfa_service = MfaService::new(state.db.clone(), state.config.clone());
+    let policy = mfa_service.update_policy(path.into_inner(), data.into_inner()).await?;
+    
+    info!("MFA policy updated for merchant {}", policy.merchant_id);
+    
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+pub async fn get_rate_limit_policy(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    
+    let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+    let policy = security_service.rate_limit_policy(path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+/// Takes effect within seconds on every worker; buckets keep their current tokens.
+pub async fn update_rate_limit_tier(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Json<UpdateRateLimitTierRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_admin(&req)?;
+    data.validate()?;
+    
+    let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+    let policy = security_service
+        .update_rate_limit_tier(&state.redis, path.into_inner(), data.into_inner())
+        .await?;
+    
+    Ok(HttpResponse::Ok().json(policy))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/admin")
+            .route("/merchants/{merchant_id}/pricing", web::get().to(get_merchant_pricing))
+            .route("/merchants/{merchant_id}/pricing_plan", web::put().to(assign_pricing_plan))
+            .route("/merchants/{merchant_id}/reserve", web::get().to(get_rolling_reserve))
+            .route("/merchants/{merchant_id}/reserve", web::put().to(update_rolling_reserve))
+            .route("/merchants/{merchant_id}/reserve", web::delete().to(remove_rolling_reserve))
+            .route("/merchants/{merchant_id}/mfa_policy", web::get().to(get_mfa_policy))
+            .route("/merchants/{merchant_id}/mfa_policy", web::put().to(update_mfa_policy))
+            .route("/merchants/{merchant_id}/rate_limit", web::get().to(get_rate_limit_policy))
+            .route("/merchants/{merchant_id}/rate_limit", web::put().to(update_rate_limit_tier))
+            .route("/pricing_plans/{code}", web::put().to(update_pricing_plan))
+    );
+}
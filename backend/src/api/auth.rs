@@ -0,0 +1,278 @@
+use actix_web::{web, HttpResponse, HttpRequest, HttpMessage};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{models::{AcceptInvitationRequest, CreateSessionRequest, RefreshTokenRequest, VerifyMfaRequest, ForgotPasswordRequest, ResetPasswordRequest, VerifyEmailRequest}, errors::DefiantError, AppState, db::AuthenticatedKey, services::{auth_service::{AuthService, merchant_subject}, mfa_service::MfaService, security_service::SignedRequest, team_service::TeamService, verification_service::{VerificationService, EmailOwner}}, custom_middleware::{auth::Claims, rate_limit::client_ip}, api::v1::permissions::bearer_token};
+
+/// Who is enrolling in MFA: the signed-in user, or a merchant signing in with its
+/// secret key, which must enroll before its first session once MFA is required.
+fn mfa_subject(req: &HttpRequest) -> Result<(String, Option<Uuid>), DefiantError> {
+    let extensions = req.extensions();
+    if let Some(claims) = extensions.get::<Claims>() {
+        let merchant_id = claims.merchant_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+        return Ok((claims.sub.clone(), merchant_id));
+    }
+    match extensions.get::<AuthenticatedKey>() {
+        Some(key) => Ok((merchant_subject(key)?, Some(key.merchant_id))),
+        None => Err(DefiantError::AuthenticationError("Authentication required".into())),
+    }
+}
+
+/// Starts a dashboard session for a team member signing in with their email and
+/// password, or for the merchant whose secret key authenticates the request, checking
+/// the second factor when MFA is enabled. Skipped by the auth middleware.
+pub async fn create_session(
+    req: HttpRequest,
+    data: Option<web::Json<CreateSessionRequest>>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let request = data.map(web::Json::into_inner).unwrap_or_default();
+    request.validate()?;
+    
+    let client_ip = client_ip(&req, state.config.trust_proxy_headers);
+    let auth_service = AuthService::new(state.db.clone(), state.config.clone());
+    let tokens = if request.email.is_some() {
+        auth_service.create_member_session(request, client_ip).await?
+    } else {
+        let signed = req.extensions().get::<SignedRequest>().cloned();
+        auth_service.create_merchant_session(&state.redis, bearer_token(&req)?, signed.as_ref(), request, client_ip).await?
+    };
+    
+    Ok(HttpResponse::Created()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(tokens))
+}
+
+/// Skipped by the auth middleware: the refresh token in the body is the credential,
+/// since the access token has usually expired by now.
+pub async fn refresh(
+    data: web::Json<RefreshTokenRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let auth_service = AuthService::new(state.db.clone(), state.config.clone());
+    let tokens = auth_service.refresh(&data.refresh_token).await?;
+    
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(tokens))
+}
+
+pub async fn logout(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let sid = req.extensions()
+        .get::<Claims>()
+        .map(|claims| claims.sid.clone())
+        .ok_or_else(|| DefiantError::AuthenticationError("An access token is required".into()))?;
+    
+    let auth_service = AuthService::new(state.db.clone(), state.config.clone());
+    auth_service.logout(sid.as_deref()).await?;
+    
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn setup_mfa(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let (subject, merchant_id) = mfa_subject(&req)?;
+    
+    let mfa_service = MfaService::new(state.db.clone(), state.config.clone());
+    let setup = mfa_service.setup(&subject, merchant_id).await?;
+    
+    Ok(HttpResponse::Created()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(setup))
+}
+
+pub async fn verify_mfa(
+    req: HttpRequest,
+    data: web::Json<VerifyMfaRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    let (subject, _) = mfa_subject(&req)?;
+    
+    let mfa_service = MfaService::new(state.db.clone(), state.config.clone());
+    let status = mfa_service
+        .verify_enrollment(&subject, &data.code, client_ip(&req, state.config.trust_proxy_headers))
+        .await?;
+    
+    Ok(HttpResponse::Ok().json(status))
+}
+
+/// Skipped by the auth middleware: the invitation token is the credential.
+pub async fn accept_invitation(
+    data: web::Json<AcceptInvitationRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let team_service = TeamService::new(state.db.clone(), state.config.clone());
+    let tokens = team_service.accept_invitation(data.into_inner()).await?;
+    
+    Ok(HttpResponse::Created()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(tokens))
+}
+
+/// Whose email is being verified: the signed-in team member or merchant, or the
+/// merchant whose secret key authenticates the request.
+fn email_owner(req: &HttpRequest) -> Result<EmailOwner, DefiantError> {
+    let extensions = req.extensions();
+    if let Some(claims) = extensions.get::<Claims>() {
+        let id = Uuid::parse_str(&claims.sub)
+            .map_err(|_| DefiantError::AuthorizationError("This session has no email to verify".into()))?;
+        return match claims.role.as_str() {
+            "team_member" => Ok(EmailOwner::TeamMember(id)),
+            "merchant" => Ok(EmailOwner::Merchant(id)),
+            _ => Err(DefiantError::AuthorizationError("This session has no email to verify".into())),
+        };
+    }
+    match extensions.get::<AuthenticatedKey>() {
+        Some(key) => {
+            merchant_subject(key)?;
+            Ok(EmailOwner::Merchant(key.merchant_id))
+        }
+        None => Err(DefiantError::AuthenticationError("Authentication required".into())),
+    }
+}
+
+/// Skipped by the auth middleware. Always accepted, whether or not the email
+/// belongs to a team member.
+pub async fn forgot_password(
+    data: web::Json<ForgotPasswordRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let verification_service = VerificationService::new(state.db.clone(), state.config.clone());
+    verification_service.forgot_password(data.into_inner()).await?;
+    
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Skipped by the auth middleware: the emailed reset token is the credential.
+pub async fn reset_password(
+    data: web::Json<ResetPasswordRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let verification_service = VerificationService::new(state.db.clone(), state.config.clone());
+    verification_service.reset_password(data.into_inner()).await?;
+    
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn request_email_verification(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let owner = email_owner(&req)?;
+    
+    let verification_service = VerificationService::new(state.db.clone(), state.config.clone());
+    let status = verification_service.send_email_verification(owner).await?;
+    
+    Ok(HttpResponse::Accepted().json(status))
+}
+
+/// Skipped by the auth middleware: the emailed verification token is the credential.
+pub async fn verify_email(
+    data: web::Json<VerifyEmailRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let verification_service = VerificationService::new(state.db.clone(), state.config.clone());
+    let status = verification_service.verify_email(data.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(status))
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/auth")
+            .route("/token", web::post().to(create_session))
+            .route("/refresh", web::post().to(refresh))
+            .route("/logout", web::post().to(logout))
+            .route("/mfa/setup", web::post().to(setup_mfa))
+            .route("/mfa/verify", web::post().to(verify_mfa))
+            .route("/invitations/accept", web::post().to(accept_invitation))
+            .route("/password/forgot", web::post().to(forgot_password))
+            .route("/password/reset", web::post().to(reset_password))
+            .route("/email/verification", web::post().to(request_email_verification))
+            .route("/email/verify", web::post().to(verify_email))
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test};
+    use sqlx::PgPool;
+    
+    use crate::test_support;
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn unsigned_session_exchange_is_refused(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let api_key = test_support::secret_key(&pool, merchant_id, None).await;
+        test_support::enable_request_signing(&pool, merchant_id).await;
+        let app = test::init_service(test_support::app(test_support::state(pool))).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/api/auth/token")
+            .insert_header(("Authorization", format!("Bearer {}", api_key)))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn session_exchange_outside_key_allowlist_is_refused(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let api_key = test_support::secret_key(&pool, merchant_id, Some(vec!["203.0.113.0/24".into()])).await;
+        let app = test::init_service(test_support::app(test_support::state(pool))).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/api/auth/token")
+            .insert_header(("Authorization", format!("Bearer {}", api_key)))
+            .insert_header(("X-Real-IP", "198.51.100.7"))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn session_keeps_key_allowlist(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let api_key = test_support::secret_key(&pool, merchant_id, Some(vec!["203.0.113.0/24".into()])).await;
+        let app = test::init_service(test_support::app(test_support::state(pool))).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/api/auth/token")
+            .insert_header(("Authorization", format!("Bearer {}", api_key)))
+            .insert_header(("X-Real-IP", "203.0.113.7"))
+            .to_request();
+        let tokens: serde_json::Value = test::call_and_read_body_json(&app, request).await;
+        let access_token = tokens["access_token"].as_str().expect("access token issued");
+        
+        let list_webhooks = |ip: &str| {
+            test::TestRequest::get()
+                .uri("/api/v1/webhooks")
+                .insert_header(("Authorization", format!("Bearer {}", access_token)))
+                .insert_header(("X-Real-IP", ip.to_string()))
+                .to_request()
+        };
+        let inside = test::call_service(&app, list_webhooks("203.0.113.7")).await;
+        let outside = test::call_service(&app, list_webhooks("198.51.100.7")).await;
+        
+        assert_eq!(inside.status(), StatusCode::OK);
+        assert_eq!(outside.status(), StatusCode::FORBIDDEN);
+    }
+}
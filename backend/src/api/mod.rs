@@ -1,6 +1,7 @@
 pub mod v1;
 pub mod auth;
 pub mod admin;
+pub mod oauth;
 
 use actix_web::web;
 
@@ -10,5 +11,6 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .configure(v1::configure)
             .configure(auth::configure)
             .configure(admin::configure)
+            .configure(oauth::configure)
     );
 }
\ No newline at end of file
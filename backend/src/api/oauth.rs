@@ -0,0 +1,83 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use crate::{models::{AuthorizationDecision, AuthorizationRequest, OAuthTokenLookup, OAuthTokenRequest}, errors::DefiantError, AppState, services::oauth_service::OAuthService, api::v1::permissions::bearer_token};
+
+/// Consent details for the dashboard to show. Called with the signed-in merchant's
+/// access token and the query string the app sent them with.
+pub async fn authorization_details(
+    req: HttpRequest,
+    query: web::Query<AuthorizationRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    query.validate()?;
+    
+    let oauth_service = OAuthService::new(state.db.clone());
+    let consent = oauth_service.consent(&query, bearer_token(&req)?).await?;
+    
+    Ok(HttpResponse::Ok().json(consent))
+}
+
+/// Approves or denies the request. The dashboard sends the browser on to
+/// `redirect_to`, which carries the code or the denial back to the app.
+pub async fn decide_authorization(
+    req: HttpRequest,
+    data: web::Json<AuthorizationDecision>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let oauth_service = OAuthService::new(state.db.clone());
+    let redirect = oauth_service.decide(&data.request, data.approved, bearer_token(&req)?).await?;
+    
+    Ok(HttpResponse::Ok().json(redirect))
+}
+
+/// Skipped by the auth middleware: the app authenticates with its client secret
+/// in the form body.
+pub async fn token(
+    form: web::Form<OAuthTokenRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let oauth_service = OAuthService::new(state.db.clone());
+    let tokens = oauth_service.exchange(form.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(tokens))
+}
+
+/// Skipped by the auth middleware, like `token`.
+pub async fn introspect(
+    form: web::Form<OAuthTokenLookup>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let oauth_service = OAuthService::new(state.db.clone());
+    let introspection = oauth_service.introspect(form.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(introspection))
+}
+
+/// Skipped by the auth middleware, like `token`.
+pub async fn revoke(
+    form: web::Form<OAuthTokenLookup>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let oauth_service = OAuthService::new(state.db.clone());
+    oauth_service.revoke(form.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().finish())
+}
+
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/oauth")
+            .route("/authorize", web::get().to(authorization_details))
+            .route("/authorize", web::post().to(decide_authorization))
+            .route("/token", web::post().to(token))
+            .route("/introspect", web::post().to(introspect))
+            .route("/revoke", web::post().to(revoke))
+    );
+}
@@ -1,11 +1,38 @@
-use actix_web::web;
-use crate::middleware::auth::AuthenticatedUser;
+use actix_web::{web, HttpMessage, HttpRequest};
+use tracing::error;
+use crate::{errors::DefiantError, middleware::auth::AuthenticatedUser};
 
+#[macro_use]
+pub mod permissions;
+pub mod conditional;
 pub mod payments;
 pub mod customers;
 pub mod webhooks;
 pub mod subscriptions;
 pub mod invoices;
+pub mod payment_links;
+pub mod checkout;
+pub mod reports;
+pub mod quotes;
+pub mod events;
+pub mod mit_agreements;
+pub mod consents;
+pub mod terminal;
+pub mod settlement_batches;
+pub mod account;
+pub mod crypto_wallets;
+pub mod fx_rates;
+pub mod statement_descriptors;
+pub mod fraud;
+pub mod ledger;
+pub mod payouts;
+pub mod connect;
+pub mod api_keys;
+pub mod tokens;
+pub mod team;
+pub mod oauth_clients;
+pub mod security;
+pub mod audit_logs;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
     cfg.service(
@@ -13,29 +40,207 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
             .service(
                 web::scope("/payments")
                     .route("", web::post().to(payments::create_payment))
+                    .route("/metadata:batch_update", web::post().to(payments::batch_update_metadata))
+                    .route("/metadata:batch_update/{batch_id}", web::get().to(payments::get_metadata_batch_update))
+                    .route("/search", web::get().to(payments::search_payments))
                     .route("/{payment_id}", web::get().to(payments::get_payment))
                     .route("/{payment_id}/capture", web::post().to(payments::capture_payment))
                     .route("/{payment_id}/refund", web::post().to(payments::refund_payment))
+                    .route("/{payment_id}/receipt", web::post().to(payments::send_receipt))
+                    .route("/{payment_id}/status", web::get().to(payments::get_payment_status))
+                    .route("/{payment_id}/confirm", web::post().to(payments::confirm_payment))
+                    .route("/{payment_id}/refund_address", web::post().to(payments::register_refund_address))
+                    .route("/{payment_id}/crypto_refunds", web::post().to(payments::refund_crypto_payment))
+                    .route("/{payment_id}/crypto_refunds/{refund_id}", web::get().to(payments::get_crypto_refund))
+                    .route("/{payment_id}/mark_fraudulent", web::post().to(payments::mark_fraudulent))
+                    .route("/{payment_id}/mark_safe", web::post().to(payments::mark_safe))
                     .route("", web::get().to(payments::list_payments))
             )
+            .service(
+                web::scope("/reviews")
+                    .route("", web::get().to(payments::list_reviews))
+                    .route("/rule_stats", web::get().to(payments::fraud_rule_stats))
+            )
+            .service(
+                web::scope("/fraud")
+                    .route("/settings", web::get().to(fraud::get_fraud_settings))
+                    .route("/settings", web::put().to(fraud::update_fraud_settings))
+            )
+            .service(
+                web::scope("/balance")
+                    .route("", web::get().to(ledger::get_balance))
+                    .route("/conversions", web::get().to(ledger::list_balance_conversions))
+            )
+            .service(
+                web::scope("/balance_transactions")
+                    .route("", web::get().to(ledger::list_balance_transactions))
+            )
+            .service(
+                web::scope("/ledger")
+                    .route("/balances", web::get().to(ledger::get_ledger_balances))
+                    .route("/entries", web::get().to(ledger::list_journal_entries))
+            )
+            .service(
+                web::scope("/payouts")
+                    .route("", web::post().to(payouts::create_payout))
+                    .route("", web::get().to(payouts::list_payouts))
+                    .route("/upcoming", web::get().to(payouts::get_upcoming_payouts))
+                    .route("/{payout_id}", web::get().to(payouts::get_payout))
+                    .route("/{payout_id}/cancel", web::post().to(payouts::cancel_payout))
+                    .route("/{payout_id}/transactions", web::get().to(payouts::list_payout_transactions))
+            )
+            .service(
+                web::scope("/connected_accounts")
+                    .route("", web::post().to(connect::create_connected_account))
+                    .route("", web::get().to(connect::list_connected_accounts))
+                    .route("/{account_id}", web::get().to(connect::get_connected_account))
+                    .route("/{account_id}/balance", web::get().to(connect::get_connected_account_balance))
+            )
+            .service(
+                web::scope("/transfers")
+                    .route("", web::post().to(connect::create_transfer))
+                    .route("", web::get().to(connect::list_transfers))
+                    .route("/{transfer_id}", web::get().to(connect::get_transfer))
+                    .route("/{transfer_id}/reversals", web::post().to(connect::create_transfer_reversal))
+                    .route("/{transfer_id}/reversals", web::get().to(connect::list_transfer_reversals))
+            )
             .service(
                 web::scope("/customers")
                     .wrap(AuthenticatedUser)
                     .route("", web::post().to(customers::create_customer))
+                    .route("/metadata:batch_update", web::post().to(customers::batch_update_metadata))
+                    .route("/metadata:batch_update/{batch_id}", web::get().to(customers::get_metadata_batch_update))
+                    .route("/search", web::get().to(customers::search_customers))
                     .route("/{customer_id}", web::get().to(customers::get_customer))
                     .route("/{customer_id}", web::put().to(customers::update_customer))
                     .route("/{customer_id}", web::delete().to(customers::delete_customer))
                     .route("", web::get().to(customers::list_customers))
                     .route("/{customer_id}/payment_methods", web::get().to(customers::list_payment_methods))
                     .route("/{customer_id}/balance_transactions", web::get().to(customers::get_balance_transactions))
+                    .route("/{customer_id}/communications", web::get().to(customers::list_communications))
+                    .route("/{customer_id}/consents", web::get().to(customers::list_consents))
             )
             .service(
                 web::scope("/webhooks")
                     .route("/stripe", web::post().to(webhooks::handle_stripe_webhook))
+                    .route("/email_receipts", web::post().to(webhooks::handle_email_receipt))
                     .route("/{webhook_id}", web::get().to(webhooks::get_webhook))
+                    .route("/{webhook_id}", web::put().to(webhooks::update_webhook))
+                    .route("/{webhook_id}", web::delete().to(webhooks::delete_webhook))
+                    .route("/{webhook_id}/deliveries", web::get().to(webhooks::list_webhook_deliveries))
+                    .route("/{webhook_id}/rotate_secret", web::post().to(webhooks::rotate_webhook_secret))
                     .route("", web::post().to(webhooks::create_webhook))
                     .route("", web::get().to(webhooks::list_webhooks))
             )
+            .service(
+                web::scope("/events")
+                    .route("", web::get().to(events::list_events))
+                    .route("/poll", web::get().to(events::poll_events))
+                    .route("/stream", web::get().to(events::stream_events))
+                    .route("/replay", web::post().to(events::replay_events))
+                    .route("/replays/{replay_id}", web::get().to(events::get_event_replay))
+                    .route("/{event_id}", web::get().to(events::get_event))
+            )
+            .service(
+                web::scope("/event_types")
+                    .route("", web::get().to(events::list_event_types))
+            )
+            .service(
+                web::scope("/fx_rates")
+                    .route("", web::get().to(fx_rates::get_fx_rate))
+            )
+            .service(
+                web::scope("/statement_descriptors")
+                    .route("/preview", web::post().to(statement_descriptors::preview_statement_descriptor))
+            )
+            .service(
+                web::scope("/mit_agreements")
+                    .route("/{agreement_id}", web::get().to(mit_agreements::get_mit_agreement))
+                    .route("/{agreement_id}/revoke", web::post().to(mit_agreements::revoke_mit_agreement))
+            )
+            .service(
+                web::scope("/consents")
+                    .route("/{consent_id}", web::get().to(consents::get_consent))
+            )
+            .service(
+                web::scope("/terminal")
+                    .route("/keys", web::post().to(terminal::register_terminal_key))
+                    .route("/keys", web::get().to(terminal::list_terminal_keys))
+            )
+            .service(
+                web::scope("/settlement_batches")
+                    .route("/{batch_id}", web::get().to(settlement_batches::get_settlement_batch))
+                    .route("/{batch_id}/file", web::get().to(settlement_batches::download_settlement_file))
+                    .route("/{batch_id}/acknowledgment", web::post().to(settlement_batches::acknowledge_settlement_batch))
+            )
+            .service(
+                web::scope("/account")
+                    .route("/go_live", web::get().to(account::get_go_live_checklist))
+                    .route("/live_keys", web::post().to(account::create_live_key))
+                    .route("/api_keys/{key_id}/roll", web::post().to(account::roll_api_key))
+                    .route("/security", web::get().to(account::get_security_settings))
+                    .route("/security/trusted_sources", web::post().to(account::create_trusted_source))
+                    .route("/security/trusted_sources/{source_id}", web::delete().to(account::delete_trusted_source))
+                    .route("/security/ip_violations", web::get().to(account::list_ip_violations))
+                    .route("/security/request_signing", web::post().to(account::enable_request_signing))
+                    .route("/security/request_signing", web::delete().to(account::disable_request_signing))
+                    .route("/risk_settings", web::get().to(account::get_risk_settings))
+                    .route("/risk_settings", web::put().to(account::update_risk_settings))
+                    .route("/payout_schedule", web::get().to(account::get_payout_schedule))
+                    .route("/payout_schedule", web::put().to(account::update_payout_schedule))
+            )
+            .service(
+                web::scope("/api_keys")
+                    .route("", web::post().to(api_keys::create_api_key))
+                    .route("", web::get().to(api_keys::list_api_keys))
+                    .route("/{key_id}/roll", web::post().to(api_keys::roll_api_key))
+                    .route("/{key_id}/allowed_ips", web::put().to(api_keys::update_api_key_allowlist))
+                    .route("/{key_id}", web::delete().to(api_keys::revoke_api_key))
+            )
+            .service(
+                web::scope("/tokens")
+                    .route("", web::post().to(tokens::create_card_token))
+            )
+            .service(
+                web::scope("/team")
+                    .route("/members", web::post().to(team::invite_team_member))
+                    .route("/members", web::get().to(team::list_team_members))
+                    .route("/members/{member_id}", web::patch().to(team::update_team_member))
+                    .route("/members/{member_id}", web::delete().to(team::remove_team_member))
+            )
+            .service(
+                web::scope("/oauth/clients")
+                    .route("", web::post().to(oauth_clients::create_oauth_client))
+                    .route("", web::get().to(oauth_clients::list_oauth_clients))
+            )
+            .service(
+                web::scope("/security")
+                    .route("/events", web::get().to(security::list_security_events))
+            )
+            .service(
+                web::scope("/audit_logs")
+                    .route("", web::get().to(audit_logs::list_audit_logs))
+            )
+            .service(
+                web::scope("/crypto_wallets")
+                    .route("", web::post().to(crypto_wallets::register_crypto_wallet))
+                    .route("", web::get().to(crypto_wallets::list_crypto_wallets))
+                    .route("/settlement", web::get().to(crypto_wallets::get_crypto_settlement))
+                    .route("/settlement", web::put().to(crypto_wallets::update_crypto_settlement))
+            )
+            .service(
+                web::scope("/crypto_payouts")
+                    .route("", web::get().to(crypto_wallets::list_crypto_payouts))
+                    .route("/{payout_id}", web::get().to(crypto_wallets::get_crypto_payout))
+            )
+            .service(
+                web::scope("/crypto")
+                    .route("/fee_estimates", web::get().to(crypto_wallets::get_fee_estimates))
+            )
+            .service(
+                web::scope("/webhook_deliveries")
+                    .route("/{delivery_id}/retry", web::post().to(webhooks::retry_webhook_delivery))
+            )
             .service(
                 web::scope("/subscriptions")
                     .wrap(AuthenticatedUser)
@@ -52,8 +257,55 @@ pub fn configure(cfg: &mut web::ServiceConfig) {
                     .route("/{invoice_id}", web::get().to(invoices::get_invoice))
                     .route("/{invoice_id}/pay", web::post().to(invoices::pay_invoice))
                     .route("/{invoice_id}/void", web::post().to(invoices::void_invoice))
+                    .route("/{invoice_id}/mark_uncollectible", web::post().to(invoices::mark_uncollectible))
+                    .route("/{invoice_id}/send", web::post().to(invoices::send_invoice))
                     .route("", web::get().to(invoices::list_invoices))
                     .route("/upcoming", web::get().to(invoices::get_upcoming_invoice))
             )
+            .service(
+                web::scope("/payment_links")
+                    .route("", web::post().to(payment_links::create_payment_link))
+                    .route("/{link_id}", web::get().to(payment_links::get_payment_link))
+                    .route("/{link_id}/restock", web::post().to(payment_links::restock_payment_link))
+            )
+            .service(
+                web::scope("/checkout/sessions")
+                    .route("", web::post().to(checkout::create_session))
+                    .route("/{session_id}", web::get().to(checkout::get_session))
+            )
+            .service(
+                web::scope("/quotes")
+                    .route("", web::post().to(quotes::create_quote))
+                    .route("/{quote_id}", web::get().to(quotes::get_quote))
+                    .route("/{quote_id}/finalize", web::post().to(quotes::finalize_quote))
+                    .route("/{quote_id}/cancel", web::post().to(quotes::cancel_quote))
+            )
+            .service(
+                web::scope("/reports")
+                    .route("/ar_aging", web::get().to(reports::ar_aging))
+                    .route("/payments", web::get().to(reports::payments_export))
+                    .route("/revenue", web::get().to(reports::revenue))
+                    .route("/payout_reconciliation", web::get().to(reports::payout_reconciliation))
+            )
+            .service(
+                web::scope("/hosted")
+                    .route("/payment_links/{link_id}/sessions", web::post().to(checkout::open_link_session))
+                    .route("/checkout/{session_id}", web::get().to(checkout::get_hosted_session))
+                    .route("/checkout/{session_id}/complete", web::post().to(checkout::complete_session))
+                    .route("/quotes/{quote_id}", web::get().to(quotes::get_hosted_quote))
+                    .route("/quotes/{quote_id}/accept", web::post().to(quotes::accept_quote))
+                    .route("/invoices/{invoice_id}", web::get().to(invoices::get_hosted_invoice))
+                    .route("/invoices/{invoice_id}/pay", web::post().to(invoices::pay_hosted_invoice))
+            )
     );
+}
+
+/// The caller's API key, available only to handlers declared with `permission!`.
+pub(crate) fn get_api_key(req: &HttpRequest) -> Result<&str, DefiantError> {
+    if req.extensions().get::<permissions::AuthorizedScope>().is_none() {
+        error!("{} {} reads an API key without declaring a permission", req.method(), req.path());
+        return Err(DefiantError::AuthorizationError("Endpoint has no permission declaration".into()));
+    }
+    
+    permissions::bearer_token(req)
 }
\ No newline at end of file
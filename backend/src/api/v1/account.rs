@@ -0,0 +1,341 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{GoLiveChecklist, CreateLiveKeyRequest, LiveApiKeyResponse, RollApiKeyRequest, SecuritySettings, CreateTrustedSourceRequest, TrustedSource, ApiKeyIpViolationListQuery, ApiKeyIpViolationListResponse, RequestSigningSecret, RiskSettings, UpdateRiskSettingsRequest, PayoutSchedule, UpdatePayoutScheduleRequest}, errors::DefiantError, AppState, services::{account_service::AccountService, security_service::SecurityService, sca_service::ScaService, payout_service::PayoutService}};
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/go_live",
+        responses(
+            (status = 200, description = "Go-live requirements and whether each is met", body = GoLiveChecklist),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_go_live_checklist(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let checklist = account_service.go_live_checklist(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(checklist))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/account/live_keys",
+        request_body = CreateLiveKeyRequest,
+        responses(
+            (status = 201, description = "Live key issued; the key is not shown again", body = LiveApiKeyResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 409, description = "Go-live requirements not met"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_live_key(
+        req: HttpRequest,
+        data: web::Json<CreateLiveKeyRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let live_key = account_service.create_live_key(data.into_inner(), api_key).await?;
+        
+        info!("Live API key issued: {}", live_key.id);
+        
+        Ok(HttpResponse::Created().json(live_key))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/account/api_keys/{key_id}/roll",
+        params(
+            ("key_id" = Uuid, Path, description = "API key to replace")
+        ),
+        request_body = RollApiKeyRequest,
+        responses(
+            (status = 201, description = "Replacement key issued; the key is not shown again", body = LiveApiKeyResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 404, description = "API key not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn roll_api_key(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<RollApiKeyRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let new_key = account_service.roll_api_key(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        info!("API key rolled: {}", new_key.id);
+        
+        Ok(HttpResponse::Created().json(new_key))
+    }
+}
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/security",
+        responses(
+            (status = 200, description = "Rate limits and trusted sources", body = SecuritySettings),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_security_settings(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        let settings = security_service.settings(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(settings))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/account/security/trusted_sources",
+        request_body = CreateTrustedSourceRequest,
+        responses(
+            (status = 201, description = "Trusted source registered", body = TrustedSource),
+            (status = 400, description = "Invalid input"),
+            (status = 409, description = "Too many trusted sources"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_trusted_source(
+        req: HttpRequest,
+        data: web::Json<CreateTrustedSourceRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        let source = security_service.create_trusted_source(data.into_inner(), api_key).await?;
+        
+        info!("Trusted source registered: {}", source.id);
+        
+        Ok(HttpResponse::Created().json(source))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/account/security/trusted_sources/{source_id}",
+        params(
+            ("source_id" = Uuid, Path, description = "Trusted source ID")
+        ),
+        responses(
+            (status = 204, description = "Trusted source removed"),
+            (status = 404, description = "Trusted source not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn delete_trusted_source(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        security_service.delete_trusted_source(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/account/security/request_signing",
+        responses(
+            (status = 201, description = "Request signing on, with a new secret; requests signed with any previous secret are rejected", body = RequestSigningSecret),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn enable_request_signing(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        let secret = security_service.enable_request_signing(api_key).await?;
+        
+        Ok(HttpResponse::Created()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(secret))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/account/security/request_signing",
+        responses(
+            (status = 204, description = "Request signing off; secret-key requests need no signature"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn disable_request_signing(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        security_service.disable_request_signing(api_key).await?;
+        
+        Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/security/ip_violations",
+        params(
+            ("api_key_id" = Option<Uuid>, Query, description = "Only violations by this key"),
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "Requests refused by API key IP allowlists, newest first", body = ApiKeyIpViolationListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_ip_violations(
+        req: HttpRequest,
+        query: web::Query<ApiKeyIpViolationListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+        let violations = security_service.list_ip_violations(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(violations))
+    }
+}
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/risk_settings",
+        responses(
+            (status = 200, description = "Risk scores card payments are challenged and declined at", body = RiskSettings),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_risk_settings(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let sca_service = ScaService::new(state.db.clone(), state.config.clone());
+        let settings = sca_service.get_risk_settings(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(settings))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/account/risk_settings",
+        request_body = UpdateRiskSettingsRequest,
+        responses(
+            (status = 200, description = "Settings saved; apply to payments created from now on", body = RiskSettings),
+            (status = 400, description = "Scores out of range, or block_score not above challenge_score"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_risk_settings(
+        req: HttpRequest,
+        data: web::Json<UpdateRiskSettingsRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let sca_service = ScaService::new(state.db.clone(), state.config.clone());
+        let settings = sca_service.update_risk_settings(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(settings))
+    }
+}
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/account/payout_schedule",
+        responses(
+            (status = 200, description = "How often the available balance is paid out; manual unless changed", body = PayoutSchedule),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_payout_schedule(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let schedule = payout_service.get_payout_schedule(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(schedule))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/account/payout_schedule",
+        request_body = UpdatePayoutScheduleRequest,
+        responses(
+            (status = 200, description = "Schedule saved; the next due day pays out the whole available balance", body = PayoutSchedule),
+            (status = 400, description = "Weekly schedule without a weekly_anchor, an unsupported settlement currency or an unknown timezone"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_payout_schedule(
+        req: HttpRequest,
+        data: web::Json<UpdatePayoutScheduleRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let schedule = payout_service.update_payout_schedule(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(schedule))
+    }
+}
@@ -0,0 +1,161 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{ApiKey, ApiKeyListQuery, CreateApiKeyRequest, LiveApiKeyResponse, RollApiKeyRequest, UpdateApiKeyAllowlistRequest}, errors::DefiantError, AppState, services::account_service::AccountService};
+
+permission! {
+    "api_keys:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/api_keys",
+        request_body = CreateApiKeyRequest,
+        responses(
+            (status = 201, description = "Key issued; the key is not shown again", body = LiveApiKeyResponse),
+            (status = 400, description = "Invalid input or unknown scope"),
+            (status = 403, description = "A requested scope is not held by the calling key"),
+            (status = 409, description = "Live key requested before the go-live requirements are met"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_api_key(
+        req: HttpRequest,
+        data: web::Json<CreateApiKeyRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let new_key = account_service.create_api_key(data.into_inner(), api_key).await?;
+        
+        info!("API key issued: {}", new_key.id);
+        
+        Ok(HttpResponse::Created().json(new_key))
+    }
+}
+
+permission! {
+    "api_keys:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/api_keys",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "API keys, newest first, including revoked ones", body = [ApiKey]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_api_keys(
+        req: HttpRequest,
+        query: web::Query<ApiKeyListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let keys = account_service.list_api_keys(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(keys))
+    }
+}
+
+permission! {
+    "api_keys:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/api_keys/{key_id}/roll",
+        params(
+            ("key_id" = Uuid, Path, description = "API key to replace")
+        ),
+        request_body = RollApiKeyRequest,
+        responses(
+            (status = 201, description = "Replacement key issued; the key is not shown again", body = LiveApiKeyResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 404, description = "API key not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn roll_api_key(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<RollApiKeyRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let new_key = account_service.roll_api_key(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        info!("API key rolled: {}", new_key.id);
+        
+        Ok(HttpResponse::Created().json(new_key))
+    }
+}
+
+permission! {
+    "api_keys:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/api_keys/{key_id}",
+        params(
+            ("key_id" = Uuid, Path, description = "API key to revoke")
+        ),
+        responses(
+            (status = 200, description = "Key revoked; requests made with it are rejected from now on", body = ApiKey),
+            (status = 404, description = "API key not found or already revoked"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn revoke_api_key(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let key = account_service.revoke_api_key(path.into_inner(), api_key).await?;
+        
+        info!("API key revoked: {}", key.id);
+        
+        Ok(HttpResponse::Ok().json(key))
+    }
+}
+
+permission! {
+    "api_keys:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/api_keys/{key_id}/allowed_ips",
+        params(
+            ("key_id" = Uuid, Path, description = "API key to restrict")
+        ),
+        request_body = UpdateApiKeyAllowlistRequest,
+        responses(
+            (status = 200, description = "Allowlist replaced; requests from other addresses are rejected with IP_NOT_ALLOWED", body = ApiKey),
+            (status = 400, description = "Invalid CIDR range, or a publishable key"),
+            (status = 404, description = "API key not found or revoked"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_api_key_allowlist(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<UpdateApiKeyAllowlistRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let account_service = AccountService::new(state.db.clone());
+        let key = account_service.update_allowlist(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        info!("API key allowlist updated: {}", key.id);
+        
+        Ok(HttpResponse::Ok().json(key))
+    }
+}
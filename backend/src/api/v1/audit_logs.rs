@@ -0,0 +1,36 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+
+use super::get_api_key;
+use crate::{models::{AuditLogListQuery, AuditLogListResponse}, errors::DefiantError, AppState, services::audit_service::AuditService};
+
+permission! {
+    "audit_logs:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/audit_logs",
+        params(
+            ("resource_type" = Option<String>, Query, description = "Only calls on this collection, such as `payments`"),
+            ("resource_id" = Option<String>, Query, description = "Only calls on this object"),
+            ("actor_type" = Option<String>, Query, description = "`api_key`, `oauth_app`, `session` or `admin`"),
+            ("actor_id" = Option<String>, Query, description = "Only calls by this key, app grant, session or admin"),
+            ("created_gte" = Option<String>, Query, description = "Only calls at or after this time (RFC 3339)"),
+            ("created_lte" = Option<String>, Query, description = "Only calls at or before this time (RFC 3339)"),
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "Mutating API calls on the account, newest first", body = AuditLogListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_audit_logs(
+        req: HttpRequest,
+        query: web::Query<AuditLogListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let audit_service = AuditService::new(state.db.clone());
+        let logs = audit_service.list(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(logs))
+    }
+}
@@ -0,0 +1,138 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateCheckoutSessionRequest, CompleteCheckoutSessionRequest, CheckoutSession, HostedCheckoutSession, CompletedCheckout}, errors::DefiantError, AppState, services::checkout_service::CheckoutService};
+
+permission! {
+    "checkout:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/checkout/sessions",
+        request_body = CreateCheckoutSessionRequest,
+        responses(
+            (status = 201, description = "Checkout session created successfully", body = CheckoutSession),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Payment link not found"),
+        )
+    )]
+    pub async fn create_session(
+        req: HttpRequest,
+        data: web::Json<CreateCheckoutSessionRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+        let session = checkout_service.create_session(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Created().json(session))
+    }
+}
+
+permission! {
+    "checkout:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/checkout/sessions/{session_id}",
+        params(
+            ("session_id" = Uuid, Path, description = "Checkout session ID")
+        ),
+        responses(
+            (status = 200, description = "Checkout session retrieved successfully", body = CheckoutSession),
+            (status = 404, description = "Checkout session not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_session(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+        let session = checkout_service.get_session(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(session))
+    }
+}
+
+// Hosted checkout endpoints are called by the customer's browser and are not
+// authenticated with a merchant key.
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/hosted/payment_links/{link_id}/sessions",
+    params(
+        ("link_id" = Uuid, Path, description = "Payment link ID")
+    ),
+    responses(
+        (status = 201, description = "Checkout session opened", body = HostedCheckoutSession),
+        (status = 404, description = "Payment link not found"),
+        (status = 409, description = "Payment link is inactive or sold out"),
+    )
+)]
+pub async fn open_link_session(
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+    let session = checkout_service.create_session_from_link(path.into_inner()).await?;
+    
+    Ok(HttpResponse::Created().json(session))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/hosted/checkout/{session_id}",
+    params(
+        ("session_id" = Uuid, Path, description = "Checkout session ID")
+    ),
+    responses(
+        (status = 200, description = "Hosted checkout session", body = HostedCheckoutSession),
+        (status = 404, description = "Checkout session not found"),
+    )
+)]
+pub async fn get_hosted_session(
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+    let session = checkout_service.get_hosted_session(path.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(session))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/hosted/checkout/{session_id}/complete",
+    params(
+        ("session_id" = Uuid, Path, description = "Checkout session ID")
+    ),
+    request_body = CompleteCheckoutSessionRequest,
+    responses(
+        (status = 200, description = "Checkout completed", body = CompletedCheckout),
+        (status = 400, description = "Missing or invalid collected fields"),
+        (status = 404, description = "Checkout session not found"),
+        (status = 409, description = "Checkout session is closed or expired, or the item sold out"),
+    )
+)]
+pub async fn complete_session(
+    path: web::Path<Uuid>,
+    data: web::Json<CompleteCheckoutSessionRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    data.validate()?;
+    
+    let session_id = path.into_inner();
+    info!("Completing checkout session: {}", session_id);
+    
+    let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+    let completed = checkout_service.complete_session(session_id, data.into_inner()).await?;
+    
+    Ok(HttpResponse::Ok().json(completed))
+}
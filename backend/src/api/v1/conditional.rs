@@ -0,0 +1,73 @@
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::http::header::{EntityTag, Header, IfMatch, IfNoneMatch, ETag, IF_MATCH};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::errors::DefiantError;
+
+/// Payments, customers and invoices are tagged by their `updated_at`, which every
+/// write to the row moves forward.
+pub fn etag(updated_at: DateTime<Utc>) -> ETag {
+    ETag(entity_tag(updated_at))
+}
+
+/// The resource with its ETag, or 304 when the client's `If-None-Match` already
+/// names the current version.
+pub fn conditional_get(req: &HttpRequest, updated_at: DateTime<Utc>, body: &impl Serialize) -> HttpResponse {
+    let tag = entity_tag(updated_at);
+    let fresh = match IfNoneMatch::parse(req) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|candidate| candidate.weak_eq(&tag)),
+        Err(_) => false,
+    };
+    
+    if fresh {
+        HttpResponse::NotModified().insert_header(ETag(tag)).finish()
+    } else {
+        HttpResponse::Ok().insert_header(ETag(tag)).json(body)
+    }
+}
+
+/// An update's `If-Match` condition. Services check it against the row they have
+/// locked, so no other write can land between the check and the update.
+#[derive(Debug, Clone, Default)]
+pub struct Precondition {
+    if_match: Option<IfMatch>,
+}
+
+impl Precondition {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        // A header that does not parse matches nothing rather than being ignored
+        let if_match = req.headers().contains_key(IF_MATCH)
+            .then(|| IfMatch::parse(req).unwrap_or(IfMatch::Items(Vec::new())));
+        Self { if_match }
+    }
+    
+    pub fn check(&self, updated_at: DateTime<Utc>) -> Result<(), DefiantError> {
+        let current = entity_tag(updated_at);
+        match &self.if_match {
+            None | Some(IfMatch::Any) => Ok(()),
+            Some(IfMatch::Items(tags)) if tags.iter().any(|candidate| candidate.strong_eq(&current)) => Ok(()),
+            Some(_) => Err(DefiantError::PreconditionFailed(
+                "The object has changed since it was read; fetch it again and retry".into(),
+            )),
+        }
+    }
+}
+
+/// Refuses a write made against an older `version` of a payment, customer or
+/// subscription than the row the service has locked. Clients that send no version
+/// write unconditionally.
+pub fn check_version(expected: Option<i64>, current: i64) -> Result<(), DefiantError> {
+    match expected {
+        Some(expected) if expected != current => Err(DefiantError::Conflict(format!(
+            "The object is at version {}, not {}; fetch it again and retry",
+            current, expected,
+        ))),
+        _ => Ok(()),
+    }
+}
+
+fn entity_tag(updated_at: DateTime<Utc>) -> EntityTag {
+    EntityTag::new_strong(format!("{:x}", updated_at.timestamp_micros()))
+}
@@ -0,0 +1,260 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{BalanceResponse, ConnectedAccount, ConnectedAccountListQuery, CreateConnectedAccountRequest, CreateTransferRequest, CreateTransferReversalRequest, Transfer, TransferListQuery, TransferReversal}, errors::DefiantError, AppState, services::connect_service::ConnectService};
+
+permission! {
+    "connect:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/connected_accounts",
+        request_body = CreateConnectedAccountRequest,
+        responses(
+            (status = 201, description = "Connected account created under the platform", body = ConnectedAccount),
+            (status = 400, description = "Invalid input"),
+            (status = 409, description = "A merchant with this email already exists"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_connected_account(
+        req: HttpRequest,
+        data: web::Json<CreateConnectedAccountRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let account = connect_service.create_connected_account(data.into_inner(), api_key).await?;
+        
+        info!("Connected account created: {}", account.id);
+        
+        Ok(HttpResponse::Created().json(account))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/connected_accounts",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "The platform's connected accounts, newest first", body = [ConnectedAccount]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_connected_accounts(
+        req: HttpRequest,
+        query: web::Query<ConnectedAccountListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let accounts = connect_service.list_connected_accounts(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(accounts))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/connected_accounts/{account_id}",
+        params(
+            ("account_id" = Uuid, Path, description = "Connected account ID")
+        ),
+        responses(
+            (status = 200, description = "Connected account retrieved successfully", body = ConnectedAccount),
+            (status = 404, description = "Not a connected account of this platform"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_connected_account(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let account = connect_service.get_connected_account(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(account))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/connected_accounts/{account_id}/balance",
+        params(
+            ("account_id" = Uuid, Path, description = "Connected account ID")
+        ),
+        responses(
+            (status = 200, description = "Available and pending funds of the connected account per currency", body = BalanceResponse),
+            (status = 404, description = "Not a connected account of this platform"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_connected_account_balance(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let balance = connect_service.get_connected_account_balance(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(balance))
+    }
+}
+
+permission! {
+    "connect:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/transfers",
+        request_body = CreateTransferRequest,
+        responses(
+            (status = 201, description = "Funds moved from the platform balance to the connected account", body = Transfer),
+            (status = 400, description = "Invalid input or unsupported currency"),
+            (status = 404, description = "Destination is not a connected account of this platform"),
+            (status = 409, description = "Amount exceeds the available balance, or the destination is inactive"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_transfer(
+        req: HttpRequest,
+        data: web::Json<CreateTransferRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let transfer = connect_service.create_transfer(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Created().json(transfer))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/transfers",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("destination" = Option<Uuid>, Query, description = "Only transfers to this connected account")
+        ),
+        responses(
+            (status = 200, description = "Transfers, newest first", body = [Transfer]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_transfers(
+        req: HttpRequest,
+        query: web::Query<TransferListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let transfers = connect_service.list_transfers(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(transfers))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/transfers/{transfer_id}",
+        params(
+            ("transfer_id" = Uuid, Path, description = "Transfer ID")
+        ),
+        responses(
+            (status = 200, description = "Transfer retrieved successfully", body = Transfer),
+            (status = 404, description = "Transfer not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_transfer(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let transfer = connect_service.get_transfer(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(transfer))
+    }
+}
+
+permission! {
+    "connect:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/transfers/{transfer_id}/reversals",
+        params(
+            ("transfer_id" = Uuid, Path, description = "Transfer ID")
+        ),
+        request_body = CreateTransferReversalRequest,
+        responses(
+            (status = 201, description = "Funds returned from the connected account to the platform balance", body = TransferReversal),
+            (status = 404, description = "Transfer not found"),
+            (status = 409, description = "Amount exceeds what is left to reverse"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_transfer_reversal(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: Option<web::Json<CreateTransferReversalRequest>>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let request = data.map(|data| data.into_inner()).unwrap_or_default();
+        request.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let reversal = connect_service.create_reversal(path.into_inner(), request, api_key).await?;
+        
+        Ok(HttpResponse::Created().json(reversal))
+    }
+}
+
+permission! {
+    "connect:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/transfers/{transfer_id}/reversals",
+        params(
+            ("transfer_id" = Uuid, Path, description = "Transfer ID")
+        ),
+        responses(
+            (status = 200, description = "Reversals of the transfer, oldest first", body = [TransferReversal]),
+            (status = 404, description = "Transfer not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_transfer_reversals(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let connect_service = ConnectService::new(state.db.clone());
+        let reversals = connect_service.list_reversals(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(reversals))
+    }
+}
@@ -0,0 +1,32 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use uuid::Uuid;
+
+use super::get_api_key;
+use crate::{models::CardOnFileConsent, errors::DefiantError, AppState, services::consent_service::ConsentService};
+
+permission! {
+    "consents:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/consents/{consent_id}",
+        params(
+            ("consent_id" = Uuid, Path, description = "Consent record ID")
+        ),
+        responses(
+            (status = 200, description = "Consent record retrieved successfully", body = CardOnFileConsent),
+            (status = 404, description = "Consent record not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_consent(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = ConsentService::new(state.db.clone());
+        let consent = service.get_consent(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(consent))
+    }
+}
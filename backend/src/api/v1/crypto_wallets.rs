@@ -0,0 +1,187 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CryptoWallet, RegisterCryptoWalletRequest, CryptoSettlementPreference, UpdateCryptoSettlementRequest, CryptoPayout, CryptoPayoutListQuery, CryptoFeeEstimates, CryptoFeeEstimateQuery}, errors::DefiantError, AppState, services::{crypto_service::CryptoService, crypto_conversion_service::CryptoConversionService, crypto_payout_service::CryptoPayoutService, crypto_fee_service::CryptoFeeService}};
+
+permission! {
+    "crypto_wallets:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/crypto_wallets",
+        request_body = RegisterCryptoWalletRequest,
+        responses(
+            (status = 201, description = "Wallet registered; replaces the previous wallet for the chain", body = CryptoWallet),
+            (status = 400, description = "Invalid or non-account-level xpub, or invalid address"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn register_crypto_wallet(
+        req: HttpRequest,
+        data: web::Json<RegisterCryptoWalletRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let crypto_service = CryptoService::new(state.db.clone());
+        let wallet = crypto_service.register_wallet(data.into_inner(), api_key).await?;
+        
+        info!("Crypto wallet registered: {}", wallet.id);
+        
+        Ok(HttpResponse::Created().json(wallet))
+    }
+}
+
+permission! {
+    "crypto_wallets:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/crypto_wallets",
+        responses(
+            (status = 200, description = "Registered wallets, newest first", body = [CryptoWallet]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_crypto_wallets(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let crypto_service = CryptoService::new(state.db.clone());
+        let wallets = crypto_service.list_wallets(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(wallets))
+    }
+}
+
+permission! {
+    "crypto_wallets:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/crypto_wallets/settlement",
+        responses(
+            (status = 200, description = "What crypto payments settle in", body = CryptoSettlementPreference),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_crypto_settlement(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let conversion_service = CryptoConversionService::new(state.db.clone(), state.config.clone());
+        let preference = conversion_service.get_preference(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(preference))
+    }
+}
+
+permission! {
+    "crypto_wallets:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/crypto_wallets/settlement",
+        request_body = UpdateCryptoSettlementRequest,
+        responses(
+            (status = 200, description = "Preference saved; applies to payments created from now on", body = CryptoSettlementPreference),
+            (status = 400, description = "Invalid settlement address or tolerance"),
+            (status = 402, description = "Stablecoin settlement is not available"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_crypto_settlement(
+        req: HttpRequest,
+        data: web::Json<UpdateCryptoSettlementRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let conversion_service = CryptoConversionService::new(state.db.clone(), state.config.clone());
+        let preference = conversion_service.update_preference(data.into_inner(), api_key).await?;
+        
+        info!("Crypto settlement set to {:?}", preference.settlement_asset);
+        
+        Ok(HttpResponse::Ok().json(preference))
+    }
+}
+
+permission! {
+    "crypto_wallets:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/crypto_payouts",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("wallet_id" = Option<Uuid>, Query, description = "Only sweeps of this wallet"),
+            ("status" = Option<String>, Query, description = "pending, broadcast, confirmed or failed")
+        ),
+        responses(
+            (status = 200, description = "Sweeps into wallets' sweep addresses, newest first", body = [CryptoPayout]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_crypto_payouts(
+        req: HttpRequest,
+        query: web::Query<CryptoPayoutListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = CryptoPayoutService::new(state.db.clone(), state.config.clone());
+        let payouts = payout_service.list_crypto_payouts(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payouts))
+    }
+}
+
+permission! {
+    "crypto_wallets:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/crypto_payouts/{payout_id}",
+        params(
+            ("payout_id" = Uuid, Path, description = "Crypto payout ID")
+        ),
+        responses(
+            (status = 200, description = "Sweep transaction and its confirmations", body = CryptoPayout),
+            (status = 404, description = "Crypto payout not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_crypto_payout(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = CryptoPayoutService::new(state.db.clone(), state.config.clone());
+        let payout = payout_service.get_crypto_payout(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payout))
+    }
+}
+
+/// Current network fees, for checkout pages to show customers the full cost of paying.
+/// Public: nothing in it is specific to a merchant.
+#[utoipa::path(
+    get,
+    path = "/api/v1/crypto/fee_estimates",
+    params(
+        ("currency" = String, Query, description = "Native coin: btc, eth or sol")
+    ),
+    responses(
+        (status = 200, description = "Fast, medium and slow fees, up to a minute old", body = CryptoFeeEstimates),
+        (status = 400, description = "Unsupported currency"),
+        (status = 402, description = "No node is configured for the chain, or it could not be reached"),
+    )
+)]
+pub async fn get_fee_estimates(
+    query: web::Query<CryptoFeeEstimateQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let service = CryptoFeeService::new(state.redis.clone(), state.config.clone());
+    let estimates = service.fee_estimates(&query.currency).await?;
+    
+    Ok(HttpResponse::Ok().json(estimates))
+}
@@ -0,0 +1,219 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use uuid::Uuid;
+
+use super::{get_api_key, conditional::Precondition};
+use crate::{models::{CustomerListQuery, CustomerListResponse, CustomerSearchQuery, CommunicationsListResponse, ConsentListResponse, CreateMetadataBatchUpdateRequest, MetadataBatchUpdate, MetadataBatchObject, DeleteCustomerQuery}, errors::DefiantError, AppState, services::{communication_service::CommunicationService, consent_service::ConsentService, customer_service::CustomerService, metadata_batch_service::MetadataBatchService}};
+
+permission! {
+    "customers:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/customers",
+        params(
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`"),
+            ("email" = Option<String>, Query, description = "Only the customer with this email")
+        ),
+        responses(
+            (status = 200, description = "Customers in the key's mode, newest first", body = CustomerListResponse),
+            (status = 400, description = "Invalid cursor"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_customers(
+        req: HttpRequest,
+        query: web::Query<CustomerListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = CustomerService::new(state.db.clone());
+        let customers = service.list_customers(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(customers))
+    }
+}
+
+permission! {
+    "customers:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/customers/search",
+        params(
+            ("query" = String, Query, description = "Words to find in the name and description, and `metadata[key]:value` clauses, all of which must match"),
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`")
+        ),
+        responses(
+            (status = 200, description = "Matching customers in the key's mode, newest first", body = CustomerListResponse),
+            (status = 400, description = "Invalid query or cursor"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn search_customers(
+        req: HttpRequest,
+        query: web::Query<CustomerSearchQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = CustomerService::new(state.db.clone());
+        let customers = service.search_customers(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(customers))
+    }
+}
+
+permission! {
+    "customers:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/customers/{customer_id}",
+        params(
+            ("customer_id" = Uuid, Path, description = "Customer ID"),
+            ("force" = Option<bool>, Query, description = "End live subscriptions, agreements and draft invoices with the customer"),
+            ("version" = Option<i64>, Query, description = "Only delete if the customer is still at this version"),
+            ("If-Match" = Option<String>, Header, description = "Only delete if the customer still has this ETag"),
+        ),
+        responses(
+            (status = 204, description = "Customer deleted"),
+            (status = 404, description = "Customer not found"),
+            (status = 409, description = "Dependent objects block the delete, or the customer has moved past `version`"),
+            (status = 412, description = "Customer has changed since the client read it"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn delete_customer(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<DeleteCustomerQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = CustomerService::new(state.db.clone());
+        service.delete_customer(path.into_inner(), query.into_inner(), &Precondition::from_request(&req), api_key).await?;
+        
+        Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+permission! {
+    "customers:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/customers/{customer_id}/communications",
+        params(
+            ("customer_id" = Uuid, Path, description = "Customer ID"),
+            ("limit" = Option<i64>, Query, description = "Number of communications to return"),
+        ),
+        responses(
+            (status = 200, description = "Communications sent to the customer", body = CommunicationsListResponse),
+            (status = 404, description = "Customer not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_communications(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<CommunicationListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = CommunicationService::new(state.db.clone());
+        let communications = service
+            .list_for_customer(path.into_inner(), query.limit, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(communications))
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+pub struct CommunicationListQuery {
+    pub limit: Option<i64>,
+}
+
+permission! {
+    "customers:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/customers/{customer_id}/consents",
+        params(
+            ("customer_id" = Uuid, Path, description = "Customer ID"),
+            ("limit" = Option<i64>, Query, description = "Number of consent records to return"),
+        ),
+        responses(
+            (status = 200, description = "Card-on-file consents given by the customer, newest first", body = ConsentListResponse),
+            (status = 404, description = "Customer not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_consents(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<CommunicationListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = ConsentService::new(state.db.clone());
+        let consents = service
+            .list_for_customer(path.into_inner(), query.limit, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(consents))
+    }
+}
+
+permission! {
+    "customers:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/customers/metadata:batch_update",
+        request_body = CreateMetadataBatchUpdateRequest,
+        responses(
+            (status = 202, description = "Batch update queued; poll it for progress", body = MetadataBatchUpdate),
+            (status = 400, description = "Empty filter or patch"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn batch_update_metadata(
+        req: HttpRequest,
+        data: web::Json<CreateMetadataBatchUpdateRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = MetadataBatchService::new(state.db.clone());
+        let batch = service
+            .request_batch_update(MetadataBatchObject::Customer, data.into_inner(), api_key)
+            .await?;
+        
+        Ok(HttpResponse::Accepted().json(batch))
+    }
+}
+
+permission! {
+    "customers:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/customers/metadata:batch_update/{batch_id}",
+        params(
+            ("batch_id" = Uuid, Path, description = "Metadata batch update ID")
+        ),
+        responses(
+            (status = 200, description = "Batch update progress", body = MetadataBatchUpdate),
+            (status = 404, description = "Metadata batch update not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_metadata_batch_update(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = MetadataBatchService::new(state.db.clone());
+        let batch = service
+            .get_batch_update(MetadataBatchObject::Customer, path.into_inner(), api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(batch))
+    }
+}
@@ -0,0 +1,231 @@
+use actix_web::{web, HttpMessage, HttpResponse, HttpRequest};
+use actix_web::http::header::ContentEncoding;
+use futures_util::StreamExt;
+use tracing::info;
+use uuid::Uuid;
+
+use super::get_api_key;
+use crate::{models::{Event, EventListQuery, EventListResponse, CreateEventReplayRequest, EventReplayResponse, EventTypeListResponse, EventPollQuery, EventPollResponse, EventStreamQuery}, errors::DefiantError, db::AuthenticatedKey, AppState, services::{event_service::{self, EventService}, event_stream::{self, TopicSubscriptions}}};
+
+permission! {
+    "events:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/events",
+        params(
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`"),
+            ("type" = Option<String>, Query, description = "Exact event type or a `family.*` wildcard"),
+            ("created_gte" = Option<String>, Query, description = "Only events created at or after this time (RFC 3339)"),
+            ("created_lte" = Option<String>, Query, description = "Only events created at or before this time (RFC 3339)")
+        ),
+        responses(
+            (status = 200, description = "Events, newest first", body = EventListResponse),
+            (status = 400, description = "Invalid type filter"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_events(
+        req: HttpRequest,
+        query: web::Query<EventListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let event_service = EventService::new(state.db.clone());
+        let events = event_service.list_events(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(events))
+    }
+}
+
+permission! {
+    "events:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/events/{event_id}",
+        params(
+            ("event_id" = Uuid, Path, description = "Event ID")
+        ),
+        responses(
+            (status = 200, description = "Event retrieved successfully", body = Event),
+            (status = 404, description = "Event not found"),
+            (status = 410, description = "Event archived; the body says where to retrieve it"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_event(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let event_service = EventService::new(state.db.clone());
+        let event = event_service.get_event(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(event))
+    }
+}
+
+permission! {
+    "events:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/events/replay",
+        request_body = CreateEventReplayRequest,
+        responses(
+            (status = 202, description = "Replay queued; poll it for progress", body = EventReplayResponse),
+            (status = 400, description = "Invalid time window"),
+            (status = 404, description = "Webhook endpoint not found"),
+            (status = 409, description = "Endpoint disabled or a replay is already running"),
+        )
+    )]
+    pub async fn replay_events(
+        req: HttpRequest,
+        data: web::Json<CreateEventReplayRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let event_service = EventService::new(state.db.clone());
+        let replay = event_service.request_replay(data.into_inner(), api_key).await?;
+        
+        info!("Event replay queued: {}", replay.replay.id);
+        
+        Ok(HttpResponse::Accepted().json(replay))
+    }
+}
+
+permission! {
+    "events:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/events/replays/{replay_id}",
+        params(
+            ("replay_id" = Uuid, Path, description = "Event replay ID")
+        ),
+        responses(
+            (status = 200, description = "Replay progress", body = EventReplayResponse),
+            (status = 404, description = "Event replay not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_event_replay(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let event_service = EventService::new(state.db.clone());
+        let replay = event_service.get_replay(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(replay))
+    }
+}
+
+permission! {
+    "events:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/events/poll",
+        params(
+            ("cursor" = Option<String>, Query, description = "Cursor from the previous poll; omit to start from now"),
+            ("type" = Option<String>, Query, description = "Exact event type or a `family.*` wildcard"),
+            ("timeout" = Option<u64>, Query, description = "Seconds to wait for new events (max 30, default 25)")
+        ),
+        responses(
+            (status = 200, description = "Events after the cursor, oldest first; empty if the wait timed out", body = EventPollResponse),
+            (status = 400, description = "Invalid cursor or type filter"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn poll_events(
+        req: HttpRequest,
+        query: web::Query<EventPollQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let event_service = EventService::new(state.db.clone());
+        let events = event_service.poll_events(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(events))
+    }
+}
+
+permission! {
+    "events:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/events/stream",
+        params(
+            ("topics" = String, Query, description = "Comma-separated event types or `family.*` wildcards, e.g. `payment.*,invoice.paid`"),
+            ("last_event_id" = Option<Uuid>, Query, description = "Replay the events after this one before streaming live ones"),
+            ("Last-Event-ID" = Option<Uuid>, Header, description = "As `last_event_id`, sent by reconnecting EventSource clients; takes precedence")
+        ),
+        responses(
+            (status = 200, description = "A text/event-stream of events, one `id`/`event`/`data` frame each, with heartbeat comments between"),
+            (status = 400, description = "Invalid topic, unknown last event, or too many missed events to replay"),
+            (status = 401, description = "Unauthorized"),
+            (status = 403, description = "A topic needs a scope the key lacks"),
+        )
+    )]
+    pub async fn stream_events(
+        req: HttpRequest,
+        query: web::Query<EventStreamQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let key = stream_key(&req, &state).await?;
+        
+        let query = query.into_inner();
+        let topics: Vec<String> = query.topics.split(',').map(|topic| topic.trim().to_string()).collect();
+        let mut subscriptions = TopicSubscriptions::default();
+        subscriptions.subscribe(&topics, &key)?;
+        
+        let last_event_id = match req.headers().get("Last-Event-ID") {
+            Some(header) => Some(header.to_str().ok()
+                .and_then(|id| id.parse::<Uuid>().ok())
+                .ok_or_else(|| DefiantError::ValidationError("Last-Event-ID must be an event id".into()))?),
+            None => query.last_event_id,
+        };
+        
+        let mut frames = event_stream::sse(
+            state.db.clone(),
+            &state.events,
+            &state.config.streaming,
+            key,
+            subscriptions,
+            last_event_id,
+        )
+        .await?;
+        
+        Ok(HttpResponse::Ok()
+            .content_type("text/event-stream")
+            .insert_header(("Cache-Control", "no-cache"))
+            // Compression and proxy buffering would hold frames back
+            .insert_header(ContentEncoding::Identity)
+            .insert_header(("X-Accel-Buffering", "no"))
+            .streaming(futures_util::stream::poll_fn(move |cx| frames.poll_recv(cx)).map(Ok::<_, DefiantError>)))
+    }
+}
+
+/// The key a stream is opened with, whose merchant, mode and scopes bound what it
+/// receives. Its scope must have been checked already.
+pub(crate) async fn stream_key(req: &HttpRequest, state: &AppState) -> Result<AuthenticatedKey, DefiantError> {
+    let resolved = req.extensions().get::<AuthenticatedKey>().cloned();
+    match resolved {
+        Some(key) => Ok(key),
+        None => state.db.authenticate_key(get_api_key(req)?).await,
+    }
+}
+
+/// Static catalog, so no API key is needed to read it.
+#[utoipa::path(
+    get,
+    path = "/api/v1/event_types",
+    responses(
+        (status = 200, description = "Every event type with its payload schema", body = EventTypeListResponse),
+    )
+)]
+pub async fn list_event_types() -> Result<HttpResponse, DefiantError> {
+    let catalog = event_service::event_catalog()?;
+    
+    Ok(HttpResponse::Ok().json(catalog))
+}
@@ -0,0 +1,54 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{FraudSettings, UpdateFraudSettingsRequest}, errors::DefiantError, AppState, services::fraud_settings_service::FraudSettingsService};
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/fraud/settings",
+        responses(
+            (status = 200, description = "Switches and limits fraud checks run with", body = FraudSettings),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_fraud_settings(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let fraud_settings_service = FraudSettingsService::new(state.db.clone(), state.redis.clone());
+        let settings = fraud_settings_service.get_settings(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(settings))
+    }
+}
+
+permission! {
+    "account:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/fraud/settings",
+        request_body = UpdateFraudSettingsRequest,
+        responses(
+            (status = 200, description = "Settings saved; apply to payments created from now on", body = FraudSettings),
+            (status = 400, description = "Non-positive amount, unsupported currency or invalid country code"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_fraud_settings(
+        req: HttpRequest,
+        data: web::Json<UpdateFraudSettingsRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let fraud_settings_service = FraudSettingsService::new(state.db.clone(), state.redis.clone());
+        let settings = fraud_settings_service.update_settings(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(settings))
+    }
+}
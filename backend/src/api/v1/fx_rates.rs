@@ -0,0 +1,48 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+
+use crate::{models::{FxRate, FxRateQuery}, errors::DefiantError, AppState, services::fx_service::{self, FxService}};
+
+permission! {
+    "fx_rates:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/fx_rates",
+        params(
+            ("from" = String, Query, description = "Currency converted from (ISO 4217)"),
+            ("to" = String, Query, description = "Currency converted to (ISO 4217)"),
+            ("date" = Option<String>, Query, description = "Day to look up (YYYY-MM-DD); defaults to today")
+        ),
+        responses(
+            (status = 200, description = "Rate in effect on the day", body = FxRate),
+            (status = 400, description = "Unsupported currency or a future date"),
+            (status = 404, description = "No rate stored for the pair on or before the day"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_fx_rate(
+        query: web::Query<FxRateQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let query = query.into_inner();
+        
+        fx_service::ensure_supported_currency(&query.from)?;
+        fx_service::ensure_supported_currency(&query.to)?;
+        
+        let today = Utc::now().date_naive();
+        let date = query.date.unwrap_or(today);
+        if date > today {
+            return Err(DefiantError::ValidationError("date cannot be in the future".into()));
+        }
+        
+        let rate = FxService::new(state.db.clone())
+            .rate_on(&query.from, &query.to, date)
+            .await?
+            .ok_or_else(|| DefiantError::NotFound(format!(
+                "No exchange rate for {} to {} on or before {}",
+                query.from.to_uppercase(), query.to.to_uppercase(), date
+            )))?;
+        
+        Ok(HttpResponse::Ok().json(rate))
+    }
+}
@@ -0,0 +1,246 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::{get_api_key, conditional::{conditional_get, etag, Precondition}};
+use crate::{models::{CreateInvoiceRequest, InvoiceResponse, InvoiceListQuery, InvoiceListResponse, HostedInvoice, HostedInvoiceQuery, HostedInvoicePayment, PayHostedInvoiceRequest}, errors::DefiantError, AppState, middleware::rate_limit::client_ip, services::{invoice_service::InvoiceService, hosted_invoice_service::HostedInvoiceService}};
+
+permission! {
+    "invoices:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/invoices",
+        request_body = CreateInvoiceRequest,
+        responses(
+            (status = 201, description = "Invoice created successfully", body = InvoiceResponse),
+            (status = 400, description = "Invalid input or mixed line item currencies"),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Customer not found"),
+        )
+    )]
+    pub async fn create_invoice(
+        req: HttpRequest,
+        data: web::Json<CreateInvoiceRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let invoice_service = InvoiceService::new(state.db.clone(), state.redis.clone());
+        let invoice = invoice_service.create_invoice(data.into_inner(), api_key).await?;
+        
+        info!("Invoice created: {}", invoice.id);
+        
+        Ok(HttpResponse::Created().json(invoice))
+    }
+}
+
+permission! {
+    "invoices:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/invoices/{invoice_id}",
+        params(
+            ("invoice_id" = Uuid, Path, description = "Invoice ID"),
+            ("If-None-Match" = Option<String>, Header, description = "ETag of a copy the client already has")
+        ),
+        responses(
+            (status = 200, description = "Invoice retrieved successfully, with its ETag", body = InvoiceResponse),
+            (status = 304, description = "The client's copy is current"),
+            (status = 404, description = "Invoice not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_invoice(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let invoice_service = InvoiceService::new(state.db.clone(), state.redis.clone());
+        let invoice = invoice_service.get_invoice(path.into_inner(), api_key).await?;
+        
+        Ok(conditional_get(&req, invoice.updated_at, &invoice))
+    }
+}
+
+permission! {
+    "invoices:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/invoices",
+        params(
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`"),
+            ("customer_id" = Option<Uuid>, Query, description = "Only this customer's invoices"),
+            ("status" = Option<String>, Query, description = "Only invoices in this status")
+        ),
+        responses(
+            (status = 200, description = "Invoices in the key's mode, newest first", body = InvoiceListResponse),
+            (status = 400, description = "Invalid cursor"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_invoices(
+        req: HttpRequest,
+        query: web::Query<InvoiceListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let invoice_service = InvoiceService::new(state.db.clone(), state.redis.clone());
+        let invoices = invoice_service.list_invoices(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(invoices))
+    }
+}
+
+permission! {
+    "invoices:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/invoices/{invoice_id}/void",
+        params(
+            ("invoice_id" = Uuid, Path, description = "Invoice ID"),
+            ("If-Match" = Option<String>, Header, description = "Only void if the invoice still has this ETag")
+        ),
+        responses(
+            (status = 200, description = "Invoice voided; applied customer credit is returned", body = InvoiceResponse),
+            (status = 404, description = "Invoice not found"),
+            (status = 409, description = "Invoice is not open or uncollectible"),
+            (status = 412, description = "Invoice has changed since the client read it"),
+        )
+    )]
+    pub async fn void_invoice(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let invoice_id = path.into_inner();
+        info!("Voiding invoice: {}", invoice_id);
+        
+        let api_key = get_api_key(&req)?;
+        let invoice_service = InvoiceService::new(state.db.clone(), state.redis.clone());
+        let invoice = invoice_service.void_invoice(invoice_id, &Precondition::from_request(&req), api_key).await?;
+        
+        Ok(HttpResponse::Ok().insert_header(etag(invoice.updated_at)).json(invoice))
+    }
+}
+
+permission! {
+    "invoices:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/invoices/{invoice_id}/mark_uncollectible",
+        params(
+            ("invoice_id" = Uuid, Path, description = "Invoice ID"),
+            ("If-Match" = Option<String>, Header, description = "Only write off if the invoice still has this ETag")
+        ),
+        responses(
+            (status = 200, description = "Invoice marked uncollectible", body = InvoiceResponse),
+            (status = 404, description = "Invoice not found"),
+            (status = 409, description = "Invoice is not open"),
+            (status = 412, description = "Invoice has changed since the client read it"),
+        )
+    )]
+    pub async fn mark_uncollectible(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let invoice_id = path.into_inner();
+        info!("Marking invoice uncollectible: {}", invoice_id);
+        
+        let api_key = get_api_key(&req)?;
+        let invoice_service = InvoiceService::new(state.db.clone(), state.redis.clone());
+        let invoice = invoice_service.mark_uncollectible(invoice_id, &Precondition::from_request(&req), api_key).await?;
+        
+        Ok(HttpResponse::Ok().insert_header(etag(invoice.updated_at)).json(invoice))
+    }
+}
+
+permission! {
+    "invoices:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/invoices/{invoice_id}/send",
+        params(
+            ("invoice_id" = Uuid, Path, description = "Invoice ID")
+        ),
+        responses(
+            (status = 200, description = "Invoice emailed with a signed link to its payment page", body = InvoiceResponse),
+            (status = 404, description = "Invoice not found"),
+            (status = 409, description = "Invoice is not open"),
+        )
+    )]
+    pub async fn send_invoice(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let invoice_id = path.into_inner();
+        info!("Sending invoice: {}", invoice_id);
+        
+        let api_key = get_api_key(&req)?;
+        let hosted_invoice_service = HostedInvoiceService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let invoice = hosted_invoice_service.send_invoice(invoice_id, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(invoice))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/hosted/invoices/{invoice_id}",
+    params(
+        ("invoice_id" = Uuid, Path, description = "Invoice ID"),
+        ("expires" = i64, Query, description = "Expiry from the invoice link"),
+        ("signature" = String, Query, description = "Signature from the invoice link"),
+    ),
+    responses(
+        (status = 200, description = "Invoice for the customer to pay", body = HostedInvoice),
+        (status = 404, description = "Invoice not found"),
+        (status = 409, description = "Payment link has expired"),
+    )
+)]
+pub async fn get_hosted_invoice(
+    path: web::Path<Uuid>,
+    query: web::Query<HostedInvoiceQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let hosted_invoice_service = HostedInvoiceService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+    let invoice = hosted_invoice_service.get_hosted_invoice(path.into_inner(), query.expires, &query.signature).await?;
+    
+    Ok(HttpResponse::Ok().json(invoice))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/hosted/invoices/{invoice_id}/pay",
+    params(
+        ("invoice_id" = Uuid, Path, description = "Invoice ID")
+    ),
+    request_body = PayHostedInvoiceRequest,
+    responses(
+        (status = 200, description = "Payment started; card payments settle the invoice immediately", body = HostedInvoicePayment),
+        (status = 400, description = "Invalid payment details"),
+        (status = 402, description = "Payment declined"),
+        (status = 404, description = "Invoice not found"),
+        (status = 409, description = "Invoice is not open, a payment is already in progress, or the link has expired"),
+    )
+)]
+pub async fn pay_hosted_invoice(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    data: web::Json<PayHostedInvoiceRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let invoice_id = path.into_inner();
+    info!("Paying invoice from its hosted page: {}", invoice_id);
+    
+    let hosted_invoice_service = HostedInvoiceService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+    let client_ip = client_ip(&req, state.config.trust_proxy_headers);
+    let payment = hosted_invoice_service.pay_hosted_invoice(invoice_id, data.into_inner(), client_ip).await?;
+    
+    Ok(HttpResponse::Ok().json(payment))
+}
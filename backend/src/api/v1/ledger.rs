@@ -0,0 +1,130 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+
+use super::get_api_key;
+use crate::{models::{BalanceResponse, BalanceConversion, BalanceConversionListQuery, BalanceTransaction, BalanceTransactionListQuery, LedgerBalancesResponse, JournalEntryListQuery, JournalEntryWithLines}, errors::DefiantError, AppState, services::{ledger::LedgerService, payout_service::PayoutService}};
+
+permission! {
+    "balance:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/balance",
+        responses(
+            (status = 200, description = "Available, pending and reserved funds per currency; card payments are pending until the settlement delay has passed", body = BalanceResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_balance(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let ledger_service = LedgerService::new(state.db.clone());
+        let balance = ledger_service.balance(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(balance))
+    }
+}
+
+permission! {
+    "balance:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/balance/conversions",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "Conversions into the settlement currency, newest first", body = [BalanceConversion]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_balance_conversions(
+        req: HttpRequest,
+        query: web::Query<BalanceConversionListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let conversions = payout_service.list_balance_conversions(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(conversions))
+    }
+}
+
+permission! {
+    "balance:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/balance_transactions",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("type" = Option<String>, Query, description = "charge, refund, fee, payout, transfer, conversion, reserve or adjustment"),
+            ("currency" = Option<String>, Query, description = "Only movements in this currency")
+        ),
+        responses(
+            (status = 200, description = "Movements of the balance, newest first, each linked to the object it came from", body = [BalanceTransaction]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_balance_transactions(
+        req: HttpRequest,
+        query: web::Query<BalanceTransactionListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let ledger_service = LedgerService::new(state.db.clone());
+        let transactions = ledger_service.list_balance_transactions(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(transactions))
+    }
+}
+
+permission! {
+    "ledger:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/ledger/balances",
+        responses(
+            (status = 200, description = "Debits, credits and balance of each of the merchant's ledger accounts, per currency", body = LedgerBalancesResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_ledger_balances(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let ledger_service = LedgerService::new(state.db.clone());
+        let balances = ledger_service.balances(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(balances))
+    }
+}
+
+permission! {
+    "ledger:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/ledger/entries",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("kind" = Option<String>, Query, description = "Only entries of this kind, e.g. payment or payout"),
+            ("payment_id" = Option<Uuid>, Query, description = "Only entries booked for this payment")
+        ),
+        responses(
+            (status = 200, description = "Journal entries with their lines, newest first", body = [JournalEntryWithLines]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_journal_entries(
+        req: HttpRequest,
+        query: web::Query<JournalEntryListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let ledger_service = LedgerService::new(state.db.clone());
+        let entries = ledger_service.list_entries(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(entries))
+    }
+}
@@ -0,0 +1,62 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+
+use super::get_api_key;
+use crate::{models::MitAgreement, errors::DefiantError, AppState, services::mit_service::MitService};
+
+permission! {
+    "mit_agreements:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/mit_agreements/{agreement_id}",
+        params(
+            ("agreement_id" = Uuid, Path, description = "MIT agreement ID")
+        ),
+        responses(
+            (status = 200, description = "MIT agreement retrieved successfully", body = MitAgreement),
+            (status = 404, description = "MIT agreement not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_mit_agreement(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let mit_service = MitService::new(state.db.clone(), state.config.clone());
+        let agreement = mit_service.get_agreement(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(agreement))
+    }
+}
+
+permission! {
+    "mit_agreements:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/mit_agreements/{agreement_id}/revoke",
+        params(
+            ("agreement_id" = Uuid, Path, description = "MIT agreement ID")
+        ),
+        responses(
+            (status = 200, description = "MIT agreement revoked", body = MitAgreement),
+            (status = 404, description = "MIT agreement not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn revoke_mit_agreement(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let mit_service = MitService::new(state.db.clone(), state.config.clone());
+        let agreement = mit_service.revoke_agreement(path.into_inner(), api_key).await?;
+        
+        info!("MIT agreement revoked: {}", agreement.id);
+        
+        Ok(HttpResponse::Ok().json(agreement))
+    }
+}
@@ -0,0 +1,63 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateOAuthClientRequest, OAuthClientCreated, OAuthClientListQuery, OAuthClientListResponse}, errors::DefiantError, AppState, services::oauth_service::OAuthService};
+
+permission! {
+    "oauth_clients:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/oauth/clients",
+        request_body = CreateOAuthClientRequest,
+        responses(
+            (status = 201, description = "App registered; the client secret is only shown now", body = OAuthClientCreated),
+            (status = 400, description = "Invalid redirect URI or scope"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_oauth_client(
+        req: HttpRequest,
+        data: web::Json<CreateOAuthClientRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let oauth_service = OAuthService::new(state.db.clone());
+        let created = oauth_service.register_client(data.into_inner(), api_key).await?;
+        
+        info!("OAuth client registered: {}", created.client.client_id);
+        
+        Ok(HttpResponse::Created()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(created))
+    }
+}
+
+permission! {
+    "oauth_clients:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/oauth/clients",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "Apps this account registered, newest first", body = OAuthClientListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_oauth_clients(
+        req: HttpRequest,
+        query: web::Query<OAuthClientListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let oauth_service = OAuthService::new(state.db.clone());
+        let clients = oauth_service.list_clients(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(clients))
+    }
+}
@@ -0,0 +1,97 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreatePaymentLinkRequest, RestockPaymentLinkRequest, PaymentLinkResponse}, errors::DefiantError, AppState, services::checkout_service::CheckoutService};
+
+permission! {
+    "payment_links:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payment_links",
+        request_body = CreatePaymentLinkRequest,
+        responses(
+            (status = 201, description = "Payment link created successfully", body = PaymentLinkResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_payment_link(
+        req: HttpRequest,
+        data: web::Json<CreatePaymentLinkRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+        let link = checkout_service.create_payment_link(data.into_inner(), api_key).await?;
+        
+        info!("Payment link created: {}", link.id);
+        
+        Ok(HttpResponse::Created().json(link))
+    }
+}
+
+permission! {
+    "payment_links:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payment_links/{link_id}",
+        params(
+            ("link_id" = Uuid, Path, description = "Payment link ID")
+        ),
+        responses(
+            (status = 200, description = "Payment link retrieved successfully", body = PaymentLinkResponse),
+            (status = 404, description = "Payment link not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_payment_link(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+        let link = checkout_service.get_payment_link(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(link))
+    }
+}
+
+
+permission! {
+    "payment_links:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payment_links/{link_id}/restock",
+        params(
+            ("link_id" = Uuid, Path, description = "Payment link ID")
+        ),
+        request_body = RestockPaymentLinkRequest,
+        responses(
+            (status = 200, description = "Inventory added", body = PaymentLinkResponse),
+            (status = 400, description = "Payment link does not track inventory"),
+            (status = 404, description = "Payment link not found"),
+        )
+    )]
+    pub async fn restock_payment_link(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<RestockPaymentLinkRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let checkout_service = CheckoutService::new(state.db.clone(), state.redis.clone());
+        let link = checkout_service
+            .restock_payment_link(path.into_inner(), data.quantity, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(link))
+    }
+}
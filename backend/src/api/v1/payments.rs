@@ -2,242 +2,568 @@ use actix_web::{web, HttpResponse, HttpRequest};
 use serde_json::json;
 use tracing::{info, error};
 use uuid::Uuid;
+use validator::Validate;
 
-use crate::{models::{CreatePaymentRequest, PaymentResponse}, errors::DefiantError, AppState, services::payment_service::PaymentService};
+use super::{get_api_key, conditional::conditional_get};
+use crate::{models::{CreatePaymentRequest, PaymentResponse, PaymentListQuery, PaymentSearchQuery, PaymentsListResponse, PaymentStatusQuery, PaymentStatusResponse, ConfirmPaymentRequest, Receipt, CreateMetadataBatchUpdateRequest, MetadataBatchUpdate, MetadataBatchObject, CreateCryptoRefundRequest, RegisterRefundAddressRequest, CryptoRefund, ReviewListQuery, PaymentReview, FraudLabel, FraudLabelRecord, LabelPaymentRequest, FraudRuleStatsResponse}, errors::DefiantError, AppState, services::{payment_service::PaymentService, receipt_service::ReceiptService, metadata_batch_service::MetadataBatchService, crypto_refund_service::CryptoRefundService, fraud_feedback_service::FraudFeedbackService}};
 
-#[utoipa::path(
-    post,
-    path = "/api/v1/payments",
-    request_body = CreatePaymentRequest,
-    responses(
-        (status = 201, description = "Payment created successfully", body = PaymentResponse),
-        (status = 400, description = "Invalid input"),
-        (status = 401, description = "Unauthorized"),
-        (status = 402, description = "Payment required"),
-        (status = 429, description = "Rate limit exceeded"),
-    ),
-    security(
-        ("bearer_auth" = [])
-    )
-)]
-pub async fn create_payment(
-    req: HttpRequest,
-    data: web::Json<CreatePaymentRequest>,
-    state: web::Data<AppState>,
-) -> Result<HttpResponse, DefiantError> {
-    info!("Creating payment for amount: {}", data.amount);
-    
-    // Validate input
-    data.validate()?;
-    
-    // Check rate limiting
-    check_rate_limit(&req, &state).await?;
-    
-    // Get API key from headers
-    let api_key = req.headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer "))
-        .ok_or_else(|| DefiantError::AuthenticationError("Missing API key".into()))?;
-    
-    // Create payment service
-    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone());
-    
-    // Create payment
-    let payment = payment_service.create_payment(data.into_inner(), api_key).await?;
-    
-    info!("Payment created: {}", payment.id);
-    
-    Ok(HttpResponse::Created().json(payment))
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments",
+        request_body = CreatePaymentRequest,
+        responses(
+            (status = 201, description = "Payment created successfully", body = PaymentResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+            (status = 402, description = "Payment required"),
+            (status = 429, description = "Rate limit exceeded"),
+        )
+    )]
+    pub async fn create_payment(
+        req: HttpRequest,
+        data: web::Json<CreatePaymentRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        info!("Creating payment for amount: {}", data.amount);
+        
+        // Validate input
+        data.validate()?;
+        
+        // Get API key from headers
+        let api_key = get_api_key(&req)?;
+        
+        // Create payment service
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        
+        // Create payment
+        let payment = payment_service.create_payment(data.into_inner(), api_key).await?;
+        
+        info!("Payment created: {}", payment.id);
+        
+        Ok(HttpResponse::Created().json(payment))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payments/{payment_id}",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID"),
+            ("If-None-Match" = Option<String>, Header, description = "ETag of a copy the client already has")
+        ),
+        responses(
+            (status = 200, description = "Payment retrieved successfully, with its ETag", body = PaymentResponse),
+            (status = 304, description = "The client's copy is current"),
+            (status = 404, description = "Payment not found"),
+            (status = 410, description = "Payment archived; the body says where to retrieve it"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_payment(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let payment_id = path.into_inner();
+        info!("Getting payment: {}", payment_id);
+        
+        // Get API key
+        let api_key = get_api_key(&req)?;
+        
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let payment = payment_service.get_payment(payment_id, api_key).await?;
+        
+        Ok(conditional_get(&req, payment.updated_at, &payment))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/capture",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        responses(
+            (status = 200, description = "Payment captured successfully", body = PaymentResponse),
+            (status = 400, description = "Cannot capture payment"),
+            (status = 404, description = "Payment not found"),
+        )
+    )]
+    pub async fn capture_payment(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let payment_id = path.into_inner();
+        info!("Capturing payment: {}", payment_id);
+        
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let payment = payment_service.capture_payment(payment_id, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payment))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/refund",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        responses(
+            (status = 200, description = "Payment refunded successfully", body = PaymentResponse),
+            (status = 400, description = "Cannot refund payment"),
+            (status = 404, description = "Payment not found"),
+        )
+    )]
+    pub async fn refund_payment(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<RefundRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let payment_id = path.into_inner();
+        info!("Refunding payment: {}", payment_id);
+        
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let payment = payment_service.refund_payment(payment_id, data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payment))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payments",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`"),
+            ("customer" = Option<Uuid>, Query, description = "Filter by customer"),
+            ("status" = Option<String>, Query, description = "Filter by status"),
+        ),
+        responses(
+            (status = 200, description = "Payments in the key's mode, newest first", body = PaymentsListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_payments(
+        req: HttpRequest,
+        query: web::Query<PaymentListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let payments = payment_service.list_payments(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payments))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payments/search",
+        params(
+            ("query" = String, Query, description = "Words to find in the description and `metadata[key]:value` clauses, all of which must match"),
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("cursor" = Option<String>, Query, description = "The previous page's `next_cursor`"),
+        ),
+        responses(
+            (status = 200, description = "Matching payments in the key's mode, newest first", body = PaymentsListResponse),
+            (status = 400, description = "Invalid query or cursor"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn search_payments(
+        req: HttpRequest,
+        query: web::Query<PaymentSearchQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let payments = payment_service.search_payments(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payments))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/receipt",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        responses(
+            (status = 200, description = "Receipt sent to the customer", body = Receipt),
+            (status = 400, description = "Payment has no customer email"),
+            (status = 404, description = "Payment not found"),
+        )
+    )]
+    pub async fn send_receipt(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let payment_id = path.into_inner();
+        info!("Sending receipt for payment: {}", payment_id);
+        
+        let api_key = get_api_key(&req)?;
+        let receipt_service = ReceiptService::new(state.db.clone(), state.config.clone());
+        let receipt = receipt_service.send_receipt(payment_id, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(receipt))
+    }
 }
 
+// Request/Response types
+#[derive(Debug, serde::Deserialize)]
+pub struct RefundRequest {
+    pub amount: Option<i64>,
+    pub reason: Option<String>,
+}
+
+/// Polled by the customer's browser or app after confirming or returning from a
+/// redirect. Authenticated by the payment's client secret, never an API key.
 #[utoipa::path(
     get,
-    path = "/api/v1/payments/{payment_id}",
+    path = "/api/v1/payments/{payment_id}/status",
     params(
-        ("payment_id" = Uuid, Path, description = "Payment ID")
+        ("payment_id" = Uuid, Path, description = "Payment ID"),
+        ("client_secret" = String, Query, description = "Client secret returned when the payment was created")
     ),
     responses(
-        (status = 200, description = "Payment retrieved successfully", body = PaymentResponse),
-        (status = 404, description = "Payment not found"),
-        (status = 401, description = "Unauthorized"),
-    ),
-    security(
-        ("bearer_auth" = [])
+        (status = 200, description = "Current payment outcome", body = PaymentStatusResponse),
+        (status = 404, description = "Payment not found or client secret does not match"),
     )
 )]
-pub async fn get_payment(
-    req: HttpRequest,
+pub async fn get_payment_status(
     path: web::Path<Uuid>,
+    query: web::Query<PaymentStatusQuery>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, DefiantError> {
-    let payment_id = path.into_inner();
-    info!("Getting payment: {}", payment_id);
-    
-    // Get API key
-    let api_key = req.headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer "))
-        .ok_or_else(|| DefiantError::AuthenticationError("Missing API key".into()))?;
+    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+    let status = payment_service.get_payment_status(path.into_inner(), &query.client_secret).await?;
     
-    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone());
-    let payment = payment_service.get_payment(payment_id, api_key).await?;
-    
-    Ok(HttpResponse::Ok().json(payment))
+    Ok(HttpResponse::Ok()
+        .insert_header(("Cache-Control", "no-store"))
+        .json(status))
 }
 
-#[utoipa::path(
-    post,
-    path = "/api/v1/payments/{payment_id}/capture",
-    params(
-        ("payment_id" = Uuid, Path, description = "Payment ID")
-    ),
-    responses(
-        (status = 200, description = "Payment captured successfully", body = PaymentResponse),
-        (status = 400, description = "Cannot capture payment"),
-        (status = 404, description = "Payment not found"),
-    ),
-    security(
-        ("bearer_auth" = [])
-    )
-)]
-pub async fn capture_payment(
-    req: HttpRequest,
-    path: web::Path<Uuid>,
-    state: web::Data<AppState>,
-) -> Result<HttpResponse, DefiantError> {
-    let payment_id = path.into_inner();
-    info!("Capturing payment: {}", payment_id);
-    
-    let api_key = get_api_key(&req)?;
-    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone());
-    let payment = payment_service.capture_payment(payment_id, api_key).await?;
-    
-    Ok(HttpResponse::Ok().json(payment))
+permission! {
+    "confirmations:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/confirm",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        request_body = ConfirmPaymentRequest,
+        responses(
+            (status = 200, description = "Payment charged with the tokenized card", body = PaymentStatusResponse),
+            (status = 400, description = "Card token is invalid, expired or already used"),
+            (status = 402, description = "Payment declined by fraud checks"),
+            (status = 404, description = "Payment not found or client secret does not match"),
+            (status = 409, description = "Payment is not awaiting confirmation"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn confirm_payment(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<ConfirmPaymentRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let status = payment_service.confirm_payment(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok()
+            .insert_header(("Cache-Control", "no-store"))
+            .json(status))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/metadata:batch_update",
+        request_body = CreateMetadataBatchUpdateRequest,
+        responses(
+            (status = 202, description = "Batch update queued; poll it for progress", body = MetadataBatchUpdate),
+            (status = 400, description = "Empty filter or patch"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn batch_update_metadata(
+        req: HttpRequest,
+        data: web::Json<CreateMetadataBatchUpdateRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = MetadataBatchService::new(state.db.clone());
+        let batch = service
+            .request_batch_update(MetadataBatchObject::Payment, data.into_inner(), api_key)
+            .await?;
+        
+        Ok(HttpResponse::Accepted().json(batch))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payments/metadata:batch_update/{batch_id}",
+        params(
+            ("batch_id" = Uuid, Path, description = "Metadata batch update ID")
+        ),
+        responses(
+            (status = 200, description = "Batch update progress", body = MetadataBatchUpdate),
+            (status = 404, description = "Metadata batch update not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_metadata_batch_update(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let service = MetadataBatchService::new(state.db.clone());
+        let batch = service
+            .get_batch_update(MetadataBatchObject::Payment, path.into_inner(), api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(batch))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/crypto_refunds",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        request_body = CreateCryptoRefundRequest,
+        responses(
+            (status = 201, description = "Refund created and its amount reserved; it is broadcast asynchronously", body = CryptoRefund),
+            (status = 400, description = "Not a crypto payment, invalid amount or address"),
+            (status = 401, description = "Unauthorized"),
+            (status = 402, description = "On-chain refunds are not available on the payment's chain"),
+            (status = 404, description = "Payment not found"),
+            (status = 409, description = "Payment cannot be refunded"),
+        )
+    )]
+    pub async fn refund_crypto_payment(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<CreateCryptoRefundRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let service = CryptoRefundService::new(state.db.clone(), state.config.clone());
+        let refund = service.refund_crypto_payment(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        info!("Crypto refund {} created for payment {}", refund.id, refund.payment_id);
+        
+        Ok(HttpResponse::Created().json(refund))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payments/{payment_id}/crypto_refunds/{refund_id}",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID"),
+            ("refund_id" = Uuid, Path, description = "Crypto refund ID")
+        ),
+        responses(
+            (status = 200, description = "Crypto refund and its confirmations", body = CryptoRefund),
+            (status = 404, description = "Crypto refund not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_crypto_refund(
+        req: HttpRequest,
+        path: web::Path<(Uuid, Uuid)>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let (payment_id, refund_id) = path.into_inner();
+        let service = CryptoRefundService::new(state.db.clone(), state.config.clone());
+        let refund = service.get_crypto_refund(payment_id, refund_id, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(refund))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reviews",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("customer_id" = Option<Uuid>, Query, description = "Only this customer's payments")
+        ),
+        responses(
+            (status = 200, description = "Payments flagged for review, newest first, with their risk and geo signals", body = [PaymentReview]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_reviews(
+        req: HttpRequest,
+        query: web::Query<ReviewListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payment_service = PaymentService::new(state.db.clone(), state.redis.clone(), state.config.clone());
+        let reviews = payment_service.list_reviews(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(reviews))
+    }
+}
+
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/mark_fraudulent",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        request_body(content = LabelPaymentRequest, description = "Optional note kept with the label"),
+        responses(
+            (status = 200, description = "Labeled; the payment's card, IP address and customer are blocklisted for future payments", body = FraudLabelRecord),
+            (status = 404, description = "Payment not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn mark_fraudulent(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: Option<web::Json<LabelPaymentRequest>>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let request = data.map(|data| data.into_inner()).unwrap_or_default();
+        request.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let feedback_service = FraudFeedbackService::new(state.db.clone(), state.redis.clone());
+        let record = feedback_service
+            .label_payment(path.into_inner(), FraudLabel::Fraudulent, request, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(record))
+    }
 }
 
+permission! {
+    "payments:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payments/{payment_id}/mark_safe",
+        params(
+            ("payment_id" = Uuid, Path, description = "Payment ID")
+        ),
+        request_body(content = LabelPaymentRequest, description = "Optional note kept with the label"),
+        responses(
+            (status = 200, description = "Labeled; blocklist entries from the payment are lifted and its velocity counters reset", body = FraudLabelRecord),
+            (status = 404, description = "Payment not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn mark_safe(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: Option<web::Json<LabelPaymentRequest>>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let request = data.map(|data| data.into_inner()).unwrap_or_default();
+        request.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let feedback_service = FraudFeedbackService::new(state.db.clone(), state.redis.clone());
+        let record = feedback_service
+            .label_payment(path.into_inner(), FraudLabel::Safe, request, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(record))
+    }
+}
+
+permission! {
+    "payments:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reviews/rule_stats",
+        responses(
+            (status = 200, description = "Precision and recall of each fraud rule against labeled payments", body = FraudRuleStatsResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn fraud_rule_stats(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let feedback_service = FraudFeedbackService::new(state.db.clone(), state.redis.clone());
+        let stats = feedback_service.rule_stats(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(stats))
+    }
+}
+
+/// Lets the customer choose where a crypto refund is sent. Authenticated by the
+/// payment's client secret, never an API key.
 #[utoipa::path(
     post,
-    path = "/api/v1/payments/{payment_id}/refund",
+    path = "/api/v1/payments/{payment_id}/refund_address",
     params(
         ("payment_id" = Uuid, Path, description = "Payment ID")
     ),
+    request_body = RegisterRefundAddressRequest,
     responses(
-        (status = 200, description = "Payment refunded successfully", body = PaymentResponse),
-        (status = 400, description = "Cannot refund payment"),
-        (status = 404, description = "Payment not found"),
-    ),
-    security(
-        ("bearer_auth" = [])
+        (status = 200, description = "Refund address registered"),
+        (status = 400, description = "Not a valid address on the payment's chain"),
+        (status = 404, description = "Payment not found or client secret does not match"),
     )
 )]
-pub async fn refund_payment(
-    req: HttpRequest,
+pub async fn register_refund_address(
     path: web::Path<Uuid>,
-    data: web::Json<RefundRequest>,
-    state: web::Data<AppState>,
-) -> Result<HttpResponse, DefiantError> {
-    let payment_id = path.into_inner();
-    info!("Refunding payment: {}", payment_id);
-    
-    let api_key = get_api_key(&req)?;
-    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone());
-    let payment = payment_service.refund_payment(payment_id, data.into_inner(), api_key).await?;
-    
-    Ok(HttpResponse::Ok().json(payment))
-}
-
-#[utoipa::path(
-    get,
-    path = "/api/v1/payments",
-    params(
-        ("limit" = Option<i64>, Query, description = "Number of payments to return"),
-        ("starting_after" = Option<Uuid>, Query, description = "Cursor for pagination"),
-        ("ending_before" = Option<Uuid>, Query, description = "Cursor for pagination"),
-        ("customer" = Option<Uuid>, Query, description = "Filter by customer"),
-        ("status" = Option<String>, Query, description = "Filter by status"),
-    ),
-    responses(
-        (status = 200, description = "List of payments", body = PaymentsListResponse),
-        (status = 401, description = "Unauthorized"),
-    ),
-    security(
-        ("bearer_auth" = [])
-    )
-)]
-pub async fn list_payments(
-    req: HttpRequest,
-    query: web::Query<PaymentListQuery>,
+    data: web::Json<RegisterRefundAddressRequest>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, DefiantError> {
-    let api_key = get_api_key(&req)?;
-    let payment_service = PaymentService::new(state.db.clone(), state.redis.clone());
-    let payments = payment_service.list_payments(query.into_inner(), api_key).await?;
-    
-    Ok(HttpResponse::Ok().json(payments))
-}
-
-// Helper functions
-async fn check_rate_limit(req: &HttpRequest, state: &web::Data<AppState>) -> Result<(), DefiantError> {
-    // Implement rate limiting using Redis
-    // This is a simplified version
-    let client_ip = req.connection_info().realip_remote_addr().unwrap_or("unknown");
-    let key = format!("rate_limit:{}", client_ip);
-    
-    let mut conn = state.redis.get_async_connection().await
-        .map_err(|_| DefiantError::InternalError)?;
-    
-    let count: i64 = redis::cmd("INCR")
-        .arg(&key)
-        .query_async(&mut conn)
-        .await
-        .map_err(|_| DefiantError::InternalError)?;
-    
-    if count == 1 {
-        let _: () = redis::cmd("EXPIRE")
-            .arg(&key)
-            .arg(60) // 1 minute
-            .query_async(&mut conn)
-            .await
-            .map_err(|_| DefiantError::InternalError)?;
-    }
+    data.validate()?;
     
-    if count > 100 { // 100 requests per minute
-        return Err(DefiantError::RateLimitError);
-    }
+    let service = CryptoRefundService::new(state.db.clone(), state.config.clone());
+    let registered = service
+        .register_refund_address(path.into_inner(), &data.client_secret, &data.address)
+        .await?;
     
-    Ok(())
+    Ok(HttpResponse::Ok().json(registered))
 }
-
-fn get_api_key(req: &HttpRequest) -> Result<&str, DefiantError> {
-    req.headers()
-        .get("Authorization")
-        .and_then(|h| h.to_str().ok())
-        .and_then(|s| s.strip_prefix("Bearer "))
-        .ok_or_else(|| DefiantError::AuthenticationError("Missing API key".into()))
-}
-
-// Request/Response types
-#[derive(Debug, serde::Deserialize)]
-pub struct RefundRequest {
-    pub amount: Option<i64>,
-    pub reason: Option<String>,
-}
-
-#[derive(Debug, serde::Deserialize)]
-pub struct PaymentListQuery {
-    pub limit: Option<i64>,
-    pub starting_after: Option<Uuid>,
-    pub ending_before: Option<Uuid>,
-    pub customer: Option<Uuid>,
-    pub status: Option<String>,
-}
-
-#[derive(Debug, serde::Serialize)]
-pub struct PaymentsListResponse {
-    pub data: Vec<PaymentResponse>,
-    pub has_more: bool,
-    pub total: i64,
-    pub url: String,
-}
\ No newline at end of file
@@ -0,0 +1,174 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{BalanceTransaction, BalanceTransactionListQuery, CreatePayoutRequest, Payout, PayoutListQuery, UpcomingPayouts}, errors::DefiantError, AppState, services::{payout_service::PayoutService, ledger::LedgerService}};
+
+permission! {
+    "payouts:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payouts",
+        request_body = CreatePayoutRequest,
+        responses(
+            (status = 201, description = "Payout created; its amount, and the fee of an instant payout, have left the available balance", body = Payout),
+            (status = 400, description = "Invalid input or unsupported currency"),
+            (status = 403, description = "Instant payout requested by a merchant not eligible for them"),
+            (status = 409, description = "Amount exceeds the available balance, or the card funds an instant payout may spend, or a plugin blocked the payout"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_payout(
+        req: HttpRequest,
+        data: web::Json<CreatePayoutRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let payout = payout_service.create_manual_payout(data.into_inner(), api_key).await?;
+        
+        info!("Payout created: {}", payout.id);
+        
+        Ok(HttpResponse::Created().json(payout))
+    }
+}
+
+permission! {
+    "payouts:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payouts",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("status" = Option<String>, Query, description = "pending, in_transit, paid, failed or canceled")
+        ),
+        responses(
+            (status = 200, description = "Payouts, newest first", body = [Payout]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_payouts(
+        req: HttpRequest,
+        query: web::Query<PayoutListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let payouts = payout_service.list_payouts(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payouts))
+    }
+}
+
+permission! {
+    "payouts:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payouts/upcoming",
+        responses(
+            (status = 200, description = "What the next scheduled run would convert, pay out and hold back; nothing is moved", body = UpcomingPayouts),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_upcoming_payouts(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let upcoming = payout_service.upcoming_payouts(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(upcoming))
+    }
+}
+
+permission! {
+    "payouts:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payouts/{payout_id}",
+        params(
+            ("payout_id" = Uuid, Path, description = "Payout ID")
+        ),
+        responses(
+            (status = 200, description = "Payout retrieved successfully", body = Payout),
+            (status = 404, description = "Payout not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_payout(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let payout = payout_service.get_payout(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payout))
+    }
+}
+
+permission! {
+    "payouts:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/payouts/{payout_id}/cancel",
+        params(
+            ("payout_id" = Uuid, Path, description = "Payout ID")
+        ),
+        responses(
+            (status = 200, description = "Payout canceled; its amount is back in the available balance", body = Payout),
+            (status = 404, description = "Payout not found"),
+            (status = 409, description = "Payout has already been sent"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn cancel_payout(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let payout_service = PayoutService::new(state.db.clone(), state.config.clone());
+        let payout = payout_service.cancel_payout(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(payout))
+    }
+}
+
+permission! {
+    "payouts:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/payouts/{payout_id}/transactions",
+        params(
+            ("payout_id" = Uuid, Path, description = "Payout ID"),
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20"),
+            ("type" = Option<String>, Query, description = "charge, refund, fee, conversion, reserve or adjustment")
+        ),
+        responses(
+            (status = 200, description = "Balance movements the payout paid out, oldest first", body = [BalanceTransaction]),
+            (status = 404, description = "Payout not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_payout_transactions(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<BalanceTransactionListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let ledger_service = LedgerService::new(state.db.clone());
+        let transactions = ledger_service
+            .list_payout_transactions(path.into_inner(), query.into_inner(), api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(transactions))
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+use actix_web::{HttpMessage, HttpRequest};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::{errors::DefiantError, db::{Database, AuthenticatedKey}, models::TeamRole, AppState, services::{account_service::is_api_key, security_service::{SecurityService, SignedRequest}}, custom_middleware::rate_limit::client_ip};
+
+/// Every scope an API key can be granted. `<resource>:write` implies `<resource>:read`.
+pub const API_KEY_SCOPES: &[&str] = &[
+    "payments:read", "payments:write",
+    "customers:read", "customers:write",
+    "webhooks:read", "webhooks:write",
+    "events:read", "events:write",
+    "invoices:read", "invoices:write",
+    "payment_links:read", "payment_links:write",
+    "checkout:read", "checkout:write",
+    "quotes:read", "quotes:write",
+    "subscriptions:read", "subscriptions:write",
+    "reports:read",
+    "settlement_batches:read", "settlement_batches:write",
+    "terminal:read", "terminal:write",
+    "mit_agreements:read", "mit_agreements:write",
+    "consents:read",
+    "account:read", "account:write",
+    "crypto_wallets:read", "crypto_wallets:write",
+    "fx_rates:read",
+    "ledger:read",
+    "balance:read",
+    "payouts:read", "payouts:write",
+    "connect:read", "connect:write",
+    "api_keys:read", "api_keys:write",
+    "tokens:write",
+    "confirmations:write",
+    "team:read", "team:write",
+    "oauth_clients:read", "oauth_clients:write",
+    "security:read",
+    "audit_logs:read",
+];
+
+/// The fixed scopes of a publishable key, which is safe to embed in a browser or app
+/// because it can do nothing beyond tokenizing a card and confirming a payment.
+pub const PUBLISHABLE_KEY_SCOPES: &[&str] = &["tokens:write", "confirmations:write"];
+
+const DEVELOPER_SCOPES: &[&str] = &[
+    "api_keys:write", "webhooks:write", "events:write",
+    "tokens:write", "confirmations:write", "oauth_clients:write",
+    "payments:read", "customers:read", "invoices:read", "payment_links:read",
+    "checkout:read", "quotes:read", "subscriptions:read", "terminal:read",
+    "mit_agreements:read", "consents:read", "account:read", "crypto_wallets:read",
+    "fx_rates:read", "ledger:read", "balance:read", "payouts:read", "connect:read",
+    "settlement_batches:read", "team:read",
+];
+
+const ANALYST_SCOPES: &[&str] = &[
+    "payments:read", "customers:read", "webhooks:read", "events:read",
+    "invoices:read", "payment_links:read", "checkout:read", "quotes:read",
+    "subscriptions:read", "reports:read", "settlement_batches:read", "terminal:read",
+    "mit_agreements:read", "consents:read", "account:read", "crypto_wallets:read",
+    "fx_rates:read", "ledger:read", "balance:read", "payouts:read", "connect:read",
+    "team:read",
+];
+
+const SUPPORT_SCOPES: &[&str] = &[
+    "payments:write", "customers:write",
+    "invoices:read", "subscriptions:read", "checkout:read", "payment_links:read",
+    "events:read", "consents:read", "mit_agreements:read", "team:read",
+];
+
+const _: () = assert!(
+    all_known(DEVELOPER_SCOPES) && all_known(ANALYST_SCOPES) && all_known(SUPPORT_SCOPES) && all_known(PUBLISHABLE_KEY_SCOPES),
+    "role or publishable key scopes include an unknown scope",
+);
+
+/// The permission matrix: scopes a dashboard session acting with `role` holds.
+/// `None` is full access, as for a secret key without a scope list.
+pub fn role_scopes(role: TeamRole) -> Option<&'static [&'static str]> {
+    match role {
+        TeamRole::Owner | TeamRole::Admin => None,
+        TeamRole::Developer => Some(DEVELOPER_SCOPES),
+        TeamRole::Analyst => Some(ANALYST_SCOPES),
+        TeamRole::Support => Some(SUPPORT_SCOPES),
+    }
+}
+
+/// Declares the scope a handler requires.
+///
+/// Wraps a `#[utoipa::path]` handler so the scope is both checked before the body
+/// runs and listed under the operation's `bearer_auth` security requirement. Unknown
+/// scopes fail to compile, and `get_api_key` refuses to hand out a key on requests
+/// that did not pass through a check, so an undeclared endpoint fails closed.
+///
+/// ```ignore
+/// permission! {
+///     "payments:read";
+///     #[utoipa::path(get, path = "/api/v1/payments/{payment_id}", ...)]
+///     pub async fn get_payment(req: HttpRequest, ...) -> Result<HttpResponse, DefiantError> { ... }
+/// }
+/// ```
+macro_rules! permission {
+    (
+        $scope:literal;
+        #[utoipa::path($($doc:tt)*)]
+        $(#[$meta:meta])*
+        $vis:vis async fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty $body:block
+    ) => {
+        const _: () = assert!(
+            $crate::api::v1::permissions::is_known_scope($scope),
+            concat!("unknown API key scope: ", $scope),
+        );
+        
+        #[utoipa::path($($doc)*, security(("bearer_auth" = [$scope])))]
+        $(#[$meta])*
+        $vis async fn $name(
+            __permission_req: actix_web::HttpRequest,
+            __permission_state: actix_web::web::Data<$crate::AppState>,
+            $($arg: $ty),*
+        ) -> $ret {
+            $crate::api::v1::permissions::require_scope(&__permission_req, &__permission_state, $scope).await?;
+            $body
+        }
+    };
+}
+
+/// Marker left on the request once its key's scope has been checked.
+#[derive(Debug, Clone, Copy)]
+pub struct AuthorizedScope(pub &'static str);
+
+pub const fn is_known_scope(scope: &str) -> bool {
+    let mut i = 0;
+    while i < API_KEY_SCOPES.len() {
+        if str_eq(API_KEY_SCOPES[i], scope) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn all_known(scopes: &[&str]) -> bool {
+    let mut i = 0;
+    while i < scopes.len() {
+        if !is_known_scope(scopes[i]) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn str_eq(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Uses the key the auth middleware resolved, or looks it up on paths the middleware
+/// does not authenticate.
+pub async fn require_scope(req: &HttpRequest, state: &AppState, scope: &'static str) -> Result<(), DefiantError> {
+    let resolved = req.extensions().get::<AuthenticatedKey>().cloned();
+    let key = match resolved {
+        Some(key) => key,
+        None => {
+            let token = bearer_token(req)?;
+            let key = state.db.authenticate_key(token).await?;
+            let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+            security_service
+                .enforce_ip_allowlist(&key, client_ip(req, state.config.trust_proxy_headers), req.method().as_str(), req.path())
+                .await?;
+            let signed = req.extensions().get::<SignedRequest>().cloned();
+            security_service
+                .enforce_request_signing(&state.redis, token, key.merchant_id, signed.as_ref())
+                .await?;
+            if is_api_key(token) {
+                touch_last_used(state.db.clone(), key.id);
+            }
+            key
+        }
+    };
+    
+    if !key.grants(scope) {
+        return Err(match key.team_role {
+            Some(role) => DefiantError::AuthorizationError(format!("The {:?} role lacks the '{}' scope", role, scope)),
+            None => DefiantError::AuthorizationError(format!("API key lacks the '{}' scope", scope)),
+        });
+    }
+    
+    req.extensions_mut().insert(AuthorizedScope(scope));
+    Ok(())
+}
+
+/// Records that the key was used, off the request path. Writes at most once a minute
+/// per key, so busy keys do not contend on their row.
+pub(crate) fn touch_last_used(db: Arc<Database>, key_id: Uuid) {
+    tokio::spawn(async move {
+        let touched = sqlx::query!(
+            r#"
+            UPDATE api_keys SET last_used_at = NOW()
+            WHERE id = $1 AND (last_used_at IS NULL OR last_used_at < NOW() - INTERVAL '1 minute')
+            "#,
+            key_id,
+        )
+        .execute(&db.pool)
+        .await;
+        if let Err(e) = touched {
+            warn!("Failed to record use of API key {}: {}", key_id, e);
+        }
+    });
+}
+
+/// Keys store `{"scopes": [...]}` in `permissions`. Keys without a scope list predate
+/// scoping and keep full access.
+pub(crate) fn grants(permissions: Option<&serde_json::Value>, scope: &str) -> bool {
+    let scopes = match permissions.and_then(|p| p.get("scopes")).and_then(|s| s.as_array()) {
+        Some(scopes) => scopes,
+        None => return true,
+    };
+    let implied_by = scope.strip_suffix(":read").map(|resource| format!("{}:write", resource));
+    
+    scopes.iter().filter_map(|s| s.as_str()).any(|granted| {
+        granted == scope || Some(granted) == implied_by.as_deref()
+    })
+}
+
+pub(crate) fn bearer_token(req: &HttpRequest) -> Result<&str, DefiantError> {
+    req.headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "))
+        .ok_or_else(|| DefiantError::AuthenticationError("Missing API key".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{http::StatusCode, test};
+    use sqlx::PgPool;
+    
+    use crate::test_support;
+    
+    /// Webhook endpoints are skipped by the auth middleware, so the handler's own
+    /// scope check must also hold the merchant to request signing.
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn unsigned_request_to_skipped_path_is_refused(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let api_key = test_support::secret_key(&pool, merchant_id, None).await;
+        test_support::enable_request_signing(&pool, merchant_id).await;
+        let app = test::init_service(test_support::app(test_support::state(pool))).await;
+        
+        let request = test::TestRequest::post()
+            .uri("/api/v1/webhooks")
+            .insert_header(("Authorization", format!("Bearer {}", api_key)))
+            .set_json(serde_json::json!({
+                "url": "https://example.com/hooks",
+                "events": ["payment.succeeded"],
+            }))
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
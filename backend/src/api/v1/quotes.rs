@@ -0,0 +1,166 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateQuoteRequest, AcceptQuoteRequest, HostedQuoteQuery, QuoteResponse}, errors::DefiantError, AppState, services::quote_service::QuoteService};
+
+permission! {
+    "quotes:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/quotes",
+        request_body = CreateQuoteRequest,
+        responses(
+            (status = 201, description = "Draft quote created", body = QuoteResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Customer or plan not found"),
+        )
+    )]
+    pub async fn create_quote(
+        req: HttpRequest,
+        data: web::Json<CreateQuoteRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+        let quote = quote_service.create_quote(data.into_inner(), api_key).await?;
+        
+        info!("Quote created: {}", quote.quote.id);
+        
+        Ok(HttpResponse::Created().json(quote))
+    }
+}
+
+permission! {
+    "quotes:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/quotes/{quote_id}",
+        params(
+            ("quote_id" = Uuid, Path, description = "Quote ID")
+        ),
+        responses(
+            (status = 200, description = "Quote retrieved successfully", body = QuoteResponse),
+            (status = 404, description = "Quote not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_quote(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+        let quote = quote_service.get_quote(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(quote))
+    }
+}
+
+permission! {
+    "quotes:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/quotes/{quote_id}/finalize",
+        params(
+            ("quote_id" = Uuid, Path, description = "Quote ID")
+        ),
+        responses(
+            (status = 200, description = "Quote opened; response includes the hosted acceptance link", body = QuoteResponse),
+            (status = 409, description = "Quote is not a draft"),
+        )
+    )]
+    pub async fn finalize_quote(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+        let quote = quote_service.finalize_quote(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(quote))
+    }
+}
+
+permission! {
+    "quotes:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/quotes/{quote_id}/cancel",
+        params(
+            ("quote_id" = Uuid, Path, description = "Quote ID")
+        ),
+        responses(
+            (status = 200, description = "Quote canceled", body = QuoteResponse),
+            (status = 409, description = "Quote was already accepted or canceled"),
+        )
+    )]
+    pub async fn cancel_quote(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+        let quote = quote_service.cancel_quote(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(quote))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/hosted/quotes/{quote_id}",
+    params(
+        ("quote_id" = Uuid, Path, description = "Quote ID"),
+        ("token" = String, Query, description = "Acceptance token from the quote link"),
+    ),
+    responses(
+        (status = 200, description = "Quote for customer review", body = QuoteResponse),
+        (status = 404, description = "Quote not found"),
+    )
+)]
+pub async fn get_hosted_quote(
+    path: web::Path<Uuid>,
+    query: web::Query<HostedQuoteQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+    let quote = quote_service.get_hosted_quote(path.into_inner(), &query.token).await?;
+    
+    Ok(HttpResponse::Ok().json(quote))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/hosted/quotes/{quote_id}/accept",
+    params(
+        ("quote_id" = Uuid, Path, description = "Quote ID")
+    ),
+    request_body = AcceptQuoteRequest,
+    responses(
+        (status = 200, description = "Quote accepted and converted", body = QuoteResponse),
+        (status = 404, description = "Quote not found"),
+        (status = 409, description = "Quote is not open or has expired"),
+    )
+)]
+pub async fn accept_quote(
+    path: web::Path<Uuid>,
+    data: web::Json<AcceptQuoteRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let quote_id = path.into_inner();
+    info!("Accepting quote: {}", quote_id);
+    
+    let quote_service = QuoteService::new(state.db.clone(), state.redis.clone());
+    let quote = quote_service.accept_quote(quote_id, &data.token).await?;
+    
+    Ok(HttpResponse::Ok().json(quote))
+}
@@ -0,0 +1,147 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+
+use super::get_api_key;
+use crate::{models::{ArAgingReport, PaymentExportRow, PayoutReconciliationRow, RevenueSummary, ReportQuery, payments_to_csv, payout_reconciliation_to_csv}, errors::DefiantError, AppState, services::report_service::ReportService};
+
+permission! {
+    "reports:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reports/ar_aging",
+        params(
+            ("as_of" = Option<String>, Query, description = "Report date (YYYY-MM-DD), defaults to today"),
+            ("format" = Option<String>, Query, description = "`json` (default) or `csv`"),
+        ),
+        responses(
+            (status = 200, description = "Accounts receivable aging report", body = ArAgingReport),
+            (status = 400, description = "Unsupported format"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn ar_aging(
+        req: HttpRequest,
+        query: web::Query<ReportQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let query = query.into_inner();
+        let report_service = ReportService::new(state.db.clone());
+        let report = report_service.ar_aging(query.as_of, api_key).await?;
+        
+        match query.format.as_deref() {
+            None | Some("json") => Ok(HttpResponse::Ok().json(report)),
+            Some("csv") => Ok(HttpResponse::Ok()
+                .content_type("text/csv")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"ar_aging_{}.csv\"", report.as_of),
+                ))
+                .body(report.to_csv())),
+            Some(other) => Err(DefiantError::BadRequest(format!("Unsupported format: {}", other))),
+        }
+    }
+}
+
+
+permission! {
+    "reports:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reports/payments",
+        params(
+            ("from" = Option<String>, Query, description = "First day to include (YYYY-MM-DD)"),
+            ("to" = Option<String>, Query, description = "Last day to include (YYYY-MM-DD)"),
+            ("format" = Option<String>, Query, description = "`json` (default) or `csv`"),
+        ),
+        responses(
+            (status = 200, description = "Payment export", body = [PaymentExportRow]),
+            (status = 400, description = "Unsupported format"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn payments_export(
+        req: HttpRequest,
+        query: web::Query<ReportQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let query = query.into_inner();
+        let report_service = ReportService::new(state.db.clone());
+        let rows = report_service.payments_export(query.from, query.to, api_key).await?;
+        
+        match query.format.as_deref() {
+            None | Some("json") => Ok(HttpResponse::Ok().json(rows)),
+            Some("csv") => Ok(HttpResponse::Ok()
+                .content_type("text/csv")
+                .insert_header(("Content-Disposition", "attachment; filename=\"payments.csv\""))
+                .body(payments_to_csv(&rows))),
+            Some(other) => Err(DefiantError::BadRequest(format!("Unsupported format: {}", other))),
+        }
+    }
+}
+
+permission! {
+    "reports:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reports/payout_reconciliation",
+        params(
+            ("from" = Option<String>, Query, description = "First payout day to include (YYYY-MM-DD)"),
+            ("to" = Option<String>, Query, description = "Last payout day to include (YYYY-MM-DD)"),
+            ("payout_id" = Option<Uuid>, Query, description = "Only this payout"),
+            ("format" = Option<String>, Query, description = "`json` (default) or `csv`"),
+        ),
+        responses(
+            (status = 200, description = "Charges, refunds and fees behind each payout, with gross, fee and net amounts", body = [PayoutReconciliationRow]),
+            (status = 400, description = "Unsupported format"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn payout_reconciliation(
+        req: HttpRequest,
+        query: web::Query<ReportQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let query = query.into_inner();
+        let report_service = ReportService::new(state.db.clone());
+        let rows = report_service.payout_reconciliation(query.from, query.to, query.payout_id, api_key).await?;
+        
+        match query.format.as_deref() {
+            None | Some("json") => Ok(HttpResponse::Ok().json(rows)),
+            Some("csv") => Ok(HttpResponse::Ok()
+                .content_type("text/csv")
+                .insert_header(("Content-Disposition", "attachment; filename=\"payout_reconciliation.csv\""))
+                .body(payout_reconciliation_to_csv(&rows))),
+            Some(other) => Err(DefiantError::BadRequest(format!("Unsupported format: {}", other))),
+        }
+    }
+}
+
+permission! {
+    "reports:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/reports/revenue",
+        params(
+            ("from" = Option<String>, Query, description = "First day to include (YYYY-MM-DD)"),
+            ("to" = Option<String>, Query, description = "Last day to include (YYYY-MM-DD)"),
+        ),
+        responses(
+            (status = 200, description = "Invoiced revenue per currency, excluding void and uncollectible invoices", body = [RevenueSummary]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn revenue(
+        req: HttpRequest,
+        query: web::Query<ReportQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let query = query.into_inner();
+        let report_service = ReportService::new(state.db.clone());
+        let summaries = report_service.revenue(query.from, query.to, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(summaries))
+    }
+}
@@ -0,0 +1,37 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use uuid::Uuid;
+
+use super::get_api_key;
+use crate::{models::{SecurityEventListQuery, SecurityEventListResponse}, errors::DefiantError, AppState, services::security_event_service::SecurityEventService};
+
+permission! {
+    "security:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/security/events",
+        params(
+            ("type" = Option<String>, Query, description = "Only events of this type, such as `login_failed`"),
+            ("subject" = Option<String>, Query, description = "Only events concerning this team member or merchant"),
+            ("api_key_id" = Option<Uuid>, Query, description = "Only events concerning this key"),
+            ("suspicious" = Option<bool>, Query, description = "Only suspicious events, or only the rest"),
+            ("created_gte" = Option<String>, Query, description = "Only events at or after this time (RFC 3339)"),
+            ("created_lte" = Option<String>, Query, description = "Only events at or before this time (RFC 3339)"),
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "The account's security log, newest first", body = SecurityEventListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_security_events(
+        req: HttpRequest,
+        query: web::Query<SecurityEventListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let security_event_service = SecurityEventService::new(state.db.clone());
+        let events = security_event_service.list(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(events))
+    }
+}
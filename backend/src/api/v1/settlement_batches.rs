@@ -0,0 +1,97 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+
+use super::get_api_key;
+use crate::{models::SettlementBatchResponse, errors::DefiantError, AppState, services::settlement_service::SettlementService};
+
+permission! {
+    "settlement_batches:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/settlement_batches/{batch_id}",
+        params(
+            ("batch_id" = Uuid, Path, description = "Settlement batch ID")
+        ),
+        responses(
+            (status = 200, description = "Settlement batch with per-transaction status", body = SettlementBatchResponse),
+            (status = 404, description = "Settlement batch not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_settlement_batch(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let settlement_service = SettlementService::new(state.db.clone());
+        let batch = settlement_service.get_batch(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(batch))
+    }
+}
+
+permission! {
+    "settlement_batches:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/settlement_batches/{batch_id}/file",
+        params(
+            ("batch_id" = Uuid, Path, description = "Settlement batch ID")
+        ),
+        responses(
+            (status = 200, description = "Batch file in the acquirer CSV format", content_type = "text/csv"),
+            (status = 404, description = "Settlement batch not found"),
+        )
+    )]
+    pub async fn download_settlement_file(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let settlement_service = SettlementService::new(state.db.clone());
+        let batch = settlement_service.get_batch(path.into_inner(), api_key).await?.batch;
+        
+        Ok(HttpResponse::Ok()
+            .content_type("text/csv")
+            .insert_header((
+                "Content-Disposition",
+                format!("attachment; filename=\"{}\"", batch.file_name),
+            ))
+            .body(batch.file_contents))
+    }
+}
+
+permission! {
+    "settlement_batches:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/settlement_batches/{batch_id}/acknowledgment",
+        params(
+            ("batch_id" = Uuid, Path, description = "Settlement batch ID")
+        ),
+        request_body(content = String, content_type = "text/csv", description = "Acquirer acknowledgment file"),
+        responses(
+            (status = 200, description = "Acknowledgment applied", body = SettlementBatchResponse),
+            (status = 400, description = "Malformed acknowledgment file"),
+            (status = 404, description = "Settlement batch not found"),
+            (status = 409, description = "Batch already acknowledged"),
+        )
+    )]
+    pub async fn acknowledge_settlement_batch(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        body: String,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let settlement_service = SettlementService::new(state.db.clone());
+        let batch = settlement_service.ingest_acknowledgment(path.into_inner(), &body, api_key).await?;
+        
+        info!("Settlement batch acknowledged: {}", batch.batch.id);
+        
+        Ok(HttpResponse::Ok().json(batch))
+    }
+}
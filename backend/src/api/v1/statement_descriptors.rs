@@ -0,0 +1,33 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{PreviewStatementDescriptorRequest, StatementDescriptorPreview}, errors::DefiantError, AppState, services::statement_descriptor_service::StatementDescriptorService};
+
+permission! {
+    "account:read";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/statement_descriptors/preview",
+        request_body = PreviewStatementDescriptorRequest,
+        responses(
+            (status = 200, description = "How the descriptor appears on each network's statements, and the rules it breaks", body = StatementDescriptorPreview),
+            (status = 400, description = "Invalid input"),
+            (status = 403, description = "Not a test mode key"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn preview_statement_descriptor(
+        req: HttpRequest,
+        data: web::Json<PreviewStatementDescriptorRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let descriptor_service = StatementDescriptorService::new(state.db.clone());
+        let preview = descriptor_service.preview(data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(preview))
+    }
+}
@@ -0,0 +1,158 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateSubscriptionRequest, UpdateSubscriptionRequest, CancelSubscriptionRequest, SubscriptionListQuery, SubscriptionResponse}, errors::DefiantError, AppState, services::subscription_service::SubscriptionService};
+
+permission! {
+    "subscriptions:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/subscriptions",
+        request_body = CreateSubscriptionRequest,
+        responses(
+            (status = 201, description = "Subscription created; the first period is invoiced unless it is a trial", body = SubscriptionResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Customer or plan not found"),
+            (status = 409, description = "Plan is no longer active"),
+        )
+    )]
+    pub async fn create_subscription(
+        req: HttpRequest,
+        data: web::Json<CreateSubscriptionRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let subscription_service = SubscriptionService::new(state.db.clone());
+        let subscription = subscription_service.create_subscription(data.into_inner(), api_key).await?;
+        
+        info!("Subscription created: {}", subscription.id);
+        
+        Ok(HttpResponse::Created().json(subscription))
+    }
+}
+
+permission! {
+    "subscriptions:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/subscriptions/{subscription_id}",
+        params(
+            ("subscription_id" = Uuid, Path, description = "Subscription ID")
+        ),
+        responses(
+            (status = 200, description = "Subscription retrieved successfully, with any change scheduled for its period end", body = SubscriptionResponse),
+            (status = 404, description = "Subscription not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_subscription(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let subscription_service = SubscriptionService::new(state.db.clone());
+        let subscription = subscription_service.get_subscription(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(subscription))
+    }
+}
+
+permission! {
+    "subscriptions:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/subscriptions/{subscription_id}",
+        params(
+            ("subscription_id" = Uuid, Path, description = "Subscription ID")
+        ),
+        request_body = UpdateSubscriptionRequest,
+        responses(
+            (status = 200, description = "Subscription updated, or the change scheduled for its period end", body = SubscriptionResponse),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Subscription or plan not found"),
+            (status = 409, description = "Subscription has ended, has moved past the request's `version`, or the plan is no longer active"),
+        )
+    )]
+    pub async fn update_subscription(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<UpdateSubscriptionRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let subscription_service = SubscriptionService::new(state.db.clone());
+        let subscription = subscription_service.update_subscription(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(subscription))
+    }
+}
+
+permission! {
+    "subscriptions:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/subscriptions/{subscription_id}/cancel",
+        params(
+            ("subscription_id" = Uuid, Path, description = "Subscription ID")
+        ),
+        request_body = CancelSubscriptionRequest,
+        responses(
+            (status = 200, description = "Subscription canceled, or its cancellation scheduled for the period end", body = SubscriptionResponse),
+            (status = 401, description = "Unauthorized"),
+            (status = 404, description = "Subscription not found"),
+            (status = 409, description = "Subscription has already ended or has moved past the request's `version`"),
+        )
+    )]
+    pub async fn cancel_subscription(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<CancelSubscriptionRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let subscription_id = path.into_inner();
+        info!("Canceling subscription: {}", subscription_id);
+        
+        let api_key = get_api_key(&req)?;
+        let subscription_service = SubscriptionService::new(state.db.clone());
+        let subscription = subscription_service.cancel_subscription(subscription_id, data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(subscription))
+    }
+}
+
+permission! {
+    "subscriptions:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/subscriptions",
+        params(
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("customer_id" = Option<Uuid>, Query, description = "Only this customer's subscriptions"),
+            ("status" = Option<String>, Query, description = "Only subscriptions in this status")
+        ),
+        responses(
+            (status = 200, description = "Subscriptions, newest first"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_subscriptions(
+        req: HttpRequest,
+        query: web::Query<SubscriptionListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let subscription_service = SubscriptionService::new(state.db.clone());
+        let subscriptions = subscription_service.list_subscriptions(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(subscriptions))
+    }
+}
@@ -0,0 +1,128 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{InviteTeamMemberRequest, UpdateTeamMemberRequest, TeamMember, TeamMemberListQuery, TeamMemberListResponse}, errors::DefiantError, AppState, services::team_service::TeamService};
+
+permission! {
+    "team:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/team/members",
+        request_body = InviteTeamMemberRequest,
+        responses(
+            (status = 201, description = "Invitation emailed to the new member", body = TeamMember),
+            (status = 400, description = "Invalid input"),
+            (status = 403, description = "Only owners can invite owners"),
+            (status = 409, description = "Email is already on the team"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn invite_team_member(
+        req: HttpRequest,
+        data: web::Json<InviteTeamMemberRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let team_service = TeamService::new(state.db.clone(), state.config.clone());
+        let member = team_service.invite(data.into_inner(), api_key).await?;
+        
+        info!("Team member invited: {}", member.id);
+        
+        Ok(HttpResponse::Created().json(member))
+    }
+}
+
+permission! {
+    "team:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/team/members",
+        params(
+            ("limit" = Option<i64>, Query, description = "At most 100; defaults to 20")
+        ),
+        responses(
+            (status = 200, description = "Members and pending invitations, newest first", body = TeamMemberListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_team_members(
+        req: HttpRequest,
+        query: web::Query<TeamMemberListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let team_service = TeamService::new(state.db.clone(), state.config.clone());
+        let members = team_service.list(query.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(members))
+    }
+}
+
+permission! {
+    "team:write";
+    #[utoipa::path(
+        patch,
+        path = "/api/v1/team/members/{member_id}",
+        params(
+            ("member_id" = Uuid, Path, description = "Team member ID")
+        ),
+        request_body = UpdateTeamMemberRequest,
+        responses(
+            (status = 200, description = "Role changed; the member's sessions are signed out", body = TeamMember),
+            (status = 403, description = "Only owners can manage owners"),
+            (status = 404, description = "Team member not found"),
+            (status = 409, description = "The team must keep at least one owner"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn update_team_member(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<UpdateTeamMemberRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let team_service = TeamService::new(state.db.clone(), state.config.clone());
+        let member = team_service.update_role(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(member))
+    }
+}
+
+permission! {
+    "team:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/team/members/{member_id}",
+        params(
+            ("member_id" = Uuid, Path, description = "Team member ID")
+        ),
+        responses(
+            (status = 200, description = "Member removed or invitation withdrawn", body = TeamMember),
+            (status = 403, description = "Only owners can manage owners"),
+            (status = 404, description = "Team member not found"),
+            (status = 409, description = "The team must keep at least one owner"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn remove_team_member(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let team_service = TeamService::new(state.db.clone(), state.config.clone());
+        let member = team_service.remove(path.into_inner(), api_key).await?;
+        
+        info!("Team member removed: {}", member.id);
+        
+        Ok(HttpResponse::Ok().json(member))
+    }
+}
@@ -0,0 +1,57 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{RegisterTerminalKeyRequest, TerminalEncryptionKey}, errors::DefiantError, AppState, services::terminal_service::TerminalService};
+
+permission! {
+    "terminal:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/terminal/keys",
+        request_body = RegisterTerminalKeyRequest,
+        responses(
+            (status = 201, description = "Key set registered; earlier keys of the same scheme start retiring", body = TerminalEncryptionKey),
+            (status = 400, description = "Invalid input"),
+            (status = 409, description = "Key set already registered"),
+        )
+    )]
+    pub async fn register_terminal_key(
+        req: HttpRequest,
+        data: web::Json<RegisterTerminalKeyRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let terminal_service = TerminalService::new(state.db.clone(), state.config.clone());
+        let key = terminal_service.register_key(data.into_inner(), api_key).await?;
+        
+        info!("Terminal key set registered: {}", key.key_set_id);
+        
+        Ok(HttpResponse::Created().json(key))
+    }
+}
+
+permission! {
+    "terminal:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/terminal/keys",
+        responses(
+            (status = 200, description = "Registered key sets, newest first", body = [TerminalEncryptionKey]),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_terminal_keys(
+        req: HttpRequest,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let terminal_service = TerminalService::new(state.db.clone(), state.config.clone());
+        let keys = terminal_service.list_keys(api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(keys))
+    }
+}
@@ -0,0 +1,35 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::info;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateCardTokenRequest, CardTokenResponse}, errors::DefiantError, AppState, services::token_service::TokenService};
+
+permission! {
+    "tokens:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/tokens",
+        request_body = CreateCardTokenRequest,
+        responses(
+            (status = 201, description = "Card tokenized; the token can be charged once within 15 minutes", body = CardTokenResponse),
+            (status = 400, description = "Invalid card details"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_card_token(
+        req: HttpRequest,
+        data: web::Json<CreateCardTokenRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let token_service = TokenService::new(state.db.clone(), state.config.clone());
+        let token = token_service.create_card_token(data.into_inner(), api_key).await?;
+        
+        info!("Card token created: {}", token.id);
+        
+        Ok(HttpResponse::Created().json(token))
+    }
+}
@@ -0,0 +1,318 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use tracing::{info, error};
+use uuid::Uuid;
+use chrono::Utc;
+use validator::Validate;
+
+use super::get_api_key;
+use crate::{models::{CreateWebhookRequest, UpdateWebhookRequest, WebhookResponse, Webhook, WebhookListQuery, WebhookListResponse, WebhookDelivery, WebhookDeliveryStatus, WebhookDeliveryListQuery, WebhookDeliveryListResponse, ForceQuery, DeliveryReceipt}, errors::DefiantError, AppState, services::{webhook_service::{WebhookService, SecretCipher}, stripe_service::{self, StripeService, StripeEvent}, communication_service::{self, CommunicationService}}};
+
+permission! {
+    "webhooks:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/webhooks",
+        request_body = CreateWebhookRequest,
+        responses(
+            (status = 201, description = "Webhook endpoint registered; the signing secret is only returned here", body = WebhookResponse),
+            (status = 400, description = "Invalid input"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn create_webhook(
+        req: HttpRequest,
+        data: web::Json<CreateWebhookRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let cipher = SecretCipher::from_hex(&state.config.webhook_encryption_key)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let webhook = webhook_service.create_endpoint(data.into_inner(), &cipher, api_key).await?;
+        
+        info!("Webhook endpoint created: {}", webhook.webhook.id);
+        
+        Ok(HttpResponse::Created().json(webhook))
+    }
+}
+
+permission! {
+    "webhooks:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/webhooks/{webhook_id}",
+        params(
+            ("webhook_id" = Uuid, Path, description = "Webhook endpoint ID")
+        ),
+        responses(
+            (status = 200, description = "Webhook endpoint retrieved successfully", body = Webhook),
+            (status = 404, description = "Webhook endpoint not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn get_webhook(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let webhook = webhook_service.get_endpoint(path.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(webhook))
+    }
+}
+
+permission! {
+    "webhooks:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/webhooks",
+        params(
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("active" = Option<bool>, Query, description = "Only enabled or only disabled endpoints")
+        ),
+        responses(
+            (status = 200, description = "Webhook endpoints, newest first", body = WebhookListResponse),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_webhooks(
+        req: HttpRequest,
+        query: web::Query<WebhookListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let webhooks = webhook_service.list_endpoints(query.limit, query.active, api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(webhooks))
+    }
+}
+
+permission! {
+    "webhooks:write";
+    #[utoipa::path(
+        put,
+        path = "/api/v1/webhooks/{webhook_id}",
+        params(
+            ("webhook_id" = Uuid, Path, description = "Webhook endpoint ID")
+        ),
+        request_body = UpdateWebhookRequest,
+        responses(
+            (status = 200, description = "Webhook endpoint updated", body = Webhook),
+            (status = 400, description = "Invalid input"),
+            (status = 404, description = "Webhook endpoint not found"),
+        )
+    )]
+    pub async fn update_webhook(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        data: web::Json<UpdateWebhookRequest>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        data.validate()?;
+        
+        let api_key = get_api_key(&req)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let webhook = webhook_service.update_endpoint(path.into_inner(), data.into_inner(), api_key).await?;
+        
+        Ok(HttpResponse::Ok().json(webhook))
+    }
+}
+
+permission! {
+    "webhooks:write";
+    #[utoipa::path(
+        delete,
+        path = "/api/v1/webhooks/{webhook_id}",
+        params(
+            ("webhook_id" = Uuid, Path, description = "Webhook endpoint ID"),
+            ("force" = Option<bool>, Query, description = "Drop undelivered events and running replays with the endpoint")
+        ),
+        responses(
+            (status = 204, description = "Webhook endpoint deleted"),
+            (status = 404, description = "Webhook endpoint not found"),
+            (status = 409, description = "Undelivered events or running replays; retry with force=true"),
+        )
+    )]
+    pub async fn delete_webhook(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<ForceQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        webhook_service.delete_endpoint(path.into_inner(), query.force, api_key).await?;
+        
+        Ok(HttpResponse::NoContent().finish())
+    }
+}
+
+permission! {
+    "webhooks:read";
+    #[utoipa::path(
+        get,
+        path = "/api/v1/webhooks/{webhook_id}/deliveries",
+        params(
+            ("webhook_id" = Uuid, Path, description = "Webhook endpoint ID"),
+            ("limit" = Option<i64>, Query, description = "Page size (1-100, default 20)"),
+            ("status" = Option<WebhookDeliveryStatus>, Query, description = "Only deliveries in this state")
+        ),
+        responses(
+            (status = 200, description = "Deliveries with their attempt history, newest first", body = WebhookDeliveryListResponse),
+            (status = 404, description = "Webhook endpoint not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn list_webhook_deliveries(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        query: web::Query<WebhookDeliveryListQuery>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let query = query.into_inner();
+        let webhook_service = WebhookService::new(state.db.clone());
+        let deliveries = webhook_service
+            .list_deliveries(path.into_inner(), query.limit, query.status, api_key)
+            .await?;
+        
+        Ok(HttpResponse::Ok().json(deliveries))
+    }
+}
+
+permission! {
+    "webhooks:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/webhook_deliveries/{delivery_id}/retry",
+        params(
+            ("delivery_id" = Uuid, Path, description = "Webhook delivery ID")
+        ),
+        responses(
+            (status = 200, description = "Delivery queued for an immediate attempt", body = WebhookDelivery),
+            (status = 404, description = "Webhook delivery not found"),
+            (status = 409, description = "Delivery in progress or endpoint disabled"),
+        )
+    )]
+    pub async fn retry_webhook_delivery(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let delivery = webhook_service.retry_delivery(path.into_inner(), api_key).await?;
+        
+        info!("Webhook delivery retry requested: {}", delivery.id);
+        
+        Ok(HttpResponse::Ok().json(delivery))
+    }
+}
+
+permission! {
+    "webhooks:write";
+    #[utoipa::path(
+        post,
+        path = "/api/v1/webhooks/{webhook_id}/rotate_secret",
+        params(
+            ("webhook_id" = Uuid, Path, description = "Webhook endpoint ID")
+        ),
+        responses(
+            (status = 200, description = "New signing secret; the previous one stays valid for 24 hours", body = WebhookResponse),
+            (status = 404, description = "Webhook endpoint not found"),
+            (status = 401, description = "Unauthorized"),
+        )
+    )]
+    pub async fn rotate_webhook_secret(
+        req: HttpRequest,
+        path: web::Path<Uuid>,
+        state: web::Data<AppState>,
+    ) -> Result<HttpResponse, DefiantError> {
+        let api_key = get_api_key(&req)?;
+        let cipher = SecretCipher::from_hex(&state.config.webhook_encryption_key)?;
+        let webhook_service = WebhookService::new(state.db.clone());
+        let webhook = webhook_service.rotate_secret(path.into_inner(), &cipher, api_key).await?;
+        
+        info!("Webhook secret rotated: {}", webhook.webhook.id);
+        
+        Ok(HttpResponse::Ok().json(webhook))
+    }
+}
+
+/// Receives events from Stripe. Authenticated by the `Stripe-Signature` header
+/// rather than an API key, so the raw body is verified before it is parsed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/stripe",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Event received"),
+        (status = 400, description = "Invalid signature or payload"),
+    )
+)]
+pub async fn handle_stripe_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let secret = state.config.stripe_webhook_secret.as_deref().ok_or_else(|| {
+        error!("Stripe webhook received but STRIPE_WEBHOOK_SECRET is not set");
+        DefiantError::InternalError
+    })?;
+    let signature = req.headers()
+        .get(stripe_service::STRIPE_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| DefiantError::WebhookError("Missing Stripe-Signature header".into()))?;
+    
+    stripe_service::verify_signature(secret, signature, &body, Utc::now().timestamp())?;
+    
+    let event: StripeEvent = serde_json::from_slice(&body)
+        .map_err(|e| DefiantError::BadRequest(format!("Invalid Stripe event: {}", e)))?;
+    let event_id = event.id.clone();
+    
+    StripeService::new(state.db.clone(), state.config.clone()).handle_event(event).await?;
+    
+    info!("Stripe event processed: {}", event_id);
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "received": true })))
+}
+
+/// Receives delivery receipts from the mail relay, signed like Stripe events but with
+/// `email_receipt_secret`, in the `X-Receipt-Signature` header.
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/email_receipts",
+    tag = "webhooks",
+    responses(
+        (status = 200, description = "Receipt applied"),
+        (status = 400, description = "Invalid signature or payload"),
+        (status = 404, description = "No message with that id"),
+    )
+)]
+pub async fn handle_email_receipt(
+    req: HttpRequest,
+    body: web::Bytes,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    let secret = state.config.email_receipt_secret.as_deref().ok_or_else(|| {
+        error!("Delivery receipt received but EMAIL_RECEIPT_SECRET is not set");
+        DefiantError::InternalError
+    })?;
+    let signature = req.headers()
+        .get(communication_service::RECEIPT_SIGNATURE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| DefiantError::WebhookError("Missing X-Receipt-Signature header".into()))?;
+    
+    stripe_service::verify_signature(secret, signature, &body, Utc::now().timestamp())?;
+    
+    let receipt: DeliveryReceipt = serde_json::from_slice(&body)
+        .map_err(|e| DefiantError::BadRequest(format!("Invalid delivery receipt: {}", e)))?;
+    
+    CommunicationService::new(state.db.clone()).update_delivery_status(receipt).await?;
+    
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "received": true })))
+}
@@ -0,0 +1,188 @@
+//! Developer CLI.
+//!
+//! `defiant listen --forward-to localhost:3000/hooks` long-polls the account's event
+//! stream and replays each event to a local URL, signed the same way live webhook
+//! deliveries are, so handlers can be exercised without a public endpoint.
+
+use std::env;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use ring::hmac;
+use serde::Deserialize;
+
+const DEFAULT_API_BASE: &str = "http://localhost:8080/api/v1";
+const SIGNATURE_HEADER: &str = "Defiant-Signature";
+const VERSION_HEADER: &str = "Defiant-Version";
+const POLL_TIMEOUT_SECS: u64 = 25;
+const RETRY_DELAY_SECS: u64 = 5;
+
+const USAGE: &str = "\
+Usage: defiant listen --forward-to <url> [options]
+
+Options:
+    --forward-to <url>   Local URL that receives events, e.g. localhost:3000/hooks
+    --events <filter>    Only forward this type or family wildcard, e.g. payment.*
+    --api-key <key>      Secret API key (default: $DEFIANT_API_KEY)
+    --api-base <url>     API base URL (default: $DEFIANT_API_BASE or http://localhost:8080/api/v1)";
+
+#[derive(Debug, Deserialize)]
+struct PollResponse {
+    data: Vec<serde_json::Value>,
+    cursor: String,
+}
+
+struct ListenOptions {
+    forward_to: String,
+    events: Option<String>,
+    api_key: String,
+    api_base: String,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    
+    match args.first().map(String::as_str) {
+        Some("listen") => match parse_listen_options(&args[1..]) {
+            Ok(options) => listen(options).await,
+            Err(message) => {
+                eprintln!("{}\n\n{}", message, USAGE);
+                ExitCode::from(2)
+            }
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_listen_options(args: &[String]) -> Result<ListenOptions, String> {
+    let mut forward_to = None;
+    let mut events = None;
+    let mut api_key = env::var("DEFIANT_API_KEY").ok();
+    let mut api_base = env::var("DEFIANT_API_BASE").unwrap_or_else(|_| DEFAULT_API_BASE.to_string());
+    
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().cloned().ok_or_else(|| format!("{} needs a value", flag));
+        match flag.as_str() {
+            "--forward-to" => forward_to = Some(value()?),
+            "--events" => events = Some(value()?),
+            "--api-key" => api_key = Some(value()?),
+            "--api-base" => api_base = value()?,
+            other => return Err(format!("Unknown option {}", other)),
+        }
+    }
+    
+    let forward_to = forward_to.ok_or("--forward-to is required")?;
+    // `localhost:3000/hooks` is the common shorthand
+    let forward_to = if forward_to.starts_with("http://") || forward_to.starts_with("https://") {
+        forward_to
+    } else {
+        format!("http://{}", forward_to)
+    };
+    
+    Ok(ListenOptions {
+        forward_to,
+        events,
+        api_key: api_key.ok_or("No API key: pass --api-key or set DEFIANT_API_KEY")?,
+        api_base: api_base.trim_end_matches('/').to_string(),
+    })
+}
+
+async fn listen(options: ListenOptions) -> ExitCode {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(POLL_TIMEOUT_SECS + 10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            eprintln!("Failed to start HTTP client: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    
+    // Local-only secret; live endpoints keep their own
+    let secret: String = rand::thread_rng().sample_iter(&Alphanumeric).take(32).map(char::from).collect();
+    println!("Ready! Forwarding events to {}", options.forward_to);
+    println!("Your local webhook signing secret is {}", secret);
+    
+    let mut cursor: Option<String> = None;
+    loop {
+        let mut request = client
+            .get(format!("{}/events/poll", options.api_base))
+            .bearer_auth(&options.api_key)
+            .query(&[("timeout", POLL_TIMEOUT_SECS.to_string())]);
+        if let Some(cursor) = &cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        if let Some(events) = &options.events {
+            request = request.query(&[("type", events)]);
+        }
+        
+        let response = match request.send().await {
+            Ok(response) => response,
+            Err(e) => {
+                eprintln!("Lost connection to {} ({}), retrying", options.api_base, e);
+                tokio::time::sleep(Duration::from_secs(RETRY_DELAY_SECS)).await;
+                continue;
+            }
+        };
+        
+        let status = response.status();
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            eprintln!("API key rejected ({}); it needs the events:read scope", status);
+            return ExitCode::FAILURE;
+        }
+        if !status.is_success() {
+            eprintln!("Event poll failed with HTTP {}, retrying", status);
+            tokio::time::sleep(Duration::from_secs(RETRY_DELAY_SECS)).await;
+            continue;
+        }
+        
+        let poll = match response.json::<PollResponse>().await {
+            Ok(poll) => poll,
+            Err(e) => {
+                eprintln!("Unreadable poll response ({}), retrying", e);
+                tokio::time::sleep(Duration::from_secs(RETRY_DELAY_SECS)).await;
+                continue;
+            }
+        };
+        
+        for event in &poll.data {
+            forward(&client, &options.forward_to, &secret, event).await;
+        }
+        cursor = Some(poll.cursor);
+    }
+}
+
+async fn forward(client: &reqwest::Client, url: &str, secret: &str, event: &serde_json::Value) {
+    let event_type = event["type"].as_str().unwrap_or("unknown");
+    let event_id = event["id"].as_str().unwrap_or("unknown");
+    let payload = event.to_string();
+    
+    let result = client
+        .post(url)
+        .header("Content-Type", "application/json")
+        .header(SIGNATURE_HEADER, sign(secret, Utc::now().timestamp(), &payload))
+        .header(VERSION_HEADER, event["api_version"].as_str().unwrap_or_default())
+        .body(payload)
+        .send()
+        .await;
+    
+    match result {
+        Ok(response) => println!("--> {} [{}]  <-- [{}]", event_type, event_id, response.status().as_u16()),
+        Err(e) => println!("--> {} [{}]  <-- failed: {}", event_type, event_id, e),
+    }
+}
+
+/// Same `t=<ts>,v1=<hex>` scheme as live deliveries.
+fn sign(secret: &str, timestamp: i64, payload: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+    format!("t={},v1={}", timestamp, hex::encode(tag.as_ref()))
+}
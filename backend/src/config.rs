@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
 
 #[derive(Debug, Clone, Deserialize)]
@@ -6,22 +7,363 @@ pub struct Config {
     pub host: String,
     pub port: u16,
     pub database_url: String,
+    /// Cluster holding data for EU-resident merchants; EU merchants are refused without it
+    pub eu_database_url: Option<String>,
+    /// Read replicas of the primary cluster, serving reads that tolerate replication lag
+    #[serde(default)]
+    pub read_replica_urls: Vec<String>,
+    /// Read replicas of the EU cluster
+    #[serde(default)]
+    pub eu_read_replica_urls: Vec<String>,
+    /// Seconds a replica may trail its primary before reads go back to the primary
+    #[serde(default = "default_replica_max_lag_secs")]
+    pub replica_max_lag_secs: u64,
+    /// Pool sizes and timeouts for every cluster and replica; unset values default by
+    /// environment
+    #[serde(default)]
+    pub database_pool: DatabasePool,
     pub redis_url: String,
     pub jwt_secret: String,
+    /// Seconds an access token is valid; sessions outlive it through refresh tokens
     pub jwt_expiration: i64,
+    /// Seconds a session's refresh token is valid; each refresh issues a new one
+    pub jwt_refresh_expiration: i64,
     pub cors_origin: String,
     pub workers: usize,
+    /// Default level for every module, e.g. `info`
     pub log_level: String,
+    /// Levels for particular modules, overriding `log_level`, e.g. `sqlx = "warn"`
+    #[serde(default)]
+    pub log_filters: HashMap<String, String>,
+    /// Defaults to JSON outside development
+    pub log_format: Option<LogFormat>,
     pub environment: Environment,
     pub stripe_secret_key: Option<String>,
     pub stripe_webhook_secret: Option<String>,
+    /// Secret the mail relay signs delivery receipts with, the way Stripe signs events;
+    /// receipts are refused while it is unset
+    pub email_receipt_secret: Option<String>,
+    /// Hex-encoded 256-bit key sealing outgoing webhook endpoint secrets
+    pub webhook_encryption_key: String,
+    /// Hex-encoded 256-bit key sealing dashboard users' TOTP secrets
+    pub mfa_encryption_key: String,
+    /// Hex-encoded 256-bit key sealing merchants' request signing secrets
+    pub request_signing_encryption_key: String,
+    pub webhook_workers: usize,
+    /// HMAC key for card fingerprints (SCA counters, saved cards, MIT agreements)
+    pub card_fingerprint_key: String,
+    /// Hex-encoded 256-bit key sealing tokenized card details until a payment redeems them
+    pub card_token_encryption_key: String,
+    /// Hex-encoded 256-bit master keys provisioned from the KMS, by KMS key id. They
+    /// wrap the data keys sealing customer and bank account PII; keep a retired key
+    /// here until re-encryption has re-wrapped everything under the active one.
+    pub pii_master_keys: HashMap<String, String>,
+    /// The master key new and re-wrapped data keys are wrapped with
+    pub pii_master_key_id: String,
+    /// HMAC key for the blind indexes that look up sealed customer emails. Changing it
+    /// orphans every existing hash, so it does not rotate.
+    pub pii_blind_index_key: String,
+    /// HMAC key signing the invoice payment links emailed to customers
+    pub invoice_link_key: String,
+    /// Origin customers reach hosted pages at, for links sent outside the API
+    pub hosted_base_url: String,
+    /// Origin of the merchant dashboard, for team invitation links
+    pub dashboard_base_url: String,
+    /// Processor endpoint that decrypts and authorizes card-present payloads
+    pub card_present_processor_url: Option<String>,
+    /// Esplora-compatible REST API the crypto watcher reads Bitcoin from
+    pub bitcoin_esplora_url: Option<String>,
+    /// Ethereum JSON-RPC node the crypto watcher reads from
+    pub ethereum_rpc_url: Option<String>,
+    /// Solana JSON-RPC node; payments count once their transaction is finalized
+    pub solana_rpc_url: Option<String>,
+    /// Spot price API crypto payments are quoted from
+    pub crypto_rate_api_url: Option<String>,
+    /// Swap provider that converts crypto payments into stablecoins for merchants who
+    /// settle in USDC/USDT
+    pub crypto_conversion_api_url: Option<String>,
+    pub crypto_conversion_api_key: Option<String>,
+    /// Custody service holding the keys on-chain refunds are sent from; it builds and
+    /// signs each refund transaction, which we broadcast through the chain's node
+    pub crypto_signer_url: Option<String>,
+    pub crypto_signer_api_key: Option<String>,
+    /// External service payments are sent to for a risk score; scored by built-in
+    /// heuristics when unset or unreachable
+    pub risk_scoring_url: Option<String>,
+    pub risk_scoring_api_key: Option<String>,
+    /// IP intelligence API answering `GET {url}/{ip}` with the IP's country and whether
+    /// it is a proxy or VPN; fraud checks go without geo signals when unset
+    pub geoip_api_url: Option<String>,
+    pub geoip_api_key: Option<String>,
+    /// Blocks deep a transaction must be before a crypto payment succeeds
+    pub bitcoin_confirmations: u32,
+    pub ethereum_confirmations: u32,
     pub smtp_host: String,
     pub smtp_port: u16,
     pub smtp_username: String,
     pub smtp_password: String,
     pub from_email: String,
+    /// Requests per `rate_limit_period` seconds for each API key of a standard-tier
+    /// merchant, and for each client IP calling without one. Higher tiers get multiples.
     pub rate_limit_requests: u32,
+    /// Requests per period for a standard-tier merchant's trusted sources combined
+    pub trusted_rate_limit_requests: u32,
     pub rate_limit_period: u64,
+    /// Take client addresses from `X-Real-IP`, as set by the fronting proxy. Leave off
+    /// when clients can reach the backend directly, or anyone can claim a trusted range.
+    pub trust_proxy_headers: bool,
+    /// Payment counts at which fraud checks hold payments for review or decline them;
+    /// unset thresholds are not checked
+    #[serde(default)]
+    pub velocity_limits: VelocityLimits,
+    /// What the platform charges merchants per card payment, booked to the ledger as a
+    /// fee when the payment succeeds
+    #[serde(default)]
+    pub processing_fees: ProcessingFees,
+    /// Days a succeeded card payment stays in the pending balance; defaults to 2
+    pub settlement_delay_days: Option<u32>,
+    /// What the platform charges per instant payout, on top of the amount paid out
+    #[serde(default = "default_instant_payout_fees")]
+    pub instant_payout_fees: ProcessingFees,
+    /// Body size and handler time limits per kind of route
+    #[serde(default)]
+    pub request_limits: RequestLimits,
+    /// Seconds in-flight requests and background workers get to finish after SIGTERM
+    /// before they are cut off
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+    /// Cron expressions replacing the default schedules of recurring tasks, by task
+    /// name, e.g. `payout_batching = "0 30 * * * *"`
+    #[serde(default)]
+    pub schedules: HashMap<String, String>,
+    /// How far ahead monthly partitions are created and how long they are kept
+    #[serde(default)]
+    pub partitioning: Partitioning,
+    /// Where and after how long old payments and events are exported and removed from
+    /// the database; unset leaves everything in place
+    pub archival: Option<Archival>,
+    /// Keep-alive and idle limits of WebSocket and event stream connections
+    #[serde(default)]
+    pub streaming: Streaming,
+}
+
+fn default_instant_payout_fees() -> ProcessingFees {
+    ProcessingFees { percent_bps: 100, fixed: 0 }
+}
+
+fn default_replica_max_lag_secs() -> u64 {
+    5
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    30
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Partitioning {
+    /// Months past the current one that always have a partition ready
+    #[serde(default = "default_months_ahead")]
+    pub months_ahead: u32,
+    /// Months of payments kept in the live table before their partitions move to the
+    /// archive schema; unset keeps them all
+    #[serde(default)]
+    pub payments_retention_months: Option<u32>,
+    /// Months of events kept in the live table; defaults to 13
+    #[serde(default = "default_events_retention_months")]
+    pub events_retention_months: Option<u32>,
+}
+
+impl Default for Partitioning {
+    fn default() -> Self {
+        Self {
+            months_ahead: default_months_ahead(),
+            payments_retention_months: None,
+            events_retention_months: default_events_retention_months(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Streaming {
+    /// Seconds between heartbeats on an open connection; kept below the idle timeout
+    /// of load balancers in front of the service, which drop silent sockets
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    /// Seconds without a message or heartbeat reply after which a connection is
+    /// closed as dead
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for Streaming {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval_secs: default_heartbeat_interval_secs(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+        }
+    }
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    30
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    90
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Archival {
+    /// Receives data from the primary cluster
+    pub bucket: ArchiveBucket,
+    /// Receives data from the EU cluster, which is not archived without it so that it
+    /// never leaves the region
+    pub eu_bucket: Option<ArchiveBucket>,
+    /// Days a payment that can no longer change stays in the database; defaults to 730
+    #[serde(default = "default_payments_archive_after_days")]
+    pub payments_after_days: u32,
+    /// Days an event stays in the database; defaults to 365, ahead of partition retention
+    #[serde(default = "default_events_archive_after_days")]
+    pub events_after_days: u32,
+}
+
+/// An S3-compatible bucket, addressed path-style.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ArchiveBucket {
+    /// e.g. `https://s3.eu-west-1.amazonaws.com`
+    pub endpoint: String,
+    pub name: String,
+    pub region: String,
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+fn default_payments_archive_after_days() -> u32 {
+    730
+}
+
+fn default_events_archive_after_days() -> u32 {
+    365
+}
+
+fn default_months_ahead() -> u32 {
+    3
+}
+
+fn default_events_retention_months() -> Option<u32> {
+    Some(13)
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DatabasePool {
+    /// Connections each pool opens at most
+    pub max_connections: Option<u32>,
+    /// Connections each pool keeps open while idle
+    pub min_connections: Option<u32>,
+    /// Seconds a query waits for a free connection before failing
+    pub acquire_timeout_secs: Option<u64>,
+    /// Milliseconds after which Postgres cancels a statement; 0 for no limit.
+    /// Migrations always run without one.
+    pub statement_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RequestLimits {
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// For batch updates and report exports
+    #[serde(default = "default_max_bulk_body_bytes")]
+    pub max_bulk_body_bytes: usize,
+    /// Seconds a GET may take
+    #[serde(default = "default_read_timeout_secs")]
+    pub read_timeout_secs: u64,
+    #[serde(default = "default_write_timeout_secs")]
+    pub write_timeout_secs: u64,
+    #[serde(default = "default_bulk_timeout_secs")]
+    pub bulk_timeout_secs: u64,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        Self {
+            max_body_bytes: default_max_body_bytes(),
+            max_bulk_body_bytes: default_max_bulk_body_bytes(),
+            read_timeout_secs: default_read_timeout_secs(),
+            write_timeout_secs: default_write_timeout_secs(),
+            bulk_timeout_secs: default_bulk_timeout_secs(),
+        }
+    }
+}
+
+fn default_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_max_bulk_body_bytes() -> usize {
+    10 * 1024 * 1024
+}
+
+fn default_read_timeout_secs() -> u64 {
+    10
+}
+
+fn default_write_timeout_secs() -> u64 {
+    30
+}
+
+fn default_bulk_timeout_secs() -> u64 {
+    120
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct ProcessingFees {
+    /// Of the payment amount
+    #[serde(default)]
+    pub percent_bps: u32,
+    /// Minor units of the payment's currency, added to every payment
+    #[serde(default)]
+    pub fixed: i64,
+}
+
+impl ProcessingFees {
+    /// Never more than the payment itself.
+    pub fn fee_for(&self, amount: i64) -> i64 {
+        let percent = (amount as i128 * self.percent_bps as i128 / 10_000) as i64;
+        percent.saturating_add(self.fixed).clamp(0, amount.max(0))
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VelocityLimits {
+    /// Per card fingerprint, across all merchants
+    #[serde(default)]
+    pub card: VelocityThresholds,
+    /// Per customer IP address, across all merchants
+    #[serde(default)]
+    pub ip: VelocityThresholds,
+    #[serde(default)]
+    pub customer: VelocityThresholds,
+    #[serde(default)]
+    pub merchant: VelocityThresholds,
+}
+
+/// Most payment attempts allowed in each trailing window before the next is flagged
+/// for review, or declined.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct VelocityThresholds {
+    pub hourly_review: Option<u32>,
+    pub hourly_block: Option<u32>,
+    pub daily_review: Option<u32>,
+    pub daily_block: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One JSON object per line, for log pipelines
+    Json,
+    /// Human-readable lines, for a terminal
+    Text,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -1,36 +1,151 @@
-use sqlx::{postgres::PgPoolOptions, PgPool, Pool, Postgres};
+use ring::digest;
+use sqlx::{postgres::PgPoolOptions, Executor, PgPool, Pool, Postgres};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::Duration;
-use tracing::info;
+use tracing::{debug, info, warn, error};
+use uuid::Uuid;
 
+use crate::{config::{Config, Environment}, errors::DefiantError, models::{DataRegion, TeamRole}, api::v1::permissions::{grants, role_scopes}, services::oauth_service::is_oauth_token};
+use crate::shutdown::Shutdown;
+
+const REPLICA_CHECK_INTERVAL_SECS: u64 = 5;
+
+/// Sizes and timeouts of every pool, from `Config::database_pool` with defaults for
+/// the environment: small pools and generous timeouts in development, where one
+/// developer's database serves everything, and a statement timeout elsewhere so a
+/// runaway query cannot hold a connection indefinitely.
+#[derive(Debug, Clone, Copy)]
+pub struct PoolSettings {
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub acquire_timeout: Duration,
+    /// Zero for no limit
+    pub statement_timeout: Duration,
+}
+
+impl PoolSettings {
+    pub fn from_config(config: &Config) -> Self {
+        let (max_connections, min_connections, acquire_timeout_secs, statement_timeout_ms) = match config.environment {
+            Environment::Development => (5, 0, 30, 0),
+            Environment::Staging => (10, 2, 5, 30_000),
+            Environment::Production => (20, 5, 5, 30_000),
+        };
+        let pool = &config.database_pool;
+        
+        Self {
+            max_connections: pool.max_connections.unwrap_or(max_connections),
+            min_connections: pool.min_connections.unwrap_or(min_connections),
+            acquire_timeout: Duration::from_secs(pool.acquire_timeout_secs.unwrap_or(acquire_timeout_secs)),
+            statement_timeout: Duration::from_millis(pool.statement_timeout_ms.unwrap_or(statement_timeout_ms)),
+        }
+    }
+}
+
+/// Connection pools for the primary (US) cluster and any regional clusters.
+///
+/// `pool` is the primary cluster: it owns the merchant directory and holds data for
+/// US merchants. Merchant-owned data must be read and written through
+/// [`Database::pool_for_merchant`] so EU merchants never touch the primary cluster.
+///
+/// Each cluster may also have read replicas. Reads that can tolerate a few seconds of
+/// lag (lists, lookups, reports) go through [`Database::read`] or
+/// [`Database::read_for_merchant`], which spread them over the cluster's healthy
+/// replicas and fall back to its primary when none are. Anything read in order to
+/// write, or read straight after a write, must stay on the primary.
+#[derive(Clone)]
 pub struct Database {
     pub pool: PgPool,
+    regional: HashMap<DataRegion, PgPool>,
+    replicas: HashMap<DataRegion, Vec<Arc<Replica>>>,
+    next_replica: Arc<AtomicUsize>,
+    merchant_regions: Arc<RwLock<HashMap<Uuid, DataRegion>>>,
+    settings: PoolSettings,
+}
+
+/// A read replica, used only while the monitor finds it reachable and caught up.
+/// Replicas start out unhealthy so none is used before its first check.
+struct Replica {
+    pool: PgPool,
+    healthy: AtomicBool,
 }
 
 impl Database {
-    pub async fn new(database_url: &str) -> Result<Self, sqlx::Error> {
+    pub async fn new(database_url: &str, settings: PoolSettings) -> Result<Self, sqlx::Error> {
         info!("Connecting to database...");
         
-        let pool = PgPoolOptions::new()
-            .max_connections(20)
-            .min_connections(5)
-            .acquire_timeout(Duration::from_secs(5))
-            .connect_timeout(Duration::from_secs(10))
-            .idle_timeout(Duration::from_secs(300))
-            .max_lifetime(Duration::from_secs(3600))
-            .connect(database_url)
-            .await?;
+        let pool = pool_options(DataRegion::Us, settings).connect(database_url).await?;
         
         info!("Database connection established");
         
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            regional: HashMap::new(),
+            replicas: HashMap::new(),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            merchant_regions: Arc::new(RwLock::new(HashMap::new())),
+            settings,
+        })
+    }
+    
+    /// A database with only the primary cluster, over a pool opened elsewhere, as
+    /// `#[sqlx::test]` provides one.
+    #[cfg(test)]
+    pub fn from_pool(pool: PgPool) -> Self {
+        Self {
+            pool,
+            regional: HashMap::new(),
+            replicas: HashMap::new(),
+            next_replica: Arc::new(AtomicUsize::new(0)),
+            merchant_regions: Arc::new(RwLock::new(HashMap::new())),
+            settings: PoolSettings {
+                max_connections: 5,
+                min_connections: 0,
+                acquire_timeout: Duration::from_secs(30),
+                statement_timeout: Duration::ZERO,
+            },
+        }
+    }
+    
+    pub async fn connect_region(&mut self, region: DataRegion, database_url: &str) -> Result<(), sqlx::Error> {
+        info!("Connecting to {} regional database...", region.as_str());
+        
+        let pool = pool_options(region, self.settings).connect(database_url).await?;
+        self.regional.insert(region, pool);
+        
+        Ok(())
+    }
+    
+    /// Adds a read replica of a region's cluster. It is connected lazily, so an
+    /// unreachable replica leaves reads on the primary rather than failing startup.
+    pub fn connect_replica(&mut self, region: DataRegion, database_url: &str) -> Result<(), sqlx::Error> {
+        info!("Adding {} read replica...", region.as_str());
+        
+        let pool = pool_options(region, self.settings).connect_lazy(database_url)?;
+        self.replicas.entry(region).or_default().push(Arc::new(Replica {
+            pool,
+            healthy: AtomicBool::new(false),
+        }));
+        
+        Ok(())
     }
     
+    /// Runs on a connection of its own without the statement timeout, as rewriting a
+    /// large table can take longer than any query should. The connection is closed
+    /// afterwards rather than returned to the pool.
     pub async fn run_migrations(&self) -> Result<(), sqlx::Error> {
         info!("Running database migrations...");
         
-        sqlx::migrate!("./migrations")
-            .run(&self.pool)
-            .await?;
+        for pool in self.pools() {
+            let mut conn = pool.acquire().await?.detach();
+            conn.execute("SET statement_timeout = 0").await?;
+            sqlx::migrate!("./migrations")
+                .run(&mut conn)
+                .await?;
+            conn.close().await?;
+        }
         
         info!("Migrations completed");
         Ok(())
@@ -39,6 +154,366 @@ impl Database {
     pub fn get_pool(&self) -> &Pool<Postgres> {
         &self.pool
     }
+    
+    /// Every cluster, primary first. Background jobs that scan by status rather than
+    /// by merchant must visit each one.
+    pub fn pools(&self) -> Vec<&PgPool> {
+        let mut pools = vec![&self.pool];
+        pools.extend(self.regional.values());
+        pools
+    }
+    
+    /// Every cluster with its region, primary first.
+    pub fn regions(&self) -> Vec<(DataRegion, &PgPool)> {
+        let mut regions = vec![(DataRegion::Us, &self.pool)];
+        regions.extend(self.regional.iter().map(|(region, pool)| (*region, pool)));
+        regions
+    }
+    
+    /// Waits for checked-out connections to be returned, then closes every cluster's
+    /// pools, replicas included.
+    pub async fn close(&self) {
+        for pool in self.pools() {
+            pool.close().await;
+        }
+        for replica in self.replicas.values().flatten() {
+            replica.pool.close().await;
+        }
+    }
+    
+    /// Connection counts for every pool in the Prometheus text format, for `/metrics`.
+    pub fn pool_metrics(&self) -> String {
+        let mut pools: Vec<(&str, String, &PgPool)> = vec![(DataRegion::Us.as_str(), "primary".into(), &self.pool)];
+        pools.extend(self.regional.iter().map(|(region, pool)| (region.as_str(), "primary".into(), pool)));
+        for (region, replicas) in &self.replicas {
+            pools.extend(replicas.iter().enumerate().map(|(i, replica)| (region.as_str(), format!("replica_{}", i), &replica.pool)));
+        }
+        
+        let mut out = String::new();
+        out.push_str("# HELP defiant_db_pool_connections Open connections in each pool, by state\n");
+        out.push_str("# TYPE defiant_db_pool_connections gauge\n");
+        for (region, role, pool) in &pools {
+            let idle = pool.num_idle() as u32;
+            let _ = writeln!(out, "defiant_db_pool_connections{{region=\"{}\",pool=\"{}\",state=\"idle\"}} {}", region, role, idle);
+            let _ = writeln!(out, "defiant_db_pool_connections{{region=\"{}\",pool=\"{}\",state=\"in_use\"}} {}", region, role, pool.size().saturating_sub(idle));
+        }
+        out.push_str("# HELP defiant_db_pool_max_connections Connections each pool may open\n");
+        out.push_str("# TYPE defiant_db_pool_max_connections gauge\n");
+        for (region, role, _) in &pools {
+            let _ = writeln!(out, "defiant_db_pool_max_connections{{region=\"{}\",pool=\"{}\"}} {}", region, role, self.settings.max_connections);
+        }
+        out.push_str("# HELP defiant_db_replica_healthy Whether each read replica is serving reads\n");
+        out.push_str("# TYPE defiant_db_replica_healthy gauge\n");
+        for (region, replicas) in &self.replicas {
+            for (i, replica) in replicas.iter().enumerate() {
+                let healthy = replica.healthy.load(Ordering::Relaxed) as u8;
+                let _ = writeln!(out, "defiant_db_replica_healthy{{region=\"{}\",pool=\"replica_{}\"}} {}", region.as_str(), i, healthy);
+            }
+        }
+        out
+    }
+    
+    /// Pool for lag-tolerant reads of the primary cluster.
+    pub fn read(&self) -> &PgPool {
+        self.replica(DataRegion::Us).unwrap_or(&self.pool)
+    }
+    
+    /// Pool for lag-tolerant reads of a region's cluster.
+    pub fn read_for_region(&self, region: DataRegion) -> Result<&PgPool, DefiantError> {
+        match self.replica(region) {
+            Some(pool) => Ok(pool),
+            None => self.pool_for_region(region),
+        }
+    }
+    
+    /// Pool for lag-tolerant reads of the given merchant's data.
+    pub async fn read_for_merchant(&self, merchant_id: Uuid) -> Result<&PgPool, DefiantError> {
+        let region = self.merchant_region(merchant_id).await?;
+        self.read_for_region(region)
+    }
+    
+    /// The next healthy replica of a region's cluster, in turn.
+    fn replica(&self, region: DataRegion) -> Option<&PgPool> {
+        let replicas = self.replicas.get(&region)?;
+        let start = self.next_replica.fetch_add(1, Ordering::Relaxed);
+        (0..replicas.len())
+            .map(|offset| &replicas[(start + offset) % replicas.len()])
+            .find(|replica| replica.healthy.load(Ordering::Relaxed))
+            .map(|replica| &replica.pool)
+    }
+    
+    /// Marks each replica healthy if it answers and has replayed to within `max_lag`
+    /// of its primary. An idle primary writes nothing to replay, so a replica that has
+    /// replayed everything it received counts as caught up however old its last
+    /// transaction is.
+    async fn check_replicas(&self, max_lag: Duration) {
+        for (region, replicas) in &self.replicas {
+            for replica in replicas {
+                let lag: Result<Option<f64>, sqlx::Error> = sqlx::query_scalar(
+                    r#"
+                    SELECT CASE
+                        WHEN NOT pg_is_in_recovery() THEN NULL
+                        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0
+                        ELSE EXTRACT(EPOCH FROM NOW() - pg_last_xact_replay_timestamp())
+                    END::FLOAT8
+                    "#,
+                )
+                .fetch_one(&replica.pool)
+                .await;
+                
+                let problem = match lag {
+                    Ok(Some(lag)) if lag <= max_lag.as_secs_f64() => None,
+                    Ok(Some(lag)) => Some(format!("is {:.1}s behind", lag)),
+                    Ok(None) => Some("is not in recovery".to_owned()),
+                    Err(e) => Some(format!("is unreachable: {}", e)),
+                };
+                
+                let was_healthy = replica.healthy.swap(problem.is_none(), Ordering::Relaxed);
+                match problem {
+                    Some(problem) if was_healthy => {
+                        warn!("{} read replica {}; reading from the primary", region.as_str(), problem);
+                    }
+                    Some(problem) => debug!("{} read replica {}", region.as_str(), problem),
+                    None if !was_healthy => info!("{} read replica is healthy", region.as_str()),
+                    None => {}
+                }
+            }
+        }
+    }
+    
+    pub fn pool_for_region(&self, region: DataRegion) -> Result<&PgPool, DefiantError> {
+        match region {
+            DataRegion::Us => Ok(&self.pool),
+            _ => self.regional.get(&region).ok_or_else(|| {
+                // Never fall back to the primary cluster: that would move resident data out of region
+                error!("No database configured for data region {}", region.as_str());
+                DefiantError::InternalError
+            }),
+        }
+    }
+    
+    pub async fn merchant_region(&self, merchant_id: Uuid) -> Result<DataRegion, DefiantError> {
+        if let Some(region) = self.merchant_regions.read().unwrap().get(&merchant_id) {
+            return Ok(*region);
+        }
+        
+        let region = sqlx::query_scalar!(
+            r#"SELECT data_region AS "data_region: DataRegion" FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        self.merchant_regions.write().unwrap().insert(merchant_id, region);
+        Ok(region)
+    }
+    
+    /// Pool holding the given merchant's data.
+    pub async fn pool_for_merchant(&self, merchant_id: Uuid) -> Result<&PgPool, DefiantError> {
+        let region = self.merchant_region(merchant_id).await?;
+        self.pool_for_region(region)
+    }
+    
+    /// Finds the cluster holding a row, for public endpoints that only carry an object id.
+    pub async fn pool_for_row(&self, table: &'static str, id: Uuid) -> Result<Option<&PgPool>, DefiantError> {
+        let query = format!("SELECT EXISTS(SELECT 1 FROM {} WHERE id = $1)", table);
+        
+        for pool in self.pools() {
+            let exists: bool = sqlx::query_scalar(&query)
+                .bind(id)
+                .fetch_one(pool)
+                .await?;
+            if exists {
+                return Ok(Some(pool));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// Resolves the merchant owning an active API key, or the merchant a dashboard
+    /// session acts for.
+    pub async fn authenticate_merchant(&self, api_key: &str) -> Result<Uuid, DefiantError> {
+        let merchant_id = sqlx::query_scalar!(
+            r#"
+            SELECT m.id AS "id!" FROM merchants m
+            JOIN api_keys ak ON m.id = ak.merchant_id
+            WHERE ak.key_hash = $1 AND ak.active = true
+            AND (ak.expires_at IS NULL OR ak.expires_at > NOW())
+            AND m.active = true
+            UNION ALL
+            SELECT m.id FROM merchants m
+            JOIN auth_sessions s ON m.id = s.merchant_id
+            WHERE s.access_token_hash = $1 AND s.revoked_at IS NULL
+            AND s.access_expires_at > NOW() AND s.team_role IS NOT NULL
+            AND m.active = true
+            UNION ALL
+            SELECT m.id FROM merchants m
+            JOIN oauth_tokens t ON m.id = t.merchant_id
+            JOIN oauth_clients c ON c.id = t.client_id
+            WHERE t.access_token_hash = $1 AND t.revoked_at IS NULL
+            AND t.access_expires_at > NOW() AND c.active = true
+            AND m.active = true
+            "#,
+            hash_api_key(api_key),
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| DefiantError::AuthenticationError("Invalid API key".into()))?;
+        
+        Ok(merchant_id)
+    }
+    
+    /// Resolves an active API key, a dashboard session's current access token with
+    /// the scopes of its team role, or an OAuth access token with the scopes the
+    /// merchant granted the app.
+    pub async fn authenticate_key(&self, api_key: &str) -> Result<AuthenticatedKey, DefiantError> {
+        let key = sqlx::query_as!(
+            AuthenticatedKey,
+            r#"
+            SELECT ak.id, m.id AS merchant_id, ak.permissions, ak.livemode,
+                NULL::team_role AS "team_role: TeamRole", ak.allowed_ips
+            FROM api_keys ak
+            JOIN merchants m ON m.id = ak.merchant_id
+            WHERE ak.key_hash = $1 AND ak.active = true
+            AND (ak.expires_at IS NULL OR ak.expires_at > NOW())
+            AND m.active = true
+            "#,
+            hash_api_key(api_key),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(key) = key {
+            return Ok(key);
+        }
+        if is_oauth_token(api_key) {
+            return self.authenticate_oauth_token(api_key).await;
+        }
+        
+        let session = sqlx::query!(
+            r#"
+            SELECT s.id, m.id AS merchant_id, s.livemode, s.team_role AS "team_role!: TeamRole", s.allowed_ips
+            FROM auth_sessions s
+            JOIN merchants m ON m.id = s.merchant_id
+            WHERE s.access_token_hash = $1 AND s.revoked_at IS NULL
+            AND s.access_expires_at > NOW() AND s.team_role IS NOT NULL
+            AND m.active = true
+            "#,
+            hash_api_key(api_key),
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| DefiantError::AuthenticationError("Invalid API key".into()))?;
+        
+        Ok(AuthenticatedKey {
+            id: session.id,
+            merchant_id: session.merchant_id,
+            permissions: role_scopes(session.team_role).map(|scopes| serde_json::json!({ "scopes": scopes })),
+            livemode: session.livemode,
+            team_role: Some(session.team_role),
+            allowed_ips: session.allowed_ips,
+        })
+    }
+    
+    async fn authenticate_oauth_token(&self, access_token: &str) -> Result<AuthenticatedKey, DefiantError> {
+        let grant = sqlx::query!(
+            r#"
+            SELECT t.id, m.id AS merchant_id, t.scopes, t.livemode
+            FROM oauth_tokens t
+            JOIN merchants m ON m.id = t.merchant_id
+            JOIN oauth_clients c ON c.id = t.client_id
+            WHERE t.access_token_hash = $1 AND t.revoked_at IS NULL
+            AND t.access_expires_at > NOW() AND c.active = true
+            AND m.active = true
+            "#,
+            hash_api_key(access_token),
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or_else(|| DefiantError::AuthenticationError("Invalid or expired access token".into()))?;
+        
+        Ok(AuthenticatedKey {
+            id: grant.id,
+            merchant_id: grant.merchant_id,
+            permissions: Some(serde_json::json!({ "scopes": grant.scopes })),
+            livemode: grant.livemode,
+            team_role: None,
+            allowed_ips: None,
+        })
+    }
+    
+    /// Fails unless the key holds `scope`. Handlers have their own scope checked by
+    /// `permission!`; services call this before side effects that reach another resource.
+    pub async fn require_scope(&self, api_key: &str, scope: &str) -> Result<(), DefiantError> {
+        let key = self.authenticate_key(api_key).await?;
+        if !key.grants(scope) {
+            return Err(DefiantError::AuthorizationError(format!("API key lacks the '{}' scope", scope)));
+        }
+        
+        Ok(())
+    }
+}
+
+/// Keeps replica health current, so reads move off a replica soon after it fails or
+/// falls behind and back once it recovers.
+fn pool_options(region: DataRegion, settings: PoolSettings) -> PgPoolOptions {
+    let statement_timeout_ms = settings.statement_timeout.as_millis();
+    PgPoolOptions::new()
+        .max_connections(settings.max_connections)
+        .min_connections(settings.min_connections)
+        .acquire_timeout(settings.acquire_timeout)
+        .connect_timeout(Duration::from_secs(10))
+        .idle_timeout(Duration::from_secs(300))
+        .max_lifetime(Duration::from_secs(3600))
+        // Lets the residency triggers know which cluster the connection belongs to
+        .after_connect(move |conn, _meta| Box::pin(async move {
+            conn.execute(format!("SET defiant.data_region = '{}'", region.as_str()).as_str()).await?;
+            conn.execute(format!("SET statement_timeout = {}", statement_timeout_ms).as_str()).await?;
+            Ok(())
+        }))
+}
+
+pub fn spawn_replica_monitor(db: Arc<Database>, max_lag_secs: u64, shutdown: &Shutdown) {
+    if db.replicas.is_empty() {
+        return;
+    }
+    
+    shutdown.spawn(|mut shutdown| async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(REPLICA_CHECK_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            db.check_replicas(Duration::from_secs(max_lag_secs)).await;
+        }
+    });
+}
+
+/// An active API key, or a dashboard session or OAuth grant standing in for one, and
+/// the scopes it may use. Resolved once per request by the auth middleware.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedKey {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub permissions: Option<serde_json::Value>,
+    /// Test keys only see and create test objects
+    pub livemode: bool,
+    /// Set when the caller is a dashboard session rather than an API key; `id` is
+    /// then the session's
+    pub team_role: Option<TeamRole>,
+    /// CIDR ranges the key may be used from, enforced by the auth middleware. Sessions
+    /// started with a key carry its ranges.
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+impl AuthenticatedKey {
+    pub fn grants(&self, scope: &str) -> bool {
+        grants(self.permissions.as_ref(), scope)
+    }
+}
+
+/// API keys are stored, and looked up, by their SHA-256 hash.
+pub fn hash_api_key(api_key: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, api_key.as_bytes()).as_ref())
 }
 
 // Connection pool extractor for Actix handlers
@@ -48,9 +523,6 @@ impl actix_web::FromRequest for Database {
     
     fn from_request(req: &actix_web::HttpRequest, _payload: &mut actix_web::dev::Payload) -> Self::Future {
         let data = req.app_data::<actix_web::web::Data<crate::AppState>>().unwrap();
-        let db = Database {
-            pool: data.db.pool.clone(),
-        };
-        std::future::ready(Ok(db))
+        std::future::ready(Ok(data.db.as_ref().clone()))
     }
 }
\ No newline at end of file
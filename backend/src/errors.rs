@@ -3,6 +3,8 @@ use serde_json::json;
 use std::fmt;
 use thiserror::Error;
 
+use crate::models::{ArchivedObject, DependencyConflict};
+
 #[derive(Error, Debug)]
 pub enum DefiantError {
     #[error("Database error: {0}")]
@@ -37,6 +39,32 @@ pub enum DefiantError {
     
     #[error("Conflict: {0}")]
     Conflict(String),
+    
+    #[error("Sold out: {0}")]
+    SoldOut(String),
+    
+    #[error("Dependency conflict: {} {} has dependent objects", .0.object, .0.id)]
+    DependencyConflict(Box<DependencyConflict>),
+    
+    #[error("Archived: {} {} was archived on {}", .0.object, .0.id, .0.archived_at)]
+    Archived(Box<ArchivedObject>),
+    
+    #[error("IP address not allowed: {0}")]
+    IpNotAllowed(String),
+    
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+    
+    #[error("Request timed out")]
+    RequestTimeout,
+    
+    #[error("Precondition failed: {0}")]
+    PreconditionFailed(String),
+    
+    /// An OAuth2 error code (RFC 6749 section 5.2) and its description, answered
+    /// in the shape OAuth clients expect
+    #[error("OAuth error: {0}: {1}")]
+    OAuthError(&'static str, String),
 }
 
 impl ResponseError for DefiantError {
@@ -96,6 +124,63 @@ impl ResponseError for DefiantError {
                     "code": "CONFLICT"
                 }))
             }
+            DefiantError::SoldOut(msg) => {
+                HttpResponse::Conflict().json(json!({
+                    "error": msg,
+                    "code": "SOLD_OUT"
+                }))
+            }
+            DefiantError::DependencyConflict(conflict) => {
+                HttpResponse::Conflict().json(json!({
+                    "error": format!("{} {} has dependent objects", conflict.object, conflict.id),
+                    "code": "DEPENDENCY_CONFLICT",
+                    "blockers": conflict.blockers,
+                    "forceable": conflict.forceable()
+                }))
+            }
+            DefiantError::Archived(archived) => {
+                HttpResponse::Gone().json(json!({
+                    "error": format!("{} {} was archived on {}", archived.object, archived.id, archived.archived_at.date_naive()),
+                    "code": "ARCHIVED",
+                    "archive_key": archived.archive_key,
+                    "retrieval": archived.retrieval_hint()
+                }))
+            }
+            DefiantError::IpNotAllowed(msg) => {
+                HttpResponse::Forbidden().json(json!({
+                    "error": msg,
+                    "code": "IP_NOT_ALLOWED"
+                }))
+            }
+            DefiantError::PayloadTooLarge(msg) => {
+                HttpResponse::PayloadTooLarge().json(json!({
+                    "error": msg,
+                    "code": "PAYLOAD_TOO_LARGE"
+                }))
+            }
+            DefiantError::RequestTimeout => {
+                HttpResponse::RequestTimeout().json(json!({
+                    "error": "Request took too long to process",
+                    "code": "REQUEST_TIMEOUT"
+                }))
+            }
+            DefiantError::PreconditionFailed(msg) => {
+                HttpResponse::PreconditionFailed().json(json!({
+                    "error": msg,
+                    "code": "PRECONDITION_FAILED"
+                }))
+            }
+            DefiantError::OAuthError(code, description) => {
+                let mut response = if *code == "invalid_client" {
+                    HttpResponse::Unauthorized()
+                } else {
+                    HttpResponse::BadRequest()
+                };
+                response.insert_header(("Cache-Control", "no-store")).json(json!({
+                    "error": code,
+                    "error_description": description
+                }))
+            }
             _ => HttpResponse::InternalServerError().json(json!({
                 "error": "Internal server error",
                 "code": "INTERNAL_ERROR"
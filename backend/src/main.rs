@@ -1,8 +1,8 @@
 use actix_cors::Cors;
-use actix_web::{web, App, HttpServer, middleware};
+use actix_web::{web, App, HttpResponse, HttpServer, middleware};
 use std::sync::Arc;
-use tracing::Level;
-use tracing_subscriber;
+use std::time::Duration;
+use tracing_actix_web::TracingLogger;
 
 mod api;
 mod models;
@@ -11,47 +11,164 @@ mod middleware as custom_middleware;
 mod config;
 mod db;
 mod errors;
+mod pii;
+mod plugins;
+mod scheduler;
+mod shutdown;
+mod telemetry;
 mod websocket;
+#[cfg(test)]
+mod test_support;
 
 use config::Config;
-use db::Database;
+use db::{Database, PoolSettings};
+use models::DataRegion;
+use plugins::PluginRegistry;
+use scheduler::Scheduler;
+use shutdown::Shutdown;
+use telemetry::RequestSpan;
+use custom_middleware::audit::AuditTrail;
 use custom_middleware::auth::Authentication;
+use custom_middleware::limits::{self, RequestLimiter};
+use custom_middleware::rate_limit::RateLimiter;
+use services::{archive_service::ArchiveService, crypto_payout_service, crypto_watcher, event_router::EventRouter, event_service, event_stream, maintenance_service::MaintenanceService, metadata_batch_service, payout_service::{self, PayoutService}, pii_service::PiiService, reserve_service, settlement_service::SettlementService, subscription_service::SubscriptionService, webhook_service::{self, SecretCipher}};
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt()
-        .with_max_level(Level::INFO)
-        .init();
-
-    // Load configuration
+    // Load configuration; logging is configured by it
     let config = Config::from_env().expect("Failed to load configuration");
+    telemetry::init(&config);
     
     // Initialize database
-    let db = Database::new(&config.database_url)
+    let mut db = Database::new(&config.database_url, PoolSettings::from_config(&config))
         .await
         .expect("Failed to connect to database");
     
+    if let Some(eu_database_url) = &config.eu_database_url {
+        db.connect_region(DataRegion::Eu, eu_database_url)
+            .await
+            .expect("Failed to connect to EU database");
+    }
+    for replica_url in &config.read_replica_urls {
+        db.connect_replica(DataRegion::Us, replica_url)
+            .expect("Invalid read replica URL");
+    }
+    for replica_url in &config.eu_read_replica_urls {
+        db.connect_replica(DataRegion::Eu, replica_url)
+            .expect("Invalid EU read replica URL");
+    }
+    
     // Run migrations
     db.run_migrations().await.expect("Failed to run migrations");
     
+    // Customer and bank account PII cannot be read or written without the keyring
+    pii::init(&db, &config).await.expect("Failed to load PII keys");
+    
     // Create Redis connection for WebSockets and rate limiting
-    let redis_client = redis::Client::open(config.redis_url.clone())
-        .expect("Failed to create Redis client");
+    let redis = Arc::new(redis::Client::open(config.redis_url.clone())
+        .expect("Failed to create Redis client"));
     
     // Create application state
     let app_state = web::Data::new(AppState {
         db: Arc::new(db),
         config: Arc::new(config.clone()),
-        redis: Arc::new(redis_client),
+        redis: redis.clone(),
+        events: EventRouter::start(redis),
     });
-
-    // Start WebSocket server
-    let ws_server = websocket::server::WebSocketServer::new(app_state.clone());
-    let ws_server = Arc::new(ws_server);
+    
+    // Plugins must be in place before anything can move money
+    let mut plugin_registry = PluginRegistry::new();
+    plugins::installed::register(&mut plugin_registry, &config);
+    plugins::install(plugin_registry);
+    
+    // Background workers stop taking new work once the HTTP server has drained
+    let shutdown = Shutdown::new();
+    
+    // Start outgoing webhook delivery workers
+    let webhook_cipher = SecretCipher::from_hex(&config.webhook_encryption_key)
+        .expect("Invalid webhook encryption key");
+    webhook_service::spawn_delivery_workers(
+        app_state.db.clone(),
+        app_state.config.clone(),
+        webhook_cipher,
+        config.webhook_workers,
+        &shutdown,
+    );
+    db::spawn_replica_monitor(app_state.db.clone(), config.replica_max_lag_secs, &shutdown);
+    pii::spawn_keyring_refresh(app_state.db.clone(), app_state.config.clone(), &shutdown);
+    payout_service::spawn_availability_watcher(app_state.db.clone(), app_state.config.clone(), &shutdown);
+    reserve_service::spawn_reserve_releaser(app_state.db.clone(), &shutdown);
+    event_service::spawn_replay_worker(app_state.db.clone(), &shutdown);
+    crypto_watcher::spawn_crypto_watcher(app_state.db.clone(), app_state.config.clone(), &shutdown);
+    crypto_payout_service::spawn_sweep_scheduler(app_state.db.clone(), app_state.config.clone(), &shutdown);
+    metadata_batch_service::spawn_batch_update_worker(app_state.db.clone(), &shutdown);
+    
+    // Recurring tasks run on one instance at a time, whichever claims each run first
+    let mut scheduler = Scheduler::new(app_state.redis.clone(), config.schedules.clone());
+    let db = app_state.db.clone();
+    scheduler.add("subscription_renewals", "0 * * * * *", Duration::from_secs(5 * 60), move || {
+        let service = SubscriptionService::new(db.clone());
+        async move { service.renew_due().await }
+    });
+    // Hourly, so merchants in every timezone are paid out on the scheduled date
+    let (db, payout_config) = (app_state.db.clone(), app_state.config.clone());
+    scheduler.add("payout_batching", "0 0 * * * *", Duration::from_secs(30 * 60), move || {
+        let (db, service) = (db.clone(), PayoutService::new(db.clone(), payout_config.clone()));
+        async move {
+            for pool in db.pools() {
+                service.run_scheduled_payouts(pool).await?;
+            }
+            Ok(())
+        }
+    });
+    // Generates the previous UTC day's batches; reruns through the day are no-ops
+    let db = app_state.db.clone();
+    scheduler.add("settlement_batches", "0 5 * * * *", Duration::from_secs(30 * 60), move || {
+        let service = SettlementService::new(db.clone());
+        async move { service.generate_batches(chrono::Utc::now().date_naive() - chrono::Duration::days(1)).await }
+    });
+    let db = app_state.db.clone();
+    scheduler.add("authorization_expiry", "0 */15 * * * *", Duration::from_secs(10 * 60), move || {
+        let service = MaintenanceService::new(db.clone());
+        async move { service.expire_authorizations().await }
+    });
+    let db = app_state.db.clone();
+    scheduler.add("data_purge", "0 30 3 * * *", Duration::from_secs(60 * 60), move || {
+        let service = MaintenanceService::new(db.clone());
+        async move { service.purge_expired().await }
+    });
+    // Daily, well ahead of each month boundary given the partitions created in advance
+    let (db, partitioning) = (app_state.db.clone(), config.partitioning.clone());
+    scheduler.add("partition_maintenance", "0 0 4 * * *", Duration::from_secs(60 * 60), move || {
+        let (service, settings) = (MaintenanceService::new(db.clone()), partitioning.clone());
+        async move { service.maintain_partitions(&settings).await }
+    });
+    if let Some(archival) = config.archival.clone() {
+        let db = app_state.db.clone();
+        scheduler.add("cold_archival", "0 0 1 * * *", Duration::from_secs(2 * 60 * 60), move || {
+            let (service, settings) = (ArchiveService::new(db.clone()), archival.clone());
+            async move { service.archive(&settings).await }
+        });
+    }
+    // Monthly, so no data key seals more than a month of writes
+    let (db, pii_config) = (app_state.db.clone(), app_state.config.clone());
+    scheduler.add("pii_key_rotation", "0 0 2 1 * *", Duration::from_secs(10 * 60), move || {
+        let (db, config) = (db.clone(), pii_config.clone());
+        async move { pii::rotate_data_key(&db, &config).await.map(|_| ()) }
+    });
+    let (db, pii_config) = (app_state.db.clone(), app_state.config.clone());
+    scheduler.add("pii_reencryption", "0 20 * * * *", Duration::from_secs(60 * 60), move || {
+        let service = PiiService::new(db.clone(), pii_config.clone());
+        async move { service.reencrypt().await }
+    });
+    scheduler.start(&shutdown);
     
     tracing::info!("Starting Defiant backend on {}:{}", config.host, config.port);
     
+    let db = app_state.db.clone();
+    
+    // Serves until SIGTERM or SIGINT, then stops accepting connections and gives
+    // in-flight requests the shutdown timeout to complete
     HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
@@ -61,11 +178,21 @@ async fn main() -> std::io::Result<()> {
         
         App::new()
             .app_data(app_state.clone())
+            // The limiter enforces each route's own cap; this only lifts the extractor's default
+            .app_data(web::JsonConfig::default()
+                .limit(app_state.config.request_limits.max_bulk_body_bytes)
+                .error_handler(limits::json_error_handler))
+            // Registered first so it sees handler responses before they are compressed
+            .wrap(AuditTrail)
             .wrap(cors)
-            .wrap(middleware::Logger::default())
             .wrap(middleware::Compress::default())
             .wrap(middleware::NormalizePath::trim())
             .wrap(Authentication)
+            .wrap(RequestLimiter)
+            // Registered after Authentication so it runs first, before any authentication work
+            .wrap(RateLimiter)
+            // Outermost, so everything else logs inside the request's span
+            .wrap(TracingLogger::<RequestSpan>::new())
             .configure(api::configure)
             .service(
                 web::scope("/ws")
@@ -76,21 +203,34 @@ async fn main() -> std::io::Result<()> {
     })
     .bind((config.host.clone(), config.port))?
     .workers(config.workers)
+    .shutdown_timeout(config.shutdown_timeout_secs)
     .run()
-    .await
+    .await?;
+    
+    // Webhook deliveries and events not yet sent stay queued in the database for the
+    // next process; only work already in hand is waited for
+    tracing::info!("HTTP server stopped; draining background workers");
+    shutdown.drain(Duration::from_secs(config.shutdown_timeout_secs)).await;
+    db.close().await;
+    
+    tracing::info!("Shutdown complete");
+    Ok(())
 }
 
 async fn health_check() -> &'static str {
     "🛡️ Defiant is running and ready for battle!"
 }
 
-async fn metrics() -> String {
-    // TODO: Implement Prometheus metrics
-    "metrics_endpoint".to_string()
+async fn metrics(state: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(state.db.pool_metrics() + &event_stream::connection_metrics() + &state.events.metrics())
 }
 
 pub struct AppState {
     pub db: Arc<Database>,
     pub config: Arc<Config>,
     pub redis: Arc<redis::Client>,
+    /// Live events for this instance's stream connections
+    pub events: Arc<EventRouter>,
 }
\ No newline at end of file
@@ -0,0 +1,147 @@
+use actix_web::{dev::{ServiceRequest, ServiceResponse}, web, Error, HttpMessage, HttpRequest};
+use actix_web::body::{self, BoxBody, MessageBody};
+use actix_web::dev::{forward_ready, Service, Transform};
+use actix_web::http::Method;
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::{models::{AuditActorType, NewAuditLog}, db::AuthenticatedKey, errors::DefiantError, services::{audit_service::AuditService, oauth_service::is_oauth_token}, AppState};
+use super::{auth::Claims, rate_limit::client_ip};
+
+/// Records every authenticated POST, PUT, PATCH and DELETE under `/api/v1` and
+/// `/api/admin` in the audit trail, with the caller, the client address and the
+/// response.
+///
+/// Must be the innermost middleware: it reads the actor the authentication middleware
+/// left in the request, and the response body before it is compressed.
+pub struct AuditTrail;
+
+impl<S, B> Transform<S, ServiceRequest> for AuditTrail
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Transform = AuditTrailMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuditTrailMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct AuditTrailMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for AuditTrailMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<BoxBody>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            let mutating = matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH | Method::DELETE);
+            let audited = req.path().starts_with("/api/v1/") || req.path().starts_with("/api/admin/");
+            if !mutating || !audited {
+                return Ok(service.call(req).await?.map_into_boxed_body());
+            }
+
+            let res = service.call(req).await?;
+            let Some((merchant_id, actor_type, actor_id)) = actor(res.request()) else {
+                return Ok(res.map_into_boxed_body());
+            };
+
+            let (http_req, res) = res.into_parts();
+            let (res, body) = res.into_parts();
+            let bytes = body::to_bytes(body).await.map_err(|_| DefiantError::InternalError)?;
+            let status = res.status();
+            let response: Option<serde_json::Value> = status.is_success()
+                .then(|| serde_json::from_slice(&bytes).ok())
+                .flatten();
+
+            // Admin changes to a merchant's account belong in that merchant's trail
+            let merchant_id = merchant_id.or_else(|| {
+                http_req.match_info().get("merchant_id").and_then(|id| Uuid::parse_str(id).ok())
+            });
+            let route = http_req.match_pattern().unwrap_or_else(|| http_req.path().to_owned());
+            // Creations name their resource in the response rather than the path
+            let resource_id = http_req.match_info().iter().next().map(|(_, id)| id.to_owned())
+                .or_else(|| response.as_ref()?.get("id")?.as_str().map(str::to_owned));
+
+            if let Some(state) = http_req.app_data::<web::Data<AppState>>() {
+                let entry = NewAuditLog {
+                    merchant_id,
+                    actor_type,
+                    actor_id,
+                    method: http_req.method().to_string(),
+                    resource_type: resource_type(&route),
+                    route,
+                    path: http_req.path().to_owned(),
+                    resource_id,
+                    status_code: status.as_u16() as i16,
+                    ip_address: client_ip(&http_req, state.config.trust_proxy_headers).map(|ip| ip.to_string()),
+                    after: response,
+                };
+                AuditService::new(state.db.clone()).record(entry).await;
+            }
+
+            let res = res.set_body(bytes).map_into_boxed_body();
+            Ok(ServiceResponse::new(http_req, res))
+        })
+    }
+}
+
+/// Who made the call, as the authentication middleware resolved it. Calls without a
+/// caller (hosted checkout, inbound webhooks) are not audited.
+fn actor(req: &HttpRequest) -> Option<(Option<Uuid>, AuditActorType, String)> {
+    if let Some(key) = req.extensions().get::<AuthenticatedKey>() {
+        let bearer = req.headers().get("Authorization")
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        let actor_type = if is_oauth_token(bearer) {
+            AuditActorType::OauthApp
+        } else if key.team_role.is_some() {
+            AuditActorType::Session
+        } else {
+            AuditActorType::ApiKey
+        };
+        return Some((Some(key.merchant_id), actor_type, key.id.to_string()));
+    }
+
+    let extensions = req.extensions();
+    let claims = extensions.get::<Claims>()?;
+    let merchant_id = claims.merchant_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+    let actor_type = if claims.role == "admin" {
+        AuditActorType::Admin
+    } else {
+        AuditActorType::Session
+    };
+    Some((merchant_id, actor_type, claims.sub.clone()))
+}
+
+/// The collection a route acts on: its last literal segment before the first
+/// parameter, so `/api/v1/payments/{payment_id}/capture` is `payments`.
+fn resource_type(route: &str) -> String {
+    let literals = route
+        .trim_start_matches("/api/v1/")
+        .trim_start_matches("/api/admin/")
+        .split('/')
+        .take_while(|segment| !segment.starts_with('{'));
+    literals.last().unwrap_or_default().to_owned()
+}
@@ -1,19 +1,26 @@
-use actix_web::{dev::ServiceRequest, error::ErrorUnauthorized, Error, HttpMessage};
+use actix_web::{dev::ServiceRequest, error::{ErrorInternalServerError, ErrorUnauthorized}, web, Error, HttpMessage};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
 use actix_web_httpauth::extractors::AuthenticationError;
 use jsonwebtoken::{decode, Validation, Algorithm, DecodingKey};
 use serde::{Deserialize, Serialize};
 use std::future::{ready, Ready};
 use std::pin::Pin;
+use std::rc::Rc;
 use actix_web::dev::{forward_ready, Service, Transform};
 use futures_util::future::LocalBoxFuture;
 
+use crate::{api::v1::permissions::touch_last_used, telemetry, services::{account_service::{is_api_key, is_secret_key}, auth_service::AuthService, oauth_service::is_oauth_token, security_service::{SecurityService, SignedRequest}}, AppState};
+use super::rate_limit::client_ip;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID
     pub exp: usize, // Expiration time
     pub role: String,
     pub merchant_id: Option<String>,
+    /// Session the token was issued for; absent on tokens minted outside `/api/auth`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
 }
 
 pub struct Authentication;
@@ -31,12 +38,12 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthenticationMiddleware { service }))
+        ready(Ok(AuthenticationMiddleware { service: Rc::new(service) }))
     }
 }
 
 pub struct AuthenticationMiddleware<S> {
-    service: S,
+    service: Rc<S>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthenticationMiddleware<S>
@@ -52,15 +59,6 @@ where
     forward_ready!(service);
 
     fn call(&self, mut req: ServiceRequest) -> Self::Future {
-        // Skip auth for certain paths
-        let path = req.path();
-        if path.starts_with("/health") 
-            || path.starts_with("/api/v1/webhooks")
-            || path == "/metrics" {
-            let fut = self.service.call(req);
-            return Box::pin(async move { fut.await });
-        }
-
         // Extract token
         let token = req.headers()
             .get("Authorization")
@@ -72,22 +70,101 @@ where
                     .split('&')
                     .find(|param| param.starts_with("token="))
                     .and_then(|param| param.split('=').nth(1))
+            })
+            .map(str::to_owned);
+
+        // Skip auth for certain paths
+        let path = req.path();
+        if path.starts_with("/health") 
+            || path.starts_with("/api/v1/webhooks")
+            || path.starts_with("/api/v1/hosted")
+            || path.starts_with("/api/v1/event_types")
+            || path.starts_with("/api/v1/crypto/fee_estimates")
+            || path == "/api/auth/token"
+            || path == "/api/auth/refresh"
+            || path == "/api/auth/invitations/accept"
+            || path == "/api/auth/password/forgot"
+            || path == "/api/auth/password/reset"
+            || path == "/api/auth/email/verify"
+            || path == "/api/oauth/token"
+            || path == "/api/oauth/introspect"
+            || path == "/api/oauth/revoke"
+            || (path.starts_with("/api/v1/payments/") && (path.ends_with("/status") || path.ends_with("/refund_address")))
+            || path == "/metrics" {
+            // The handler authenticates these itself, and can only check a secret
+            // key's request signature against the body as sent
+            if !token.as_deref().is_some_and(is_secret_key) {
+                let fut = self.service.call(req);
+                return Box::pin(async move { fut.await });
+            }
+            let service = self.service.clone();
+            return Box::pin(async move {
+                capture_signed_request(&mut req).await?;
+                service.call(req).await
             });
+        }
 
         match token {
+            // API keys and OAuth access tokens are resolved here once; `permission!`
+            // then checks their scopes against the scope each handler declares
+            Some(token) if is_api_key(&token) || is_oauth_token(&token) => {
+                let service = self.service.clone();
+                Box::pin(async move {
+                    let state = req.app_data::<web::Data<AppState>>()
+                        .cloned()
+                        .ok_or_else(|| ErrorInternalServerError("Application state missing"))?;
+                    let key = state.db.authenticate_key(&token).await?;
+                    let client_ip = client_ip(req.request(), state.config.trust_proxy_headers);
+                    let security_service = SecurityService::new(state.db.clone(), state.config.clone());
+                    security_service
+                        .enforce_ip_allowlist(&key, client_ip, req.method().as_str(), req.path())
+                        .await?;
+
+                    // Merchants with request signing on must also sign the body, so it is
+                    // read here and put back for the handler
+                    let signed = if is_secret_key(&token) {
+                        Some(capture_signed_request(&mut req).await?)
+                    } else {
+                        None
+                    };
+                    security_service
+                        .enforce_request_signing(&state.redis, &token, key.merchant_id, signed.as_ref())
+                        .await?;
+                    if is_api_key(&token) {
+                        touch_last_used(state.db.clone(), key.id);
+                    }
+                    telemetry::record_merchant(&req, key.merchant_id);
+                    req.extensions_mut().insert(key);
+                    service.call(req).await
+                })
+            }
             Some(token) => {
-                // Validate token
-                match validate_token(token) {
-                    Ok(claims) => {
-                        // Insert claims into request extensions
-                        req.extensions_mut().insert(claims);
-                        let fut = self.service.call(req);
-                        Box::pin(async move { fut.await })
+                let service = self.service.clone();
+                Box::pin(async move {
+                    let state = req.app_data::<web::Data<AppState>>()
+                        .cloned()
+                        .ok_or_else(|| ErrorInternalServerError("Application state missing"))?;
+
+                    // Validate token
+                    let claims = validate_token(&token, &state.config.jwt_secret)
+                        .map_err(|_| ErrorUnauthorized("Invalid token"))?;
+
+                    // Access tokens of a revoked session stop working before they expire
+                    if let Some(sid) = &claims.sid {
+                        let auth_service = AuthService::new(state.db.clone(), state.config.clone());
+                        if !auth_service.session_active(sid).await? {
+                            return Err(ErrorUnauthorized("Session has been revoked"));
+                        }
                     }
-                    Err(_) => Box::pin(async move {
-                        Err(ErrorUnauthorized("Invalid token"))
-                    }),
-                }
+
+                    if let Some(merchant_id) = claims.merchant_id.as_deref().and_then(|id| id.parse().ok()) {
+                        telemetry::record_merchant(&req, merchant_id);
+                    }
+
+                    // Insert claims into request extensions
+                    req.extensions_mut().insert(claims);
+                    service.call(req).await
+                })
             }
             None => Box::pin(async move {
                 Err(ErrorUnauthorized("Missing authentication token"))
@@ -96,10 +173,19 @@ where
     }
 }
 
-fn validate_token(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
-    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "secret".to_string());
+/// Reads the body for a signature check and puts it back for the handler, leaving
+/// what was signed on the request.
+async fn capture_signed_request(req: &mut ServiceRequest) -> Result<SignedRequest, Error> {
+    let body = req.extract::<web::Bytes>().await?;
+    req.set_payload(body.clone().into());
+    let signed = SignedRequest::capture(req.request(), body);
+    req.extensions_mut().insert(signed.clone());
+    Ok(signed)
+}
+
+fn validate_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     let decoding_key = DecodingKey::from_secret(secret.as_bytes());
-    
+
     decode::<Claims>(
         token,
         &decoding_key,
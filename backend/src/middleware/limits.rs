@@ -0,0 +1,147 @@
+use actix_web::{dev::ServiceRequest, web, Error, HttpRequest};
+use actix_web::dev::{forward_ready, Payload, Service, Transform};
+use actix_web::error::{JsonPayloadError, PayloadError};
+use actix_web::http::{header::CONTENT_LENGTH, Method};
+use futures_util::future::LocalBoxFuture;
+use futures_util::StreamExt;
+use std::future::{ready, Ready};
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::{config::RequestLimits, errors::DefiantError, AppState};
+
+/// How long a route may take and how much it may upload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RouteClass {
+    Read,
+    Write,
+    /// Batch updates and report exports, which legitimately move a lot of data
+    Bulk,
+}
+
+impl RouteClass {
+    fn of(req: &ServiceRequest) -> Self {
+        let path = req.path();
+        if path.contains(":batch_update") || path.starts_with("/api/v1/reports/") {
+            RouteClass::Bulk
+        } else if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+            RouteClass::Read
+        } else {
+            RouteClass::Write
+        }
+    }
+
+    fn max_body_bytes(self, limits: &RequestLimits) -> usize {
+        match self {
+            RouteClass::Bulk => limits.max_bulk_body_bytes,
+            _ => limits.max_body_bytes,
+        }
+    }
+
+    fn timeout(self, limits: &RequestLimits) -> Duration {
+        Duration::from_secs(match self {
+            RouteClass::Read => limits.read_timeout_secs,
+            RouteClass::Write => limits.write_timeout_secs,
+            RouteClass::Bulk => limits.bulk_timeout_secs,
+        })
+    }
+}
+
+/// Caps request bodies and handler time per route class, so slow or oversized
+/// uploads cannot tie up workers.
+///
+/// Bodies that declare a `Content-Length` over the limit are refused with 413 before
+/// they are read; chunked bodies are cut off once they pass it. Handlers still running
+/// at the route's deadline are dropped and answered with 408, rolling back any open
+/// transaction.
+pub struct RequestLimiter;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestLimiter
+where
+    S: Service<ServiceRequest, Response = actix_web::dev::ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RequestLimiterMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RequestLimiterMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = actix_web::dev::ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            if !req.path().starts_with("/api/") {
+                return service.call(req).await;
+            }
+
+            let state = req.app_data::<web::Data<AppState>>().ok_or(DefiantError::InternalError)?;
+            let class = RouteClass::of(&req);
+            let max_body_bytes = class.max_body_bytes(&state.config.request_limits);
+            let timeout = class.timeout(&state.config.request_limits);
+
+            let declared = req.headers().get(CONTENT_LENGTH)
+                .and_then(|h| h.to_str().ok())
+                .and_then(|len| len.parse::<usize>().ok());
+            if declared.is_some_and(|len| len > max_body_bytes) {
+                return Err(too_large(max_body_bytes).into());
+            }
+
+            let mut received = 0;
+            let limited = req.take_payload().map(move |chunk| {
+                let chunk = chunk?;
+                received += chunk.len();
+                if received > max_body_bytes {
+                    return Err(PayloadError::Overflow);
+                }
+                Ok(chunk)
+            });
+            req.set_payload(Payload::Stream { payload: Box::pin(limited) });
+
+            match tokio::time::timeout(timeout, service.call(req)).await {
+                Ok(res) => res,
+                Err(_) => Err(DefiantError::RequestTimeout.into()),
+            }
+        })
+    }
+}
+
+/// Answers JSON bodies cut off by the limiter, or over the extractor's own limit,
+/// with the structured 413 instead of actix's plain-text one.
+pub fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> Error {
+    match err {
+        JsonPayloadError::Overflow { limit } | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            too_large(limit).into()
+        }
+        JsonPayloadError::Payload(PayloadError::Overflow) => {
+            DefiantError::PayloadTooLarge("Request body is too large".into()).into()
+        }
+        err => err.into(),
+    }
+}
+
+fn too_large(limit: usize) -> DefiantError {
+    DefiantError::PayloadTooLarge(format!("Request body must not exceed {} bytes", limit))
+}
@@ -0,0 +1,4 @@
+pub mod audit;
+pub mod auth;
+pub mod limits;
+pub mod rate_limit;
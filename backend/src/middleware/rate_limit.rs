@@ -0,0 +1,201 @@
+use actix_web::{dev::ServiceRequest, web, Error, HttpRequest, ResponseError};
+use actix_web::dev::{forward_ready, Service, Transform};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue, RETRY_AFTER};
+use futures_util::future::LocalBoxFuture;
+use std::future::{ready, Ready};
+use std::net::IpAddr;
+use std::rc::Rc;
+use std::sync::OnceLock;
+
+use crate::{errors::DefiantError, AppState, api::v1::permissions::bearer_token, services::security_service::{SecurityService, ServiceSignature, SERVICE_IDENTITY_HEADER, SERVICE_SIGNATURE_HEADER}};
+
+/// Takes one token from the bucket in `KEYS[1]` holding at most `ARGV[1]` tokens and
+/// refilling completely every `ARGV[2]` seconds, in one step so concurrent requests
+/// cannot both spend the last token. Uses the Redis clock so every worker agrees.
+/// Returns whether the request is allowed, the tokens left, and the milliseconds until
+/// the next token and until the bucket is full.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = capacity / (tonumber(ARGV[2]) * 1000)
+local time = redis.call('TIME')
+local now = tonumber(time[1]) * 1000 + math.floor(tonumber(time[2]) / 1000)
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'updated_at')
+local tokens = tonumber(bucket[1]) or capacity
+local updated_at = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - updated_at) * rate)
+local allowed = 0
+if tokens >= 1 then
+    tokens = tokens - 1
+    allowed = 1
+end
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'updated_at', now)
+local full_in = math.ceil((capacity - tokens) / rate)
+redis.call('PEXPIRE', KEYS[1], full_in + 1000)
+local next_in = 0
+if allowed == 0 then
+    next_in = math.ceil((1 - tokens) / rate)
+end
+return {allowed, math.floor(tokens), next_in, full_in}
+"#;
+
+/// Hashed once; calls run it by SHA and only send the source when Redis lacks it.
+static TOKEN_BUCKET: OnceLock<redis::Script> = OnceLock::new();
+
+/// Token-bucket request limits for the API, kept in Redis so every worker shares them.
+///
+/// A request with a valid bearer token draws on the bucket of its API key (or session,
+/// or app grant), sized by the merchant's rate-limit tier. Traffic from one of the
+/// merchant's trusted sources draws on a single per-merchant bucket at the trusted quota
+/// instead, and everything else on a bucket per client IP at `rate_limit_requests`.
+///
+/// Responses carry `X-RateLimit-Limit`, `X-RateLimit-Remaining` and `X-RateLimit-Reset`
+/// (seconds until the bucket is full again); refused ones also carry `Retry-After`.
+pub struct RateLimiter;
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimiter
+where
+    S: Service<ServiceRequest, Response = actix_web::dev::ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimiterMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimiterMiddleware { service: Rc::new(service) }))
+    }
+}
+
+pub struct RateLimiterMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimiterMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = actix_web::dev::ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = actix_web::dev::ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            if !req.path().starts_with("/api/") {
+                return service.call(req).await;
+            }
+
+            let decision = enforce(&req).await?;
+            if !decision.allowed {
+                let mut response = DefiantError::RateLimitError.error_response();
+                decision.write_headers(response.headers_mut());
+                return Err(InternalError::from_response(DefiantError::RateLimitError, response).into());
+            }
+
+            let mut res = service.call(req).await?;
+            decision.write_headers(res.headers_mut());
+            Ok(res)
+        })
+    }
+}
+
+/// The outcome of taking a token, reported back in the response headers.
+struct Decision {
+    allowed: bool,
+    limit: u32,
+    remaining: i64,
+    retry_after_ms: i64,
+    reset_ms: i64,
+}
+
+impl Decision {
+    fn write_headers(&self, headers: &mut HeaderMap) {
+        headers.insert(HeaderName::from_static("x-ratelimit-limit"), HeaderValue::from(self.limit));
+        headers.insert(HeaderName::from_static("x-ratelimit-remaining"), HeaderValue::from(self.remaining));
+        headers.insert(HeaderName::from_static("x-ratelimit-reset"), HeaderValue::from(ceil_secs(self.reset_ms)));
+        if !self.allowed {
+            headers.insert(RETRY_AFTER, HeaderValue::from(ceil_secs(self.retry_after_ms).max(1)));
+        }
+    }
+}
+
+async fn enforce(req: &ServiceRequest) -> Result<Decision, DefiantError> {
+    let state = req.app_data::<web::Data<AppState>>().ok_or(DefiantError::InternalError)?;
+    let config = &state.config;
+    let client_ip = client_ip(req.request(), config.trust_proxy_headers);
+    let security_service = SecurityService::new(state.db.clone(), config.clone());
+
+    let signature = match (header(req, SERVICE_IDENTITY_HEADER), header(req, SERVICE_SIGNATURE_HEADER)) {
+        (Some(identity), Some(signature)) => Some(ServiceSignature::parse(identity, signature)?),
+        _ => None,
+    };
+
+    let (holder, trusted) = match bearer_token(req.request()) {
+        Ok(token) => {
+            let holder = security_service.rate_limit_holder(&state.redis, token).await?;
+            let path_and_query = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or_else(|| req.path());
+            let trusted = security_service
+                .trusted_merchant(token, client_ip, signature.as_ref(), req.method().as_str(), path_and_query)
+                .await?;
+            (holder, trusted)
+        }
+        Err(_) => (None, None),
+    };
+
+    let (bucket, limit) = match (holder, trusted) {
+        (Some((_, policy)), Some(merchant_id)) => (format!("rate_limit:trusted:{}", merchant_id), policy.trusted_requests_per_period),
+        (Some((key_id, policy)), None) => (format!("rate_limit:key:{}", key_id), policy.requests_per_period),
+        (None, _) => (
+            format!("rate_limit:ip:{}", client_ip.map(|ip| ip.to_string()).unwrap_or_else(|| "unknown".into())),
+            config.rate_limit_requests,
+        ),
+    };
+    let limit = limit.max(1);
+
+    let mut conn = state.redis.get_async_connection().await
+        .map_err(|_| DefiantError::InternalError)?;
+    let (allowed, remaining, retry_after_ms, reset_ms): (i64, i64, i64, i64) = TOKEN_BUCKET
+        .get_or_init(|| redis::Script::new(TOKEN_BUCKET_SCRIPT))
+        .key(&bucket)
+        .arg(limit)
+        .arg(config.rate_limit_period.max(1))
+        .invoke_async(&mut conn)
+        .await
+        .map_err(|_| DefiantError::InternalError)?;
+
+    Ok(Decision {
+        allowed: allowed == 1,
+        limit,
+        remaining,
+        retry_after_ms,
+        reset_ms,
+    })
+}
+
+fn ceil_secs(ms: i64) -> i64 {
+    (ms + 999) / 1000
+}
+
+/// The address a request came from, as the rate limiter counts it.
+pub(crate) fn client_ip(req: &HttpRequest, trust_proxy_headers: bool) -> Option<IpAddr> {
+    if trust_proxy_headers {
+        let real_ip = req.headers().get("X-Real-IP").and_then(|h| h.to_str().ok());
+        if let Some(ip) = real_ip.and_then(|ip| ip.trim().parse().ok()) {
+            return Some(ip);
+        }
+    }
+    req.peer_addr().map(|addr| addr.ip())
+}
+
+fn header<'a>(req: &'a ServiceRequest, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|h| h.to_str().ok())
+}
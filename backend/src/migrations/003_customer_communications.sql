@@ -0,0 +1,48 @@
+-- Communication enums
+CREATE TYPE communication_channel AS ENUM (
+    'email',
+    'sms'
+);
+
+CREATE TYPE communication_kind AS ENUM (
+    'receipt',
+    'invoice',
+    'dunning',
+    'payment_failed',
+    'subscription_notice',
+    'other'
+);
+
+CREATE TYPE communication_status AS ENUM (
+    'queued',
+    'sent',
+    'delivered',
+    'bounced',
+    'failed'
+);
+
+-- Every message the platform sends to a customer
+CREATE TABLE customer_communications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id) ON DELETE CASCADE,
+    channel communication_channel NOT NULL,
+    kind communication_kind NOT NULL,
+    recipient VARCHAR(255) NOT NULL,
+    subject TEXT,
+    related_object_type VARCHAR(50),
+    related_object_id UUID,
+    status communication_status NOT NULL DEFAULT 'queued',
+    provider_message_id VARCHAR(255),
+    error_message TEXT,
+    sent_at TIMESTAMP WITH TIME ZONE,
+    delivered_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_customer_communications_customer_id ON customer_communications(customer_id, created_at DESC);
+CREATE INDEX idx_customer_communications_provider_message_id ON customer_communications(provider_message_id);
+
+CREATE TRIGGER update_customer_communications_updated_at BEFORE UPDATE ON customer_communications
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,33 @@
+-- Daily exchange rates used for conversions
+CREATE TABLE exchange_rates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    base_currency VARCHAR(3) NOT NULL,
+    quote_currency VARCHAR(3) NOT NULL,
+    rate NUMERIC(24, 12) NOT NULL CHECK (rate > 0),
+    rate_date DATE NOT NULL,
+    source VARCHAR(50) NOT NULL DEFAULT 'manual',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE(base_currency, quote_currency, rate_date)
+);
+
+CREATE INDEX idx_exchange_rates_pair_date ON exchange_rates(base_currency, quote_currency, rate_date DESC);
+
+-- Conversion snapshot taken when the invoice was created
+ALTER TABLE invoices
+ADD COLUMN source_currency VARCHAR(3),
+ADD COLUMN exchange_rate NUMERIC(24, 12),
+ADD COLUMN exchange_rate_date DATE;
+
+CREATE TABLE invoice_line_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    invoice_id UUID REFERENCES invoices(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    quantity INTEGER NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    unit_amount BIGINT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    amount BIGINT NOT NULL,
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_invoice_line_items_invoice_id ON invoice_line_items(invoice_id);
@@ -0,0 +1,59 @@
+CREATE TYPE checkout_session_status AS ENUM (
+    'open',
+    'complete',
+    'expired'
+);
+
+-- Reusable hosted checkout links
+CREATE TABLE payment_links (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    field_collection JSONB NOT NULL DEFAULT '{}',
+    active BOOLEAN DEFAULT true,
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE checkout_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_link_id UUID REFERENCES payment_links(id) ON DELETE SET NULL,
+    customer_id UUID REFERENCES customers(id) ON DELETE SET NULL,
+    payment_id UUID REFERENCES payments(id) ON DELETE SET NULL,
+    status checkout_session_status NOT NULL DEFAULT 'open',
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    field_collection JSONB NOT NULL DEFAULT '{}',
+    collected_fields JSONB,
+    success_url TEXT,
+    cancel_url TEXT,
+    metadata JSONB DEFAULT '{}',
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Values collected by hosted checkout
+ALTER TABLE payments
+ADD COLUMN checkout_session_id UUID,
+ADD COLUMN collected_fields JSONB;
+
+ALTER TABLE customers
+ADD COLUMN shipping_address JSONB,
+ADD COLUMN consents JSONB DEFAULT '{}';
+
+CREATE INDEX idx_payment_links_merchant_id ON payment_links(merchant_id);
+CREATE INDEX idx_checkout_sessions_merchant_id ON checkout_sessions(merchant_id);
+CREATE INDEX idx_checkout_sessions_payment_link_id ON checkout_sessions(payment_link_id);
+
+CREATE TRIGGER update_payment_links_updated_at BEFORE UPDATE ON payment_links
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_checkout_sessions_updated_at BEFORE UPDATE ON checkout_sessions
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,13 @@
+-- Merchant-defined shipping options
+ALTER TABLE payment_links
+ADD COLUMN shipping_options JSONB NOT NULL DEFAULT '[]';
+
+ALTER TABLE checkout_sessions
+ADD COLUMN shipping_options JSONB NOT NULL DEFAULT '[]',
+ADD COLUMN shipping_option JSONB,
+ADD COLUMN shipping_amount BIGINT NOT NULL DEFAULT 0;
+
+-- Shipping is a component of the payment amount, kept separately for receipts and exports
+ALTER TABLE payments
+ADD COLUMN shipping_amount BIGINT NOT NULL DEFAULT 0 CHECK (shipping_amount >= 0),
+ADD COLUMN shipping JSONB;
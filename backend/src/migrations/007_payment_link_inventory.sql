@@ -0,0 +1,4 @@
+-- Optional stock counter for limited-run payment links; NULL means unlimited
+ALTER TABLE payment_links
+ADD COLUMN inventory_total INTEGER CHECK (inventory_total >= 0),
+ADD COLUMN inventory_remaining INTEGER CHECK (inventory_remaining >= 0);
@@ -0,0 +1,20 @@
+-- Customer credit consumed by an invoice; returned to the customer if the invoice is voided.
+-- A positive customers.balance is credit owed to the customer.
+ALTER TABLE invoices
+ADD COLUMN customer_balance_applied BIGINT NOT NULL DEFAULT 0,
+ADD COLUMN marked_uncollectible_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE customer_balance_transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id) ON DELETE CASCADE,
+    invoice_id UUID REFERENCES invoices(id) ON DELETE SET NULL,
+    type VARCHAR(50) NOT NULL CHECK (type IN ('applied_to_invoice', 'invoice_voided', 'adjustment', 'overpayment_credit')),
+    amount BIGINT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    ending_balance BIGINT NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_customer_balance_transactions_customer_id ON customer_balance_transactions(customer_id, created_at DESC);
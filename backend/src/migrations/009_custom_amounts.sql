@@ -0,0 +1,13 @@
+-- Customer-chosen (pay-what-you-want) amounts and donation flagging
+ALTER TABLE payment_links
+ADD COLUMN custom_amount JSONB,
+ADD COLUMN is_donation BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE checkout_sessions
+ADD COLUMN custom_amount JSONB,
+ADD COLUMN is_donation BOOLEAN NOT NULL DEFAULT false;
+
+ALTER TABLE payments
+ADD COLUMN is_donation BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX idx_payments_donations ON payments(merchant_id, created_at) WHERE is_donation;
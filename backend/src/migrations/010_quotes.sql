@@ -0,0 +1,62 @@
+CREATE TYPE quote_status AS ENUM (
+    'draft',
+    'open',
+    'accepted',
+    'invoiced',
+    'canceled'
+);
+
+CREATE TABLE quotes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id) ON DELETE CASCADE,
+    status quote_status NOT NULL DEFAULT 'draft',
+    number VARCHAR(50) UNIQUE,
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    amount_total BIGINT NOT NULL DEFAULT 0,
+    description TEXT,
+    plan_id UUID REFERENCES plans(id),
+    acceptance_token VARCHAR(64),
+    expires_at TIMESTAMP WITH TIME ZONE,
+    finalized_at TIMESTAMP WITH TIME ZONE,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    canceled_at TIMESTAMP WITH TIME ZONE,
+    invoice_id UUID REFERENCES invoices(id) ON DELETE SET NULL,
+    subscription_id UUID REFERENCES subscriptions(id) ON DELETE SET NULL,
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE quote_line_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    quote_id UUID REFERENCES quotes(id) ON DELETE CASCADE,
+    description TEXT NOT NULL,
+    quantity INTEGER NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    unit_amount BIGINT NOT NULL,
+    amount BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_quotes_merchant_id ON quotes(merchant_id);
+CREATE INDEX idx_quotes_customer_id ON quotes(customer_id);
+CREATE INDEX idx_quote_line_items_quote_id ON quote_line_items(quote_id);
+
+CREATE TRIGGER update_quotes_updated_at BEFORE UPDATE ON quotes
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE SEQUENCE quote_number_seq START 1;
+
+CREATE OR REPLACE FUNCTION generate_quote_number()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.number IS NULL THEN
+        NEW.number = 'QT-' || TO_CHAR(NOW(), 'YYYYMMDD') || '-' ||
+                     LPAD(NEXTVAL('quote_number_seq')::TEXT, 6, '0');
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER set_quote_number BEFORE INSERT ON quotes
+    FOR EACH ROW EXECUTE FUNCTION generate_quote_number();
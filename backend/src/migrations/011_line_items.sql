@@ -0,0 +1,24 @@
+CREATE TYPE tax_behavior AS ENUM (
+    'inclusive',
+    'exclusive',
+    'unspecified'
+);
+
+-- Cart contents offered on a checkout session
+ALTER TABLE checkout_sessions
+ADD COLUMN line_items JSONB NOT NULL DEFAULT '[]';
+
+-- What a payment was for
+CREATE TABLE payment_line_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    payment_id UUID REFERENCES payments(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    quantity INTEGER NOT NULL DEFAULT 1 CHECK (quantity > 0),
+    unit_amount BIGINT NOT NULL CHECK (unit_amount >= 0),
+    amount BIGINT NOT NULL,
+    tax_behavior tax_behavior NOT NULL DEFAULT 'unspecified',
+    metadata JSONB DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_payment_line_items_payment_id ON payment_line_items(payment_id);
@@ -0,0 +1,11 @@
+-- Deliveries that exhaust their retry window stop here
+ALTER TYPE webhook_delivery_status ADD VALUE 'dead_lettered';
+
+ALTER TABLE webhooks ADD COLUMN consecutive_failures INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE webhooks ADD COLUMN failing_since TIMESTAMP WITH TIME ZONE;
+ALTER TABLE webhooks ADD COLUMN disabled_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE webhooks ADD COLUMN disabled_reason TEXT;
+
+DROP INDEX idx_webhook_deliveries_due;
+CREATE INDEX idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at)
+    WHERE status IN ('pending', 'in_progress', 'failed');
@@ -0,0 +1,59 @@
+CREATE TYPE payout_status AS ENUM (
+    'pending',
+    'in_transit',
+    'paid',
+    'failed',
+    'canceled'
+);
+
+CREATE TYPE topup_status AS ENUM (
+    'pending',
+    'succeeded',
+    'failed',
+    'canceled'
+);
+
+CREATE TABLE payouts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    status payout_status NOT NULL DEFAULT 'pending',
+    description TEXT,
+    arrival_date TIMESTAMP WITH TIME ZONE,
+    failure_code VARCHAR(50),
+    failure_message TEXT,
+    metadata JSONB DEFAULT '{}',
+    paid_at TIMESTAMP WITH TIME ZONE,
+    failed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE topups (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    status topup_status NOT NULL DEFAULT 'pending',
+    description TEXT,
+    metadata JSONB DEFAULT '{}',
+    succeeded_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Set once a `balance.available` event has covered the transaction
+ALTER TABLE balance_transactions ADD COLUMN availability_announced BOOLEAN NOT NULL DEFAULT false;
+
+CREATE INDEX idx_payouts_merchant_id ON payouts(merchant_id);
+CREATE INDEX idx_payouts_status ON payouts(status);
+CREATE INDEX idx_topups_merchant_id ON topups(merchant_id);
+CREATE INDEX idx_balance_transactions_unannounced ON balance_transactions(available_on)
+    WHERE availability_announced = false;
+
+CREATE TRIGGER update_payouts_updated_at BEFORE UPDATE ON payouts
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_topups_updated_at BEFORE UPDATE ON topups
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,42 @@
+-- Each merchant's data lives in one region. The merchant directory (merchants, api_keys)
+-- is mirrored to every regional cluster so foreign keys hold; everything else the
+-- merchant owns is written only to its home cluster.
+CREATE TYPE data_region AS ENUM (
+    'us',
+    'eu'
+);
+
+ALTER TABLE merchants ADD COLUMN data_region data_region NOT NULL DEFAULT 'us';
+
+-- Backstop for code paths that bypass `Database::pool_for_merchant`: every connection
+-- declares its cluster's region via `defiant.data_region`, and PII rows for merchants
+-- homed elsewhere are rejected outright.
+CREATE OR REPLACE FUNCTION enforce_data_residency()
+RETURNS TRIGGER AS $$
+DECLARE
+    merchant_region data_region;
+BEGIN
+    SELECT data_region INTO merchant_region FROM merchants WHERE id = NEW.merchant_id;
+    IF merchant_region IS NOT NULL
+        AND merchant_region::TEXT <> COALESCE(NULLIF(current_setting('defiant.data_region', true), ''), 'us') THEN
+        RAISE EXCEPTION 'data residency violation: % row for % merchant written outside its region',
+            TG_TABLE_NAME, merchant_region;
+    END IF;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER enforce_customers_residency BEFORE INSERT OR UPDATE ON customers
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_payment_methods_residency BEFORE INSERT OR UPDATE ON payment_methods
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_customer_communications_residency BEFORE INSERT OR UPDATE ON customer_communications
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_payments_residency BEFORE INSERT OR UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_checkout_sessions_residency BEFORE INSERT OR UPDATE ON checkout_sessions
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
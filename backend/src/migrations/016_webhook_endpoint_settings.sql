@@ -0,0 +1,3 @@
+-- Payload schema the endpoint is pinned to; new endpoints get the current version
+ALTER TABLE webhooks ADD COLUMN api_version VARCHAR(20) NOT NULL DEFAULT '2024-01-01';
+ALTER TABLE webhooks ADD COLUMN description TEXT;
@@ -0,0 +1,43 @@
+CREATE TYPE sca_exemption AS ENUM (
+    'low_value',
+    'transaction_risk_analysis',
+    'merchant_initiated'
+);
+
+CREATE TYPE sca_status AS ENUM (
+    'not_required',
+    'exempted',
+    'challenge_required',
+    'exemption_declined',
+    'authenticated'
+);
+
+ALTER TABLE payments ADD COLUMN sca_status sca_status NOT NULL DEFAULT 'not_required';
+ALTER TABLE payments ADD COLUMN sca_exemption sca_exemption;
+
+-- Low-value exemptions are capped per card at 5 transactions or EUR 100 since the
+-- card last went through strong authentication
+CREATE TABLE sca_low_value_counters (
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    card_fingerprint VARCHAR(64) NOT NULL,
+    transaction_count INTEGER NOT NULL DEFAULT 0,
+    cumulative_amount_eur BIGINT NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (merchant_id, card_fingerprint)
+);
+
+CREATE TABLE sca_exemption_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_id UUID REFERENCES payments(id) ON DELETE CASCADE,
+    exemption sca_exemption NOT NULL,
+    amount_eur BIGINT NOT NULL,
+    accepted BOOLEAN NOT NULL,
+    decline_code VARCHAR(50),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_sca_exemption_attempts_merchant_id ON sca_exemption_attempts(merchant_id, created_at);
+
+CREATE TRIGGER update_sca_low_value_counters_updated_at BEFORE UPDATE ON sca_low_value_counters
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
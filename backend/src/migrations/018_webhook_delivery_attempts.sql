@@ -0,0 +1,16 @@
+-- One row per HTTP attempt so merchants can see exactly what was sent and what came back
+CREATE TABLE webhook_delivery_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    delivery_id UUID REFERENCES webhook_deliveries(id) ON DELETE CASCADE,
+    attempt_number INTEGER NOT NULL,
+    request_body TEXT NOT NULL,
+    response_status INTEGER,
+    -- Truncated; enough to read an error page, not to store arbitrary payloads
+    response_body TEXT,
+    latency_ms INTEGER NOT NULL,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_delivery_attempts_delivery_id ON webhook_delivery_attempts(delivery_id, attempt_number);
@@ -0,0 +1,8 @@
+-- Events are listed per merchant, newest first, optionally narrowed by type
+DROP INDEX idx_events_merchant_id;
+DROP INDEX idx_events_type;
+CREATE INDEX idx_events_merchant_id_created_at ON events(merchant_id, created_at DESC);
+CREATE INDEX idx_events_merchant_id_type ON events(merchant_id, type, created_at DESC);
+
+-- Webhook endpoints pin versions up to 20 characters; events carry the same values
+ALTER TABLE events ALTER COLUMN api_version TYPE VARCHAR(20);
@@ -0,0 +1,35 @@
+CREATE TYPE mit_agreement_type AS ENUM (
+    'unscheduled',
+    'recurring',
+    'installment'
+);
+
+-- A customer's consent, given during an on-session payment, to be charged later
+-- without being present. Networks require every MIT to reference the transaction
+-- in which the agreement was made.
+CREATE TABLE mit_agreements (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id) ON DELETE CASCADE,
+    agreement_type mit_agreement_type NOT NULL,
+    card_fingerprint VARCHAR(64) NOT NULL,
+    initial_payment_id UUID REFERENCES payments(id) ON DELETE RESTRICT,
+    network_transaction_id VARCHAR(64) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_mit_agreements_customer_id ON mit_agreements(merchant_id, customer_id);
+
+-- Scheme reference for this authorization, and for MITs the agreement it relies on
+ALTER TABLE payments ADD COLUMN network_transaction_id VARCHAR(64);
+ALTER TABLE payments ADD COLUMN mit_agreement_id UUID REFERENCES mit_agreements(id) ON DELETE SET NULL;
+ALTER TABLE payments ADD COLUMN original_network_transaction_id VARCHAR(64);
+
+CREATE TRIGGER update_mit_agreements_updated_at BEFORE UPDATE ON mit_agreements
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_mit_agreements_residency BEFORE INSERT OR UPDATE ON mit_agreements
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,31 @@
+-- Evidence that a customer agreed to have a card stored and charged later.
+-- Rows are written once and never edited; disputes rely on them verbatim.
+CREATE TABLE card_on_file_consents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    customer_id UUID REFERENCES customers(id) ON DELETE CASCADE,
+    payment_method_id UUID NOT NULL,
+    mit_agreement_id UUID,
+    payment_id UUID,
+    consent_text_version VARCHAR(50) NOT NULL,
+    ip_address VARCHAR(45) NOT NULL,
+    user_agent TEXT,
+    accepted_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_card_on_file_consents_customer_id ON card_on_file_consents(merchant_id, customer_id, created_at DESC);
+CREATE INDEX idx_card_on_file_consents_payment_method_id ON card_on_file_consents(payment_method_id);
+
+CREATE OR REPLACE FUNCTION prevent_consent_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'card_on_file_consents rows are immutable';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER prevent_card_on_file_consents_update BEFORE UPDATE ON card_on_file_consents
+    FOR EACH ROW EXECUTE FUNCTION prevent_consent_changes();
+
+CREATE TRIGGER enforce_card_on_file_consents_residency BEFORE INSERT ON card_on_file_consents
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,39 @@
+CREATE TYPE event_replay_status AS ENUM (
+    'pending',
+    'running',
+    'completed',
+    'failed'
+);
+
+-- A merchant-requested re-delivery of every matching event in a time window to one endpoint.
+-- The job walks events in (created_at, id) order and persists its cursor after each batch,
+-- so a worker that dies mid-run is picked up where it left off.
+CREATE TABLE event_replays (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    webhook_id UUID REFERENCES webhooks(id) ON DELETE CASCADE,
+    created_gte TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_lte TIMESTAMP WITH TIME ZONE NOT NULL,
+    status event_replay_status NOT NULL DEFAULT 'pending',
+    total_events INTEGER NOT NULL DEFAULT 0,
+    queued_events INTEGER NOT NULL DEFAULT 0,
+    cursor_created_at TIMESTAMP WITH TIME ZONE,
+    cursor_event_id UUID,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    error TEXT,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_event_replays_webhook_id ON event_replays(webhook_id);
+CREATE INDEX idx_event_replays_due ON event_replays(created_at)
+    WHERE status IN ('pending', 'running');
+
+-- Replayed deliveries go through the normal queue; this ties them back to the job
+ALTER TABLE webhook_deliveries ADD COLUMN replay_id UUID REFERENCES event_replays(id) ON DELETE SET NULL;
+CREATE INDEX idx_webhook_deliveries_replay_id ON webhook_deliveries(replay_id) WHERE replay_id IS NOT NULL;
+
+CREATE TRIGGER update_event_replays_updated_at BEFORE UPDATE ON event_replays
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
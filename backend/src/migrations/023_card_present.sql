@@ -0,0 +1,46 @@
+ALTER TYPE payment_method ADD VALUE 'card_present';
+
+CREATE TYPE card_entry_mode AS ENUM (
+    'chip',
+    'contactless',
+    'magstripe',
+    'fallback_magstripe'
+);
+
+CREATE TYPE card_encryption_scheme AS ENUM (
+    'dukpt_tdes',
+    'dukpt_aes'
+);
+
+CREATE TYPE terminal_key_status AS ENUM (
+    'active',
+    'retiring',
+    'retired'
+);
+
+-- Metadata for the key sets injected into a merchant's readers. The keys themselves
+-- live with the processor's HSM; Defiant only tracks which sets are valid so it can
+-- refuse payloads encrypted under a retired key before forwarding them.
+CREATE TABLE terminal_encryption_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    key_set_id VARCHAR(50) NOT NULL,
+    scheme card_encryption_scheme NOT NULL,
+    status terminal_key_status NOT NULL DEFAULT 'active',
+    activated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    -- Readers still on a superseded key keep working until this time
+    retire_after TIMESTAMP WITH TIME ZONE,
+    retired_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (merchant_id, key_set_id)
+);
+
+-- Only what is needed to trace an authorization back to a reader's key; the encrypted
+-- track and PIN data are forwarded and never stored
+ALTER TABLE payments ADD COLUMN entry_mode card_entry_mode;
+ALTER TABLE payments ADD COLUMN terminal_key_set_id VARCHAR(50);
+ALTER TABLE payments ADD COLUMN terminal_ksn VARCHAR(24);
+
+CREATE TRIGGER update_terminal_encryption_keys_updated_at BEFORE UPDATE ON terminal_encryption_keys
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,3 @@
+-- During a rotation the superseded secret keeps signing alongside the new one
+ALTER TABLE webhooks ADD COLUMN previous_encrypted_secret TEXT;
+ALTER TABLE webhooks ADD COLUMN previous_secret_expires_at TIMESTAMP WITH TIME ZONE;
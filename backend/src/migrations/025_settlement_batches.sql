@@ -0,0 +1,61 @@
+-- Net-settlement acquirers take one file of captured transactions per merchant,
+-- currency and business day, and answer with an acknowledgment file.
+ALTER TABLE merchants ADD COLUMN batch_settlement_enabled BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE merchants ADD COLUMN acquirer_merchant_id VARCHAR(50);
+
+CREATE TYPE settlement_batch_status AS ENUM (
+    'generated',
+    'accepted',
+    'partially_accepted',
+    'rejected'
+);
+
+CREATE TYPE settlement_item_status AS ENUM (
+    'pending',
+    'accepted',
+    'rejected'
+);
+
+CREATE TABLE settlement_batches (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    business_date DATE NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    status settlement_batch_status NOT NULL DEFAULT 'generated',
+    file_name VARCHAR(255) NOT NULL,
+    file_contents TEXT NOT NULL,
+    transaction_count INTEGER NOT NULL,
+    total_amount BIGINT NOT NULL,
+    accepted_count INTEGER NOT NULL DEFAULT 0,
+    rejected_count INTEGER NOT NULL DEFAULT 0,
+    acknowledged_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (merchant_id, business_date, currency)
+);
+
+CREATE TABLE settlement_batch_items (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    batch_id UUID REFERENCES settlement_batches(id) ON DELETE CASCADE,
+    payment_id UUID REFERENCES payments(id) ON DELETE RESTRICT,
+    sequence_number INTEGER NOT NULL,
+    amount BIGINT NOT NULL,
+    status settlement_item_status NOT NULL DEFAULT 'pending',
+    reject_code VARCHAR(20),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (batch_id, sequence_number)
+);
+
+CREATE INDEX idx_settlement_batch_items_payment_id ON settlement_batch_items(payment_id);
+
+-- A payment is batched at most once; rejected items are cleared so the next run picks them up
+ALTER TABLE payments ADD COLUMN settlement_batch_id UUID REFERENCES settlement_batches(id) ON DELETE SET NULL;
+CREATE INDEX idx_payments_unbatched ON payments(merchant_id, created_at)
+    WHERE settlement_batch_id IS NULL AND status = 'succeeded';
+
+CREATE TRIGGER update_settlement_batches_updated_at BEFORE UPDATE ON settlement_batches
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_settlement_batch_items_updated_at BEFORE UPDATE ON settlement_batch_items
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,10 @@
+-- Stripe retries deliveries; each event is applied once
+CREATE TABLE stripe_events (
+    id VARCHAR(255) PRIMARY KEY,
+    type VARCHAR(255) NOT NULL,
+    processed_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Disputes and refunds reference the charge, not our payment id
+ALTER TABLE payments ADD COLUMN stripe_charge_id VARCHAR(255);
+CREATE UNIQUE INDEX idx_payments_stripe_charge_id ON payments(stripe_charge_id) WHERE stripe_charge_id IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Events record the payload version their data was written in so it can be
+-- rendered into whatever version an endpoint is pinned to
+UPDATE events SET api_version = '2024-01-01' WHERE api_version IS NULL;
+ALTER TABLE events ALTER COLUMN api_version SET NOT NULL;
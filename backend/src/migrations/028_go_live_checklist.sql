@@ -0,0 +1,44 @@
+-- Keys are test or live. Keys issued before the split were already used for live
+-- traffic; new keys start in test mode and live keys are only issued once the
+-- merchant passes the go-live checklist.
+ALTER TABLE api_keys ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE api_keys ALTER COLUMN livemode SET DEFAULT false;
+
+ALTER TABLE payments ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE payments ALTER COLUMN livemode SET DEFAULT false;
+CREATE INDEX idx_payments_test_succeeded ON payments(merchant_id) WHERE livemode = false AND status = 'succeeded';
+
+CREATE TYPE kyc_status AS ENUM (
+    'unverified',
+    'pending',
+    'verified',
+    'rejected'
+);
+
+-- Set by the onboarding review
+ALTER TABLE merchants ADD COLUMN kyc_status kyc_status NOT NULL DEFAULT 'unverified';
+ALTER TABLE merchants ADD COLUMN kyc_verified_at TIMESTAMP WITH TIME ZONE;
+
+-- Destination accounts for payouts, captured during onboarding. Only the last
+-- four digits of the account number are kept here.
+CREATE TABLE merchant_bank_accounts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    account_holder_name VARCHAR(255) NOT NULL,
+    bank_name VARCHAR(255),
+    country VARCHAR(2) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    routing_number VARCHAR(50),
+    last4 VARCHAR(4) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_merchant_bank_accounts_merchant_id ON merchant_bank_accounts(merchant_id);
+
+CREATE TRIGGER update_merchant_bank_accounts_updated_at BEFORE UPDATE ON merchant_bank_accounts
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_merchant_bank_accounts_residency BEFORE INSERT OR UPDATE ON merchant_bank_accounts
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,3 @@
+-- Client secrets let browsers and apps read a payment's outcome without the
+-- merchant's key. Only a SHA-256 of the secret is stored.
+ALTER TABLE payments ADD COLUMN client_secret_hash VARCHAR(64);
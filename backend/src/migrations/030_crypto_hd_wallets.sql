@@ -0,0 +1,40 @@
+CREATE TYPE crypto_network AS ENUM (
+    'bitcoin',
+    'ethereum'
+);
+
+-- Account-level extended public keys (m/44'/coin'/account'). Payment addresses are
+-- derived from the external chain, so funds stay recoverable from the merchant's seed.
+CREATE TABLE merchant_crypto_wallets (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    network crypto_network NOT NULL,
+    xpub VARCHAR(255) NOT NULL,
+    -- Next unused index on the external chain
+    next_index INTEGER NOT NULL DEFAULT 0,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE UNIQUE INDEX idx_merchant_crypto_wallets_active ON merchant_crypto_wallets(merchant_id, network)
+    WHERE active = true;
+
+CREATE TABLE crypto_addresses (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    wallet_id UUID REFERENCES merchant_crypto_wallets(id) ON DELETE RESTRICT,
+    payment_id UUID REFERENCES payments(id) ON DELETE CASCADE,
+    network crypto_network NOT NULL,
+    address VARCHAR(100) NOT NULL,
+    derivation_index INTEGER NOT NULL,
+    derivation_path VARCHAR(100) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (network, address),
+    UNIQUE (wallet_id, derivation_index)
+);
+
+CREATE INDEX idx_crypto_addresses_payment_id ON crypto_addresses(payment_id);
+
+CREATE TRIGGER update_merchant_crypto_wallets_updated_at BEFORE UPDATE ON merchant_crypto_wallets
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,18 @@
+CREATE TYPE crypto_address_status AS ENUM (
+    'awaiting_payment',
+    'detected',
+    'confirmed',
+    'expired'
+);
+
+-- Amounts are in the chain's base unit (satoshi, wei)
+ALTER TABLE crypto_addresses ADD COLUMN expected_amount NUMERIC(78, 0) NOT NULL DEFAULT 0;
+ALTER TABLE crypto_addresses ADD COLUMN received_amount NUMERIC(78, 0) NOT NULL DEFAULT 0;
+ALTER TABLE crypto_addresses ADD COLUMN confirmed_amount NUMERIC(78, 0) NOT NULL DEFAULT 0;
+ALTER TABLE crypto_addresses ADD COLUMN status crypto_address_status NOT NULL DEFAULT 'awaiting_payment';
+ALTER TABLE crypto_addresses ADD COLUMN detected_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE crypto_addresses ADD COLUMN confirmed_at TIMESTAMP WITH TIME ZONE;
+ALTER TABLE crypto_addresses ADD COLUMN last_checked_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_crypto_addresses_watch ON crypto_addresses(last_checked_at NULLS FIRST)
+    WHERE status IN ('awaiting_payment', 'detected');
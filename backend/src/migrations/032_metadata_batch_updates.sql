@@ -0,0 +1,44 @@
+CREATE TYPE metadata_batch_object AS ENUM (
+    'payment',
+    'customer'
+);
+
+CREATE TYPE metadata_batch_status AS ENUM (
+    'pending',
+    'running',
+    'completed',
+    'failed'
+);
+
+-- A merchant-requested metadata patch over every object matching a filter. Like event
+-- replays, the job walks objects in (created_at, id) order and persists its cursor with
+-- each batch, so a worker that dies mid-run resumes without re-patching earlier rows.
+CREATE TABLE metadata_batch_updates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    object metadata_batch_object NOT NULL,
+    filter JSONB NOT NULL,
+    -- Merged into each object's metadata; null values remove the key
+    patch JSONB NOT NULL,
+    status metadata_batch_status NOT NULL DEFAULT 'pending',
+    matched_objects INTEGER NOT NULL DEFAULT 0,
+    updated_objects INTEGER NOT NULL DEFAULT 0,
+    cursor_created_at TIMESTAMP WITH TIME ZONE,
+    cursor_object_id UUID,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    error TEXT,
+    started_at TIMESTAMP WITH TIME ZONE,
+    completed_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_metadata_batch_updates_merchant_id ON metadata_batch_updates(merchant_id);
+CREATE INDEX idx_metadata_batch_updates_due ON metadata_batch_updates(created_at)
+    WHERE status IN ('pending', 'running');
+
+CREATE TRIGGER update_metadata_batch_updates_updated_at BEFORE UPDATE ON metadata_batch_updates
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_metadata_batch_updates_residency BEFORE INSERT OR UPDATE ON metadata_batch_updates
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,25 @@
+ALTER TYPE crypto_network RENAME TO crypto_chain;
+ALTER TYPE crypto_chain ADD VALUE 'solana';
+
+ALTER TABLE merchant_crypto_wallets RENAME COLUMN network TO chain;
+ALTER TABLE crypto_addresses RENAME COLUMN network TO chain;
+
+-- Solana keys are ed25519, which has no public derivation: those wallets register a
+-- single receiving address and each payment is told apart by a Solana Pay reference.
+ALTER TABLE merchant_crypto_wallets ALTER COLUMN xpub DROP NOT NULL;
+ALTER TABLE merchant_crypto_wallets ADD COLUMN address VARCHAR(100);
+ALTER TABLE merchant_crypto_wallets ADD CONSTRAINT merchant_crypto_wallets_key_check
+    CHECK ((xpub IS NULL) <> (address IS NULL));
+
+ALTER TABLE crypto_addresses ALTER COLUMN derivation_index DROP NOT NULL;
+ALTER TABLE crypto_addresses ALTER COLUMN derivation_path DROP NOT NULL;
+ALTER TABLE crypto_addresses ADD COLUMN reference VARCHAR(64);
+-- ERC-20 contract the payment is due in; NULL for the chain's native coin
+ALTER TABLE crypto_addresses ADD COLUMN token_contract VARCHAR(42);
+
+-- Reference-based addresses repeat across payments
+ALTER TABLE crypto_addresses DROP CONSTRAINT crypto_addresses_network_address_key;
+CREATE UNIQUE INDEX idx_crypto_addresses_chain_address ON crypto_addresses(chain, address)
+    WHERE reference IS NULL;
+CREATE UNIQUE INDEX idx_crypto_addresses_reference ON crypto_addresses(reference)
+    WHERE reference IS NOT NULL;
@@ -0,0 +1,10 @@
+ALTER TYPE crypto_address_status ADD VALUE 'rate_expired';
+
+-- The fiat -> crypto quote the expected amount was locked at. Funds first seen after
+-- the quote expires are not honored at the locked amount.
+ALTER TABLE crypto_addresses ADD COLUMN quote_currency VARCHAR(3);
+ALTER TABLE crypto_addresses ADD COLUMN quote_rate NUMERIC(38, 18);
+ALTER TABLE crypto_addresses ADD COLUMN quote_expires_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_crypto_addresses_quote_expiry ON crypto_addresses(quote_expires_at)
+    WHERE status = 'awaiting_payment';
@@ -0,0 +1,24 @@
+CREATE TYPE trusted_source_kind AS ENUM (
+    'ip_range',
+    'service_identity'
+);
+
+-- Merchant backends that get the elevated rate limit. Kept in the directory database
+-- next to api_keys, since the limiter consults it before a request reaches a region.
+CREATE TABLE merchant_trusted_sources (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    kind trusted_source_kind NOT NULL,
+    name VARCHAR(255) NOT NULL,
+    -- Network address in CIDR notation, for `ip_range`
+    ip_range TEXT,
+    -- Hex-encoded Ed25519 public key requests are signed with, for `service_identity`
+    public_key TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT merchant_trusted_sources_kind_check CHECK (
+        (kind = 'ip_range' AND ip_range IS NOT NULL AND public_key IS NULL)
+        OR (kind = 'service_identity' AND public_key IS NOT NULL AND ip_range IS NULL)
+    )
+);
+
+CREATE INDEX idx_merchant_trusted_sources_merchant_id ON merchant_trusted_sources(merchant_id);
@@ -0,0 +1,72 @@
+CREATE TYPE crypto_settlement_asset AS ENUM (
+    'native',
+    'usdc',
+    'usdt'
+);
+
+CREATE TYPE crypto_conversion_status AS ENUM (
+    'awaiting_deposit',
+    'converting',
+    'settled',
+    'failed'
+);
+
+-- How a merchant wants crypto payments settled. Merchants without a row are settled in
+-- whatever asset the customer paid, straight into their own wallet.
+CREATE TABLE crypto_settlement_preferences (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    settlement_asset crypto_settlement_asset NOT NULL DEFAULT 'native',
+    -- Ethereum address stablecoin settlements are sent to
+    settlement_address VARCHAR(42),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT crypto_settlement_preferences_address_check
+        CHECK (settlement_asset = 'native' OR settlement_address IS NOT NULL)
+);
+
+-- A payment collected through the conversion provider: the customer pays into a
+-- provider deposit address (a crypto_addresses row without a wallet), and the provider
+-- sends the stablecoin on to the merchant's settlement address.
+CREATE TABLE crypto_conversions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_id UUID REFERENCES payments(id) ON DELETE CASCADE,
+    crypto_address_id UUID UNIQUE REFERENCES crypto_addresses(id) ON DELETE CASCADE,
+    provider_reference VARCHAR(255) NOT NULL,
+    -- Ticker, or ERC-20 contract for tokens we cannot name, and base units deposited
+    deposit_asset VARCHAR(42) NOT NULL,
+    deposit_amount NUMERIC(78, 0) NOT NULL,
+    settlement_asset crypto_settlement_asset NOT NULL,
+    settlement_address VARCHAR(42) NOT NULL,
+    -- The provider's quote until settled, then what it actually sent
+    settlement_amount NUMERIC(78, 0) NOT NULL,
+    settlement_tx_hash VARCHAR(100),
+    status crypto_conversion_status NOT NULL DEFAULT 'awaiting_deposit',
+    error TEXT,
+    settled_at TIMESTAMP WITH TIME ZONE,
+    last_checked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_crypto_conversions_merchant_id ON crypto_conversions(merchant_id);
+CREATE INDEX idx_crypto_conversions_in_flight ON crypto_conversions(last_checked_at NULLS FIRST)
+    WHERE status = 'converting';
+
+-- What a crypto payment received, and what the merchant was settled in
+ALTER TABLE balance_transactions ADD COLUMN crypto_asset VARCHAR(42);
+ALTER TABLE balance_transactions ADD COLUMN crypto_amount NUMERIC(78, 0);
+ALTER TABLE balance_transactions ADD COLUMN settlement_asset VARCHAR(42);
+ALTER TABLE balance_transactions ADD COLUMN settlement_amount NUMERIC(78, 0);
+
+CREATE TRIGGER update_crypto_settlement_preferences_updated_at BEFORE UPDATE ON crypto_settlement_preferences
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_crypto_conversions_updated_at BEFORE UPDATE ON crypto_conversions
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_crypto_settlement_preferences_residency BEFORE INSERT OR UPDATE ON crypto_settlement_preferences
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_crypto_conversions_residency BEFORE INSERT OR UPDATE ON crypto_conversions
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,14 @@
+-- The address's quote lapsed with less than the tolerated amount confirmed; the rest
+-- is due at a remaining-balance address
+ALTER TYPE crypto_address_status ADD VALUE 'underpaid';
+ALTER TYPE payment_status ADD VALUE 'partially_paid';
+
+-- Basis points a crypto payment may fall short by and still succeed, or exceed by
+-- before the excess is credited to the customer's balance
+ALTER TABLE crypto_settlement_preferences ADD COLUMN payment_tolerance_bps INTEGER NOT NULL DEFAULT 0
+    CHECK (payment_tolerance_bps BETWEEN 0 AND 1000);
+
+-- A remaining-balance address points at the underpaid address it continues
+ALTER TABLE crypto_addresses ADD COLUMN parent_address_id UUID REFERENCES crypto_addresses(id) ON DELETE SET NULL;
+
+ALTER TABLE customer_balance_transactions ADD COLUMN payment_id UUID REFERENCES payments(id) ON DELETE SET NULL;
@@ -0,0 +1,23 @@
+-- Minutes an unpaid crypto payment stays open before it is canceled
+ALTER TABLE crypto_settlement_preferences ADD COLUMN payment_expiry_minutes INTEGER NOT NULL DEFAULT 60
+    CHECK (payment_expiry_minutes BETWEEN 15 AND 10080);
+
+ALTER TABLE payments ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE;
+
+-- Open crypto payments from before expiry was configurable keep the old seven-day window
+UPDATE payments SET expires_at = created_at + INTERVAL '7 days'
+WHERE payment_method = 'crypto' AND status IN ('pending', 'requires_action');
+
+CREATE INDEX idx_payments_expiring ON payments(expires_at)
+    WHERE expires_at IS NOT NULL AND status IN ('pending', 'requires_action');
+
+-- Wallet software stops scanning after this many unused receiving addresses in a row,
+-- so we never hand out an index further than that past the last one paid
+ALTER TABLE merchant_crypto_wallets ADD COLUMN gap_limit INTEGER NOT NULL DEFAULT 20
+    CHECK (gap_limit BETWEEN 1 AND 1000);
+ALTER TABLE merchant_crypto_wallets ADD COLUMN last_used_index INTEGER;
+
+UPDATE merchant_crypto_wallets w SET last_used_index = (
+    SELECT MAX(derivation_index) FROM crypto_addresses a
+    WHERE a.wallet_id = w.id AND a.received_amount > 0
+);
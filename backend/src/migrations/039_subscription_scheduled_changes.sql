@@ -0,0 +1,12 @@
+-- Plan a subscription switches to when its current period ends
+ALTER TABLE subscriptions ADD COLUMN pending_plan_id UUID REFERENCES plans(id);
+
+UPDATE subscriptions SET cancel_at_period_end = false WHERE cancel_at_period_end IS NULL;
+ALTER TABLE subscriptions ALTER COLUMN cancel_at_period_end SET NOT NULL;
+
+CREATE INDEX idx_subscriptions_renewal_due ON subscriptions(current_period_end)
+    WHERE status IN ('active', 'trialing', 'past_due');
+
+-- One renewal invoice per subscription period, however often the scheduler retries
+CREATE UNIQUE INDEX idx_invoices_subscription_period ON invoices(subscription_id, period_start)
+    WHERE subscription_id IS NOT NULL;
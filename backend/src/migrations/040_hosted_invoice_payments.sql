@@ -0,0 +1,13 @@
+-- Payments made from an invoice's hosted page settle that invoice
+UPDATE payments p SET invoice_id = NULL
+WHERE invoice_id IS NOT NULL AND NOT EXISTS (SELECT 1 FROM invoices i WHERE i.id = p.invoice_id);
+ALTER TABLE payments ADD CONSTRAINT payments_invoice_id_fkey
+    FOREIGN KEY (invoice_id) REFERENCES invoices(id) ON DELETE SET NULL;
+CREATE INDEX idx_payments_invoice_id ON payments(invoice_id) WHERE invoice_id IS NOT NULL;
+
+-- Invoices are paid without an API key, so the mode of the key that sent the invoice
+-- is kept for the payments made against it. Invoices from before this were created
+-- for live traffic.
+ALTER TABLE invoices ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE invoices ALTER COLUMN livemode SET DEFAULT false;
+ALTER TABLE invoices ADD COLUMN sent_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,44 @@
+CREATE TYPE crypto_refund_status AS ENUM (
+    'pending',
+    'broadcast',
+    'confirmed',
+    'failed'
+);
+
+-- Where the customer asked for a refund to be sent, registered with the client secret
+ALTER TABLE payments ADD COLUMN crypto_refund_address VARCHAR(100);
+
+-- A refund sent back on chain, in the asset the customer paid. The amount is reserved
+-- against the payment when the refund is created and released if it fails.
+CREATE TABLE crypto_refunds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_id UUID NOT NULL REFERENCES payments(id) ON DELETE CASCADE,
+    chain crypto_chain NOT NULL,
+    token_contract VARCHAR(42),
+    address VARCHAR(100) NOT NULL,
+    -- In the payment's currency, and what that was on chain at the payment's rate
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency VARCHAR(3) NOT NULL,
+    crypto_amount NUMERIC(78, 0) NOT NULL CHECK (crypto_amount > 0),
+    reason TEXT,
+    status crypto_refund_status NOT NULL DEFAULT 'pending',
+    tx_hash VARCHAR(128),
+    confirmations INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    broadcast_at TIMESTAMP WITH TIME ZONE,
+    confirmed_at TIMESTAMP WITH TIME ZONE,
+    last_checked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_crypto_refunds_payment_id ON crypto_refunds(payment_id);
+CREATE INDEX idx_crypto_refunds_in_flight ON crypto_refunds(last_checked_at NULLS FIRST)
+    WHERE status IN ('pending', 'broadcast');
+
+CREATE TRIGGER update_crypto_refunds_updated_at BEFORE UPDATE ON crypto_refunds
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_crypto_refunds_residency BEFORE INSERT OR UPDATE ON crypto_refunds
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
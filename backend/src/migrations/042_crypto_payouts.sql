@@ -0,0 +1,50 @@
+CREATE TYPE crypto_payout_status AS ENUM (
+    'pending',
+    'broadcast',
+    'confirmed',
+    'failed'
+);
+
+-- Cold or settlement wallet confirmed funds are swept into; wallets without one are
+-- never swept
+ALTER TABLE merchant_crypto_wallets ADD COLUMN sweep_address VARCHAR(100);
+
+-- One sweep transaction consolidating payment addresses of a wallet
+CREATE TABLE crypto_payouts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    wallet_id UUID NOT NULL REFERENCES merchant_crypto_wallets(id) ON DELETE RESTRICT,
+    chain crypto_chain NOT NULL,
+    destination VARCHAR(100) NOT NULL,
+    address_count INTEGER NOT NULL CHECK (address_count > 0),
+    -- Base units swept in, and the estimated network fee paid out of them
+    amount NUMERIC(78, 0) NOT NULL CHECK (amount > 0),
+    fee NUMERIC(78, 0) NOT NULL,
+    -- Sat per vbyte on Bitcoin, wei per gas on Ethereum
+    fee_rate NUMERIC(38, 9) NOT NULL,
+    status crypto_payout_status NOT NULL DEFAULT 'pending',
+    tx_hash VARCHAR(128),
+    confirmations INTEGER NOT NULL DEFAULT 0,
+    error TEXT,
+    broadcast_at TIMESTAMP WITH TIME ZONE,
+    confirmed_at TIMESTAMP WITH TIME ZONE,
+    last_checked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_crypto_payouts_merchant_id ON crypto_payouts(merchant_id, created_at DESC);
+CREATE INDEX idx_crypto_payouts_in_flight ON crypto_payouts(last_checked_at NULLS FIRST)
+    WHERE status IN ('pending', 'broadcast');
+
+-- The sweep an address's funds left in; cleared again if the sweep fails
+ALTER TABLE crypto_addresses ADD COLUMN crypto_payout_id UUID REFERENCES crypto_payouts(id) ON DELETE SET NULL;
+
+CREATE INDEX idx_crypto_addresses_unswept ON crypto_addresses(wallet_id)
+    WHERE status IN ('confirmed', 'underpaid') AND crypto_payout_id IS NULL;
+
+CREATE TRIGGER update_crypto_payouts_updated_at BEFORE UPDATE ON crypto_payouts
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_crypto_payouts_residency BEFORE INSERT OR UPDATE ON crypto_payouts
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,5 @@
+-- Why fraud checks let a payment through but flagged it for the merchant to look at,
+-- e.g. a velocity counter over its review threshold
+ALTER TABLE payments ADD COLUMN review_reason TEXT;
+
+CREATE INDEX idx_payments_review ON payments(merchant_id, created_at DESC) WHERE review_reason IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Risk score (0-100, higher is riskier) given at creation, and what drove it. Absent on
+-- payments created before scoring.
+ALTER TABLE payments ADD COLUMN risk_score SMALLINT CHECK (risk_score BETWEEN 0 AND 100);
+ALTER TABLE payments ADD COLUMN risk_reasons TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE INDEX idx_payments_risk_score ON payments(merchant_id, risk_score) WHERE risk_score IS NOT NULL;
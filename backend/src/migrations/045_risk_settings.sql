@@ -0,0 +1,21 @@
+-- How a merchant acts on risk scores. Merchants without a row challenge card payments
+-- scoring 50 or more with 3DS and never decline on score alone.
+CREATE TABLE risk_settings (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    -- Card payments scoring at least this are sent through 3DS
+    challenge_score SMALLINT NOT NULL DEFAULT 50 CHECK (challenge_score BETWEEN 0 AND 100),
+    -- Payments scoring at least this are declined outright
+    block_score SMALLINT CHECK (block_score BETWEEN 0 AND 100),
+    -- Let risky payments under the PSD2 low-value limit skip the challenge
+    low_value_exemption BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT risk_settings_block_above_challenge_check
+        CHECK (block_score IS NULL OR block_score > challenge_score)
+);
+
+CREATE TRIGGER update_risk_settings_updated_at BEFORE UPDATE ON risk_settings
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_risk_settings_residency BEFORE INSERT OR UPDATE ON risk_settings
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,3 @@
+-- IP country, proxy/VPN detection and country mismatches fraud checks derived from the
+-- customer's IP when the payment was created
+ALTER TABLE payments ADD COLUMN geo_signals JSONB;
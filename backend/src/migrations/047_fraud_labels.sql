@@ -0,0 +1,60 @@
+CREATE TYPE fraud_label AS ENUM (
+    'fraudulent',
+    'safe'
+);
+
+CREATE TYPE blocklist_kind AS ENUM (
+    'card_fingerprint',
+    'ip_address',
+    'customer'
+);
+
+-- What fraud checks counted a payment against, for labels and the blocklist to use
+ALTER TABLE payments ADD COLUMN card_fingerprint VARCHAR(64);
+ALTER TABLE payments ADD COLUMN client_ip VARCHAR(45);
+
+-- A merchant's verdict on a payment, with the rules that fired on it at the time, as
+-- training data for the scorer and for per-rule precision and recall. Relabeling
+-- replaces the earlier verdict.
+CREATE TABLE fraud_labels (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id) ON DELETE CASCADE,
+    label fraud_label NOT NULL,
+    -- Risk reasons plus the velocity counter that opened a review, if any
+    rules TEXT[] NOT NULL DEFAULT '{}',
+    risk_score SMALLINT,
+    amount BIGINT NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    note TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Cards, addresses and customers a merchant's payments are declined from, added when a
+-- payment is labeled fraudulent and lifted when it is relabeled safe
+CREATE TABLE fraud_blocklist (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    kind blocklist_kind NOT NULL,
+    value VARCHAR(100) NOT NULL,
+    source_payment_id UUID REFERENCES payments(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (merchant_id, kind, value)
+);
+
+CREATE INDEX idx_fraud_labels_merchant_id ON fraud_labels(merchant_id);
+CREATE INDEX idx_fraud_blocklist_source_payment_id ON fraud_blocklist(source_payment_id);
+
+CREATE TRIGGER update_fraud_labels_updated_at BEFORE UPDATE ON fraud_labels
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER update_fraud_blocklist_updated_at BEFORE UPDATE ON fraud_blocklist
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_fraud_labels_residency BEFORE INSERT OR UPDATE ON fraud_labels
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_fraud_blocklist_residency BEFORE INSERT OR UPDATE ON fraud_blocklist
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
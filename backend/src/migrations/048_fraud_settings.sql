@@ -0,0 +1,21 @@
+-- Switches and limits fraud checks apply per merchant. Merchants without a row run
+-- velocity checks, review on velocity alone and block no country.
+CREATE TABLE fraud_settings (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    velocity_checks_enabled BOOLEAN NOT NULL DEFAULT true,
+    -- In minor units of currency; larger payments are flagged for review
+    max_amount_without_review BIGINT CHECK (max_amount_without_review > 0),
+    -- In minor units of currency; card payments at least this large always go through 3DS
+    three_ds_amount_threshold BIGINT CHECK (three_ds_amount_threshold > 0),
+    currency VARCHAR(3) NOT NULL DEFAULT 'USD',
+    -- ISO 3166-1 alpha-2; matched against the IP, billing and card countries
+    blocked_countries TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TRIGGER update_fraud_settings_updated_at BEFORE UPDATE ON fraud_settings
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_fraud_settings_residency BEFORE INSERT OR UPDATE ON fraud_settings
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
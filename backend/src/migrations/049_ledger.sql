@@ -0,0 +1,89 @@
+-- Double-entry ledger. Every movement of a merchant's money is a journal entry whose
+-- lines debit and credit ledger accounts by the same total; balances are the sum of
+-- an account's lines. Entries and lines are written once and never edited: mistakes
+-- are corrected by a reversing entry.
+CREATE TYPE ledger_direction AS ENUM ('debit', 'credit');
+
+-- One per merchant, account code and currency, opened the first time it is posted to
+CREATE TABLE ledger_accounts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    code VARCHAR(50) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    -- The side that increases the balance: debit for assets and expenses, credit for
+    -- liabilities and revenue
+    normal_balance ledger_direction NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (merchant_id, code, currency)
+);
+
+CREATE TABLE journal_entries (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    -- payment, fee, refund, payout, payout_failure, topup, crypto_payment, crypto_refund
+    kind VARCHAR(50) NOT NULL,
+    currency VARCHAR(3) NOT NULL,
+    description TEXT,
+    -- What the entry was booked for, e.g. {"payment_id": ...}
+    metadata JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TABLE journal_lines (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    journal_entry_id UUID NOT NULL REFERENCES journal_entries(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    account_id UUID NOT NULL REFERENCES ledger_accounts(id),
+    direction ledger_direction NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_journal_entries_merchant_id ON journal_entries(merchant_id, created_at DESC);
+CREATE INDEX idx_journal_entries_payment_id ON journal_entries((metadata->>'payment_id'));
+CREATE INDEX idx_journal_lines_entry_id ON journal_lines(journal_entry_id);
+CREATE INDEX idx_journal_lines_account_id ON journal_lines(account_id);
+
+-- Checked at commit, once every line of the entry has been written
+CREATE OR REPLACE FUNCTION check_journal_entry_balanced()
+RETURNS TRIGGER AS $$
+DECLARE
+    imbalance BIGINT;
+BEGIN
+    SELECT COALESCE(SUM(CASE WHEN direction = 'debit' THEN amount ELSE -amount END), 0)
+    INTO imbalance
+    FROM journal_lines
+    WHERE journal_entry_id = NEW.journal_entry_id;
+    
+    IF imbalance <> 0 THEN
+        RAISE EXCEPTION 'journal entry % is unbalanced by %', NEW.journal_entry_id, imbalance;
+    END IF;
+    RETURN NULL;
+END;
+$$ language 'plpgsql';
+
+CREATE CONSTRAINT TRIGGER check_journal_lines_balanced AFTER INSERT ON journal_lines
+    DEFERRABLE INITIALLY DEFERRED
+    FOR EACH ROW EXECUTE FUNCTION check_journal_entry_balanced();
+
+CREATE OR REPLACE FUNCTION prevent_ledger_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION '% rows are immutable', TG_TABLE_NAME;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER prevent_journal_entries_update BEFORE UPDATE ON journal_entries
+    FOR EACH ROW EXECUTE FUNCTION prevent_ledger_changes();
+
+CREATE TRIGGER prevent_journal_lines_update BEFORE UPDATE ON journal_lines
+    FOR EACH ROW EXECUTE FUNCTION prevent_ledger_changes();
+
+CREATE TRIGGER enforce_ledger_accounts_residency BEFORE INSERT ON ledger_accounts
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_journal_entries_residency BEFORE INSERT ON journal_entries
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_journal_lines_residency BEFORE INSERT ON journal_lines
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
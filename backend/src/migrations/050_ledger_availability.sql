@@ -0,0 +1,19 @@
+-- When an entry's funds count towards the available balance rather than the pending
+-- one: card payments and their fees after the settlement delay, everything else at once
+ALTER TABLE journal_entries ADD COLUMN available_on TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW();
+
+CREATE INDEX idx_journal_entries_available_on ON journal_entries(merchant_id, available_on);
+
+-- How far each merchant's `balance.available` events have got
+CREATE TABLE balance_announcements (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    announced_through TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE TRIGGER update_balance_announcements_updated_at BEFORE UPDATE ON balance_announcements
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_balance_announcements_residency BEFORE INSERT OR UPDATE ON balance_announcements
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,28 @@
+CREATE TYPE payout_interval AS ENUM ('daily', 'weekly', 'manual');
+
+-- When a merchant's available balance is paid out without being asked. Merchants
+-- without a row are paid out only when they create a payout themselves.
+CREATE TABLE payout_schedules (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    interval payout_interval NOT NULL DEFAULT 'manual',
+    -- ISO day of the week weekly payouts run on, 1 (Monday) to 7 (Sunday)
+    weekly_anchor SMALLINT CHECK (weekly_anchor BETWEEN 1 AND 7),
+    -- Day the scheduler last paid the balance out
+    last_run_on DATE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT payout_schedules_weekly_anchor_check
+        CHECK (interval <> 'weekly' OR weekly_anchor IS NOT NULL)
+);
+
+-- Created by the scheduler rather than the merchant
+ALTER TABLE payouts ADD COLUMN automatic BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE payouts ADD COLUMN canceled_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX idx_payouts_merchant_created ON payouts(merchant_id, created_at DESC);
+
+CREATE TRIGGER update_payout_schedules_updated_at BEFORE UPDATE ON payout_schedules
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_payout_schedules_residency BEFORE INSERT OR UPDATE ON payout_schedules
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
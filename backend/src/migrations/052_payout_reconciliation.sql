@@ -0,0 +1,13 @@
+-- The balance movements each payout paid out. A journal entry belongs to at most one
+-- payout; entries of a payout that fails or is canceled are released to the next one.
+CREATE TABLE payout_journal_entries (
+    journal_entry_id UUID PRIMARY KEY REFERENCES journal_entries(id),
+    payout_id UUID NOT NULL REFERENCES payouts(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_payout_journal_entries_payout_id ON payout_journal_entries(payout_id);
+
+CREATE TRIGGER enforce_payout_journal_entries_residency BEFORE INSERT OR UPDATE ON payout_journal_entries
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
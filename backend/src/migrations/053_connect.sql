@@ -0,0 +1,54 @@
+-- Connected accounts are merchants owned by a platform merchant. They share the
+-- platform's region so transfers between them are booked in a single transaction.
+ALTER TABLE merchants ADD COLUMN platform_merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_merchants_platform_merchant_id ON merchants(platform_merchant_id)
+    WHERE platform_merchant_id IS NOT NULL;
+
+-- Where a payment's funds are sent once it succeeds; the whole amount when unset
+ALTER TABLE payments ADD COLUMN transfer_destination UUID REFERENCES merchants(id);
+ALTER TABLE payments ADD COLUMN transfer_amount BIGINT CHECK (transfer_amount > 0);
+
+-- Funds moved from a platform's balance to one of its connected accounts
+CREATE TABLE transfers (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    destination UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    amount_reversed BIGINT NOT NULL DEFAULT 0,
+    currency VARCHAR(3) NOT NULL,
+    -- Payment whose funds were routed with `transfer_data`
+    source_payment_id UUID REFERENCES payments(id) ON DELETE SET NULL,
+    description TEXT,
+    metadata JSONB,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CONSTRAINT transfers_amount_reversed_check CHECK (amount_reversed BETWEEN 0 AND amount)
+);
+
+CREATE INDEX idx_transfers_merchant_created ON transfers(merchant_id, created_at DESC);
+CREATE INDEX idx_transfers_destination ON transfers(destination);
+CREATE UNIQUE INDEX idx_transfers_source_payment ON transfers(source_payment_id)
+    WHERE source_payment_id IS NOT NULL;
+
+-- Funds returned from the connected account to the platform, up to the transfer amount
+CREATE TABLE transfer_reversals (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    transfer_id UUID NOT NULL REFERENCES transfers(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    description TEXT,
+    metadata JSONB,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_transfer_reversals_transfer_id ON transfer_reversals(transfer_id);
+
+CREATE TRIGGER update_transfers_updated_at BEFORE UPDATE ON transfers
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_transfers_residency BEFORE INSERT OR UPDATE ON transfers
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_transfer_reversals_residency BEFORE INSERT ON transfer_reversals
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,37 @@
+-- Processing fee pricing. Plans and their rules belong to the merchant directory and
+-- are mirrored to every regional cluster with it, so fees are priced in the same
+-- transaction that books them.
+CREATE TYPE card_region AS ENUM ('domestic', 'international');
+
+CREATE TABLE pricing_plans (
+    code VARCHAR(50) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- A plan without rules charges `processing_fees` from the config on card payments
+INSERT INTO pricing_plans (code, name) VALUES ('standard', 'Standard');
+
+-- Unset criteria match any payment; the rule matching the most criteria applies
+CREATE TABLE pricing_rules (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    plan_code VARCHAR(50) NOT NULL REFERENCES pricing_plans(code) ON DELETE CASCADE,
+    payment_method payment_method,
+    card_brand VARCHAR(20),
+    card_region card_region,
+    percent_bps INTEGER NOT NULL CHECK (percent_bps BETWEEN 0 AND 10000),
+    fixed BIGINT NOT NULL DEFAULT 0 CHECK (fixed >= 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_pricing_rules_plan_code ON pricing_rules(plan_code);
+
+ALTER TABLE merchants ADD COLUMN pricing_plan VARCHAR(50) NOT NULL DEFAULT 'standard' REFERENCES pricing_plans(code);
+
+-- Read from the card details at creation; kept for pricing the payment's fee
+ALTER TABLE payments ADD COLUMN card_brand VARCHAR(20);
+ALTER TABLE payments ADD COLUMN card_country VARCHAR(2);
+
+CREATE TRIGGER update_pricing_plans_updated_at BEFORE UPDATE ON pricing_plans
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,26 @@
+-- Currency scheduled payouts are made in. Available funds in the merchant's other
+-- currencies are converted into it first; without one, each currency is paid out as
+-- it is.
+ALTER TABLE payout_schedules ADD COLUMN settlement_currency VARCHAR(3);
+
+-- Funds exchanged between two of a merchant's balances. Each leg is a journal entry in
+-- its own currency whose metadata names the conversion.
+CREATE TABLE balance_conversions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    from_currency VARCHAR(3) NOT NULL,
+    from_amount BIGINT NOT NULL CHECK (from_amount > 0),
+    to_currency VARCHAR(3) NOT NULL,
+    to_amount BIGINT NOT NULL CHECK (to_amount >= 0),
+    -- Units of to_currency per unit of from_currency
+    rate NUMERIC(24, 12) NOT NULL CHECK (rate > 0),
+    rate_date DATE NOT NULL,
+    rate_source VARCHAR(50) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    CHECK (from_currency <> to_currency)
+);
+
+CREATE INDEX idx_balance_conversions_merchant_created ON balance_conversions(merchant_id, created_at DESC);
+
+CREATE TRIGGER enforce_balance_conversions_residency BEFORE INSERT OR UPDATE ON balance_conversions
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
@@ -0,0 +1,33 @@
+-- Share of each card payment held back from the merchant's available balance, and
+-- for how long. Merchants without a row have nothing held back.
+CREATE TABLE rolling_reserves (
+    merchant_id UUID PRIMARY KEY REFERENCES merchants(id) ON DELETE CASCADE,
+    percent_bps INTEGER NOT NULL CHECK (percent_bps BETWEEN 1 AND 10000),
+    hold_days INTEGER NOT NULL CHECK (hold_days > 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+-- Funds held from one payment, returned to the balance once release_on has passed
+CREATE TABLE reserve_holds (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    payment_id UUID NOT NULL UNIQUE REFERENCES payments(id) ON DELETE CASCADE,
+    currency VARCHAR(3) NOT NULL,
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    release_on TIMESTAMP WITH TIME ZONE NOT NULL,
+    released_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX idx_reserve_holds_due ON reserve_holds(release_on) WHERE released_at IS NULL;
+CREATE INDEX idx_reserve_holds_merchant_id ON reserve_holds(merchant_id, created_at DESC);
+
+CREATE TRIGGER update_rolling_reserves_updated_at BEFORE UPDATE ON rolling_reserves
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_rolling_reserves_residency BEFORE INSERT OR UPDATE ON rolling_reserves
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+
+CREATE TRIGGER enforce_reserve_holds_residency BEFORE INSERT OR UPDATE ON reserve_holds
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
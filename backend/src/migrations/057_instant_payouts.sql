@@ -0,0 +1,5 @@
+CREATE TYPE payout_method AS ENUM ('standard', 'instant');
+
+-- Instant payouts are sent at once for a fee, booked to the ledger alongside the payout
+ALTER TABLE payouts ADD COLUMN method payout_method NOT NULL DEFAULT 'standard';
+ALTER TABLE payouts ADD COLUMN fee BIGINT NOT NULL DEFAULT 0 CHECK (fee >= 0);
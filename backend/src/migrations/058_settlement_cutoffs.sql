@@ -0,0 +1,6 @@
+-- Scheduled payouts run once the cutoff hour has passed on the scheduled day in the
+-- merchant's timezone, and pay out funds that were available by then. Balances below
+-- the minimum roll over to the next scheduled day.
+ALTER TABLE payout_schedules ADD COLUMN cutoff_hour SMALLINT NOT NULL DEFAULT 0 CHECK (cutoff_hour BETWEEN 0 AND 23);
+ALTER TABLE payout_schedules ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'UTC';
+ALTER TABLE payout_schedules ADD COLUMN minimum_payout_amount BIGINT NOT NULL DEFAULT 0 CHECK (minimum_payout_amount >= 0);
@@ -0,0 +1,14 @@
+-- Keys are stored as SHA-256 hashes; the plaintext is returned once, when the key is
+-- issued. The prefix keeps enough of it for merchants to tell their keys apart.
+ALTER TABLE api_keys ADD COLUMN key_hash VARCHAR(64);
+ALTER TABLE api_keys ADD COLUMN key_prefix VARCHAR(16);
+ALTER TABLE api_keys ADD COLUMN revoked_at TIMESTAMP WITH TIME ZONE;
+
+UPDATE api_keys SET key_hash = encode(sha256(convert_to(key, 'UTF8')), 'hex'), key_prefix = left(key, 12);
+
+ALTER TABLE api_keys ALTER COLUMN key_hash SET NOT NULL;
+ALTER TABLE api_keys ALTER COLUMN key_prefix SET NOT NULL;
+ALTER TABLE api_keys ADD CONSTRAINT api_keys_key_hash_key UNIQUE (key_hash);
+ALTER TABLE api_keys DROP COLUMN key;
+
+CREATE INDEX idx_api_keys_merchant_id ON api_keys(merchant_id, created_at DESC);
@@ -0,0 +1,30 @@
+-- Test and live data are kept apart: every resource records the mode of the key
+-- that created it, and list endpoints only return objects in the caller's mode.
+-- Rows from before the split were created for live traffic.
+ALTER TABLE customers ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE customers ALTER COLUMN livemode SET DEFAULT false;
+ALTER TABLE customers DROP CONSTRAINT customers_merchant_id_email_key;
+ALTER TABLE customers ADD CONSTRAINT customers_merchant_id_livemode_email_key UNIQUE (merchant_id, livemode, email);
+
+ALTER TABLE subscriptions ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE subscriptions ALTER COLUMN livemode SET DEFAULT false;
+
+ALTER TABLE quotes ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE quotes ALTER COLUMN livemode SET DEFAULT false;
+
+ALTER TABLE payment_links ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE payment_links ALTER COLUMN livemode SET DEFAULT false;
+
+ALTER TABLE checkout_sessions ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE checkout_sessions ALTER COLUMN livemode SET DEFAULT false;
+
+-- Endpoints only receive events of their own mode
+ALTER TABLE webhooks ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE webhooks ALTER COLUMN livemode SET DEFAULT false;
+
+ALTER TABLE events ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE events ALTER COLUMN livemode SET DEFAULT false;
+
+CREATE INDEX idx_payments_merchant_livemode ON payments(merchant_id, livemode, created_at DESC);
+CREATE INDEX idx_subscriptions_merchant_livemode ON subscriptions(merchant_id, livemode, created_at DESC);
+CREATE INDEX idx_events_merchant_livemode ON events(merchant_id, livemode, created_at DESC);
@@ -0,0 +1,34 @@
+-- Publishable keys (`pk_live_`, `pk_test_`) are safe to ship in browsers and apps:
+-- they can only tokenize cards and confirm payments by their client secret.
+CREATE TYPE api_key_type AS ENUM (
+    'secret',
+    'publishable'
+);
+
+ALTER TABLE api_keys ADD COLUMN key_type api_key_type NOT NULL DEFAULT 'secret';
+
+-- Card details collected client-side, sealed until a payment redeems them. Tokens
+-- are single-use and expire if no payment uses them.
+CREATE TABLE card_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    livemode BOOLEAN NOT NULL,
+    encrypted_card TEXT NOT NULL,
+    brand VARCHAR(20) NOT NULL,
+    last4 VARCHAR(4) NOT NULL,
+    exp_month SMALLINT NOT NULL,
+    exp_year SMALLINT NOT NULL,
+    issuer_country VARCHAR(2),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_card_tokens_merchant_id ON card_tokens(merchant_id);
+
+CREATE TRIGGER update_card_tokens_updated_at BEFORE UPDATE ON card_tokens
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TRIGGER enforce_card_tokens_residency BEFORE INSERT OR UPDATE ON card_tokens
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
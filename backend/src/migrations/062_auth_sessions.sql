@@ -0,0 +1,22 @@
+-- Dashboard sessions behind short-lived JWT access tokens. The refresh token is
+-- kept only as a hash and replaced on every refresh; presenting a replaced one
+-- revokes the session, since it means the token was copied.
+CREATE TABLE auth_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    subject VARCHAR(255) NOT NULL,
+    role VARCHAR(50) NOT NULL,
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    previous_refresh_token_hash VARCHAR(64),
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    last_refreshed_at TIMESTAMP WITH TIME ZONE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_auth_sessions_subject ON auth_sessions(subject);
+CREATE INDEX idx_auth_sessions_previous_refresh_token_hash ON auth_sessions(previous_refresh_token_hash);
+
+CREATE TRIGGER update_auth_sessions_updated_at BEFORE UPDATE ON auth_sessions
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
@@ -0,0 +1,21 @@
+-- TOTP second factor for dashboard sign-in. Enrollments are keyed by the session
+-- subject and only count once a first code has been verified.
+CREATE TABLE mfa_enrollments (
+    subject VARCHAR(255) PRIMARY KEY,
+    merchant_id UUID REFERENCES merchants(id) ON DELETE CASCADE,
+    encrypted_secret TEXT NOT NULL,
+    backup_code_hashes TEXT[] NOT NULL DEFAULT '{}',
+    -- Last 30-second step a code was accepted for, so a code cannot be used twice
+    last_used_step BIGINT,
+    enabled_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_mfa_enrollments_merchant_id ON mfa_enrollments(merchant_id);
+
+CREATE TRIGGER update_mfa_enrollments_updated_at BEFORE UPDATE ON mfa_enrollments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Set by platform admins; sign-in is refused until the user has enrolled
+ALTER TABLE merchants ADD COLUMN mfa_required BOOLEAN NOT NULL DEFAULT false;
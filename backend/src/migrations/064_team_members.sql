@@ -0,0 +1,36 @@
+-- Dashboard team members. Each member has one role; the scopes a role grants are
+-- defined in code (`role_scopes`) so they apply the same way API key scopes do.
+CREATE TYPE team_role AS ENUM (
+    'owner',
+    'admin',
+    'developer',
+    'analyst',
+    'support'
+);
+
+CREATE TABLE team_members (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    role team_role NOT NULL,
+    password_hash TEXT,
+    invite_token_hash VARCHAR(64) UNIQUE,
+    invite_expires_at TIMESTAMP WITH TIME ZONE,
+    invited_by VARCHAR(255),
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    UNIQUE (merchant_id, email)
+);
+
+CREATE INDEX idx_team_members_email ON team_members(email);
+
+CREATE TRIGGER update_team_members_updated_at BEFORE UPDATE ON team_members
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Sessions act for the merchant with their role's scopes. The current access token
+-- is kept as a hash so services can resolve it the way they resolve API keys.
+ALTER TABLE auth_sessions ADD COLUMN team_role team_role;
+ALTER TABLE auth_sessions ADD COLUMN livemode BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE auth_sessions ADD COLUMN access_token_hash VARCHAR(64) UNIQUE;
+ALTER TABLE auth_sessions ADD COLUMN access_expires_at TIMESTAMP WITH TIME ZONE;
@@ -0,0 +1,58 @@
+-- OAuth2 authorization-code grants for third-party apps. Apps are registered by a
+-- merchant (the developer) and authorized by any merchant from its dashboard.
+-- Secrets, codes and tokens are kept only as SHA-256 hashes.
+CREATE TABLE oauth_clients (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    client_id VARCHAR(64) NOT NULL UNIQUE,
+    client_secret_hash VARCHAR(64) NOT NULL,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    redirect_uris TEXT[] NOT NULL,
+    scopes TEXT[] NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_clients_merchant_id ON oauth_clients(merchant_id);
+
+CREATE TRIGGER update_oauth_clients_updated_at BEFORE UPDATE ON oauth_clients
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+
+-- Single-use codes handed to the app's redirect URI after consent
+CREATE TABLE oauth_authorization_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    code_hash VARCHAR(64) NOT NULL UNIQUE,
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    scopes TEXT[] NOT NULL,
+    redirect_uri TEXT NOT NULL,
+    livemode BOOLEAN NOT NULL,
+    code_challenge VARCHAR(128),
+    approved_by VARCHAR(255) NOT NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- One row per grant. The access token is replaced whenever the refresh token is
+-- used; revoking either ends the grant.
+CREATE TABLE oauth_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    client_id UUID NOT NULL REFERENCES oauth_clients(id) ON DELETE CASCADE,
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    authorization_code_id UUID REFERENCES oauth_authorization_codes(id) ON DELETE SET NULL,
+    scopes TEXT[] NOT NULL,
+    livemode BOOLEAN NOT NULL,
+    access_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    access_expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    refresh_token_hash VARCHAR(64) NOT NULL UNIQUE,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_oauth_tokens_client_merchant ON oauth_tokens(client_id, merchant_id);
+
+CREATE TRIGGER update_oauth_tokens_updated_at BEFORE UPDATE ON oauth_tokens
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
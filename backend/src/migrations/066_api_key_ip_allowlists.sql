@@ -0,0 +1,16 @@
+-- Optional per-key allowlist of CIDR ranges; a key without one works from anywhere
+ALTER TABLE api_keys ADD COLUMN allowed_ips TEXT[];
+
+-- Security log of requests refused because they came from outside the key's allowlist
+CREATE TABLE api_key_ip_violations (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    api_key_id UUID NOT NULL REFERENCES api_keys(id) ON DELETE CASCADE,
+    -- Unset when the client address could not be determined
+    ip_address TEXT,
+    method VARCHAR(10) NOT NULL,
+    path TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_api_key_ip_violations_merchant ON api_key_ip_violations(merchant_id, created_at DESC);
@@ -0,0 +1,4 @@
+-- Merchants that opt in must sign every secret-key request with an HMAC under this
+-- secret, sealed with the request signing encryption key. Unset when signing is off.
+ALTER TABLE merchants ADD COLUMN request_signing_secret TEXT;
+ALTER TABLE merchants ADD COLUMN request_signing_enabled_at TIMESTAMP WITH TIME ZONE;
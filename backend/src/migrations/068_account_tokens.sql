@@ -0,0 +1,26 @@
+-- Single-use tokens emailed to prove control of an address: to reset a team
+-- member's password, or to verify a member's or merchant's email. Kept as hashes.
+CREATE TYPE account_token_purpose AS ENUM (
+    'password_reset',
+    'email_verification'
+);
+
+CREATE TABLE account_tokens (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    purpose account_token_purpose NOT NULL,
+    -- `team_member:<id>`, or `merchant:<id>` for a merchant's own email
+    subject VARCHAR(255) NOT NULL,
+    email VARCHAR(255) NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_account_tokens_subject ON account_tokens(subject, purpose, created_at DESC);
+
+-- Accepting an invitation proves the address, since the invitation was emailed to it
+ALTER TABLE team_members ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
+UPDATE team_members SET email_verified_at = accepted_at WHERE accepted_at IS NOT NULL;
+
+ALTER TABLE merchants ADD COLUMN email_verified_at TIMESTAMP WITH TIME ZONE;
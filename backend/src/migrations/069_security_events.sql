@@ -0,0 +1,31 @@
+-- Security log: sign-ins, API key and MFA changes, and refused requests, queryable by
+-- the merchant and notified by webhook when suspicious
+CREATE TYPE security_event_type AS ENUM (
+    'login_succeeded',
+    'login_failed',
+    'api_key_created',
+    'api_key_revoked',
+    'mfa_enabled',
+    'mfa_backup_code_used',
+    'mfa_policy_updated',
+    'ip_not_allowed'
+);
+
+CREATE TABLE security_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    merchant_id UUID NOT NULL REFERENCES merchants(id) ON DELETE CASCADE,
+    event_type security_event_type NOT NULL,
+    -- Whose account the event concerns: a team member or merchant session subject
+    subject VARCHAR(255),
+    -- The key created, revoked or refused; not a foreign key so the log outlives the key
+    api_key_id UUID,
+    -- Unset when the client address is unknown or the change was made server-side
+    ip_address TEXT,
+    details JSONB NOT NULL DEFAULT '{}',
+    suspicious BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_security_events_merchant ON security_events(merchant_id, created_at DESC);
+CREATE INDEX idx_security_events_subject ON security_events(subject, event_type, created_at DESC)
+    WHERE subject IS NOT NULL;
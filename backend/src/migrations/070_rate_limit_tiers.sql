@@ -0,0 +1,8 @@
+-- Rate-limit quota tier; each tier is a multiple of the configured base quotas
+CREATE TYPE rate_limit_tier AS ENUM (
+    'standard',
+    'growth',
+    'enterprise'
+);
+
+ALTER TABLE merchants ADD COLUMN rate_limit_tier rate_limit_tier NOT NULL DEFAULT 'standard';
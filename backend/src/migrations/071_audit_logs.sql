@@ -0,0 +1,42 @@
+-- Append-only record of every mutating API call: who made it, from where, and what
+-- the resource looked like before and after
+CREATE TYPE audit_actor_type AS ENUM ('api_key', 'oauth_app', 'session', 'admin');
+
+CREATE TABLE audit_logs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    -- Not a foreign key: the trail outlives the merchant, and deleting rows is refused.
+    -- Unset for platform-level admin calls
+    merchant_id UUID,
+    actor_type audit_actor_type NOT NULL,
+    -- API key, app grant or session id, or the admin's subject
+    actor_id VARCHAR(255) NOT NULL,
+    method VARCHAR(10) NOT NULL,
+    -- The matched route, e.g. /api/v1/payments/{payment_id}/capture
+    route TEXT NOT NULL,
+    path TEXT NOT NULL,
+    resource_type VARCHAR(100) NOT NULL,
+    resource_id VARCHAR(255),
+    status_code SMALLINT NOT NULL,
+    ip_address TEXT,
+    -- Secrets are redacted from both snapshots
+    before JSONB,
+    after JSONB,
+    -- Top-level fields that differ between the snapshots, as {"field": {"before", "after"}}
+    changes JSONB,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_logs_merchant ON audit_logs(merchant_id, created_at DESC);
+CREATE INDEX idx_audit_logs_resource ON audit_logs(merchant_id, resource_type, resource_id, created_at DESC);
+CREATE INDEX idx_audit_logs_actor ON audit_logs(merchant_id, actor_id, created_at DESC);
+
+CREATE OR REPLACE FUNCTION refuse_audit_log_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_logs is append-only';
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER audit_logs_append_only
+    BEFORE UPDATE OR DELETE ON audit_logs
+    FOR EACH ROW EXECUTE FUNCTION refuse_audit_log_change();
@@ -0,0 +1,11 @@
+-- List endpoints page newest first by (created_at, id) within the caller's mode.
+-- These indexes cover both the filter and the sort, so each page is a range scan
+-- however deep into the list it starts.
+CREATE INDEX idx_payments_merchant_keyset ON payments(merchant_id, livemode, created_at DESC, id DESC);
+CREATE INDEX idx_customers_merchant_keyset ON customers(merchant_id, livemode, created_at DESC, id DESC);
+CREATE INDEX idx_invoices_merchant_keyset ON invoices(merchant_id, livemode, created_at DESC, id DESC);
+CREATE INDEX idx_events_merchant_keyset ON events(merchant_id, livemode, created_at DESC, id DESC);
+
+-- Superseded by the keyset indexes above
+DROP INDEX idx_payments_merchant_livemode;
+DROP INDEX idx_events_merchant_livemode;
@@ -0,0 +1,21 @@
+-- Every update to a payment, customer or subscription moves its version forward,
+-- whatever made it. Clients send back the version they read and writes made
+-- against an older one are refused, so concurrent edits cannot overwrite each other.
+ALTER TABLE payments ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE customers ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+ALTER TABLE subscriptions ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
+
+CREATE OR REPLACE FUNCTION bump_row_version()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.version = OLD.version + 1;
+    RETURN NEW;
+END;
+$$ language 'plpgsql';
+
+CREATE TRIGGER bump_payments_version BEFORE UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
+CREATE TRIGGER bump_customers_version BEFORE UPDATE ON customers
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
+CREATE TRIGGER bump_subscriptions_version BEFORE UPDATE ON subscriptions
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
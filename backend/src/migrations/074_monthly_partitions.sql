@@ -0,0 +1,187 @@
+-- Payments and events are append-heavy and read newest first, so both are
+-- partitioned by month of created_at. The existing tables become a single
+-- partition holding everything created up to the end of the current month; the
+-- maintenance job creates the months after it ahead of time and moves partitions
+-- past their retention into the archive schema.
+--
+-- Unique constraints on a partitioned table must include the partition key, so
+-- nothing can reference payments(id) or events(id) any more. Those foreign keys
+-- are dropped: rows that point at a payment or event keep the id, and lookups
+-- through it find nothing once the partition has been archived.
+ALTER TABLE payment_line_items DROP CONSTRAINT payment_line_items_payment_id_fkey;
+ALTER TABLE checkout_sessions DROP CONSTRAINT checkout_sessions_payment_id_fkey;
+ALTER TABLE sca_exemption_attempts DROP CONSTRAINT sca_exemption_attempts_payment_id_fkey;
+ALTER TABLE mit_agreements DROP CONSTRAINT mit_agreements_initial_payment_id_fkey;
+ALTER TABLE settlement_batch_items DROP CONSTRAINT settlement_batch_items_payment_id_fkey;
+ALTER TABLE crypto_addresses DROP CONSTRAINT crypto_addresses_payment_id_fkey;
+ALTER TABLE crypto_conversions DROP CONSTRAINT crypto_conversions_payment_id_fkey;
+ALTER TABLE customer_balance_transactions DROP CONSTRAINT customer_balance_transactions_payment_id_fkey;
+ALTER TABLE crypto_refunds DROP CONSTRAINT crypto_refunds_payment_id_fkey;
+ALTER TABLE fraud_labels DROP CONSTRAINT fraud_labels_payment_id_fkey;
+ALTER TABLE fraud_blocklist DROP CONSTRAINT fraud_blocklist_source_payment_id_fkey;
+ALTER TABLE transfers DROP CONSTRAINT transfers_source_payment_id_fkey;
+ALTER TABLE reserve_holds DROP CONSTRAINT reserve_holds_payment_id_fkey;
+ALTER TABLE webhook_deliveries DROP CONSTRAINT webhook_deliveries_event_id_fkey;
+
+UPDATE payments SET created_at = NOW() WHERE created_at IS NULL;
+ALTER TABLE payments ALTER COLUMN created_at SET NOT NULL;
+UPDATE events SET created_at = NOW() WHERE created_at IS NULL;
+ALTER TABLE events ALTER COLUMN created_at SET NOT NULL;
+
+ALTER TABLE payments RENAME TO payments_legacy;
+ALTER TABLE events RENAME TO events_legacy;
+
+-- The partitioned tables carry the triggers and indexes from now on; the legacy
+-- tables' own would fire or be maintained twice once they are attached
+DROP TRIGGER update_payments_updated_at ON payments_legacy;
+DROP TRIGGER enforce_payments_residency ON payments_legacy;
+DROP TRIGGER bump_payments_version ON payments_legacy;
+
+DROP INDEX idx_payments_merchant_id;
+DROP INDEX idx_payments_customer_id;
+DROP INDEX idx_payments_status;
+DROP INDEX idx_payments_created_at;
+DROP INDEX idx_payments_donations;
+DROP INDEX idx_payments_unbatched;
+DROP INDEX idx_payments_stripe_charge_id;
+DROP INDEX idx_payments_test_succeeded;
+DROP INDEX idx_payments_expiring;
+DROP INDEX idx_payments_invoice_id;
+DROP INDEX idx_payments_review;
+DROP INDEX idx_payments_risk_score;
+DROP INDEX idx_payments_merchant_keyset;
+DROP INDEX idx_events_merchant_id;
+DROP INDEX idx_events_type;
+DROP INDEX idx_events_created_at;
+DROP INDEX idx_events_merchant_id_created_at;
+DROP INDEX idx_events_merchant_id_type;
+DROP INDEX idx_events_merchant_keyset;
+
+CREATE TABLE payments (LIKE payments_legacy INCLUDING DEFAULTS INCLUDING CONSTRAINTS INCLUDING STORAGE INCLUDING COMMENTS)
+    PARTITION BY RANGE (created_at);
+ALTER TABLE payments ADD PRIMARY KEY (id, created_at);
+
+CREATE TABLE events (LIKE events_legacy INCLUDING DEFAULTS INCLUDING CONSTRAINTS INCLUDING STORAGE INCLUDING COMMENTS)
+    PARTITION BY RANGE (created_at);
+ALTER TABLE events ADD PRIMARY KEY (id, created_at);
+
+-- Foreign keys from payments and events to other tables still hold
+DO $$
+DECLARE
+    fk RECORD;
+BEGIN
+    FOR fk IN
+        SELECT conrelid::regclass AS legacy, conname, pg_get_constraintdef(oid) AS definition
+        FROM pg_constraint
+        WHERE conrelid IN ('payments_legacy'::regclass, 'events_legacy'::regclass) AND contype = 'f'
+    LOOP
+        EXECUTE format(
+            'ALTER TABLE %I ADD CONSTRAINT %I %s',
+            replace(fk.legacy::TEXT, '_legacy', ''), fk.conname, fk.definition
+        );
+    END LOOP;
+END $$;
+
+CREATE TRIGGER update_payments_updated_at BEFORE UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
+CREATE TRIGGER enforce_payments_residency BEFORE INSERT OR UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION enforce_data_residency();
+CREATE TRIGGER bump_payments_version BEFORE UPDATE ON payments
+    FOR EACH ROW EXECUTE FUNCTION bump_row_version();
+
+-- Every partition of the table with its bounds; the legacy partition starts at NULL
+CREATE OR REPLACE FUNCTION table_partitions(parent REGCLASS)
+RETURNS TABLE (partition_name TEXT, starts_at TIMESTAMPTZ, ends_at TIMESTAMPTZ) AS $$
+    SELECT c.relname::TEXT,
+        substring(pg_get_expr(c.relpartbound, c.oid) FROM 'FROM \(''([^'']+)''\)')::TIMESTAMPTZ,
+        substring(pg_get_expr(c.relpartbound, c.oid) FROM 'TO \(''([^'']+)''\)')::TIMESTAMPTZ
+    FROM pg_inherits i
+    JOIN pg_class c ON c.oid = i.inhrelid
+    WHERE i.inhparent = parent
+$$ language 'sql' STABLE;
+
+-- Creates monthly partitions, named like payments_p202611, from the end of the
+-- newest one through `months_ahead` months after the current UTC month. Returns
+-- how many were created.
+CREATE OR REPLACE FUNCTION create_monthly_partitions(parent REGCLASS, months_ahead INTEGER)
+RETURNS INTEGER AS $$
+DECLARE
+    month_start TIMESTAMPTZ;
+    created INTEGER := 0;
+BEGIN
+    SELECT MAX(ends_at) INTO month_start FROM table_partitions(parent);
+    month_start := COALESCE(month_start, date_trunc('month', NOW(), 'UTC'));
+
+    WHILE month_start < date_trunc('month', NOW(), 'UTC') + make_interval(months => months_ahead + 1) LOOP
+        EXECUTE format(
+            'CREATE TABLE %I PARTITION OF %s FOR VALUES FROM (%L) TO (%L)',
+            parent::TEXT || '_p' || to_char(month_start AT TIME ZONE 'UTC', 'YYYYMM'),
+            parent,
+            month_start,
+            month_start + INTERVAL '1 month'
+        );
+        month_start := month_start + INTERVAL '1 month';
+        created := created + 1;
+    END LOOP;
+
+    RETURN created;
+END;
+$$ language 'plpgsql';
+
+-- Detaches every partition holding only rows created before `cutoff` and moves it
+-- into the archive schema, where it stays queryable but out of every list, report
+-- and index scan. Returns the partitions moved.
+CREATE SCHEMA IF NOT EXISTS archive;
+
+CREATE OR REPLACE FUNCTION archive_partitions_before(parent REGCLASS, cutoff TIMESTAMPTZ)
+RETURNS SETOF TEXT AS $$
+DECLARE
+    expired RECORD;
+BEGIN
+    FOR expired IN
+        SELECT partition_name FROM table_partitions(parent) WHERE ends_at <= cutoff ORDER BY ends_at
+    LOOP
+        EXECUTE format('ALTER TABLE %s DETACH PARTITION %I', parent, expired.partition_name);
+        EXECUTE format('ALTER TABLE %I SET SCHEMA archive', expired.partition_name);
+        RETURN NEXT expired.partition_name;
+    END LOOP;
+END;
+$$ language 'plpgsql';
+
+DO $$
+DECLARE
+    next_month TIMESTAMPTZ := date_trunc('month', NOW(), 'UTC') + INTERVAL '1 month';
+BEGIN
+    EXECUTE format('ALTER TABLE payments ATTACH PARTITION payments_legacy FOR VALUES FROM (MINVALUE) TO (%L)', next_month);
+    EXECUTE format('ALTER TABLE events ATTACH PARTITION events_legacy FOR VALUES FROM (MINVALUE) TO (%L)', next_month);
+END $$;
+
+SELECT create_monthly_partitions('payments', 3);
+SELECT create_monthly_partitions('events', 3);
+
+-- Indexes on the parents are created on every partition, present and future
+CREATE INDEX idx_payments_merchant_id ON payments(merchant_id);
+CREATE INDEX idx_payments_customer_id ON payments(customer_id);
+CREATE INDEX idx_payments_status ON payments(status);
+CREATE INDEX idx_payments_created_at ON payments(created_at);
+CREATE INDEX idx_payments_id ON payments(id);
+CREATE INDEX idx_payments_donations ON payments(merchant_id, created_at) WHERE is_donation;
+CREATE INDEX idx_payments_unbatched ON payments(merchant_id, created_at)
+    WHERE settlement_batch_id IS NULL AND status = 'succeeded';
+-- No longer unique: charge ids are Stripe's, and unique indexes must include created_at
+CREATE INDEX idx_payments_stripe_charge_id ON payments(stripe_charge_id) WHERE stripe_charge_id IS NOT NULL;
+CREATE INDEX idx_payments_test_succeeded ON payments(merchant_id) WHERE livemode = false AND status = 'succeeded';
+CREATE INDEX idx_payments_expiring ON payments(expires_at)
+    WHERE expires_at IS NOT NULL AND status IN ('pending', 'requires_action');
+CREATE INDEX idx_payments_invoice_id ON payments(invoice_id) WHERE invoice_id IS NOT NULL;
+CREATE INDEX idx_payments_review ON payments(merchant_id, created_at DESC) WHERE review_reason IS NOT NULL;
+CREATE INDEX idx_payments_risk_score ON payments(merchant_id, risk_score) WHERE risk_score IS NOT NULL;
+CREATE INDEX idx_payments_merchant_keyset ON payments(merchant_id, livemode, created_at DESC, id DESC);
+
+CREATE INDEX idx_events_merchant_id ON events(merchant_id);
+CREATE INDEX idx_events_type ON events(type);
+CREATE INDEX idx_events_created_at ON events(created_at);
+CREATE INDEX idx_events_id ON events(id);
+CREATE INDEX idx_events_merchant_id_created_at ON events(merchant_id, created_at DESC);
+CREATE INDEX idx_events_merchant_id_type ON events(merchant_id, type, created_at DESC);
+CREATE INDEX idx_events_merchant_keyset ON events(merchant_id, livemode, created_at DESC, id DESC);
@@ -0,0 +1,12 @@
+-- Search matches text with to_tsvector over these exact expressions, so queries must
+-- repeat them to use the indexes. The 'simple' configuration neither stems nor drops
+-- stop words: descriptions and names are as often order numbers and brands as prose.
+CREATE INDEX idx_payments_description_search ON payments
+    USING GIN (to_tsvector('simple', COALESCE(description, '')));
+CREATE INDEX idx_customers_search ON customers
+    USING GIN (to_tsvector('simple', COALESCE(name, '') || ' ' || email || ' ' || COALESCE(description, '')));
+
+-- jsonb_path_ops indexes only serve @>, which is all metadata search uses, and are
+-- a fraction of the size of the default operator class
+CREATE INDEX idx_payments_metadata ON payments USING GIN (metadata jsonb_path_ops);
+CREATE INDEX idx_customers_metadata ON customers USING GIN (metadata jsonb_path_ops);
@@ -0,0 +1,28 @@
+-- Data keys sealing customer emails and phone numbers and merchant bank account
+-- details, each wrapped by a master key held in the KMS. Keys are never deleted:
+-- values sealed with one stay readable until re-encryption moves them to a newer key.
+CREATE TABLE pii_data_keys (
+    id SERIAL PRIMARY KEY,
+    master_key_id VARCHAR(255) NOT NULL,
+    wrapped_key TEXT NOT NULL,
+    -- Instances seal with the newest key past this, once all have loaded it
+    activates_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+-- Sealed values are several times longer than the plaintext. Existing rows stay
+-- plaintext until the re-encryption job reaches them.
+ALTER TABLE customers ALTER COLUMN email TYPE TEXT, ALTER COLUMN phone TYPE TEXT;
+ALTER TABLE merchant_bank_accounts ALTER COLUMN account_holder_name TYPE TEXT, ALTER COLUMN routing_number TYPE TEXT;
+
+-- A keyed hash of the lowercased email, for lookups and uniqueness now that the email
+-- itself is sealed. The re-encryption job fills it in for existing customers.
+ALTER TABLE customers ADD COLUMN email_hash VARCHAR(64);
+ALTER TABLE customers DROP CONSTRAINT customers_merchant_id_livemode_email_key;
+CREATE UNIQUE INDEX idx_customers_email_hash ON customers(merchant_id, livemode, email_hash);
+DROP INDEX idx_customers_email;
+
+-- Sealed emails have nothing to search
+DROP INDEX idx_customers_search;
+CREATE INDEX idx_customers_search ON customers
+    USING GIN (to_tsvector('simple', COALESCE(name, '') || ' ' || COALESCE(description, '')));
@@ -0,0 +1,14 @@
+-- Payments and events exported to cold storage and removed from their tables. Each
+-- row says which storage object the record went to, so a request for it can be
+-- answered with where to retrieve it rather than a plain 404.
+CREATE TABLE archived_objects (
+    object VARCHAR(20) NOT NULL,
+    id UUID NOT NULL,
+    merchant_id UUID NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    archived_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    archive_key TEXT NOT NULL,
+    PRIMARY KEY (object, id)
+);
+
+CREATE INDEX idx_archived_objects_merchant_id ON archived_objects(merchant_id, object, created_at DESC);
@@ -0,0 +1,3 @@
+-- Sessions started with an API key keep the key's allowlist of CIDR ranges, so exchanging
+-- the key for a session does not lift it
+ALTER TABLE auth_sessions ADD COLUMN allowed_ips TEXT[];
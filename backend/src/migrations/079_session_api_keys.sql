@@ -0,0 +1,5 @@
+-- Sessions a merchant started by exchanging a secret key end with that key, whether it
+-- is revoked or rolled
+ALTER TABLE auth_sessions ADD COLUMN api_key_id UUID REFERENCES api_keys(id) ON DELETE CASCADE;
+
+CREATE INDEX idx_auth_sessions_api_key_id ON auth_sessions(api_key_id);
@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "kyc_status", rename_all = "snake_case")]
+pub enum KycStatus {
+    Unverified,
+    Pending,
+    Verified,
+    Rejected,
+}
+
+/// One go-live requirement and whether the merchant meets it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoLiveRequirement {
+    pub id: String,
+    pub description: String,
+    pub satisfied: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoLiveChecklist {
+    /// True once every requirement is satisfied; live keys can then be issued
+    pub ready: bool,
+    pub requirements: Vec<GoLiveRequirement>,
+}
+
+/// Secret keys stay on the merchant's servers; publishable keys are embedded in
+/// browsers and apps and can only tokenize cards and confirm payments.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "api_key_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyType {
+    #[default]
+    Secret,
+    Publishable,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateLiveKeyRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+/// A newly issued key. The key itself is only ever returned here.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveApiKeyResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub key: String,
+    #[serde(rename = "type")]
+    pub key_type: ApiKeyType,
+    pub livemode: bool,
+    pub allowed_ips: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// Live keys are only issued once every go-live requirement is met
+    #[serde(default)]
+    pub livemode: bool,
+    #[serde(default, rename = "type")]
+    pub key_type: ApiKeyType,
+    /// Scopes such as `payments:write`, each of which the calling key must hold
+    /// itself. The key has full access when omitted. Publishable keys have a fixed
+    /// set and take none.
+    #[validate(length(min = 1, max = 50))]
+    pub scopes: Option<Vec<String>>,
+    /// CIDR ranges such as `203.0.113.0/24` the key may be used from; anywhere when omitted
+    #[validate(length(min = 1, max = 100))]
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+/// An API key as listed. Only the prefix of the key is kept; the rest cannot be recovered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// The first characters of the key, e.g. `sk_live_AbCd`
+    pub key_prefix: String,
+    #[serde(rename = "type")]
+    pub key_type: ApiKeyType,
+    pub livemode: bool,
+    /// The resources and verbs the key may use; full access when absent
+    pub scopes: Option<Vec<String>>,
+    /// CIDR ranges the key may be used from; anywhere when absent
+    pub allowed_ips: Option<Vec<String>>,
+    pub active: Option<bool>,
+    /// Updated at most once a minute
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyListResponse {
+    pub data: Vec<ApiKey>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RollApiKeyRequest {
+    /// Hours the old key keeps working after the roll; it stops immediately when omitted
+    #[validate(range(min = 0, max = 168))]
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateApiKeyAllowlistRequest {
+    /// Replaces the allowlist; `null` lets the key be used from anywhere again
+    #[validate(length(min = 1, max = 100))]
+    pub allowed_ips: Option<Vec<String>>,
+}
+
+/// A request refused because it came from outside its key's allowlist.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ApiKeyIpViolation {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub api_key_id: Uuid,
+    /// Unset when the client address could not be determined
+    pub ip_address: Option<String>,
+    pub method: String,
+    pub path: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKeyIpViolationListQuery {
+    pub api_key_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ApiKeyIpViolationListResponse {
+    pub data: Vec<ApiKeyIpViolation>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+/// Payload of the configuration-change events (`api_key.*`, `webhook_endpoint.*`,
+/// `crypto_wallet.*`, `crypto_settlement.*`, `trusted_source.*`, `request_signing.*`). Secrets and key values are never included.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConfigurationChangeData {
+    /// `api_key`, `webhook_endpoint`, `crypto_wallet`, `crypto_settlement`, `trusted_source` or `request_signing`
+    pub object: String,
+    pub id: Uuid,
+    /// Fields the change set; empty for creations and deletions
+    pub changed_fields: Vec<String>,
+    /// The object this one replaced, for key rolls and wallet re-registrations
+    pub previous_id: Option<Uuid>,
+    /// The API key that made the change
+    pub actor_api_key_id: Option<Uuid>,
+}
+
+impl ConfigurationChangeData {
+    pub fn new(object: &str, id: Uuid, changed_fields: Vec<String>) -> Self {
+        Self {
+            object: object.to_string(),
+            id,
+            changed_fields,
+            previous_id: None,
+            actor_api_key_id: None,
+        }
+    }
+}
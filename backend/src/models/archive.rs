@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+/// What remains in the database of a payment or event exported to cold storage.
+/// Returned as a 410 to requests for the object.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedObject {
+    pub object: String,
+    pub id: Uuid,
+    pub archived_at: DateTime<Utc>,
+    /// The storage object holding it, among many others
+    pub archive_key: String,
+}
+
+impl ArchivedObject {
+    pub fn retrieval_hint(&self) -> String {
+        format!(
+            "Contact support with archive key {} to have the {} retrieved from cold storage",
+            self.archive_key, self.object,
+        )
+    }
+}
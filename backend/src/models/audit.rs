@@ -0,0 +1,76 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "audit_actor_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AuditActorType {
+    ApiKey,
+    /// An OAuth app acting on the merchant's behalf
+    OauthApp,
+    /// A dashboard user
+    Session,
+    /// Platform staff
+    Admin,
+}
+
+/// One mutating API call. `before` is the resource as the previous audited call left
+/// it, so it is unset for creations and for resources last changed before auditing.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub merchant_id: Option<Uuid>,
+    pub actor_type: AuditActorType,
+    pub actor_id: String,
+    pub method: String,
+    /// The matched route, e.g. `/api/v1/payments/{payment_id}/capture`
+    pub route: String,
+    pub path: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub status_code: i16,
+    pub ip_address: Option<String>,
+    pub before: Option<serde_json::Value>,
+    /// The response, for calls that succeeded
+    pub after: Option<serde_json::Value>,
+    /// Top-level fields that changed, as `{"field": {"before": ..., "after": ...}}`
+    pub changes: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewAuditLog {
+    pub merchant_id: Option<Uuid>,
+    pub actor_type: AuditActorType,
+    pub actor_id: String,
+    pub method: String,
+    pub route: String,
+    pub path: String,
+    pub resource_type: String,
+    pub resource_id: Option<String>,
+    pub status_code: i16,
+    pub ip_address: Option<String>,
+    pub after: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuditLogListQuery {
+    /// e.g. `payments`
+    pub resource_type: Option<String>,
+    pub resource_id: Option<String>,
+    pub actor_type: Option<AuditActorType>,
+    pub actor_id: Option<String>,
+    pub created_gte: Option<DateTime<Utc>>,
+    pub created_lte: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuditLogListResponse {
+    pub data: Vec<AuditLog>,
+    pub has_more: bool,
+    pub url: String,
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use super::TeamRole;
+
+/// A signed-in dashboard session. Access tokens name it in their `sid` claim and
+/// stop working once it is revoked, even before they expire.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AuthSession {
+    pub id: Uuid,
+    pub subject: String,
+    pub role: String,
+    pub merchant_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub refresh_token_hash: String,
+    #[serde(skip_serializing)]
+    pub previous_refresh_token_hash: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub last_refreshed_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Role the session acts with; merchants signing in with their secret key are owners
+    pub team_role: Option<TeamRole>,
+    pub livemode: bool,
+    #[serde(skip_serializing)]
+    pub access_token_hash: Option<String>,
+    pub access_expires_at: Option<DateTime<Utc>>,
+}
+
+/// Issued when a session starts and on every refresh. The refresh token replaces
+/// the one presented and is not shown again.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+    pub refresh_token: String,
+    pub refresh_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct RefreshTokenRequest {
+    #[validate(length(min = 1))]
+    pub refresh_token: String,
+}
+
+/// Team members sign in with their email and password; merchants can instead send
+/// no credentials in the body and authenticate with their secret key.
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
+pub struct CreateSessionRequest {
+    #[validate(email)]
+    pub email: Option<String>,
+    pub password: Option<String>,
+    /// Needed when the email belongs to members of several merchants
+    pub merchant_id: Option<Uuid>,
+    /// Whether the session works with live data. Ignored for secret keys, which have a mode.
+    #[serde(default)]
+    pub livemode: bool,
+    /// A current TOTP code or an unused backup code, required once MFA is enabled
+    pub mfa_code: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct MfaEnrollment {
+    pub subject: String,
+    pub merchant_id: Option<Uuid>,
+    pub encrypted_secret: String,
+    pub backup_code_hashes: Vec<String>,
+    pub last_used_step: Option<i64>,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Shown once. MFA is not enabled until a code from the authenticator app is
+/// verified.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MfaSetupResponse {
+    /// Base32 secret, for apps that cannot scan the QR code
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub provisioning_uri: String,
+    /// Single-use codes accepted in place of a TOTP code
+    pub backup_codes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct VerifyMfaRequest {
+    #[validate(length(min = 6, max = 6))]
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MfaStatus {
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub backup_codes_remaining: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MfaPolicy {
+    pub merchant_id: Uuid,
+    /// Dashboard users of the merchant must enroll before they can sign in
+    pub mfa_required: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdateMfaPolicyRequest {
+    pub mfa_required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "account_token_purpose", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum AccountTokenPurpose {
+    PasswordReset,
+    EmailVerification,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AccountToken {
+    pub id: Uuid,
+    pub purpose: AccountTokenPurpose,
+    pub subject: String,
+    pub email: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Always answered with 202, whether or not the email belongs to a team member.
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+    /// Needed when the email belongs to members of several merchants
+    pub merchant_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct ResetPasswordRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 12, max = 128))]
+    pub password: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EmailVerificationStatus {
+    pub email: String,
+    pub verified_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,435 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use super::payment::{Address, CreateLineItem};
+use crate::errors::DefiantError;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PaymentLink {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub field_collection: serde_json::Value,
+    pub shipping_options: serde_json::Value,
+    pub active: bool,
+    pub metadata: Option<serde_json::Value>,
+    pub inventory_total: Option<i32>,
+    pub inventory_remaining: Option<i32>,
+    pub custom_amount: Option<serde_json::Value>,
+    pub is_donation: bool,
+    /// Created with a live key rather than a test key
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl PaymentLink {
+    pub fn is_sold_out(&self) -> bool {
+        matches!(self.inventory_remaining, Some(remaining) if remaining <= 0)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentLinkResponse {
+    #[serde(flatten)]
+    pub link: PaymentLink,
+    pub sold_out: bool,
+}
+
+impl From<PaymentLink> for PaymentLinkResponse {
+    fn from(link: PaymentLink) -> Self {
+        Self { sold_out: link.is_sold_out(), link }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CheckoutSession {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub payment_link_id: Option<Uuid>,
+    pub customer_id: Option<Uuid>,
+    pub payment_id: Option<Uuid>,
+    pub status: CheckoutSessionStatus,
+    pub amount: i64,
+    pub currency: String,
+    pub field_collection: serde_json::Value,
+    pub collected_fields: Option<serde_json::Value>,
+    pub shipping_options: serde_json::Value,
+    pub shipping_option: Option<serde_json::Value>,
+    pub shipping_amount: i64,
+    pub success_url: Option<String>,
+    pub cancel_url: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub custom_amount: Option<serde_json::Value>,
+    pub is_donation: bool,
+    pub line_items: serde_json::Value,
+    pub expires_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Inherited from the payment link, or the key that created the session
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "checkout_session_status", rename_all = "snake_case")]
+pub enum CheckoutSessionStatus {
+    Open,
+    Complete,
+    Expired,
+}
+
+/// Which fields hosted checkout asks the customer for. Email is always collected.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, Validate)]
+pub struct FieldCollection {
+    #[serde(default)]
+    pub phone: FieldRequirement,
+    
+    #[serde(default)]
+    pub shipping_address: FieldRequirement,
+    
+    #[serde(default)]
+    #[validate(length(max = 5, message = "At most 5 consent checkboxes are allowed"))]
+    #[validate]
+    pub consents: Vec<ConsentField>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FieldRequirement {
+    #[default]
+    Hidden,
+    Optional,
+    Required,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ConsentField {
+    #[validate(length(min = 1, max = 50))]
+    pub key: String,
+    
+    #[validate(length(min = 1, max = 1000))]
+    pub label: String,
+    
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// A merchant-defined shipping rate offered at checkout.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ShippingOption {
+    #[validate(length(min = 1, max = 50))]
+    pub id: String,
+    
+    #[validate(length(min = 1, max = 100))]
+    pub display_name: String,
+    
+    #[validate(range(min = 0))]
+    pub amount: i64,
+    
+    #[validate(length(max = 100))]
+    pub delivery_estimate: Option<String>,
+}
+
+impl ShippingOption {
+    pub fn list_from_value(value: &serde_json::Value) -> Vec<Self> {
+        serde_json::from_value(value.clone()).unwrap_or_default()
+    }
+    
+    pub fn ensure_unique(options: &[ShippingOption]) -> Result<(), DefiantError> {
+        if options.len() > 10 {
+            return Err(DefiantError::ValidationError("shipping_options: at most 10 options are allowed".into()));
+        }
+        
+        for (i, option) in options.iter().enumerate() {
+            if options[..i].iter().any(|o| o.id == option.id) {
+                return Err(DefiantError::ValidationError(format!(
+                    "shipping_options: duplicate id '{}'",
+                    option.id
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Lets the customer choose the amount, within bounds, optionally offering presets.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CustomAmount {
+    #[validate(range(min = 50, message = "Minimum must be at least $0.50"))]
+    pub minimum: Option<i64>,
+    
+    #[validate(range(min = 50))]
+    pub maximum: Option<i64>,
+    
+    #[serde(default)]
+    #[validate(length(max = 4, message = "At most 4 suggested amounts are allowed"))]
+    pub suggested: Vec<i64>,
+}
+
+impl CustomAmount {
+    /// Platform floor applied when the merchant sets no minimum.
+    pub const PLATFORM_MINIMUM: i64 = 50;
+    
+    pub fn from_value(value: Option<&serde_json::Value>) -> Option<Self> {
+        value.and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+    
+    fn minimum_amount(&self) -> i64 {
+        self.minimum.unwrap_or(Self::PLATFORM_MINIMUM).max(Self::PLATFORM_MINIMUM)
+    }
+    
+    /// Validates the configuration itself: bounds are ordered and every preset fits them.
+    pub fn ensure_consistent(&self) -> Result<(), DefiantError> {
+        if let Some(maximum) = self.maximum {
+            if maximum < self.minimum_amount() {
+                return Err(DefiantError::ValidationError("custom_amount: maximum must not be below minimum".into()));
+            }
+        }
+        
+        for suggested in &self.suggested {
+            self.check(*suggested)
+                .map_err(|_| DefiantError::ValidationError(format!(
+                    "custom_amount: suggested amount {} is outside the allowed range",
+                    suggested
+                )))?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Checks a customer-entered amount against the bounds.
+    pub fn check(&self, amount: i64) -> Result<(), DefiantError> {
+        let minimum = self.minimum_amount();
+        if amount < minimum {
+            return Err(DefiantError::ValidationError(format!("amount: must be at least {}", minimum)));
+        }
+        
+        if let Some(maximum) = self.maximum {
+            if amount > maximum {
+                return Err(DefiantError::ValidationError(format!("amount: must be at most {}", maximum)));
+            }
+        }
+        
+        Ok(())
+    }
+}
+
+/// Values entered by the customer on hosted checkout.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CollectedFields {
+    #[validate(email)]
+    pub email: String,
+    
+    #[validate(length(min = 1, max = 200))]
+    pub name: Option<String>,
+    
+    #[validate(length(min = 10, max = 15))]
+    pub phone: Option<String>,
+    
+    pub shipping_address: Option<Address>,
+    
+    #[serde(default)]
+    pub consents: HashMap<String, bool>,
+}
+
+impl FieldCollection {
+    pub fn from_value(value: &serde_json::Value) -> Self {
+        serde_json::from_value(value.clone()).unwrap_or_default()
+    }
+    
+    /// Checks collected values against this configuration, dropping anything
+    /// hidden so checkout can't be used to smuggle extra data onto a customer.
+    pub fn sanitize(&self, mut collected: CollectedFields) -> Result<CollectedFields, DefiantError> {
+        if self.phone == FieldRequirement::Hidden {
+            collected.phone = None;
+        } else if self.phone == FieldRequirement::Required && collected.phone.is_none() {
+            return Err(DefiantError::ValidationError("phone: phone number is required".into()));
+        }
+        
+        if self.shipping_address == FieldRequirement::Hidden {
+            collected.shipping_address = None;
+        } else if self.shipping_address == FieldRequirement::Required && collected.shipping_address.is_none() {
+            return Err(DefiantError::ValidationError("shipping_address: shipping address is required".into()));
+        }
+        
+        let mut consents = HashMap::with_capacity(self.consents.len());
+        for field in &self.consents {
+            let accepted = collected.consents.get(&field.key).copied().unwrap_or(false);
+            if field.required && !accepted {
+                return Err(DefiantError::ValidationError(format!(
+                    "consents: '{}' must be accepted",
+                    field.key
+                )));
+            }
+            consents.insert(field.key.clone(), accepted);
+        }
+        collected.consents = consents;
+        
+        Ok(collected)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreatePaymentLinkRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    
+    #[validate(range(min = 50, message = "Amount must be at least $0.50"))]
+    pub amount: i64,
+    
+    #[validate(length(min = 3, max = 3))]
+    pub currency: String,
+    
+    #[serde(default)]
+    #[validate]
+    pub field_collection: FieldCollection,
+    
+    #[serde(default)]
+    #[validate]
+    pub shipping_options: Vec<ShippingOption>,
+    
+    /// Number of units available; omit for unlimited
+    #[validate(range(min = 1, max = 1000000))]
+    pub inventory: Option<i32>,
+    
+    /// Lets the customer choose the amount; `amount` is then the prefilled default
+    #[validate]
+    pub custom_amount: Option<CustomAmount>,
+    
+    #[serde(default)]
+    pub is_donation: bool,
+    
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RestockPaymentLinkRequest {
+    #[validate(range(min = 1, max = 1000000))]
+    pub quantity: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateCheckoutSessionRequest {
+    pub payment_link_id: Option<Uuid>,
+    
+    /// Required when no payment link is given
+    #[validate(range(min = 50, message = "Amount must be at least $0.50"))]
+    pub amount: Option<i64>,
+    
+    #[validate(length(min = 3, max = 3))]
+    pub currency: Option<String>,
+    
+    /// Overrides the payment link's configuration for this session
+    #[validate]
+    pub field_collection: Option<FieldCollection>,
+    
+    /// Overrides the payment link's shipping options for this session
+    #[validate]
+    pub shipping_options: Option<Vec<ShippingOption>>,
+    
+    #[validate]
+    pub custom_amount: Option<CustomAmount>,
+    
+    pub is_donation: Option<bool>,
+    
+    /// Itemized cart; when given, the session amount is the sum of the lines
+    #[serde(default)]
+    #[validate(length(max = 100))]
+    #[validate]
+    pub line_items: Vec<CreateLineItem>,
+    
+    #[validate(url)]
+    pub success_url: Option<String>,
+    
+    #[validate(url)]
+    pub cancel_url: Option<String>,
+    
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CompleteCheckoutSessionRequest {
+    #[validate]
+    pub fields: CollectedFields,
+    
+    /// Required when the session offers shipping options
+    pub shipping_option: Option<String>,
+    
+    /// Customer-chosen amount, only accepted when the session allows custom amounts
+    pub amount: Option<i64>,
+    
+    pub payment_method: super::PaymentMethod,
+}
+
+/// What the hosted page needs to render; contains no merchant secrets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedCheckoutSession {
+    pub id: Uuid,
+    pub status: CheckoutSessionStatus,
+    pub amount: i64,
+    pub currency: String,
+    pub field_collection: FieldCollection,
+    pub shipping_options: Vec<ShippingOption>,
+    pub custom_amount: Option<CustomAmount>,
+    pub is_donation: bool,
+    pub line_items: Vec<CreateLineItem>,
+    pub expires_at: DateTime<Utc>,
+    pub cancel_url: Option<String>,
+}
+
+impl From<CheckoutSession> for HostedCheckoutSession {
+    fn from(session: CheckoutSession) -> Self {
+        Self {
+            id: session.id,
+            status: session.status,
+            amount: session.amount,
+            currency: session.currency,
+            field_collection: FieldCollection::from_value(&session.field_collection),
+            shipping_options: ShippingOption::list_from_value(&session.shipping_options),
+            custom_amount: CustomAmount::from_value(session.custom_amount.as_ref()),
+            is_donation: session.is_donation,
+            line_items: CreateLineItem::list_from_value(&session.line_items),
+            expires_at: session.expires_at,
+            cancel_url: session.cancel_url,
+        }
+    }
+}
+
+/// Everything needed to open a checkout session.
+#[derive(Debug, Clone)]
+pub struct NewCheckoutSession {
+    pub merchant_id: Uuid,
+    pub payment_link_id: Option<Uuid>,
+    pub amount: i64,
+    pub currency: String,
+    pub field_collection: FieldCollection,
+    pub shipping_options: Vec<ShippingOption>,
+    pub custom_amount: Option<CustomAmount>,
+    pub is_donation: bool,
+    pub line_items: Vec<CreateLineItem>,
+    pub success_url: Option<String>,
+    pub cancel_url: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub livemode: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompletedCheckout {
+    pub session_id: Uuid,
+    pub payment_id: Uuid,
+    pub amount: i64,
+    pub shipping_amount: i64,
+    pub customer_id: Uuid,
+    pub success_url: Option<String>,
+}
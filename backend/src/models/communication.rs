@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CustomerCommunication {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub channel: CommunicationChannel,
+    pub kind: CommunicationKind,
+    pub recipient: String,
+    pub subject: Option<String>,
+    pub related_object_type: Option<String>,
+    pub related_object_id: Option<Uuid>,
+    pub status: CommunicationStatus,
+    pub provider_message_id: Option<String>,
+    pub error_message: Option<String>,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "communication_channel", rename_all = "snake_case")]
+pub enum CommunicationChannel {
+    Email,
+    Sms,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "communication_kind", rename_all = "snake_case")]
+pub enum CommunicationKind {
+    Receipt,
+    Invoice,
+    Dunning,
+    PaymentFailed,
+    SubscriptionNotice,
+    Other,
+}
+
+/// How far a message got: `sent` once the relay accepted it, then `delivered` or
+/// `bounced` when the relay posts a delivery receipt for it.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "communication_status", rename_all = "snake_case")]
+pub enum CommunicationStatus {
+    Queued,
+    Sent,
+    Delivered,
+    Bounced,
+    Failed,
+}
+
+/// A delivery receipt the mail relay posts for a message it accepted, naming the
+/// message by the id it answered with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeliveryReceipt {
+    pub message_id: String,
+    pub status: DeliveryOutcome,
+    /// Why the message bounced, when the relay says
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryOutcome {
+    Delivered,
+    Bounced,
+}
+
+/// A message about to be sent, recorded before it leaves the system.
+#[derive(Debug, Clone)]
+pub struct NewCommunication {
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub channel: CommunicationChannel,
+    pub kind: CommunicationKind,
+    pub recipient: String,
+    pub subject: Option<String>,
+    pub related_object_type: Option<String>,
+    pub related_object_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommunicationsListResponse {
+    pub data: Vec<CustomerCommunication>,
+    pub has_more: bool,
+    pub url: String,
+}
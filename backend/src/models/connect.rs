@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// A merchant a platform onboards and sends funds to. It inherits the platform's
+/// data region.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ConnectedAccount {
+    pub id: Uuid,
+    pub platform_merchant_id: Uuid,
+    pub name: String,
+    pub email: String,
+    pub website: Option<String>,
+    pub active: bool,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateConnectedAccountRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate(email)]
+    pub email: String,
+    #[validate(url)]
+    pub website: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConnectedAccountListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectedAccountListResponse {
+    pub data: Vec<ConnectedAccount>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+/// Sends a payment's funds on to a connected account once it succeeds. The platform
+/// keeps the difference, less the processing fee it pays.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct TransferData {
+    pub destination: Uuid,
+    /// Minor units; the whole payment amount when omitted
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Transfer {
+    pub id: Uuid,
+    /// The platform the funds came from
+    pub merchant_id: Uuid,
+    /// Connected account the funds went to
+    pub destination: Uuid,
+    pub amount: i64,
+    pub amount_reversed: i64,
+    pub currency: String,
+    pub source_payment_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl Transfer {
+    pub fn reversible_amount(&self) -> i64 {
+        self.amount - self.amount_reversed
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateTransferRequest {
+    /// Minor units; at most the platform's available balance in `currency`
+    #[validate(range(min = 1))]
+    pub amount: i64,
+    #[validate(length(equal = 3))]
+    pub currency: String,
+    pub destination: Uuid,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TransferListQuery {
+    pub limit: Option<i64>,
+    pub destination: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransferListResponse {
+    pub data: Vec<Transfer>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct TransferReversal {
+    pub id: Uuid,
+    pub transfer_id: Uuid,
+    pub merchant_id: Uuid,
+    pub amount: i64,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
+pub struct CreateTransferReversalRequest {
+    /// Minor units; whatever has not been reversed yet when omitted
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
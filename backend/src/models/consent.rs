@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CardOnFileConsent {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub payment_method_id: Uuid,
+    pub mit_agreement_id: Option<Uuid>,
+    pub payment_id: Option<Uuid>,
+    /// Version of the wording the customer was shown, as published by the merchant
+    pub consent_text_version: String,
+    pub ip_address: String,
+    pub user_agent: Option<String>,
+    pub accepted_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What the merchant captured from the customer when they agreed to save their card.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct ConsentDetails {
+    #[validate(length(min = 1, max = 50))]
+    pub text_version: String,
+    /// The customer's IP address, not the merchant server's
+    #[validate(length(min = 3, max = 45))]
+    pub ip_address: String,
+    #[validate(length(max = 1000))]
+    pub user_agent: Option<String>,
+    /// When the customer accepted; defaults to the time of the request
+    pub accepted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsentListResponse {
+    pub data: Vec<CardOnFileConsent>,
+    pub has_more: bool,
+    pub url: String,
+}
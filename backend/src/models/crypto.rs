@@ -0,0 +1,391 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use utoipa::ToSchema;
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "crypto_chain", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum Chain {
+    Bitcoin,
+    /// Ether, or any ERC-20 token via `token_contract`
+    Ethereum,
+    Solana,
+}
+
+impl Chain {
+    /// Whether payment addresses are derived from an xpub. Other chains pay into one
+    /// registered address and tell payments apart by reference.
+    pub fn is_hd(&self) -> bool {
+        !matches!(self, Chain::Solana)
+    }
+    
+    /// BIP43 purpose: native segwit (BIP84) for Bitcoin, BIP44 otherwise.
+    pub fn purpose(&self) -> u32 {
+        match self {
+            Chain::Bitcoin => 84,
+            Chain::Ethereum | Chain::Solana => 44,
+        }
+    }
+    
+    /// SLIP-44 coin type used in the derivation path.
+    pub fn coin_type(&self) -> u32 {
+        match self {
+            Chain::Bitcoin => 0,
+            Chain::Ethereum => 60,
+            Chain::Solana => 501,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "crypto_address_status", rename_all = "snake_case")]
+pub enum CryptoAddressStatus {
+    AwaitingPayment,
+    /// Funds seen on chain, not yet deep enough to count
+    Detected,
+    Confirmed,
+    /// Nothing arrived within the watch window
+    Expired,
+    /// Nothing arrived before the locked quote expired; the payment needs a new quote
+    RateExpired,
+    /// The quote lapsed short of the amount due; the rest is collected at a
+    /// remaining-balance address
+    Underpaid,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CryptoWallet {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub chain: Chain,
+    pub xpub: Option<String>,
+    /// Receiving address on chains without xpub derivation
+    pub address: Option<String>,
+    pub next_index: i32,
+    /// Unused receiving addresses in a row the merchant's wallet software scans past
+    pub gap_limit: i32,
+    /// Highest index that has received funds
+    pub last_used_index: Option<i32>,
+    pub active: bool,
+    /// Where confirmed funds are swept to; never swept when absent
+    pub sweep_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RegisterCryptoWalletRequest {
+    pub chain: Chain,
+    /// Account-level extended public key, i.e. at m/purpose'/coin'/account'. Required on
+    /// Bitcoin and Ethereum; `zpub`/`vpub` exports are accepted for Bitcoin.
+    #[validate(length(min = 100, max = 255))]
+    pub xpub: Option<String>,
+    /// Receiving address, required on Solana
+    #[validate(length(min = 32, max = 100))]
+    pub address: Option<String>,
+    /// Gap limit of the wallet software the xpub is used in; defaults to BIP44's 20
+    #[validate(range(min = 1, max = 1000))]
+    pub gap_limit: Option<i32>,
+    /// Cold or settlement wallet to sweep confirmed payments into, on HD chains. The
+    /// signer must hold the wallet's keys.
+    #[validate(length(min = 26, max = 100))]
+    pub sweep_address: Option<String>,
+}
+
+/// An address handed out for a payment, with enough to re-derive it from the seed, or
+/// the reference that identifies the payment's transaction on reference-based chains.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CryptoAddress {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    /// Absent for conversion provider deposit addresses
+    pub wallet_id: Option<Uuid>,
+    pub payment_id: Uuid,
+    pub chain: Chain,
+    pub address: String,
+    pub derivation_index: Option<i32>,
+    pub derivation_path: Option<String>,
+    /// Solana Pay reference key the customer's transaction must include
+    pub reference: Option<String>,
+    /// ERC-20 contract the amount is denominated in; native coin when absent
+    pub token_contract: Option<String>,
+    /// Base units (satoshi, wei, lamports, token units) the payment is complete at
+    pub expected_amount: Decimal,
+    pub received_amount: Decimal,
+    pub confirmed_amount: Decimal,
+    pub status: CryptoAddressStatus,
+    /// Fiat currency the amount was quoted from, with the rate (fiat per whole coin)
+    pub quote_currency: Option<String>,
+    pub quote_rate: Option<Decimal>,
+    pub quote_expires_at: Option<DateTime<Utc>>,
+    pub detected_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    /// The underpaid address this one collects the remaining balance of
+    pub parent_address_id: Option<Uuid>,
+    /// Sweep the funds left the address in
+    pub crypto_payout_id: Option<Uuid>,
+}
+
+/// How much to collect on chain, locked for a limited time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptoQuote {
+    /// Base units due
+    pub amount: Decimal,
+    pub currency: String,
+    /// Fiat per whole coin, when converted by us rather than quoted by the merchant
+    pub rate: Option<Decimal>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What crypto payments settle to the merchant in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "crypto_settlement_asset", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoSettlementAsset {
+    /// Whatever the customer paid, straight into the merchant's wallet
+    Native,
+    Usdc,
+    Usdt,
+}
+
+impl CryptoSettlementAsset {
+    /// ERC-20 contract of the stablecoin, lowercase; `None` for `Native`.
+    pub fn token_contract(&self) -> Option<&'static str> {
+        match self {
+            CryptoSettlementAsset::Native => None,
+            CryptoSettlementAsset::Usdc => Some("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"),
+            CryptoSettlementAsset::Usdt => Some("0xdac17f958d2ee523a2206206994597c13d831ec7"),
+        }
+    }
+    
+    pub fn symbol(&self) -> Option<&'static str> {
+        match self {
+            CryptoSettlementAsset::Native => None,
+            CryptoSettlementAsset::Usdc => Some("USDC"),
+            CryptoSettlementAsset::Usdt => Some("USDT"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CryptoSettlementPreference {
+    pub merchant_id: Uuid,
+    pub settlement_asset: CryptoSettlementAsset,
+    /// Ethereum address stablecoin settlements are sent to
+    pub settlement_address: Option<String>,
+    /// How far (in basis points) a payment may fall short and still succeed, or
+    /// exceed before the excess is credited to the customer
+    pub payment_tolerance_bps: i32,
+    /// Minutes an unpaid payment stays open before it is canceled
+    pub payment_expiry_minutes: i32,
+    /// Absent until the merchant first sets a preference
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCryptoSettlementRequest {
+    pub settlement_asset: CryptoSettlementAsset,
+    /// Required for stablecoin settlement
+    pub settlement_address: Option<String>,
+    /// 0 to 1000; payments must match the quote exactly when omitted
+    #[serde(default)]
+    pub payment_tolerance_bps: i32,
+    /// 15 to 10080; defaults to 60
+    pub payment_expiry_minutes: Option<i32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "crypto_conversion_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoConversionStatus {
+    AwaitingDeposit,
+    /// The deposit confirmed; the provider is swapping and sending the stablecoin
+    Converting,
+    Settled,
+    Failed,
+}
+
+/// A payment's journey from the asset the customer paid to the merchant's stablecoin.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CryptoConversion {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub payment_id: Uuid,
+    pub crypto_address_id: Uuid,
+    #[serde(skip_serializing)]
+    pub provider_reference: String,
+    pub deposit_asset: String,
+    /// Base units of `deposit_asset`
+    pub deposit_amount: Decimal,
+    pub settlement_asset: CryptoSettlementAsset,
+    pub settlement_address: String,
+    /// Stablecoin base units: the quote until settled, then the amount sent
+    pub settlement_amount: Decimal,
+    pub settlement_tx_hash: Option<String>,
+    pub status: CryptoConversionStatus,
+    pub error: Option<String>,
+    pub settled_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "crypto_refund_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoRefundStatus {
+    /// Reserved against the payment, waiting to be signed and broadcast
+    Pending,
+    /// On chain, not yet deep enough to count
+    Broadcast,
+    Confirmed,
+    /// Signing or broadcasting failed, or the transaction reverted; the amount is
+    /// refundable again
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CryptoRefund {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub payment_id: Uuid,
+    pub chain: Chain,
+    /// ERC-20 contract refunded in; the chain's native coin when absent
+    pub token_contract: Option<String>,
+    pub address: String,
+    /// In the payment's currency
+    pub amount: i64,
+    pub currency: String,
+    /// Base units sent: `amount`'s share of what the payment collected on chain
+    pub crypto_amount: Decimal,
+    pub reason: Option<String>,
+    pub status: CryptoRefundStatus,
+    pub tx_hash: Option<String>,
+    pub confirmations: i32,
+    pub error: Option<String>,
+    pub broadcast_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateCryptoRefundRequest {
+    /// Minor units of the payment's currency; defaults to everything not yet refunded
+    #[validate(range(min = 1))]
+    pub amount: Option<i64>,
+    /// Defaults to the address the customer registered for refunds
+    #[validate(length(min = 26, max = 100))]
+    pub address: Option<String>,
+    #[validate(length(max = 500))]
+    pub reason: Option<String>,
+}
+
+/// Sent by the customer's client, authenticated by the payment's client secret.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RegisterRefundAddressRequest {
+    pub client_secret: String,
+    #[validate(length(min = 26, max = 100))]
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefundAddressResponse {
+    pub payment_id: Uuid,
+    pub chain: Chain,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "crypto_payout_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CryptoPayoutStatus {
+    /// Waiting to be signed and broadcast
+    Pending,
+    Broadcast,
+    Confirmed,
+    /// The addresses are swept again with the next batch
+    Failed,
+}
+
+/// A sweep of a wallet's payment addresses into its sweep address.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct CryptoPayout {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub wallet_id: Uuid,
+    pub chain: Chain,
+    pub destination: String,
+    pub address_count: i32,
+    /// Base units swept in; the destination receives this less `fee`
+    pub amount: Decimal,
+    /// Estimated network fee, in base units
+    pub fee: Decimal,
+    /// Sat per vbyte on Bitcoin, wei per gas on Ethereum
+    pub fee_rate: Decimal,
+    pub status: CryptoPayoutStatus,
+    pub tx_hash: Option<String>,
+    pub confirmations: i32,
+    pub error: Option<String>,
+    pub broadcast_at: Option<DateTime<Utc>>,
+    pub confirmed_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub last_checked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CryptoPayoutListQuery {
+    pub limit: Option<i64>,
+    pub wallet_id: Option<Uuid>,
+    pub status: Option<CryptoPayoutStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CryptoPayoutListResponse {
+    pub data: Vec<CryptoPayout>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CryptoFeeEstimateQuery {
+    /// Native coin to estimate for: btc, eth or sol
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CryptoFeeLevel {
+    /// Sat per vbyte on Bitcoin, wei per gas on Ethereum, micro-lamports per compute
+    /// unit of priority fee on Solana
+    pub rate: Decimal,
+    /// What a typical single-output payment costs at `rate`, in base units
+    pub network_fee: Decimal,
+}
+
+/// Current network fees for paying in a chain's native coin, so totals shown to
+/// customers include what their wallet will add.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CryptoFeeEstimates {
+    /// Lowercase ticker
+    pub currency: String,
+    pub chain: Chain,
+    /// Likely to confirm in the next block or two
+    pub fast: CryptoFeeLevel,
+    /// Within about an hour
+    pub medium: CryptoFeeLevel,
+    /// Cheapest that still confirms, possibly after hours
+    pub slow: CryptoFeeLevel,
+    pub fetched_at: DateTime<Utc>,
+}
@@ -1,23 +1,31 @@
 use serde::{Deserialize, Serialize};
-use sqlx::prelude::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+use super::Pii;
+
+#[derive(Debug, Clone, Serialize)]
 pub struct Customer {
     pub id: Uuid,
-    pub email: String,
+    pub email: Pii,
     pub name: Option<String>,
-    pub phone: Option<String>,
+    pub phone: Option<Pii>,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub default_payment_method: Option<Uuid>,
     pub currency: Option<String>,
     pub balance: i64,
     pub delinquent: bool,
+    pub shipping_address: Option<serde_json::Value>,
+    pub consents: Option<serde_json::Value>,
+    /// Created with a live key rather than a test key
+    pub livemode: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Moves forward on every update; send it back with changes to have them refused
+    /// if the object has changed since it was read
+    pub version: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -81,12 +89,12 @@ pub struct Address {
     pub country: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct CustomerResponse {
     pub id: Uuid,
-    pub email: String,
+    pub email: Pii,
     pub name: Option<String>,
-    pub phone: Option<String>,
+    pub phone: Option<Pii>,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
     pub default_payment_method: Option<String>,
@@ -109,4 +117,40 @@ pub struct PaymentMethodResponse {
     pub country: Option<String>,
     pub is_default: bool,
     pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomerListQuery {
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub email: Option<String>,
+}
+
+/// See `services::search::SearchFilter` for the query syntax; customer text is the
+/// name and description, as emails are sealed.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomerSearchQuery {
+    pub query: String,
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DeleteCustomerQuery {
+    /// End live subscriptions, agreements and draft invoices with the customer
+    #[serde(default)]
+    pub force: bool,
+    /// The customer's `version` when the client read it
+    pub version: Option<i64>,
+}
+
+/// Customers in the mode of the key that listed them, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct CustomerListResponse {
+    pub data: Vec<Customer>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+    pub url: String,
 }
\ No newline at end of file
@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// What `force=true` does about a blocker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceResolution {
+    /// Removed or ended together with the object
+    Cascade,
+    /// Has to be dealt with first; `force` does not override it
+    Block,
+}
+
+/// Related objects of one kind that stand in the way of a delete.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyBlocker {
+    pub object: String,
+    pub count: i64,
+    /// The first few blocking ids, newest first
+    pub ids: Vec<Uuid>,
+    pub description: String,
+    pub on_force: ForceResolution,
+}
+
+/// Returned as a 409 when a delete would orphan or silently end related objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyConflict {
+    pub object: String,
+    pub id: Uuid,
+    pub blockers: Vec<DependencyBlocker>,
+}
+
+impl DependencyConflict {
+    /// Whether retrying with `force=true` would succeed.
+    pub fn forceable(&self) -> bool {
+        self.blockers.iter().all(|b| b.on_force == ForceResolution::Cascade)
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ForceQuery {
+    #[serde(default)]
+    pub force: bool,
+}
@@ -0,0 +1,313 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use utoipa::openapi::{RefOr, Schema};
+
+use super::{Payment, Invoice, Payout, Topup, Transfer, TransferReversal, ConnectedAccount, BalanceAvailableData, ConfigurationChangeData, SecurityEvent, CryptoConversion, CryptoRefund, CryptoPayout, SubscriptionResponse};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Event {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+    /// Payload version `data` was written in
+    pub api_version: String,
+    /// The mode of the object the event describes
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The envelope sent to an endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderedEvent {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: serde_json::Value,
+    pub api_version: String,
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventListQuery {
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    /// Exact type such as `payment.succeeded`, or a family wildcard such as `payment.*`
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    pub created_gte: Option<DateTime<Utc>>,
+    pub created_lte: Option<DateTime<Utc>>,
+}
+
+/// Long-poll for events after `cursor`, as consumed by `defiant listen`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventPollQuery {
+    /// Cursor from the previous response; omitted to start from now
+    pub cursor: Option<String>,
+    #[serde(rename = "type")]
+    pub event_type: Option<String>,
+    /// Seconds to hold the request open while nothing is new (max 30)
+    pub timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventPollResponse {
+    /// Oldest first, rendered in the current payload version
+    pub data: Vec<RenderedEvent>,
+    pub cursor: String,
+}
+
+/// Opens a Server-Sent Events stream of the merchant's events.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventStreamQuery {
+    /// Comma-separated topics to receive, e.g. `payment.*,invoice.paid`
+    pub topics: String,
+    /// Replays the events after this one first; a `Last-Event-ID` header, as sent by
+    /// reconnecting `EventSource`s, takes precedence
+    pub last_event_id: Option<Uuid>,
+}
+
+/// Opens a WebSocket stream of the merchant's events at `/ws`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebSocketQuery {
+    /// Comma-separated topics to start with, e.g. `payment.*,invoice.paid`; more can be
+    /// subscribed to, and these unsubscribed from, over the connection
+    pub topics: Option<String>,
+    /// Replays the events after this one on the starting topics before live ones
+    pub last_event_id: Option<Uuid>,
+}
+
+/// What a streaming client sends to choose the events it receives, e.g.
+/// `{"subscribe": ["payment.*", "invoice.paid"]}`. Topics are event filters as
+/// webhook endpoints take them.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamClientMessage {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+/// The server's answer to a `StreamClientMessage`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamServerMessage {
+    /// Every topic the connection is now subscribed to
+    Ack { topics: Vec<String> },
+    /// The message was refused as a whole; the subscriptions are as they were
+    Nack(StreamNack),
+    /// An event on one of the connection's topics, sent on WebSocket connections
+    Event(RenderedEvent),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamNack {
+    /// The topic that was refused, unless the message as a whole was
+    pub topic: Option<String>,
+    /// `invalid_topic`, `unknown_topic`, `forbidden`, `too_many_topics` or
+    /// `invalid_message`
+    pub code: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventListResponse {
+    pub data: Vec<Event>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EventReplay {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub webhook_id: Uuid,
+    pub created_gte: DateTime<Utc>,
+    pub created_lte: DateTime<Utc>,
+    pub status: EventReplayStatus,
+    /// Matching events in the window, counted when the job starts
+    pub total_events: i32,
+    /// Events queued for delivery so far
+    pub queued_events: i32,
+    #[serde(skip_serializing)]
+    pub cursor_created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub cursor_event_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub locked_until: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "event_replay_status", rename_all = "snake_case")]
+pub enum EventReplayStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateEventReplayRequest {
+    pub webhook_id: Uuid,
+    pub created_gte: DateTime<Utc>,
+    pub created_lte: DateTime<Utc>,
+}
+
+/// Replay job with delivery outcomes for the events it has queued.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventReplayResponse {
+    #[serde(flatten)]
+    pub replay: EventReplay,
+    pub delivered_events: i64,
+    pub failed_events: i64,
+}
+
+pub const PAYMENT_CREATED: &str = "payment.created";
+pub const PAYMENT_SUCCEEDED: &str = "payment.succeeded";
+pub const PAYMENT_FAILED: &str = "payment.failed";
+pub const PAYMENT_CANCELED: &str = "payment.canceled";
+pub const PAYMENT_REQUIRES_ACTION: &str = "payment.requires_action";
+pub const PAYMENT_REFUNDED: &str = "payment.refunded";
+pub const PAYMENT_DISPUTED: &str = "payment.disputed";
+pub const PAYMENT_PARTIALLY_PAID: &str = "payment.partially_paid";
+pub const PAYMENT_UNDERPAID_ACCEPTED: &str = "payment.underpaid_accepted";
+pub const PAYMENT_OVERPAID: &str = "payment.overpaid";
+pub const PAYMENT_REVIEW_OPENED: &str = "payment.review_opened";
+pub const INVOICE_CREATED: &str = "invoice.created";
+pub const INVOICE_PAID: &str = "invoice.paid";
+pub const INVOICE_VOIDED: &str = "invoice.voided";
+pub const INVOICE_MARKED_UNCOLLECTIBLE: &str = "invoice.marked_uncollectible";
+pub const SUBSCRIPTION_CREATED: &str = "subscription.created";
+pub const SUBSCRIPTION_UPDATED: &str = "subscription.updated";
+pub const SUBSCRIPTION_RENEWED: &str = "subscription.renewed";
+pub const SUBSCRIPTION_CANCELED: &str = "subscription.canceled";
+pub const PAYOUT_CREATED: &str = "payout.created";
+pub const PAYOUT_PAID: &str = "payout.paid";
+pub const PAYOUT_FAILED: &str = "payout.failed";
+pub const PAYOUT_CANCELED: &str = "payout.canceled";
+pub const BALANCE_AVAILABLE: &str = "balance.available";
+pub const TOPUP_SUCCEEDED: &str = "topup.succeeded";
+pub const CONNECTED_ACCOUNT_CREATED: &str = "connected_account.created";
+pub const TRANSFER_CREATED: &str = "transfer.created";
+pub const TRANSFER_REVERSED: &str = "transfer.reversed";
+pub const API_KEY_CREATED: &str = "api_key.created";
+pub const API_KEY_ROTATED: &str = "api_key.rotated";
+pub const API_KEY_REVOKED: &str = "api_key.revoked";
+pub const API_KEY_UPDATED: &str = "api_key.updated";
+pub const WEBHOOK_ENDPOINT_CREATED: &str = "webhook_endpoint.created";
+pub const WEBHOOK_ENDPOINT_UPDATED: &str = "webhook_endpoint.updated";
+pub const WEBHOOK_ENDPOINT_SECRET_ROTATED: &str = "webhook_endpoint.secret_rotated";
+pub const WEBHOOK_ENDPOINT_DELETED: &str = "webhook_endpoint.deleted";
+pub const CRYPTO_WALLET_UPDATED: &str = "crypto_wallet.updated";
+pub const TRUSTED_SOURCE_CREATED: &str = "trusted_source.created";
+pub const TRUSTED_SOURCE_DELETED: &str = "trusted_source.deleted";
+pub const REQUEST_SIGNING_UPDATED: &str = "request_signing.updated";
+pub const SECURITY_EVENT_SUSPICIOUS: &str = "security_event.suspicious";
+pub const CRYPTO_SETTLEMENT_UPDATED: &str = "crypto_settlement.updated";
+pub const RISK_SETTINGS_UPDATED: &str = "risk_settings.updated";
+pub const FRAUD_SETTINGS_UPDATED: &str = "fraud_settings.updated";
+pub const PAYOUT_SCHEDULE_UPDATED: &str = "payout_schedule.updated";
+pub const CRYPTO_CONVERSION_SETTLED: &str = "crypto_conversion.settled";
+pub const CRYPTO_CONVERSION_FAILED: &str = "crypto_conversion.failed";
+pub const CRYPTO_REFUND_CREATED: &str = "crypto_refund.created";
+pub const CRYPTO_REFUND_BROADCAST: &str = "crypto_refund.broadcast";
+pub const CRYPTO_REFUND_CONFIRMED: &str = "crypto_refund.confirmed";
+pub const CRYPTO_REFUND_FAILED: &str = "crypto_refund.failed";
+pub const CRYPTO_PAYOUT_CREATED: &str = "crypto_payout.created";
+pub const CRYPTO_PAYOUT_CONFIRMED: &str = "crypto_payout.confirmed";
+pub const CRYPTO_PAYOUT_FAILED: &str = "crypto_payout.failed";
+
+/// An event type the platform emits, and the Rust type its `data` is serialized from.
+pub struct EventTypeSpec {
+    pub event_type: &'static str,
+    pub description: &'static str,
+    pub payload: fn() -> (&'static str, RefOr<Schema>),
+}
+
+/// Every event type that can reach an endpoint. New constants above belong here too,
+/// or they will be missing from `GET /v1/event_types`.
+pub const EVENT_CATALOG: &[EventTypeSpec] = &[
+    EventTypeSpec { event_type: PAYMENT_CREATED, description: "A payment was created", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_SUCCEEDED, description: "A payment was authorized or captured", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_FAILED, description: "A payment attempt failed", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_CANCELED, description: "A crypto payment expired before anything was paid", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_REQUIRES_ACTION, description: "A payment needs customer authentication to continue", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_REFUNDED, description: "A payment was fully or partially refunded", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_DISPUTED, description: "The cardholder disputed a payment", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_PARTIALLY_PAID, description: "A crypto payment's quote lapsed short; the rest is due at a new address", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_UNDERPAID_ACCEPTED, description: "A crypto payment succeeded short of its quote, within the merchant's tolerance", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_OVERPAID, description: "A crypto payment received more than its quote; the excess was credited to the customer's balance", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYMENT_REVIEW_OPENED, description: "Fraud checks let a payment through but flagged it for review; see review_reason", payload: <Payment as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: INVOICE_CREATED, description: "An invoice was created", payload: <Invoice as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: INVOICE_PAID, description: "An invoice was paid in full from its hosted payment page", payload: <Invoice as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: INVOICE_VOIDED, description: "An invoice was voided", payload: <Invoice as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: INVOICE_MARKED_UNCOLLECTIBLE, description: "An invoice was marked uncollectible", payload: <Invoice as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: SUBSCRIPTION_CREATED, description: "A subscription was started", payload: <SubscriptionResponse as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: SUBSCRIPTION_UPDATED, description: "A subscription's plan changed, or a change was scheduled for or withdrawn from its period end", payload: <SubscriptionResponse as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: SUBSCRIPTION_RENEWED, description: "A subscription started a new period and its invoice was issued", payload: <SubscriptionResponse as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: SUBSCRIPTION_CANCELED, description: "A subscription ended, immediately or at its period end", payload: <SubscriptionResponse as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYOUT_CREATED, description: "A payout was created and debited from the balance", payload: <Payout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYOUT_PAID, description: "A payout arrived in the destination account", payload: <Payout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYOUT_FAILED, description: "A payout failed and its funds were returned", payload: <Payout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYOUT_CANCELED, description: "A payout was canceled before it was sent and its funds were returned", payload: <Payout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: BALANCE_AVAILABLE, description: "Pending funds became available", payload: <BalanceAvailableData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: TOPUP_SUCCEEDED, description: "A top-up was credited to the balance", payload: <Topup as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CONNECTED_ACCOUNT_CREATED, description: "A connected account was created under the platform", payload: <ConnectedAccount as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: TRANSFER_CREATED, description: "Funds were sent from the platform balance to a connected account", payload: <Transfer as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: TRANSFER_REVERSED, description: "Funds of a transfer were returned from the connected account to the platform", payload: <TransferReversal as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: API_KEY_CREATED, description: "An API key was issued", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: API_KEY_ROTATED, description: "An API key was rolled and replaced by a new one", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: API_KEY_REVOKED, description: "An API key was revoked", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: API_KEY_UPDATED, description: "An API key's IP allowlist changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: WEBHOOK_ENDPOINT_CREATED, description: "A webhook endpoint was registered", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: WEBHOOK_ENDPOINT_UPDATED, description: "A webhook endpoint's URL, events, version or status changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: WEBHOOK_ENDPOINT_SECRET_ROTATED, description: "A webhook endpoint's signing secret was rotated", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: WEBHOOK_ENDPOINT_DELETED, description: "A webhook endpoint was deleted", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_WALLET_UPDATED, description: "The wallet crypto payments settle to was replaced", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: TRUSTED_SOURCE_CREATED, description: "An IP range or service identity was trusted with elevated rate limits", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: TRUSTED_SOURCE_DELETED, description: "A trusted source was removed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: REQUEST_SIGNING_UPDATED, description: "Request signing was turned on or off, or its secret was rotated", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: SECURITY_EVENT_SUSPICIOUS, description: "Repeated failed sign-ins, or a key used from outside its IP allowlist", payload: <SecurityEvent as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_SETTLEMENT_UPDATED, description: "The asset or address crypto payments settle to changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: RISK_SETTINGS_UPDATED, description: "The risk scores payments are challenged or declined at changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: FRAUD_SETTINGS_UPDATED, description: "The fraud checks payments run through, or their limits, changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: PAYOUT_SCHEDULE_UPDATED, description: "How often the available balance is paid out changed", payload: <ConfigurationChangeData as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_CONVERSION_SETTLED, description: "A crypto payment was converted and the stablecoin sent to the merchant", payload: <CryptoConversion as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_CONVERSION_FAILED, description: "The conversion provider could not settle a crypto payment", payload: <CryptoConversion as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_REFUND_CREATED, description: "An on-chain refund was requested and its amount reserved", payload: <CryptoRefund as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_REFUND_BROADCAST, description: "An on-chain refund transaction was broadcast", payload: <CryptoRefund as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_REFUND_CONFIRMED, description: "An on-chain refund reached the required confirmations", payload: <CryptoRefund as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_REFUND_FAILED, description: "An on-chain refund could not be sent; its amount can be refunded again", payload: <CryptoRefund as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_PAYOUT_CREATED, description: "Confirmed payment addresses were batched into a sweep to the wallet's sweep address", payload: <CryptoPayout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_PAYOUT_CONFIRMED, description: "A sweep reached the required confirmations", payload: <CryptoPayout as utoipa::ToSchema>::schema },
+    EventTypeSpec { event_type: CRYPTO_PAYOUT_FAILED, description: "A sweep could not be sent; its addresses are swept with the next batch", payload: <CryptoPayout as utoipa::ToSchema>::schema },
+];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTypeDefinition {
+    #[serde(rename = "type")]
+    pub event_type: &'static str,
+    pub description: &'static str,
+    /// JSON schema of the event's `data`, referencing `components.schemas`
+    pub schema: serde_json::Value,
+}
+
+/// Catalog of event types. Payload schemas are OpenAPI 3 component schemas, so
+/// `$ref`s resolve against this document's `components`.
+#[derive(Debug, Clone, Serialize)]
+pub struct EventTypeListResponse {
+    pub data: Vec<EventTypeDefinition>,
+    pub api_version: &'static str,
+    pub components: serde_json::Value,
+    pub url: String,
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ExchangeRate {
+    pub id: Uuid,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub rate: Decimal,
+    pub rate_date: NaiveDate,
+    pub source: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FxRateQuery {
+    pub from: String,
+    pub to: String,
+    /// Day to look up; defaults to today (UTC)
+    pub date: Option<NaiveDate>,
+}
+
+/// The rate in effect for a pair on a day: the latest stored on or before it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FxRate {
+    pub from: String,
+    pub to: String,
+    /// Units of `to` per unit of `from`
+    pub rate: Decimal,
+    pub date: NaiveDate,
+    /// The stored rate's own date, earlier than `date` on days without a fixing
+    pub rate_date: NaiveDate,
+    pub source: String,
+    /// True when derived from the stored `to -> from` rate
+    pub inverted: bool,
+}
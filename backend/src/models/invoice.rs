@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Invoice {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub subscription_id: Option<Uuid>,
+    pub status: InvoiceStatus,
+    pub amount_due: i64,
+    pub amount_paid: i64,
+    pub amount_remaining: i64,
+    pub currency: String,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub hosted_invoice_url: Option<String>,
+    pub invoice_pdf: Option<String>,
+    pub number: Option<String>,
+    pub period_start: Option<DateTime<Utc>>,
+    pub period_end: Option<DateTime<Utc>>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub source_currency: Option<String>,
+    pub exchange_rate: Option<Decimal>,
+    pub exchange_rate_date: Option<NaiveDate>,
+    pub customer_balance_applied: i64,
+    pub marked_uncollectible_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Sent with a live key; payments made from its hosted page are live
+    pub livemode: bool,
+    /// When the payment link was last emailed to the customer
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "invoice_status", rename_all = "snake_case")]
+pub enum InvoiceStatus {
+    Draft,
+    Open,
+    Paid,
+    Void,
+    Uncollectible,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InvoiceLineItem {
+    pub id: Uuid,
+    pub invoice_id: Uuid,
+    pub description: String,
+    pub quantity: i32,
+    /// Unit price in the line's original currency
+    pub unit_amount: i64,
+    pub currency: String,
+    /// Line total converted into the invoice currency
+    pub amount: i64,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateInvoiceRequest {
+    pub customer_id: Uuid,
+    
+    #[validate(length(min = 3, max = 3))]
+    pub currency: String,
+    
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    
+    pub due_date: Option<DateTime<Utc>>,
+    
+    pub metadata: Option<serde_json::Value>,
+    
+    #[validate(length(min = 1, max = 250, message = "Invoice must have between 1 and 250 line items"))]
+    #[validate]
+    pub line_items: Vec<CreateInvoiceLineItem>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateInvoiceLineItem {
+    #[validate(length(min = 1, max = 500))]
+    pub description: String,
+    
+    #[validate(range(min = 1, max = 100000))]
+    pub quantity: i32,
+    
+    #[validate(range(min = 0))]
+    pub unit_amount: i64,
+    
+    /// Defaults to the invoice currency when omitted
+    #[validate(length(min = 3, max = 3))]
+    pub currency: Option<String>,
+    
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionSnapshot {
+    pub source_currency: String,
+    pub target_currency: String,
+    pub rate: Decimal,
+    pub rate_date: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceResponse {
+    pub id: Uuid,
+    pub number: Option<String>,
+    pub customer_id: Uuid,
+    pub subscription_id: Option<Uuid>,
+    pub status: InvoiceStatus,
+    pub amount_due: i64,
+    pub amount_paid: i64,
+    pub amount_remaining: i64,
+    pub currency: String,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub hosted_invoice_url: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub voided_at: Option<DateTime<Utc>>,
+    pub marked_uncollectible_at: Option<DateTime<Utc>>,
+    pub customer_balance_applied: i64,
+    pub sent_at: Option<DateTime<Utc>>,
+    pub conversion: Option<ConversionSnapshot>,
+    pub line_items: Vec<InvoiceLineItem>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl InvoiceResponse {
+    pub fn from_parts(invoice: Invoice, line_items: Vec<InvoiceLineItem>) -> Self {
+        let conversion = match (invoice.source_currency.clone(), invoice.exchange_rate, invoice.exchange_rate_date) {
+            (Some(source_currency), Some(rate), Some(rate_date)) => Some(ConversionSnapshot {
+                source_currency,
+                target_currency: invoice.currency.clone(),
+                rate,
+                rate_date,
+            }),
+            _ => None,
+        };
+        
+        Self {
+            id: invoice.id,
+            number: invoice.number,
+            customer_id: invoice.customer_id,
+            subscription_id: invoice.subscription_id,
+            status: invoice.status,
+            amount_due: invoice.amount_due,
+            amount_paid: invoice.amount_paid,
+            amount_remaining: invoice.amount_remaining,
+            currency: invoice.currency,
+            description: invoice.description,
+            metadata: invoice.metadata,
+            hosted_invoice_url: invoice.hosted_invoice_url,
+            due_date: invoice.due_date,
+            paid_at: invoice.paid_at,
+            voided_at: invoice.voided_at,
+            marked_uncollectible_at: invoice.marked_uncollectible_at,
+            customer_balance_applied: invoice.customer_balance_applied,
+            sent_at: invoice.sent_at,
+            conversion,
+            line_items,
+            created_at: invoice.created_at,
+            updated_at: invoice.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InvoiceListQuery {
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub customer_id: Option<Uuid>,
+    pub status: Option<InvoiceStatus>,
+}
+
+/// Invoices in the mode of the key that listed them, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceListResponse {
+    pub data: Vec<InvoiceResponse>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+    pub url: String,
+}
+
+/// The signature on a hosted invoice link; it stands in for a login.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedInvoiceQuery {
+    /// Unix time the link stops working
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// Where to send a bank transfer, and the reference that matches it to the invoice.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BankTransferInstructions {
+    pub reference: String,
+    pub amount: i64,
+    pub currency: String,
+}
+
+/// What the hosted payment page needs to render; contains no merchant secrets.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedInvoice {
+    pub id: Uuid,
+    pub number: Option<String>,
+    pub merchant_name: String,
+    pub status: InvoiceStatus,
+    pub amount_due: i64,
+    pub amount_paid: i64,
+    pub amount_remaining: i64,
+    pub currency: String,
+    pub description: Option<String>,
+    pub due_date: Option<DateTime<Utc>>,
+    pub line_items: Vec<InvoiceLineItem>,
+    /// Methods the page can take payment with directly
+    pub payment_methods: Vec<super::PaymentMethod>,
+    pub bank_transfer: Option<BankTransferInstructions>,
+    /// The most recent payment started from the page, so a reload shows its progress
+    pub latest_payment: Option<HostedInvoicePayment>,
+    pub link_expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayHostedInvoiceRequest {
+    pub expires: i64,
+    pub signature: String,
+    /// `card` or `crypto`; bank transfers follow the page's instructions instead
+    pub payment_method: super::PaymentMethod,
+    pub source: Option<super::PaymentSource>,
+    pub crypto_chain: Option<super::Chain>,
+    pub crypto_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostedInvoicePayment {
+    pub payment_id: Uuid,
+    pub status: super::PaymentStatus,
+    pub amount: i64,
+    pub currency: String,
+    /// Address and amount to pay a crypto payment to
+    pub crypto: Option<serde_json::Value>,
+    pub invoice_status: InvoiceStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CustomerBalanceTransaction {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub invoice_id: Option<Uuid>,
+    /// Crypto payment whose overpayment was credited
+    pub payment_id: Option<Uuid>,
+    #[sqlx(rename = "type")]
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    pub amount: i64,
+    pub currency: String,
+    pub ending_balance: i64,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
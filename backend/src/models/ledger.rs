@@ -0,0 +1,241 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+use super::AvailableBalance;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "ledger_direction", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerDirection {
+    Debit,
+    Credit,
+}
+
+/// The accounts a merchant's money moves between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerAccountCode {
+    /// Card funds held at the processor on the merchant's behalf
+    ProcessorFunds,
+    /// What the platform owes the merchant, paid out to its bank account
+    MerchantBalance,
+    /// Processing fees the platform earned from the merchant
+    FeeRevenue,
+    /// Crypto received at the merchant's own addresses; it never reaches the payout
+    /// balance
+    CryptoWallet,
+    /// What customers paid in crypto, net of on-chain refunds
+    CryptoSales,
+    /// Funds moved between a platform and its connected accounts; nets to zero across
+    /// the platform and all of them
+    ConnectedTransfers,
+    /// Funds exchanged between the merchant's currencies; each conversion leaves it in
+    /// credit in the currency sold and in debit in the one bought
+    FxConversion,
+    /// Funds held back from the merchant balance under a rolling reserve until released
+    Reserve,
+}
+
+impl LedgerAccountCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LedgerAccountCode::ProcessorFunds => "processor_funds",
+            LedgerAccountCode::MerchantBalance => "merchant_balance",
+            LedgerAccountCode::FeeRevenue => "fee_revenue",
+            LedgerAccountCode::CryptoWallet => "crypto_wallet",
+            LedgerAccountCode::CryptoSales => "crypto_sales",
+            LedgerAccountCode::ConnectedTransfers => "connected_transfers",
+            LedgerAccountCode::FxConversion => "fx_conversion",
+            LedgerAccountCode::Reserve => "reserve",
+        }
+    }
+    
+    /// The side that increases the account's balance.
+    pub fn normal_balance(&self) -> LedgerDirection {
+        match self {
+            LedgerAccountCode::ProcessorFunds | LedgerAccountCode::CryptoWallet | LedgerAccountCode::ConnectedTransfers | LedgerAccountCode::FxConversion => LedgerDirection::Debit,
+            LedgerAccountCode::MerchantBalance | LedgerAccountCode::FeeRevenue | LedgerAccountCode::CryptoSales | LedgerAccountCode::Reserve => LedgerDirection::Credit,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct LedgerAccount {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub code: String,
+    pub currency: String,
+    pub normal_balance: LedgerDirection,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// One movement of money, as balanced debits and credits across ledger accounts.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct JournalEntry {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    /// payment, fee, refund, payout, payout_fee, payout_failure, payout_fee_refund,
+    /// payout_cancellation, topup,
+    /// transfer, transfer_reversal, conversion, reserve_hold, reserve_release,
+    /// crypto_payment or crypto_refund
+    pub kind: String,
+    pub currency: String,
+    pub description: Option<String>,
+    /// What the entry was booked for, e.g. `{"payment_id": ...}`
+    pub metadata: serde_json::Value,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Until then the entry counts towards the pending balance
+    pub available_on: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JournalLine {
+    pub account: String,
+    pub direction: LedgerDirection,
+    /// Minor units, always positive
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct JournalEntryWithLines {
+    #[serde(flatten)]
+    pub entry: JournalEntry,
+    pub lines: Vec<JournalLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LedgerAccountBalance {
+    pub account: String,
+    pub currency: String,
+    pub normal_balance: LedgerDirection,
+    pub debits: i64,
+    pub credits: i64,
+    /// On the account's normal side; negative when the other side is larger
+    pub balance: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LedgerBalancesResponse {
+    pub data: Vec<LedgerAccountBalance>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JournalEntryListQuery {
+    pub limit: Option<i64>,
+    pub kind: Option<String>,
+    pub payment_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntryListResponse {
+    pub data: Vec<JournalEntryWithLines>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+/// What the merchant's ledger balance holds per currency. Card payments stay pending
+/// until the settlement delay has passed; everything else is available at once.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceResponse {
+    pub available: Vec<AvailableBalance>,
+    pub pending: Vec<AvailableBalance>,
+    /// Held back under a rolling reserve; counted in neither of the others
+    pub reserved: Vec<AvailableBalance>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceTransactionType {
+    Charge,
+    Refund,
+    Fee,
+    /// A payout and its instant fee, or the return of a failed or canceled one
+    Payout,
+    /// Funds sent between a platform and a connected account, or their reversal
+    Transfer,
+    /// Funds exchanged into or out of another of the merchant's currencies
+    Conversion,
+    /// Funds held back under a rolling reserve, or their release
+    Reserve,
+    /// A movement the platform made by hand, such as a top-up
+    Adjustment,
+}
+
+impl BalanceTransactionType {
+    /// Journal entry kinds shown as this type.
+    pub fn kinds(&self) -> &'static [&'static str] {
+        match self {
+            BalanceTransactionType::Charge => &["payment"],
+            BalanceTransactionType::Refund => &["refund"],
+            BalanceTransactionType::Fee => &["fee"],
+            BalanceTransactionType::Payout => &["payout", "payout_fee", "payout_failure", "payout_fee_refund", "payout_cancellation"],
+            BalanceTransactionType::Transfer => &["transfer", "transfer_reversal"],
+            BalanceTransactionType::Conversion => &["conversion"],
+            BalanceTransactionType::Reserve => &["reserve_hold", "reserve_release"],
+            BalanceTransactionType::Adjustment => &["topup"],
+        }
+    }
+    
+    pub fn from_kind(kind: &str) -> Self {
+        match kind {
+            "payment" => BalanceTransactionType::Charge,
+            "refund" => BalanceTransactionType::Refund,
+            "fee" => BalanceTransactionType::Fee,
+            "payout" | "payout_fee" | "payout_failure" | "payout_fee_refund" | "payout_cancellation" => BalanceTransactionType::Payout,
+            "transfer" | "transfer_reversal" => BalanceTransactionType::Transfer,
+            "conversion" => BalanceTransactionType::Conversion,
+            "reserve_hold" | "reserve_release" => BalanceTransactionType::Reserve,
+            _ => BalanceTransactionType::Adjustment,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceTransactionStatus {
+    Pending,
+    Available,
+}
+
+/// The object a balance movement came from.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceTransactionSource {
+    /// transfer, payment, payout, topup or balance_conversion
+    pub object: String,
+    pub id: Uuid,
+}
+
+/// One movement of the merchant's balance, as booked to the ledger.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceTransaction {
+    /// The journal entry that moved the balance
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub transaction_type: BalanceTransactionType,
+    /// Minor units; negative when funds left the balance
+    pub amount: i64,
+    pub currency: String,
+    pub status: BalanceTransactionStatus,
+    pub available_on: DateTime<Utc>,
+    pub description: Option<String>,
+    pub source: Option<BalanceTransactionSource>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceTransactionListQuery {
+    pub limit: Option<i64>,
+    #[serde(rename = "type")]
+    pub transaction_type: Option<BalanceTransactionType>,
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceTransactionListResponse {
+    pub data: Vec<BalanceTransaction>,
+    pub has_more: bool,
+    pub url: String,
+}
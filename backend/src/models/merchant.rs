@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Where a merchant's data is stored and processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "data_region", rename_all = "snake_case")]
+pub enum DataRegion {
+    Us,
+    Eu,
+}
+
+impl DataRegion {
+    /// Value each cluster's connections advertise in `defiant.data_region`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataRegion::Us => "us",
+            DataRegion::Eu => "eu",
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "metadata_batch_object", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum MetadataBatchObject {
+    Payment,
+    Customer,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "metadata_batch_status", rename_all = "snake_case")]
+pub enum MetadataBatchStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MetadataBatchUpdate {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub object: MetadataBatchObject,
+    pub filter: serde_json::Value,
+    pub patch: serde_json::Value,
+    pub status: MetadataBatchStatus,
+    /// Objects matching the filter, counted when the job starts
+    pub matched_objects: i32,
+    /// Objects patched so far
+    pub updated_objects: i32,
+    #[serde(skip_serializing)]
+    pub cursor_created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing)]
+    pub cursor_object_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub locked_until: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Which objects a batch update applies to. Criteria are ANDed; at least one is required
+/// so an empty body cannot patch a whole account.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetadataBatchFilter {
+    pub ids: Option<Vec<Uuid>>,
+    pub created_gte: Option<DateTime<Utc>>,
+    pub created_lte: Option<DateTime<Utc>>,
+    /// Objects whose metadata contains all of these key/value pairs
+    pub metadata: Option<serde_json::Map<String, serde_json::Value>>,
+}
+
+impl MetadataBatchFilter {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_none() && self.created_gte.is_none() && self.created_lte.is_none() && self.metadata.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMetadataBatchUpdateRequest {
+    pub filter: MetadataBatchFilter,
+    /// Keys to set on each matching object; a null value removes the key
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MitAgreement {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub agreement_type: MitAgreementType,
+    #[serde(skip_serializing)]
+    pub card_fingerprint: String,
+    /// The customer-initiated payment in which consent was given
+    pub initial_payment_id: Uuid,
+    /// Scheme reference of the initial payment, quoted on every later MIT
+    pub network_transaction_id: String,
+    pub active: bool,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "mit_agreement_type", rename_all = "snake_case")]
+pub enum MitAgreementType {
+    /// Charges at varying times and amounts, e.g. account top-ups
+    Unscheduled,
+    /// Fixed-interval charges such as subscriptions
+    Recurring,
+    /// A purchase split into a known number of payments
+    Installment,
+}
@@ -5,6 +5,37 @@ pub mod webhook;
 pub mod subscription;
 pub mod invoice;
 pub mod event;
+pub mod communication;
+pub mod fx_rate;
+pub mod checkout;
+pub mod report;
+pub mod receipt;
+pub mod quote;
+pub mod payout;
+pub mod merchant;
+pub mod sca;
+pub mod mit;
+pub mod consent;
+pub mod terminal;
+pub mod settlement;
+pub mod account;
+pub mod crypto;
+pub mod metadata_batch;
+pub mod dependency;
+pub mod security;
+pub mod statement_descriptor;
+pub mod review;
+pub mod ledger;
+pub mod connect;
+pub mod pricing;
+pub mod reserve;
+pub mod token;
+pub mod auth;
+pub mod team;
+pub mod oauth;
+pub mod audit;
+pub mod pii;
+pub mod archive;
 
 pub use payment::*;
 pub use customer::*;
@@ -12,4 +43,35 @@ pub use user::*;
 pub use webhook::*;
 pub use subscription::*;
 pub use invoice::*;
-pub use event::*;
\ No newline at end of file
+pub use event::*;
+pub use communication::*;
+pub use fx_rate::*;
+pub use checkout::*;
+pub use report::*;
+pub use receipt::*;
+pub use quote::*;
+pub use payout::*;
+pub use merchant::*;
+pub use sca::*;
+pub use mit::*;
+pub use consent::*;
+pub use terminal::*;
+pub use settlement::*;
+pub use account::*;
+pub use crypto::*;
+pub use metadata_batch::*;
+pub use dependency::*;
+pub use security::*;
+pub use statement_descriptor::*;
+pub use review::*;
+pub use ledger::*;
+pub use connect::*;
+pub use pricing::*;
+pub use reserve::*;
+pub use token::*;
+pub use auth::*;
+pub use team::*;
+pub use oauth::*;
+pub use audit::*;
+pub use pii::*;
+pub use archive::*;
\ No newline at end of file
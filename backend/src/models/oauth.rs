@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// A third-party app that merchants can authorize to act on their account.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct OAuthClient {
+    pub id: Uuid,
+    /// Public identifier the app sends to `/api/oauth/authorize`, `oc_` followed by 24 characters
+    pub client_id: String,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: String,
+    /// Merchant that registered the app
+    pub merchant_id: Uuid,
+    pub name: String,
+    /// Exact URIs an authorization may redirect to
+    pub redirect_uris: Vec<String>,
+    /// The most an authorization may grant
+    pub scopes: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreateOAuthClientRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// HTTPS URIs, or `http://localhost` ones for development
+    #[validate(length(min = 1, max = 10))]
+    pub redirect_uris: Vec<String>,
+    /// Resource scopes such as `payments:read`. Apps cannot be granted the
+    /// `api_keys`, `team`, `oauth_clients`, `security` or `audit_logs` scopes.
+    #[validate(length(min = 1, max = 50))]
+    pub scopes: Vec<String>,
+}
+
+/// A newly registered app. The client secret is only ever returned here.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OAuthClientCreated {
+    #[serde(flatten)]
+    pub client: OAuthClient,
+    pub client_secret: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthClientListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OAuthClientListResponse {
+    pub data: Vec<OAuthClient>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+/// The parameters an app sends the merchant to `/api/oauth/authorize` with, which
+/// the dashboard passes on unchanged.
+#[derive(Debug, Clone, Deserialize, Serialize, Validate, ToSchema)]
+pub struct AuthorizationRequest {
+    /// Only `code` is supported
+    pub response_type: String,
+    pub client_id: String,
+    /// Defaults to the app's only redirect URI; required when it has several
+    pub redirect_uri: Option<String>,
+    /// Space-separated scopes; defaults to every scope the app registered
+    pub scope: Option<String>,
+    /// Echoed back on the redirect
+    #[validate(length(max = 500))]
+    pub state: Option<String>,
+    /// PKCE (RFC 7636) challenge, for apps that cannot keep their secret
+    #[validate(length(min = 43, max = 128))]
+    pub code_challenge: Option<String>,
+    /// Only `S256` is supported
+    pub code_challenge_method: Option<String>,
+}
+
+/// The merchant's answer on the consent screen, with the request it answers.
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct AuthorizationDecision {
+    #[serde(flatten)]
+    #[validate]
+    pub request: AuthorizationRequest,
+    pub approved: bool,
+}
+
+/// What the dashboard shows on the consent screen.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ConsentDetails {
+    pub client_id: String,
+    pub client_name: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    /// Test-mode sessions authorize test-mode access only
+    pub livemode: bool,
+}
+
+/// Where the dashboard sends the merchant once they approve or deny access.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct AuthorizationRedirect {
+    pub redirect_to: String,
+}
+
+/// `application/x-www-form-urlencoded` body of `/api/oauth/token` (RFC 6749 section 4.1.3 and 6).
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthTokenRequest {
+    /// `authorization_code` or `refresh_token`
+    pub grant_type: String,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OAuthTokenResponse {
+    /// `oat_` followed by 48 characters; use it as a bearer token on `/api/v1`
+    pub access_token: String,
+    pub token_type: String,
+    /// Seconds until the access token expires
+    pub expires_in: i64,
+    /// Replaces the one presented, which stops working
+    pub refresh_token: String,
+    /// Space-separated scopes granted
+    pub scope: String,
+}
+
+/// A grant held by an app, one per authorization.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct OAuthToken {
+    pub id: Uuid,
+    pub client_id: Uuid,
+    pub merchant_id: Uuid,
+    pub authorization_code_id: Option<Uuid>,
+    pub scopes: Vec<String>,
+    pub livemode: bool,
+    #[serde(skip_serializing)]
+    pub access_token_hash: String,
+    pub access_expires_at: DateTime<Utc>,
+    #[serde(skip_serializing)]
+    pub refresh_token_hash: String,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Form body of `/api/oauth/introspect` (RFC 7662) and `/api/oauth/revoke` (RFC 7009).
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthTokenLookup {
+    /// An access or refresh token
+    pub token: String,
+    pub token_type_hint: Option<String>,
+    pub client_id: String,
+    pub client_secret: String,
+}
+
+/// RFC 7662 response. Inactive tokens, and tokens of other apps, report only `active`.
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct IntrospectionResponse {
+    pub active: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<String>,
+    /// Unix time the access token expires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exp: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iat: Option<i64>,
+    /// The authorizing merchant
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub livemode: Option<bool>,
+}
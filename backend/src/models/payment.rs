@@ -1,12 +1,17 @@
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 use sqlx::prelude::FromRow;
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
 use validator::Validate;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+use super::{ScaStatus, ScaExemption, MitAgreementType, ConsentDetails, CardEntryMode, EncryptedCardPresentData, Chain, TransferData};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Payment {
     pub id: Uuid,
+    pub merchant_id: Uuid,
     pub amount: i64,
     pub currency: String,
     pub status: PaymentStatus,
@@ -18,11 +23,57 @@ pub struct Payment {
     pub refund_reason: Option<String>,
     pub failure_code: Option<String>,
     pub failure_message: Option<String>,
+    pub checkout_session_id: Option<Uuid>,
+    pub collected_fields: Option<serde_json::Value>,
+    pub shipping_amount: i64,
+    pub shipping: Option<serde_json::Value>,
+    pub is_donation: bool,
+    pub sca_status: ScaStatus,
+    pub sca_exemption: Option<ScaExemption>,
+    pub network_transaction_id: Option<String>,
+    pub mit_agreement_id: Option<Uuid>,
+    pub original_network_transaction_id: Option<String>,
+    pub entry_mode: Option<CardEntryMode>,
+    pub terminal_key_set_id: Option<String>,
+    pub terminal_ksn: Option<String>,
+    pub settlement_batch_id: Option<Uuid>,
+    pub stripe_charge_id: Option<String>,
+    /// Created with a live key rather than a test key
+    pub livemode: bool,
+    /// When a crypto payment still unpaid is canceled
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Invoice the payment was made against from its hosted page
+    pub invoice_id: Option<Uuid>,
+    /// Where the customer asked for crypto refunds to be sent
+    pub crypto_refund_address: Option<String>,
+    /// Set when fraud checks flagged the payment for the merchant to review
+    pub review_reason: Option<String>,
+    /// 0 to 100, higher is riskier; absent on payments created before scoring
+    pub risk_score: Option<i16>,
+    /// What drove `risk_score`
+    pub risk_reasons: Vec<String>,
+    /// What fraud checks learned from the client IP; see `GeoSignals`
+    pub geo_signals: Option<serde_json::Value>,
+    /// visa, mastercard, amex, discover or other; card payments only
+    pub card_brand: Option<String>,
+    /// ISO 3166-1 alpha-2 country the card was issued in
+    pub card_country: Option<String>,
+    /// Connected account the funds are sent on to once the payment succeeds
+    pub transfer_destination: Option<Uuid>,
+    /// Minor units sent on; the whole amount when absent
+    pub transfer_amount: Option<i64>,
+    #[serde(skip_serializing)]
+    pub card_fingerprint: Option<String>,
+    #[serde(skip_serializing)]
+    pub client_ip: Option<String>,
+    #[serde(skip_serializing)]
+    pub client_secret_hash: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub version: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
 #[sqlx(type_name = "payment_status", rename_all = "snake_case")]
 pub enum PaymentStatus {
     Pending,
@@ -36,9 +87,11 @@ pub enum PaymentStatus {
     Refunded,
     PartiallyRefunded,
     Disputed,
+    /// A crypto payment received part of its amount; the rest is due at a new address
+    PartiallyPaid,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
 #[sqlx(type_name = "payment_method", rename_all = "snake_case")]
 pub enum PaymentMethod {
     Card,
@@ -48,6 +101,8 @@ pub enum PaymentMethod {
     GooglePay,
     PayPal,
     Custom,
+    /// Read by a physical terminal; card data arrives encrypted end to end
+    CardPresent,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Validate)]
@@ -68,6 +123,46 @@ pub struct CreatePaymentRequest {
     pub customer_id: Option<Uuid>,
     
     pub source: Option<PaymentSource>,
+    
+    /// Charged without the customer present, e.g. a renewal against a saved card.
+    /// Requires `mit_agreement_id`.
+    #[serde(default)]
+    pub off_session: bool,
+    
+    /// Agreement an off-session charge is made under
+    pub mit_agreement_id: Option<Uuid>,
+    
+    /// Asks the customer, during this on-session payment, to agree to future
+    /// merchant-initiated charges on the same card
+    pub setup_future_usage: Option<MitAgreementType>,
+    
+    /// Required with `setup_future_usage`; kept as evidence of the customer's consent
+    #[validate]
+    pub consent: Option<ConsentDetails>,
+    
+    /// Required for `card_present` payments
+    #[validate]
+    pub card_present: Option<EncryptedCardPresentData>,
+    
+    /// Chain to collect a `crypto` payment on
+    #[serde(alias = "crypto_network")]
+    pub crypto_chain: Option<Chain>,
+    
+    /// ERC-20 contract to collect in instead of ether; Ethereum only
+    pub crypto_token: Option<String>,
+    
+    /// Amount due on chain in base units (satoshi, wei, lamports, token units). When
+    /// omitted, `amount` is converted at the current rate and locked for 15 minutes
+    pub crypto_amount: Option<Decimal>,
+    
+    /// The customer's IP address, not the merchant server's; counted by velocity fraud
+    /// checks. Defaults to the IP in `consent`
+    #[validate(ip)]
+    pub client_ip: Option<String>,
+    
+    /// Routes the funds to one of the platform's connected accounts; card payments only
+    #[validate]
+    pub transfer_data: Option<TransferData>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -92,6 +187,10 @@ pub struct CardDetails {
     pub cvc: String,
     
     pub name: Option<String>,
+    
+    /// ISO 3166-1 alpha-2 country of the issuing bank, from the BIN lookup at tokenization
+    #[validate(length(equal = 2))]
+    pub issuer_country: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -112,6 +211,77 @@ pub struct Address {
     pub country: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PaymentLineItem {
+    pub id: Uuid,
+    pub payment_id: Uuid,
+    pub name: String,
+    pub quantity: i32,
+    pub unit_amount: i64,
+    pub amount: i64,
+    pub tax_behavior: TaxBehavior,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whether `unit_amount` already includes tax.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "tax_behavior", rename_all = "snake_case")]
+pub enum TaxBehavior {
+    Inclusive,
+    Exclusive,
+    #[default]
+    Unspecified,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateLineItem {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    
+    #[validate(range(min = 1, max = 100000))]
+    pub quantity: i32,
+    
+    #[validate(range(min = 0))]
+    pub unit_amount: i64,
+    
+    #[serde(default)]
+    pub tax_behavior: TaxBehavior,
+    
+    pub metadata: Option<serde_json::Value>,
+}
+
+impl CreateLineItem {
+    pub fn amount(&self) -> Option<i64> {
+        self.unit_amount.checked_mul(self.quantity as i64)
+    }
+    
+    pub fn list_from_value(value: &serde_json::Value) -> Vec<Self> {
+        serde_json::from_value(value.clone()).unwrap_or_default()
+    }
+    
+    /// Sum of all line totals, or `None` on overflow.
+    pub fn total(items: &[CreateLineItem]) -> Option<i64> {
+        items.iter().try_fold(0i64, |total, item| total.checked_add(item.amount()?))
+    }
+}
+
+/// Where and how an order ships; `amount` is included in the payment amount.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShippingDetails {
+    pub name: Option<String>,
+    pub address: Address,
+    pub option_id: String,
+    pub display_name: String,
+    pub amount: i64,
+}
+
+impl ShippingDetails {
+    pub fn from_value(value: Option<&serde_json::Value>) -> Option<Self> {
+        value.and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentResponse {
     pub id: Uuid,
@@ -122,11 +292,71 @@ pub struct PaymentResponse {
     pub customer_id: Option<Uuid>,
     pub description: Option<String>,
     pub metadata: Option<serde_json::Value>,
+    pub shipping_amount: i64,
+    pub shipping: Option<ShippingDetails>,
+    pub is_donation: bool,
+    pub sca_status: ScaStatus,
+    pub sca_exemption: Option<ScaExemption>,
+    pub mit_agreement_id: Option<Uuid>,
+    pub livemode: bool,
+    /// Why fraud checks flagged the payment for review
+    pub review_reason: Option<String>,
+    pub risk_score: Option<i16>,
+    pub line_items: Vec<PaymentLineItem>,
     pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Moves forward on every update; send it back with changes to have them refused
+    /// if the object has changed since it was read
+    pub version: i64,
     pub client_secret: Option<String>,
     pub next_action: Option<NextAction>,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentListQuery {
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+    pub customer: Option<Uuid>,
+    pub status: Option<PaymentStatus>,
+}
+
+/// See `services::search::SearchFilter` for the query syntax; payment text is the description.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentSearchQuery {
+    pub query: String,
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page
+    pub cursor: Option<String>,
+}
+
+/// Payments in the mode of the key that listed them, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentsListResponse {
+    pub data: Vec<PaymentResponse>,
+    pub has_more: bool,
+    pub next_cursor: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PaymentStatusQuery {
+    pub client_secret: String,
+}
+
+/// What a customer's browser or app may learn about a payment via its client secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentStatusResponse {
+    pub id: Uuid,
+    pub status: PaymentStatus,
+    pub amount: i64,
+    pub currency: String,
+    pub livemode: bool,
+    pub failure_code: Option<String>,
+    pub failure_message: Option<String>,
+    pub next_action: Option<NextAction>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NextAction {
     Redirect { url: String },
@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+use validator::Validate;
+use rust_decimal::Decimal;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Payout {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub amount: i64,
+    pub currency: String,
+    pub status: PayoutStatus,
+    pub description: Option<String>,
+    pub arrival_date: Option<DateTime<Utc>>,
+    pub failure_code: Option<String>,
+    pub failure_message: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub paid_at: Option<DateTime<Utc>>,
+    pub failed_at: Option<DateTime<Utc>>,
+    /// Created by the merchant's payout schedule rather than on request
+    pub automatic: bool,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub method: PayoutMethod,
+    /// Minor units charged on top of `amount`; zero for standard payouts
+    pub fee: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "payout_method", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutMethod {
+    /// Arrives with the next bank settlement run
+    #[default]
+    Standard,
+    /// Sent at once for a fee, from funds card payments have made available
+    Instant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "payout_status", rename_all = "snake_case")]
+pub enum PayoutStatus {
+    Pending,
+    InTransit,
+    Paid,
+    Failed,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct Topup {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub amount: i64,
+    pub currency: String,
+    pub status: TopupStatus,
+    pub description: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+    pub succeeded_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "topup_status", rename_all = "snake_case")]
+pub enum TopupStatus {
+    Pending,
+    Succeeded,
+    Failed,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct CreatePayoutRequest {
+    /// Minor units; at most the available balance in `currency`
+    #[validate(range(min = 1))]
+    pub amount: i64,
+    #[validate(length(equal = 3))]
+    pub currency: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    /// Defaults to standard
+    #[serde(default)]
+    pub method: PayoutMethod,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PayoutListQuery {
+    pub limit: Option<i64>,
+    pub status: Option<PayoutStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutListResponse {
+    pub data: Vec<Payout>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "payout_interval", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutInterval {
+    Daily,
+    Weekly,
+    /// Only payouts the merchant creates
+    Manual,
+}
+
+/// When the available balance is paid out on its own.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PayoutSchedule {
+    pub merchant_id: Uuid,
+    pub interval: PayoutInterval,
+    /// ISO day of the week weekly payouts run on, 1 (Monday) to 7 (Sunday)
+    pub weekly_anchor: Option<i16>,
+    /// Scheduled payouts convert the other available currencies into this one first
+    pub settlement_currency: Option<String>,
+    /// Hour of the scheduled day, in `timezone`, payouts run at and take funds available
+    /// by
+    pub cutoff_hour: i16,
+    /// IANA name, e.g. `Europe/Berlin`
+    pub timezone: String,
+    /// Minor units; smaller balances roll over to the next scheduled day
+    pub minimum_payout_amount: i64,
+    /// Scheduled day, in `timezone`, payouts last ran on
+    pub last_run_on: Option<NaiveDate>,
+    /// Absent until the merchant first changes the default
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdatePayoutScheduleRequest {
+    pub interval: PayoutInterval,
+    /// Required for weekly payouts
+    #[validate(range(min = 1, max = 7))]
+    pub weekly_anchor: Option<i16>,
+    /// Omit to pay out each currency as it is
+    #[validate(length(equal = 3))]
+    pub settlement_currency: Option<String>,
+    /// Defaults to 0, midnight
+    #[validate(range(min = 0, max = 23))]
+    pub cutoff_hour: Option<i16>,
+    /// IANA name; defaults to UTC
+    #[validate(length(min = 1, max = 64))]
+    pub timezone: Option<String>,
+    /// Minor units; defaults to 0
+    #[validate(range(min = 0))]
+    pub minimum_payout_amount: Option<i64>,
+}
+
+/// Why part of the balance would stay behind on the next scheduled run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HeldBackReason {
+    BelowMinimum,
+    /// No rate for converting it into the settlement currency
+    NoExchangeRate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HeldBackBalance {
+    pub currency: String,
+    pub amount: i64,
+    pub reason: HeldBackReason,
+}
+
+/// A conversion the next scheduled run would make, at today's rate.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ConversionPreview {
+    pub from_currency: String,
+    pub from_amount: i64,
+    pub to_currency: String,
+    pub to_amount: i64,
+    pub rate: Decimal,
+}
+
+/// What the next scheduled run would pay out if nothing else moved the balance. Funds
+/// still pending count when they become available before the cutoff.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpcomingPayouts {
+    pub interval: PayoutInterval,
+    /// Absent for manual schedules
+    pub cutoff: Option<DateTime<Utc>>,
+    pub conversions: Vec<ConversionPreview>,
+    pub payouts: Vec<AvailableBalance>,
+    pub held_back: Vec<HeldBackBalance>,
+}
+
+/// Available funds exchanged from one of the merchant's currencies into another at
+/// the stored rate for the day.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct BalanceConversion {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub from_currency: String,
+    /// Minor units of `from_currency` taken from the balance
+    pub from_amount: i64,
+    pub to_currency: String,
+    /// Minor units of `to_currency` added to the balance
+    pub to_amount: i64,
+    /// Units of `to_currency` per unit of `from_currency`
+    pub rate: Decimal,
+    pub rate_date: NaiveDate,
+    pub rate_source: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceConversionListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BalanceConversionListResponse {
+    pub data: Vec<BalanceConversion>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+/// Funds that have cleared for one currency, as carried by `balance.available`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AvailableBalance {
+    pub currency: String,
+    pub amount: i64,
+}
+
+/// Payload of `balance.available`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BalanceAvailableData {
+    pub available: Vec<AvailableBalance>,
+}
@@ -0,0 +1,92 @@
+use std::fmt;
+use serde::{Serialize, Serializer};
+use sqlx::{Decode, Postgres, Type};
+use sqlx::error::BoxDynError;
+use sqlx::postgres::{PgTypeInfo, PgValueRef};
+
+use crate::{errors::DefiantError, pii};
+
+/// Personal data stored encrypted: sealed for the column and row it is written to,
+/// opened from a `SealedPii` read back from them, and plaintext everywhere in between.
+/// Write `sealed()`.
+///
+/// Debug output is redacted, so it can sit inside logged structs.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Pii {
+    plaintext: String,
+    sealed: String,
+}
+
+impl Pii {
+    /// `context` names where the value is stored, from `pii::context`.
+    pub fn seal(context: &str, plaintext: impl Into<String>) -> Result<Self, DefiantError> {
+        let plaintext = plaintext.into();
+        let sealed = pii::keyring()?.seal(context, &plaintext)?;
+        Ok(Self { plaintext, sealed })
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.plaintext
+    }
+    
+    pub fn into_string(self) -> String {
+        self.plaintext
+    }
+    
+    /// The stored form, to bind in writes.
+    pub fn sealed(&self) -> &str {
+        &self.sealed
+    }
+}
+
+impl fmt::Debug for Pii {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Pii(..)")
+    }
+}
+
+impl Serialize for Pii {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.plaintext)
+    }
+}
+
+/// A `Pii` column as stored, read with a type override, as in
+/// `email AS "email: SealedPii"`. It opens only with the context it was sealed for,
+/// so a value copied to another row or column is refused.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SealedPii(String);
+
+impl SealedPii {
+    pub fn open(&self, context: &str) -> Result<Pii, DefiantError> {
+        let plaintext = pii::keyring()?.open(context, &self.0)?;
+        Ok(Pii { plaintext, sealed: self.0.clone() })
+    }
+    
+    /// The stored form, to compare against in writes.
+    pub fn sealed(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SealedPii {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SealedPii(..)")
+    }
+}
+
+impl Type<Postgres> for SealedPii {
+    fn type_info() -> PgTypeInfo {
+        <String as Type<Postgres>>::type_info()
+    }
+    
+    fn compatible(ty: &PgTypeInfo) -> bool {
+        <String as Type<Postgres>>::compatible(ty)
+    }
+}
+
+impl<'r> Decode<'r, Postgres> for SealedPii {
+    fn decode(value: PgValueRef<'r>) -> Result<Self, BoxDynError> {
+        Ok(Self(<String as Decode<Postgres>>::decode(value)?))
+    }
+}
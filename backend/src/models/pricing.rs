@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use super::PaymentMethod;
+
+/// Plan merchants are on until an admin assigns another.
+pub const DEFAULT_PRICING_PLAN: &str = "standard";
+
+/// Card network, as told by the card number's leading digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CardBrand {
+    Visa,
+    Mastercard,
+    Amex,
+    Discover,
+    Other,
+}
+
+impl CardBrand {
+    pub fn from_number(number: &str) -> Self {
+        let prefix = |digits: usize| number.get(..digits).and_then(|p| p.parse::<u32>().ok()).unwrap_or(0);
+        match (prefix(1), prefix(2), prefix(4)) {
+            (4, _, _) => CardBrand::Visa,
+            (_, 51..=55, _) | (_, _, 2221..=2720) => CardBrand::Mastercard,
+            (_, 34 | 37, _) => CardBrand::Amex,
+            (_, 65, _) | (_, _, 6011) => CardBrand::Discover,
+            _ => CardBrand::Other,
+        }
+    }
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CardBrand::Visa => "visa",
+            CardBrand::Mastercard => "mastercard",
+            CardBrand::Amex => "amex",
+            CardBrand::Discover => "discover",
+            CardBrand::Other => "other",
+        }
+    }
+}
+
+/// Where a card was issued relative to the merchant's data region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "card_region", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum CardRegion {
+    /// Issued in the merchant's region: the US, or the EEA and UK
+    Domestic,
+    International,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PricingPlan {
+    pub code: String,
+    pub name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Fee for the payments a rule matches. Unset criteria match anything; the rule
+/// matching the most criteria wins.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct PricingRule {
+    pub id: Uuid,
+    pub plan_code: String,
+    pub payment_method: Option<PaymentMethod>,
+    /// visa, mastercard, amex, discover or other
+    pub card_brand: Option<String>,
+    pub card_region: Option<CardRegion>,
+    /// Of the payment amount
+    pub percent_bps: i32,
+    /// Minor units of the payment's currency
+    pub fixed: i64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct PricingRuleInput {
+    pub payment_method: Option<PaymentMethod>,
+    pub card_brand: Option<CardBrand>,
+    pub card_region: Option<CardRegion>,
+    #[validate(range(min = 0, max = 10000))]
+    pub percent_bps: i32,
+    #[validate(range(min = 0))]
+    #[serde(default)]
+    pub fixed: i64,
+}
+
+/// Replaces a plan's rules, creating the plan if needed.
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdatePricingPlanRequest {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    #[validate]
+    pub rules: Vec<PricingRuleInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PricingPlanWithRules {
+    #[serde(flatten)]
+    pub plan: PricingPlan,
+    pub rules: Vec<PricingRule>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct AssignPricingPlanRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub plan: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
+pub struct FeeSchedule {
+    pub percent_bps: i64,
+    pub fixed: i64,
+}
+
+/// What a merchant is charged: its plan's rules, most specific first, and the card fee
+/// applied when none of them matches. Unmatched non-card payments carry no fee.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EffectivePricing {
+    pub merchant_id: Uuid,
+    pub plan: String,
+    pub rules: Vec<PricingRule>,
+    pub default_card_fee: FeeSchedule,
+}
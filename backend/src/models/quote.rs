@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Quote {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub status: QuoteStatus,
+    pub number: Option<String>,
+    pub currency: String,
+    pub amount_total: i64,
+    pub description: Option<String>,
+    pub plan_id: Option<Uuid>,
+    #[serde(skip_serializing)]
+    pub acceptance_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub finalized_at: Option<DateTime<Utc>>,
+    pub accepted_at: Option<DateTime<Utc>>,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub invoice_id: Option<Uuid>,
+    pub subscription_id: Option<Uuid>,
+    pub metadata: Option<serde_json::Value>,
+    /// Created with a live key rather than a test key
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// `draft -> open -> accepted -> invoiced`. Quotes for a plan stop at
+/// `accepted` once their subscription is created.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "quote_status", rename_all = "snake_case")]
+pub enum QuoteStatus {
+    Draft,
+    Open,
+    Accepted,
+    Invoiced,
+    Canceled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct QuoteLineItem {
+    pub id: Uuid,
+    pub quote_id: Uuid,
+    pub description: String,
+    pub quantity: i32,
+    pub unit_amount: i64,
+    pub amount: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateQuoteRequest {
+    pub customer_id: Uuid,
+    
+    #[validate(length(min = 3, max = 3))]
+    pub currency: String,
+    
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    
+    /// Accepting the quote starts a subscription to this plan instead of invoicing the line items
+    pub plan_id: Option<Uuid>,
+    
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+    
+    #[serde(default)]
+    #[validate(length(max = 250))]
+    #[validate]
+    pub line_items: Vec<CreateQuoteLineItem>,
+    
+    pub metadata: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateQuoteLineItem {
+    #[validate(length(min = 1, max = 500))]
+    pub description: String,
+    
+    #[validate(range(min = 1, max = 100000))]
+    pub quantity: i32,
+    
+    #[validate(range(min = 0))]
+    pub unit_amount: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AcceptQuoteRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostedQuoteQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteResponse {
+    #[serde(flatten)]
+    pub quote: Quote,
+    pub line_items: Vec<QuoteLineItem>,
+    /// Signed link the customer uses to review and accept; present once finalized
+    pub hosted_url: Option<String>,
+}
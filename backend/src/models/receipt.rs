@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+
+use super::{PaymentLineItem, ShippingDetails};
+use crate::services::fx_service::format_amount;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Receipt {
+    pub payment_id: Uuid,
+    pub merchant_name: String,
+    pub customer_email: String,
+    pub currency: String,
+    pub line_items: Vec<PaymentLineItem>,
+    /// Amount before shipping
+    pub subtotal: i64,
+    pub shipping: Option<ShippingDetails>,
+    pub total: i64,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Receipt {
+    pub fn subject(&self) -> String {
+        format!("Your receipt from {}", self.merchant_name)
+    }
+    
+    pub fn render_html(&self) -> String {
+        let mut rows: String = self.line_items
+            .iter()
+            .map(|item| format!(
+                "<tr><td>{} &times; {}</td><td>{}</td></tr>",
+                item.quantity,
+                html_escape(&item.name),
+                format_amount(item.amount, &self.currency)
+            ))
+            .collect();
+        
+        rows.push_str(&format!(
+            "<tr><td>Subtotal</td><td>{}</td></tr>",
+            format_amount(self.subtotal, &self.currency)
+        ));
+        
+        if let Some(shipping) = &self.shipping {
+            rows.push_str(&format!(
+                "<tr><td>Shipping ({})</td><td>{}</td></tr>",
+                html_escape(&shipping.display_name),
+                format_amount(shipping.amount, &self.currency)
+            ));
+        }
+        
+        rows.push_str(&format!(
+            "<tr><td><strong>Total</strong></td><td><strong>{}</strong></td></tr>",
+            format_amount(self.total, &self.currency)
+        ));
+        
+        format!(
+            "<h2>Receipt from {}</h2><p>{}</p><p>Payment {} on {}</p><table>{}</table>",
+            html_escape(&self.merchant_name),
+            html_escape(self.description.as_deref().unwrap_or("")),
+            self.payment_id,
+            self.created_at.format("%Y-%m-%d"),
+            rows
+        )
+    }
+}
+
+pub fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
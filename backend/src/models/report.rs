@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+use super::Pii;
+
+/// Outstanding invoice balance for one customer in one currency, bucketed by days past due.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArAgingRow {
+    pub customer_id: Uuid,
+    pub customer_email: String,
+    pub customer_name: Option<String>,
+    pub currency: String,
+    pub days_0_30: i64,
+    pub days_31_60: i64,
+    pub days_61_90: i64,
+    pub days_over_90: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArAgingTotals {
+    pub currency: String,
+    pub days_0_30: i64,
+    pub days_31_60: i64,
+    pub days_61_90: i64,
+    pub days_over_90: i64,
+    pub total: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArAgingReport {
+    pub as_of: NaiveDate,
+    pub customers: Vec<ArAgingRow>,
+    pub totals: Vec<ArAgingTotals>,
+}
+
+impl ArAgingReport {
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("customer_id,customer_email,customer_name,currency,days_0_30,days_31_60,days_61_90,days_over_90,total\n");
+        
+        for row in &self.customers {
+            out.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{}\n",
+                row.customer_id,
+                csv_escape(&row.customer_email),
+                csv_escape(row.customer_name.as_deref().unwrap_or("")),
+                row.currency,
+                row.days_0_30,
+                row.days_31_60,
+                row.days_61_90,
+                row.days_over_90,
+                row.total,
+            ));
+        }
+        
+        for total in &self.totals {
+            out.push_str(&format!(
+                "TOTAL,,,{},{},{},{},{},{}\n",
+                total.currency,
+                total.days_0_30,
+                total.days_31_60,
+                total.days_61_90,
+                total.days_over_90,
+                total.total,
+            ));
+        }
+        
+        out
+    }
+}
+
+/// Invoiced revenue for one currency. Void and uncollectible invoices are
+/// excluded from `revenue`; write-offs are reported separately for audit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevenueSummary {
+    pub currency: String,
+    pub invoice_count: i64,
+    pub revenue: i64,
+    pub collected: i64,
+    pub uncollectible_count: i64,
+    pub written_off: i64,
+}
+
+/// One payment in a merchant export; `amount` includes `shipping_amount`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PaymentExportRow {
+    pub id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub status: String,
+    pub payment_method: String,
+    pub currency: String,
+    pub amount: i64,
+    pub shipping_amount: i64,
+    pub customer_email: Option<Pii>,
+    pub description: Option<String>,
+    pub shipping_option: Option<String>,
+    pub shipping_country: Option<String>,
+    pub is_donation: bool,
+    /// e.g. "2 x T-shirt; 1 x Mug"
+    pub line_items: Option<String>,
+    pub risk_score: Option<i16>,
+    /// e.g. "large_amount; no_customer"
+    pub risk_reasons: Option<String>,
+}
+
+pub fn payments_to_csv(rows: &[PaymentExportRow]) -> String {
+    let mut out = String::from("id,created_at,status,payment_method,currency,subtotal,shipping_amount,amount,customer_email,description,shipping_option,shipping_country,is_donation,line_items,risk_score,risk_reasons\n");
+    
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            row.id,
+            row.created_at.to_rfc3339(),
+            row.status,
+            row.payment_method,
+            row.currency,
+            row.amount - row.shipping_amount,
+            row.shipping_amount,
+            row.amount,
+            csv_escape(row.customer_email.as_ref().map(Pii::as_str).unwrap_or("")),
+            csv_escape(row.description.as_deref().unwrap_or("")),
+            csv_escape(row.shipping_option.as_deref().unwrap_or("")),
+            row.shipping_country.as_deref().unwrap_or(""),
+            row.is_donation,
+            csv_escape(row.line_items.as_deref().unwrap_or("")),
+            row.risk_score.map(|score| score.to_string()).unwrap_or_default(),
+            csv_escape(row.risk_reasons.as_deref().unwrap_or("")),
+        ));
+    }
+    
+    out
+}
+
+/// What one source contributed to a payout: a charge net of its processing fee, a
+/// refund, or an adjustment such as a top-up. Rows of a payout that paid out the
+/// whole available balance net to its amount.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayoutReconciliationRow {
+    pub payout_id: Uuid,
+    pub payout_created_at: DateTime<Utc>,
+    /// When the funds are expected in the bank account
+    pub arrival_date: Option<DateTime<Utc>>,
+    pub payout_amount: i64,
+    pub currency: String,
+    /// charge, refund, transfer, conversion, reserve or adjustment
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    /// Payment for charges, refunds and reserves, transfer for transfers, balance
+    /// conversion for conversions, top-up for top-ups
+    pub source_id: Option<Uuid>,
+    pub gross: i64,
+    pub fee: i64,
+    pub net: i64,
+    pub available_on: DateTime<Utc>,
+}
+
+pub fn payout_reconciliation_to_csv(rows: &[PayoutReconciliationRow]) -> String {
+    let mut out = String::from("payout_id,payout_created_at,arrival_date,payout_amount,currency,type,source_id,gross,fee,net,available_on\n");
+    
+    for row in rows {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{}\n",
+            row.payout_id,
+            row.payout_created_at.to_rfc3339(),
+            row.arrival_date.map(|date| date.to_rfc3339()).unwrap_or_default(),
+            row.payout_amount,
+            row.currency,
+            row.transaction_type,
+            row.source_id.map(|id| id.to_string()).unwrap_or_default(),
+            row.gross,
+            row.fee,
+            row.net,
+            row.available_on.to_rfc3339(),
+        ));
+    }
+    
+    out
+}
+
+pub fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportQuery {
+    pub as_of: Option<NaiveDate>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub payout_id: Option<Uuid>,
+    pub format: Option<String>,
+}
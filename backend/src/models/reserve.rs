@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// Share of each card payment held back from the available balance, e.g. 10% for 90
+/// days.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RollingReserve {
+    pub merchant_id: Uuid,
+    /// Basis points of the payment amount
+    pub percent_bps: i32,
+    /// Held this long after the payment's funds become available
+    pub hold_days: i32,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdateRollingReserveRequest {
+    #[validate(range(min = 1, max = 10000))]
+    pub percent_bps: i32,
+    #[validate(range(min = 1, max = 365))]
+    pub hold_days: i32,
+}
+
+/// Funds held back from one payment.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct ReserveHold {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub payment_id: Uuid,
+    pub currency: String,
+    /// Minor units
+    pub amount: i64,
+    pub release_on: DateTime<Utc>,
+    /// Set once the funds are back in the balance
+    pub released_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
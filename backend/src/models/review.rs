@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use super::{Payment, PaymentMethod, PaymentStatus};
+
+/// What the customer's IP address says about them, next to what the payment claims.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct GeoSignals {
+    pub ip_address: String,
+    /// ISO 3166-1 alpha-2 country the IP is located in
+    pub ip_country: Option<String>,
+    /// Any anonymizer: open proxy, VPN, Tor or hosting provider
+    pub ip_is_proxy: bool,
+    pub ip_is_vpn: bool,
+    pub billing_country: Option<String>,
+    pub card_country: Option<String>,
+    /// Both countries are known and differ
+    pub billing_country_mismatch: bool,
+    pub card_country_mismatch: bool,
+}
+
+/// A payment fraud checks let through flagged for review, with what flagged it.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PaymentReview {
+    pub payment_id: Uuid,
+    pub amount: i64,
+    pub currency: String,
+    pub status: PaymentStatus,
+    pub payment_method: PaymentMethod,
+    pub customer_id: Uuid,
+    pub review_reason: String,
+    pub risk_score: Option<i16>,
+    pub risk_reasons: Vec<String>,
+    /// Absent when the payment carried no client IP, or it could not be looked up
+    pub geo_signals: Option<GeoSignals>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PaymentReview {
+    pub fn from_payment(payment: Payment) -> Self {
+        Self {
+            payment_id: payment.id,
+            amount: payment.amount,
+            currency: payment.currency,
+            status: payment.status,
+            payment_method: payment.payment_method,
+            customer_id: payment.customer_id,
+            review_reason: payment.review_reason.unwrap_or_default(),
+            risk_score: payment.risk_score,
+            risk_reasons: payment.risk_reasons,
+            geo_signals: payment.geo_signals.and_then(|signals| serde_json::from_value(signals).ok()),
+            created_at: payment.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReviewListQuery {
+    pub limit: Option<i64>,
+    pub customer_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewListResponse {
+    pub data: Vec<PaymentReview>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "fraud_label", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum FraudLabel {
+    Fraudulent,
+    Safe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "blocklist_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum BlocklistKind {
+    CardFingerprint,
+    IpAddress,
+    Customer,
+}
+
+/// A merchant's verdict on a payment, kept with the rules that fired on it.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FraudLabelRecord {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub payment_id: Uuid,
+    pub label: FraudLabel,
+    /// Risk reasons, and the velocity counter that opened a review
+    pub rules: Vec<String>,
+    pub risk_score: Option<i16>,
+    pub amount: i64,
+    pub currency: String,
+    pub note: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate, ToSchema)]
+pub struct LabelPaymentRequest {
+    #[validate(length(max = 1000))]
+    pub note: Option<String>,
+}
+
+/// How well one rule has picked out the payments merchants labeled fraudulent.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FraudRuleStats {
+    pub rule: String,
+    /// Labeled fraudulent, and the rule fired
+    pub true_positives: i64,
+    /// Labeled safe, but the rule fired
+    pub false_positives: i64,
+    /// Labeled fraudulent, and the rule did not fire
+    pub false_negatives: i64,
+    /// Absent until the rule has fired on a labeled payment
+    pub precision: Option<f64>,
+    /// Absent until a payment has been labeled fraudulent
+    pub recall: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FraudRuleStatsResponse {
+    pub labeled_fraudulent: i64,
+    pub labeled_safe: i64,
+    pub data: Vec<FraudRuleStats>,
+}
+
+/// Switches and limits the merchant's fraud checks run with.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct FraudSettings {
+    pub merchant_id: Uuid,
+    /// Whether payments are counted per card, IP address and customer, and flagged or
+    /// declined when they come too fast
+    pub velocity_checks_enabled: bool,
+    /// Larger payments are flagged for review; never when absent
+    pub max_amount_without_review: Option<i64>,
+    /// Card payments at least this large always go through 3DS; never on amount alone
+    /// when absent
+    pub three_ds_amount_threshold: Option<i64>,
+    /// Of both amounts; payments in other currencies are converted at the day's rate
+    pub currency: String,
+    /// Payments from these countries, by IP address, billing address or card issuer,
+    /// are declined
+    pub blocked_countries: Vec<String>,
+    /// Absent until the merchant first changes the defaults
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdateFraudSettingsRequest {
+    /// Defaults to true
+    pub velocity_checks_enabled: Option<bool>,
+    #[validate(range(min = 1))]
+    pub max_amount_without_review: Option<i64>,
+    #[validate(range(min = 1))]
+    pub three_ds_amount_threshold: Option<i64>,
+    /// Of both amounts; defaults to USD
+    #[validate(length(equal = 3))]
+    pub currency: Option<String>,
+    /// ISO 3166-1 alpha-2 codes
+    #[serde(default)]
+    pub blocked_countries: Vec<String>,
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+/// PSD2 grounds for skipping strong customer authentication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "sca_exemption", rename_all = "snake_case")]
+pub enum ScaExemption {
+    /// Under EUR 30, within the per-card cumulative limits
+    LowValue,
+    /// Under the merchant's fraud-rate-dependent TRA threshold
+    TransactionRiskAnalysis,
+    /// Off-session charge against a stored mandate; out of SCA scope
+    MerchantInitiated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "sca_status", rename_all = "snake_case")]
+pub enum ScaStatus {
+    /// Card issued outside the EEA, or not a card payment
+    NotRequired,
+    Exempted,
+    ChallengeRequired,
+    /// The issuer refused the exemption (soft decline) and asked for a challenge
+    ExemptionDeclined,
+    Authenticated,
+}
+
+/// Outcome of the exemption engine for one authorization attempt.
+#[derive(Debug, Clone)]
+pub struct ScaDecision {
+    pub status: ScaStatus,
+    pub exemption: Option<ScaExemption>,
+    /// Payment amount in euro cents, the unit PSD2 thresholds are defined in
+    pub amount_eur: i64,
+    pub card_fingerprint: Option<String>,
+}
+
+impl ScaDecision {
+    pub fn not_required() -> Self {
+        Self {
+            status: ScaStatus::NotRequired,
+            exemption: None,
+            amount_eur: 0,
+            card_fingerprint: None,
+        }
+    }
+    
+    pub fn requires_challenge(&self) -> bool {
+        matches!(self.status, ScaStatus::ChallengeRequired | ScaStatus::ExemptionDeclined)
+    }
+}
+
+/// How a merchant acts on payment risk scores.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct RiskSettings {
+    pub merchant_id: Uuid,
+    /// Card payments scoring at least this are sent through 3DS
+    pub challenge_score: i16,
+    /// Payments scoring at least this are declined outright; never when absent
+    pub block_score: Option<i16>,
+    /// Whether risky card payments under the PSD2 low-value limit may still skip the
+    /// challenge
+    pub low_value_exemption: bool,
+    /// Absent until the merchant first changes the defaults
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct UpdateRiskSettingsRequest {
+    /// 0 to 100
+    pub challenge_score: i16,
+    /// Above `challenge_score`; omit to never decline on score alone
+    pub block_score: Option<i16>,
+    /// Defaults to true
+    pub low_value_exemption: Option<bool>,
+}
@@ -0,0 +1,184 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "trusted_source_kind", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TrustedSourceKind {
+    /// Requests from addresses inside `ip_range`
+    IpRange,
+    /// Requests signed with the private half of `public_key`
+    ServiceIdentity,
+}
+
+/// A merchant backend that gets the trusted rate limit.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrustedSource {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub kind: TrustedSourceKind,
+    pub name: String,
+    pub ip_range: Option<String>,
+    pub public_key: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateTrustedSourceRequest {
+    pub kind: TrustedSourceKind,
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+    /// CIDR such as `203.0.113.0/24`; required for `ip_range`
+    pub ip_range: Option<String>,
+    /// Hex-encoded Ed25519 public key; required for `service_identity`
+    pub public_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "rate_limit_tier", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitTier {
+    #[default]
+    Standard,
+    Growth,
+    Enterprise,
+}
+
+impl RateLimitTier {
+    /// How many times the configured base quotas the tier gets.
+    pub fn multiplier(self) -> u32 {
+        match self {
+            RateLimitTier::Standard => 1,
+            RateLimitTier::Growth => 5,
+            RateLimitTier::Enterprise => 25,
+        }
+    }
+}
+
+/// A merchant's quotas, as the rate limiter applies them.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RateLimitPolicy {
+    pub merchant_id: Uuid,
+    pub tier: RateLimitTier,
+    /// Requests per period for each API key or session
+    pub requests_per_period: u32,
+    /// Requests per period for trusted sources, shared across all of them
+    pub trusted_requests_per_period: u32,
+    pub period_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdateRateLimitTierRequest {
+    pub tier: RateLimitTier,
+}
+
+/// Rate limits applied to the merchant's traffic, and the sources that qualify for
+/// the trusted one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecuritySettings {
+    pub rate_limit_tier: RateLimitTier,
+    /// Requests per period for each API key or session not calling from a trusted source
+    pub rate_limit_requests: u32,
+    /// Requests per period for trusted sources, shared across all of them
+    pub trusted_rate_limit_requests: u32,
+    pub rate_limit_period_secs: u64,
+    pub trusted_sources: Vec<TrustedSource>,
+    /// Whether secret-key requests must carry a `Defiant-Request-Signature`
+    pub request_signing_enabled: bool,
+}
+
+/// The signing secret, shown only when signing is turned on or the secret rotated.
+///
+/// Sign each request as `Defiant-Request-Signature: t=<unix seconds>,n=<nonce>,v1=<hex>`,
+/// where `v1` is the HMAC-SHA256 under `secret` of `"<t>.<n>.<METHOD>.<path and query>.<body>"`.
+/// `t` must be within five minutes of our clock and each nonce used only once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestSigningSecret {
+    pub secret: String,
+    pub enabled_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "security_event_type", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+    LoginSucceeded,
+    /// A wrong password or MFA code for a known account
+    LoginFailed,
+    ApiKeyCreated,
+    ApiKeyRevoked,
+    MfaEnabled,
+    /// Signed in with a backup code instead of the authenticator app
+    MfaBackupCodeUsed,
+    MfaPolicyUpdated,
+    /// A key was used from outside its IP allowlist
+    IpNotAllowed,
+}
+
+/// An entry in the merchant's security log. Suspicious entries are also sent as
+/// `security_event.suspicious` to endpoints subscribed to it.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct SecurityEvent {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: SecurityEventType,
+    /// The team member or merchant signing in or enrolling, when the event concerns one
+    pub subject: Option<String>,
+    /// The key created, revoked or refused
+    pub api_key_id: Option<Uuid>,
+    /// Unset when the client address is unknown
+    pub ip_address: Option<String>,
+    pub details: serde_json::Value,
+    /// Part of a burst of failed sign-ins, or a refused request
+    pub suspicious: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What a service records; the log fills in the rest.
+#[derive(Debug, Clone)]
+pub struct NewSecurityEvent {
+    pub merchant_id: Uuid,
+    pub event_type: SecurityEventType,
+    pub subject: Option<String>,
+    pub api_key_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub details: serde_json::Value,
+}
+
+impl NewSecurityEvent {
+    pub fn new(merchant_id: Uuid, event_type: SecurityEventType) -> Self {
+        Self {
+            merchant_id,
+            event_type,
+            subject: None,
+            api_key_id: None,
+            ip_address: None,
+            details: serde_json::json!({}),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SecurityEventListQuery {
+    #[serde(rename = "type")]
+    pub event_type: Option<SecurityEventType>,
+    pub subject: Option<String>,
+    pub api_key_id: Option<Uuid>,
+    /// Only suspicious events
+    pub suspicious: Option<bool>,
+    pub created_gte: Option<DateTime<Utc>>,
+    pub created_lte: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SecurityEventListResponse {
+    pub data: Vec<SecurityEvent>,
+    pub has_more: bool,
+    pub url: String,
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, NaiveDate, Utc};
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SettlementBatch {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub business_date: NaiveDate,
+    pub currency: String,
+    pub status: SettlementBatchStatus,
+    pub file_name: String,
+    /// The file as submitted to the acquirer; served by the download endpoint
+    #[serde(skip_serializing)]
+    pub file_contents: String,
+    pub transaction_count: i32,
+    pub total_amount: i64,
+    pub accepted_count: i32,
+    pub rejected_count: i32,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "settlement_batch_status", rename_all = "snake_case")]
+pub enum SettlementBatchStatus {
+    /// Written and awaiting the acquirer's acknowledgment
+    Generated,
+    Accepted,
+    PartiallyAccepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SettlementBatchItem {
+    pub id: Uuid,
+    pub batch_id: Uuid,
+    pub payment_id: Uuid,
+    pub sequence_number: i32,
+    pub amount: i64,
+    pub status: SettlementItemStatus,
+    pub reject_code: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "settlement_item_status", rename_all = "snake_case")]
+pub enum SettlementItemStatus {
+    Pending,
+    Accepted,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettlementBatchResponse {
+    #[serde(flatten)]
+    pub batch: SettlementBatch,
+    pub items: Vec<SettlementBatchItem>,
+}
+
+/// A transaction line as written to the acquirer file.
+#[derive(Debug, Clone)]
+pub struct SettlementRecord {
+    pub sequence_number: i32,
+    pub payment_id: Uuid,
+    pub amount: i64,
+    pub network_transaction_id: Option<String>,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Builds the acquirer CSV: one `H` header, a `D` line per captured transaction and a
+/// `T` trailer carrying the count and total the acquirer uses to check the file.
+pub fn settlement_file(
+    batch_id: Uuid,
+    acquirer_merchant_id: &str,
+    business_date: NaiveDate,
+    currency: &str,
+    records: &[SettlementRecord],
+) -> String {
+    let mut out = format!(
+        "H,{},{},{},{}\n",
+        batch_id,
+        acquirer_merchant_id,
+        business_date.format("%Y%m%d"),
+        currency,
+    );
+    
+    for record in records {
+        out.push_str(&format!(
+            "D,{},SALE,{},{},{},{}\n",
+            record.sequence_number,
+            record.payment_id,
+            record.network_transaction_id.as_deref().unwrap_or(""),
+            record.amount,
+            record.captured_at.format("%Y%m%d%H%M%S"),
+        ));
+    }
+    
+    let total: i64 = records.iter().map(|r| r.amount).sum();
+    out.push_str(&format!("T,{},{}\n", records.len(), total));
+    out
+}
+
+/// One line of an acknowledgment file: `A,<batch id>,<ACCEPTED|REJECTED|PARTIAL>` once,
+/// then `R,<sequence number>,<reject code>` for every rejected transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AcknowledgmentLine {
+    Batch { batch_id: Uuid, disposition: String },
+    Rejected { sequence_number: i32, reject_code: String },
+}
+
+pub fn parse_acknowledgment(contents: &str) -> Result<Vec<AcknowledgmentLine>, String> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            match fields.as_slice() {
+                ["A", batch_id, disposition] => Ok(AcknowledgmentLine::Batch {
+                    batch_id: batch_id.parse().map_err(|_| format!("line {}: invalid batch id", i + 1))?,
+                    disposition: disposition.to_uppercase(),
+                }),
+                ["R", sequence_number, reject_code] => Ok(AcknowledgmentLine::Rejected {
+                    sequence_number: sequence_number
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid sequence number", i + 1))?,
+                    reject_code: reject_code.to_string(),
+                }),
+                _ => Err(format!("line {}: unrecognised record", i + 1)),
+            }
+        })
+        .collect()
+}
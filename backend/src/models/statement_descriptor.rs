@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct PreviewStatementDescriptorRequest {
+    /// Defaults to the merchant's name
+    #[validate(length(min = 1, max = 255))]
+    pub descriptor: Option<String>,
+    /// Per-payment part shown after the descriptor, such as an order number
+    #[validate(length(min = 1, max = 255))]
+    pub suffix: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardNetwork {
+    Visa,
+    Mastercard,
+    Amex,
+    Discover,
+}
+
+/// A character the network cannot print, and what it prints instead (nothing when
+/// `replacement` is empty).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CharacterSubstitution {
+    pub original: String,
+    pub replacement: String,
+}
+
+/// How one network's statements show the descriptor.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementRendering {
+    pub network: CardNetwork,
+    pub text: String,
+    /// Characters the network's statement line holds
+    pub max_length: usize,
+    pub truncated: bool,
+    pub substitutions: Vec<CharacterSubstitution>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementDescriptorPreview {
+    /// The descriptor as submitted, joined to the suffix when one was given
+    pub descriptor: String,
+    pub valid: bool,
+    /// Network rules the descriptor breaks
+    pub errors: Vec<String>,
+    /// Ways the descriptor may read differently from what was intended, or fail to be
+    /// recognized by customers
+    pub warnings: Vec<String>,
+    pub renderings: Vec<StatementRendering>,
+}
@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Months, Utc};
+use validator::Validate;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub status: SubscriptionStatus,
+    pub plan_id: Uuid,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+    pub cancel_at_period_end: bool,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub trial_start: Option<DateTime<Utc>>,
+    pub trial_end: Option<DateTime<Utc>>,
+    pub metadata: Option<serde_json::Value>,
+    /// Created with a live key rather than a test key
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Plan the subscription switches to at its next renewal
+    pub pending_plan_id: Option<Uuid>,
+    pub version: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    Active,
+    PastDue,
+    Unpaid,
+    Canceled,
+    Incomplete,
+    IncompleteExpired,
+    Trialing,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Plan {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub product_id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub amount: i64,
+    pub currency: String,
+    pub interval: String,
+    pub interval_count: i32,
+    pub trial_period_days: i32,
+    pub active: bool,
+    pub metadata: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Plan {
+    /// End of a billing period of this plan starting at `start`.
+    pub fn period_end(&self, start: DateTime<Utc>) -> DateTime<Utc> {
+        let count = self.interval_count.max(1) as u32;
+        match self.interval.as_str() {
+            "day" => start + Duration::days(count as i64),
+            "week" => start + Duration::weeks(count as i64),
+            "year" => start.checked_add_months(Months::new(12 * count)).unwrap_or(start),
+            _ => start.checked_add_months(Months::new(count)).unwrap_or(start),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateSubscriptionRequest {
+    pub customer_id: Uuid,
+    pub plan_id: Uuid,
+    /// Overrides the plan's trial; 0 starts billing immediately
+    #[validate(range(min = 0, max = 730))]
+    pub trial_period_days: Option<i32>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// When a plan change takes effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionChangeTiming {
+    /// Switch now; the new plan is first billed at the next renewal
+    #[default]
+    Immediately,
+    /// Switch at the next renewal, keeping the current plan until then
+    PeriodEnd,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateSubscriptionRequest {
+    pub plan_id: Option<Uuid>,
+    #[serde(default)]
+    pub change_timing: SubscriptionChangeTiming,
+    /// `true` schedules cancellation for the end of the period; `false` withdraws it
+    pub cancel_at_period_end: Option<bool>,
+    pub metadata: Option<serde_json::Value>,
+    /// The subscription's `version` when the client read it
+    pub version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CancelSubscriptionRequest {
+    /// Keep the subscription until the period it has been billed for ends
+    #[serde(default)]
+    pub at_period_end: bool,
+    /// The subscription's `version` when the client read it
+    pub version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubscriptionListQuery {
+    pub limit: Option<i64>,
+    pub customer_id: Option<Uuid>,
+    pub status: Option<SubscriptionStatus>,
+}
+
+/// What happens to the subscription when its current period ends.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PendingSubscriptionUpdate {
+    /// Plan the subscription renews on instead of its current one
+    pub plan_id: Option<Uuid>,
+    /// The subscription ends instead of renewing
+    pub cancel: bool,
+    pub effective_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubscriptionResponse {
+    pub id: Uuid,
+    pub customer_id: Uuid,
+    pub status: SubscriptionStatus,
+    pub plan_id: Uuid,
+    pub current_period_start: DateTime<Utc>,
+    pub current_period_end: DateTime<Utc>,
+    pub cancel_at_period_end: bool,
+    pub canceled_at: Option<DateTime<Utc>>,
+    pub trial_start: Option<DateTime<Utc>>,
+    pub trial_end: Option<DateTime<Utc>>,
+    pub metadata: Option<serde_json::Value>,
+    pub pending_update: Option<PendingSubscriptionUpdate>,
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    /// Moves forward on every update; send it back with changes to have them refused
+    /// if the object has changed since it was read
+    pub version: i64,
+}
+
+impl From<Subscription> for SubscriptionResponse {
+    fn from(subscription: Subscription) -> Self {
+        let pending_update = (subscription.cancel_at_period_end || subscription.pending_plan_id.is_some())
+            .then(|| PendingSubscriptionUpdate {
+                plan_id: subscription.pending_plan_id,
+                cancel: subscription.cancel_at_period_end,
+                effective_at: subscription.current_period_end,
+            });
+        
+        Self {
+            id: subscription.id,
+            customer_id: subscription.customer_id,
+            status: subscription.status,
+            plan_id: subscription.plan_id,
+            current_period_start: subscription.current_period_start,
+            current_period_end: subscription.current_period_end,
+            cancel_at_period_end: subscription.cancel_at_period_end,
+            canceled_at: subscription.canceled_at,
+            trial_start: subscription.trial_start,
+            trial_end: subscription.trial_end,
+            metadata: subscription.metadata,
+            pending_update,
+            livemode: subscription.livemode,
+            created_at: subscription.created_at,
+            version: subscription.version,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubscriptionListResponse {
+    pub data: Vec<SubscriptionResponse>,
+    pub has_more: bool,
+    pub url: String,
+}
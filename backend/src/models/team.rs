@@ -0,0 +1,77 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// What a team member may do in the dashboard. See `role_scopes` for the scopes
+/// each role grants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "team_role", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TeamRole {
+    /// Everything, including managing other owners
+    Owner,
+    /// Everything except managing owners
+    Admin,
+    /// API keys, webhooks, events and OAuth apps, with read access elsewhere
+    Developer,
+    /// Read-only access, including reports
+    Analyst,
+    /// Customer and payment support: refunds, customer updates and lookups
+    Support,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, ToSchema)]
+pub struct TeamMember {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub email: String,
+    pub role: TeamRole,
+    #[serde(skip_serializing)]
+    pub password_hash: Option<String>,
+    #[serde(skip_serializing)]
+    pub invite_token_hash: Option<String>,
+    pub invite_expires_at: Option<DateTime<Utc>>,
+    /// API key or session that sent the invitation
+    pub invited_by: Option<String>,
+    /// Unset while the invitation is pending
+    pub accepted_at: Option<DateTime<Utc>>,
+    /// Set once the member proves they control the address, on accepting the invitation
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct InviteTeamMemberRequest {
+    #[validate(email)]
+    pub email: String,
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct UpdateTeamMemberRequest {
+    pub role: TeamRole,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TeamMemberListQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TeamMemberListResponse {
+    pub data: Vec<TeamMember>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate, ToSchema)]
+pub struct AcceptInvitationRequest {
+    #[validate(length(min = 1))]
+    pub token: String,
+    #[validate(length(min = 12, max = 128))]
+    pub password: String,
+}
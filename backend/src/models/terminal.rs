@@ -0,0 +1,93 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type, ToSchema)]
+#[sqlx(type_name = "card_entry_mode", rename_all = "snake_case")]
+pub enum CardEntryMode {
+    Chip,
+    Contactless,
+    Magstripe,
+    /// Swiped after the chip could not be read
+    FallbackMagstripe,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "card_encryption_scheme", rename_all = "snake_case")]
+pub enum CardEncryptionScheme {
+    DukptTdes,
+    DukptAes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "terminal_key_status", rename_all = "snake_case")]
+pub enum TerminalKeyStatus {
+    Active,
+    /// Superseded, but still accepted until `retire_after`
+    Retiring,
+    Retired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TerminalEncryptionKey {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    /// Identifier of the base derivation key at the processor
+    pub key_set_id: String,
+    pub scheme: CardEncryptionScheme,
+    pub status: TerminalKeyStatus,
+    pub activated_at: DateTime<Utc>,
+    pub retire_after: Option<DateTime<Utc>>,
+    pub retired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct RegisterTerminalKeyRequest {
+    #[validate(length(min = 1, max = 50))]
+    pub key_set_id: String,
+    pub scheme: CardEncryptionScheme,
+    /// How long readers still on the previous key of this scheme keep working
+    #[validate(range(min = 0, max = 90))]
+    pub grace_period_days: Option<i64>,
+}
+
+/// Card-present data as produced by the reader's secure element. Every field except
+/// the key metadata and entry mode is ciphertext Defiant cannot and does not decrypt.
+#[derive(Clone, Serialize, Deserialize, Validate)]
+pub struct EncryptedCardPresentData {
+    pub entry_mode: CardEntryMode,
+    #[validate(length(min = 1, max = 50))]
+    pub key_set_id: String,
+    /// DUKPT key serial number identifying the per-transaction key, hex
+    #[validate(length(min = 20, max = 24))]
+    pub ksn: String,
+    /// Encrypted track 2 equivalent data, hex
+    #[validate(length(min = 16, max = 512))]
+    pub encrypted_track2: String,
+    /// Encrypted PIN block, hex; absent for no-CVM and signature transactions
+    #[validate(length(min = 16, max = 64))]
+    pub encrypted_pin_block: Option<String>,
+    /// ISO 9564 format of the PIN block, e.g. `iso_0` or `iso_4`
+    #[validate(length(max = 10))]
+    pub pin_block_format: Option<String>,
+    /// EMV TLV data from the chip (ARQC and friends), hex
+    #[validate(length(max = 4096))]
+    pub emv_data: Option<String>,
+}
+
+// Ciphertext is still cardholder data; keep it out of logs entirely
+impl fmt::Debug for EncryptedCardPresentData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedCardPresentData")
+            .field("entry_mode", &self.entry_mode)
+            .field("key_set_id", &self.key_set_id)
+            .field("ksn", &self.ksn)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+use super::CardDetails;
+
+/// Card details a customer's client tokenized with a publishable key, held sealed
+/// until one payment redeems them.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct CardToken {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub livemode: bool,
+    #[serde(skip_serializing)]
+    pub encrypted_card: String,
+    pub brand: String,
+    pub last4: String,
+    pub exp_month: i16,
+    pub exp_year: i16,
+    pub issuer_country: Option<String>,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CreateCardTokenRequest {
+    #[validate]
+    pub card: CardDetails,
+}
+
+/// What the client learns about its token. Pass `id` as `source.token` when creating
+/// the payment, or to `confirm` a payment created without card details.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardTokenResponse {
+    /// `tok_` followed by the token's ID
+    pub id: String,
+    pub brand: String,
+    pub last4: String,
+    pub exp_month: i16,
+    pub exp_year: i16,
+    pub livemode: bool,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<CardToken> for CardTokenResponse {
+    fn from(token: CardToken) -> Self {
+        Self {
+            id: format!("tok_{}", token.id.simple()),
+            brand: token.brand,
+            last4: token.last4,
+            exp_month: token.exp_month,
+            exp_year: token.exp_year,
+            livemode: token.livemode,
+            expires_at: token.expires_at,
+            created_at: token.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ConfirmPaymentRequest {
+    /// Returned when the payment was created, and handed to the customer's client
+    #[validate(length(min = 1))]
+    pub client_secret: String,
+    /// A card token from `POST /api/v1/tokens`
+    #[validate(length(min = 1))]
+    pub token: String,
+}
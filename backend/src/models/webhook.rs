@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use validator::Validate;
+
+/// Payload versions endpoints can pin to, oldest first. The last entry is current.
+pub const WEBHOOK_API_VERSIONS: &[&str] = &["2024-01-01"];
+
+pub fn current_webhook_api_version() -> &'static str {
+    WEBHOOK_API_VERSIONS[WEBHOOK_API_VERSIONS.len() - 1]
+}
+
+/// A subscription entry is `*`, a family wildcard such as `invoice.*`, or an exact type
+/// such as `payment.succeeded`.
+pub fn is_valid_event_filter(filter: &str) -> bool {
+    if filter == "*" {
+        return true;
+    }
+    
+    let name = filter.strip_suffix(".*").unwrap_or(filter);
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.ends_with('.')
+        && !name.contains("..")
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.')
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub url: String,
+    pub description: Option<String>,
+    /// Signing secret sealed with the server key; only ever revealed at creation
+    #[serde(skip_serializing)]
+    pub encrypted_secret: String,
+    /// Secret replaced by the last rotation, still signing until it expires
+    #[serde(skip_serializing)]
+    pub previous_encrypted_secret: Option<String>,
+    pub previous_secret_expires_at: Option<DateTime<Utc>>,
+    pub events: Vec<String>,
+    pub active: bool,
+    /// Payload version this endpoint receives, independent of the account default
+    pub api_version: String,
+    pub last_triggered_at: Option<DateTime<Utc>>,
+    pub consecutive_failures: i32,
+    /// Start of the current unbroken run of failed deliveries
+    pub failing_since: Option<DateTime<Utc>>,
+    pub disabled_at: Option<DateTime<Utc>>,
+    pub disabled_reason: Option<String>,
+    /// Receives only events of this mode
+    pub livemode: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_id: Uuid,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_response_status: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    /// Set when the delivery was queued by an event replay
+    pub replay_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A single HTTP request made for a delivery.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct WebhookDeliveryAttempt {
+    pub id: Uuid,
+    pub merchant_id: Uuid,
+    pub delivery_id: Uuid,
+    pub attempt_number: i32,
+    pub request_body: String,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub latency_ms: i32,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryLog {
+    #[serde(flatten)]
+    pub delivery: WebhookDelivery,
+    pub event_type: String,
+    /// Every attempt made so far, oldest first
+    pub attempt_log: Vec<WebhookDeliveryAttempt>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookDeliveryListQuery {
+    pub limit: Option<i64>,
+    pub status: Option<WebhookDeliveryStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDeliveryListResponse {
+    pub data: Vec<WebhookDeliveryLog>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "webhook_delivery_status", rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    InProgress,
+    Succeeded,
+    /// Last attempt failed; another is scheduled at `next_attempt_at`
+    Failed,
+    /// Retry window exhausted (or the endpoint was disabled); no further attempts
+    DeadLettered,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(url(message = "Webhook URL must be a valid URL"))]
+    pub url: String,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    /// Event types to deliver: exact types, `family.*` wildcards, or `*` for everything
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 event types are required"))]
+    pub events: Vec<String>,
+    /// Defaults to the current version
+    pub api_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Validate)]
+pub struct UpdateWebhookRequest {
+    #[validate(url(message = "Webhook URL must be a valid URL"))]
+    pub url: Option<String>,
+    #[validate(length(max = 500))]
+    pub description: Option<String>,
+    #[validate(length(min = 1, max = 100, message = "Between 1 and 100 event types are required"))]
+    pub events: Option<Vec<String>>,
+    /// Re-enabling an endpoint also clears any automatic disable
+    pub active: Option<bool>,
+    pub api_version: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WebhookListQuery {
+    pub limit: Option<i64>,
+    pub active: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookListResponse {
+    pub data: Vec<Webhook>,
+    pub has_more: bool,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookResponse {
+    #[serde(flatten)]
+    pub webhook: Webhook,
+    /// Plaintext signing secret, returned only when the endpoint is created or its secret rotated
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
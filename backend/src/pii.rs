@@ -0,0 +1,333 @@
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use ring::{aead, hmac, rand::{SecureRandom, SystemRandom}};
+use sqlx::PgPool;
+use tracing::{info, error};
+use uuid::Uuid;
+
+use crate::{config::Config, errors::DefiantError, db::Database, shutdown::Shutdown};
+
+/// Sealed values read `pii2:<data key id>:<hex nonce and ciphertext>`, bound to the
+/// column and row they are stored in so they cannot be copied to another. Values
+/// without a prefix are plaintext written before encryption, read as they are until
+/// re-encryption reaches them.
+const SEALED_PREFIX: &str = "pii2:";
+/// Values sealed before they were bound to where they are stored, opened without
+/// context until re-encryption reaches them.
+const UNBOUND_SEALED_PREFIX: &str = "pii1:";
+/// A new data key only starts sealing values once every instance has had time to load
+/// it, so none meets a value it cannot open.
+pub const DATA_KEY_ACTIVATION_DELAY_SECS: i32 = 5 * 60;
+const KEYRING_REFRESH_INTERVAL_SECS: u64 = 60;
+
+static KEYRING: OnceLock<RwLock<Arc<Keyring>>> = OnceLock::new();
+
+/// The KMS-held master keys that wrap data keys, by KMS key id.
+pub struct MasterKeys {
+    keys: HashMap<String, aead::LessSafeKey>,
+    /// Wraps new data keys; data keys wrapped by the others are re-wrapped with it
+    pub active_id: String,
+}
+
+impl MasterKeys {
+    pub fn from_config(config: &Config) -> Result<Self, DefiantError> {
+        let keys = config.pii_master_keys.iter()
+            .map(|(id, key_hex)| Ok((id.clone(), aes_key(key_hex, "PII master key")?)))
+            .collect::<Result<HashMap<_, _>, DefiantError>>()?;
+        if !keys.contains_key(&config.pii_master_key_id) {
+            return Err(DefiantError::BadRequest(format!(
+                "PII master key {} is not among the configured master keys",
+                config.pii_master_key_id,
+            )));
+        }
+        
+        Ok(Self { keys, active_id: config.pii_master_key_id.clone() })
+    }
+    
+    /// Wraps a data key with the active master key, binding it to that key's id.
+    pub fn wrap(&self, data_key: &[u8]) -> Result<String, DefiantError> {
+        seal_bytes(&self.keys[&self.active_id], self.active_id.as_bytes(), data_key)
+    }
+    
+    pub fn unwrap(&self, master_key_id: &str, wrapped: &str) -> Result<Vec<u8>, DefiantError> {
+        let key = self.keys.get(master_key_id).ok_or_else(|| {
+            error!("PII data key wrapped by unknown master key {}", master_key_id);
+            DefiantError::InternalError
+        })?;
+        open_bytes(key, master_key_id.as_bytes(), wrapped)
+    }
+}
+
+/// The unwrapped data keys, shared by every request.
+pub struct Keyring {
+    data_keys: HashMap<i32, aead::LessSafeKey>,
+    /// The newest data key past its activation delay, which seals new values
+    active_id: i32,
+    blind_index: hmac::Key,
+}
+
+impl Keyring {
+    /// Seals a value for the place named by `context`, from `context()`; it opens only
+    /// with the same context.
+    pub fn seal(&self, context: &str, plaintext: &str) -> Result<String, DefiantError> {
+        let sealed = seal_bytes(&self.data_keys[&self.active_id], context.as_bytes(), plaintext.as_bytes())?;
+        Ok(format!("{}{}:{}", SEALED_PREFIX, self.active_id, sealed))
+    }
+    
+    pub fn open(&self, context: &str, stored: &str) -> Result<String, DefiantError> {
+        let (aad, sealed) = match (stored.strip_prefix(SEALED_PREFIX), stored.strip_prefix(UNBOUND_SEALED_PREFIX)) {
+            (Some(sealed), _) => (context.as_bytes(), sealed),
+            (None, Some(sealed)) => (&[][..], sealed),
+            (None, None) => return Ok(stored.to_string()),
+        };
+        let (key_id, sealed) = sealed.split_once(':').ok_or(DefiantError::InternalError)?;
+        let key = key_id.parse().ok()
+            .and_then(|key_id: i32| self.data_keys.get(&key_id))
+            .ok_or_else(|| {
+                error!("Value sealed with unknown PII data key {}", key_id);
+                DefiantError::InternalError
+            })?;
+        
+        String::from_utf8(open_bytes(key, aad, sealed)?).map_err(|_| DefiantError::InternalError)
+    }
+    
+    /// A keyed hash standing in for an encrypted value in equality lookups. Case and
+    /// surrounding whitespace are ignored, as they are for emails.
+    pub fn blind_index(&self, value: &str) -> String {
+        hex::encode(hmac::sign(&self.blind_index, value.trim().to_lowercase().as_bytes()).as_ref())
+    }
+    
+    /// What every value sealed with the active data key starts with.
+    pub fn active_prefix(&self) -> String {
+        format!("{}{}:", SEALED_PREFIX, self.active_id)
+    }
+}
+
+/// Where a sealed value is stored, as `table.column:row_id`, e.g.
+/// `customers.email:<customer id>`.
+pub fn context(column: &str, row_id: Uuid) -> String {
+    format!("{}:{}", column, row_id)
+}
+
+/// The keyring loaded at startup, or an error before `init` has run.
+pub fn keyring() -> Result<Arc<Keyring>, DefiantError> {
+    let keyring = KEYRING.get().ok_or(DefiantError::InternalError)?;
+    Ok(keyring.read().map_err(|_| DefiantError::InternalError)?.clone())
+}
+
+/// Loads the data keys from the directory database, creating the first on a fresh
+/// install, and installs the keyring.
+pub async fn init(db: &Database, config: &Config) -> Result<(), DefiantError> {
+    let masters = MasterKeys::from_config(config)?;
+    let has_keys = sqlx::query_scalar!(r#"SELECT EXISTS(SELECT 1 FROM pii_data_keys) AS "exists!""#)
+        .fetch_one(&db.pool)
+        .await?;
+    if !has_keys {
+        insert_data_key(&db.pool, &masters, &random_data_key()?, 0).await?;
+    }
+    
+    let keyring = load(&db.pool, &masters, config).await?;
+    info!("Loaded PII keyring; sealing with data key {}", keyring.active_id);
+    if KEYRING.set(RwLock::new(Arc::new(keyring))).is_err() {
+        error!("PII keyring initialized twice");
+    }
+    Ok(())
+}
+
+/// Adds a data key, which starts sealing after `DATA_KEY_ACTIVATION_DELAY_SECS`.
+/// Values sealed with older keys stay readable until re-encryption moves them.
+pub async fn rotate_data_key(db: &Database, config: &Config) -> Result<i32, DefiantError> {
+    let masters = MasterKeys::from_config(config)?;
+    let id = insert_data_key(&db.pool, &masters, &random_data_key()?, DATA_KEY_ACTIVATION_DELAY_SECS).await?;
+    
+    info!("Created PII data key {}", id);
+    Ok(id)
+}
+
+/// Re-wraps data keys held under a master key other than the active one, so the old
+/// master key can be disabled in the KMS once this has run. Values sealed with the
+/// data keys are untouched.
+pub async fn rewrap_data_keys(db: &Database, config: &Config) -> Result<(), DefiantError> {
+    let masters = MasterKeys::from_config(config)?;
+    let stale = sqlx::query!(
+        "SELECT id, master_key_id, wrapped_key FROM pii_data_keys WHERE master_key_id <> $1",
+        masters.active_id,
+    )
+    .fetch_all(&db.pool)
+    .await?;
+    
+    for row in stale {
+        let key = masters.unwrap(&row.master_key_id, &row.wrapped_key)?;
+        sqlx::query!(
+            "UPDATE pii_data_keys SET master_key_id = $2, wrapped_key = $3 WHERE id = $1 AND master_key_id = $4",
+            row.id,
+            masters.active_id,
+            masters.wrap(&key)?,
+            row.master_key_id,
+        )
+        .execute(&db.pool)
+        .await?;
+        info!("Re-wrapped PII data key {} from master key {} to {}", row.id, row.master_key_id, masters.active_id);
+    }
+    
+    Ok(())
+}
+
+/// Reloads the keyring every minute, picking up keys created by other instances and
+/// activating new ones once their delay has passed.
+pub fn spawn_keyring_refresh(db: Arc<Database>, config: Arc<Config>, shutdown: &Shutdown) {
+    shutdown.spawn(|mut shutdown| async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(KEYRING_REFRESH_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            let reloaded = match MasterKeys::from_config(&config) {
+                Ok(masters) => load(&db.pool, &masters, &config).await,
+                Err(e) => Err(e),
+            };
+            match (reloaded, KEYRING.get()) {
+                (Ok(keyring), Some(current)) => match current.write() {
+                    Ok(mut current) => *current = Arc::new(keyring),
+                    Err(_) => error!("PII keyring lock poisoned"),
+                },
+                (Ok(_), None) => {}
+                (Err(e), _) => error!("Failed to reload the PII keyring: {}", e),
+            }
+        }
+    });
+}
+
+async fn load(pool: &PgPool, masters: &MasterKeys, config: &Config) -> Result<Keyring, DefiantError> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT id, master_key_id, wrapped_key, activates_at <= NOW() AS "active!"
+        FROM pii_data_keys
+        ORDER BY id
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let mut data_keys = HashMap::with_capacity(rows.len());
+    let mut active_id = None;
+    for row in rows {
+        let key = masters.unwrap(&row.master_key_id, &row.wrapped_key)?;
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &key).map_err(|_| DefiantError::InternalError)?;
+        data_keys.insert(row.id, aead::LessSafeKey::new(key));
+        if row.active {
+            active_id = Some(row.id);
+        }
+    }
+    
+    Ok(Keyring {
+        data_keys,
+        active_id: active_id.ok_or(DefiantError::InternalError)?,
+        blind_index: hmac::Key::new(hmac::HMAC_SHA256, config.pii_blind_index_key.as_bytes()),
+    })
+}
+
+fn random_data_key() -> Result<[u8; 32], DefiantError> {
+    let mut key = [0u8; 32];
+    SystemRandom::new().fill(&mut key).map_err(|_| DefiantError::InternalError)?;
+    Ok(key)
+}
+
+async fn insert_data_key(
+    pool: &PgPool,
+    masters: &MasterKeys,
+    key: &[u8],
+    activation_delay_secs: i32,
+) -> Result<i32, DefiantError> {
+    let id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO pii_data_keys (master_key_id, wrapped_key, activates_at)
+        VALUES ($1, $2, NOW() + make_interval(secs => $3))
+        RETURNING id
+        "#,
+        masters.active_id,
+        masters.wrap(key)?,
+        activation_delay_secs as f64,
+    )
+    .fetch_one(pool)
+    .await?;
+    
+    Ok(id)
+}
+
+fn aes_key(key_hex: &str, name: &str) -> Result<aead::LessSafeKey, DefiantError> {
+    let bytes = hex::decode(key_hex)
+        .map_err(|_| DefiantError::BadRequest(format!("{} must be hex-encoded", name)))?;
+    let unbound = aead::UnboundKey::new(&aead::AES_256_GCM, &bytes)
+        .map_err(|_| DefiantError::BadRequest(format!("{} must be 32 bytes", name)))?;
+    Ok(aead::LessSafeKey::new(unbound))
+}
+
+fn seal_bytes(key: &aead::LessSafeKey, aad: &[u8], plaintext: &[u8]) -> Result<String, DefiantError> {
+    let mut nonce_bytes = [0u8; aead::NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce_bytes).map_err(|_| DefiantError::InternalError)?;
+    
+    let mut in_out = plaintext.to_vec();
+    key.seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce_bytes), aead::Aad::from(aad), &mut in_out)
+        .map_err(|_| DefiantError::InternalError)?;
+    
+    let mut sealed = nonce_bytes.to_vec();
+    sealed.extend_from_slice(&in_out);
+    Ok(hex::encode(sealed))
+}
+
+fn open_bytes(key: &aead::LessSafeKey, aad: &[u8], sealed_hex: &str) -> Result<Vec<u8>, DefiantError> {
+    let sealed = hex::decode(sealed_hex).map_err(|_| DefiantError::InternalError)?;
+    if sealed.len() < aead::NONCE_LEN {
+        return Err(DefiantError::InternalError);
+    }
+    
+    let (nonce_bytes, ciphertext) = sealed.split_at(aead::NONCE_LEN);
+    let nonce = aead::Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| DefiantError::InternalError)?;
+    let mut in_out = ciphertext.to_vec();
+    let plaintext = key.open_in_place(nonce, aead::Aad::from(aad), &mut in_out)
+        .map_err(|_| DefiantError::InternalError)?;
+    
+    Ok(plaintext.to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn keyring() -> Keyring {
+        let key = aead::UnboundKey::new(&aead::AES_256_GCM, &[7u8; 32]).expect("data key");
+        Keyring {
+            data_keys: HashMap::from([(1, aead::LessSafeKey::new(key))]),
+            active_id: 1,
+            blind_index: hmac::Key::new(hmac::HMAC_SHA256, b"test-blind-index-key"),
+        }
+    }
+    
+    #[test]
+    fn sealed_value_opens_only_where_it_was_sealed() {
+        let keyring = keyring();
+        let customer_id = Uuid::new_v4();
+        let sealed = keyring.seal(&context("customers.email", customer_id), "ada@example.com").expect("sealed");
+        
+        assert!(sealed.starts_with(&keyring.active_prefix()));
+        assert_eq!(keyring.open(&context("customers.email", customer_id), &sealed).expect("opened"), "ada@example.com");
+        assert!(keyring.open(&context("customers.email", Uuid::new_v4()), &sealed).is_err());
+        assert!(keyring.open(&context("customers.phone", customer_id), &sealed).is_err());
+    }
+    
+    #[test]
+    fn unbound_and_plaintext_values_open_until_resealed() {
+        let keyring = keyring();
+        let location = context("customers.email", Uuid::new_v4());
+        let unbound = format!(
+            "{}1:{}",
+            UNBOUND_SEALED_PREFIX,
+            seal_bytes(&keyring.data_keys[&1], &[], b"ada@example.com").expect("sealed"),
+        );
+        
+        assert_eq!(keyring.open(&location, &unbound).expect("opened"), "ada@example.com");
+        assert_eq!(keyring.open(&location, "ada@example.com").expect("opened"), "ada@example.com");
+        // Re-encryption picks up whatever lacks the active prefix
+        assert!(!unbound.starts_with(&keyring.active_prefix()));
+    }
+}
@@ -0,0 +1,25 @@
+use crate::config::Config;
+use super::PluginRegistry;
+
+/// Where a deployment registers its plugins; this is the only file operators need to
+/// touch. For example, to refuse payments above a limit:
+///
+/// ```ignore
+/// struct AmountLimit(i64);
+///
+/// impl Plugin for AmountLimit {
+///     fn name(&self) -> &'static str { "amount_limit" }
+///
+///     fn pre_payment<'a>(&'a self, attempt: &'a PaymentAttempt<'a>) -> BoxFuture<'a, HookResult> {
+///         Box::pin(async move {
+///             if attempt.request.amount > self.0 {
+///                 return Err(Veto::new("amount exceeds the limit"));
+///             }
+///             Ok(())
+///         })
+///     }
+/// }
+///
+/// registry.register(Box::new(AmountLimit(1_000_000)));
+/// ```
+pub fn register(_registry: &mut PluginRegistry, _config: &Config) {}
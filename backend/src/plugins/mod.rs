@@ -0,0 +1,163 @@
+use std::sync::OnceLock;
+use std::time::Duration as StdDuration;
+use futures_util::future::BoxFuture;
+use rust_decimal::Decimal;
+use tracing::{info, warn, error};
+use uuid::Uuid;
+
+use crate::{models::{Chain, CreatePaymentRequest, Payment}, errors::DefiantError};
+
+pub mod installed;
+
+const HOOK_TIMEOUT_SECS: u64 = 5;
+
+static REGISTRY: OnceLock<PluginRegistry> = OnceLock::new();
+
+/// A plugin's reason for refusing an operation, returned to the API caller.
+#[derive(Debug, Clone)]
+pub struct Veto {
+    pub reason: String,
+}
+
+impl Veto {
+    pub fn new(reason: impl Into<String>) -> Self {
+        Self { reason: reason.into() }
+    }
+}
+
+pub type HookResult = Result<(), Veto>;
+
+/// A payment about to be created, before anything is recorded or charged.
+pub struct PaymentAttempt<'a> {
+    pub merchant_id: Uuid,
+    pub request: &'a CreatePaymentRequest,
+    /// Invoice the payment is made against from its hosted page
+    pub invoice_id: Option<Uuid>,
+}
+
+/// A refund that has been recorded against its payment.
+pub struct RefundNotice<'a> {
+    pub merchant_id: Uuid,
+    /// The payment as it stands after the refund
+    pub payment: &'a Payment,
+    /// Minor units refunded this time
+    pub amount: i64,
+    pub reason: Option<&'a str>,
+}
+
+pub enum PayoutAttempt<'a> {
+    /// Funds leaving the merchant's balance for their bank account
+    Balance {
+        merchant_id: Uuid,
+        amount: i64,
+        currency: &'a str,
+    },
+    /// Confirmed crypto payments being swept into the merchant's own wallet
+    CryptoSweep {
+        merchant_id: Uuid,
+        chain: Chain,
+        destination: &'a str,
+        /// Base units
+        amount: Decimal,
+    },
+}
+
+/// Operator logic run at fixed points of payment, refund and payout lifecycles, so
+/// deployments can add business rules without forking the services.
+///
+/// Plugins are registered once at startup in [`installed::register`]. `pre_*` hooks run
+/// before money moves and any plugin can veto the operation; `post_*` hooks only observe
+/// what already happened. Plugins run in registration order and the first veto wins.
+/// Hooks run inline on the request path and are held to `HOOK_TIMEOUT_SECS`: a pre-hook
+/// that does not answer in time vetoes, since it may have been about to.
+///
+/// Every hook defaults to doing nothing, so plugins implement only what they need.
+pub trait Plugin: Send + Sync {
+    /// Identifies the plugin in logs and in vetoes returned to API callers.
+    fn name(&self) -> &'static str;
+    
+    fn pre_payment<'a>(&'a self, _attempt: &'a PaymentAttempt<'a>) -> BoxFuture<'a, HookResult> {
+        Box::pin(async { Ok(()) })
+    }
+    
+    fn post_refund<'a>(&'a self, _refund: &'a RefundNotice<'a>) -> BoxFuture<'a, ()> {
+        Box::pin(async {})
+    }
+    
+    fn pre_payout<'a>(&'a self, _attempt: &'a PayoutAttempt<'a>) -> BoxFuture<'a, HookResult> {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn Plugin>>,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn register(&mut self, plugin: Box<dyn Plugin>) {
+        info!("Registered plugin {}", plugin.name());
+        self.plugins.push(plugin);
+    }
+    
+    /// A veto declines the payment before it is recorded.
+    pub async fn pre_payment(&self, attempt: &PaymentAttempt<'_>) -> Result<(), DefiantError> {
+        for plugin in &self.plugins {
+            let decision = run_pre_hook(plugin.name(), "pre_payment", plugin.pre_payment(attempt)).await;
+            if let Err(veto) = decision {
+                return Err(DefiantError::PaymentError(format!("Payment declined by {}: {}", plugin.name(), veto.reason)));
+            }
+        }
+        Ok(())
+    }
+    
+    pub async fn post_refund(&self, refund: &RefundNotice<'_>) {
+        for plugin in &self.plugins {
+            if tokio::time::timeout(StdDuration::from_secs(HOOK_TIMEOUT_SECS), plugin.post_refund(refund)).await.is_err() {
+                warn!("Plugin {} timed out in post_refund for payment {}", plugin.name(), refund.payment.id);
+            }
+        }
+    }
+    
+    /// A veto stops the payout before any funds are moved.
+    pub async fn pre_payout(&self, attempt: &PayoutAttempt<'_>) -> Result<(), DefiantError> {
+        for plugin in &self.plugins {
+            let decision = run_pre_hook(plugin.name(), "pre_payout", plugin.pre_payout(attempt)).await;
+            if let Err(veto) = decision {
+                return Err(DefiantError::Conflict(format!("Payout blocked by {}: {}", plugin.name(), veto.reason)));
+            }
+        }
+        Ok(())
+    }
+}
+
+async fn run_pre_hook(plugin: &str, hook: &str, decision: BoxFuture<'_, HookResult>) -> HookResult {
+    match tokio::time::timeout(StdDuration::from_secs(HOOK_TIMEOUT_SECS), decision).await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(veto)) => {
+            info!("Plugin {} vetoed {}: {}", plugin, hook, veto.reason);
+            Err(veto)
+        }
+        Err(_) => {
+            error!("Plugin {} timed out in {}", plugin, hook);
+            Err(Veto::new("timed out"))
+        }
+    }
+}
+
+/// Makes `registry` the one every service consults. Called once, before the server
+/// and background workers start.
+pub fn install(registry: PluginRegistry) {
+    if REGISTRY.set(registry).is_err() {
+        warn!("Plugins were already installed; ignoring the second registry");
+    }
+}
+
+/// The installed registry, or an empty one when nothing was installed.
+pub fn hooks() -> &'static PluginRegistry {
+    REGISTRY.get_or_init(PluginRegistry::new)
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use cron::Schedule;
+use futures_util::future::BoxFuture;
+use tracing::{info, warn, error};
+use uuid::Uuid;
+
+use crate::{errors::DefiantError, shutdown::Shutdown};
+
+/// Claims a firing of a task for one instance: refuses when the firing in `ARGV[2]`
+/// has already been claimed, or while another instance holds the lease in `KEYS[1]`.
+const CLAIM_SCRIPT: &str = r#"
+local last = tonumber(redis.call('GET', KEYS[2]) or '0')
+if last >= tonumber(ARGV[2]) then
+    return 0
+end
+if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'PX', ARGV[3]) then
+    redis.call('SET', KEYS[2], ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+/// Drops the lease in `KEYS[1]` only if this instance still holds it.
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+static CLAIM: OnceLock<redis::Script> = OnceLock::new();
+static RELEASE: OnceLock<redis::Script> = OnceLock::new();
+
+type TaskFn = Arc<dyn Fn() -> BoxFuture<'static, Result<(), DefiantError>> + Send + Sync>;
+
+struct Task {
+    name: &'static str,
+    schedule: Schedule,
+    lease: Duration,
+    run: TaskFn,
+}
+
+/// Runs recurring tasks on cron schedules, once per firing across every instance of
+/// the backend.
+///
+/// Each instance wakes for every firing, and the first to claim it in Redis runs it
+/// while the others skip it. The claim is held as a lease for up to the task's
+/// `lease`, so a run that outlasts its lease can overlap the next firing elsewhere;
+/// leases should comfortably exceed how long the task takes. When Redis cannot be
+/// reached the firing is skipped rather than risk running twice.
+pub struct Scheduler {
+    redis: Arc<redis::Client>,
+    instance_id: String,
+    overrides: HashMap<String, String>,
+    tasks: Vec<Task>,
+}
+
+impl Scheduler {
+    /// `overrides` replace the expressions tasks are added with, by task name.
+    pub fn new(redis: Arc<redis::Client>, overrides: HashMap<String, String>) -> Self {
+        Self {
+            redis,
+            instance_id: Uuid::new_v4().to_string(),
+            overrides,
+            tasks: Vec::new(),
+        }
+    }
+    
+    /// Adds a task. Expressions have six fields, seconds first: `0 */5 * * * *` runs
+    /// every five minutes. Panics on an invalid expression, which is a configuration
+    /// error to catch at startup.
+    pub fn add<F, Fut>(&mut self, name: &'static str, expression: &str, lease: Duration, task: F)
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), DefiantError>> + Send + 'static,
+    {
+        let expression = self.overrides.get(name).map(String::as_str).unwrap_or(expression);
+        let schedule = Schedule::from_str(expression)
+            .unwrap_or_else(|e| panic!("Invalid schedule '{}' for task {}: {}", expression, name, e));
+        
+        self.tasks.push(Task {
+            name,
+            schedule,
+            lease,
+            run: Arc::new(move || Box::pin(task())),
+        });
+    }
+    
+    pub fn start(self, shutdown: &Shutdown) {
+        for task in self.tasks {
+            let redis = self.redis.clone();
+            let instance_id = self.instance_id.clone();
+            shutdown.spawn(|shutdown| run(task, redis, instance_id, shutdown));
+        }
+    }
+}
+
+async fn run(task: Task, redis: Arc<redis::Client>, instance_id: String, mut shutdown: Shutdown) {
+    info!("Scheduled task {} started", task.name);
+    
+    while let Some(fire_at) = task.schedule.after(&Utc::now()).next() {
+        let wait = (fire_at - Utc::now()).to_std().unwrap_or_default();
+        if !shutdown.sleep(wait).await {
+            break;
+        }
+        
+        match claim(&redis, &task, &instance_id, fire_at).await {
+            Ok(true) => {
+                if let Err(e) = (task.run)().await {
+                    error!("Scheduled task {} failed: {}", task.name, e);
+                }
+                if let Err(e) = release(&redis, &task, &instance_id).await {
+                    warn!("Failed to release the lease on scheduled task {}: {}", task.name, e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Skipping scheduled task {}; could not claim it: {}", task.name, e),
+        }
+    }
+}
+
+async fn claim(
+    redis: &redis::Client,
+    task: &Task,
+    instance_id: &str,
+    fire_at: DateTime<Utc>,
+) -> Result<bool, redis::RedisError> {
+    let mut conn = redis.get_async_connection().await?;
+    let claimed: i64 = CLAIM
+        .get_or_init(|| redis::Script::new(CLAIM_SCRIPT))
+        .key(format!("scheduler:lease:{}", task.name))
+        .key(format!("scheduler:last_fired:{}", task.name))
+        .arg(instance_id)
+        .arg(fire_at.timestamp())
+        .arg(task.lease.as_millis() as u64)
+        .invoke_async(&mut conn)
+        .await?;
+    
+    Ok(claimed == 1)
+}
+
+async fn release(redis: &redis::Client, task: &Task, instance_id: &str) -> Result<(), redis::RedisError> {
+    let mut conn = redis.get_async_connection().await?;
+    let _: i64 = RELEASE
+        .get_or_init(|| redis::Script::new(RELEASE_SCRIPT))
+        .key(format!("scheduler:lease:{}", task.name))
+        .arg(instance_id)
+        .invoke_async(&mut conn)
+        .await?;
+    
+    Ok(())
+}
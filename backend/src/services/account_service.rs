@@ -0,0 +1,592 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{GoLiveChecklist, GoLiveRequirement, CreateLiveKeyRequest, CreateApiKeyRequest, ApiKey, ApiKeyType, ApiKeyListQuery, ApiKeyListResponse, LiveApiKeyResponse, RollApiKeyRequest, ConfigurationChangeData, KycStatus, UpdateApiKeyAllowlistRequest, NewSecurityEvent, SecurityEventType, API_KEY_CREATED, API_KEY_ROTATED, API_KEY_REVOKED, API_KEY_UPDATED}, errors::DefiantError, db::{Database, AuthenticatedKey, hash_api_key}, api::v1::permissions::{API_KEY_SCOPES, PUBLISHABLE_KEY_SCOPES, is_known_scope}};
+use super::webhook_service::WebhookService;
+use super::security_service::normalize_allowlist;
+use super::security_event_service::SecurityEventService;
+
+pub(crate) const LIVE_KEY_PREFIX: &str = "sk_live_";
+pub(crate) const TEST_KEY_PREFIX: &str = "sk_test_";
+pub(crate) const LIVE_PUBLISHABLE_KEY_PREFIX: &str = "pk_live_";
+pub(crate) const TEST_PUBLISHABLE_KEY_PREFIX: &str = "pk_test_";
+
+/// Whether a bearer token has the shape of a key issued by `create_api_key`.
+pub(crate) fn is_api_key(token: &str) -> bool {
+    [LIVE_KEY_PREFIX, TEST_KEY_PREFIX, LIVE_PUBLISHABLE_KEY_PREFIX, TEST_PUBLISHABLE_KEY_PREFIX]
+        .iter()
+        .any(|prefix| token.starts_with(prefix))
+}
+
+/// Secret keys, as opposed to publishable ones, are the keys request signing applies to.
+pub(crate) fn is_secret_key(token: &str) -> bool {
+    token.starts_with(LIVE_KEY_PREFIX) || token.starts_with(TEST_KEY_PREFIX)
+}
+
+/// Sandbox-to-live promotion. Live keys are only issued to merchants who pass
+/// every check in `go_live_checklist`.
+pub struct AccountService {
+    db: Arc<Database>,
+}
+
+impl AccountService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn go_live_checklist(&self, api_key: &str) -> Result<GoLiveChecklist, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let kyc_status = sqlx::query_scalar!(
+            r#"SELECT kyc_status AS "kyc_status: KycStatus" FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        let checks = sqlx::query!(
+            r#"
+            SELECT
+                EXISTS(
+                    SELECT 1 FROM webhooks
+                    WHERE merchant_id = $1 AND active = true AND last_triggered_at IS NOT NULL
+                ) AS "webhook_verified!",
+                EXISTS(
+                    SELECT 1 FROM merchant_bank_accounts WHERE merchant_id = $1 AND active = true
+                ) AS "bank_account_added!",
+                EXISTS(
+                    SELECT 1 FROM payments WHERE merchant_id = $1 AND livemode = false AND status = 'succeeded'
+                ) AS "test_payment_succeeded!"
+            "#,
+            merchant_id,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        let requirements = vec![
+            requirement(
+                "webhook_endpoint_verified",
+                "An active webhook endpoint has acknowledged at least one delivery",
+                checks.webhook_verified,
+            ),
+            requirement(
+                "payout_bank_account",
+                "A bank account is on file for payouts",
+                checks.bank_account_added,
+            ),
+            requirement(
+                "kyc_complete",
+                "Identity and business verification is complete",
+                kyc_status == KycStatus::Verified,
+            ),
+            requirement(
+                "test_payment",
+                "A payment has succeeded with a test key",
+                checks.test_payment_succeeded,
+            ),
+        ];
+        
+        Ok(GoLiveChecklist {
+            ready: requirements.iter().all(|r| r.satisfied),
+            requirements,
+        })
+    }
+    
+    pub async fn create_live_key(
+        &self,
+        request: CreateLiveKeyRequest,
+        api_key: &str,
+    ) -> Result<LiveApiKeyResponse, DefiantError> {
+        let request = CreateApiKeyRequest {
+            name: request.name,
+            livemode: true,
+            key_type: ApiKeyType::Secret,
+            scopes: None,
+            allowed_ips: None,
+        };
+        self.create_api_key(request, api_key).await
+    }
+    
+    /// Issues a key holding the given scopes, each of which the calling key must hold
+    /// too. Only a hash of the key is stored, so the response is the one time it is shown.
+    pub async fn create_api_key(
+        &self,
+        request: CreateApiKeyRequest,
+        api_key: &str,
+    ) -> Result<LiveApiKeyResponse, DefiantError> {
+        if request.livemode {
+            let checklist = self.go_live_checklist(api_key).await?;
+            if !checklist.ready {
+                let unmet: Vec<&str> = checklist.requirements
+                    .iter()
+                    .filter(|r| !r.satisfied)
+                    .map(|r| r.id.as_str())
+                    .collect();
+                return Err(DefiantError::Conflict(format!("Go-live requirements not met: {}", unmet.join(", "))));
+            }
+        }
+        
+        let caller = self.db.authenticate_key(api_key).await?;
+        let merchant_id = caller.merchant_id;
+        
+        let permissions = match (&request.scopes, request.key_type) {
+            (Some(_), ApiKeyType::Publishable) => {
+                return Err(DefiantError::ValidationError("Publishable keys have a fixed set of scopes".into()));
+            }
+            (None, ApiKeyType::Publishable) => {
+                if let Some(scope) = PUBLISHABLE_KEY_SCOPES.iter().find(|scope| !caller.grants(scope)) {
+                    return Err(DefiantError::AuthorizationError(format!("This API key cannot grant {}", scope)));
+                }
+                serde_json::json!({ "scopes": PUBLISHABLE_KEY_SCOPES })
+            }
+            (Some(scopes), ApiKeyType::Secret) => {
+                for scope in scopes {
+                    if !is_known_scope(scope) {
+                        return Err(DefiantError::ValidationError(format!("Unknown scope: {}", scope)));
+                    }
+                    if !caller.grants(scope) {
+                        return Err(DefiantError::AuthorizationError(format!("This API key cannot grant {}", scope)));
+                    }
+                }
+                serde_json::json!({ "scopes": scopes })
+            }
+            (None, ApiKeyType::Secret) => {
+                if API_KEY_SCOPES.iter().any(|scope| !caller.grants(scope)) {
+                    return Err(DefiantError::AuthorizationError("A restricted API key cannot issue a full-access key".into()));
+                }
+                serde_json::json!({})
+            }
+        };
+        
+        let allowed_ips = match (&request.allowed_ips, request.key_type) {
+            (Some(_), ApiKeyType::Publishable) => {
+                return Err(DefiantError::ValidationError("Publishable keys are used from customers' devices and cannot have an IP allowlist".into()));
+            }
+            (Some(ranges), ApiKeyType::Secret) => Some(normalize_allowlist(ranges)?),
+            (None, _) => None,
+        };
+        
+        let key = generate_key(request.key_type, request.livemode);
+        let issued = sqlx::query!(
+            r#"
+            INSERT INTO api_keys (merchant_id, key_hash, key_prefix, name, permissions, livemode, key_type, allowed_ips)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id, created_at AS "created_at!"
+            "#,
+            merchant_id,
+            hash_api_key(&key),
+            key_prefix(&key),
+            request.name,
+            permissions,
+            request.livemode,
+            request.key_type as ApiKeyType,
+            allowed_ips.as_deref(),
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("API key {} issued to merchant {}", issued.id, merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                API_KEY_CREATED,
+                ConfigurationChangeData::new("api_key", issued.id, Vec::new()),
+                api_key,
+            )
+            .await;
+        SecurityEventService::new(self.db.clone())
+            .record(NewSecurityEvent {
+                api_key_id: Some(issued.id),
+                details: serde_json::json!({ "name": request.name, "livemode": request.livemode, "key_type": request.key_type }),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::ApiKeyCreated)
+            })
+            .await;
+        
+        Ok(LiveApiKeyResponse {
+            id: issued.id,
+            name: request.name,
+            key,
+            key_type: request.key_type,
+            livemode: request.livemode,
+            allowed_ips,
+            created_at: issued.created_at,
+        })
+    }
+    
+    pub async fn list_api_keys(
+        &self,
+        query: ApiKeyListQuery,
+        api_key: &str,
+    ) -> Result<ApiKeyListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            ApiKey,
+            r#"
+            SELECT id, name, key_prefix, key_type AS "key_type: ApiKeyType", livemode,
+                CASE WHEN permissions ? 'scopes'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(permissions -> 'scopes'))
+                END AS scopes,
+                allowed_ips, active, last_used_at, expires_at, revoked_at, created_at
+            FROM api_keys
+            WHERE merchant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(ApiKeyListResponse {
+            data,
+            has_more,
+            url: "/api/v1/api_keys".into(),
+        })
+    }
+    
+    /// Stops the key working at once. Revoked keys stay listed but cannot be rolled.
+    pub async fn revoke_api_key(&self, key_id: Uuid, api_key: &str) -> Result<ApiKey, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        let merchant_id = caller.merchant_id;
+        self.ensure_covers(&caller, key_id).await?;
+        
+        let revoked = sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys SET active = false, revoked_at = NOW(), expires_at = NOW()
+            WHERE id = $1 AND merchant_id = $2 AND revoked_at IS NULL
+            RETURNING id, name, key_prefix, key_type AS "key_type: ApiKeyType", livemode,
+                CASE WHEN permissions ? 'scopes'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(permissions -> 'scopes'))
+                END AS scopes,
+                allowed_ips, active, last_used_at, expires_at, revoked_at, created_at
+            "#,
+            key_id,
+            merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("API key not found".into()))?;
+        
+        // Dashboard sessions exchanged for the key end with it
+        sqlx::query!(
+            r#"UPDATE auth_sessions SET revoked_at = NOW() WHERE api_key_id = $1 AND revoked_at IS NULL"#,
+            key_id,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        info!("API key {} revoked for merchant {}", key_id, merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                API_KEY_REVOKED,
+                ConfigurationChangeData::new("api_key", key_id, vec!["active".into()]),
+                api_key,
+            )
+            .await;
+        SecurityEventService::new(self.db.clone())
+            .record(NewSecurityEvent {
+                api_key_id: Some(key_id),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::ApiKeyRevoked)
+            })
+            .await;
+        
+        Ok(revoked)
+    }
+    
+    /// Replaces the key's IP allowlist. Requests already in flight finish; later ones
+    /// are checked against the new list.
+    pub async fn update_allowlist(
+        &self,
+        key_id: Uuid,
+        request: UpdateApiKeyAllowlistRequest,
+        api_key: &str,
+    ) -> Result<ApiKey, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        let merchant_id = caller.merchant_id;
+        self.ensure_covers(&caller, key_id).await?;
+        let allowed_ips = request.allowed_ips.as_deref().map(normalize_allowlist).transpose()?;
+        let mut tx = self.db.pool.begin().await?;
+        
+        let updated = sqlx::query_as!(
+            ApiKey,
+            r#"
+            UPDATE api_keys SET allowed_ips = $3
+            WHERE id = $1 AND merchant_id = $2 AND revoked_at IS NULL
+            RETURNING id, name, key_prefix, key_type AS "key_type: ApiKeyType", livemode,
+                CASE WHEN permissions ? 'scopes'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(permissions -> 'scopes'))
+                END AS scopes,
+                allowed_ips, active, last_used_at, expires_at, revoked_at, created_at
+            "#,
+            key_id,
+            merchant_id,
+            allowed_ips.as_deref(),
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("API key not found".into()))?;
+        
+        if updated.key_type == ApiKeyType::Publishable && updated.allowed_ips.is_some() {
+            return Err(DefiantError::ValidationError("Publishable keys are used from customers' devices and cannot have an IP allowlist".into()));
+        }
+        tx.commit().await?;
+        
+        info!("IP allowlist of API key {} updated for merchant {}", key_id, merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                API_KEY_UPDATED,
+                ConfigurationChangeData::new("api_key", key_id, vec!["allowed_ips".into()]),
+                api_key,
+            )
+            .await;
+        
+        Ok(updated)
+    }
+    
+    /// Replaces a key with a new one carrying the same name, mode and permissions. The
+    /// old key stops working immediately, or after `expires_in_hours` so deployments
+    /// can switch over.
+    pub async fn roll_api_key(
+        &self,
+        key_id: Uuid,
+        request: RollApiKeyRequest,
+        api_key: &str,
+    ) -> Result<LiveApiKeyResponse, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        let merchant_id = caller.merchant_id;
+        self.ensure_covers(&caller, key_id).await?;
+        let mut tx = self.db.pool.begin().await?;
+        
+        let existing = sqlx::query!(
+            r#"
+            SELECT livemode, key_type AS "key_type: ApiKeyType" FROM api_keys
+            WHERE id = $1 AND merchant_id = $2 AND active = true
+            AND (expires_at IS NULL OR expires_at > NOW())
+            FOR UPDATE
+            "#,
+            key_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("API key not found".into()))?;
+        
+        let key = generate_key(existing.key_type, existing.livemode);
+        let issued = sqlx::query!(
+            r#"
+            INSERT INTO api_keys (merchant_id, key_hash, key_prefix, name, permissions, livemode, key_type, allowed_ips)
+            SELECT merchant_id, $3, $4, name, permissions, livemode, key_type, allowed_ips FROM api_keys
+            WHERE id = $1 AND merchant_id = $2
+            RETURNING id, name, livemode, key_type AS "key_type: ApiKeyType", allowed_ips, created_at AS "created_at!"
+            "#,
+            key_id,
+            merchant_id,
+            hash_api_key(&key),
+            key_prefix(&key),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let new_key = LiveApiKeyResponse {
+            id: issued.id,
+            name: issued.name,
+            key,
+            key_type: issued.key_type,
+            livemode: issued.livemode,
+            allowed_ips: issued.allowed_ips,
+            created_at: issued.created_at,
+        };
+        
+        // Dashboard sessions exchanged for the old key end when it stops working
+        let old_key_expires_at = match request.expires_in_hours.filter(|hours| *hours > 0) {
+            Some(hours) => {
+                let expires_at = Utc::now() + Duration::hours(hours);
+                sqlx::query!(
+                    r#"UPDATE api_keys SET expires_at = $2 WHERE id = $1"#,
+                    key_id,
+                    expires_at,
+                )
+                .execute(&mut *tx)
+                .await?;
+                expires_at
+            }
+            None => {
+                sqlx::query!(
+                    r#"UPDATE api_keys SET active = false, expires_at = NOW() WHERE id = $1"#,
+                    key_id,
+                )
+                .execute(&mut *tx)
+                .await?;
+                sqlx::query!(
+                    r#"UPDATE auth_sessions SET revoked_at = NOW() WHERE api_key_id = $1 AND revoked_at IS NULL"#,
+                    key_id,
+                )
+                .execute(&mut *tx)
+                .await?;
+                Utc::now()
+            }
+        };
+        
+        tx.commit().await?;
+        
+        info!("API key {} rolled to {} for merchant {}", key_id, new_key.id, merchant_id);
+        let change = ConfigurationChangeData {
+            previous_id: Some(key_id),
+            ..ConfigurationChangeData::new("api_key", new_key.id, vec!["key".into()])
+        };
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(merchant_id, API_KEY_ROTATED, change, api_key)
+            .await;
+        let security_events = SecurityEventService::new(self.db.clone());
+        security_events
+            .record(NewSecurityEvent {
+                api_key_id: Some(new_key.id),
+                details: serde_json::json!({ "name": new_key.name, "livemode": new_key.livemode, "key_type": new_key.key_type, "previous_id": key_id }),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::ApiKeyCreated)
+            })
+            .await;
+        security_events
+            .record(NewSecurityEvent {
+                api_key_id: Some(key_id),
+                details: serde_json::json!({ "replaced_by": new_key.id, "expires_at": old_key_expires_at }),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::ApiKeyRevoked)
+            })
+            .await;
+        
+        Ok(new_key)
+    }
+    
+    /// Refuses unless the caller holds every scope of the key it is changing, as it
+    /// would to issue that key, so a restricted key cannot take over a broader one.
+    async fn ensure_covers(&self, caller: &AuthenticatedKey, key_id: Uuid) -> Result<(), DefiantError> {
+        let scopes = sqlx::query_scalar!(
+            r#"
+            SELECT CASE WHEN permissions ? 'scopes'
+                THEN ARRAY(SELECT jsonb_array_elements_text(permissions -> 'scopes'))
+            END AS scopes
+            FROM api_keys
+            WHERE id = $1 AND merchant_id = $2
+            "#,
+            key_id,
+            caller.merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("API key not found".into()))?;
+        
+        match scopes {
+            Some(scopes) => match scopes.iter().find(|scope| !caller.grants(scope)) {
+                Some(scope) => Err(DefiantError::AuthorizationError(format!("This API key cannot manage a key holding {}", scope))),
+                None => Ok(()),
+            },
+            None if API_KEY_SCOPES.iter().any(|scope| !caller.grants(scope)) => {
+                Err(DefiantError::AuthorizationError("A restricted API key cannot manage a full-access key".into()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn requirement(id: &str, description: &str, satisfied: bool) -> GoLiveRequirement {
+    GoLiveRequirement {
+        id: id.to_string(),
+        description: description.to_string(),
+        satisfied,
+    }
+}
+
+fn generate_key(key_type: ApiKeyType, livemode: bool) -> String {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    
+    let prefix = match (key_type, livemode) {
+        (ApiKeyType::Secret, true) => LIVE_KEY_PREFIX,
+        (ApiKeyType::Secret, false) => TEST_KEY_PREFIX,
+        (ApiKeyType::Publishable, true) => LIVE_PUBLISHABLE_KEY_PREFIX,
+        (ApiKeyType::Publishable, false) => TEST_PUBLISHABLE_KEY_PREFIX,
+    };
+    format!("{}{}", prefix, token)
+}
+
+/// What is kept of a key after it is issued: the mode prefix and four characters.
+fn key_prefix(key: &str) -> String {
+    key.chars().take(LIVE_KEY_PREFIX.len() + 4).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    
+    use super::*;
+    use crate::{models::TeamRole, services::auth_service::AuthService, test_support};
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn restricted_key_cannot_roll_a_full_access_key(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let full_access = test_support::secret_key(&pool, merchant_id, None).await;
+        let restricted = test_support::restricted_key(&pool, merchant_id, &["api_keys:write"]).await;
+        let target = test_support::key_id(&pool, &full_access).await;
+        let accounts = AccountService::new(Arc::new(Database::from_pool(pool.clone())));
+        
+        let rolled = accounts.roll_api_key(target, RollApiKeyRequest { expires_in_hours: None }, &restricted).await;
+        assert!(matches!(rolled, Err(DefiantError::AuthorizationError(_))));
+        assert!(matches!(accounts.revoke_api_key(target, &restricted).await, Err(DefiantError::AuthorizationError(_))));
+        
+        let active = sqlx::query_scalar!(r#"SELECT active AS "active!" FROM api_keys WHERE id = $1"#, target)
+            .fetch_one(&pool)
+            .await
+            .expect("key still exists");
+        assert!(active);
+    }
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn full_access_key_can_roll_a_restricted_key(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let full_access = test_support::secret_key(&pool, merchant_id, None).await;
+        let restricted = test_support::restricted_key(&pool, merchant_id, &["payments:read"]).await;
+        let target = test_support::key_id(&pool, &restricted).await;
+        let accounts = AccountService::new(Arc::new(Database::from_pool(pool)));
+        
+        let rolled = accounts.roll_api_key(target, RollApiKeyRequest { expires_in_hours: None }, &full_access).await;
+        assert!(rolled.is_ok());
+    }
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn revoking_a_key_ends_its_sessions(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let caller = test_support::secret_key(&pool, merchant_id, None).await;
+        let exchanged = test_support::secret_key(&pool, merchant_id, None).await;
+        let db = Arc::new(Database::from_pool(pool.clone()));
+        let auth = AuthService::new(db.clone(), Arc::new(test_support::config()));
+        let key = db.authenticate_key(&exchanged).await.expect("key authenticates");
+        let tokens = auth
+            .start_session(&merchant_id.to_string(), "merchant", Some(merchant_id), Some(TeamRole::Owner), false, Some(&key))
+            .await
+            .expect("session started");
+        
+        AccountService::new(db).revoke_api_key(key.id, &caller).await.expect("key revoked");
+        
+        let revoked = sqlx::query_scalar!(r#"SELECT revoked_at IS NOT NULL AS "revoked!" FROM auth_sessions WHERE api_key_id = $1"#, key.id)
+            .fetch_one(&pool)
+            .await
+            .expect("session exists");
+        assert!(revoked);
+        assert!(matches!(auth.refresh(&tokens.refresh_token).await, Err(DefiantError::AuthenticationError(_))));
+    }
+}
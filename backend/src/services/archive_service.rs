@@ -0,0 +1,225 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{ArchivedObject, DataRegion}, errors::DefiantError, db::Database, config::{Archival, ArchiveBucket}};
+use super::object_storage::ObjectStorage;
+
+/// Records exported per storage object.
+const ARCHIVE_BATCH_SIZE: i64 = 1000;
+/// Bounds a run well inside its lease; whatever is left waits for the next run.
+const MAX_BATCHES_PER_RUN: usize = 200;
+
+struct ArchiveRow {
+    id: Uuid,
+    merchant_id: Uuid,
+    created_at: DateTime<Utc>,
+    record: serde_json::Value,
+}
+
+/// Moves payments and events past their retention window out of the database and
+/// into cold storage as NDJSON, one record per line, leaving an `archived_objects`
+/// row behind for each.
+///
+/// A batch is deleted only after its upload succeeds. A failure between the two
+/// leaves the rows in place to be exported again, so an object may repeat records
+/// of an earlier one but none are lost.
+pub struct ArchiveService {
+    db: Arc<Database>,
+}
+
+impl ArchiveService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn archive(&self, settings: &Archival) -> Result<(), DefiantError> {
+        for (region, pool) in self.db.regions() {
+            let Some(bucket) = bucket_for(settings, region) else {
+                continue;
+            };
+            let storage = ObjectStorage::new(bucket.clone())?;
+            
+            let payments = self.archive_payments(pool, &storage, settings.payments_after_days).await?;
+            let events = self.archive_events(pool, &storage, settings.events_after_days).await?;
+            if payments > 0 || events > 0 {
+                info!("Archived {} payments and {} events from the {} cluster", payments, events, region.as_str());
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Only payments that can no longer change: nothing left to capture, confirm,
+    /// refund or dispute.
+    async fn archive_payments(&self, pool: &PgPool, storage: &ObjectStorage, after_days: u32) -> Result<usize, DefiantError> {
+        let mut archived = 0;
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let rows = sqlx::query_as!(
+                ArchiveRow,
+                r#"
+                SELECT p.id, p.merchant_id AS "merchant_id!", p.created_at,
+                    to_jsonb(p) || jsonb_build_object('line_items', COALESCE(
+                        (SELECT jsonb_agg(li ORDER BY li.created_at) FROM payment_line_items li WHERE li.payment_id = p.id),
+                        '[]'::jsonb
+                    )) AS "record!"
+                FROM payments p
+                WHERE p.created_at < NOW() - make_interval(days => $1)
+                AND p.status IN ('succeeded', 'failed', 'canceled', 'refunded')
+                ORDER BY p.created_at, p.id
+                LIMIT $2
+                "#,
+                after_days as i32,
+                ARCHIVE_BATCH_SIZE,
+            )
+            .fetch_all(pool)
+            .await?;
+            if rows.is_empty() {
+                break;
+            }
+            
+            let key = self.upload(storage, "payments", &rows).await?;
+            let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+            
+            let mut tx = pool.begin().await?;
+            record_archived(&mut tx, "payment", &rows, &key).await?;
+            sqlx::query!("DELETE FROM payment_line_items WHERE payment_id = ANY($1)", &ids)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query!("DELETE FROM payments WHERE id = ANY($1)", &ids)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            
+            archived += rows.len();
+            if (rows.len() as i64) < ARCHIVE_BATCH_SIZE {
+                break;
+            }
+        }
+        
+        Ok(archived)
+    }
+    
+    async fn archive_events(&self, pool: &PgPool, storage: &ObjectStorage, after_days: u32) -> Result<usize, DefiantError> {
+        let mut archived = 0;
+        for _ in 0..MAX_BATCHES_PER_RUN {
+            let rows = sqlx::query_as!(
+                ArchiveRow,
+                r#"
+                SELECT e.id, e.merchant_id AS "merchant_id!", e.created_at, to_jsonb(e) AS "record!"
+                FROM events e
+                WHERE e.created_at < NOW() - make_interval(days => $1)
+                ORDER BY e.created_at, e.id
+                LIMIT $2
+                "#,
+                after_days as i32,
+                ARCHIVE_BATCH_SIZE,
+            )
+            .fetch_all(pool)
+            .await?;
+            if rows.is_empty() {
+                break;
+            }
+            
+            let key = self.upload(storage, "events", &rows).await?;
+            let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+            
+            let mut tx = pool.begin().await?;
+            record_archived(&mut tx, "event", &rows, &key).await?;
+            sqlx::query!("DELETE FROM events WHERE id = ANY($1)", &ids)
+                .execute(&mut *tx)
+                .await?;
+            tx.commit().await?;
+            
+            archived += rows.len();
+            if (rows.len() as i64) < ARCHIVE_BATCH_SIZE {
+                break;
+            }
+        }
+        
+        Ok(archived)
+    }
+    
+    /// Keys sort by the oldest record they hold, e.g.
+    /// `payments/2024/03/20240301T000512-<uuid>.ndjson`.
+    async fn upload(&self, storage: &ObjectStorage, table: &str, rows: &[ArchiveRow]) -> Result<String, DefiantError> {
+        let oldest = rows[0].created_at;
+        let key = format!("{}/{}-{}.ndjson", table, oldest.format("%Y/%m/%Y%m%dT%H%M%S"), Uuid::new_v4());
+        
+        let mut body = Vec::new();
+        for row in rows {
+            serde_json::to_writer(&mut body, &row.record).map_err(|_| DefiantError::InternalError)?;
+            body.push(b'\n');
+        }
+        
+        storage.put(&key, "application/x-ndjson", body).await?;
+        Ok(key)
+    }
+}
+
+/// The error for a payment or event that could not be found: `Archived`, with where
+/// it went, if it was archived, and `NotFound(message)` otherwise.
+pub async fn not_found(
+    pool: &PgPool,
+    object: &str,
+    id: Uuid,
+    merchant_id: Uuid,
+    message: &str,
+) -> DefiantError {
+    let archived = sqlx::query_as!(
+        ArchivedObject,
+        r#"
+        SELECT object, id, archived_at, archive_key
+        FROM archived_objects
+        WHERE object = $1 AND id = $2 AND merchant_id = $3
+        "#,
+        object,
+        id,
+        merchant_id,
+    )
+    .fetch_optional(pool)
+    .await;
+    
+    match archived {
+        Ok(Some(archived)) => DefiantError::Archived(Box::new(archived)),
+        Ok(None) => DefiantError::NotFound(message.to_string()),
+        Err(e) => e.into(),
+    }
+}
+
+fn bucket_for(settings: &Archival, region: DataRegion) -> Option<&ArchiveBucket> {
+    match region {
+        DataRegion::Us => Some(&settings.bucket),
+        DataRegion::Eu => settings.eu_bucket.as_ref(),
+    }
+}
+
+async fn record_archived(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    object: &str,
+    rows: &[ArchiveRow],
+    key: &str,
+) -> Result<(), DefiantError> {
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.id).collect();
+    let merchant_ids: Vec<Uuid> = rows.iter().map(|row| row.merchant_id).collect();
+    let created_at: Vec<DateTime<Utc>> = rows.iter().map(|row| row.created_at).collect();
+    
+    sqlx::query!(
+        r#"
+        INSERT INTO archived_objects (object, id, merchant_id, created_at, archive_key)
+        SELECT $1, id, merchant_id, created_at, $5
+        FROM UNNEST($2::UUID[], $3::UUID[], $4::TIMESTAMPTZ[]) AS a(id, merchant_id, created_at)
+        "#,
+        object,
+        &ids,
+        &merchant_ids,
+        &created_at,
+        key,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    Ok(())
+}
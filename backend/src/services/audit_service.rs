@@ -0,0 +1,165 @@
+use std::sync::Arc;
+use serde_json::{Map, Value};
+use tracing::error;
+
+use crate::{models::{AuditLog, AuditActorType, NewAuditLog, AuditLogListQuery, AuditLogListResponse}, errors::DefiantError, db::Database};
+
+/// Fields whose values never reach the trail, wherever they appear in a snapshot:
+/// any name containing one of the fragments, and these exact names.
+const REDACTED_FRAGMENTS: &[&str] = &["secret", "password", "token"];
+const REDACTED_FIELDS: &[&str] = &["key", "backup_codes", "cvc"];
+
+/// The audit trail of mutating API calls, kept in the directory database beside the
+/// security log.
+pub struct AuditService {
+    db: Arc<Database>,
+}
+
+impl AuditService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Appends a call to the trail, diffing its response against the resource as the
+    /// previous audited call left it. The call has already happened, so a failure here
+    /// is logged rather than returned.
+    pub(crate) async fn record(&self, entry: NewAuditLog) {
+        let route = entry.route.clone();
+        if let Err(e) = self.try_record(entry).await {
+            error!("Failed to record audit log for {}: {}", route, e);
+        }
+    }
+    
+    pub async fn list(
+        &self,
+        query: AuditLogListQuery,
+        api_key: &str,
+    ) -> Result<AuditLogListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT id, merchant_id, actor_type AS "actor_type: AuditActorType", actor_id, method, route, path,
+                resource_type, resource_id, status_code, ip_address, before, after, changes, created_at
+            FROM audit_logs
+            WHERE merchant_id = $1
+            AND ($2::TEXT IS NULL OR resource_type = $2)
+            AND ($3::TEXT IS NULL OR resource_id = $3)
+            AND ($4::audit_actor_type IS NULL OR actor_type = $4)
+            AND ($5::TEXT IS NULL OR actor_id = $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+            AND ($7::TIMESTAMPTZ IS NULL OR created_at <= $7)
+            ORDER BY created_at DESC
+            LIMIT $8
+            "#,
+            merchant_id,
+            query.resource_type,
+            query.resource_id,
+            query.actor_type as Option<AuditActorType>,
+            query.actor_id,
+            query.created_gte,
+            query.created_lte,
+            limit + 1,
+        )
+        .fetch_all(self.db.read())
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(AuditLogListResponse {
+            data,
+            has_more,
+            url: "/api/v1/audit_logs".into(),
+        })
+    }
+    
+    async fn try_record(&self, entry: NewAuditLog) -> Result<(), DefiantError> {
+        let after = entry.after.map(redact);
+        let before = match &entry.resource_id {
+            Some(resource_id) => sqlx::query_scalar!(
+                r#"
+                SELECT after AS "after!" FROM audit_logs
+                WHERE merchant_id IS NOT DISTINCT FROM $1 AND resource_type = $2 AND resource_id = $3
+                AND after IS NOT NULL
+                ORDER BY created_at DESC
+                LIMIT 1
+                "#,
+                entry.merchant_id,
+                entry.resource_type,
+                resource_id,
+            )
+            .fetch_optional(&self.db.pool)
+            .await?,
+            None => None,
+        };
+        let changes = match (&before, &after) {
+            (Some(Value::Object(before)), Some(Value::Object(after))) if before.get("id") == after.get("id") => {
+                Some(diff(before, after))
+            }
+            _ => None,
+        };
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_logs (merchant_id, actor_type, actor_id, method, route, path, resource_type,
+                resource_id, status_code, ip_address, before, after, changes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#,
+            entry.merchant_id,
+            entry.actor_type as AuditActorType,
+            entry.actor_id,
+            entry.method,
+            entry.route,
+            entry.path,
+            entry.resource_type,
+            entry.resource_id,
+            entry.status_code,
+            entry.ip_address,
+            before,
+            after,
+            changes,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        Ok(())
+    }
+}
+
+/// Replaces the values of secret-looking fields, at any depth.
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(name, value)| {
+                    let sensitive = REDACTED_FRAGMENTS.iter().any(|fragment| name.contains(fragment))
+                        || REDACTED_FIELDS.contains(&name.as_str());
+                    let value = if sensitive {
+                        Value::String("[redacted]".into())
+                    } else {
+                        redact(value)
+                    };
+                    (name, value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.into_iter().map(redact).collect()),
+        value => value,
+    }
+}
+
+fn diff(before: &Map<String, Value>, after: &Map<String, Value>) -> Value {
+    let mut changes = Map::new();
+    for name in before.keys().chain(after.keys().filter(|name| !before.contains_key(*name))) {
+        let (old, new) = (before.get(name), after.get(name));
+        if old != new {
+            changes.insert(name.clone(), serde_json::json!({ "before": old, "after": new }));
+        }
+    }
+    Value::Object(changes)
+}
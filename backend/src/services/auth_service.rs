@@ -0,0 +1,390 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::digest;
+use uuid::Uuid;
+use tracing::{info, warn};
+
+use crate::{models::{AuthSession, CreateSessionRequest, TeamMember, TeamRole, TokenPair, NewSecurityEvent, SecurityEventType}, errors::DefiantError, db::{Database, AuthenticatedKey, hash_api_key}, config::Config, custom_middleware::auth::Claims, api::v1::permissions::API_KEY_SCOPES};
+use super::account_service::is_api_key;
+use super::mfa_service::{MfaService, SignIn};
+use super::security_event_service::SecurityEventService;
+use super::security_service::{SecurityService, SignedRequest};
+use super::team_service::{TeamService, verify_password};
+
+const REFRESH_TOKEN_PREFIX: &str = "rt_";
+/// JWT role of a session that may only enroll in MFA
+pub(crate) const MFA_ENROLLMENT_ROLE: &str = "mfa_enrollment";
+const ENROLLMENT_SESSION_MINUTES: i64 = 15;
+
+/// Dashboard sessions: short-lived JWT access tokens, renewed with a long-lived
+/// refresh token kept server-side so a session can be revoked at any time.
+pub struct AuthService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl AuthService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Signs a merchant into the dashboard with a full-access secret key, so the key
+    /// itself never has to be kept in the browser. The session acts as an owner, from
+    /// within the key's IP allowlist, and ends when the key stops working. The auth
+    /// middleware skips the exchange, so the allowlist and request signing are enforced
+    /// here.
+    pub async fn create_merchant_session(
+        &self,
+        redis: &redis::Client,
+        api_key: &str,
+        signed: Option<&SignedRequest>,
+        request: CreateSessionRequest,
+        client_ip: Option<IpAddr>,
+    ) -> Result<TokenPair, DefiantError> {
+        if !is_api_key(api_key) {
+            return Err(DefiantError::AuthenticationError("A secret API key is required".into()));
+        }
+        let key = self.db.authenticate_key(api_key).await?;
+        let security_service = SecurityService::new(self.db.clone(), self.config.clone());
+        security_service
+            .enforce_ip_allowlist(&key, client_ip, "POST", "/api/auth/token")
+            .await?;
+        security_service
+            .enforce_request_signing(redis, api_key, key.merchant_id, signed)
+            .await?;
+        let subject = merchant_subject(&key)?;
+        // The key itself can enroll, so it needs no enrollment session
+        let sign_in = self.check_second_factor(&subject, key.merchant_id, request.mfa_code.as_deref(), client_ip).await?;
+        if sign_in == SignIn::EnrollmentRequired {
+            return Err(DefiantError::AuthorizationError(
+                "This merchant requires MFA; enroll with /api/auth/mfa/setup before signing in".into(),
+            ));
+        }
+        
+        self.record_sign_in(SecurityEventType::LoginSucceeded, &subject, key.merchant_id, client_ip, "api_key").await;
+        self.start_session(&subject, "merchant", Some(key.merchant_id), Some(TeamRole::Owner), key.livemode, Some(&key)).await
+    }
+    
+    /// Signs a team member in with their email and password, then their second
+    /// factor when they have MFA enabled.
+    pub async fn create_member_session(
+        &self,
+        request: CreateSessionRequest,
+        client_ip: Option<IpAddr>,
+    ) -> Result<TokenPair, DefiantError> {
+        let invalid = || DefiantError::AuthenticationError("Invalid email or password".into());
+        let (Some(email), Some(password)) = (request.email.as_deref(), request.password.clone()) else {
+            return Err(DefiantError::ValidationError("email and password are required".into()));
+        };
+        
+        let member = TeamService::new(self.db.clone(), self.config.clone())
+            .find_for_sign_in(email, request.merchant_id)
+            .await?
+            .ok_or_else(invalid)?;
+        let password_hash = member.password_hash.clone().ok_or_else(invalid)?;
+        if !verify_password(password, password_hash).await? {
+            warn!("Failed sign-in for team member {}", member.id);
+            self.record_sign_in(SecurityEventType::LoginFailed, &member.id.to_string(), member.merchant_id, client_ip, "password").await;
+            return Err(invalid());
+        }
+        
+        self.start_member_session(&member, request.mfa_code.as_deref(), request.livemode, client_ip, "password").await
+    }
+    
+    /// Starts a session for a team member who proved who they are with `method`, once
+    /// their second factor passes. A member whose merchant requires MFA but who has not
+    /// enrolled gets an enrollment session instead.
+    pub(crate) async fn start_member_session(
+        &self,
+        member: &TeamMember,
+        mfa_code: Option<&str>,
+        livemode: bool,
+        client_ip: Option<IpAddr>,
+        method: &str,
+    ) -> Result<TokenPair, DefiantError> {
+        let subject = member.id.to_string();
+        let sign_in = self.check_second_factor(&subject, member.merchant_id, mfa_code, client_ip).await?;
+        
+        self.record_sign_in(SecurityEventType::LoginSucceeded, &subject, member.merchant_id, client_ip, method).await;
+        match sign_in {
+            SignIn::Allowed => {
+                self.start_session(&subject, "team_member", Some(member.merchant_id), Some(member.role), livemode, None).await
+            }
+            SignIn::EnrollmentRequired => self.start_enrollment_session(&subject, member.merchant_id).await,
+        }
+    }
+    
+    /// `role` is the JWT role claim; `team_role` decides what the session may do
+    /// through the API. A session exchanged for `api_key` keeps its IP allowlist and
+    /// lasts only as long as the key does.
+    pub async fn start_session(
+        &self,
+        subject: &str,
+        role: &str,
+        merchant_id: Option<Uuid>,
+        team_role: Option<TeamRole>,
+        livemode: bool,
+        api_key: Option<&AuthenticatedKey>,
+    ) -> Result<TokenPair, DefiantError> {
+        let refresh_token = generate_refresh_token();
+        let session = sqlx::query_as!(
+            AuthSession,
+            r#"
+            INSERT INTO auth_sessions (subject, role, merchant_id, team_role, livemode, refresh_token_hash, expires_at, allowed_ips, api_key_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING id, subject, role, merchant_id, refresh_token_hash, previous_refresh_token_hash,
+                expires_at, last_refreshed_at, revoked_at, created_at, updated_at,
+                team_role AS "team_role: TeamRole", livemode, access_token_hash, access_expires_at
+            "#,
+            subject,
+            role,
+            merchant_id,
+            team_role as Option<TeamRole>,
+            livemode,
+            hash_refresh_token(&refresh_token),
+            Utc::now() + Duration::seconds(self.config.jwt_refresh_expiration),
+            api_key.and_then(|key| key.allowed_ips.as_deref()),
+            api_key.map(|key| key.id),
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("Session {} started for {} ({})", session.id, subject, role);
+        self.issue_tokens(&session, refresh_token).await
+    }
+    
+    /// A session that can only set up and verify MFA: it has no team role, so the API
+    /// does not accept it, and it ends after a few minutes. The member signs in again
+    /// once enrolled.
+    async fn start_enrollment_session(&self, subject: &str, merchant_id: Uuid) -> Result<TokenPair, DefiantError> {
+        let refresh_token = generate_refresh_token();
+        let session = sqlx::query_as!(
+            AuthSession,
+            r#"
+            INSERT INTO auth_sessions (subject, role, merchant_id, refresh_token_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, subject, role, merchant_id, refresh_token_hash, previous_refresh_token_hash,
+                expires_at, last_refreshed_at, revoked_at, created_at, updated_at,
+                team_role AS "team_role: TeamRole", livemode, access_token_hash, access_expires_at
+            "#,
+            subject,
+            MFA_ENROLLMENT_ROLE,
+            merchant_id,
+            hash_refresh_token(&refresh_token),
+            Utc::now() + Duration::minutes(ENROLLMENT_SESSION_MINUTES),
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("MFA enrollment session {} started for {}", session.id, subject);
+        self.issue_tokens(&session, refresh_token).await
+    }
+    
+    /// Ends every session of a subject, e.g. a team member whose role changed.
+    pub(crate) async fn revoke_subject(&self, subject: &str) -> Result<(), DefiantError> {
+        sqlx::query!(
+            r#"UPDATE auth_sessions SET revoked_at = NOW() WHERE subject = $1 AND revoked_at IS NULL"#,
+            subject,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Exchanges a refresh token for a new access token and a replacement refresh
+    /// token. The session keeps its original expiry, and one exchanged for an API key
+    /// stops refreshing once the key is no longer active.
+    pub async fn refresh(&self, refresh_token: &str) -> Result<TokenPair, DefiantError> {
+        let invalid = || DefiantError::AuthenticationError("Refresh token is invalid, expired or revoked".into());
+        let presented = hash_refresh_token(refresh_token);
+        let replacement = generate_refresh_token();
+        
+        let session = sqlx::query_as!(
+            AuthSession,
+            r#"
+            UPDATE auth_sessions
+            SET refresh_token_hash = $2, previous_refresh_token_hash = refresh_token_hash, last_refreshed_at = NOW()
+            WHERE refresh_token_hash = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            AND (api_key_id IS NULL OR EXISTS(
+                SELECT 1 FROM api_keys k
+                WHERE k.id = auth_sessions.api_key_id AND k.active = true
+                AND (k.expires_at IS NULL OR k.expires_at > NOW())
+            ))
+            RETURNING id, subject, role, merchant_id, refresh_token_hash, previous_refresh_token_hash,
+                expires_at, last_refreshed_at, revoked_at, created_at, updated_at,
+                team_role AS "team_role: TeamRole", livemode, access_token_hash, access_expires_at
+            "#,
+            presented,
+            hash_refresh_token(&replacement),
+        )
+        .fetch_optional(&self.db.pool)
+        .await?;
+        
+        match session {
+            Some(session) => self.issue_tokens(&session, replacement).await,
+            None => {
+                // A token that was already replaced has been copied; end the session
+                // so neither holder can keep using it
+                let reused = sqlx::query_scalar!(
+                    r#"
+                    UPDATE auth_sessions SET revoked_at = NOW()
+                    WHERE previous_refresh_token_hash = $1 AND revoked_at IS NULL
+                    RETURNING id
+                    "#,
+                    presented,
+                )
+                .fetch_optional(&self.db.pool)
+                .await?;
+                if let Some(session_id) = reused {
+                    warn!("Replaced refresh token presented; session {} revoked", session_id);
+                }
+                Err(invalid())
+            }
+        }
+    }
+    
+    /// Revokes the session named by an access token's `sid` claim. Its access tokens
+    /// are rejected from now on and its refresh token no longer works.
+    pub async fn logout(&self, sid: Option<&str>) -> Result<(), DefiantError> {
+        let session_id = sid
+            .and_then(|sid| Uuid::parse_str(sid).ok())
+            .ok_or_else(|| DefiantError::BadRequest("Token does not belong to a session".into()))?;
+        
+        sqlx::query!(
+            r#"UPDATE auth_sessions SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL"#,
+            session_id,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        info!("Session {} revoked by logout", session_id);
+        Ok(())
+    }
+    
+    /// Checked by the auth middleware on every request made with an access token.
+    pub(crate) async fn session_active(&self, sid: &str) -> Result<bool, DefiantError> {
+        let Ok(session_id) = Uuid::parse_str(sid) else {
+            return Ok(false);
+        };
+        
+        let active = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM auth_sessions
+                WHERE id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+                AND (api_key_id IS NULL OR EXISTS(
+                    SELECT 1 FROM api_keys k
+                    WHERE k.id = auth_sessions.api_key_id AND k.active = true
+                    AND (k.expires_at IS NULL OR k.expires_at > NOW())
+                ))
+            ) AS "active!"
+            "#,
+            session_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        Ok(active)
+    }
+    
+    /// Runs the MFA check for a sign-in, recording the failure when a code was given
+    /// and rejected.
+    async fn check_second_factor(
+        &self,
+        subject: &str,
+        merchant_id: Uuid,
+        mfa_code: Option<&str>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<SignIn, DefiantError> {
+        let second_factor = MfaService::new(self.db.clone(), self.config.clone())
+            .check_sign_in(subject, Some(merchant_id), mfa_code, client_ip)
+            .await;
+        if let (Err(DefiantError::AuthenticationError(_)), Some(_)) = (&second_factor, mfa_code) {
+            self.record_sign_in(SecurityEventType::LoginFailed, subject, merchant_id, client_ip, "mfa_code").await;
+        }
+        second_factor
+    }
+    
+    /// `method` is how the user signed in, or which check they failed.
+    async fn record_sign_in(
+        &self,
+        event_type: SecurityEventType,
+        subject: &str,
+        merchant_id: Uuid,
+        client_ip: Option<IpAddr>,
+        method: &str,
+    ) {
+        SecurityEventService::new(self.db.clone())
+            .record(NewSecurityEvent {
+                subject: Some(subject.to_string()),
+                ip_address: client_ip.map(|ip| ip.to_string()),
+                details: serde_json::json!({ "method": method }),
+                ..NewSecurityEvent::new(merchant_id, event_type)
+            })
+            .await;
+    }
+    
+    /// Signs a new access token and records its hash, replacing the session's previous
+    /// access token for API calls.
+    async fn issue_tokens(&self, session: &AuthSession, refresh_token: String) -> Result<TokenPair, DefiantError> {
+        let expires_at = Utc::now() + Duration::seconds(self.config.jwt_expiration);
+        let claims = Claims {
+            sub: session.subject.clone(),
+            exp: expires_at.timestamp() as usize,
+            role: session.role.clone(),
+            merchant_id: session.merchant_id.map(|id| id.to_string()),
+            sid: Some(session.id.to_string()),
+        };
+        let access_token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.config.jwt_secret.as_bytes()),
+        )
+        .map_err(|_| DefiantError::InternalError)?;
+        
+        sqlx::query!(
+            r#"UPDATE auth_sessions SET access_token_hash = $2, access_expires_at = $3 WHERE id = $1"#,
+            session.id,
+            hash_api_key(&access_token),
+            expires_at,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        Ok(TokenPair {
+            access_token,
+            token_type: "Bearer".into(),
+            expires_in: self.config.jwt_expiration,
+            refresh_token,
+            refresh_expires_at: session.expires_at,
+        })
+    }
+}
+
+/// The session subject for a merchant signing in with an API key, which must be a
+/// full-access secret key.
+pub(crate) fn merchant_subject(key: &AuthenticatedKey) -> Result<String, DefiantError> {
+    if API_KEY_SCOPES.iter().any(|scope| !key.grants(scope)) {
+        return Err(DefiantError::AuthorizationError("Only a full-access secret key can sign in".into()));
+    }
+    
+    Ok(key.merchant_id.to_string())
+}
+
+fn generate_refresh_token() -> String {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(48)
+        .map(char::from)
+        .collect();
+    
+    format!("{}{}", REFRESH_TOKEN_PREFIX, token)
+}
+
+fn hash_refresh_token(refresh_token: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, refresh_token.as_bytes()).as_ref())
+}
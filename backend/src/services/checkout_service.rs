@@ -0,0 +1,656 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{Duration, Utc};
+use redis::aio::ConnectionManager;
+use tracing::info;
+
+use crate::{models::{PaymentLink, CheckoutSession, CheckoutSessionStatus, FieldCollection, PaymentLinkResponse, CreatePaymentLinkRequest, CreateCheckoutSessionRequest, CompleteCheckoutSessionRequest, HostedCheckoutSession, CompletedCheckout, PaymentStatus, PaymentMethod, ShippingOption, ShippingDetails, CollectedFields, NewCheckoutSession, FieldRequirement, CustomAmount, CreateLineItem, TaxBehavior, Pii}, errors::DefiantError, db::Database, pii};
+use super::fx_service;
+
+/// How long a customer has to complete a hosted checkout session.
+const SESSION_TTL_HOURS: i64 = 24;
+
+pub struct CheckoutService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+}
+
+impl CheckoutService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
+        Self { db, redis }
+    }
+    
+    pub async fn create_payment_link(
+        &self,
+        mut request: CreatePaymentLinkRequest,
+        api_key: &str,
+    ) -> Result<PaymentLinkResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        fx_service::ensure_supported_currency(&request.currency)?;
+        ShippingOption::ensure_unique(&request.shipping_options)?;
+        
+        if !request.shipping_options.is_empty() {
+            // Shipping options are meaningless without somewhere to ship to
+            request.field_collection.shipping_address = FieldRequirement::Required;
+        }
+        
+        let custom_amount = match &request.custom_amount {
+            Some(config) => {
+                config.ensure_consistent()?;
+                config.check(request.amount)?;
+                Some(serde_json::to_value(config).map_err(|_| DefiantError::InternalError)?)
+            }
+            None => None,
+        };
+        
+        let field_collection = serde_json::to_value(&request.field_collection)
+            .map_err(|_| DefiantError::InternalError)?;
+        let shipping_options = serde_json::to_value(&request.shipping_options)
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let link = sqlx::query_as!(
+            PaymentLink,
+            r#"
+            INSERT INTO payment_links (
+                merchant_id, name, description, amount, currency,
+                field_collection, shipping_options, metadata,
+                inventory_total, inventory_remaining, custom_amount, is_donation, livemode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9, $10, $11, $12)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.name,
+            request.description,
+            request.amount,
+            request.currency.to_uppercase(),
+            field_collection,
+            shipping_options,
+            request.metadata,
+            request.inventory,
+            custom_amount,
+            request.is_donation,
+            key.livemode,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Payment link created: {}", link.id);
+        Ok(link.into())
+    }
+    
+    pub async fn get_payment_link(
+        &self,
+        link_id: Uuid,
+        api_key: &str,
+    ) -> Result<PaymentLinkResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let link = sqlx::query_as!(
+            PaymentLink,
+            r#"SELECT * FROM payment_links WHERE id = $1 AND merchant_id = $2"#,
+            link_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment link not found".into()))?;
+        
+        Ok(link.into())
+    }
+    
+    /// Adds units to a limited-stock link. Links without inventory tracking are left unlimited.
+    pub async fn restock_payment_link(
+        &self,
+        link_id: Uuid,
+        quantity: i32,
+        api_key: &str,
+    ) -> Result<PaymentLinkResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let link = sqlx::query_as!(
+            PaymentLink,
+            r#"
+            UPDATE payment_links
+            SET inventory_total = inventory_total + $1,
+                inventory_remaining = inventory_remaining + $1
+            WHERE id = $2 AND merchant_id = $3
+            RETURNING *
+            "#,
+            quantity,
+            link_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment link not found".into()))?;
+        
+        if link.inventory_total.is_none() {
+            return Err(DefiantError::BadRequest("Payment link does not track inventory".into()));
+        }
+        
+        info!("Payment link {} restocked by {}", link.id, quantity);
+        Ok(link.into())
+    }
+    
+    pub async fn create_session(
+        &self,
+        request: CreateCheckoutSessionRequest,
+        api_key: &str,
+    ) -> Result<CheckoutSession, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let link = match request.payment_link_id {
+            Some(link_id) => Some(self.active_link(pool, link_id, Some(merchant_id)).await?),
+            None => None,
+        };
+        if link.as_ref().is_some_and(|link| link.livemode != key.livemode) {
+            return Err(DefiantError::NotFound("Payment link not found".into()));
+        }
+        
+        let cart_total = if request.line_items.is_empty() {
+            None
+        } else {
+            Some(CreateLineItem::total(&request.line_items)
+                .ok_or_else(|| DefiantError::ValidationError("line_items: total is out of range".into()))?)
+        };
+        
+        let (amount, currency) = match (&link, cart_total.or(request.amount), request.currency.as_deref()) {
+            (_, Some(amount), Some(currency)) => (amount, currency.to_uppercase()),
+            (Some(link), None, _) => (link.amount, link.currency.clone()),
+            (Some(link), Some(amount), None) => (amount, link.currency.clone()),
+            (None, _, _) => {
+                return Err(DefiantError::ValidationError(
+                    "amount (or line_items) and currency are required without a payment_link_id".into(),
+                ))
+            }
+        };
+        fx_service::ensure_supported_currency(&currency)?;
+        
+        if let (Some(total), Some(requested)) = (cart_total, request.amount) {
+            if total != requested {
+                return Err(DefiantError::ValidationError(format!(
+                    "amount: {} does not match the line item total {}",
+                    requested, total
+                )));
+            }
+        }
+        
+        let line_items = match (request.line_items, &link) {
+            (items, _) if !items.is_empty() => items,
+            (_, Some(link)) if cart_total.is_none() && request.amount.is_none() => vec![link_line_item(link)],
+            _ => Vec::new(),
+        };
+        
+        let mut field_collection = match (request.field_collection, &link) {
+            (Some(config), _) => config,
+            (None, Some(link)) => FieldCollection::from_value(&link.field_collection),
+            (None, None) => FieldCollection::default(),
+        };
+        
+        let shipping_options = match (request.shipping_options, &link) {
+            (Some(options), _) => options,
+            (None, Some(link)) => ShippingOption::list_from_value(&link.shipping_options),
+            (None, None) => Vec::new(),
+        };
+        ShippingOption::ensure_unique(&shipping_options)?;
+        if !shipping_options.is_empty() {
+            field_collection.shipping_address = FieldRequirement::Required;
+        }
+        
+        let custom_amount = match (request.custom_amount, &link) {
+            (Some(config), _) => Some(config),
+            (None, Some(link)) => CustomAmount::from_value(link.custom_amount.as_ref()),
+            (None, None) => None,
+        };
+        if let Some(config) = &custom_amount {
+            config.ensure_consistent()?;
+            if line_items.len() > 1 {
+                return Err(DefiantError::ValidationError(
+                    "custom_amount cannot be combined with an itemized cart".into(),
+                ));
+            }
+        }
+        let is_donation = request.is_donation
+            .unwrap_or_else(|| link.as_ref().map(|l| l.is_donation).unwrap_or(false));
+        
+        self.insert_session(pool, NewCheckoutSession {
+            merchant_id,
+            payment_link_id: link.as_ref().map(|l| l.id),
+            amount,
+            currency,
+            field_collection,
+            shipping_options,
+            custom_amount,
+            is_donation,
+            line_items,
+            success_url: request.success_url,
+            cancel_url: request.cancel_url,
+            metadata: request.metadata,
+            livemode: key.livemode,
+        })
+        .await
+    }
+    
+    /// Opens a session for a customer who followed a payment link.
+    pub async fn create_session_from_link(&self, link_id: Uuid) -> Result<HostedCheckoutSession, DefiantError> {
+        let pool = self.db.pool_for_row("payment_links", link_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Payment link not found".into()))?;
+        let link = self.active_link(pool, link_id, None).await?;
+        
+        let session = self
+            .insert_session(pool, NewCheckoutSession {
+                merchant_id: link.merchant_id,
+                payment_link_id: Some(link.id),
+                amount: link.amount,
+                currency: link.currency.clone(),
+                field_collection: FieldCollection::from_value(&link.field_collection),
+                shipping_options: ShippingOption::list_from_value(&link.shipping_options),
+                custom_amount: CustomAmount::from_value(link.custom_amount.as_ref()),
+                is_donation: link.is_donation,
+                line_items: vec![link_line_item(&link)],
+                success_url: None,
+                cancel_url: None,
+                metadata: link.metadata.clone(),
+                livemode: link.livemode,
+            })
+            .await?;
+        
+        Ok(session.into())
+    }
+    
+    pub async fn get_session(
+        &self,
+        session_id: Uuid,
+        api_key: &str,
+    ) -> Result<CheckoutSession, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.pool_for_merchant(key.merchant_id).await?;
+        
+        sqlx::query_as!(
+            CheckoutSession,
+            r#"SELECT * FROM checkout_sessions WHERE id = $1 AND merchant_id = $2 AND livemode = $3"#,
+            session_id,
+            key.merchant_id,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Checkout session not found".into()))
+    }
+    
+    pub async fn get_hosted_session(&self, session_id: Uuid) -> Result<HostedCheckoutSession, DefiantError> {
+        let pool = self.db.pool_for_row("checkout_sessions", session_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Checkout session not found".into()))?;
+        let session = sqlx::query_as!(
+            CheckoutSession,
+            r#"SELECT * FROM checkout_sessions WHERE id = $1"#,
+            session_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Checkout session not found".into()))?;
+        
+        Ok(session.into())
+    }
+    
+    /// Completes hosted checkout: validates the collected fields against the
+    /// session's configuration, stores them on the customer and a new payment,
+    /// and closes the session, all in one transaction.
+    pub async fn complete_session(
+        &self,
+        session_id: Uuid,
+        request: CompleteCheckoutSessionRequest,
+    ) -> Result<CompletedCheckout, DefiantError> {
+        let pool = self.db.pool_for_row("checkout_sessions", session_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Checkout session not found".into()))?;
+        let mut tx = pool.begin().await?;
+        
+        let session = sqlx::query_as!(
+            CheckoutSession,
+            r#"SELECT * FROM checkout_sessions WHERE id = $1 FOR UPDATE"#,
+            session_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Checkout session not found".into()))?;
+        
+        if !matches!(session.status, CheckoutSessionStatus::Open) {
+            return Err(DefiantError::Conflict("Checkout session is no longer open".into()));
+        }
+        
+        let now = Utc::now();
+        if session.expires_at <= now {
+            sqlx::query!(
+                r#"UPDATE checkout_sessions SET status = $1 WHERE id = $2"#,
+                CheckoutSessionStatus::Expired as CheckoutSessionStatus,
+                session.id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            return Err(DefiantError::Conflict("Checkout session has expired".into()));
+        }
+        
+        let field_collection = FieldCollection::from_value(&session.field_collection);
+        let fields = field_collection.sanitize(request.fields)?;
+        
+        // Claim a unit of stock; the conditional update makes concurrent
+        // completions race safely for the last unit
+        if let Some(link_id) = session.payment_link_id {
+            let claimed = sqlx::query!(
+                r#"
+                UPDATE payment_links
+                SET inventory_remaining = inventory_remaining - 1
+                WHERE id = $1 AND (inventory_remaining IS NULL OR inventory_remaining > 0)
+                "#,
+                link_id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            
+            if claimed.rows_affected() == 0 {
+                return Err(DefiantError::SoldOut("This item sold out before checkout completed".into()));
+            }
+        }
+        
+        let shipping = resolve_shipping(&session, &fields, request.shipping_option.as_deref())?;
+        let shipping_amount = shipping.as_ref().map(|s| s.amount).unwrap_or(0);
+        let base_amount = match (CustomAmount::from_value(session.custom_amount.as_ref()), request.amount) {
+            (Some(config), Some(chosen)) => {
+                config.check(chosen)?;
+                chosen
+            }
+            (Some(_), None) => session.amount,
+            (None, Some(_)) => {
+                return Err(DefiantError::ValidationError("amount: this checkout has a fixed amount".into()))
+            }
+            (None, None) => session.amount,
+        };
+        
+        // A customer-chosen amount replaces the cart with a single line for that amount
+        let mut line_items = CreateLineItem::list_from_value(&session.line_items);
+        if line_items.is_empty() || base_amount != session.amount {
+            let name = line_items
+                .first()
+                .map(|item| item.name.clone())
+                .unwrap_or_else(|| if session.is_donation { "Donation".into() } else { "Payment".into() });
+            line_items = vec![CreateLineItem {
+                name,
+                quantity: 1,
+                unit_amount: base_amount,
+                tax_behavior: TaxBehavior::default(),
+                metadata: None,
+            }];
+        }
+        
+        let amount = base_amount
+            .checked_add(shipping_amount)
+            .ok_or_else(|| DefiantError::ValidationError("Payment amount is out of range".into()))?;
+        let shipping_json = shipping
+            .as_ref()
+            .map(|s| serde_json::to_value(s).map_err(|_| DefiantError::InternalError))
+            .transpose()?;
+        let collected = serde_json::to_value(&fields).map_err(|_| DefiantError::InternalError)?;
+        
+        // Consents are kept with when and where they were given
+        let consents: serde_json::Map<String, serde_json::Value> = fields.consents
+            .iter()
+            .map(|(key, accepted)| {
+                (key.clone(), serde_json::json!({
+                    "accepted": accepted,
+                    "recorded_at": now,
+                    "checkout_session_id": session.id,
+                }))
+            })
+            .collect();
+        let shipping_address = fields.shipping_address
+            .as_ref()
+            .map(|address| serde_json::to_value(address).map_err(|_| DefiantError::InternalError))
+            .transpose()?;
+        
+        // Sealed for the id a new customer gets; a returning one keeps its email
+        let new_customer_id = Uuid::new_v4();
+        let email = Pii::seal(&pii::context("customers.email", new_customer_id), fields.email.to_lowercase())?;
+        
+        // Returning customers are matched on the email's blind index, as the email is sealed
+        let customer_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO customers (id, merchant_id, email, email_hash, name, shipping_address, consents, livemode)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (merchant_id, livemode, email_hash) DO UPDATE SET
+                name = COALESCE(EXCLUDED.name, customers.name),
+                shipping_address = COALESCE(EXCLUDED.shipping_address, customers.shipping_address),
+                consents = COALESCE(customers.consents, '{}'::jsonb) || EXCLUDED.consents
+            RETURNING id
+            "#,
+            new_customer_id,
+            session.merchant_id,
+            email.sealed(),
+            pii::keyring()?.blind_index(email.as_str()),
+            fields.name,
+            shipping_address,
+            serde_json::Value::Object(consents),
+            session.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        // Sealed once the row is known, as it is bound to the customer it is stored on
+        if let Some(phone) = &fields.phone {
+            let phone = Pii::seal(&pii::context("customers.phone", customer_id), phone.as_str())?;
+            sqlx::query!(
+                "UPDATE customers SET phone = $2 WHERE id = $1",
+                customer_id,
+                phone.sealed(),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        let payment_id = sqlx::query_scalar!(
+            r#"
+            INSERT INTO payments (
+                merchant_id, customer_id, amount, currency, status, payment_method,
+                metadata, checkout_session_id, collected_fields, shipping_amount, shipping,
+                is_donation, livemode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            RETURNING id
+            "#,
+            session.merchant_id,
+            customer_id,
+            amount,
+            session.currency,
+            PaymentStatus::Pending as PaymentStatus,
+            request.payment_method as PaymentMethod,
+            session.metadata,
+            session.id,
+            collected,
+            shipping_amount,
+            shipping_json,
+            session.is_donation,
+            session.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        for item in &line_items {
+            let item_amount = item.amount()
+                .ok_or_else(|| DefiantError::ValidationError("line_items: amount is out of range".into()))?;
+            sqlx::query!(
+                r#"
+                INSERT INTO payment_line_items (
+                    payment_id, name, quantity, unit_amount, amount, tax_behavior, metadata
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                "#,
+                payment_id,
+                item.name,
+                item.quantity,
+                item.unit_amount,
+                item_amount,
+                item.tax_behavior as TaxBehavior,
+                item.metadata,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        sqlx::query!(
+            r#"
+            UPDATE checkout_sessions
+            SET status = $1, customer_id = $2, payment_id = $3,
+                collected_fields = $4, completed_at = $5,
+                shipping_option = $6, shipping_amount = $7
+            WHERE id = $8
+            "#,
+            CheckoutSessionStatus::Complete as CheckoutSessionStatus,
+            customer_id,
+            payment_id,
+            collected,
+            now,
+            shipping_json,
+            shipping_amount,
+            session.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Checkout session {} completed with payment {}", session.id, payment_id);
+        
+        Ok(CompletedCheckout {
+            session_id: session.id,
+            payment_id,
+            amount,
+            shipping_amount,
+            customer_id,
+            success_url: session.success_url,
+        })
+    }
+    
+    async fn active_link(&self, pool: &PgPool, link_id: Uuid, merchant_id: Option<Uuid>) -> Result<PaymentLink, DefiantError> {
+        let link = sqlx::query_as!(
+            PaymentLink,
+            r#"
+            SELECT * FROM payment_links
+            WHERE id = $1 AND ($2::uuid IS NULL OR merchant_id = $2)
+            "#,
+            link_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment link not found".into()))?;
+        
+        if !link.active {
+            return Err(DefiantError::Conflict("Payment link is no longer active".into()));
+        }
+        
+        if link.is_sold_out() {
+            return Err(DefiantError::SoldOut("This item is sold out".into()));
+        }
+        
+        Ok(link)
+    }
+    
+    async fn insert_session(&self, pool: &PgPool, new: NewCheckoutSession) -> Result<CheckoutSession, DefiantError> {
+        let field_collection = serde_json::to_value(&new.field_collection)
+            .map_err(|_| DefiantError::InternalError)?;
+        let shipping_options = serde_json::to_value(&new.shipping_options)
+            .map_err(|_| DefiantError::InternalError)?;
+        let line_items = serde_json::to_value(&new.line_items)
+            .map_err(|_| DefiantError::InternalError)?;
+        let custom_amount = new.custom_amount
+            .as_ref()
+            .map(|config| serde_json::to_value(config).map_err(|_| DefiantError::InternalError))
+            .transpose()?;
+        
+        let session = sqlx::query_as!(
+            CheckoutSession,
+            r#"
+            INSERT INTO checkout_sessions (
+                merchant_id, payment_link_id, amount, currency, field_collection,
+                shipping_options, success_url, cancel_url, metadata, expires_at,
+                custom_amount, is_donation, line_items, livemode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            RETURNING *
+            "#,
+            new.merchant_id,
+            new.payment_link_id,
+            new.amount,
+            new.currency,
+            field_collection,
+            shipping_options,
+            new.success_url,
+            new.cancel_url,
+            new.metadata,
+            Utc::now() + Duration::hours(SESSION_TTL_HOURS),
+            custom_amount,
+            new.is_donation,
+            line_items,
+            new.livemode,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Checkout session created: {}", session.id);
+        Ok(session)
+    }
+}
+
+fn link_line_item(link: &PaymentLink) -> CreateLineItem {
+    CreateLineItem {
+        name: link.name.clone(),
+        quantity: 1,
+        unit_amount: link.amount,
+        tax_behavior: TaxBehavior::default(),
+        metadata: None,
+    }
+}
+
+/// Picks the customer's shipping option from those offered on the session.
+fn resolve_shipping(
+    session: &CheckoutSession,
+    fields: &CollectedFields,
+    selected: Option<&str>,
+) -> Result<Option<ShippingDetails>, DefiantError> {
+    let options = ShippingOption::list_from_value(&session.shipping_options);
+    
+    if options.is_empty() {
+        return match selected {
+            Some(_) => Err(DefiantError::ValidationError("shipping_option: this session does not offer shipping".into())),
+            None => Ok(None),
+        };
+    }
+    
+    let selected = selected
+        .ok_or_else(|| DefiantError::ValidationError("shipping_option: a shipping option must be selected".into()))?;
+    let option = options
+        .into_iter()
+        .find(|o| o.id == selected)
+        .ok_or_else(|| DefiantError::ValidationError(format!("shipping_option: unknown option '{}'", selected)))?;
+    let address = fields.shipping_address
+        .clone()
+        .ok_or_else(|| DefiantError::ValidationError("shipping_address: shipping address is required".into()))?;
+    
+    Ok(Some(ShippingDetails {
+        name: fields.name.clone(),
+        address,
+        option_id: option.id,
+        display_name: option.display_name,
+        amount: option.amount,
+    }))
+}
@@ -0,0 +1,178 @@
+use std::sync::Arc;
+use uuid::Uuid;
+use chrono::Utc;
+use tracing::info;
+
+use crate::{models::{CustomerCommunication, CommunicationStatus, CommunicationChannel, CommunicationKind, NewCommunication, CommunicationsListResponse, DeliveryReceipt, DeliveryOutcome}, errors::DefiantError, db::Database};
+
+/// Header carrying the relay's signature over a delivery receipt
+pub const RECEIPT_SIGNATURE_HEADER: &str = "X-Receipt-Signature";
+
+pub struct CommunicationService {
+    db: Arc<Database>,
+}
+
+impl CommunicationService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Records a communication in the `queued` state before it is handed to a provider.
+    pub async fn record(&self, communication: NewCommunication) -> Result<CustomerCommunication, DefiantError> {
+        let pool = self.db.pool_for_merchant(communication.merchant_id).await?;
+        
+        let record = sqlx::query_as!(
+            CustomerCommunication,
+            r#"
+            INSERT INTO customer_communications (
+                merchant_id, customer_id, channel, kind, recipient,
+                subject, related_object_type, related_object_id, status
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            communication.merchant_id,
+            communication.customer_id,
+            communication.channel as CommunicationChannel,
+            communication.kind as CommunicationKind,
+            communication.recipient,
+            communication.subject,
+            communication.related_object_type,
+            communication.related_object_id,
+            CommunicationStatus::Queued as CommunicationStatus,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(record)
+    }
+    
+    pub async fn mark_sent(
+        &self,
+        merchant_id: Uuid,
+        communication_id: Uuid,
+        provider_message_id: Option<String>,
+    ) -> Result<(), DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query!(
+            r#"
+            UPDATE customer_communications
+            SET status = $1, provider_message_id = $2, sent_at = $3
+            WHERE id = $4
+            "#,
+            CommunicationStatus::Sent as CommunicationStatus,
+            provider_message_id,
+            Utc::now(),
+            communication_id,
+        )
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    pub async fn mark_failed(&self, merchant_id: Uuid, communication_id: Uuid, error: &str) -> Result<(), DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query!(
+            r#"
+            UPDATE customer_communications
+            SET status = $1, error_message = $2
+            WHERE id = $3
+            "#,
+            CommunicationStatus::Failed as CommunicationStatus,
+            error,
+            communication_id,
+        )
+        .execute(pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Applies a delivery receipt posted by the mail relay.
+    pub async fn update_delivery_status(&self, receipt: DeliveryReceipt) -> Result<(), DefiantError> {
+        let (status, delivered_at) = match receipt.status {
+            DeliveryOutcome::Delivered => (CommunicationStatus::Delivered, Some(Utc::now())),
+            DeliveryOutcome::Bounced => (CommunicationStatus::Bounced, None),
+        };
+        
+        // Receipts only carry the relay's message id, so check every cluster
+        let mut rows_affected = 0;
+        for pool in self.db.pools() {
+            let result = sqlx::query!(
+                r#"
+                UPDATE customer_communications
+                SET status = $1,
+                    error_message = COALESCE($2, error_message),
+                    delivered_at = COALESCE($3, delivered_at)
+                WHERE provider_message_id = $4
+                "#,
+                status as CommunicationStatus,
+                receipt.error,
+                delivered_at,
+                receipt.message_id,
+            )
+            .execute(pool)
+            .await?;
+            rows_affected += result.rows_affected();
+        }
+        
+        if rows_affected == 0 {
+            return Err(DefiantError::NotFound("Communication not found".into()));
+        }
+        
+        info!("Delivery status updated for message {}", receipt.message_id);
+        Ok(())
+    }
+    
+    pub async fn list_for_customer(
+        &self,
+        customer_id: Uuid,
+        limit: Option<i64>,
+        api_key: &str,
+    ) -> Result<CommunicationsListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = limit.unwrap_or(20).clamp(1, 100);
+        
+        let customer_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1 AND merchant_id = $2)"#,
+            customer_id,
+            merchant_id,
+        )
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(false);
+        
+        if !customer_exists {
+            return Err(DefiantError::NotFound("Customer not found".into()));
+        }
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            CustomerCommunication,
+            r#"
+            SELECT * FROM customer_communications
+            WHERE customer_id = $1 AND merchant_id = $2
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            customer_id,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(CommunicationsListResponse {
+            data,
+            has_more,
+            url: format!("/api/v1/customers/{}/communications", customer_id),
+        })
+    }
+}
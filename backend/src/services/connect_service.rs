@@ -0,0 +1,367 @@
+use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::{models::{Payment, BalanceResponse, ConnectedAccount, CreateConnectedAccountRequest, ConnectedAccountListQuery, ConnectedAccountListResponse, TransferData, Transfer, CreateTransferRequest, TransferListQuery, TransferListResponse, TransferReversal, CreateTransferReversalRequest, CONNECTED_ACCOUNT_CREATED, TRANSFER_CREATED, TRANSFER_REVERSED}, errors::DefiantError, db::Database};
+use super::webhook_service::WebhookService;
+use super::ledger;
+use super::fx_service;
+
+/// Platforms and the connected accounts they send funds to.
+pub struct ConnectService {
+    db: Arc<Database>,
+}
+
+impl ConnectService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Creates a merchant owned by the platform, homed in the platform's region.
+    pub async fn create_connected_account(
+        &self,
+        request: CreateConnectedAccountRequest,
+        api_key: &str,
+    ) -> Result<ConnectedAccount, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        
+        let account = sqlx::query_as!(
+            ConnectedAccount,
+            r#"
+            INSERT INTO merchants (name, email, website, platform_merchant_id, data_region)
+            SELECT $1, $2, $3, id, data_region FROM merchants WHERE id = $4
+            ON CONFLICT (email) DO NOTHING
+            RETURNING id, platform_merchant_id AS "platform_merchant_id!", name, email, website,
+                active AS "active!", created_at
+            "#,
+            request.name,
+            request.email.to_lowercase(),
+            request.website,
+            platform_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("A merchant with this email already exists".into()))?;
+        
+        info!("Connected account {} created for platform {}", account.id, platform_id);
+        self.emit(platform_id, CONNECTED_ACCOUNT_CREATED, serde_json::json!(account)).await;
+        
+        Ok(account)
+    }
+    
+    pub async fn list_connected_accounts(
+        &self,
+        query: ConnectedAccountListQuery,
+        api_key: &str,
+    ) -> Result<ConnectedAccountListResponse, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            ConnectedAccount,
+            r#"
+            SELECT id, platform_merchant_id AS "platform_merchant_id!", name, email, website,
+                active AS "active!", created_at
+            FROM merchants
+            WHERE platform_merchant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            platform_id,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(ConnectedAccountListResponse {
+            data,
+            has_more,
+            url: "/api/v1/connected_accounts".into(),
+        })
+    }
+    
+    pub async fn get_connected_account(&self, account_id: Uuid, api_key: &str) -> Result<ConnectedAccount, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        self.connected_account(platform_id, account_id).await
+    }
+    
+    /// The connected account's available and pending funds, as its ledger holds them.
+    pub async fn get_connected_account_balance(
+        &self,
+        account_id: Uuid,
+        api_key: &str,
+    ) -> Result<BalanceResponse, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        let account = self.connected_account(platform_id, account_id).await?;
+        let pool = self.db.pool_for_merchant(account.id).await?;
+        
+        ledger::balance(pool, account.id).await
+    }
+    
+    /// Sends funds from the platform's available balance to a connected account.
+    pub async fn create_transfer(
+        &self,
+        request: CreateTransferRequest,
+        api_key: &str,
+    ) -> Result<Transfer, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        fx_service::ensure_supported_currency(&request.currency)?;
+        let destination = self.connected_account(platform_id, request.destination).await?;
+        if !destination.active {
+            return Err(DefiantError::Conflict("Connected account is not active".into()));
+        }
+        
+        let pool = self.db.pool_for_merchant(platform_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let available = ledger::lock_available(&mut tx, platform_id, &request.currency).await?;
+        if request.amount > available {
+            return Err(DefiantError::Conflict(format!(
+                "Transfer exceeds the available balance of {}",
+                fx_service::format_amount(available, &request.currency),
+            )));
+        }
+        
+        let transfer = sqlx::query_as!(
+            Transfer,
+            r#"
+            INSERT INTO transfers (merchant_id, destination, amount, currency, description, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            platform_id,
+            destination.id,
+            request.amount,
+            request.currency.to_uppercase(),
+            request.description,
+            request.metadata,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        ledger::record_transfer(&mut tx, &transfer, None).await?;
+        tx.commit().await?;
+        
+        info!("Transfer {} of {} to connected account {}", transfer.id, fx_service::format_amount(transfer.amount, &transfer.currency), destination.id);
+        self.emit(platform_id, TRANSFER_CREATED, serde_json::json!(transfer)).await;
+        
+        Ok(transfer)
+    }
+    
+    pub async fn list_transfers(
+        &self,
+        query: TransferListQuery,
+        api_key: &str,
+    ) -> Result<TransferListResponse, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(platform_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            Transfer,
+            r#"
+            SELECT * FROM transfers
+            WHERE merchant_id = $1
+            AND ($2::UUID IS NULL OR destination = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            platform_id,
+            query.destination,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(TransferListResponse {
+            data,
+            has_more,
+            url: "/api/v1/transfers".into(),
+        })
+    }
+    
+    pub async fn get_transfer(&self, transfer_id: Uuid, api_key: &str) -> Result<Transfer, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(platform_id).await?;
+        
+        sqlx::query_as!(
+            Transfer,
+            r#"SELECT * FROM transfers WHERE id = $1 AND merchant_id = $2"#,
+            transfer_id,
+            platform_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Transfer not found".into()))
+    }
+    
+    /// Takes funds of a transfer back from the connected account. The connected
+    /// account's balance may go negative when it has already paid the funds out.
+    pub async fn create_reversal(
+        &self,
+        transfer_id: Uuid,
+        request: CreateTransferReversalRequest,
+        api_key: &str,
+    ) -> Result<TransferReversal, DefiantError> {
+        let platform_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(platform_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let transfer = sqlx::query_as!(
+            Transfer,
+            r#"SELECT * FROM transfers WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            transfer_id,
+            platform_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Transfer not found".into()))?;
+        
+        let amount = request.amount.unwrap_or(transfer.reversible_amount());
+        if amount == 0 || amount > transfer.reversible_amount() {
+            return Err(DefiantError::Conflict(format!(
+                "At most {} of this transfer can be reversed",
+                fx_service::format_amount(transfer.reversible_amount(), &transfer.currency),
+            )));
+        }
+        
+        let reversal = sqlx::query_as!(
+            TransferReversal,
+            r#"
+            INSERT INTO transfer_reversals (transfer_id, merchant_id, amount, description, metadata)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+            transfer.id,
+            platform_id,
+            amount,
+            request.description,
+            request.metadata,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let transfer = sqlx::query_as!(
+            Transfer,
+            r#"
+            UPDATE transfers SET amount_reversed = amount_reversed + $1
+            WHERE id = $2
+            RETURNING *
+            "#,
+            amount,
+            transfer.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        ledger::record_transfer_reversal(&mut tx, &transfer, &reversal).await?;
+        tx.commit().await?;
+        
+        info!("Reversed {} of transfer {}", fx_service::format_amount(amount, &transfer.currency), transfer.id);
+        self.emit(platform_id, TRANSFER_REVERSED, serde_json::json!(reversal)).await;
+        
+        Ok(reversal)
+    }
+    
+    pub async fn list_reversals(&self, transfer_id: Uuid, api_key: &str) -> Result<Vec<TransferReversal>, DefiantError> {
+        let transfer = self.get_transfer(transfer_id, api_key).await?;
+        let pool = self.db.pool_for_merchant(transfer.merchant_id).await?;
+        
+        let reversals = sqlx::query_as!(
+            TransferReversal,
+            r#"SELECT * FROM transfer_reversals WHERE transfer_id = $1 ORDER BY created_at"#,
+            transfer.id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(reversals)
+    }
+    
+    /// Checks a payment's `transfer_data` before the payment is recorded.
+    pub async fn validate_transfer_data(
+        &self,
+        platform_id: Uuid,
+        amount: i64,
+        transfer_data: &TransferData,
+    ) -> Result<(), DefiantError> {
+        let destination = self.connected_account(platform_id, transfer_data.destination).await?;
+        if !destination.active {
+            return Err(DefiantError::ValidationError("transfer_data.destination is not active".into()));
+        }
+        if transfer_data.amount.is_some_and(|transfer_amount| transfer_amount > amount) {
+            return Err(DefiantError::ValidationError("transfer_data.amount cannot exceed the payment amount".into()));
+        }
+        
+        Ok(())
+    }
+    
+    async fn connected_account(&self, platform_id: Uuid, account_id: Uuid) -> Result<ConnectedAccount, DefiantError> {
+        sqlx::query_as!(
+            ConnectedAccount,
+            r#"
+            SELECT id, platform_merchant_id AS "platform_merchant_id!", name, email, website,
+                active AS "active!", created_at
+            FROM merchants
+            WHERE id = $1 AND platform_merchant_id = $2
+            "#,
+            account_id,
+            platform_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Connected account not found".into()))
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, data: serde_json::Value) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, data)
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Sends a succeeded payment's funds on to the connected account it was routed to.
+/// The transfer stays pending for as long as the payment's own funds do.
+pub async fn transfer_payment_funds(
+    tx: &mut Transaction<'_, Postgres>,
+    payment: &Payment,
+    destination: Uuid,
+    available_on: Option<DateTime<Utc>>,
+) -> Result<(), DefiantError> {
+    let transfer = sqlx::query_as!(
+        Transfer,
+        r#"
+        INSERT INTO transfers (merchant_id, destination, amount, currency, source_payment_id, description)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (source_payment_id) WHERE source_payment_id IS NOT NULL DO NOTHING
+        RETURNING *
+        "#,
+        payment.merchant_id,
+        destination,
+        payment.transfer_amount.unwrap_or(payment.amount),
+        payment.currency,
+        payment.id,
+        payment.description,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    if let Some(transfer) = transfer {
+        ledger::record_transfer(tx, &transfer, available_on).await?;
+    }
+    
+    Ok(())
+}
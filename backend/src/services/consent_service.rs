@@ -0,0 +1,120 @@
+use std::sync::Arc;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use chrono::Utc;
+use tracing::info;
+
+use crate::{models::{CardOnFileConsent, ConsentDetails, ConsentListResponse}, errors::DefiantError, db::Database};
+
+/// Card-on-file consent evidence. Records are append-only; a new consent is
+/// written each time a card is saved, never an edit of an old one.
+pub struct ConsentService {
+    db: Arc<Database>,
+}
+
+impl ConsentService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn record(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        customer_id: Uuid,
+        payment_method_id: Uuid,
+        mit_agreement_id: Option<Uuid>,
+        payment_id: Option<Uuid>,
+        consent: &ConsentDetails,
+    ) -> Result<CardOnFileConsent, DefiantError> {
+        let record = sqlx::query_as!(
+            CardOnFileConsent,
+            r#"
+            INSERT INTO card_on_file_consents (
+                merchant_id, customer_id, payment_method_id, mit_agreement_id, payment_id,
+                consent_text_version, ip_address, user_agent, accepted_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            merchant_id,
+            customer_id,
+            payment_method_id,
+            mit_agreement_id,
+            payment_id,
+            consent.text_version,
+            consent.ip_address,
+            consent.user_agent,
+            consent.accepted_at.unwrap_or_else(Utc::now),
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        info!("Card-on-file consent {} recorded for payment method {}", record.id, payment_method_id);
+        Ok(record)
+    }
+    
+    pub async fn get_consent(&self, consent_id: Uuid, api_key: &str) -> Result<CardOnFileConsent, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query_as!(
+            CardOnFileConsent,
+            r#"SELECT * FROM card_on_file_consents WHERE id = $1 AND merchant_id = $2"#,
+            consent_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Consent record not found".into()))
+    }
+    
+    pub async fn list_for_customer(
+        &self,
+        customer_id: Uuid,
+        limit: Option<i64>,
+        api_key: &str,
+    ) -> Result<ConsentListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = limit.unwrap_or(20).clamp(1, 100);
+        
+        let customer_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1 AND merchant_id = $2)"#,
+            customer_id,
+            merchant_id,
+        )
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(false);
+        
+        if !customer_exists {
+            return Err(DefiantError::NotFound("Customer not found".into()));
+        }
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            CardOnFileConsent,
+            r#"
+            SELECT * FROM card_on_file_consents
+            WHERE customer_id = $1 AND merchant_id = $2
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            customer_id,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(ConsentListResponse {
+            data,
+            has_more,
+            url: format!("/api/v1/customers/{}/consents", customer_id),
+        })
+    }
+}
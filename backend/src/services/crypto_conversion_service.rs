@@ -0,0 +1,574 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{Chain, CryptoAddress, CryptoConversion, CryptoConversionStatus, CryptoQuote, CryptoSettlementAsset, CryptoSettlementPreference, UpdateCryptoSettlementRequest, ConfigurationChangeData, Payment, CRYPTO_CONVERSION_SETTLED, CRYPTO_CONVERSION_FAILED, CRYPTO_SETTLEMENT_UPDATED}, errors::DefiantError, db::Database, config::Config};
+use super::{crypto_rate_service, ledger, crypto_service::validate_address, webhook_service::WebhookService};
+
+const PROVIDER_TIMEOUT_SECS: u64 = 15;
+const CONVERSION_CHECK_INTERVAL_SECS: f64 = 60.0;
+const CONVERSION_BATCH_SIZE: i64 = 50;
+const MAX_PAYMENT_TOLERANCE_BPS: i32 = 1000;
+pub const DEFAULT_PAYMENT_EXPIRY_MINUTES: i32 = 60;
+/// An expiry shorter than the quote lock would cancel payments still being quoted.
+const PAYMENT_EXPIRY_MINUTES: std::ops::RangeInclusive<i32> = 15..=10080;
+
+#[derive(Debug, Serialize)]
+struct OpenOrderRequest<'a> {
+    deposit_chain: Chain,
+    deposit_asset: &'a str,
+    deposit_amount: String,
+    settle_chain: Chain,
+    settle_asset: &'a str,
+    settle_address: &'a str,
+    client_reference: Uuid,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProviderOrder {
+    id: String,
+    deposit_address: Option<String>,
+    /// `awaiting_deposit`, `processing`, `settled`, `failed` or `refunded`
+    status: String,
+    #[serde(with = "rust_decimal::serde::str")]
+    settle_amount: Decimal,
+    settle_tx_hash: Option<String>,
+    error: Option<String>,
+}
+
+/// Stablecoin settlement for crypto payments.
+///
+/// Merchants who opt into USDC or USDT have their crypto payments collected through a
+/// swap provider instead of their own wallet: the customer pays into a deposit address
+/// the provider issues for the payment, the watcher confirms it like any other address,
+/// and the provider then sends the stablecoin to the merchant's settlement address on
+/// Ethereum. Either way, a confirmed payment lands in `balance_transactions` with both
+/// what the customer paid and what the merchant was settled in.
+///
+/// The provider speaks `POST {base}/orders` and `GET {base}/orders/{id}`. Solana
+/// payments, and payments already due in the settlement stablecoin, settle natively.
+pub struct CryptoConversionService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl CryptoConversionService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn get_preference(&self, api_key: &str) -> Result<CryptoSettlementPreference, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let preference = sqlx::query_as!(
+            CryptoSettlementPreference,
+            r#"SELECT * FROM crypto_settlement_preferences WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(preference.unwrap_or(CryptoSettlementPreference {
+            merchant_id,
+            settlement_asset: CryptoSettlementAsset::Native,
+            settlement_address: None,
+            payment_tolerance_bps: 0,
+            payment_expiry_minutes: DEFAULT_PAYMENT_EXPIRY_MINUTES,
+            created_at: None,
+            updated_at: None,
+        }))
+    }
+    
+    /// The settlement asset applies to payments created from now on, and conversions
+    /// already open keep the address they were opened with, and so does the expiry. The
+    /// tolerance applies to every payment confirmed from now on.
+    pub async fn update_preference(
+        &self,
+        request: UpdateCryptoSettlementRequest,
+        api_key: &str,
+    ) -> Result<CryptoSettlementPreference, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        if !(0..=MAX_PAYMENT_TOLERANCE_BPS).contains(&request.payment_tolerance_bps) {
+            return Err(DefiantError::ValidationError(format!(
+                "payment_tolerance_bps must be between 0 and {}",
+                MAX_PAYMENT_TOLERANCE_BPS,
+            )));
+        }
+        let payment_expiry_minutes = request.payment_expiry_minutes.unwrap_or(DEFAULT_PAYMENT_EXPIRY_MINUTES);
+        if !PAYMENT_EXPIRY_MINUTES.contains(&payment_expiry_minutes) {
+            return Err(DefiantError::ValidationError(format!(
+                "payment_expiry_minutes must be between {} and {}",
+                PAYMENT_EXPIRY_MINUTES.start(),
+                PAYMENT_EXPIRY_MINUTES.end(),
+            )));
+        }
+        
+        let settlement_address = match (request.settlement_asset, request.settlement_address) {
+            (CryptoSettlementAsset::Native, None) => None,
+            (CryptoSettlementAsset::Native, Some(_)) => {
+                return Err(DefiantError::ValidationError("settlement_address only applies to stablecoin settlement".into()));
+            }
+            (_, None) => {
+                return Err(DefiantError::ValidationError("settlement_address is required for stablecoin settlement".into()));
+            }
+            (_, Some(address)) => {
+                if self.config.crypto_conversion_api_url.is_none() {
+                    return Err(DefiantError::PaymentError("Stablecoin settlement is not available".into()));
+                }
+                Some(validate_address(Chain::Ethereum, &address)?)
+            }
+        };
+        
+        let preference = sqlx::query_as!(
+            CryptoSettlementPreference,
+            r#"
+            INSERT INTO crypto_settlement_preferences (
+                merchant_id, settlement_asset, settlement_address, payment_tolerance_bps, payment_expiry_minutes
+            )
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (merchant_id) DO UPDATE
+            SET settlement_asset = EXCLUDED.settlement_asset,
+                settlement_address = EXCLUDED.settlement_address,
+                payment_tolerance_bps = EXCLUDED.payment_tolerance_bps,
+                payment_expiry_minutes = EXCLUDED.payment_expiry_minutes
+            RETURNING *
+            "#,
+            merchant_id,
+            request.settlement_asset as CryptoSettlementAsset,
+            settlement_address,
+            request.payment_tolerance_bps,
+            payment_expiry_minutes,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Merchant {} now settles crypto payments in {:?}", merchant_id, preference.settlement_asset);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                CRYPTO_SETTLEMENT_UPDATED,
+                ConfigurationChangeData::new(
+                    "crypto_settlement",
+                    merchant_id,
+                    vec![
+                        "settlement_asset".into(),
+                        "settlement_address".into(),
+                        "payment_tolerance_bps".into(),
+                        "payment_expiry_minutes".into(),
+                    ],
+                ),
+                api_key,
+            )
+            .await;
+        
+        Ok(preference)
+    }
+    
+    /// The stablecoin preference a new payment in `chain`/`token_contract` has to be
+    /// converted for, if any.
+    pub async fn conversion_for(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        chain: Chain,
+        token_contract: Option<&str>,
+    ) -> Result<Option<CryptoSettlementPreference>, DefiantError> {
+        let preference = sqlx::query_as!(
+            CryptoSettlementPreference,
+            r#"SELECT * FROM crypto_settlement_preferences WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        Ok(preference.filter(|preference| {
+            let already_settled = matches!(
+                (preference.settlement_asset.token_contract(), token_contract),
+                (Some(stablecoin), Some(token)) if stablecoin.eq_ignore_ascii_case(token)
+            );
+            preference.settlement_asset != CryptoSettlementAsset::Native && chain != Chain::Solana && !already_settled
+        }))
+    }
+    
+    /// Opens a provider order for the payment and records its deposit address as the
+    /// address the customer pays.
+    pub async fn open_conversion(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        payment_id: Uuid,
+        chain: Chain,
+        token_contract: Option<&str>,
+        quote: &CryptoQuote,
+        preference: &CryptoSettlementPreference,
+    ) -> Result<CryptoAddress, DefiantError> {
+        let token_contract = match token_contract {
+            Some(_) if chain != Chain::Ethereum => {
+                return Err(DefiantError::ValidationError("crypto_token: tokens are only supported on Ethereum".into()));
+            }
+            Some(contract) => Some(validate_address(Chain::Ethereum, contract)
+                .map_err(|_| DefiantError::ValidationError("crypto_token: not a valid ERC-20 contract address".into()))?),
+            None => None,
+        };
+        let settlement_asset = preference.settlement_asset.symbol().ok_or(DefiantError::InternalError)?;
+        let settlement_address = preference.settlement_address.as_deref().ok_or(DefiantError::InternalError)?;
+        let deposit_asset = crypto_rate_service::asset_symbol(chain, token_contract.as_deref());
+        
+        let order: ProviderOrder = self
+            .provider(reqwest::Method::POST, "orders")?
+            .json(&OpenOrderRequest {
+                deposit_chain: chain,
+                deposit_asset: &deposit_asset,
+                deposit_amount: quote.amount.to_string(),
+                settle_chain: Chain::Ethereum,
+                settle_asset: settlement_asset,
+                settle_address: settlement_address,
+                client_reference: payment_id,
+            })
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        
+        let deposit_address = order.deposit_address
+            .ok_or_else(|| DefiantError::PaymentError("Conversion provider returned no deposit address".into()))?;
+        
+        let assigned = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            INSERT INTO crypto_addresses (
+                merchant_id, payment_id, chain, address, token_contract,
+                expected_amount, quote_currency, quote_rate, quote_expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            preference.merchant_id,
+            payment_id,
+            chain as Chain,
+            deposit_address,
+            token_contract,
+            quote.amount,
+            quote.currency,
+            quote.rate,
+            quote.expires_at,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO crypto_conversions (
+                merchant_id, payment_id, crypto_address_id, provider_reference, deposit_asset, deposit_amount,
+                settlement_asset, settlement_address, settlement_amount
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            preference.merchant_id,
+            payment_id,
+            assigned.id,
+            order.id,
+            deposit_asset,
+            quote.amount,
+            preference.settlement_asset as CryptoSettlementAsset,
+            settlement_address,
+            order.settle_amount,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        Ok(assigned)
+    }
+    
+    /// Polls the provider for conversions whose deposit has confirmed.
+    pub async fn advance_conversions(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        if self.config.crypto_conversion_api_url.is_none() {
+            return Ok(());
+        }
+        
+        // Stamp the batch so overlapping scans pick different rows
+        let due = sqlx::query_as!(
+            CryptoConversion,
+            r#"
+            UPDATE crypto_conversions SET last_checked_at = NOW()
+            WHERE id IN (
+                SELECT id FROM crypto_conversions
+                WHERE status = 'converting'
+                  AND (last_checked_at IS NULL OR last_checked_at < NOW() - make_interval(secs => $1))
+                ORDER BY last_checked_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            CONVERSION_CHECK_INTERVAL_SECS,
+            CONVERSION_BATCH_SIZE,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        for conversion in due {
+            if let Err(e) = self.advance(pool, &conversion).await {
+                warn!("Failed to check crypto conversion {}: {}", conversion.id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn advance(&self, pool: &PgPool, conversion: &CryptoConversion) -> Result<(), DefiantError> {
+        let order: ProviderOrder = self
+            .provider(reqwest::Method::GET, &format!("orders/{}", conversion.provider_reference))?
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let updated = match order.status.as_str() {
+            "settled" => {
+                let settled = sqlx::query_as!(
+                    CryptoConversion,
+                    r#"
+                    UPDATE crypto_conversions
+                    SET status = 'settled', settlement_amount = $2, settlement_tx_hash = $3, settled_at = NOW()
+                    WHERE id = $1 AND status = 'converting'
+                    RETURNING *
+                    "#,
+                    conversion.id,
+                    order.settle_amount,
+                    order.settle_tx_hash,
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+                
+                if let Some(settled) = &settled {
+                    book_settled_conversions(&mut tx, &self.db.pool, settled).await?;
+                }
+                settled
+            }
+            "failed" | "refunded" => sqlx::query_as!(
+                CryptoConversion,
+                r#"
+                UPDATE crypto_conversions SET status = 'failed', error = $2
+                WHERE id = $1 AND status = 'converting'
+                RETURNING *
+                "#,
+                conversion.id,
+                order.error.unwrap_or_else(|| format!("Provider order {}", order.status)),
+            )
+            .fetch_optional(&mut *tx)
+            .await?,
+            _ => None,
+        };
+        
+        tx.commit().await?;
+        
+        if let Some(updated) = updated {
+            let event_type = match updated.status {
+                CryptoConversionStatus::Settled => CRYPTO_CONVERSION_SETTLED,
+                _ => CRYPTO_CONVERSION_FAILED,
+            };
+            info!("Crypto conversion {} for payment {} is {:?}", updated.id, updated.payment_id, updated.status);
+            self.emit(updated.merchant_id, event_type, &updated).await;
+        }
+        
+        Ok(())
+    }
+    
+    fn provider(&self, method: reqwest::Method, path: &str) -> Result<reqwest::RequestBuilder, DefiantError> {
+        let base_url = self.config.crypto_conversion_api_url.as_deref()
+            .ok_or_else(|| DefiantError::PaymentError("Stablecoin settlement is not available".into()))?;
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(PROVIDER_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let request = client.request(method, format!("{}/{}", base_url.trim_end_matches('/'), path));
+        Ok(match &self.config.crypto_conversion_api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        })
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, conversion: &CryptoConversion) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(conversion))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Hands a deposit that is final (paid in full, or short when its quote lapsed) on to
+/// the provider. Returns whether the address belongs to a conversion.
+pub async fn deposit_confirmed(
+    tx: &mut Transaction<'_, Postgres>,
+    address: &CryptoAddress,
+) -> Result<bool, DefiantError> {
+    let conversion = sqlx::query_scalar!(
+        r#"SELECT id FROM crypto_conversions WHERE crypto_address_id = $1 FOR UPDATE"#,
+        address.id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    if let Some(conversion_id) = conversion {
+        // The ledger entry waits for the provider to report what it sent
+        sqlx::query!(
+            r#"
+            UPDATE crypto_conversions SET status = 'converting', deposit_amount = $2
+            WHERE id = $1 AND status = 'awaiting_deposit'
+            "#,
+            conversion_id,
+            address.confirmed_amount,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    
+    Ok(conversion.is_some())
+}
+
+/// Books a payment whose last deposit just confirmed: conversions move on to the
+/// provider and are booked once it settles them all, anything else was settled natively
+/// into the merchant's wallet and is recorded now, with what every address of the
+/// payment collected.
+pub async fn settle_confirmed(
+    tx: &mut Transaction<'_, Postgres>,
+    directory: &PgPool,
+    address: &CryptoAddress,
+    payment: &Payment,
+) -> Result<(), DefiantError> {
+    if deposit_confirmed(tx, address).await? {
+        return Ok(());
+    }
+    
+    let collected = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(confirmed_amount), 0) AS "collected!" FROM crypto_addresses
+        WHERE payment_id = $1 AND status IN ('confirmed', 'underpaid')
+        "#,
+        payment.id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let asset = crypto_rate_service::asset_symbol(address.chain, address.token_contract.as_deref());
+    let ledger = LedgerAmounts {
+        crypto_asset: &asset,
+        crypto_amount: collected,
+        settlement_asset: &asset,
+        settlement_amount: collected,
+    };
+    book_crypto_payment(tx, directory, address.merchant_id, payment, &ledger, None).await
+}
+
+/// Books a converted payment once it has succeeded and the provider has settled every
+/// deposit made towards it. Conversions that failed are left out of the entry.
+async fn book_settled_conversions(
+    tx: &mut Transaction<'_, Postgres>,
+    directory: &PgPool,
+    settled: &CryptoConversion,
+) -> Result<(), DefiantError> {
+    let payment = sqlx::query_as!(
+        Payment,
+        r#"SELECT * FROM payments WHERE id = $1 AND status = 'succeeded'"#,
+        settled.payment_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    let payment = match payment {
+        Some(payment) => payment,
+        None => return Ok(()),
+    };
+    
+    let totals = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE status IN ('awaiting_deposit', 'converting')) AS "in_flight!",
+            COALESCE(SUM(deposit_amount) FILTER (WHERE status = 'settled'), 0) AS "deposit_amount!",
+            COALESCE(SUM(settlement_amount) FILTER (WHERE status = 'settled'), 0) AS "settlement_amount!"
+        FROM crypto_conversions
+        WHERE payment_id = $1
+        "#,
+        payment.id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    if totals.in_flight > 0 {
+        return Ok(());
+    }
+    
+    let settlement_asset = settled.settlement_asset.symbol().unwrap_or_default();
+    let ledger = LedgerAmounts {
+        crypto_asset: &settled.deposit_asset,
+        crypto_amount: totals.deposit_amount,
+        settlement_asset,
+        settlement_amount: totals.settlement_amount,
+    };
+    book_crypto_payment(tx, directory, settled.merchant_id, &payment, &ledger, Some(settled.id)).await
+}
+
+/// Both sides of a crypto payment, in base units of each asset.
+struct LedgerAmounts<'a> {
+    crypto_asset: &'a str,
+    crypto_amount: Decimal,
+    settlement_asset: &'a str,
+    settlement_amount: Decimal,
+}
+
+async fn book_crypto_payment(
+    tx: &mut Transaction<'_, Postgres>,
+    directory: &PgPool,
+    merchant_id: Uuid,
+    payment: &Payment,
+    ledger: &LedgerAmounts<'_>,
+    conversion_id: Option<Uuid>,
+) -> Result<(), DefiantError> {
+    // Crypto settles on chain to the merchant, never into the payout balance, so the
+    // entry is never made available
+    sqlx::query!(
+        r#"
+        INSERT INTO balance_transactions (
+            merchant_id, customer_id, amount, currency, net, type, description, metadata,
+            crypto_asset, crypto_amount, settlement_asset, settlement_amount
+        )
+        VALUES ($1, $2, $3, $4, $3, 'crypto_payment', $5, $6, $7, $8, $9, $10)
+        "#,
+        merchant_id,
+        payment.customer_id,
+        payment.amount,
+        payment.currency,
+        payment.description,
+        serde_json::json!({ "payment_id": payment.id, "crypto_conversion_id": conversion_id }),
+        ledger.crypto_asset,
+        ledger.crypto_amount,
+        ledger.settlement_asset,
+        ledger.settlement_amount,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    ledger::record_crypto_payment(tx, directory, payment, conversion_id).await
+}
+
+fn provider_error(e: reqwest::Error) -> DefiantError {
+    DefiantError::PaymentError(format!("Conversion provider request failed: {}", e))
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use tracing::warn;
+
+use crate::{models::{Chain, CryptoFeeEstimates, CryptoFeeLevel}, errors::DefiantError, config::Config};
+use super::crypto_signer::{fee_rate_for_target, CryptoSigner};
+
+/// Fees move block by block; a minute old is still accurate enough to quote.
+const FEE_CACHE_SECS: u64 = 60;
+/// Confirmation targets, in blocks, for fast, medium and slow Bitcoin fees.
+const BITCOIN_TARGETS: [u32; 3] = [1, 6, 144];
+/// Native segwit payment with one input, and outputs to the payee and for change.
+const BITCOIN_PAYMENT_VBYTES: u64 = 11 + 68 + 2 * 31;
+/// Priority fee percentiles, for fast, medium and slow, that recent blocks paid.
+const ETHEREUM_PERCENTILES: [f64; 3] = [90.0, 50.0, 10.0];
+/// Gas of a plain ether transfer.
+const ETHER_TRANSFER_GAS: u64 = 21_000;
+const SOLANA_PERCENTILES: [usize; 3] = [75, 50, 25];
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+/// Compute units a transaction is charged priority fees on when it sets no limit.
+const SOLANA_DEFAULT_COMPUTE_UNITS: u64 = 200_000;
+
+/// Network fee estimates for checkout pages, read from the same nodes the watcher uses
+/// and shared between callers through Redis.
+pub struct CryptoFeeService {
+    redis: Arc<ConnectionManager>,
+    config: Arc<Config>,
+}
+
+impl CryptoFeeService {
+    pub fn new(redis: Arc<ConnectionManager>, config: Arc<Config>) -> Self {
+        Self { redis, config }
+    }
+    
+    /// Fast, medium and slow fees for paying in `currency` (btc, eth or sol). A cache
+    /// Redis cannot serve falls back to asking the node.
+    pub async fn fee_estimates(&self, currency: &str) -> Result<CryptoFeeEstimates, DefiantError> {
+        let currency = currency.to_lowercase();
+        let chain = match currency.as_str() {
+            "btc" => Chain::Bitcoin,
+            "eth" => Chain::Ethereum,
+            "sol" => Chain::Solana,
+            _ => return Err(DefiantError::ValidationError("currency must be one of btc, eth or sol".into())),
+        };
+        
+        let cache_key = format!("crypto_fee_estimates:{}", currency);
+        match redis::cmd("GET")
+            .arg(&cache_key)
+            .query_async::<_, Option<String>>(&mut self.redis.as_ref().clone())
+            .await
+        {
+            Ok(Some(cached)) => {
+                if let Ok(estimates) = serde_json::from_str(&cached) {
+                    return Ok(estimates);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached {} fee estimates: {}", currency, e),
+        }
+        
+        let signer = CryptoSigner::new(&self.config)?;
+        let [fast, medium, slow] = match chain {
+            Chain::Bitcoin => bitcoin_levels(&signer).await?,
+            Chain::Ethereum => ethereum_levels(&signer).await?,
+            Chain::Solana => solana_levels(&signer).await?,
+        };
+        let estimates = CryptoFeeEstimates {
+            currency,
+            chain,
+            fast,
+            medium,
+            slow,
+            fetched_at: Utc::now(),
+        };
+        
+        if let Err(e) = redis::cmd("SET")
+            .arg(&cache_key)
+            .arg(serde_json::to_string(&estimates).map_err(|_| DefiantError::InternalError)?)
+            .arg("EX")
+            .arg(FEE_CACHE_SECS)
+            .query_async::<_, ()>(&mut self.redis.as_ref().clone())
+            .await
+        {
+            warn!("Failed to cache {} fee estimates: {}", estimates.currency, e);
+        }
+        
+        Ok(estimates)
+    }
+}
+
+async fn bitcoin_levels(signer: &CryptoSigner<'_>) -> Result<[CryptoFeeLevel; 3], DefiantError> {
+    let estimates = signer.bitcoin_fee_estimates().await?;
+    
+    let mut levels = Vec::with_capacity(BITCOIN_TARGETS.len());
+    for target in BITCOIN_TARGETS {
+        let rate = decimal(fee_rate_for_target(&estimates, target)?)?.round_dp(3);
+        levels.push(CryptoFeeLevel {
+            rate,
+            network_fee: (rate * Decimal::from(BITCOIN_PAYMENT_VBYTES)).ceil(),
+        });
+    }
+    into_levels(levels)
+}
+
+/// What a transaction pays per gas is the next block's base fee plus its tip.
+async fn ethereum_levels(signer: &CryptoSigner<'_>) -> Result<[CryptoFeeLevel; 3], DefiantError> {
+    let (base_fee, tips) = signer.ethereum_fee_history(&ETHEREUM_PERCENTILES).await?;
+    
+    let mut levels = Vec::with_capacity(tips.len());
+    for tip in tips {
+        let rate = Decimal::from_u128(base_fee.saturating_add(tip))
+            .ok_or_else(|| DefiantError::PaymentError("Gas price exceeds supported precision".into()))?;
+        levels.push(CryptoFeeLevel {
+            rate,
+            network_fee: rate * Decimal::from(ETHER_TRANSFER_GAS),
+        });
+    }
+    into_levels(levels)
+}
+
+/// Every signature costs the same; only the priority fee on top varies.
+async fn solana_levels(signer: &CryptoSigner<'_>) -> Result<[CryptoFeeLevel; 3], DefiantError> {
+    let fees = signer.solana_priority_fees().await?;
+    
+    let levels = SOLANA_PERCENTILES
+        .iter()
+        .map(|percentile| {
+            let rate = match fees.len() {
+                0 => 0,
+                len => fees[(len - 1) * percentile / 100],
+            };
+            let priority_lamports = (u128::from(rate) * u128::from(SOLANA_DEFAULT_COMPUTE_UNITS)).div_ceil(1_000_000);
+            CryptoFeeLevel {
+                rate: Decimal::from(rate),
+                network_fee: Decimal::from(LAMPORTS_PER_SIGNATURE) + Decimal::from_u128(priority_lamports).unwrap_or(Decimal::MAX),
+            }
+        })
+        .collect();
+    into_levels(levels)
+}
+
+fn into_levels(levels: Vec<CryptoFeeLevel>) -> Result<[CryptoFeeLevel; 3], DefiantError> {
+    levels
+        .try_into()
+        .map_err(|_| DefiantError::PaymentError("Node returned incomplete fee estimates".into()))
+}
+
+fn decimal(value: f64) -> Result<Decimal, DefiantError> {
+    Decimal::from_f64(value)
+        .ok_or_else(|| DefiantError::PaymentError("Unreadable fee estimate".into()))
+}
@@ -0,0 +1,484 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{Chain, CryptoAddress, CryptoWallet, CryptoPayout, CryptoPayoutStatus, CryptoPayoutListQuery, CryptoPayoutListResponse, CRYPTO_PAYOUT_CREATED, CRYPTO_PAYOUT_CONFIRMED, CRYPTO_PAYOUT_FAILED}, errors::DefiantError, db::Database, config::Config, plugins::{self, PayoutAttempt}};
+use crate::shutdown::Shutdown;
+use super::{crypto_signer::{ChainProgress, CryptoSigner}, webhook_service::WebhookService};
+
+const SWEEP_INTERVAL_SECS: u64 = 3600;
+const PAYOUT_CHECK_INTERVAL_SECS: f64 = 60.0;
+const PAYOUT_BATCH_SIZE: i64 = 50;
+/// Inputs per Bitcoin sweep, well under standard transaction size limits.
+const MAX_BITCOIN_INPUTS: i64 = 200;
+/// Blocks the fee is estimated to confirm within; sweeps are not urgent.
+const BITCOIN_FEE_TARGET_BLOCKS: u32 = 12;
+/// A sweep waits for more funds, or cheaper fees, while the fee would cost more than
+/// this share (in basis points) of what it consolidates.
+const MAX_FEE_BPS: i64 = 200;
+/// Gas of a plain ether transfer.
+const ETHER_TRANSFER_GAS: u64 = 21_000;
+
+#[derive(Debug, Serialize)]
+struct SweepInput<'a> {
+    address: &'a str,
+    derivation_path: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+struct SignSweepRequest<'a> {
+    client_reference: Uuid,
+    merchant_id: Uuid,
+    chain: Chain,
+    inputs: Vec<SweepInput<'a>>,
+    to: &'a str,
+    /// Sat per vbyte, or wei per gas
+    fee_rate: String,
+}
+
+/// Sweeps of confirmed crypto payments into the merchant's cold or settlement wallet.
+///
+/// HD payment addresses are single-use, so funds end up spread over many of them. On a
+/// schedule, every wallet with a sweep address has its confirmed, unswept addresses
+/// batched into one `crypto_payouts` row: all of them in a single transaction on
+/// Bitcoin, one address per transaction on Ethereum, where an account can only spend
+/// its own balance. The fee is estimated up front and a batch is held back while it
+/// would cost more than `MAX_FEE_BPS` of what it moves. Token balances are left in
+/// place, as sweeping them needs ether at every address to pay for gas.
+///
+/// The signer at `crypto_signer_url` must hold the wallet's keys; it spends everything
+/// at the listed addresses, less the fee. Sweeps are then broadcast and tracked like
+/// refunds, and a failed one releases its addresses to the next batch. Funds never
+/// leave the merchant's own wallets, so sweeps are not booked to the ledger.
+pub struct CryptoPayoutService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl CryptoPayoutService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn list_crypto_payouts(
+        &self,
+        query: CryptoPayoutListQuery,
+        api_key: &str,
+    ) -> Result<CryptoPayoutListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            CryptoPayout,
+            r#"
+            SELECT * FROM crypto_payouts
+            WHERE merchant_id = $1
+            AND ($2::UUID IS NULL OR wallet_id = $2)
+            AND ($3::crypto_payout_status IS NULL OR status = $3)
+            ORDER BY created_at DESC
+            LIMIT $4
+            "#,
+            merchant_id,
+            query.wallet_id,
+            query.status as Option<CryptoPayoutStatus>,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(CryptoPayoutListResponse {
+            data,
+            has_more,
+            url: "/api/v1/crypto_payouts".into(),
+        })
+    }
+    
+    pub async fn get_crypto_payout(&self, payout_id: Uuid, api_key: &str) -> Result<CryptoPayout, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let payout = sqlx::query_as!(
+            CryptoPayout,
+            r#"SELECT * FROM crypto_payouts WHERE id = $1 AND merchant_id = $2"#,
+            payout_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Crypto payout not found".into()))?;
+        
+        Ok(payout)
+    }
+    
+    /// Batches every sweepable wallet's unswept addresses into new payouts.
+    pub async fn sweep_due(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let wallets = sqlx::query_as!(
+            CryptoWallet,
+            r#"
+            SELECT * FROM merchant_crypto_wallets w
+            WHERE sweep_address IS NOT NULL
+            AND EXISTS (
+                SELECT 1 FROM crypto_addresses a
+                WHERE a.wallet_id = w.id
+                  AND a.status IN ('confirmed', 'underpaid')
+                  AND a.crypto_payout_id IS NULL
+                  AND a.token_contract IS NULL
+            )
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let signer = CryptoSigner::new(&self.config)?;
+        
+        for wallet in wallets {
+            if !CryptoSigner::available(&self.config, wallet.chain) {
+                continue;
+            }
+            if let Err(e) = self.sweep_wallet(&signer, pool, &wallet).await {
+                warn!("Failed to sweep crypto wallet {}: {}", wallet.id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn sweep_wallet(&self, signer: &CryptoSigner<'_>, pool: &PgPool, wallet: &CryptoWallet) -> Result<(), DefiantError> {
+        let destination = match wallet.sweep_address.as_deref() {
+            Some(destination) => destination,
+            None => return Ok(()),
+        };
+        
+        // An Ethereum address pays its own fee, so one too small to be worth it is
+        // simply skipped; a Bitcoin batch shares the fee and is judged as a whole
+        let (fee_rate, batch_size, minimum) = match wallet.chain {
+            Chain::Bitcoin => (decimal(signer.bitcoin_fee_rate(BITCOIN_FEE_TARGET_BLOCKS).await?)?, MAX_BITCOIN_INPUTS, Decimal::ZERO),
+            Chain::Ethereum => {
+                let fee_rate = Decimal::from_u128(signer.ethereum_gas_price().await?)
+                    .ok_or_else(|| DefiantError::PaymentError("Gas price exceeds supported precision".into()))?;
+                let minimum = estimate_fee(Chain::Ethereum, fee_rate, 1) * Decimal::from(10_000) / Decimal::from(MAX_FEE_BPS);
+                (fee_rate, 1, minimum)
+            }
+            Chain::Solana => return Ok(()),
+        };
+        
+        loop {
+            let mut tx = pool.begin().await?;
+            
+            let addresses = sqlx::query_as!(
+                CryptoAddress,
+                r#"
+                SELECT * FROM crypto_addresses
+                WHERE wallet_id = $1
+                  AND status IN ('confirmed', 'underpaid')
+                  AND crypto_payout_id IS NULL
+                  AND token_contract IS NULL
+                  AND confirmed_amount > 0
+                  AND confirmed_amount >= $3
+                ORDER BY confirmed_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+                "#,
+                wallet.id,
+                batch_size,
+                minimum,
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            
+            if addresses.is_empty() {
+                return Ok(());
+            }
+            
+            let amount: Decimal = addresses.iter().map(|address| address.confirmed_amount).sum();
+            let fee = estimate_fee(wallet.chain, fee_rate, addresses.len());
+            if fee * Decimal::from(10_000) > amount * Decimal::from(MAX_FEE_BPS) {
+                return Ok(());
+            }
+            
+            let attempt = PayoutAttempt::CryptoSweep {
+                merchant_id: wallet.merchant_id,
+                chain: wallet.chain,
+                destination,
+                amount,
+            };
+            if let Err(e) = plugins::hooks().pre_payout(&attempt).await {
+                // Held back until a plugin lets it through
+                warn!("Sweep of crypto wallet {} not created: {}", wallet.id, e);
+                return Ok(());
+            }
+            
+            let payout = sqlx::query_as!(
+                CryptoPayout,
+                r#"
+                INSERT INTO crypto_payouts (
+                    merchant_id, wallet_id, chain, destination, address_count, amount, fee, fee_rate
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING *
+                "#,
+                wallet.merchant_id,
+                wallet.id,
+                wallet.chain as Chain,
+                destination,
+                addresses.len() as i32,
+                amount,
+                fee,
+                fee_rate,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            
+            let ids: Vec<Uuid> = addresses.iter().map(|address| address.id).collect();
+            sqlx::query!(
+                r#"UPDATE crypto_addresses SET crypto_payout_id = $1 WHERE id = ANY($2)"#,
+                payout.id,
+                &ids,
+            )
+            .execute(&mut *tx)
+            .await?;
+            
+            tx.commit().await?;
+            
+            info!("Crypto payout {} sweeps {} addresses of wallet {}", payout.id, payout.address_count, wallet.id);
+            self.emit(payout.merchant_id, CRYPTO_PAYOUT_CREATED, &payout).await;
+            
+            // A short batch took everything there was
+            if (addresses.len() as i64) < batch_size {
+                return Ok(());
+            }
+        }
+    }
+    
+    /// Signs and broadcasts pending sweeps, and tracks broadcast ones until they are
+    /// deep enough to count.
+    pub async fn advance_payouts(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        if self.config.crypto_signer_url.is_none() {
+            return Ok(());
+        }
+        
+        // Stamp the batch so overlapping scans pick different rows
+        let due = sqlx::query_as!(
+            CryptoPayout,
+            r#"
+            UPDATE crypto_payouts SET last_checked_at = NOW()
+            WHERE id IN (
+                SELECT id FROM crypto_payouts
+                WHERE status IN ('pending', 'broadcast')
+                  AND (last_checked_at IS NULL OR last_checked_at < NOW() - make_interval(secs => $1))
+                ORDER BY last_checked_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            PAYOUT_CHECK_INTERVAL_SECS,
+            PAYOUT_BATCH_SIZE,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        if due.is_empty() {
+            return Ok(());
+        }
+        
+        let signer = CryptoSigner::new(&self.config)?;
+        
+        for payout in due {
+            if !CryptoSigner::available(&self.config, payout.chain) {
+                continue;
+            }
+            let result = match (payout.status, payout.tx_hash.as_deref()) {
+                (CryptoPayoutStatus::Broadcast, Some(tx_hash)) => self.track(&signer, pool, &payout, tx_hash).await,
+                _ => self.broadcast(&signer, pool, &payout).await,
+            };
+            if let Err(e) = result {
+                warn!("Failed to advance crypto payout {}: {}", payout.id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn broadcast(&self, signer: &CryptoSigner<'_>, pool: &PgPool, payout: &CryptoPayout) -> Result<(), DefiantError> {
+        let addresses = sqlx::query_as!(
+            CryptoAddress,
+            r#"SELECT * FROM crypto_addresses WHERE crypto_payout_id = $1 ORDER BY confirmed_at"#,
+            payout.id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let request = SignSweepRequest {
+            client_reference: payout.id,
+            merchant_id: payout.merchant_id,
+            chain: payout.chain,
+            inputs: addresses
+                .iter()
+                .map(|address| SweepInput {
+                    address: &address.address,
+                    derivation_path: address.derivation_path.as_deref(),
+                })
+                .collect(),
+            to: &payout.destination,
+            fee_rate: payout.fee_rate.normalize().to_string(),
+        };
+        let signed = match signer.sign("sweeps", &request).await {
+            Ok(signed) => signed,
+            Err(DefiantError::ValidationError(reason)) => return self.fail(pool, payout, &reason).await,
+            Err(e) => return Err(e),
+        };
+        
+        let tx_hash = signer.broadcast(payout.chain, signed.raw_transaction).await?;
+        
+        sqlx::query!(
+            r#"
+            UPDATE crypto_payouts SET status = 'broadcast', tx_hash = $1, broadcast_at = NOW()
+            WHERE id = $2 AND status = 'pending'
+            "#,
+            tx_hash,
+            payout.id,
+        )
+        .execute(pool)
+        .await?;
+        
+        info!("Crypto payout {} broadcast as {}", payout.id, tx_hash);
+        
+        Ok(())
+    }
+    
+    async fn track(
+        &self,
+        signer: &CryptoSigner<'_>,
+        pool: &PgPool,
+        payout: &CryptoPayout,
+        tx_hash: &str,
+    ) -> Result<(), DefiantError> {
+        let depth = match signer.progress(payout.chain, tx_hash).await? {
+            ChainProgress::Pending => return Ok(()),
+            ChainProgress::Reverted => return self.fail(pool, payout, "Sweep transaction failed on chain").await,
+            ChainProgress::Confirmations(depth) => {
+                sqlx::query!(
+                    r#"UPDATE crypto_payouts SET confirmations = $1 WHERE id = $2 AND status = 'broadcast'"#,
+                    depth,
+                    payout.id,
+                )
+                .execute(pool)
+                .await?;
+                return Ok(());
+            }
+            ChainProgress::Final(depth) => depth,
+        };
+        
+        let updated = sqlx::query_as!(
+            CryptoPayout,
+            r#"
+            UPDATE crypto_payouts SET status = 'confirmed', confirmations = $1, confirmed_at = NOW()
+            WHERE id = $2 AND status = 'broadcast'
+            RETURNING *
+            "#,
+            depth,
+            payout.id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        if let Some(updated) = updated {
+            info!("Crypto payout {} confirmed", updated.id);
+            self.emit(updated.merchant_id, CRYPTO_PAYOUT_CONFIRMED, &updated).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Gives up on a sweep and releases its addresses to the next batch.
+    async fn fail(&self, pool: &PgPool, payout: &CryptoPayout, reason: &str) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        let updated = sqlx::query_as!(
+            CryptoPayout,
+            r#"
+            UPDATE crypto_payouts SET status = 'failed', error = $1
+            WHERE id = $2 AND status IN ('pending', 'broadcast')
+            RETURNING *
+            "#,
+            reason,
+            payout.id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(()),
+        };
+        
+        sqlx::query!(
+            r#"UPDATE crypto_addresses SET crypto_payout_id = NULL WHERE crypto_payout_id = $1"#,
+            updated.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        warn!("Crypto payout {} failed: {}", updated.id, reason);
+        self.emit(updated.merchant_id, CRYPTO_PAYOUT_FAILED, &updated).await;
+        
+        Ok(())
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, payout: &CryptoPayout) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(payout))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Periodically batches confirmed payment addresses into sweeps. Broadcasting and
+/// confirming them happens in the crypto watcher's scans.
+pub fn spawn_sweep_scheduler(db: Arc<Database>, config: Arc<Config>, shutdown: &Shutdown) {
+    if config.crypto_signer_url.is_none() {
+        return;
+    }
+    
+    shutdown.spawn(|mut shutdown| async move {
+        let service = CryptoPayoutService::new(db.clone(), config);
+        let mut interval = tokio::time::interval(StdDuration::from_secs(SWEEP_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            for pool in db.pools() {
+                if let Err(e) = service.sweep_due(pool).await {
+                    error!("Crypto sweep failed: {}", e);
+                }
+            }
+        }
+    });
+}
+
+/// Bitcoin sweeps are native segwit: roughly 68 vbytes per input and 31 for the single
+/// output, on top of the fixed overhead.
+fn estimate_fee(chain: Chain, fee_rate: Decimal, inputs: usize) -> Decimal {
+    match chain {
+        Chain::Bitcoin => (fee_rate * Decimal::from(11 + 68 * inputs + 31)).ceil(),
+        _ => fee_rate * Decimal::from(ETHER_TRANSFER_GAS) * Decimal::from(inputs as u64),
+    }
+}
+
+fn decimal(value: f64) -> Result<Decimal, DefiantError> {
+    Decimal::from_f64(value)
+        .ok_or_else(|| DefiantError::PaymentError("Unreadable fee estimate".into()))
+}
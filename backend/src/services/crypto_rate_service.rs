@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use chrono::{Duration, Utc};
+use rust_decimal::{Decimal, RoundingStrategy};
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{models::{Chain, CryptoQuote}, errors::DefiantError, config::Config};
+use super::fx_service;
+
+/// How long a quoted crypto amount is honored.
+pub const QUOTE_LOCK_MINUTES: i64 = 15;
+const PROVIDER_TIMEOUT_SECS: u64 = 10;
+
+/// ERC-20 tokens we can price: contract (lowercase), symbol, decimals.
+const KNOWN_TOKENS: &[(&str, &str, u32)] = &[
+    ("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48", "USDC", 6),
+    ("0xdac17f958d2ee523a2206206994597c13d831ec7", "USDT", 6),
+    ("0x6b175474e89094c44da98b954eedeac495271d0f", "DAI", 18),
+];
+
+#[derive(Debug, Deserialize)]
+struct SpotPriceResponse {
+    data: SpotPrice,
+}
+
+#[derive(Debug, Deserialize)]
+struct SpotPrice {
+    #[serde(with = "rust_decimal::serde::str")]
+    amount: Decimal,
+}
+
+/// Converts fiat amounts into on-chain amounts at the current spot rate.
+///
+/// Rates come from a Coinbase-style `GET {base}/prices/{ASSET}-{FIAT}/spot` endpoint.
+/// Quotes round up to the next base unit so the merchant never receives less than the
+/// fiat amount at the locked rate.
+pub struct CryptoRateService {
+    config: Arc<Config>,
+}
+
+impl CryptoRateService {
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+    
+    /// Locked quote for `amount` minor units of `currency`, payable in the chain's native
+    /// coin or the given ERC-20 token.
+    pub async fn quote(
+        &self,
+        chain: Chain,
+        token_contract: Option<&str>,
+        amount: i64,
+        currency: &str,
+    ) -> Result<CryptoQuote, DefiantError> {
+        let exponent = fx_service::currency_exponent(currency)
+            .ok_or_else(|| DefiantError::ValidationError(format!("Unsupported currency: {}", currency)))?;
+        let (symbol, decimals) = asset(chain, token_contract)?;
+        let rate = self.spot_rate(symbol, currency).await?;
+        
+        if rate <= Decimal::ZERO {
+            return Err(DefiantError::PaymentError(format!("No usable {}-{} rate", symbol, currency)));
+        }
+        
+        let coins = Decimal::new(amount, exponent) / rate;
+        let base_units = (coins * Decimal::from(10u64.pow(decimals)))
+            .round_dp_with_strategy(0, RoundingStrategy::AwayFromZero);
+        
+        info!("Quoted {} {} as {} {} base units at {}", amount, currency, base_units, symbol, rate);
+        
+        Ok(CryptoQuote {
+            amount: base_units,
+            currency: currency.to_uppercase(),
+            rate: Some(rate),
+            expires_at: Utc::now() + Duration::minutes(QUOTE_LOCK_MINUTES),
+        })
+    }
+    
+    async fn spot_rate(&self, symbol: &str, currency: &str) -> Result<Decimal, DefiantError> {
+        let base_url = self.config.crypto_rate_api_url.as_deref().ok_or_else(|| {
+            DefiantError::PaymentError("Crypto rates are not configured; pass crypto_amount instead".into())
+        })?;
+        
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(PROVIDER_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let price: SpotPriceResponse = client
+            .get(format!("{}/prices/{}-{}/spot", base_url.trim_end_matches('/'), symbol, currency.to_uppercase()))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| DefiantError::PaymentError(format!("Rate provider request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| DefiantError::PaymentError(format!("Rate provider returned an unreadable price: {}", e)))?;
+        
+        Ok(price.data.amount)
+    }
+}
+
+/// Name of what is being collected, for the ledger: its ticker, or the contract address
+/// of a token we cannot name.
+pub fn asset_symbol(chain: Chain, token_contract: Option<&str>) -> String {
+    match asset(chain, token_contract) {
+        Ok((symbol, _)) => symbol.to_string(),
+        Err(_) => token_contract.unwrap_or_default().to_lowercase(),
+    }
+}
+
+/// Ticker and base-unit decimals of what is being collected.
+fn asset(chain: Chain, token_contract: Option<&str>) -> Result<(&'static str, u32), DefiantError> {
+    match (chain, token_contract) {
+        (Chain::Bitcoin, None) => Ok(("BTC", 8)),
+        (Chain::Ethereum, None) => Ok(("ETH", 18)),
+        (Chain::Solana, None) => Ok(("SOL", 9)),
+        (Chain::Ethereum, Some(contract)) => KNOWN_TOKENS
+            .iter()
+            .find(|(address, _, _)| address.eq_ignore_ascii_case(contract))
+            .map(|(_, symbol, decimals)| (*symbol, *decimals))
+            .ok_or_else(|| DefiantError::ValidationError("crypto_amount is required for tokens we cannot price".into())),
+        (_, Some(_)) => Err(DefiantError::ValidationError("crypto_token: tokens are only supported on Ethereum".into())),
+    }
+}
@@ -0,0 +1,482 @@
+use std::sync::Arc;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{Chain, CryptoAddress, CryptoRefund, CryptoRefundStatus, CreateCryptoRefundRequest, RefundAddressResponse, Payment, PaymentMethod, PaymentStatus, PAYMENT_REFUNDED, CRYPTO_REFUND_CREATED, CRYPTO_REFUND_BROADCAST, CRYPTO_REFUND_CONFIRMED, CRYPTO_REFUND_FAILED}, errors::DefiantError, db::Database, config::Config, plugins::{self, RefundNotice}};
+use super::{crypto_rate_service, ledger, crypto_service::validate_address, crypto_signer::{ChainProgress, CryptoSigner}, payment_service::hash_client_secret, webhook_service::WebhookService};
+
+const REFUND_CHECK_INTERVAL_SECS: f64 = 30.0;
+const REFUND_BATCH_SIZE: i64 = 50;
+
+#[derive(Debug, Serialize)]
+struct SignTransactionRequest<'a> {
+    client_reference: Uuid,
+    merchant_id: Uuid,
+    chain: Chain,
+    token_contract: Option<&'a str>,
+    to: &'a str,
+    amount: String,
+}
+
+/// On-chain refunds of crypto payments.
+///
+/// A refund is sent back in the asset the customer paid, to an address the merchant
+/// supplies or the one the customer registered with the payment's client secret. Its
+/// crypto amount is the refund's share of what the payment collected, so the customer
+/// gets back what they paid at the payment's rate, not today's. The amount is reserved
+/// against the payment as soon as the refund is created and released if it fails.
+///
+/// Deployments hold no private keys: a signing service at `crypto_signer_url` builds
+/// and signs each transaction (idempotently per refund, via `client_reference`), and
+/// the watcher broadcasts it through the chain's node and tracks its confirmations like
+/// an inbound payment. A confirmed refund is booked against the merchant's ledger.
+pub struct CryptoRefundService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl CryptoRefundService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn refund_crypto_payment(
+        &self,
+        payment_id: Uuid,
+        request: CreateCryptoRefundRequest,
+        api_key: &str,
+    ) -> Result<CryptoRefund, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"SELECT * FROM payments WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            payment_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment not found".into()))?;
+        
+        if !matches!(payment.payment_method, PaymentMethod::Crypto) {
+            return Err(DefiantError::ValidationError("Only crypto payments can be refunded on chain".into()));
+        }
+        if !matches!(payment.status, PaymentStatus::Succeeded | PaymentStatus::PartiallyRefunded) {
+            return Err(DefiantError::Conflict(format!("Payment is {:?} and cannot be refunded", payment.status)));
+        }
+        
+        let refundable = payment.amount - payment.refunded_amount;
+        let amount = request.amount.unwrap_or(refundable);
+        if amount <= 0 || amount > refundable {
+            return Err(DefiantError::ValidationError(format!("amount: at most {} can be refunded", refundable)));
+        }
+        
+        // Every address of a payment is in the asset it was quoted in
+        let deposit = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            SELECT * FROM crypto_addresses
+            WHERE payment_id = $1 AND status IN ('confirmed', 'underpaid')
+            ORDER BY created_at
+            LIMIT 1
+            "#,
+            payment.id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Payment has no confirmed crypto to refund".into()))?;
+        
+        // What went towards the payment on chain; overpayments were credited separately
+        let collected = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(LEAST(confirmed_amount, expected_amount)), 0) AS "collected!"
+            FROM crypto_addresses
+            WHERE payment_id = $1 AND status IN ('confirmed', 'underpaid')
+            "#,
+            payment.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        self.ensure_available(deposit.chain)?;
+        
+        let address = match request.address.as_deref().or(payment.crypto_refund_address.as_deref()) {
+            Some(address) => validate_address(deposit.chain, address)?,
+            None => return Err(DefiantError::ValidationError(
+                "address: required when the customer has not registered a refund address".into(),
+            )),
+        };
+        
+        let crypto_amount = (collected * Decimal::from(amount) / Decimal::from(payment.amount)).floor();
+        if crypto_amount.is_zero() {
+            return Err(DefiantError::ValidationError("amount: too small to send on chain".into()));
+        }
+        
+        let refund = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            INSERT INTO crypto_refunds (
+                merchant_id, payment_id, chain, token_contract, address, amount, currency,
+                crypto_amount, reason
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            merchant_id,
+            payment.id,
+            deposit.chain as Chain,
+            deposit.token_contract,
+            address,
+            amount,
+            payment.currency,
+            crypto_amount,
+            request.reason,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            UPDATE payments
+            SET refunded_amount = refunded_amount + $1,
+                status = CASE WHEN refunded_amount + $1 >= amount THEN 'refunded'::payment_status
+                              ELSE 'partially_refunded'::payment_status END,
+                refund_reason = COALESCE($2, refund_reason),
+                updated_at = NOW()
+            WHERE id = $3
+            RETURNING *
+            "#,
+            amount,
+            request.reason,
+            payment.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Crypto refund {} created for payment {}", refund.id, payment.id);
+        self.emit(merchant_id, CRYPTO_REFUND_CREATED, serde_json::json!(refund)).await;
+        self.emit(merchant_id, PAYMENT_REFUNDED, serde_json::json!(payment)).await;
+        
+        plugins::hooks()
+            .post_refund(&RefundNotice { merchant_id, payment: &payment, amount, reason: request.reason.as_deref() })
+            .await;
+        
+        Ok(refund)
+    }
+    
+    /// Lets the customer say where refunds should go, authenticated by the payment's
+    /// client secret rather than an API key.
+    pub async fn register_refund_address(
+        &self,
+        payment_id: Uuid,
+        client_secret: &str,
+        address: &str,
+    ) -> Result<RefundAddressResponse, DefiantError> {
+        let not_found = || DefiantError::NotFound("Payment not found".into());
+        let pool = self.db.pool_for_row("payments", payment_id).await?.ok_or_else(not_found)?;
+        
+        // A wrong secret looks exactly like a missing payment
+        let deposit = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            SELECT a.* FROM crypto_addresses a
+            JOIN payments p ON p.id = a.payment_id
+            WHERE p.id = $1 AND p.client_secret_hash = $2
+            ORDER BY a.created_at
+            LIMIT 1
+            "#,
+            payment_id,
+            hash_client_secret(client_secret),
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(not_found)?;
+        
+        let address = validate_address(chain, address)?;
+        
+        sqlx::query!(
+            r#"UPDATE payments SET crypto_refund_address = $1, updated_at = NOW() WHERE id = $2"#,
+            address,
+            payment_id,
+        )
+        .execute(pool)
+        .await?;
+        
+        Ok(RefundAddressResponse { payment_id, chain: deposit.chain, address })
+    }
+    
+    pub async fn get_crypto_refund(
+        &self,
+        payment_id: Uuid,
+        refund_id: Uuid,
+        api_key: &str,
+    ) -> Result<CryptoRefund, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let refund = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            SELECT * FROM crypto_refunds
+            WHERE id = $1 AND payment_id = $2 AND merchant_id = $3
+            "#,
+            refund_id,
+            payment_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Crypto refund not found".into()))?;
+        
+        Ok(refund)
+    }
+    
+    /// Signs and broadcasts pending refunds, and tracks broadcast ones until they are
+    /// deep enough to count.
+    pub async fn advance_refunds(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        if self.config.crypto_signer_url.is_none() {
+            return Ok(());
+        }
+        
+        // Stamp the batch so overlapping scans pick different rows
+        let due = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            UPDATE crypto_refunds SET last_checked_at = NOW()
+            WHERE id IN (
+                SELECT id FROM crypto_refunds
+                WHERE status IN ('pending', 'broadcast')
+                  AND (last_checked_at IS NULL OR last_checked_at < NOW() - make_interval(secs => $1))
+                ORDER BY last_checked_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            REFUND_CHECK_INTERVAL_SECS,
+            REFUND_BATCH_SIZE,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        if due.is_empty() {
+            return Ok(());
+        }
+        
+        let signer = CryptoSigner::new(&self.config)?;
+        
+        for refund in due {
+            if !CryptoSigner::available(&self.config, refund.chain) {
+                continue;
+            }
+            let result = match (refund.status, refund.tx_hash.as_deref()) {
+                (CryptoRefundStatus::Broadcast, Some(tx_hash)) => self.track(&signer, pool, &refund, tx_hash).await,
+                _ => self.broadcast(&signer, pool, &refund).await,
+            };
+            if let Err(e) = result {
+                warn!("Failed to advance crypto refund {}: {}", refund.id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn broadcast(&self, signer: &CryptoSigner<'_>, pool: &PgPool, refund: &CryptoRefund) -> Result<(), DefiantError> {
+        let request = SignTransactionRequest {
+            client_reference: refund.id,
+            merchant_id: refund.merchant_id,
+            chain: refund.chain,
+            token_contract: refund.token_contract.as_deref(),
+            to: &refund.address,
+            amount: refund.crypto_amount.to_string(),
+        };
+        let signed = match signer.sign("transactions", &request).await {
+            Ok(signed) => signed,
+            Err(DefiantError::ValidationError(reason)) => return self.fail(pool, refund, &reason).await,
+            Err(e) => return Err(e),
+        };
+        
+        // Failing here instead of retrying could refund the customer twice
+        let tx_hash = signer.broadcast(refund.chain, signed.raw_transaction).await?;
+        
+        let updated = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            UPDATE crypto_refunds SET status = 'broadcast', tx_hash = $1, broadcast_at = NOW()
+            WHERE id = $2 AND status = 'pending'
+            RETURNING *
+            "#,
+            tx_hash,
+            refund.id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        if let Some(updated) = updated {
+            info!("Crypto refund {} broadcast as {}", updated.id, tx_hash);
+            self.emit(updated.merchant_id, CRYPTO_REFUND_BROADCAST, serde_json::json!(updated)).await;
+        }
+        
+        Ok(())
+    }
+    
+    async fn track(
+        &self,
+        signer: &CryptoSigner<'_>,
+        pool: &PgPool,
+        refund: &CryptoRefund,
+        tx_hash: &str,
+    ) -> Result<(), DefiantError> {
+        let progress = signer.progress(refund.chain, tx_hash).await?;
+        
+        match progress {
+            ChainProgress::Pending => Ok(()),
+            ChainProgress::Reverted => self.fail(pool, refund, "Refund transaction failed on chain").await,
+            ChainProgress::Confirmations(depth) => {
+                sqlx::query!(
+                    r#"UPDATE crypto_refunds SET confirmations = $1 WHERE id = $2 AND status = 'broadcast'"#,
+                    depth,
+                    refund.id,
+                )
+                .execute(pool)
+                .await?;
+                Ok(())
+            }
+            ChainProgress::Final(depth) => self.confirm(pool, refund, depth).await,
+        }
+    }
+    
+    /// Marks a refund confirmed and books it against the merchant's ledger.
+    async fn confirm(&self, pool: &PgPool, refund: &CryptoRefund, depth: i32) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        let updated = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            UPDATE crypto_refunds SET status = 'confirmed', confirmations = $1, confirmed_at = NOW()
+            WHERE id = $2 AND status = 'broadcast'
+            RETURNING *
+            "#,
+            depth,
+            refund.id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(()),
+        };
+        
+        book_crypto_refund(&mut tx, &updated).await?;
+        tx.commit().await?;
+        
+        info!("Crypto refund {} confirmed", updated.id);
+        self.emit(updated.merchant_id, CRYPTO_REFUND_CONFIRMED, serde_json::json!(updated)).await;
+        
+        Ok(())
+    }
+    
+    /// Gives up on a refund and releases its amount so it can be refunded again.
+    async fn fail(&self, pool: &PgPool, refund: &CryptoRefund, reason: &str) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        let updated = sqlx::query_as!(
+            CryptoRefund,
+            r#"
+            UPDATE crypto_refunds SET status = 'failed', error = $1
+            WHERE id = $2 AND status IN ('pending', 'broadcast')
+            RETURNING *
+            "#,
+            reason,
+            refund.id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(()),
+        };
+        
+        sqlx::query!(
+            r#"
+            UPDATE payments
+            SET refunded_amount = GREATEST(refunded_amount - $1, 0),
+                status = CASE WHEN refunded_amount - $1 > 0 THEN 'partially_refunded'::payment_status
+                              ELSE 'succeeded'::payment_status END,
+                updated_at = NOW()
+            WHERE id = $2
+            "#,
+            updated.amount,
+            updated.payment_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        warn!("Crypto refund {} failed: {}", updated.id, reason);
+        self.emit(updated.merchant_id, CRYPTO_REFUND_FAILED, serde_json::json!(updated)).await;
+        
+        Ok(())
+    }
+    
+    /// Refunds need both a signer and a node to broadcast through.
+    fn ensure_available(&self, chain: Chain) -> Result<(), DefiantError> {
+        if !CryptoSigner::available(&self.config, chain) {
+            return Err(DefiantError::PaymentError(format!("On-chain refunds are not available on {:?}", chain)));
+        }
+        Ok(())
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, payload: serde_json::Value) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, payload)
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Refunds leave the merchant's wallet on chain, never the payout balance, so like the
+/// payment they reverse the entry is never made available.
+async fn book_crypto_refund(
+    tx: &mut Transaction<'_, Postgres>,
+    refund: &CryptoRefund,
+) -> Result<(), DefiantError> {
+    let asset = crypto_rate_service::asset_symbol(refund.chain, refund.token_contract.as_deref());
+    
+    sqlx::query!(
+        r#"
+        INSERT INTO balance_transactions (
+            merchant_id, customer_id, amount, currency, net, type, description, metadata,
+            crypto_asset, crypto_amount, settlement_asset, settlement_amount
+        )
+        SELECT $1, p.customer_id, $2, $3, $2, 'crypto_refund', $4, $5, $6, $7, $6, $7
+        FROM payments p WHERE p.id = $8
+        "#,
+        refund.merchant_id,
+        -refund.amount,
+        refund.currency,
+        refund.reason,
+        serde_json::json!({ "payment_id": refund.payment_id, "crypto_refund_id": refund.id, "tx_hash": refund.tx_hash }),
+        asset,
+        -refund.crypto_amount,
+        refund.payment_id,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    ledger::record_crypto_refund(tx, refund).await
+}
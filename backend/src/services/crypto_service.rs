@@ -0,0 +1,407 @@
+use std::str::FromStr;
+use std::sync::Arc;
+use bitcoin::bip32::{ChildNumber, Xpub};
+use bitcoin::secp256k1::Secp256k1;
+use bitcoin::{base58, Address};
+use rand::RngCore;
+use sha3::{Digest, Keccak256};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{Chain, ConfigurationChangeData, CryptoAddress, CryptoQuote, CryptoWallet, RegisterCryptoWalletRequest, CRYPTO_WALLET_UPDATED}, errors::DefiantError, db::Database};
+use super::webhook_service::WebhookService;
+
+/// BIP44 external (receiving) chain.
+const EXTERNAL_CHAIN: u32 = 0;
+/// Depth of an account-level key: m / purpose' / coin' / account'.
+const ACCOUNT_DEPTH: u8 = 3;
+/// BIP44's gap limit, which most wallet software uses.
+const DEFAULT_GAP_LIMIT: i32 = 20;
+/// SLIP-132 prefixes for BIP84 keys, mapped to the plain xpub/tpub versions they replace.
+const SLIP132_VERSIONS: &[([u8; 4], [u8; 4])] = &[
+    ([0x04, 0xb2, 0x47, 0x46], [0x04, 0x88, 0xb2, 0x1e]), // zpub -> xpub
+    ([0x04, 0x5f, 0x1c, 0xf6], [0x04, 0x35, 0x87, 0xcf]), // vpub -> tpub
+];
+
+/// Payment addresses on Bitcoin, Ethereum (including ERC-20 tokens) and Solana.
+///
+/// We never hold private keys. On Bitcoin and Ethereum each payment gets the next
+/// receiving address of the merchant's account xpub, so funds land in the merchant's
+/// own wallet and every address we issued can be re-derived from the seed they already
+/// hold. Solana has no public derivation, so payments go to the merchant's registered
+/// address and carry a fresh Solana Pay reference instead.
+///
+/// No address is ever issued twice, even once its payment expires: indexes only move
+/// forward, across re-registrations of the same xpub too, and references are random.
+/// Wallet software only finds funds up to its gap limit of unused addresses past the
+/// last paid one, so indexes beyond that are not handed out.
+pub struct CryptoService {
+    db: Arc<Database>,
+}
+
+impl CryptoService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Stores the xpub (or, on Solana, the address) payments on `chain` are collected
+    /// with. A new wallet replaces the previous one; addresses already issued keep
+    /// pointing at the old wallet. Registering an xpub again (say, to change its gap
+    /// limit) continues from the last index issued for it.
+    pub async fn register_wallet(
+        &self,
+        request: RegisterCryptoWalletRequest,
+        api_key: &str,
+    ) -> Result<CryptoWallet, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let (xpub, address) = match (request.chain.is_hd(), request.xpub, request.address) {
+            (true, Some(xpub), None) => {
+                // Catch keys the chain cannot derive from (or the wrong network) before any payment does
+                derive_address(request.chain, &parse_account_xpub(&xpub)?, 0)?;
+                (Some(xpub), None)
+            }
+            (false, None, Some(address)) => (None, Some(validate_address(request.chain, &address)?)),
+            (true, _, _) => return Err(DefiantError::ValidationError(format!("{:?} wallets are registered with an xpub only", request.chain))),
+            (false, _, _) => return Err(DefiantError::ValidationError(format!("{:?} wallets are registered with an address only", request.chain))),
+        };
+        // Reference-chain payments already land in the registered address
+        let sweep_address = match request.sweep_address {
+            Some(sweep_address) if request.chain.is_hd() => Some(validate_address(request.chain, &sweep_address)?),
+            Some(_) => return Err(DefiantError::ValidationError(format!("sweep_address: {:?} wallets are not swept", request.chain))),
+            None => None,
+        };
+        
+        let mut tx = pool.begin().await?;
+        
+        let previous_id = sqlx::query_scalar!(
+            r#"
+            UPDATE merchant_crypto_wallets SET active = false
+            WHERE merchant_id = $1 AND chain = $2 AND active = true
+            RETURNING id
+            "#,
+            merchant_id,
+            request.chain as Chain,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let issued = sqlx::query!(
+            r#"
+            SELECT COALESCE(MAX(next_index), 0) AS "next_index!", MAX(last_used_index) AS last_used_index
+            FROM merchant_crypto_wallets
+            WHERE merchant_id = $1 AND chain = $2 AND xpub = $3
+            "#,
+            merchant_id,
+            request.chain as Chain,
+            xpub,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let wallet = sqlx::query_as!(
+            CryptoWallet,
+            r#"
+            INSERT INTO merchant_crypto_wallets (merchant_id, chain, xpub, address, next_index, last_used_index, gap_limit, sweep_address)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.chain as Chain,
+            xpub,
+            address,
+            issued.next_index,
+            issued.last_used_index,
+            request.gap_limit.unwrap_or(DEFAULT_GAP_LIMIT),
+            sweep_address,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Crypto wallet {} registered for merchant {} on {:?}", wallet.id, merchant_id, wallet.chain);
+        
+        let mut changed_fields = vec![if wallet.xpub.is_some() { "xpub" } else { "address" }.to_string()];
+        if wallet.sweep_address.is_some() {
+            changed_fields.push("sweep_address".into());
+        }
+        let change = ConfigurationChangeData {
+            previous_id,
+            ..ConfigurationChangeData::new("crypto_wallet", wallet.id, changed_fields)
+        };
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(merchant_id, CRYPTO_WALLET_UPDATED, change, api_key)
+            .await;
+        
+        Ok(wallet)
+    }
+    
+    pub async fn list_wallets(&self, api_key: &str) -> Result<Vec<CryptoWallet>, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let wallets = sqlx::query_as!(
+            CryptoWallet,
+            r#"
+            SELECT * FROM merchant_crypto_wallets
+            WHERE merchant_id = $1
+            ORDER BY created_at DESC
+            "#,
+            merchant_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(wallets)
+    }
+    
+    /// Issues where the payment is to be sent. On HD chains this claims the wallet's next
+    /// receiving index; on reference chains it mints a new reference for the payment.
+    pub async fn assign_address(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        payment_id: Uuid,
+        chain: Chain,
+        token_contract: Option<&str>,
+        quote: &CryptoQuote,
+    ) -> Result<CryptoAddress, DefiantError> {
+        let token_contract = match token_contract {
+            Some(_) if chain != Chain::Ethereum => {
+                return Err(DefiantError::ValidationError("crypto_token: tokens are only supported on Ethereum".into()));
+            }
+            Some(contract) => Some(validate_address(Chain::Ethereum, contract)
+                .map_err(|_| DefiantError::ValidationError("crypto_token: not a valid ERC-20 contract address".into()))?),
+            None => None,
+        };
+        
+        if !chain.is_hd() {
+            return self.assign_reference(tx, merchant_id, payment_id, chain, quote).await;
+        }
+        
+        // Row lock serializes concurrent payments so no index is handed out twice
+        let wallet = sqlx::query!(
+            r#"
+            UPDATE merchant_crypto_wallets
+            SET next_index = next_index + 1
+            WHERE merchant_id = $1 AND chain = $2 AND active = true
+            AND next_index <= COALESCE(last_used_index, -1) + gap_limit
+            RETURNING id, xpub AS "xpub!", next_index - 1 AS "index!"
+            "#,
+            merchant_id,
+            chain as Chain,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        let wallet = match wallet {
+            Some(wallet) => wallet,
+            None => {
+                let gap_limit = sqlx::query_scalar!(
+                    r#"
+                    SELECT gap_limit FROM merchant_crypto_wallets
+                    WHERE merchant_id = $1 AND chain = $2 AND active = true
+                    "#,
+                    merchant_id,
+                    chain as Chain,
+                )
+                .fetch_optional(&mut **tx)
+                .await?;
+                
+                return Err(DefiantError::PaymentError(match gap_limit {
+                    Some(gap_limit) => format!(
+                        "The {:?} wallet has reached its gap limit of {} unpaid addresses; raise the limit in the wallet and re-register the xpub with it",
+                        chain, gap_limit,
+                    ),
+                    None => format!("No {:?} wallet registered for crypto payments", chain),
+                }));
+            }
+        };
+        
+        let xpub = parse_account_xpub(&wallet.xpub)?;
+        let address = derive_address(chain, &xpub, wallet.index as u32)?;
+        let derivation_path = derivation_path(chain, &xpub, wallet.index as u32);
+        
+        let assigned = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            INSERT INTO crypto_addresses (
+                merchant_id, wallet_id, payment_id, chain, address, derivation_index, derivation_path,
+                token_contract, expected_amount, quote_currency, quote_rate, quote_expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING *
+            "#,
+            merchant_id,
+            wallet.id,
+            payment_id,
+            chain as Chain,
+            address,
+            wallet.index,
+            derivation_path,
+            token_contract,
+            quote.amount,
+            quote.currency,
+            quote.rate,
+            quote.expires_at,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        Ok(assigned)
+    }
+    
+    async fn assign_reference(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        payment_id: Uuid,
+        chain: Chain,
+        quote: &CryptoQuote,
+    ) -> Result<CryptoAddress, DefiantError> {
+        let wallet = sqlx::query!(
+            r#"
+            SELECT id, address AS "address!" FROM merchant_crypto_wallets
+            WHERE merchant_id = $1 AND chain = $2 AND active = true
+            "#,
+            merchant_id,
+            chain as Chain,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::PaymentError(format!("No {:?} wallet registered for crypto payments", chain)))?;
+        
+        // Any 32 bytes form a valid reference; it only has to be unique to this payment
+        let mut reference = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut reference);
+        
+        let assigned = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            INSERT INTO crypto_addresses (
+                merchant_id, wallet_id, payment_id, chain, address, reference,
+                expected_amount, quote_currency, quote_rate, quote_expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+            merchant_id,
+            wallet.id,
+            payment_id,
+            chain as Chain,
+            wallet.address,
+            base58::encode(&reference),
+            quote.amount,
+            quote.currency,
+            quote.rate,
+            quote.expires_at,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        Ok(assigned)
+    }
+}
+
+fn parse_account_xpub(xpub: &str) -> Result<Xpub, DefiantError> {
+    let invalid = |e: &dyn std::fmt::Display| DefiantError::ValidationError(format!("xpub: not a valid extended public key ({})", e));
+    
+    let mut data = base58::decode_check(xpub.trim()).map_err(|e| invalid(&e))?;
+    if let Some((_, plain)) = SLIP132_VERSIONS.iter().find(|(prefix, _)| data.starts_with(prefix)) {
+        data[..4].copy_from_slice(plain);
+    }
+    let xpub = Xpub::decode(&data).map_err(|e| invalid(&e))?;
+    
+    if xpub.depth != ACCOUNT_DEPTH {
+        return Err(DefiantError::ValidationError(format!(
+            "xpub: expected an account-level key (depth {}), got depth {}",
+            ACCOUNT_DEPTH, xpub.depth
+        )));
+    }
+    
+    Ok(xpub)
+}
+
+/// Receiving address `index` on the account's external chain.
+pub fn derive_address(chain: Chain, xpub: &Xpub, index: u32) -> Result<String, DefiantError> {
+    let secp = Secp256k1::verification_only();
+    let path = [
+        ChildNumber::from_normal_idx(EXTERNAL_CHAIN).map_err(|_| DefiantError::InternalError)?,
+        ChildNumber::from_normal_idx(index)
+            .map_err(|_| DefiantError::PaymentError("Wallet has exhausted its receiving addresses".into()))?,
+    ];
+    let child = xpub.derive_pub(&secp, &path)
+        .map_err(|e| DefiantError::ValidationError(format!("xpub: derivation failed ({})", e)))?;
+    
+    match chain {
+        Chain::Bitcoin => Address::p2wpkh(&child.to_pub(), xpub.network)
+            .map(|address| address.to_string())
+            .map_err(|e| DefiantError::ValidationError(format!("xpub: cannot derive a segwit address ({})", e))),
+        Chain::Ethereum => Ok(ethereum_address(&child.public_key.serialize_uncompressed())),
+        Chain::Solana => Err(DefiantError::ValidationError("Solana addresses cannot be derived from an xpub".into())),
+    }
+}
+
+/// Checks `address` is well-formed for `chain` and returns it in canonical form.
+pub fn validate_address(chain: Chain, address: &str) -> Result<String, DefiantError> {
+    let address = address.trim();
+    let invalid = || DefiantError::ValidationError(format!("address: not a valid {:?} address", chain));
+    
+    match chain {
+        Chain::Bitcoin => {
+            let parsed = Address::from_str(address).map_err(|_| invalid())?;
+            Ok(parsed.assume_checked().to_string())
+        }
+        Chain::Ethereum => {
+            let hex_part = address.strip_prefix("0x").ok_or_else(invalid)?;
+            if hex_part.len() != 40 || !hex_part.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            let checksummed = eip55(&hex_part.to_ascii_lowercase());
+            // All-lowercase and all-uppercase carry no checksum; mixed case must match it
+            let mixed_case = hex_part.chars().any(|c| c.is_ascii_lowercase()) && hex_part.chars().any(|c| c.is_ascii_uppercase());
+            if mixed_case && checksummed[2..] != *hex_part {
+                return Err(DefiantError::ValidationError("address: EIP-55 checksum mismatch".into()));
+            }
+            Ok(checksummed)
+        }
+        Chain::Solana => {
+            match base58::decode(address) {
+                Ok(bytes) if bytes.len() == 32 => Ok(address.to_string()),
+                _ => Err(invalid()),
+            }
+        }
+    }
+}
+
+fn derivation_path(chain: Chain, xpub: &Xpub, index: u32) -> String {
+    let account = match xpub.child_number {
+        ChildNumber::Hardened { index } | ChildNumber::Normal { index } => index,
+    };
+    format!("m/{}'/{}'/{}'/{}/{}", chain.purpose(), chain.coin_type(), account, EXTERNAL_CHAIN, index)
+}
+
+/// Last 20 bytes of the Keccak-256 of the uncompressed key, with the EIP-55 checksum.
+fn ethereum_address(uncompressed: &[u8; 65]) -> String {
+    let hash = Keccak256::digest(&uncompressed[1..]);
+    eip55(&hex::encode(&hash[12..]))
+}
+
+/// Mixed-case checksum encoding of a lowercase hex address.
+fn eip55(address: &str) -> String {
+    let checksum = Keccak256::digest(address.as_bytes());
+    
+    let checksummed: String = address
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (checksum[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if c.is_ascii_alphabetic() && nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect();
+    
+    format!("0x{}", checksummed)
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::time::Duration as StdDuration;
+use serde::{Deserialize, Serialize};
+
+use crate::{models::Chain, errors::DefiantError, config::Config};
+use super::crypto_watcher::{json_rpc, parse_quantity, provider_error};
+
+const PROVIDER_TIMEOUT_SECS: u64 = 15;
+/// Blocks of history priority fees are read from
+const FEE_HISTORY_BLOCKS: u64 = 20;
+
+#[derive(Debug, Deserialize)]
+pub(super) struct SignedTransaction {
+    /// Hex for Bitcoin and Ethereum, base64 for Solana
+    pub raw_transaction: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EthereumReceipt {
+    status: Option<String>,
+    block_number: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolanaSignatureStatus {
+    confirmations: Option<u64>,
+    err: Option<serde_json::Value>,
+    confirmation_status: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EthereumFeeHistory {
+    base_fee_per_gas: Vec<String>,
+    #[serde(default)]
+    reward: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolanaPrioritizationFee {
+    prioritization_fee: u64,
+}
+
+/// Where a broadcast transaction stands on chain.
+pub(super) enum ChainProgress {
+    /// Not yet mined, or not yet seen by the node
+    Pending,
+    Confirmations(i32),
+    /// Deep enough to count
+    Final(i32),
+    Reverted,
+}
+
+/// Outbound transactions: the custody service at `crypto_signer_url` builds and signs
+/// them, and they are broadcast and tracked through the same nodes the watcher reads.
+///
+/// The signer is idempotent per `client_reference`, so asking again for a transaction
+/// that may already have been broadcast hands back the same one.
+pub(super) struct CryptoSigner<'a> {
+    config: &'a Config,
+    client: reqwest::Client,
+}
+
+impl<'a> CryptoSigner<'a> {
+    pub fn new(config: &'a Config) -> Result<Self, DefiantError> {
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(PROVIDER_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        Ok(Self { config, client })
+    }
+    
+    /// Whether transactions can be both signed and broadcast on `chain`.
+    pub fn available(config: &Config, chain: Chain) -> bool {
+        config.crypto_signer_url.is_some() && node_url(config, chain).is_some()
+    }
+    
+    /// Has the signer build a transaction. A refusal (rather than the signer being
+    /// unreachable) comes back as a `ValidationError`, and is final.
+    pub async fn sign<T: Serialize>(&self, path: &str, request: &T) -> Result<SignedTransaction, DefiantError> {
+        let base_url = self.config.crypto_signer_url.as_deref()
+            .ok_or_else(|| DefiantError::PaymentError("No crypto signer is configured".into()))?;
+        
+        let request = self.client
+            .post(format!("{}/{}", base_url.trim_end_matches('/'), path))
+            .json(request);
+        let request = match &self.config.crypto_signer_api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        };
+        
+        let response = request.send().await.map_err(signer_error)?;
+        if response.status().is_client_error() {
+            let reason = response.text().await.unwrap_or_default();
+            return Err(DefiantError::ValidationError(format!("Signer refused the transaction: {}", reason.trim())));
+        }
+        
+        response
+            .error_for_status()
+            .map_err(signer_error)?
+            .json()
+            .await
+            .map_err(signer_error)
+    }
+    
+    /// Returns the transaction's hash. A rejected broadcast is an error to retry, never
+    /// a failure: the node may only be refusing the transaction because it already has it.
+    pub async fn broadcast(&self, chain: Chain, raw_transaction: String) -> Result<String, DefiantError> {
+        let node_url = self.node_url(chain)?;
+        
+        let tx_hash = match chain {
+            Chain::Bitcoin => self.client
+                .post(format!("{}/tx", node_url))
+                .body(raw_transaction)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(provider_error)?
+                .text()
+                .await
+                .map_err(provider_error)?
+                .trim()
+                .to_string(),
+            Chain::Ethereum => json_rpc(&self.client, node_url, "eth_sendRawTransaction", serde_json::json!([raw_transaction]))
+                .await?
+                .as_str()
+                .map(str::to_string)
+                .unwrap_or_default(),
+            Chain::Solana => json_rpc(
+                &self.client,
+                node_url,
+                "sendTransaction",
+                serde_json::json!([raw_transaction, { "encoding": "base64" }]),
+            )
+            .await?
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_default(),
+        };
+        
+        if tx_hash.is_empty() {
+            return Err(DefiantError::PaymentError("Node returned no transaction hash".into()));
+        }
+        Ok(tx_hash)
+    }
+    
+    /// Depth is held to the same confirmation thresholds as inbound payments; Solana
+    /// counts once finalized.
+    pub async fn progress(&self, chain: Chain, tx_hash: &str) -> Result<ChainProgress, DefiantError> {
+        let node_url = self.node_url(chain)?;
+        
+        match chain {
+            Chain::Bitcoin => self.bitcoin_progress(node_url, tx_hash).await,
+            Chain::Ethereum => self.ethereum_progress(node_url, tx_hash).await,
+            Chain::Solana => self.solana_progress(node_url, tx_hash).await,
+        }
+    }
+    
+    /// Sat per virtual byte Esplora expects to confirm within `target` blocks.
+    pub async fn bitcoin_fee_rate(&self, target: u32) -> Result<f64, DefiantError> {
+        let estimates = self.bitcoin_fee_estimates().await?;
+        fee_rate_for_target(&estimates, target)
+    }
+    
+    /// Esplora's sat per virtual byte, keyed by confirmation target in blocks.
+    pub async fn bitcoin_fee_estimates(&self) -> Result<HashMap<u32, f64>, DefiantError> {
+        let node_url = self.node_url(Chain::Bitcoin)?;
+        let estimates: HashMap<String, f64> = self.client
+            .get(format!("{}/fee-estimates", node_url))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        
+        Ok(estimates
+            .into_iter()
+            .filter_map(|(blocks, rate)| blocks.parse::<u32>().ok().map(|blocks| (blocks, rate)))
+            .collect())
+    }
+    
+    /// Wei per unit of gas.
+    pub async fn ethereum_gas_price(&self) -> Result<u128, DefiantError> {
+        let node_url = self.node_url(Chain::Ethereum)?;
+        let price = json_rpc(&self.client, node_url, "eth_gasPrice", serde_json::json!([])).await?;
+        parse_quantity(price.as_str().unwrap_or_default())
+    }
+    
+    /// Base fee of the next block and, for each of `percentiles`, the median priority
+    /// fee paid at that percentile over recent blocks; all in wei per gas.
+    pub async fn ethereum_fee_history(&self, percentiles: &[f64]) -> Result<(u128, Vec<u128>), DefiantError> {
+        let node_url = self.node_url(Chain::Ethereum)?;
+        let history = json_rpc(
+            &self.client,
+            node_url,
+            "eth_feeHistory",
+            serde_json::json!([format!("{:#x}", FEE_HISTORY_BLOCKS), "latest", percentiles]),
+        )
+        .await?;
+        let history: EthereumFeeHistory = serde_json::from_value(history)
+            .map_err(|_| DefiantError::PaymentError("eth_feeHistory returned an unreadable result".into()))?;
+        
+        // The last base fee is the one the next block will charge
+        let base_fee = match history.base_fee_per_gas.last() {
+            Some(base_fee) => parse_quantity(base_fee)?,
+            None => return Err(DefiantError::PaymentError("eth_feeHistory returned no base fee".into())),
+        };
+        
+        let mut tips = Vec::with_capacity(percentiles.len());
+        for column in 0..percentiles.len() {
+            let mut paid = history.reward
+                .iter()
+                .filter_map(|block| block.get(column))
+                .map(|tip| parse_quantity(tip.as_str()))
+                .collect::<Result<Vec<u128>, _>>()?;
+            paid.sort_unstable();
+            tips.push(paid.get(paid.len() / 2).copied().unwrap_or(0));
+        }
+        
+        Ok((base_fee, tips))
+    }
+    
+    /// Priority fees, in micro-lamports per compute unit, that landed transactions paid
+    /// over recent slots, lowest first.
+    pub async fn solana_priority_fees(&self) -> Result<Vec<u64>, DefiantError> {
+        let node_url = self.node_url(Chain::Solana)?;
+        let result = json_rpc(&self.client, node_url, "getRecentPrioritizationFees", serde_json::json!([])).await?;
+        let samples: Vec<SolanaPrioritizationFee> = serde_json::from_value(result)
+            .map_err(|_| DefiantError::PaymentError("getRecentPrioritizationFees returned an unreadable result".into()))?;
+        
+        let mut fees: Vec<u64> = samples.into_iter().map(|sample| sample.prioritization_fee).collect();
+        fees.sort_unstable();
+        Ok(fees)
+    }
+    
+    async fn bitcoin_progress(&self, base_url: &str, txid: &str) -> Result<ChainProgress, DefiantError> {
+        let response = self.client
+            .get(format!("{}/tx/{}/status", base_url, txid))
+            .send()
+            .await
+            .map_err(provider_error)?;
+        // Not yet propagated to this node
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(ChainProgress::Pending);
+        }
+        let status: EsploraTxStatus = response
+            .error_for_status()
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        
+        let height = match (status.confirmed, status.block_height) {
+            (true, Some(height)) => height,
+            _ => return Ok(ChainProgress::Pending),
+        };
+        let tip: u64 = self.client
+            .get(format!("{}/blocks/tip/height", base_url))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .text()
+            .await
+            .map_err(provider_error)?
+            .trim()
+            .parse()
+            .map_err(|_| DefiantError::PaymentError("Esplora returned an unreadable tip height".into()))?;
+        
+        let depth = tip.saturating_sub(height) + 1;
+        Ok(progress_at(depth, self.config.bitcoin_confirmations.max(1) as u64))
+    }
+    
+    async fn ethereum_progress(&self, rpc_url: &str, tx_hash: &str) -> Result<ChainProgress, DefiantError> {
+        let receipt = json_rpc(&self.client, rpc_url, "eth_getTransactionReceipt", serde_json::json!([tx_hash])).await?;
+        if receipt.is_null() {
+            return Ok(ChainProgress::Pending);
+        }
+        let receipt: EthereumReceipt = serde_json::from_value(receipt)
+            .map_err(|_| DefiantError::PaymentError("eth_getTransactionReceipt returned an unreadable result".into()))?;
+        
+        if receipt.status.as_deref() == Some("0x0") {
+            return Ok(ChainProgress::Reverted);
+        }
+        let block = match receipt.block_number.as_deref() {
+            Some(block) => parse_quantity(block)?,
+            None => return Ok(ChainProgress::Pending),
+        };
+        let tip = json_rpc(&self.client, rpc_url, "eth_blockNumber", serde_json::json!([])).await?;
+        let tip = parse_quantity(tip.as_str().unwrap_or_default())?;
+        
+        let depth = tip.saturating_sub(block) as u64 + 1;
+        Ok(progress_at(depth, self.config.ethereum_confirmations.max(1) as u64))
+    }
+    
+    /// Solana reports no depth once a transaction is finalized, which is what counts.
+    async fn solana_progress(&self, rpc_url: &str, signature: &str) -> Result<ChainProgress, DefiantError> {
+        let result = json_rpc(
+            &self.client,
+            rpc_url,
+            "getSignatureStatuses",
+            serde_json::json!([[signature], { "searchTransactionHistory": true }]),
+        )
+        .await?;
+        
+        let status = match result["value"].get(0).filter(|status| !status.is_null()) {
+            Some(status) => serde_json::from_value::<SolanaSignatureStatus>(status.clone())
+                .map_err(|_| DefiantError::PaymentError("getSignatureStatuses returned an unreadable result".into()))?,
+            None => return Ok(ChainProgress::Pending),
+        };
+        
+        if status.err.is_some() {
+            return Ok(ChainProgress::Reverted);
+        }
+        let depth = status.confirmations.unwrap_or(0).min(i32::MAX as u64) as i32;
+        Ok(match status.confirmation_status.as_deref() {
+            Some("finalized") => ChainProgress::Final(depth),
+            _ => ChainProgress::Confirmations(depth),
+        })
+    }
+    
+    fn node_url(&self, chain: Chain) -> Result<&str, DefiantError> {
+        node_url(self.config, chain)
+            .map(|url| url.trim_end_matches('/'))
+            .ok_or_else(|| DefiantError::PaymentError(format!("No {:?} node is configured", chain)))
+    }
+}
+
+fn node_url(config: &Config, chain: Chain) -> Option<&str> {
+    match chain {
+        Chain::Bitcoin => config.bitcoin_esplora_url.as_deref(),
+        Chain::Ethereum => config.ethereum_rpc_url.as_deref(),
+        Chain::Solana => config.solana_rpc_url.as_deref(),
+    }
+}
+
+/// Esplora's targets are sparse; takes the nearest one at or beyond `target`.
+pub(super) fn fee_rate_for_target(estimates: &HashMap<u32, f64>, target: u32) -> Result<f64, DefiantError> {
+    estimates
+        .iter()
+        .filter(|(blocks, _)| **blocks >= target)
+        .min_by_key(|(blocks, _)| **blocks)
+        .map(|(_, rate)| *rate)
+        .ok_or_else(|| DefiantError::PaymentError("Esplora returned no usable fee estimate".into()))
+}
+
+fn progress_at(depth: u64, threshold: u64) -> ChainProgress {
+    let confirmations = depth.min(i32::MAX as u64) as i32;
+    if depth >= threshold {
+        ChainProgress::Final(confirmations)
+    } else {
+        ChainProgress::Confirmations(confirmations)
+    }
+}
+
+fn signer_error(e: reqwest::Error) -> DefiantError {
+    DefiantError::PaymentError(format!("Crypto signer request failed: {}", e))
+}
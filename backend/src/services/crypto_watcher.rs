@@ -0,0 +1,801 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use chrono::{Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use serde::Deserialize;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{CryptoAddress, CryptoAddressStatus, CryptoQuote, Chain, Payment, PaymentStatus, PAYMENT_SUCCEEDED, PAYMENT_CANCELED, PAYMENT_REQUIRES_ACTION, PAYMENT_PARTIALLY_PAID, PAYMENT_UNDERPAID_ACCEPTED, PAYMENT_OVERPAID}, errors::DefiantError, db::Database, config::Config};
+use crate::shutdown::Shutdown;
+use super::{crypto_conversion_service::{self, CryptoConversionService}, crypto_payout_service::CryptoPayoutService, crypto_refund_service::CryptoRefundService, crypto_rate_service::QUOTE_LOCK_MINUTES, crypto_service::CryptoService, hosted_invoice_service, invoice_service, webhook_service::WebhookService};
+
+const WATCH_INTERVAL_SECS: u64 = 30;
+const WATCH_BATCH_SIZE: i64 = 100;
+const PROVIDER_TIMEOUT_SECS: u64 = 15;
+/// `balanceOf(address)` selector.
+const ERC20_BALANCE_OF: &str = "0x70a08231";
+/// Funds first seen this soon after a quote expires still count, since addresses are
+/// only polled every `WATCH_INTERVAL_SECS`.
+const QUOTE_GRACE_SECS: f64 = (2 * WATCH_INTERVAL_SECS) as f64;
+
+/// What a final deposit did to its payment, and the events that announce it.
+struct Settlement {
+    payment: Payment,
+    events: Vec<&'static str>,
+}
+
+/// What a provider reports for one address, in base units.
+struct AddressBalance {
+    /// Everything sent to the address, including unconfirmed transactions
+    received: Decimal,
+    /// Only funds at least the configured number of blocks deep
+    confirmed: Decimal,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraTx {
+    status: EsploraTxStatus,
+    vout: Vec<EsploraOutput>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraTxStatus {
+    confirmed: bool,
+    block_height: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EsploraOutput {
+    scriptpubkey_address: Option<String>,
+    value: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct RpcResponse {
+    result: Option<serde_json::Value>,
+    error: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SolanaSignature {
+    signature: String,
+    err: Option<serde_json::Value>,
+    confirmation_status: Option<String>,
+}
+
+/// Watches issued crypto addresses and settles their payments once funds are
+/// buried deep enough on chain.
+///
+/// Amounts are held to the quote within the merchant's tolerance. A payment short of
+/// it when the quote lapses becomes partially paid and the rest is due at a
+/// remaining-balance address; one that overshoots by more than the tolerance succeeds
+/// and the excess is credited to the customer's balance. Payments with nothing paid by
+/// their expiry are canceled.
+///
+/// HD addresses are single-use, so their balance is the payment: Bitcoin is read from
+/// an Esplora REST API and Ethereum (ether or the ERC-20 the payment is due in) from a
+/// JSON-RPC node. Solana payments share the merchant's address and are found through
+/// the transactions that carry the payment's reference. Deposit addresses opened with
+/// the conversion provider are single-use too and watched the same way. Providers are
+/// configured per deployment, and a chain without one is simply not watched.
+pub struct CryptoWatcher {
+    db: Arc<Database>,
+    config: Arc<Config>,
+    client: reqwest::Client,
+}
+
+impl CryptoWatcher {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Result<Self, DefiantError> {
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(PROVIDER_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        Ok(Self { db, config, client })
+    }
+    
+    pub async fn scan(&self) -> Result<(), DefiantError> {
+        for pool in self.db.pools() {
+            self.cancel_expired(pool).await?;
+            self.expire_quotes(pool).await?;
+            
+            for address in self.claim_due(pool).await? {
+                if let Err(e) = self.check(pool, &address).await {
+                    warn!("Failed to check {:?} address {}: {}", address.chain, address.address, e);
+                }
+            }
+            
+            CryptoConversionService::new(self.db.clone(), self.config.clone())
+                .advance_conversions(pool)
+                .await?;
+            CryptoRefundService::new(self.db.clone(), self.config.clone())
+                .advance_refunds(pool)
+                .await?;
+            CryptoPayoutService::new(self.db.clone(), self.config.clone())
+                .advance_payouts(pool)
+                .await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Stamps a batch as checked so overlapping scans pick different rows.
+    async fn claim_due(&self, pool: &PgPool) -> Result<Vec<CryptoAddress>, DefiantError> {
+        let addresses = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            UPDATE crypto_addresses SET last_checked_at = NOW()
+            WHERE id IN (
+                SELECT id FROM crypto_addresses
+                WHERE status IN ('awaiting_payment', 'detected')
+                  AND (last_checked_at IS NULL OR last_checked_at < NOW() - make_interval(secs => $1))
+                ORDER BY last_checked_at NULLS FIRST
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            WATCH_INTERVAL_SECS as f64,
+            WATCH_BATCH_SIZE,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(addresses)
+    }
+    
+    async fn check(&self, pool: &PgPool, address: &CryptoAddress) -> Result<(), DefiantError> {
+        let balance = match (address.chain, address.reference.as_deref()) {
+            (Chain::Bitcoin, _) => match self.config.bitcoin_esplora_url.as_deref() {
+                Some(url) => self.bitcoin_balance(url, &address.address).await?,
+                None => return Ok(()),
+            },
+            (Chain::Ethereum, _) => match self.config.ethereum_rpc_url.as_deref() {
+                Some(url) => self.ethereum_balance(url, &address.address, address.token_contract.as_deref()).await?,
+                None => return Ok(()),
+            },
+            (Chain::Solana, Some(reference)) => match self.config.solana_rpc_url.as_deref() {
+                Some(url) => self.solana_received(url, &address.address, reference).await?,
+                None => return Ok(()),
+            },
+            (Chain::Solana, None) => return Ok(()),
+        };
+        
+        if balance.received.is_zero() {
+            return Ok(());
+        }
+        
+        let mut tx = pool.begin().await?;
+        
+        let tolerance_bps = sqlx::query_scalar!(
+            r#"SELECT payment_tolerance_bps FROM crypto_settlement_preferences WHERE merchant_id = $1"#,
+            address.merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .unwrap_or(0);
+        let accepted_at = address.expected_amount - tolerance(address.expected_amount, tolerance_bps);
+        
+        let updated = sqlx::query_as!(
+            CryptoAddress,
+            r#"
+            UPDATE crypto_addresses
+            SET received_amount = $1,
+                confirmed_amount = $2,
+                status = CASE
+                    WHEN $2 >= $5 THEN 'confirmed'::crypto_address_status
+                    -- Short with nothing still in flight, and too late to top up
+                    WHEN $6 AND quote_expires_at + make_interval(secs => $4) < NOW() THEN 'underpaid'::crypto_address_status
+                    ELSE 'detected'::crypto_address_status
+                END,
+                detected_at = COALESCE(detected_at, NOW()),
+                confirmed_at = CASE WHEN $2 >= $5 THEN NOW() END
+            WHERE id = $3
+            AND (
+                status = 'detected'
+                -- First sighting must fall inside the locked quote
+                OR (status = 'awaiting_payment' AND (quote_expires_at IS NULL OR quote_expires_at + make_interval(secs => $4) >= NOW()))
+            )
+            RETURNING *
+            "#,
+            balance.received,
+            balance.confirmed,
+            address.id,
+            QUOTE_GRACE_SECS,
+            accepted_at,
+            balance.received == balance.confirmed,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let updated = match updated {
+            Some(updated) => updated,
+            None => return Ok(()),
+        };
+        
+        // Move the wallet's gap window along as soon as an index is paid to
+        if let (CryptoAddressStatus::AwaitingPayment, Some(wallet_id), Some(index)) =
+            (address.status, updated.wallet_id, updated.derivation_index)
+        {
+            sqlx::query!(
+                r#"UPDATE merchant_crypto_wallets SET last_used_index = GREATEST(last_used_index, $2) WHERE id = $1"#,
+                wallet_id,
+                index,
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        let settlement = match updated.status {
+            CryptoAddressStatus::Confirmed => self.confirm(&mut tx, &updated, tolerance_bps).await?,
+            CryptoAddressStatus::Underpaid => self.collect_remaining(&mut tx, &updated).await?,
+            _ => {
+                // Seen but not yet final; nothing to announce until it confirms
+                sqlx::query!(
+                    r#"
+                    UPDATE payments SET status = $1, updated_at = NOW()
+                    WHERE id = $2 AND status = 'pending'
+                    "#,
+                    PaymentStatus::Processing as PaymentStatus,
+                    updated.payment_id,
+                )
+                .execute(&mut *tx)
+                .await?;
+                None
+            }
+        };
+        
+        // A payment made from an invoice's hosted page settles the invoice with it
+        let invoice = match &settlement {
+            Some(Settlement { payment, .. }) if matches!(payment.status, PaymentStatus::Succeeded) => match payment.invoice_id {
+                Some(invoice_id) => invoice_service::apply_payment(&mut tx, invoice_id, payment.amount).await?,
+                None => None,
+            },
+            _ => None,
+        };
+        
+        tx.commit().await?;
+        
+        if address.status == CryptoAddressStatus::AwaitingPayment {
+            info!("Detected {} on {:?} address {} for payment {}", balance.received, address.chain, address.address, address.payment_id);
+        }
+        if let Some(settlement) = settlement {
+            info!(
+                "Crypto payment {} is {:?} with {} of {} received",
+                settlement.payment.id, settlement.payment.status, updated.confirmed_amount, updated.expected_amount,
+            );
+            for event_type in settlement.events {
+                self.emit(updated.merchant_id, &settlement.payment, event_type).await;
+            }
+            if let Some(invoice) = &invoice {
+                hosted_invoice_service::invoice_paid(self.db.clone(), self.config.clone(), invoice, settlement.payment.id).await;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Succeeds the payment, noting a shortfall the tolerance let through, or crediting
+    /// an overpayment beyond it to the customer's balance.
+    async fn confirm(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        address: &CryptoAddress,
+        tolerance_bps: i32,
+    ) -> Result<Option<Settlement>, DefiantError> {
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            UPDATE payments SET status = $1, updated_at = NOW()
+            WHERE id = $2 AND status IN ('pending', 'processing', 'partially_paid')
+            RETURNING *
+            "#,
+            PaymentStatus::Succeeded as PaymentStatus,
+            address.payment_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        let payment = match payment {
+            Some(payment) => payment,
+            None => return Ok(None),
+        };
+        
+        crypto_conversion_service::settle_confirmed(tx, &self.db.pool, address, &payment).await?;
+        
+        let excess = address.confirmed_amount - address.expected_amount;
+        let (details, outcome) = if excess.is_sign_negative() {
+            (serde_json::json!({ "crypto_amount_short": (-excess).to_string() }), PAYMENT_UNDERPAID_ACCEPTED)
+        } else if excess > tolerance(address.expected_amount, tolerance_bps) {
+            let credited = self.credit_overpayment(tx, address, &payment, excess).await?;
+            (
+                serde_json::json!({ "crypto_amount_overpaid": excess.to_string(), "customer_balance_credited": credited }),
+                PAYMENT_OVERPAID,
+            )
+        } else {
+            return Ok(Some(Settlement { payment, events: vec![PAYMENT_SUCCEEDED] }));
+        };
+        
+        let payment = merge_metadata(tx, payment.id, details).await?;
+        Ok(Some(Settlement { payment, events: vec![PAYMENT_SUCCEEDED, outcome] }))
+    }
+    
+    /// Credits what `excess` base units were worth at the payment's quote to the
+    /// customer's balance, returning the minor units credited.
+    async fn credit_overpayment(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        address: &CryptoAddress,
+        payment: &Payment,
+        excess: Decimal,
+    ) -> Result<i64, DefiantError> {
+        // Underpaid addresses before this one collected the rest of what was quoted
+        let collected_before = sqlx::query_scalar!(
+            r#"
+            SELECT COALESCE(SUM(confirmed_amount), 0) AS "collected!" FROM crypto_addresses
+            WHERE payment_id = $1 AND status = 'underpaid'
+            "#,
+            payment.id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        let quoted = collected_before + address.expected_amount;
+        let credit = (excess / quoted * Decimal::from(payment.amount)).floor().to_i64().unwrap_or(0);
+        if credit <= 0 {
+            return Ok(0);
+        }
+        
+        let ending_balance = sqlx::query_scalar!(
+            r#"
+            UPDATE customers SET balance = COALESCE(balance, 0) + $1
+            WHERE id = $2
+            RETURNING balance AS "balance!"
+            "#,
+            credit,
+            payment.customer_id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO customer_balance_transactions (
+                merchant_id, customer_id, payment_id, type, amount, currency, ending_balance, description
+            )
+            VALUES ($1, $2, $3, 'overpayment_credit', $4, $5, $6, $7)
+            "#,
+            address.merchant_id,
+            payment.customer_id,
+            payment.id,
+            credit,
+            payment.currency,
+            ending_balance,
+            format!("Crypto overpayment on payment {}", payment.id),
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        Ok(credit)
+    }
+    
+    /// Marks the payment partially paid and issues a remaining-balance address for the
+    /// shortfall, at the original rate and with a fresh quote window.
+    async fn collect_remaining(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        address: &CryptoAddress,
+    ) -> Result<Option<Settlement>, DefiantError> {
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            UPDATE payments SET status = $1, updated_at = NOW()
+            WHERE id = $2 AND status IN ('pending', 'processing', 'partially_paid')
+            RETURNING *
+            "#,
+            PaymentStatus::PartiallyPaid as PaymentStatus,
+            address.payment_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        let payment = match payment {
+            Some(payment) => payment,
+            None => return Ok(None),
+        };
+        
+        crypto_conversion_service::deposit_confirmed(tx, address).await?;
+        
+        let quote = CryptoQuote {
+            amount: address.expected_amount - address.confirmed_amount,
+            currency: address.quote_currency.clone().unwrap_or_else(|| payment.currency.clone()),
+            rate: address.quote_rate,
+            expires_at: Utc::now() + Duration::minutes(QUOTE_LOCK_MINUTES),
+        };
+        let token_contract = address.token_contract.as_deref();
+        let conversions = CryptoConversionService::new(self.db.clone(), self.config.clone());
+        let remaining = match conversions.conversion_for(tx, address.merchant_id, address.chain, token_contract).await? {
+            Some(preference) => conversions
+                .open_conversion(tx, payment.id, address.chain, token_contract, &quote, &preference)
+                .await?,
+            None => CryptoService::new(self.db.clone())
+                .assign_address(tx, address.merchant_id, payment.id, address.chain, token_contract, &quote)
+                .await?,
+        };
+        
+        sqlx::query!(
+            r#"UPDATE crypto_addresses SET parent_address_id = $1 WHERE id = $2"#,
+            address.id,
+            remaining.id,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        let mut details = serde_json::json!({
+            "crypto_address": remaining.address,
+            "crypto_amount_remaining": quote.amount.to_string(),
+            "crypto_quote_expires_at": quote.expires_at,
+        });
+        if let Some(reference) = &remaining.reference {
+            details["crypto_reference"] = serde_json::json!(reference);
+        }
+        
+        let payment = merge_metadata(tx, payment.id, details).await?;
+        Ok(Some(Settlement { payment, events: vec![PAYMENT_PARTIALLY_PAID] }))
+    }
+    
+    /// Cancels payments that reached their expiry with nothing paid. Their addresses
+    /// are retired, never to be issued again.
+    async fn cancel_expired(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        let expired = sqlx::query!(
+            r#"
+            UPDATE crypto_addresses a SET status = 'expired'
+            FROM payments p
+            WHERE a.payment_id = p.id AND a.status IN ('awaiting_payment', 'rate_expired')
+            AND p.expires_at < NOW() AND p.status IN ('pending', 'requires_action')
+            RETURNING a.merchant_id, a.payment_id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let mut canceled = Vec::new();
+        for address in &expired {
+            let payment = sqlx::query_as!(
+                Payment,
+                r#"
+                UPDATE payments SET status = $1, updated_at = NOW()
+                WHERE id = $2 AND status IN ('pending', 'requires_action')
+                RETURNING *
+                "#,
+                PaymentStatus::Canceled as PaymentStatus,
+                address.payment_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            if let Some(payment) = payment {
+                canceled.push((address.merchant_id, payment));
+            }
+        }
+        
+        tx.commit().await?;
+        
+        for (merchant_id, payment) in &canceled {
+            info!("Crypto payment {} expired unpaid", payment.id);
+            self.emit(*merchant_id, payment, PAYMENT_CANCELED).await;
+        }
+        
+        Ok(())
+    }
+    
+    /// Quotes that lapsed unpaid leave the payment waiting for the customer to requote
+    /// until it expires.
+    async fn expire_quotes(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        let expired = sqlx::query!(
+            r#"
+            UPDATE crypto_addresses SET status = 'rate_expired'
+            WHERE status = 'awaiting_payment' AND quote_expires_at + make_interval(secs => $1) < NOW()
+            RETURNING merchant_id, payment_id
+            "#,
+            QUOTE_GRACE_SECS,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let mut lapsed = Vec::new();
+        for address in &expired {
+            let payment = sqlx::query_as!(
+                Payment,
+                r#"
+                UPDATE payments SET status = $1, updated_at = NOW()
+                WHERE id = $2 AND status = 'pending'
+                RETURNING *
+                "#,
+                PaymentStatus::RequiresAction as PaymentStatus,
+                address.payment_id,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            if let Some(payment) = payment {
+                lapsed.push((address.merchant_id, payment));
+            }
+        }
+        
+        tx.commit().await?;
+        
+        for (merchant_id, payment) in &lapsed {
+            info!("Crypto quote for payment {} expired unpaid", payment.id);
+            self.emit(*merchant_id, payment, PAYMENT_REQUIRES_ACTION).await;
+        }
+        
+        Ok(())
+    }
+    
+    async fn bitcoin_balance(&self, base_url: &str, address: &str) -> Result<AddressBalance, DefiantError> {
+        let base_url = base_url.trim_end_matches('/');
+        let tip: u64 = self.client
+            .get(format!("{}/blocks/tip/height", base_url))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .text()
+            .await
+            .map_err(provider_error)?
+            .trim()
+            .parse()
+            .map_err(|_| DefiantError::PaymentError("Esplora returned an unreadable tip height".into()))?;
+        
+        let txs: Vec<EsploraTx> = self.client
+            .get(format!("{}/address/{}/txs", base_url, address))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(provider_error)?
+            .json()
+            .await
+            .map_err(provider_error)?;
+        
+        let threshold = self.config.bitcoin_confirmations.max(1) as u64;
+        let mut received = 0u64;
+        let mut confirmed = 0u64;
+        
+        for tx in &txs {
+            let value: u64 = tx.vout
+                .iter()
+                .filter(|output| output.scriptpubkey_address.as_deref() == Some(address))
+                .map(|output| output.value)
+                .sum();
+            received += value;
+            
+            let depth = match (tx.status.confirmed, tx.status.block_height) {
+                (true, Some(height)) => tip.saturating_sub(height) + 1,
+                _ => 0,
+            };
+            if depth >= threshold {
+                confirmed += value;
+            }
+        }
+        
+        Ok(AddressBalance {
+            received: Decimal::from(received),
+            confirmed: Decimal::from(confirmed),
+        })
+    }
+    
+    /// The balance at `tip - threshold + 1` is what has `threshold` confirmations.
+    async fn ethereum_balance(
+        &self,
+        rpc_url: &str,
+        address: &str,
+        token_contract: Option<&str>,
+    ) -> Result<AddressBalance, DefiantError> {
+        let tip = parse_quantity(&self.rpc_quantity(rpc_url, "eth_blockNumber", serde_json::json!([])).await?)?;
+        let threshold = self.config.ethereum_confirmations.max(1) as u128;
+        
+        let received = self.ethereum_balance_at(rpc_url, address, token_contract, "latest".into()).await?;
+        let confirmed = match tip.checked_sub(threshold - 1) {
+            Some(block) => self.ethereum_balance_at(rpc_url, address, token_contract, format!("{:#x}", block)).await?,
+            None => 0,
+        };
+        
+        Ok(AddressBalance {
+            received: to_decimal(received)?,
+            confirmed: to_decimal(confirmed)?,
+        })
+    }
+    
+    /// Ether balance, or the ERC-20 `balanceOf` when a token contract is given.
+    async fn ethereum_balance_at(
+        &self,
+        rpc_url: &str,
+        address: &str,
+        token_contract: Option<&str>,
+        block: String,
+    ) -> Result<u128, DefiantError> {
+        let quantity = match token_contract {
+            Some(contract) => {
+                let call = serde_json::json!({
+                    "to": contract,
+                    "data": format!("{}{:0>64}", ERC20_BALANCE_OF, address.trim_start_matches("0x").to_ascii_lowercase()),
+                });
+                self.rpc_quantity(rpc_url, "eth_call", serde_json::json!([call, block])).await?
+            }
+            None => self.rpc_quantity(rpc_url, "eth_getBalance", serde_json::json!([address, block])).await?,
+        };
+        
+        parse_quantity(&quantity)
+    }
+    
+    /// Lamports paid to `recipient` by transactions that include the payment's reference.
+    /// Finalized transactions count as confirmed.
+    async fn solana_received(&self, rpc_url: &str, recipient: &str, reference: &str) -> Result<AddressBalance, DefiantError> {
+        let signatures: Vec<SolanaSignature> = serde_json::from_value(
+            self.rpc(rpc_url, "getSignaturesForAddress", serde_json::json!([reference, { "commitment": "confirmed" }])).await?,
+        )
+        .map_err(|_| DefiantError::PaymentError("getSignaturesForAddress returned an unreadable result".into()))?;
+        
+        let mut received = 0u64;
+        let mut confirmed = 0u64;
+        
+        for signature in signatures.iter().filter(|s| s.err.is_none()) {
+            let tx = self.rpc(
+                rpc_url,
+                "getTransaction",
+                serde_json::json!([signature.signature, {
+                    "commitment": "confirmed",
+                    "encoding": "json",
+                    "maxSupportedTransactionVersion": 0,
+                }]),
+            )
+            .await?;
+            
+            let keys = tx["transaction"]["message"]["accountKeys"].as_array().cloned().unwrap_or_default();
+            let index = match keys.iter().position(|key| key.as_str() == Some(recipient)) {
+                Some(index) => index,
+                None => continue,
+            };
+            let pre = tx["meta"]["preBalances"][index].as_u64().unwrap_or(0);
+            let post = tx["meta"]["postBalances"][index].as_u64().unwrap_or(0);
+            let value = post.saturating_sub(pre);
+            
+            received += value;
+            if signature.confirmation_status.as_deref() == Some("finalized") {
+                confirmed += value;
+            }
+        }
+        
+        Ok(AddressBalance {
+            received: Decimal::from(received),
+            confirmed: Decimal::from(confirmed),
+        })
+    }
+    
+    async fn rpc_quantity(&self, url: &str, method: &str, params: serde_json::Value) -> Result<String, DefiantError> {
+        self.rpc(url, method, params)
+            .await?
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| DefiantError::PaymentError(format!("{} returned a non-quantity result", method)))
+    }
+    
+    async fn rpc(&self, url: &str, method: &str, params: serde_json::Value) -> Result<serde_json::Value, DefiantError> {
+        json_rpc(&self.client, url, method, params).await
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, payment: &Payment, event_type: &str) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(payment))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Periodically checks every watched address against the chain.
+pub fn spawn_crypto_watcher(db: Arc<Database>, config: Arc<Config>, shutdown: &Shutdown) {
+    if config.bitcoin_esplora_url.is_none() && config.ethereum_rpc_url.is_none() && config.solana_rpc_url.is_none() {
+        warn!("No crypto providers configured; crypto payments will not confirm automatically");
+        return;
+    }
+    
+    shutdown.spawn(|mut shutdown| async move {
+        let watcher = match CryptoWatcher::new(db, config) {
+            Ok(watcher) => watcher,
+            Err(e) => {
+                error!("Failed to start crypto watcher: {}", e);
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(StdDuration::from_secs(WATCH_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            if let Err(e) = watcher.scan().await {
+                error!("Crypto watcher scan failed: {}", e);
+            }
+        }
+    });
+}
+
+/// A JSON-RPC call, with the node's error surfaced as a payment error.
+pub(super) async fn json_rpc(
+    client: &reqwest::Client,
+    url: &str,
+    method: &str,
+    params: serde_json::Value,
+) -> Result<serde_json::Value, DefiantError> {
+    let response: RpcResponse = client
+        .post(url)
+        .json(&serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(provider_error)?
+        .json()
+        .await
+        .map_err(provider_error)?;
+    
+    // A null result is an answer too, such as a receipt for a transaction not yet mined
+    match response.error {
+        None => Ok(response.result.unwrap_or_default()),
+        Some(error) => Err(DefiantError::PaymentError(format!("{} failed: {}", method, error))),
+    }
+}
+
+pub(super) fn provider_error(e: reqwest::Error) -> DefiantError {
+    DefiantError::PaymentError(format!("Chain provider request failed: {}", e))
+}
+
+/// `bps` basis points of `amount`, in whole base units.
+fn tolerance(amount: Decimal, bps: i32) -> Decimal {
+    (amount * Decimal::from(bps) / Decimal::from(10_000)).floor()
+}
+
+async fn merge_metadata(
+    tx: &mut Transaction<'_, Postgres>,
+    payment_id: Uuid,
+    details: serde_json::Value,
+) -> Result<Payment, DefiantError> {
+    let payment = sqlx::query_as!(
+        Payment,
+        r#"
+        UPDATE payments
+        SET metadata = COALESCE(metadata, '{}'::jsonb) || $1::jsonb, updated_at = NOW()
+        WHERE id = $2
+        RETURNING *
+        "#,
+        details,
+        payment_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(payment)
+}
+
+/// JSON-RPC quantities are `0x`-prefixed hex.
+pub(super) fn parse_quantity(value: &str) -> Result<u128, DefiantError> {
+    u128::from_str_radix(value.trim_start_matches("0x"), 16)
+        .map_err(|_| DefiantError::PaymentError(format!("Unreadable RPC quantity {}", value)))
+}
+
+fn to_decimal(value: u128) -> Result<Decimal, DefiantError> {
+    Decimal::from_u128(value)
+        .ok_or_else(|| DefiantError::PaymentError("Balance exceeds supported precision".into()))
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use uuid::Uuid;
+use chrono::{DateTime, Utc};
+use tracing::info;
+
+use crate::{api::v1::conditional::{check_version, Precondition}, models::{Customer, SealedPii, CustomerListQuery, CustomerListResponse, CustomerSearchQuery, DeleteCustomerQuery}, errors::DefiantError, db::Database, pii};
+use super::dependency_service::{self, Dependent};
+use super::pagination::{self, Cursor, Page};
+use super::search::SearchFilter;
+
+/// A customer row as read, before its contact details are opened.
+struct StoredCustomer {
+    id: Uuid,
+    email: SealedPii,
+    name: Option<String>,
+    phone: Option<SealedPii>,
+    description: Option<String>,
+    metadata: Option<serde_json::Value>,
+    default_payment_method: Option<Uuid>,
+    currency: Option<String>,
+    balance: i64,
+    delinquent: bool,
+    shipping_address: Option<serde_json::Value>,
+    consents: Option<serde_json::Value>,
+    livemode: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    version: i64,
+}
+
+impl StoredCustomer {
+    fn open(self) -> Result<Customer, DefiantError> {
+        let email = self.email.open(&pii::context("customers.email", self.id))?;
+        let phone = self.phone
+            .map(|phone| phone.open(&pii::context("customers.phone", self.id)))
+            .transpose()?;
+        
+        Ok(Customer {
+            id: self.id,
+            email,
+            name: self.name,
+            phone,
+            description: self.description,
+            metadata: self.metadata,
+            default_payment_method: self.default_payment_method,
+            currency: self.currency,
+            balance: self.balance,
+            delinquent: self.delinquent,
+            shipping_address: self.shipping_address,
+            consents: self.consents,
+            livemode: self.livemode,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+            version: self.version,
+        })
+    }
+}
+
+pub struct CustomerService {
+    db: Arc<Database>,
+}
+
+impl CustomerService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn list_customers(
+        &self,
+        query: CustomerListQuery,
+        api_key: &str,
+    ) -> Result<CustomerListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        // Emails are found by blind index; customers not yet re-encrypted have none
+        let email = query.email.map(|email| email.trim().to_lowercase());
+        let keyring = pii::keyring()?;
+        let email_hash = email.as_deref().map(|email| keyring.blind_index(email));
+        
+        // Fetch one extra row to learn whether another page exists
+        let customers = sqlx::query_as!(
+            StoredCustomer,
+            r#"
+            SELECT id, email AS "email: SealedPii", name, phone AS "phone: SealedPii", description, metadata,
+                default_payment_method_id AS default_payment_method, currency,
+                balance AS "balance!", delinquent AS "delinquent!", shipping_address, consents, livemode,
+                created_at AS "created_at!", updated_at AS "updated_at!", version
+            FROM customers
+            WHERE merchant_id = $1 AND livemode = $2
+            AND ($3::TEXT IS NULL OR email_hash = $3 OR (email_hash IS NULL AND email = $4))
+            AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            key.merchant_id,
+            key.livemode,
+            email_hash,
+            email,
+            after_created,
+            after_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(StoredCustomer::open)
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        let page = Page::new(customers, limit, |customer| Cursor::new(customer.created_at, customer.id));
+        
+        Ok(CustomerListResponse {
+            data: page.data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/customers".into(),
+        })
+    }
+    
+    /// Customers matching a search, newest first, using the same indexes as payment
+    /// search over the name and description.
+    pub async fn search_customers(
+        &self,
+        query: CustomerSearchQuery,
+        api_key: &str,
+    ) -> Result<CustomerListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let filter = SearchFilter::parse(&query.query)?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        
+        // The text expression must match idx_customers_search to use it
+        let customers = sqlx::query_as!(
+            StoredCustomer,
+            r#"
+            SELECT id, email AS "email: SealedPii", name, phone AS "phone: SealedPii", description, metadata,
+                default_payment_method_id AS default_payment_method, currency,
+                balance AS "balance!", delinquent AS "delinquent!", shipping_address, consents, livemode,
+                created_at AS "created_at!", updated_at AS "updated_at!", version
+            FROM customers
+            WHERE merchant_id = $1 AND livemode = $2
+            AND ($3::TEXT IS NULL
+                OR to_tsvector('simple', COALESCE(name, '') || ' ' || COALESCE(description, ''))
+                    @@ websearch_to_tsquery('simple', $3))
+            AND ($4::JSONB IS NULL OR metadata @> $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            key.merchant_id,
+            key.livemode,
+            filter.text,
+            filter.metadata,
+            after_created,
+            after_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(StoredCustomer::open)
+        .collect::<Result<Vec<_>, _>>()?;
+        
+        let page = Page::new(customers, limit, |customer| Cursor::new(customer.created_at, customer.id));
+        
+        Ok(CustomerListResponse {
+            data: page.data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/customers/search".into(),
+        })
+    }
+    
+    /// Deletes a customer. Live subscriptions, agreements and draft invoices go with it
+    /// only when `force` is set; open invoices, payments in progress and a non-zero
+    /// balance always block.
+    pub async fn delete_customer(
+        &self,
+        customer_id: Uuid,
+        query: DeleteCustomerQuery,
+        precondition: &Precondition,
+        api_key: &str,
+    ) -> Result<(), DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let customer = sqlx::query!(
+            r#"SELECT updated_at AS "updated_at!", version FROM customers WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            customer_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Customer not found".into()))?;
+        precondition.check(customer.updated_at)?;
+        check_version(query.version, customer.version)?;
+        
+        dependency_service::ensure_removable(&mut tx, merchant_id, Dependent::Customer(customer_id), query.force).await?;
+        
+        sqlx::query!(
+            r#"DELETE FROM customers WHERE id = $1 AND merchant_id = $2"#,
+            customer_id,
+            merchant_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Customer {} deleted", customer_id);
+        Ok(())
+    }
+}
@@ -0,0 +1,249 @@
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{models::{DependencyBlocker, DependencyConflict, ForceResolution}, errors::DefiantError};
+
+/// How many blocking ids a conflict lists per relation.
+const SAMPLE_IDS: i32 = 10;
+
+/// An object whose removal other objects depend on.
+#[derive(Debug, Clone, Copy)]
+pub enum Dependent {
+    Customer(Uuid),
+    WebhookEndpoint(Uuid),
+}
+
+impl Dependent {
+    fn object(&self) -> &'static str {
+        match self {
+            Dependent::Customer(_) => "customer",
+            Dependent::WebhookEndpoint(_) => "webhook_endpoint",
+        }
+    }
+    
+    fn id(&self) -> Uuid {
+        match self {
+            Dependent::Customer(id) | Dependent::WebhookEndpoint(id) => *id,
+        }
+    }
+}
+
+/// Checks nothing still depends on `target` before the caller removes it in the same
+/// transaction.
+///
+/// Every relation is classified once, here: `Cascade` blockers end together with the
+/// object and only need the caller's `force` to go ahead, while `Block` blockers (money
+/// in flight, amounts owed) fail the delete even when forced. Without `force`, any
+/// blocker fails it, so nothing is removed as a side effect the caller did not ask for.
+pub async fn ensure_removable(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    target: Dependent,
+    force: bool,
+) -> Result<(), DefiantError> {
+    let blockers = blockers(tx, merchant_id, target).await?;
+    if blockers.is_empty() {
+        return Ok(());
+    }
+    
+    let conflict = DependencyConflict {
+        object: target.object().into(),
+        id: target.id(),
+        blockers,
+    };
+    if force && conflict.forceable() {
+        return Ok(());
+    }
+    
+    Err(DefiantError::DependencyConflict(Box::new(conflict)))
+}
+
+/// Everything currently standing in the way of removing `target`.
+pub async fn blockers(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    target: Dependent,
+) -> Result<Vec<DependencyBlocker>, DefiantError> {
+    let blockers = match target {
+        Dependent::Customer(customer_id) => customer_blockers(tx, merchant_id, customer_id).await?,
+        Dependent::WebhookEndpoint(webhook_id) => webhook_endpoint_blockers(tx, merchant_id, webhook_id).await?,
+    };
+    
+    Ok(blockers.into_iter().flatten().collect())
+}
+
+async fn customer_blockers(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    customer_id: Uuid,
+) -> Result<Vec<Option<DependencyBlocker>>, DefiantError> {
+    let subscriptions = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM subscriptions
+        WHERE merchant_id = $1 AND customer_id = $2
+        AND status IN ('active', 'trialing', 'past_due', 'unpaid', 'incomplete')
+        "#,
+        merchant_id,
+        customer_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let agreements = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM mit_agreements
+        WHERE merchant_id = $1 AND customer_id = $2 AND active = true
+        "#,
+        merchant_id,
+        customer_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let draft_invoices = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM invoices
+        WHERE merchant_id = $1 AND customer_id = $2 AND status = 'draft'
+        "#,
+        merchant_id,
+        customer_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let open_invoices = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM invoices
+        WHERE merchant_id = $1 AND customer_id = $2 AND status = 'open'
+        "#,
+        merchant_id,
+        customer_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let payments = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM payments
+        WHERE merchant_id = $1 AND customer_id = $2
+        AND status IN ('pending', 'processing', 'requires_action', 'requires_confirmation', 'requires_capture')
+        "#,
+        merchant_id,
+        customer_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let balance = sqlx::query_scalar!(
+        r#"SELECT COALESCE(balance, 0) AS "balance!" FROM customers WHERE id = $1 AND merchant_id = $2"#,
+        customer_id,
+        merchant_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(vec![
+        blocker(
+            "subscription", subscriptions.count, subscriptions.ids,
+            "Live subscriptions; forcing ends them with the customer",
+            ForceResolution::Cascade,
+        ),
+        blocker(
+            "mit_agreement", agreements.count, agreements.ids,
+            "Active merchant-initiated charge agreements; forcing revokes them",
+            ForceResolution::Cascade,
+        ),
+        blocker(
+            "invoice", draft_invoices.count, draft_invoices.ids,
+            "Draft invoices; forcing discards them",
+            ForceResolution::Cascade,
+        ),
+        blocker(
+            "invoice", open_invoices.count, open_invoices.ids,
+            "Open invoices; void or collect them first",
+            ForceResolution::Block,
+        ),
+        blocker(
+            "payment", payments.count, payments.ids,
+            "Payments still in progress; wait for them to settle or cancel them first",
+            ForceResolution::Block,
+        ),
+        (balance != 0).then(|| DependencyBlocker {
+            object: "customer_balance".into(),
+            count: 1,
+            ids: Vec::new(),
+            description: format!("Customer balance of {} is not zero; settle it first", balance),
+            on_force: ForceResolution::Block,
+        }),
+    ])
+}
+
+async fn webhook_endpoint_blockers(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    webhook_id: Uuid,
+) -> Result<Vec<Option<DependencyBlocker>>, DefiantError> {
+    let replays = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$3] AS ids
+        FROM event_replays
+        WHERE merchant_id = $1 AND webhook_id = $2 AND status IN ('pending', 'running')
+        "#,
+        merchant_id,
+        webhook_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    let deliveries = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "count!", (array_agg(id ORDER BY created_at DESC))[1:$2] AS ids
+        FROM webhook_deliveries
+        WHERE webhook_id = $1 AND status IN ('pending', 'in_progress')
+        "#,
+        webhook_id,
+        SAMPLE_IDS,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(vec![
+        blocker(
+            "event_replay", replays.count, replays.ids,
+            "Replays still running; forcing stops them",
+            ForceResolution::Cascade,
+        ),
+        blocker(
+            "webhook_delivery", deliveries.count, deliveries.ids,
+            "Events not yet delivered; forcing drops them",
+            ForceResolution::Cascade,
+        ),
+    ])
+}
+
+fn blocker(
+    object: &str,
+    count: i64,
+    ids: Option<Vec<Uuid>>,
+    description: &str,
+    on_force: ForceResolution,
+) -> Option<DependencyBlocker> {
+    (count > 0).then(|| DependencyBlocker {
+        object: object.into(),
+        count,
+        ids: ids.unwrap_or_default(),
+        description: description.into(),
+        on_force,
+    })
+}
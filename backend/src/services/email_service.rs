@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use lettre::{
+    message::header::ContentType,
+    transport::smtp::authentication::Credentials,
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::{config::Config, db::Database, errors::DefiantError, models::{CommunicationChannel, CommunicationKind, NewCommunication}};
+use super::communication_service::CommunicationService;
+
+/// An email addressed to a customer; every send is recorded in the communication log.
+#[derive(Debug, Clone)]
+pub struct CustomerEmail {
+    pub merchant_id: Uuid,
+    pub customer_id: Uuid,
+    pub kind: CommunicationKind,
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub related_object_type: Option<String>,
+    pub related_object_id: Option<Uuid>,
+}
+
+pub struct EmailService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl EmailService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn send_to_customer(&self, email: CustomerEmail) -> Result<(), DefiantError> {
+        let communications = CommunicationService::new(self.db.clone());
+        
+        let record = communications.record(NewCommunication {
+            merchant_id: email.merchant_id,
+            customer_id: email.customer_id,
+            channel: CommunicationChannel::Email,
+            kind: email.kind,
+            recipient: email.to.clone(),
+            subject: Some(email.subject.clone()),
+            related_object_type: email.related_object_type,
+            related_object_id: email.related_object_id,
+        }).await?;
+        
+        match self.deliver(&email.to, &email.subject, email.html_body).await {
+            Ok(message_id) => {
+                info!("Email {} sent to customer {}", record.id, email.customer_id);
+                communications.mark_sent(record.merchant_id, record.id, message_id).await
+            }
+            Err(e) => {
+                error!("Failed to send email {}: {}", record.id, e);
+                communications.mark_failed(record.merchant_id, record.id, &e.to_string()).await?;
+                Err(e)
+            }
+        }
+    }
+    
+    /// Sends an email that is not tied to a customer (merchant or dashboard user notices).
+    pub async fn send(&self, to: &str, subject: &str, html_body: String) -> Result<(), DefiantError> {
+        self.deliver(to, subject, html_body).await.map(|_| ())
+    }
+    
+    async fn deliver(&self, to: &str, subject: &str, html_body: String) -> Result<Option<String>, DefiantError> {
+        let message = Message::builder()
+            .from(self.config.from_email.parse().map_err(|_| DefiantError::InternalError)?)
+            .to(to.parse().map_err(|_| DefiantError::ValidationError(format!("Invalid recipient: {}", to)))?)
+            .subject(subject)
+            .header(ContentType::TEXT_HTML)
+            .body(html_body)
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let mailer = AsyncSmtpTransport::<Tokio1Executor>::relay(&self.config.smtp_host)
+            .map_err(|_| DefiantError::InternalError)?
+            .port(self.config.smtp_port)
+            .credentials(Credentials::new(
+                self.config.smtp_username.clone(),
+                self.config.smtp_password.clone(),
+            ))
+            .build();
+        
+        let response = mailer
+            .send(message)
+            .await
+            .map_err(|e| {
+                error!("SMTP error: {}", e);
+                DefiantError::InternalError
+            })?;
+        
+        // Most relays answer "250 OK queued as <id>"; keep the id for delivery receipts
+        let message_id = response
+            .message()
+            .last()
+            .and_then(|line| line.rsplit(' ').next())
+            .map(|id| id.to_string());
+        
+        Ok(message_id)
+    }
+}
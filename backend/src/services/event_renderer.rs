@@ -0,0 +1,64 @@
+use crate::{models::{Event, RenderedEvent, WEBHOOK_API_VERSIONS}, errors::DefiantError};
+
+/// A breaking change to event payloads, introduced in `version`.
+///
+/// Event data is always stored in the shape of the version it was written in.
+/// Rendering walks the changes between that version and the endpoint's, calling
+/// `upgrade` going forward and `downgrade` going back, so an endpoint keeps
+/// receiving the shape it integrated against.
+pub struct VersionChange {
+    pub version: &'static str,
+    pub description: &'static str,
+    /// Event type filters (`*`, `payment.*`, `invoice.paid`) the change applies to.
+    pub event_types: &'static [&'static str],
+    pub upgrade: fn(&mut serde_json::Value),
+    pub downgrade: fn(&mut serde_json::Value),
+}
+
+/// Every payload change, oldest first. Adding a version to `WEBHOOK_API_VERSIONS`
+/// that reshapes an existing model must come with an entry here.
+pub const VERSION_CHANGES: &[VersionChange] = &[];
+
+/// Renders an event's payload in `target_version`.
+pub fn render(event: &Event, target_version: &str) -> Result<RenderedEvent, DefiantError> {
+    let source_version = event.api_version.as_str();
+    for version in [source_version, target_version] {
+        if !WEBHOOK_API_VERSIONS.contains(&version) {
+            return Err(DefiantError::ValidationError(format!("Unknown event payload version '{}'", version)));
+        }
+    }
+    
+    // Versions are ISO dates, so they order lexicographically
+    let mut data = event.data.clone();
+    if target_version > source_version {
+        for change in VERSION_CHANGES.iter()
+            .filter(|c| c.version > source_version && c.version <= target_version)
+            .filter(|c| applies_to(c, &event.event_type))
+        {
+            (change.upgrade)(&mut data);
+        }
+    } else {
+        for change in VERSION_CHANGES.iter().rev()
+            .filter(|c| c.version > target_version && c.version <= source_version)
+            .filter(|c| applies_to(c, &event.event_type))
+        {
+            (change.downgrade)(&mut data);
+        }
+    }
+    
+    Ok(RenderedEvent {
+        id: event.id,
+        event_type: event.event_type.clone(),
+        data,
+        api_version: target_version.to_string(),
+        livemode: event.livemode,
+        created_at: event.created_at,
+    })
+}
+
+fn applies_to(change: &VersionChange, event_type: &str) -> bool {
+    change.event_types.iter().any(|filter| match filter.strip_suffix('*') {
+        Some(prefix) => event_type.starts_with(prefix),
+        None => *filter == event_type,
+    })
+}
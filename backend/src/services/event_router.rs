@@ -0,0 +1,219 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use futures_util::StreamExt;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::models::RenderedEvent;
+
+/// Live events held per merchant for connections reading slower than they arrive.
+const ROUTER_BUFFER_EVENTS: usize = 256;
+const ROUTER_RECONNECT_DELAY_SECS: u64 = 5;
+
+/// The Redis channel a merchant's live events are published on.
+pub fn merchant_channel(merchant_id: Uuid) -> String {
+    format!("payments:{}", merchant_id)
+}
+
+#[derive(Debug, Clone)]
+enum Routed {
+    Event(Arc<RenderedEvent>),
+    /// The Redis connection dropped, so events published meanwhile were missed
+    Gap,
+}
+
+enum RouterCommand {
+    Subscribe { merchant_id: Uuid, ready: oneshot::Sender<()> },
+    Unsubscribe(Uuid),
+}
+
+/// Fans live events out to this instance's stream connections over a single Redis
+/// pub/sub connection, subscribed only to the channels of merchants that have a
+/// connection open here. Instances can be added without each receiving every
+/// merchant's events.
+pub struct EventRouter {
+    merchants: Mutex<HashMap<Uuid, broadcast::Sender<Routed>>>,
+    commands: mpsc::UnboundedSender<RouterCommand>,
+}
+
+impl EventRouter {
+    pub fn start(redis: Arc<redis::Client>) -> Arc<Self> {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        let router = Arc::new(Self { merchants: Mutex::new(HashMap::new()), commands });
+        tokio::spawn(route(router.clone(), redis, receiver));
+        router
+    }
+    
+    /// Events published for `merchant_id` from the time this returns, until the
+    /// handle is dropped.
+    pub async fn subscribe(self: &Arc<Self>, merchant_id: Uuid) -> MerchantEvents {
+        let (ready, subscribed) = oneshot::channel();
+        let receiver = {
+            let mut merchants = self.merchants();
+            let receiver = match merchants.get(&merchant_id) {
+                Some(sender) => sender.subscribe(),
+                None => {
+                    let (sender, receiver) = broadcast::channel(ROUTER_BUFFER_EVENTS);
+                    merchants.insert(merchant_id, sender);
+                    receiver
+                }
+            };
+            // Sent under the lock, so it cannot overtake the unsubscribe of a handle
+            // dropped just before
+            let _ = self.commands.send(RouterCommand::Subscribe { merchant_id, ready });
+            receiver
+        };
+        // Cancelled only when the connection dropped mid-subscribe, which also sends
+        // a gap to this handle
+        let _ = subscribed.await;
+        
+        MerchantEvents { router: self.clone(), merchant_id, receiver }
+    }
+    
+    /// Prometheus text for the merchant channels this instance listens on.
+    pub fn metrics(&self) -> String {
+        let mut out = String::new();
+        out.push_str("# HELP defiant_event_router_channels Merchant event channels this instance is subscribed to\n");
+        out.push_str("# TYPE defiant_event_router_channels gauge\n");
+        let _ = writeln!(out, "defiant_event_router_channels {}", self.merchants().len());
+        out
+    }
+    
+    fn merchants(&self) -> MutexGuard<'_, HashMap<Uuid, broadcast::Sender<Routed>>> {
+        self.merchants.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    
+    fn dispatch(&self, message: &redis::Msg) {
+        let merchant_id = message.get_channel_name()
+            .strip_prefix("payments:")
+            .and_then(|id| id.parse::<Uuid>().ok());
+        let event = message.get_payload::<String>().ok()
+            .and_then(|payload| serde_json::from_str::<RenderedEvent>(&payload).ok());
+        
+        match (merchant_id, event) {
+            (Some(merchant_id), Some(event)) => {
+                if let Some(sender) = self.merchants().get(&merchant_id) {
+                    let _ = sender.send(Routed::Event(Arc::new(event)));
+                }
+            }
+            _ => warn!("Dropped unreadable message on {}", message.get_channel_name()),
+        }
+    }
+    
+    fn signal_gap(&self) {
+        for sender in self.merchants().values() {
+            let _ = sender.send(Routed::Gap);
+        }
+    }
+}
+
+/// One connection's feed of a merchant's live events.
+pub struct MerchantEvents {
+    router: Arc<EventRouter>,
+    merchant_id: Uuid,
+    receiver: broadcast::Receiver<Routed>,
+}
+
+impl MerchantEvents {
+    /// The next event, or `None` once events may have been missed, because the
+    /// connection fell too far behind or Redis was unreachable. The stream should then
+    /// end so the client resumes from its last event.
+    pub async fn recv(&mut self) -> Option<Arc<RenderedEvent>> {
+        match self.receiver.recv().await {
+            Ok(Routed::Event(event)) => Some(event),
+            Ok(Routed::Gap) | Err(_) => None,
+        }
+    }
+}
+
+impl Drop for MerchantEvents {
+    fn drop(&mut self) {
+        let mut merchants = self.router.merchants();
+        // The count still includes this handle's receiver
+        if merchants.get(&self.merchant_id).is_some_and(|sender| sender.receiver_count() <= 1) {
+            merchants.remove(&self.merchant_id);
+            let _ = self.router.commands.send(RouterCommand::Unsubscribe(self.merchant_id));
+        }
+    }
+}
+
+enum Next {
+    Message(Option<redis::Msg>),
+    Command(Option<RouterCommand>),
+}
+
+/// Owns the pub/sub connection: applies subscription changes in the order they were
+/// made and hands each message to the merchant's connections, reconnecting and
+/// resubscribing whenever Redis drops it.
+async fn route(router: Arc<EventRouter>, redis: Arc<redis::Client>, mut commands: mpsc::UnboundedReceiver<RouterCommand>) {
+    let mut subscribed: HashSet<Uuid> = HashSet::new();
+    
+    loop {
+        let mut pubsub = match connect(&redis, &subscribed).await {
+            Ok(pubsub) => pubsub,
+            Err(e) => {
+                error!("Failed to connect the event router to Redis: {}", e);
+                tokio::time::sleep(Duration::from_secs(ROUTER_RECONNECT_DELAY_SECS)).await;
+                continue;
+            }
+        };
+        
+        loop {
+            // The message stream borrows the connection, so it is dropped before
+            // subscriptions change; messages arriving meanwhile wait on the socket
+            let next = {
+                let mut messages = pubsub.on_message();
+                tokio::select! {
+                    message = messages.next() => Next::Message(message),
+                    command = commands.recv() => Next::Command(command),
+                }
+            };
+            
+            let applied = match next {
+                Next::Message(Some(message)) => {
+                    router.dispatch(&message);
+                    Ok(())
+                }
+                Next::Message(None) => break,
+                Next::Command(None) => return,
+                Next::Command(Some(RouterCommand::Subscribe { merchant_id, ready })) => {
+                    let result = if subscribed.insert(merchant_id) {
+                        pubsub.subscribe(merchant_channel(merchant_id)).await
+                    } else {
+                        Ok(())
+                    };
+                    if result.is_ok() {
+                        let _ = ready.send(());
+                    }
+                    result
+                }
+                Next::Command(Some(RouterCommand::Unsubscribe(merchant_id))) => {
+                    if subscribed.remove(&merchant_id) {
+                        pubsub.unsubscribe(merchant_channel(merchant_id)).await
+                    } else {
+                        Ok(())
+                    }
+                }
+            };
+            if let Err(e) = applied {
+                error!("Event router failed to change subscriptions: {}", e);
+                break;
+            }
+        }
+        
+        warn!("Event router lost its Redis connection; reconnecting");
+        router.signal_gap();
+        tokio::time::sleep(Duration::from_secs(ROUTER_RECONNECT_DELAY_SECS)).await;
+    }
+}
+
+async fn connect(redis: &redis::Client, subscribed: &HashSet<Uuid>) -> redis::RedisResult<redis::aio::PubSub> {
+    let mut pubsub = redis.get_async_connection().await?.into_pubsub();
+    for merchant_id in subscribed {
+        pubsub.subscribe(merchant_channel(*merchant_id)).await?;
+    }
+    Ok(pubsub)
+}
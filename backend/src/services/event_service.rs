@@ -0,0 +1,600 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, error};
+use utoipa::OpenApi;
+
+use crate::{models::{Event, EventListQuery, EventListResponse, EventReplay, EventReplayStatus, EventReplayResponse, CreateEventReplayRequest, Webhook, EventTypeDefinition, EventTypeListResponse, EventPollQuery, EventPollResponse, RenderedEvent, EVENT_CATALOG, current_webhook_api_version, is_valid_event_filter, Payment, PaymentStatus, PaymentMethod, ScaStatus, ScaExemption, CardEntryMode, Invoice, InvoiceStatus, Payout, PayoutStatus, Topup, TopupStatus, AvailableBalance, BalanceAvailableData, ConfigurationChangeData, SecurityEvent, SecurityEventType, CryptoConversion, CryptoConversionStatus, CryptoSettlementAsset, CryptoRefund, CryptoRefundStatus, CryptoPayout, CryptoPayoutStatus, Chain, SubscriptionResponse, SubscriptionStatus, PendingSubscriptionUpdate}, errors::DefiantError, db::{Database, AuthenticatedKey}};
+use crate::shutdown::Shutdown;
+use super::archive_service;
+use super::event_renderer;
+use super::pagination::{self, Cursor, Page};
+
+/// Longest window a single replay may cover.
+const MAX_REPLAY_WINDOW_DAYS: i64 = 30;
+const REPLAY_BATCH_SIZE: i64 = 500;
+const REPLAY_POLL_INTERVAL_SECS: u64 = 5;
+/// A running job whose lease lapses is assumed dead and resumed from its cursor.
+const REPLAY_LEASE_SECS: i64 = 120;
+const POLL_MAX_TIMEOUT_SECS: u64 = 30;
+const POLL_DEFAULT_TIMEOUT_SECS: u64 = 25;
+const POLL_CHECK_INTERVAL_MS: u64 = 1000;
+const POLL_BATCH_SIZE: i64 = 100;
+/// Most events replayed to a resuming stream; further back, it pages through the list
+const RESUME_MAX_EVENTS: i64 = 1000;
+
+/// Every type reachable from an event payload, so catalog `$ref`s always resolve.
+#[derive(OpenApi)]
+#[openapi(components(schemas(
+    Payment, PaymentStatus, PaymentMethod, ScaStatus, ScaExemption, CardEntryMode,
+    Invoice, InvoiceStatus,
+    SubscriptionResponse, SubscriptionStatus, PendingSubscriptionUpdate,
+    Payout, PayoutStatus, Topup, TopupStatus, AvailableBalance, BalanceAvailableData,
+    ConfigurationChangeData, SecurityEvent, SecurityEventType,
+    CryptoConversion, CryptoConversionStatus, CryptoSettlementAsset,
+    CryptoRefund, CryptoRefundStatus, CryptoPayout, CryptoPayoutStatus, Chain,
+)))]
+struct EventPayloadSchemas;
+
+/// Describes every event type with the schema of its payload in the current version.
+pub fn event_catalog() -> Result<EventTypeListResponse, DefiantError> {
+    let components = serde_json::to_value(EventPayloadSchemas::openapi().components)
+        .map_err(|_| DefiantError::InternalError)?;
+    let data = EVENT_CATALOG
+        .iter()
+        .map(|spec| {
+            let (name, _) = (spec.payload)();
+            EventTypeDefinition {
+                event_type: spec.event_type,
+                description: spec.description,
+                schema: serde_json::json!({ "$ref": format!("#/components/schemas/{}", name) }),
+            }
+        })
+        .collect();
+    
+    Ok(EventTypeListResponse {
+        data,
+        api_version: current_webhook_api_version(),
+        components,
+        url: "/api/v1/event_types".into(),
+    })
+}
+
+/// Read side of the event log, plus replays of it. Events are written by
+/// `WebhookService::enqueue_event`, so every event a merchant can list here is also
+/// what their endpoints received.
+pub struct EventService {
+    db: Arc<Database>,
+}
+
+impl EventService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn get_event(&self, event_id: Uuid, api_key: &str) -> Result<Event, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let event = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT id, merchant_id, type AS event_type, data, api_version, livemode, created_at
+            FROM events
+            WHERE id = $1 AND merchant_id = $2
+            "#,
+            event_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        match event {
+            Some(event) => Ok(event),
+            None => Err(archive_service::not_found(pool, "event", event_id, merchant_id, "Event not found").await),
+        }
+    }
+    
+    pub async fn list_events(&self, query: EventListQuery, api_key: &str) -> Result<EventListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        
+        if let Some(event_type) = &query.event_type {
+            if !is_valid_event_filter(event_type) {
+                return Err(DefiantError::ValidationError(format!("Invalid event type filter: {}", event_type)));
+            }
+        }
+        
+        // Fetch one extra row to learn whether another page exists
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT id, merchant_id, type AS event_type, data, api_version, livemode, created_at
+            FROM events
+            WHERE merchant_id = $1 AND livemode = $6
+            AND (
+                $2::TEXT IS NULL OR $2 = '*' OR type = $2
+                OR ($2 LIKE '%.*' AND type LIKE left($2, -1) || '%')
+            )
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+            AND ($7::TIMESTAMPTZ IS NULL OR (created_at, id) < ($7, $8::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $5
+            "#,
+            merchant_id,
+            query.event_type,
+            query.created_gte,
+            query.created_lte,
+            limit + 1,
+            key.livemode,
+            after_created,
+            after_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let page = Page::new(events, limit, |event| Cursor::new(event.created_at, event.id));
+        
+        Ok(EventListResponse {
+            data: page.data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/events".into(),
+        })
+    }
+    
+    /// Waits up to `timeout` seconds for events after the cursor, for local forwarding
+    /// with `defiant listen`. Without a cursor, only events from now on are returned.
+    pub async fn poll_events(&self, query: EventPollQuery, api_key: &str) -> Result<EventPollResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let timeout = query.timeout.unwrap_or(POLL_DEFAULT_TIMEOUT_SECS).min(POLL_MAX_TIMEOUT_SECS);
+        
+        if let Some(event_type) = &query.event_type {
+            if !is_valid_event_filter(event_type) {
+                return Err(DefiantError::ValidationError(format!("Invalid event type filter: {}", event_type)));
+            }
+        }
+        
+        let (after_created, after_id) = match &query.cursor {
+            Some(cursor) => parse_poll_cursor(cursor)?,
+            None => (Utc::now(), Uuid::nil()),
+        };
+        let deadline = tokio::time::Instant::now() + StdDuration::from_secs(timeout);
+        
+        loop {
+            let events = sqlx::query_as!(
+                Event,
+                r#"
+                SELECT id, merchant_id, type AS event_type, data, api_version, livemode, created_at
+                FROM events
+                WHERE merchant_id = $1 AND livemode = $6
+                AND (created_at, id) > ($2, $3)
+                AND (
+                    $4::TEXT IS NULL OR $4 = '*' OR type = $4
+                    OR ($4 LIKE '%.*' AND type LIKE left($4, -1) || '%')
+                )
+                ORDER BY created_at, id
+                LIMIT $5
+                "#,
+                merchant_id,
+                after_created,
+                after_id,
+                query.event_type,
+                POLL_BATCH_SIZE,
+                key.livemode,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            if !events.is_empty() || tokio::time::Instant::now() >= deadline {
+                let cursor = match events.last() {
+                    Some(last) => poll_cursor(last.created_at, last.id),
+                    None => poll_cursor(after_created, after_id),
+                };
+                let data = events
+                    .iter()
+                    .map(|event| event_renderer::render(event, current_webhook_api_version()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                
+                return Ok(EventPollResponse { data, cursor });
+            }
+            
+            tokio::time::sleep(StdDuration::from_millis(POLL_CHECK_INTERVAL_MS)).await;
+        }
+    }
+    
+    /// Events after `last_event_id` that match `topics`, oldest first, for a stream
+    /// resuming after a disconnect. Clients that missed more than
+    /// `RESUME_MAX_EVENTS` are told to catch up through `list_events` instead.
+    pub async fn events_since(
+        &self,
+        key: &AuthenticatedKey,
+        last_event_id: Uuid,
+        topics: &[String],
+    ) -> Result<Vec<RenderedEvent>, DefiantError> {
+        let pool = self.db.pool_for_merchant(key.merchant_id).await?;
+        let last_created_at = sqlx::query_scalar!(
+            "SELECT created_at FROM events WHERE id = $1 AND merchant_id = $2 AND livemode = $3",
+            last_event_id,
+            key.merchant_id,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::ValidationError(format!("last_event_id: no event {}", last_event_id)))?;
+        
+        let events = sqlx::query_as!(
+            Event,
+            r#"
+            SELECT id, merchant_id, type AS event_type, data, api_version, livemode, created_at
+            FROM events
+            WHERE merchant_id = $1 AND livemode = $2
+            AND (created_at, id) > ($3, $4)
+            AND EXISTS (
+                SELECT 1 FROM unnest($5::TEXT[]) AS filter
+                WHERE filter = '*'
+                OR filter = type
+                OR (filter LIKE '%.*' AND type LIKE left(filter, -1) || '%')
+            )
+            ORDER BY created_at, id
+            LIMIT $6
+            "#,
+            key.merchant_id,
+            key.livemode,
+            last_created_at,
+            last_event_id,
+            topics,
+            RESUME_MAX_EVENTS + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        if events.len() as i64 > RESUME_MAX_EVENTS {
+            return Err(DefiantError::ValidationError(format!(
+                "More than {} events were missed since {}; list them from GET /api/v1/events",
+                RESUME_MAX_EVENTS,
+                last_event_id,
+            )));
+        }
+        
+        events
+            .iter()
+            .map(|event| event_renderer::render(event, current_webhook_api_version()))
+            .collect()
+    }
+    
+    /// Queues a background job that re-delivers every event in the window that the
+    /// endpoint is subscribed to. Progress is reported through `get_replay`.
+    pub async fn request_replay(
+        &self,
+        request: CreateEventReplayRequest,
+        api_key: &str,
+    ) -> Result<EventReplayResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        if request.created_gte >= request.created_lte {
+            return Err(DefiantError::ValidationError("created_gte must be before created_lte".into()));
+        }
+        if request.created_lte - request.created_gte > Duration::days(MAX_REPLAY_WINDOW_DAYS) {
+            return Err(DefiantError::ValidationError(format!(
+                "A replay can cover at most {} days",
+                MAX_REPLAY_WINDOW_DAYS
+            )));
+        }
+        
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"SELECT * FROM webhooks WHERE id = $1 AND merchant_id = $2"#,
+            request.webhook_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Webhook endpoint not found".into()))?;
+        
+        if !webhook.active {
+            return Err(DefiantError::Conflict("Webhook endpoint is disabled; re-enable it before replaying".into()));
+        }
+        
+        let in_flight = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM event_replays
+                WHERE webhook_id = $1 AND status IN ('pending', 'running')
+            )
+            "#,
+            webhook.id,
+        )
+        .fetch_one(pool)
+        .await?
+        .unwrap_or(false);
+        
+        if in_flight {
+            return Err(DefiantError::Conflict("A replay is already in progress for this endpoint".into()));
+        }
+        
+        let replay = sqlx::query_as!(
+            EventReplay,
+            r#"
+            INSERT INTO event_replays (merchant_id, webhook_id, created_gte, created_lte)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            merchant_id,
+            webhook.id,
+            request.created_gte,
+            request.created_lte,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Event replay {} requested for webhook {}", replay.id, webhook.id);
+        
+        Ok(EventReplayResponse { replay, delivered_events: 0, failed_events: 0 })
+    }
+    
+    pub async fn get_replay(&self, replay_id: Uuid, api_key: &str) -> Result<EventReplayResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let replay = sqlx::query_as!(
+            EventReplay,
+            r#"SELECT * FROM event_replays WHERE id = $1 AND merchant_id = $2"#,
+            replay_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Event replay not found".into()))?;
+        
+        let outcomes = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE status = 'succeeded') AS "delivered!",
+                COUNT(*) FILTER (WHERE status = 'dead_lettered') AS "failed!"
+            FROM webhook_deliveries
+            WHERE replay_id = $1
+            "#,
+            replay.id,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(EventReplayResponse {
+            replay,
+            delivered_events: outcomes.delivered,
+            failed_events: outcomes.failed,
+        })
+    }
+}
+
+/// Cursors are `<created_at micros>_<event id>`, matching the poll's sort order.
+fn poll_cursor(created_at: DateTime<Utc>, id: Uuid) -> String {
+    format!("{}_{}", created_at.timestamp_micros(), id)
+}
+
+fn parse_poll_cursor(cursor: &str) -> Result<(DateTime<Utc>, Uuid), DefiantError> {
+    let invalid = || DefiantError::ValidationError("Invalid cursor".into());
+    let (micros, id) = cursor.split_once('_').ok_or_else(invalid)?;
+    let created_at = micros.parse::<i64>().ok()
+        .and_then(DateTime::<Utc>::from_timestamp_micros)
+        .ok_or_else(invalid)?;
+    let id = id.parse::<Uuid>().map_err(|_| invalid())?;
+    
+    Ok((created_at, id))
+}
+
+/// Runs queued replays in the background, one job at a time per cluster.
+pub fn spawn_replay_worker(db: Arc<Database>, shutdown: &Shutdown) {
+    for pool in db.pools() {
+        let worker = ReplayWorker { pool: pool.clone() };
+        
+        shutdown.spawn(|mut shutdown| async move {
+            let mut interval = tokio::time::interval(StdDuration::from_secs(REPLAY_POLL_INTERVAL_SECS));
+            
+            while shutdown.tick(&mut interval).await {
+                loop {
+                    match worker.claim_next().await {
+                        Ok(Some(replay)) => worker.run(replay).await,
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("Failed to claim event replay: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+struct ReplayWorker {
+    pool: PgPool,
+}
+
+impl ReplayWorker {
+    async fn claim_next(&self) -> Result<Option<EventReplay>, DefiantError> {
+        let replay = sqlx::query_as!(
+            EventReplay,
+            r#"
+            UPDATE event_replays
+            SET status = $1, started_at = COALESCE(started_at, NOW()), locked_until = $2
+            WHERE id = (
+                SELECT id FROM event_replays
+                WHERE status = 'pending' OR (status = 'running' AND locked_until < NOW())
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            EventReplayStatus::Running as EventReplayStatus,
+            Utc::now() + Duration::seconds(REPLAY_LEASE_SECS),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(replay)
+    }
+    
+    async fn run(&self, replay: EventReplay) {
+        info!("Running event replay {}", replay.id);
+        
+        if let Err(e) = self.process(&replay).await {
+            error!("Event replay {} failed: {}", replay.id, e);
+            if let Err(e) = sqlx::query!(
+                r#"UPDATE event_replays SET status = $1, error = $2, locked_until = NULL WHERE id = $3"#,
+                EventReplayStatus::Failed as EventReplayStatus,
+                e.to_string(),
+                replay.id,
+            )
+            .execute(&self.pool)
+            .await
+            {
+                error!("Failed to record event replay {} failure: {}", replay.id, e);
+            }
+        }
+    }
+    
+    async fn process(&self, replay: &EventReplay) -> Result<(), DefiantError> {
+        let filters = sqlx::query_scalar!(
+            r#"SELECT events FROM webhooks WHERE id = $1"#,
+            replay.webhook_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        
+        // Counted once, on the first run, so resuming does not reset progress
+        if replay.cursor_created_at.is_none() {
+            sqlx::query!(
+                r#"
+                UPDATE event_replays
+                SET total_events = (
+                    SELECT COUNT(*) FROM events
+                    WHERE merchant_id = $2 AND created_at BETWEEN $3 AND $4
+                    AND livemode = (SELECT livemode FROM webhooks WHERE id = $6)
+                    AND EXISTS (
+                        SELECT 1 FROM unnest($5::TEXT[]) AS filter
+                        WHERE filter = '*' OR filter = type
+                        OR (filter LIKE '%.*' AND type LIKE left(filter, -1) || '%')
+                    )
+                )
+                WHERE id = $1
+                "#,
+                replay.id,
+                replay.merchant_id,
+                replay.created_gte,
+                replay.created_lte,
+                &filters,
+                replay.webhook_id,
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        
+        let mut cursor = replay.cursor_created_at.zip(replay.cursor_event_id);
+        
+        loop {
+            match self.queue_batch(replay, &filters, cursor).await? {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        
+        sqlx::query!(
+            r#"UPDATE event_replays SET status = $1, completed_at = NOW(), locked_until = NULL WHERE id = $2"#,
+            EventReplayStatus::Completed as EventReplayStatus,
+            replay.id,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        info!("Event replay {} completed", replay.id);
+        Ok(())
+    }
+    
+    /// Queues deliveries for the next batch after `cursor` and advances the stored cursor
+    /// in the same transaction. Returns the new cursor, or `None` once the window is exhausted.
+    async fn queue_batch(
+        &self,
+        replay: &EventReplay,
+        filters: &[String],
+        cursor: Option<(DateTime<Utc>, Uuid)>,
+    ) -> Result<Option<(DateTime<Utc>, Uuid)>, DefiantError> {
+        let mut tx = self.pool.begin().await?;
+        let (cursor_created_at, cursor_event_id) = cursor.unzip();
+        
+        let batch = sqlx::query!(
+            r#"
+            SELECT id, created_at AS "created_at!" FROM events
+            WHERE merchant_id = $1 AND created_at BETWEEN $2 AND $3
+            AND ($4::TIMESTAMPTZ IS NULL OR (created_at, id) > ($4, $5))
+            AND livemode = (SELECT livemode FROM webhooks WHERE id = $8)
+            AND EXISTS (
+                SELECT 1 FROM unnest($6::TEXT[]) AS filter
+                WHERE filter = '*' OR filter = type
+                OR (filter LIKE '%.*' AND type LIKE left(filter, -1) || '%')
+            )
+            ORDER BY created_at, id
+            LIMIT $7
+            "#,
+            replay.merchant_id,
+            replay.created_gte,
+            replay.created_lte,
+            cursor_created_at,
+            cursor_event_id,
+            filters,
+            REPLAY_BATCH_SIZE,
+            replay.webhook_id,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let last = match batch.last() {
+            Some(last) => (last.created_at, last.id),
+            None => return Ok(None),
+        };
+        let event_ids: Vec<Uuid> = batch.iter().map(|e| e.id).collect();
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (merchant_id, webhook_id, event_id, replay_id)
+            SELECT $1, $2, event_id, $3 FROM unnest($4::UUID[]) AS event_id
+            "#,
+            replay.merchant_id,
+            replay.webhook_id,
+            replay.id,
+            &event_ids,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"
+            UPDATE event_replays
+            SET queued_events = queued_events + $2, cursor_created_at = $3, cursor_event_id = $4, locked_until = $5
+            WHERE id = $1
+            "#,
+            replay.id,
+            event_ids.len() as i32,
+            last.0,
+            last.1,
+            Utc::now() + Duration::seconds(REPLAY_LEASE_SECS),
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        Ok(Some(last))
+    }
+}
@@ -0,0 +1,317 @@
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use actix_web::web::Bytes;
+use tokio::sync::mpsc::{self, error::SendTimeoutError};
+use uuid::Uuid;
+
+use crate::{models::{is_valid_event_filter, RenderedEvent, StreamClientMessage, StreamServerMessage, StreamNack, EVENT_CATALOG}, db::{Database, AuthenticatedKey}, errors::DefiantError, config::Streaming};
+use super::event_service::EventService;
+use super::event_router::EventRouter;
+
+/// Topics one connection may hold at once.
+pub const MAX_TOPICS_PER_CONNECTION: usize = 50;
+
+/// Frames buffered for a client before sends wait on it.
+const SSE_BUFFER_FRAMES: usize = 64;
+
+static OPEN_CONNECTIONS: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+static REAPED_CONNECTIONS: [AtomicU64; 2] = [AtomicU64::new(0), AtomicU64::new(0)];
+
+/// The scope an event family needs besides `events:read`, since each event carries
+/// the object it is about. `*` needs all of them.
+const FAMILY_SCOPES: &[(&str, &str)] = &[
+    ("payment", "payments:read"),
+    ("invoice", "invoices:read"),
+    ("subscription", "subscriptions:read"),
+    ("payout", "payouts:read"),
+    ("payout_schedule", "payouts:read"),
+    ("balance", "balance:read"),
+    ("topup", "balance:read"),
+    ("connected_account", "connect:read"),
+    ("transfer", "connect:read"),
+    ("api_key", "api_keys:read"),
+    ("webhook_endpoint", "webhooks:read"),
+    ("crypto_wallet", "crypto_wallets:read"),
+    ("crypto_settlement", "crypto_wallets:read"),
+    ("crypto_conversion", "crypto_wallets:read"),
+    ("crypto_refund", "crypto_wallets:read"),
+    ("crypto_payout", "crypto_wallets:read"),
+    ("trusted_source", "security:read"),
+    ("request_signing", "security:read"),
+    ("security_event", "security:read"),
+    ("risk_settings", "account:read"),
+    ("fraud_settings", "account:read"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transport {
+    WebSocket,
+    Sse,
+}
+
+impl Transport {
+    const ALL: [Transport; 2] = [Transport::WebSocket, Transport::Sse];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Transport::WebSocket => "websocket",
+            Transport::Sse => "sse",
+        }
+    }
+    
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Liveness of one open connection, counted in the connection metrics for as long as
+/// it is held.
+///
+/// The connection's loop sends a heartbeat every `heartbeat_interval` and calls
+/// `seen` whenever the client sends anything, replies to heartbeats included. Once
+/// `idle` reports true the loop closes the connection and calls `reap`.
+pub struct Liveness {
+    transport: Transport,
+    last_seen: Instant,
+    pub heartbeat_interval: Duration,
+    idle_timeout: Duration,
+}
+
+impl Liveness {
+    pub fn open(transport: Transport, settings: &Streaming) -> Self {
+        OPEN_CONNECTIONS[transport.index()].fetch_add(1, Ordering::Relaxed);
+        Self {
+            transport,
+            last_seen: Instant::now(),
+            heartbeat_interval: Duration::from_secs(settings.heartbeat_interval_secs),
+            idle_timeout: Duration::from_secs(settings.idle_timeout_secs),
+        }
+    }
+    
+    pub fn seen(&mut self) {
+        self.last_seen = Instant::now();
+    }
+    
+    pub fn idle(&self) -> bool {
+        self.last_seen.elapsed() >= self.idle_timeout
+    }
+    
+    pub fn reap(&self) {
+        REAPED_CONNECTIONS[self.transport.index()].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Liveness {
+    fn drop(&mut self) {
+        OPEN_CONNECTIONS[self.transport.index()].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Prometheus text for open and reaped stream connections, by transport.
+pub fn connection_metrics() -> String {
+    let mut out = String::new();
+    out.push_str("# HELP defiant_stream_connections Open event stream connections\n");
+    out.push_str("# TYPE defiant_stream_connections gauge\n");
+    for transport in Transport::ALL {
+        let open = OPEN_CONNECTIONS[transport.index()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "defiant_stream_connections{{transport=\"{}\"}} {}", transport.as_str(), open);
+    }
+    out.push_str("# HELP defiant_stream_connections_reaped_total Connections closed as dead after the idle timeout\n");
+    out.push_str("# TYPE defiant_stream_connections_reaped_total counter\n");
+    for transport in Transport::ALL {
+        let reaped = REAPED_CONNECTIONS[transport.index()].load(Ordering::Relaxed);
+        let _ = writeln!(out, "defiant_stream_connections_reaped_total{{transport=\"{}\"}} {}", transport.as_str(), reaped);
+    }
+    out
+}
+
+/// The topics one streaming connection is subscribed to, and so the event types it
+/// receives. Connections start with none.
+#[derive(Debug, Clone, Default)]
+pub struct TopicSubscriptions {
+    topics: BTreeSet<String>,
+}
+
+impl TopicSubscriptions {
+    /// Applies a client message, answering with the resulting topics or why it was
+    /// refused.
+    pub fn apply(&mut self, message: StreamClientMessage, key: &AuthenticatedKey) -> StreamServerMessage {
+        let applied = match message {
+            StreamClientMessage::Subscribe(topics) => self.subscribe(&topics, key),
+            StreamClientMessage::Unsubscribe(topics) => {
+                self.unsubscribe(&topics);
+                Ok(())
+            }
+        };
+        
+        match applied {
+            Ok(()) => StreamServerMessage::Ack { topics: self.topics() },
+            Err(nack) => StreamServerMessage::Nack(nack),
+        }
+    }
+    
+    /// Adds every topic or, if any is invalid, unknown or beyond the key's scopes,
+    /// none of them.
+    pub fn subscribe(&mut self, topics: &[String], key: &AuthenticatedKey) -> Result<(), StreamNack> {
+        for topic in topics {
+            check_topic(topic, key)?;
+        }
+        
+        let added: BTreeSet<&String> = topics.iter().filter(|topic| !self.topics.contains(*topic)).collect();
+        if self.topics.len() + added.len() > MAX_TOPICS_PER_CONNECTION {
+            return Err(StreamNack {
+                topic: None,
+                code: "too_many_topics",
+                message: format!("A connection can subscribe to at most {} topics", MAX_TOPICS_PER_CONNECTION),
+            });
+        }
+        
+        self.topics.extend(topics.iter().cloned());
+        Ok(())
+    }
+    
+    /// Topics the connection is not subscribed to are ignored.
+    pub fn unsubscribe(&mut self, topics: &[String]) {
+        for topic in topics {
+            self.topics.remove(topic);
+        }
+    }
+    
+    pub fn topics(&self) -> Vec<String> {
+        self.topics.iter().cloned().collect()
+    }
+    
+    /// Whether events of `event_type` go out on this connection, matched as webhook
+    /// endpoints match their filters.
+    pub fn matches(&self, event_type: &str) -> bool {
+        self.topics.iter().any(|topic| {
+            topic == "*"
+                || topic == event_type
+                || topic.strip_suffix('*').is_some_and(|family| event_type.starts_with(family))
+        })
+    }
+}
+
+/// Starts a Server-Sent Events stream for `key`'s merchant and mode: first the events
+/// after `last_event_id`, if given, then live ones as they are published to the
+/// merchant's channel, each as an `id`/`event`/`data` frame. A comment frame goes out
+/// every heartbeat interval so proxies keep the connection open, and a client that
+/// reads nothing for the idle timeout is dropped.
+///
+/// Live events are subscribed to before the replay is read, so events published in
+/// between are sent once, from the replay.
+pub async fn sse(
+    db: Arc<Database>,
+    router: &Arc<EventRouter>,
+    settings: &Streaming,
+    key: AuthenticatedKey,
+    subscriptions: TopicSubscriptions,
+    last_event_id: Option<Uuid>,
+) -> Result<mpsc::Receiver<Bytes>, DefiantError> {
+    let mut live = router.subscribe(key.merchant_id).await;
+    
+    let replayed = match last_event_id {
+        Some(last_event_id) => {
+            EventService::new(db)
+                .events_since(&key, last_event_id, &subscriptions.topics())
+                .await?
+        }
+        None => Vec::new(),
+    };
+    
+    let (tx, rx) = mpsc::channel(SSE_BUFFER_FRAMES);
+    let liveness = Liveness::open(Transport::Sse, settings);
+    tokio::spawn(async move {
+        let replayed_ids: HashSet<Uuid> = replayed.iter().map(|event| event.id).collect();
+        for event in &replayed {
+            if !deliver(&tx, sse_frame(event), &liveness).await {
+                return;
+            }
+        }
+        
+        let mut heartbeat = tokio::time::interval(liveness.heartbeat_interval);
+        heartbeat.tick().await;
+        loop {
+            let frame = tokio::select! {
+                event = live.recv() => {
+                    // Ending the stream makes the client reconnect with its last event
+                    // id and be replayed what it missed
+                    let Some(event) = event else {
+                        return;
+                    };
+                    if event.livemode != key.livemode
+                        || !subscriptions.matches(&event.event_type)
+                        || replayed_ids.contains(&event.id)
+                    {
+                        continue;
+                    }
+                    sse_frame(&event)
+                }
+                _ = heartbeat.tick() => Bytes::from_static(b": heartbeat\n\n"),
+            };
+            
+            if !deliver(&tx, frame, &liveness).await {
+                return;
+            }
+        }
+    });
+    
+    Ok(rx)
+}
+
+/// False once the stream should end: the client is gone, or stopped reading and was
+/// reaped.
+async fn deliver(tx: &mpsc::Sender<Bytes>, frame: Bytes, liveness: &Liveness) -> bool {
+    match tx.send_timeout(frame, liveness.idle_timeout).await {
+        Ok(()) => true,
+        Err(SendTimeoutError::Timeout(_)) => {
+            liveness.reap();
+            false
+        }
+        Err(SendTimeoutError::Closed(_)) => false,
+    }
+}
+
+fn sse_frame(event: &RenderedEvent) -> Bytes {
+    Bytes::from(format!(
+        "id: {}\nevent: {}\ndata: {}\n\n",
+        event.id,
+        event.event_type,
+        serde_json::json!(event),
+    ))
+}
+
+impl From<StreamNack> for DefiantError {
+    fn from(nack: StreamNack) -> Self {
+        match nack.code {
+            "forbidden" => DefiantError::AuthorizationError(nack.message),
+            _ => DefiantError::ValidationError(nack.message),
+        }
+    }
+}
+
+fn check_topic(topic: &str, key: &AuthenticatedKey) -> Result<(), StreamNack> {
+    let refuse = |code, message| StreamNack { topic: Some(topic.to_string()), code, message };
+    if !is_valid_event_filter(topic) {
+        return Err(refuse("invalid_topic", format!("'{}' is not a valid event type or wildcard", topic)));
+    }
+    
+    let required: Vec<&str> = if topic == "*" {
+        FAMILY_SCOPES.iter().map(|(_, scope)| *scope).collect()
+    } else {
+        let family = topic.split('.').next().unwrap_or(topic);
+        let known = topic.ends_with(".*") || EVENT_CATALOG.iter().any(|spec| spec.event_type == topic);
+        match FAMILY_SCOPES.iter().find(|(name, _)| *name == family) {
+            Some((_, scope)) if known => vec![*scope],
+            _ => return Err(refuse("unknown_topic", format!("'{}' matches no event type", topic))),
+        }
+    };
+    
+    match std::iter::once("events:read").chain(required).find(|scope| !key.grants(scope)) {
+        Some(scope) => Err(refuse("forbidden", format!("Subscribing to '{}' requires the '{}' scope", topic, scope))),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Duration as StdDuration;
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::{models::{GeoSignals, PaymentMethod}, errors::DefiantError, config::{Config, VelocityLimits, VelocityThresholds}};
+use super::fx_service;
+
+const HOUR_SECS: i64 = 3600;
+const DAY_SECS: i64 = 24 * HOUR_SECS;
+/// Scoring runs on the payment path; a slow scorer is replaced by the heuristics.
+const RISK_SCORING_TIMEOUT_MS: u64 = 2000;
+
+/// What a payment attempt is counted against.
+pub struct VelocitySubject<'a> {
+    pub merchant_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub card_fingerprint: Option<&'a str>,
+    pub client_ip: Option<&'a str>,
+}
+
+pub enum VelocityOutcome {
+    Allow,
+    /// The payment goes ahead flagged with the tripped counter
+    Review(String),
+    /// The payment is declined, naming the tripped counter
+    Block(String),
+}
+
+/// Records the attempt against each of its counters and checks them against
+/// `limits`; a counter over its block threshold wins over any review. Every attempt
+/// counts, declined ones included, so a card being tested keeps tripping the limit
+/// until the window passes.
+///
+/// Counters are sorted sets of attempt timestamps, one per subject, holding a day of
+/// history; both windows are read from the same set. Redis being unavailable lets the
+/// payment through rather than declining everyone.
+pub async fn check_velocity(
+    redis: &ConnectionManager,
+    limits: &VelocityLimits,
+    subject: &VelocitySubject<'_>,
+) -> VelocityOutcome {
+    let merchant_id = subject.merchant_id.to_string();
+    let customer_id = subject.customer_id.map(|id| id.to_string());
+    let counters = [
+        ("card", subject.card_fingerprint, &limits.card),
+        ("ip", subject.client_ip, &limits.ip),
+        ("customer", customer_id.as_deref(), &limits.customer),
+        ("merchant", Some(merchant_id.as_str()), &limits.merchant),
+    ];
+    
+    let attempt = Uuid::new_v4().to_string();
+    let now = Utc::now().timestamp();
+    let mut block = None;
+    let mut review = None;
+    
+    for (dimension, value, thresholds) in counters {
+        let value = match value {
+            Some(value) if is_checked(thresholds) => value,
+            _ => continue,
+        };
+        
+        let key = format!("velocity:{}:{}", dimension, value);
+        let counts: Result<(i64, i64), _> = redis::pipe()
+            .atomic()
+            .cmd("ZREMRANGEBYSCORE").arg(&key).arg("-inf").arg(now - DAY_SECS).ignore()
+            .cmd("ZADD").arg(&key).arg(now).arg(&attempt).ignore()
+            .cmd("EXPIRE").arg(&key).arg(DAY_SECS).ignore()
+            .cmd("ZCOUNT").arg(&key).arg(now - HOUR_SECS).arg("+inf")
+            .cmd("ZCARD").arg(&key)
+            .query_async(&mut redis.clone())
+            .await;
+        
+        let (hourly, daily) = match counts {
+            Ok(counts) => counts,
+            Err(e) => {
+                error!("Velocity counters unavailable, skipping: {}", e);
+                return VelocityOutcome::Allow;
+            }
+        };
+        
+        let windows = [
+            ("1h", hourly, thresholds.hourly_block, thresholds.hourly_review),
+            ("24h", daily, thresholds.daily_block, thresholds.daily_review),
+        ];
+        for (window, count, block_at, review_at) in windows {
+            let tripped = |limit: Option<u32>| {
+                limit
+                    .filter(|limit| count > i64::from(*limit))
+                    .map(|limit| describe(dimension, window, count, limit))
+            };
+            block = block.or_else(|| tripped(block_at));
+            review = review.or_else(|| tripped(review_at));
+        }
+    }
+    
+    match (block, review) {
+        (Some(reason), _) => VelocityOutcome::Block(reason),
+        (None, Some(reason)) => VelocityOutcome::Review(reason),
+        (None, None) => VelocityOutcome::Allow,
+    }
+}
+
+/// Forgets the recent attempts counted against a payment's card, address and customer,
+/// once the merchant has vouched for it.
+pub async fn reset_velocity(redis: &ConnectionManager, subject: &VelocitySubject<'_>) {
+    let customer_id = subject.customer_id.map(|id| id.to_string());
+    let keys: Vec<String> = [
+        ("card", subject.card_fingerprint),
+        ("ip", subject.client_ip),
+        ("customer", customer_id.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(dimension, value)| value.map(|value| format!("velocity:{}:{}", dimension, value)))
+    .collect();
+    if keys.is_empty() {
+        return;
+    }
+    
+    if let Err(e) = redis::cmd("DEL").arg(&keys).query_async::<_, ()>(&mut redis.clone()).await {
+        error!("Failed to reset velocity counters: {}", e);
+    }
+}
+
+/// The blocklist entry, if any, the merchant has for the attempt's card, address or
+/// customer.
+pub async fn blocklisted(
+    tx: &mut Transaction<'_, Postgres>,
+    subject: &VelocitySubject<'_>,
+) -> Result<Option<String>, DefiantError> {
+    let kind = sqlx::query_scalar!(
+        r#"
+        SELECT kind::text AS "kind!" FROM fraud_blocklist
+        WHERE merchant_id = $1
+        AND (
+            (kind = 'card_fingerprint' AND value = $2)
+            OR (kind = 'ip_address' AND value = $3)
+            OR (kind = 'customer' AND value = $4)
+        )
+        LIMIT 1
+        "#,
+        subject.merchant_id,
+        subject.card_fingerprint,
+        subject.client_ip,
+        subject.customer_id.map(|id| id.to_string()),
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    Ok(kind.map(|kind| format!("blocklist_{}", kind)))
+}
+
+fn is_checked(thresholds: &VelocityThresholds) -> bool {
+    thresholds.hourly_review.is_some()
+        || thresholds.hourly_block.is_some()
+        || thresholds.daily_review.is_some()
+        || thresholds.daily_block.is_some()
+}
+
+fn describe(dimension: &str, window: &str, count: i64, limit: u32) -> String {
+    format!("velocity_{}_{}: {} payments in {} (limit {})", dimension, window, count, window, limit)
+}
+
+/// What a risk scorer sees of a payment attempt.
+#[derive(Debug, Serialize)]
+pub struct RiskContext<'a> {
+    pub merchant_id: Uuid,
+    pub amount: i64,
+    pub currency: &'a str,
+    pub payment_method: &'a PaymentMethod,
+    pub customer_id: Option<Uuid>,
+    pub card_fingerprint: Option<&'a str>,
+    pub client_ip: Option<&'a str>,
+    pub off_session: bool,
+    /// The velocity counter that flagged the attempt for review, if any
+    pub velocity_review: Option<&'a str>,
+    /// Absent without a client IP or a GeoIP provider
+    pub geo: Option<&'a GeoSignals>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskAssessment {
+    /// 0 to 100, higher is riskier
+    pub score: i16,
+    pub reasons: Vec<String>,
+}
+
+/// Scores a payment attempt before it is recorded. The score is kept on the payment for
+/// reporting; declining and review are left to the velocity checks.
+pub trait RiskScorer: Send + Sync {
+    fn score<'a>(&'a self, context: &'a RiskContext<'a>) -> BoxFuture<'a, Result<RiskAssessment, DefiantError>>;
+}
+
+/// The scorer for this deployment: the external service when one is configured,
+/// otherwise the built-in heuristics.
+pub fn risk_scorer(config: &Config) -> Box<dyn RiskScorer> {
+    match &config.risk_scoring_url {
+        Some(url) => Box::new(HttpRiskScorer {
+            url: url.clone(),
+            api_key: config.risk_scoring_api_key.clone(),
+        }),
+        None => Box::new(HeuristicRiskScorer),
+    }
+}
+
+/// Additive rules over what the attempt itself shows.
+pub struct HeuristicRiskScorer;
+
+impl RiskScorer for HeuristicRiskScorer {
+    fn score<'a>(&'a self, context: &'a RiskContext<'a>) -> BoxFuture<'a, Result<RiskAssessment, DefiantError>> {
+        Box::pin(async move {
+            let mut score = 0i16;
+            let mut reasons = Vec::new();
+            let mut add = |points: i16, reason: &str| {
+                score += points;
+                reasons.push(reason.to_string());
+            };
+            
+            // In major units, so thresholds mean the same in every currency
+            let exponent = fx_service::currency_exponent(context.currency).unwrap_or(2);
+            let major_units = context.amount / 10i64.pow(exponent);
+            if major_units >= 5_000 {
+                add(25, "large_amount");
+            } else if major_units >= 1_000 {
+                add(10, "elevated_amount");
+            }
+            
+            if let Some(counter) = context.velocity_review {
+                add(40, counter.split(':').next().unwrap_or("velocity"));
+            }
+            if context.customer_id.is_none() {
+                add(10, "no_customer");
+            }
+            if !context.off_session && context.client_ip.is_none() {
+                add(10, "no_client_ip");
+            }
+            if matches!(context.payment_method, PaymentMethod::Card) && context.card_fingerprint.is_none() {
+                add(15, "no_card_details");
+            }
+            if let Some(geo) = context.geo {
+                if geo.ip_is_proxy {
+                    add(20, "anonymizing_proxy");
+                }
+                if geo.billing_country_mismatch {
+                    add(15, "ip_billing_country_mismatch");
+                }
+                if geo.card_country_mismatch {
+                    add(10, "ip_card_country_mismatch");
+                }
+            }
+            
+            Ok(RiskAssessment { score: score.min(100), reasons })
+        })
+    }
+}
+
+/// Sends the attempt as JSON to an external scoring service, which answers with a
+/// `RiskAssessment`.
+pub struct HttpRiskScorer {
+    url: String,
+    api_key: Option<String>,
+}
+
+impl RiskScorer for HttpRiskScorer {
+    fn score<'a>(&'a self, context: &'a RiskContext<'a>) -> BoxFuture<'a, Result<RiskAssessment, DefiantError>> {
+        Box::pin(async move {
+            let client = reqwest::Client::builder()
+                .timeout(StdDuration::from_millis(RISK_SCORING_TIMEOUT_MS))
+                .build()
+                .map_err(|_| DefiantError::InternalError)?;
+            
+            let request = client.post(&self.url).json(context);
+            let request = match &self.api_key {
+                Some(key) => request.bearer_auth(key),
+                None => request,
+            };
+            
+            let assessment: RiskAssessment = request
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| DefiantError::PaymentError(format!("Risk scoring request failed: {}", e)))?
+                .json()
+                .await
+                .map_err(|e| DefiantError::PaymentError(format!("Risk scorer returned an unreadable score: {}", e)))?;
+            
+            Ok(RiskAssessment { score: assessment.score.clamp(0, 100), reasons: assessment.reasons })
+        })
+    }
+}
+
+/// Scores with `scorer`, falling back to the heuristics when it fails, so a scoring
+/// outage never holds up payments.
+pub async fn assess_risk(scorer: &dyn RiskScorer, context: &RiskContext<'_>) -> RiskAssessment {
+    match scorer.score(context).await {
+        Ok(assessment) => assessment,
+        Err(e) => {
+            warn!("Risk scoring failed, using heuristics: {}", e);
+            let mut assessment = HeuristicRiskScorer
+                .score(context)
+                .await
+                .unwrap_or(RiskAssessment { score: 0, reasons: Vec::new() });
+            assessment.reasons.push("scorer_unavailable".into());
+            assessment
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use redis::aio::ConnectionManager;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{Payment, FraudLabel, FraudLabelRecord, BlocklistKind, LabelPaymentRequest, FraudRuleStats, FraudRuleStatsResponse}, errors::DefiantError, db::Database};
+use super::fraud_detection::{reset_velocity, VelocitySubject};
+
+/// Merchants' verdicts on their payments, and what they teach the fraud checks.
+///
+/// A payment labeled fraudulent blocklists its card, address and customer for the
+/// merchant; relabeling it safe lifts those entries and forgets the recent attempts
+/// counted against them, so the customer is not held back by velocity limits. Labels
+/// keep the rules that fired at the time, which is what per-rule stats are drawn from.
+pub struct FraudFeedbackService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+}
+
+impl FraudFeedbackService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
+        Self { db, redis }
+    }
+    
+    pub async fn label_payment(
+        &self,
+        payment_id: Uuid,
+        label: FraudLabel,
+        request: LabelPaymentRequest,
+        api_key: &str,
+    ) -> Result<FraudLabelRecord, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"SELECT * FROM payments WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            payment_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment not found".into()))?;
+        
+        let record = sqlx::query_as!(
+            FraudLabelRecord,
+            r#"
+            INSERT INTO fraud_labels (merchant_id, payment_id, label, rules, risk_score, amount, currency, note)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (payment_id) DO UPDATE
+            SET label = EXCLUDED.label,
+                note = EXCLUDED.note
+            RETURNING *
+            "#,
+            merchant_id,
+            payment.id,
+            label as FraudLabel,
+            &fired_rules(&payment),
+            payment.risk_score,
+            payment.amount,
+            payment.currency,
+            request.note,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        match label {
+            FraudLabel::Fraudulent => self.blocklist(&mut tx, &payment).await?,
+            FraudLabel::Safe => {
+                sqlx::query!(
+                    r#"DELETE FROM fraud_blocklist WHERE merchant_id = $1 AND source_payment_id = $2"#,
+                    merchant_id,
+                    payment.id,
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+        
+        tx.commit().await?;
+        
+        if label == FraudLabel::Safe {
+            let subject = VelocitySubject {
+                merchant_id,
+                customer_id: Some(payment.customer_id),
+                card_fingerprint: payment.card_fingerprint.as_deref(),
+                client_ip: payment.client_ip.as_deref(),
+            };
+            reset_velocity(&self.redis, &subject).await;
+        }
+        
+        info!("Payment {} labeled {:?}", payment.id, label);
+        
+        Ok(record)
+    }
+    
+    /// Precision and recall of every rule that has fired on a labeled payment.
+    pub async fn rule_stats(&self, api_key: &str) -> Result<FraudRuleStatsResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let totals = sqlx::query!(
+            r#"
+            SELECT
+                COUNT(*) FILTER (WHERE label = 'fraudulent') AS "fraudulent!",
+                COUNT(*) FILTER (WHERE label = 'safe') AS "safe!"
+            FROM fraud_labels
+            WHERE merchant_id = $1
+            "#,
+            merchant_id,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        let rows = sqlx::query!(
+            r#"
+            WITH labeled AS (
+                SELECT label, rules FROM fraud_labels WHERE merchant_id = $1
+            ),
+            fired AS (
+                SELECT DISTINCT unnest(rules) AS rule FROM labeled
+            )
+            SELECT
+                f.rule AS "rule!",
+                COUNT(*) FILTER (WHERE f.rule = ANY(l.rules) AND l.label = 'fraudulent') AS "true_positives!",
+                COUNT(*) FILTER (WHERE f.rule = ANY(l.rules) AND l.label = 'safe') AS "false_positives!",
+                COUNT(*) FILTER (WHERE NOT f.rule = ANY(l.rules) AND l.label = 'fraudulent') AS "false_negatives!"
+            FROM fired f
+            CROSS JOIN labeled l
+            GROUP BY f.rule
+            ORDER BY f.rule
+            "#,
+            merchant_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let data = rows
+            .into_iter()
+            .map(|row| FraudRuleStats {
+                precision: ratio(row.true_positives, row.true_positives + row.false_positives),
+                recall: ratio(row.true_positives, row.true_positives + row.false_negatives),
+                rule: row.rule,
+                true_positives: row.true_positives,
+                false_positives: row.false_positives,
+                false_negatives: row.false_negatives,
+            })
+            .collect();
+        
+        Ok(FraudRuleStatsResponse {
+            labeled_fraudulent: totals.fraudulent,
+            labeled_safe: totals.safe,
+            data,
+        })
+    }
+    
+    async fn blocklist(&self, tx: &mut Transaction<'_, Postgres>, payment: &Payment) -> Result<(), DefiantError> {
+        let entries = [
+            (BlocklistKind::CardFingerprint, payment.card_fingerprint.clone()),
+            (BlocklistKind::IpAddress, payment.client_ip.clone()),
+            (BlocklistKind::Customer, Some(payment.customer_id.to_string())),
+        ];
+        
+        for (kind, value) in entries {
+            let value = match value {
+                Some(value) => value,
+                None => continue,
+            };
+            sqlx::query!(
+                r#"
+                INSERT INTO fraud_blocklist (merchant_id, kind, value, source_payment_id)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (merchant_id, kind, value) DO UPDATE
+                SET source_payment_id = EXCLUDED.source_payment_id
+                "#,
+                payment.merchant_id,
+                kind as BlocklistKind,
+                value,
+                payment.id,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        
+        Ok(())
+    }
+}
+
+/// Risk reasons, plus the velocity counter named at the front of a review reason.
+fn fired_rules(payment: &Payment) -> Vec<String> {
+    let mut rules = payment.risk_reasons.clone();
+    if let Some(counter) = payment.review_reason.as_deref().and_then(|reason| reason.split(':').next()) {
+        if !rules.iter().any(|rule| rule == counter) {
+            rules.push(counter.to_string());
+        }
+    }
+    rules
+}
+
+fn ratio(numerator: i64, denominator: i64) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
+}
@@ -0,0 +1,199 @@
+use std::sync::Arc;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use uuid::Uuid;
+use tracing::{info, warn};
+
+use crate::{models::{FraudSettings, UpdateFraudSettingsRequest, ConfigurationChangeData, FRAUD_SETTINGS_UPDATED}, errors::DefiantError, db::Database};
+use super::fx_service::{self, FxService};
+use super::webhook_service::WebhookService;
+
+/// Every payment reads them, and updates clear the cached copy.
+const SETTINGS_CACHE_SECS: u64 = 300;
+
+/// Per-merchant switches and limits for fraud checks.
+///
+/// Settings are read on every payment, so they are cached in Redis and the cache is
+/// cleared whenever the merchant saves new ones; when Redis is unavailable they are
+/// read from the database instead.
+pub struct FraudSettingsService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+}
+
+impl FraudSettingsService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
+        Self { db, redis }
+    }
+    
+    pub async fn get_settings(&self, api_key: &str) -> Result<FraudSettings, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        self.settings(merchant_id).await
+    }
+    
+    pub async fn update_settings(
+        &self,
+        request: UpdateFraudSettingsRequest,
+        api_key: &str,
+    ) -> Result<FraudSettings, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let currency = request.currency.as_deref().unwrap_or("USD").to_uppercase();
+        fx_service::ensure_supported_currency(&currency)?;
+        
+        let mut blocked_countries = Vec::with_capacity(request.blocked_countries.len());
+        for country in &request.blocked_countries {
+            if country.len() != 2 || !country.chars().all(|c| c.is_ascii_alphabetic()) {
+                return Err(DefiantError::ValidationError(format!("Invalid country code: {}", country)));
+            }
+            let country = country.to_uppercase();
+            if !blocked_countries.contains(&country) {
+                blocked_countries.push(country);
+            }
+        }
+        
+        let settings = sqlx::query_as!(
+            FraudSettings,
+            r#"
+            INSERT INTO fraud_settings (
+                merchant_id, velocity_checks_enabled, max_amount_without_review,
+                three_ds_amount_threshold, currency, blocked_countries
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (merchant_id) DO UPDATE
+            SET velocity_checks_enabled = EXCLUDED.velocity_checks_enabled,
+                max_amount_without_review = EXCLUDED.max_amount_without_review,
+                three_ds_amount_threshold = EXCLUDED.three_ds_amount_threshold,
+                currency = EXCLUDED.currency,
+                blocked_countries = EXCLUDED.blocked_countries
+            RETURNING *
+            "#,
+            merchant_id,
+            request.velocity_checks_enabled.unwrap_or(true),
+            request.max_amount_without_review,
+            request.three_ds_amount_threshold,
+            currency,
+            &blocked_countries,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        if let Err(e) = redis::cmd("DEL")
+            .arg(cache_key(merchant_id))
+            .query_async::<_, ()>(&mut self.redis.as_ref().clone())
+            .await
+        {
+            // Payments keep the old settings until the cached copy expires
+            warn!("Failed to clear cached fraud settings for merchant {}: {}", merchant_id, e);
+        }
+        
+        info!(
+            "Merchant {} updated fraud settings: velocity checks {}, {} blocked countries",
+            merchant_id, settings.velocity_checks_enabled, settings.blocked_countries.len(),
+        );
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                FRAUD_SETTINGS_UPDATED,
+                ConfigurationChangeData::new(
+                    "fraud_settings",
+                    merchant_id,
+                    vec![
+                        "velocity_checks_enabled".into(),
+                        "max_amount_without_review".into(),
+                        "three_ds_amount_threshold".into(),
+                        "currency".into(),
+                        "blocked_countries".into(),
+                    ],
+                ),
+                api_key,
+            )
+            .await;
+        
+        Ok(settings)
+    }
+    
+    /// The merchant's settings, or the defaults when it has never changed them.
+    pub async fn settings(&self, merchant_id: Uuid) -> Result<FraudSettings, DefiantError> {
+        match redis::cmd("GET")
+            .arg(cache_key(merchant_id))
+            .query_async::<_, Option<String>>(&mut self.redis.as_ref().clone())
+            .await
+        {
+            Ok(Some(cached)) => {
+                if let Ok(settings) = serde_json::from_str(&cached) {
+                    return Ok(settings);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached fraud settings: {}", e),
+        }
+        
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let settings = sqlx::query_as!(
+            FraudSettings,
+            r#"SELECT * FROM fraud_settings WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .unwrap_or(FraudSettings {
+            merchant_id,
+            velocity_checks_enabled: true,
+            max_amount_without_review: None,
+            three_ds_amount_threshold: None,
+            currency: "USD".into(),
+            blocked_countries: Vec::new(),
+            created_at: None,
+            updated_at: None,
+        });
+        
+        if let Ok(cached) = serde_json::to_string(&settings) {
+            if let Err(e) = redis::cmd("SET")
+                .arg(cache_key(merchant_id))
+                .arg(cached)
+                .arg("EX")
+                .arg(SETTINGS_CACHE_SECS)
+                .query_async::<_, ()>(&mut self.redis.as_ref().clone())
+                .await
+            {
+                warn!("Failed to cache fraud settings: {}", e);
+            }
+        }
+        
+        Ok(settings)
+    }
+    
+    /// `amount` in the currency of the settings' limits, or `None` when there is no
+    /// rate to convert it at.
+    pub async fn amount_in_settings_currency(
+        &self,
+        settings: &FraudSettings,
+        amount: i64,
+        currency: &str,
+    ) -> Option<i64> {
+        if currency.eq_ignore_ascii_case(&settings.currency) {
+            return Some(amount);
+        }
+        
+        let converted = match FxService::new(self.db.clone())
+            .get_rate(currency, &settings.currency, Utc::now().date_naive())
+            .await
+        {
+            Ok((rate, _)) => fx_service::convert_amount(amount, currency, &settings.currency, rate),
+            Err(e) => Err(e),
+        };
+        match converted {
+            Ok(converted) => Some(converted),
+            Err(e) => {
+                warn!("No {} rate for fraud limits ({})", settings.currency, e);
+                None
+            }
+        }
+    }
+}
+
+fn cache_key(merchant_id: Uuid) -> String {
+    format!("fraud_settings:{}", merchant_id)
+}
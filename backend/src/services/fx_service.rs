@@ -0,0 +1,151 @@
+use std::sync::Arc;
+use chrono::NaiveDate;
+use rust_decimal::{Decimal, RoundingStrategy, prelude::ToPrimitive};
+
+use crate::{models::{ExchangeRate, FxRate}, errors::DefiantError, db::Database};
+
+/// ISO 4217 currencies the platform accepts, with their minor-unit exponent.
+pub const SUPPORTED_CURRENCIES: &[(&str, u32)] = &[
+    ("USD", 2), ("EUR", 2), ("GBP", 2), ("CAD", 2), ("AUD", 2),
+    ("CHF", 2), ("SEK", 2), ("NOK", 2), ("DKK", 2), ("PLN", 2),
+    ("NGN", 2), ("ZAR", 2), ("KES", 2), ("INR", 2), ("SGD", 2),
+    ("HKD", 2), ("MXN", 2), ("BRL", 2), ("JPY", 0), ("KRW", 0),
+];
+
+pub fn currency_exponent(currency: &str) -> Option<u32> {
+    SUPPORTED_CURRENCIES
+        .iter()
+        .find(|(code, _)| code.eq_ignore_ascii_case(currency))
+        .map(|(_, exponent)| *exponent)
+}
+
+pub fn ensure_supported_currency(currency: &str) -> Result<(), DefiantError> {
+    currency_exponent(currency)
+        .map(|_| ())
+        .ok_or_else(|| DefiantError::ValidationError(format!("Unsupported currency: {}", currency)))
+}
+
+/// Formats minor units for display, e.g. `1999, "USD"` -> `"19.99 USD"`.
+pub fn format_amount(amount: i64, currency: &str) -> String {
+    let exponent = currency_exponent(currency).unwrap_or(2);
+    format!("{} {}", Decimal::new(amount, exponent), currency.to_uppercase())
+}
+
+/// Converts an amount in minor units of `from` into minor units of `to`,
+/// accounting for differing exponents and rounding half away from zero.
+pub fn convert_amount(amount: i64, from: &str, to: &str, rate: Decimal) -> Result<i64, DefiantError> {
+    let from_exponent = currency_exponent(from)
+        .ok_or_else(|| DefiantError::ValidationError(format!("Unsupported currency: {}", from)))?;
+    let to_exponent = currency_exponent(to)
+        .ok_or_else(|| DefiantError::ValidationError(format!("Unsupported currency: {}", to)))?;
+    
+    let out_of_range = || DefiantError::ValidationError("Converted amount is out of range".into());
+    let major = Decimal::new(amount, from_exponent);
+    let converted = major
+        .checked_mul(rate)
+        .and_then(|converted| converted.checked_mul(Decimal::from(10i64.pow(to_exponent))))
+        .ok_or_else(out_of_range)?;
+    
+    converted
+        .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+        .to_i64()
+        .ok_or_else(out_of_range)
+}
+
+pub struct FxService {
+    db: Arc<Database>,
+}
+
+impl FxService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Returns the most recent stored rate for `from -> to` on or before `on`.
+    /// Falls back to the inverse pair when only that direction is stored.
+    pub async fn get_rate(&self, from: &str, to: &str, on: NaiveDate) -> Result<(Decimal, NaiveDate), DefiantError> {
+        self.rate_on(from, to, on)
+            .await?
+            .map(|rate| (rate.rate, rate.rate_date))
+            .ok_or_else(|| DefiantError::ValidationError(format!(
+                "No exchange rate available for {} to {} on {}",
+                from.to_uppercase(), to.to_uppercase(), on
+            )))
+    }
+    
+    /// Like `get_rate`, with where the rate came from.
+    pub async fn rate_on(&self, from: &str, to: &str, on: NaiveDate) -> Result<Option<FxRate>, DefiantError> {
+        let from = from.to_uppercase();
+        let to = to.to_uppercase();
+        
+        if from == to {
+            return Ok(Some(FxRate {
+                from,
+                to,
+                rate: Decimal::ONE,
+                date: on,
+                rate_date: on,
+                source: "identity".into(),
+                inverted: false,
+            }));
+        }
+        
+        if let Some(rate) = self.find_rate(&from, &to, on).await? {
+            return Ok(Some(FxRate {
+                from,
+                to,
+                rate: rate.rate,
+                date: on,
+                rate_date: rate.rate_date,
+                source: rate.source,
+                inverted: false,
+            }));
+        }
+        
+        if let Some(inverse) = self.find_rate(&to, &from, on).await? {
+            return Ok(Some(FxRate {
+                from,
+                to,
+                rate: Decimal::ONE / inverse.rate,
+                date: on,
+                rate_date: inverse.rate_date,
+                source: inverse.source,
+                inverted: true,
+            }));
+        }
+        
+        Ok(None)
+    }
+    
+    async fn find_rate(&self, base: &str, quote: &str, on: NaiveDate) -> Result<Option<ExchangeRate>, DefiantError> {
+        let rate = sqlx::query_as!(
+            ExchangeRate,
+            r#"
+            SELECT * FROM exchange_rates
+            WHERE base_currency = $1 AND quote_currency = $2 AND rate_date <= $3
+            ORDER BY rate_date DESC
+            LIMIT 1
+            "#,
+            base,
+            quote,
+            on,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?;
+        
+        Ok(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn conversions_beyond_range_are_refused() {
+        let rate = Decimal::new(150, 0);
+        assert!(matches!(convert_amount(i64::MAX, "USD", "JPY", rate), Err(DefiantError::ValidationError(_))));
+        assert!(matches!(convert_amount(i64::MAX, "USD", "EUR", Decimal::MAX), Err(DefiantError::ValidationError(_))));
+        assert_eq!(convert_amount(1999, "USD", "JPY", rate).unwrap(), 2999);
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use redis::aio::ConnectionManager;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{models::GeoSignals, errors::DefiantError, config::Config};
+
+/// IP allocations and proxy lists change slowly; a day-old answer is still good.
+const LOOKUP_CACHE_SECS: u64 = 24 * 3600;
+/// Lookups run on the payment path.
+const PROVIDER_TIMEOUT_MS: u64 = 1500;
+
+#[derive(Debug, Deserialize)]
+struct ProviderResponse {
+    #[serde(alias = "country")]
+    country_code: Option<String>,
+    #[serde(default)]
+    is_proxy: bool,
+    #[serde(default)]
+    is_vpn: bool,
+    #[serde(default)]
+    is_tor: bool,
+    #[serde(default)]
+    is_hosting: bool,
+}
+
+/// What the provider knows about one IP, as cached.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpIntelligence {
+    pub country: Option<String>,
+    /// Any anonymizer, VPNs included
+    pub proxy: bool,
+    pub vpn: bool,
+}
+
+/// IP geolocation and anonymizer detection for fraud checks, from the API at
+/// `geoip_api_url`, with answers shared between workers through Redis.
+///
+/// Lookups never fail a payment: without a provider, or when it cannot be reached,
+/// payments are checked without geo signals.
+pub struct GeoIpService {
+    redis: Arc<ConnectionManager>,
+    config: Arc<Config>,
+}
+
+impl GeoIpService {
+    pub fn new(redis: Arc<ConnectionManager>, config: Arc<Config>) -> Self {
+        Self { redis, config }
+    }
+    
+    /// Signals for a payment from `ip`, compared with the countries it claims to be from.
+    pub async fn signals(
+        &self,
+        ip: &str,
+        billing_country: Option<&str>,
+        card_country: Option<&str>,
+    ) -> Option<GeoSignals> {
+        let intelligence = self.lookup(ip).await?;
+        let mismatch = |claimed: Option<&str>| match (intelligence.country.as_deref(), claimed) {
+            (Some(located), Some(claimed)) => !located.eq_ignore_ascii_case(claimed),
+            _ => false,
+        };
+        
+        Some(GeoSignals {
+            ip_address: ip.to_string(),
+            ip_country: intelligence.country.clone(),
+            ip_is_proxy: intelligence.proxy,
+            ip_is_vpn: intelligence.vpn,
+            billing_country: billing_country.map(str::to_uppercase),
+            card_country: card_country.map(str::to_uppercase),
+            billing_country_mismatch: mismatch(billing_country),
+            card_country_mismatch: mismatch(card_country),
+        })
+    }
+    
+    pub async fn lookup(&self, ip: &str) -> Option<IpIntelligence> {
+        self.config.geoip_api_url.as_ref()?;
+        
+        let cache_key = format!("geoip:{}", ip);
+        match redis::cmd("GET")
+            .arg(&cache_key)
+            .query_async::<_, Option<String>>(&mut self.redis.as_ref().clone())
+            .await
+        {
+            Ok(Some(cached)) => {
+                if let Ok(intelligence) = serde_json::from_str(&cached) {
+                    return Some(intelligence);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read cached GeoIP lookup: {}", e),
+        }
+        
+        let intelligence = match self.fetch(ip).await {
+            Ok(intelligence) => intelligence,
+            Err(e) => {
+                warn!("GeoIP lookup for {} failed: {}", ip, e);
+                return None;
+            }
+        };
+        
+        if let Ok(cached) = serde_json::to_string(&intelligence) {
+            if let Err(e) = redis::cmd("SET")
+                .arg(&cache_key)
+                .arg(cached)
+                .arg("EX")
+                .arg(LOOKUP_CACHE_SECS)
+                .query_async::<_, ()>(&mut self.redis.as_ref().clone())
+                .await
+            {
+                warn!("Failed to cache GeoIP lookup: {}", e);
+            }
+        }
+        
+        Some(intelligence)
+    }
+    
+    async fn fetch(&self, ip: &str) -> Result<IpIntelligence, DefiantError> {
+        let base_url = self.config.geoip_api_url.as_deref().ok_or(DefiantError::InternalError)?;
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_millis(PROVIDER_TIMEOUT_MS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let request = client.get(format!("{}/{}", base_url.trim_end_matches('/'), ip));
+        let request = match &self.config.geoip_api_key {
+            Some(key) => request.bearer_auth(key),
+            None => request,
+        };
+        
+        let response: ProviderResponse = request
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| DefiantError::PaymentError(format!("GeoIP request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| DefiantError::PaymentError(format!("GeoIP provider returned an unreadable answer: {}", e)))?;
+        
+        Ok(IpIntelligence {
+            country: response.country_code.map(|country| country.to_uppercase()),
+            proxy: response.is_proxy || response.is_vpn || response.is_tor || response.is_hosting,
+            vpn: response.is_vpn,
+        })
+    }
+}
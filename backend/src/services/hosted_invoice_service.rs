@@ -0,0 +1,377 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use redis::aio::ConnectionManager;
+use ring::hmac;
+use tracing::{info, error};
+
+use crate::{models::{Invoice, InvoiceLineItem, InvoiceStatus, InvoiceResponse, HostedInvoice, HostedInvoicePayment, PayHostedInvoiceRequest, BankTransferInstructions, Payment, PaymentMethod, PaymentStatus, CreatePaymentRequest, CommunicationKind, SealedPii, html_escape, INVOICE_PAID}, errors::DefiantError, db::{Database, hash_api_key}, config::Config, pii};
+use super::email_service::{EmailService, CustomerEmail};
+use super::fx_service::format_amount;
+use super::invoice_service;
+use super::payment_service::PaymentService;
+use super::receipt_service::ReceiptService;
+use super::webhook_service::WebhookService;
+
+/// How long an emailed payment link works past the invoice's due date.
+const LINK_VALIDITY_DAYS: i64 = 30;
+/// Payment details a customer needs to finish a crypto payment from the page.
+const CRYPTO_DETAIL_KEYS: &[&str] = &[
+    "crypto_chain", "crypto_address", "crypto_amount", "crypto_quote_expires_at", "crypto_reference", "crypto_token",
+];
+
+/// Invoice payment pages reached from a link in the invoice email, without a login.
+///
+/// The link carries its expiry and an HMAC over the invoice ID and that expiry, so it
+/// needs no stored token and cannot be altered to reach another invoice. Customers pay
+/// by card or crypto on the page, or by bank transfer quoting the invoice number. A
+/// payment that succeeds marks the invoice paid and emails the customer a receipt,
+/// whether it succeeds on the spot or, for crypto, once the watcher sees it confirm.
+pub struct HostedInvoiceService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+    config: Arc<Config>,
+}
+
+impl HostedInvoiceService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>, config: Arc<Config>) -> Self {
+        Self { db, redis, config }
+    }
+    
+    /// Emails the customer a signed link to the invoice's payment page. Sending again
+    /// issues a fresh link; earlier ones keep working until they expire.
+    pub async fn send_invoice(&self, invoice_id: Uuid, api_key: &str) -> Result<InvoiceResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let sender = sqlx::query!(
+            r#"
+            SELECT ak.livemode, m.name FROM api_keys ak
+            JOIN merchants m ON m.id = ak.merchant_id
+            WHERE ak.key_hash = $1 AND m.id = $2
+            "#,
+            hash_api_key(api_key),
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"SELECT * FROM invoices WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            invoice_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Invoice not found".into()))?;
+        
+        if !matches!(invoice.status, InvoiceStatus::Open) {
+            return Err(DefiantError::Conflict(format!(
+                "Only open invoices can be sent (invoice is {:?})",
+                invoice.status
+            )));
+        }
+        
+        let customer_email = sqlx::query_scalar!(
+            r#"SELECT email AS "email: SealedPii" FROM customers WHERE id = $1"#,
+            invoice.customer_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .open(&pii::context("customers.email", invoice.customer_id))?
+        .into_string();
+        
+        let now = Utc::now();
+        let expires = (invoice.due_date.unwrap_or(now).max(now) + Duration::days(LINK_VALIDITY_DAYS)).timestamp();
+        let url = format!(
+            "{}/invoices/{}?expires={}&signature={}",
+            self.config.hosted_base_url.trim_end_matches('/'),
+            invoice.id,
+            expires,
+            self.sign(invoice.id, expires),
+        );
+        
+        let sent = sqlx::query_as!(
+            Invoice,
+            r#"
+            UPDATE invoices SET hosted_invoice_url = $1, sent_at = $2, livemode = $3
+            WHERE id = $4
+            RETURNING *
+            "#,
+            url,
+            now,
+            sender.livemode,
+            invoice.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        EmailService::new(self.db.clone(), self.config.clone())
+            .send_to_customer(CustomerEmail {
+                merchant_id,
+                customer_id: sent.customer_id,
+                kind: CommunicationKind::Invoice,
+                to: customer_email,
+                subject: format!("Invoice {} from {}", sent.number.as_deref().unwrap_or_default(), sender.name),
+                html_body: render_invoice_email(&sent, &sender.name, &url),
+                related_object_type: Some("invoice".into()),
+                related_object_id: Some(sent.id),
+            })
+            .await?;
+        
+        info!("Invoice {} sent to customer {}", sent.id, sent.customer_id);
+        
+        let line_items = line_items(pool, sent.id).await?;
+        Ok(InvoiceResponse::from_parts(sent, line_items))
+    }
+    
+    pub async fn get_hosted_invoice(
+        &self,
+        invoice_id: Uuid,
+        expires: i64,
+        signature: &str,
+    ) -> Result<HostedInvoice, DefiantError> {
+        let link_expires_at = self.verify(invoice_id, expires, signature)?;
+        let pool = self.db.pool_for_row("invoices", invoice_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Invoice not found".into()))?;
+        
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"SELECT * FROM invoices WHERE id = $1"#,
+            invoice_id,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        let merchant_name = sqlx::query_scalar!(
+            r#"SELECT name FROM merchants WHERE id = $1"#,
+            invoice.merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        let latest_payment = sqlx::query_as!(
+            Payment,
+            r#"SELECT * FROM payments WHERE invoice_id = $1 ORDER BY created_at DESC LIMIT 1"#,
+            invoice.id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|payment| to_hosted_payment(&payment, invoice.status.clone()));
+        
+        let payable = matches!(invoice.status, InvoiceStatus::Open) && invoice.amount_remaining > 0;
+        let payment_methods = if payable { vec![PaymentMethod::Card, PaymentMethod::Crypto] } else { Vec::new() };
+        let bank_transfer = payable.then(|| BankTransferInstructions {
+            reference: invoice.number.clone().unwrap_or_else(|| invoice.id.to_string()),
+            amount: invoice.amount_remaining,
+            currency: invoice.currency.clone(),
+        });
+        let line_items = line_items(pool, invoice.id).await?;
+        
+        Ok(HostedInvoice {
+            id: invoice.id,
+            number: invoice.number,
+            merchant_name,
+            status: invoice.status,
+            amount_due: invoice.amount_due,
+            amount_paid: invoice.amount_paid,
+            amount_remaining: invoice.amount_remaining,
+            currency: invoice.currency,
+            description: invoice.description,
+            due_date: invoice.due_date,
+            line_items,
+            payment_methods,
+            bank_transfer,
+            latest_payment,
+            link_expires_at,
+        })
+    }
+    
+    /// Pays what remains due on the invoice. Card payments settle the invoice at once;
+    /// crypto payments return the address to pay and settle it once they confirm.
+    pub async fn pay_hosted_invoice(
+        &self,
+        invoice_id: Uuid,
+        request: PayHostedInvoiceRequest,
+        client_ip: Option<IpAddr>,
+    ) -> Result<HostedInvoicePayment, DefiantError> {
+        self.verify(invoice_id, request.expires, &request.signature)?;
+        let pool = self.db.pool_for_row("invoices", invoice_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Invoice not found".into()))?;
+        let mut tx = pool.begin().await?;
+        
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"SELECT * FROM invoices WHERE id = $1 FOR UPDATE"#,
+            invoice_id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        if !matches!(invoice.status, InvoiceStatus::Open) || invoice.amount_remaining <= 0 {
+            return Err(DefiantError::Conflict(format!("Invoice is not open for payment (invoice is {:?})", invoice.status)));
+        }
+        
+        // One payment at a time, so a crypto deposit still confirming is not paid again by card
+        let in_flight = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM payments
+                WHERE invoice_id = $1 AND status IN ('pending', 'processing', 'requires_action', 'partially_paid')
+            )
+            "#,
+            invoice.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(false);
+        
+        if in_flight {
+            return Err(DefiantError::Conflict("A payment for this invoice is already in progress".into()));
+        }
+        
+        let payment_request = CreatePaymentRequest {
+            amount: invoice.amount_remaining,
+            currency: invoice.currency.clone(),
+            payment_method: request.payment_method,
+            description: Some(format!("Invoice {}", invoice.number.as_deref().unwrap_or_default())),
+            metadata: None,
+            customer_id: Some(invoice.customer_id),
+            source: request.source,
+            off_session: false,
+            mit_agreement_id: None,
+            setup_future_usage: None,
+            consent: None,
+            card_present: None,
+            crypto_chain: request.crypto_chain,
+            crypto_token: request.crypto_token,
+            crypto_amount: None,
+            client_ip: client_ip.map(|ip| ip.to_string()),
+            transfer_data: None,
+        };
+        
+        let payments = PaymentService::new(self.db.clone(), self.redis.clone(), self.config.clone());
+        let payment = payments.charge_invoice(&mut tx, &invoice, &payment_request).await?;
+        
+        let settled = match payment.status {
+            PaymentStatus::Succeeded => invoice_service::apply_payment(&mut tx, invoice.id, payment.amount).await?,
+            _ => None,
+        };
+        
+        tx.commit().await?;
+        
+        info!("Hosted payment {} for invoice {} is {:?}", payment.id, invoice.id, payment.status);
+        payments.emit_created(invoice.merchant_id, &payment).await;
+        
+        let invoice_status = match &settled {
+            Some(paid) => {
+                invoice_paid(self.db.clone(), self.config.clone(), paid, payment.id).await;
+                paid.status.clone()
+            }
+            None => invoice.status,
+        };
+        
+        Ok(to_hosted_payment(&payment, invoice_status))
+    }
+    
+    fn sign(&self, invoice_id: Uuid, expires: i64) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.config.invoice_link_key.as_bytes());
+        hex::encode(hmac::sign(&key, link_message(invoice_id, expires).as_bytes()).as_ref())
+    }
+    
+    /// Checks a link's signature, returning when the link expires. A bad signature
+    /// looks exactly like a missing invoice.
+    fn verify(&self, invoice_id: Uuid, expires: i64, signature: &str) -> Result<DateTime<Utc>, DefiantError> {
+        let not_found = || DefiantError::NotFound("Invoice not found".into());
+        let tag = hex::decode(signature).map_err(|_| not_found())?;
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.config.invoice_link_key.as_bytes());
+        hmac::verify(&key, link_message(invoice_id, expires).as_bytes(), &tag).map_err(|_| not_found())?;
+        
+        let expires_at = DateTime::<Utc>::from_timestamp(expires, 0).ok_or_else(not_found)?;
+        if expires_at <= Utc::now() {
+            return Err(DefiantError::Conflict("This payment link has expired; ask the merchant to send the invoice again".into()));
+        }
+        
+        Ok(expires_at)
+    }
+}
+
+/// Announces an invoice paid from its hosted page and emails the customer a receipt
+/// for the payment. Failures are logged; the invoice stays paid.
+pub(crate) async fn invoice_paid(db: Arc<Database>, config: Arc<Config>, invoice: &Invoice, payment_id: Uuid) {
+    if !matches!(invoice.status, InvoiceStatus::Paid) {
+        return;
+    }
+    
+    if let Err(e) = WebhookService::new(db.clone())
+        .enqueue_event(invoice.merchant_id, INVOICE_PAID, serde_json::json!(invoice))
+        .await
+    {
+        error!("Failed to enqueue {} event: {}", INVOICE_PAID, e);
+    }
+    
+    if let Err(e) = ReceiptService::new(db, config).deliver(payment_id, invoice.merchant_id).await {
+        error!("Failed to send receipt for invoice {}: {}", invoice.id, e);
+    }
+}
+
+fn link_message(invoice_id: Uuid, expires: i64) -> String {
+    format!("{}.{}", invoice_id, expires)
+}
+
+fn to_hosted_payment(payment: &Payment, invoice_status: InvoiceStatus) -> HostedInvoicePayment {
+    let crypto = match (&payment.payment_method, &payment.metadata) {
+        (PaymentMethod::Crypto, Some(serde_json::Value::Object(metadata))) => Some(serde_json::Value::Object(
+            metadata
+                .iter()
+                .filter(|(key, _)| CRYPTO_DETAIL_KEYS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        )),
+        _ => None,
+    };
+    
+    HostedInvoicePayment {
+        payment_id: payment.id,
+        status: payment.status.clone(),
+        amount: payment.amount,
+        currency: payment.currency.clone(),
+        crypto,
+        invoice_status,
+    }
+}
+
+async fn line_items(pool: &PgPool, invoice_id: Uuid) -> Result<Vec<InvoiceLineItem>, DefiantError> {
+    let items = sqlx::query_as!(
+        InvoiceLineItem,
+        r#"SELECT * FROM invoice_line_items WHERE invoice_id = $1 ORDER BY created_at, id"#,
+        invoice_id,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(items)
+}
+
+fn render_invoice_email(invoice: &Invoice, merchant_name: &str, url: &str) -> String {
+    let due = invoice.due_date
+        .map(|due| format!(", due {}", due.format("%Y-%m-%d")))
+        .unwrap_or_default();
+    
+    format!(
+        "<h2>Invoice {} from {}</h2><p>{}</p><p>Amount due: {}{}</p><p><a href=\"{}\">View and pay this invoice</a></p>",
+        html_escape(invoice.number.as_deref().unwrap_or_default()),
+        html_escape(merchant_name),
+        html_escape(invoice.description.as_deref().unwrap_or("")),
+        format_amount(invoice.amount_remaining, &invoice.currency),
+        due,
+        html_escape(url),
+    )
+}
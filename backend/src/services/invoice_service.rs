@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use tracing::{info, error};
+
+use crate::{api::v1::conditional::Precondition, models::{Invoice, InvoiceLineItem, InvoiceStatus, CreateInvoiceRequest, InvoiceResponse, InvoiceListQuery, InvoiceListResponse, INVOICE_CREATED, INVOICE_VOIDED, INVOICE_MARKED_UNCOLLECTIBLE}, errors::DefiantError, db::Database};
+use super::fx_service::{self, FxService};
+use super::webhook_service::WebhookService;
+use super::pagination::{self, Cursor, Page};
+
+pub struct InvoiceService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+}
+
+impl InvoiceService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
+        Self { db, redis }
+    }
+    
+    pub async fn create_invoice(
+        &self,
+        request: CreateInvoiceRequest,
+        api_key: &str,
+    ) -> Result<InvoiceResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let currency = request.currency.to_uppercase();
+        fx_service::ensure_supported_currency(&currency)?;
+        
+        // All lines must be priced in one currency; it may differ from the invoice currency
+        let source_currency = resolve_line_currency(&request, &currency)?;
+        fx_service::ensure_supported_currency(&source_currency)?;
+        
+        let snapshot = if source_currency != currency {
+            let (rate, rate_date) = FxService::new(self.db.clone())
+                .get_rate(&source_currency, &currency, Utc::now().date_naive())
+                .await?;
+            Some((rate, rate_date))
+        } else {
+            None
+        };
+        
+        let mut converted_lines = Vec::with_capacity(request.line_items.len());
+        for line in &request.line_items {
+            let original = line.unit_amount
+                .checked_mul(line.quantity as i64)
+                .ok_or_else(|| DefiantError::ValidationError("Line item amount is out of range".into()))?;
+            let amount = match &snapshot {
+                Some((rate, _)) => fx_service::convert_amount(original, &source_currency, &currency, *rate)?,
+                None => original,
+            };
+            converted_lines.push((line, amount));
+        }
+        
+        let amount_due = converted_lines.iter()
+            .try_fold(0i64, |total, (_, amount)| total.checked_add(*amount))
+            .ok_or_else(|| DefiantError::ValidationError("Invoice amount is out of range".into()))?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let customer_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1 AND merchant_id = $2 AND livemode = $3)"#,
+            request.customer_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(false);
+        
+        if !customer_exists {
+            return Err(DefiantError::NotFound("Customer not found".into()));
+        }
+        
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            INSERT INTO invoices (
+                merchant_id, customer_id, status, amount_due, amount_remaining,
+                currency, description, metadata, due_date,
+                source_currency, exchange_rate, exchange_rate_date, livemode
+            )
+            VALUES ($1, $2, $3, $4, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.customer_id,
+            InvoiceStatus::Draft as InvoiceStatus,
+            amount_due,
+            currency,
+            request.description,
+            request.metadata,
+            request.due_date,
+            snapshot.as_ref().map(|_| source_currency.clone()),
+            snapshot.as_ref().map(|(rate, _)| *rate),
+            snapshot.as_ref().map(|(_, date)| *date),
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let mut line_items = Vec::with_capacity(converted_lines.len());
+        for (line, amount) in converted_lines {
+            let item = sqlx::query_as!(
+                InvoiceLineItem,
+                r#"
+                INSERT INTO invoice_line_items (
+                    invoice_id, description, quantity, unit_amount, currency, amount, metadata
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+                "#,
+                invoice.id,
+                line.description,
+                line.quantity,
+                line.unit_amount,
+                source_currency,
+                amount,
+                line.metadata,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            line_items.push(item);
+        }
+        
+        tx.commit().await?;
+        
+        info!("Invoice created: {} ({} {})", invoice.id, amount_due, invoice.currency);
+        self.emit(merchant_id, INVOICE_CREATED, &invoice).await;
+        
+        Ok(InvoiceResponse::from_parts(invoice, line_items))
+    }
+    
+    pub async fn get_invoice(
+        &self,
+        invoice_id: Uuid,
+        api_key: &str,
+    ) -> Result<InvoiceResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        let invoice = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT * FROM invoices
+            WHERE id = $1 AND merchant_id = $2
+            "#,
+            invoice_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Invoice not found".into()))?;
+        
+        let line_items = self.line_items(pool, invoice.id).await?;
+        
+        Ok(InvoiceResponse::from_parts(invoice, line_items))
+    }
+    
+    pub async fn list_invoices(
+        &self,
+        query: InvoiceListQuery,
+        api_key: &str,
+    ) -> Result<InvoiceListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        
+        // Fetch one extra row to learn whether another page exists
+        let invoices = sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT * FROM invoices
+            WHERE merchant_id = $1 AND livemode = $2
+            AND ($3::UUID IS NULL OR customer_id = $3)
+            AND ($4::invoice_status IS NULL OR status = $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            key.merchant_id,
+            key.livemode,
+            query.customer_id,
+            query.status as Option<InvoiceStatus>,
+            after_created,
+            after_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let page = Page::new(invoices, limit, |invoice| Cursor::new(invoice.created_at, invoice.id));
+        let ids: Vec<Uuid> = page.data.iter().map(|invoice| invoice.id).collect();
+        let line_items = sqlx::query_as!(
+            InvoiceLineItem,
+            r#"
+            SELECT * FROM invoice_line_items
+            WHERE invoice_id = ANY($1)
+            ORDER BY created_at, id
+            "#,
+            &ids,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let mut lines_by_invoice: HashMap<Uuid, Vec<InvoiceLineItem>> = HashMap::new();
+        for line in line_items {
+            lines_by_invoice.entry(line.invoice_id).or_default().push(line);
+        }
+        let page = page.map(|invoice| {
+            let lines = lines_by_invoice.remove(&invoice.id).unwrap_or_default();
+            InvoiceResponse::from_parts(invoice, lines)
+        });
+        
+        Ok(InvoiceListResponse {
+            data: page.data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/invoices".into(),
+        })
+    }
+    
+    /// Voids an open or uncollectible invoice. Any customer credit the invoice
+    /// consumed is returned to the customer's balance in the same transaction.
+    pub async fn void_invoice(
+        &self,
+        invoice_id: Uuid,
+        precondition: &Precondition,
+        api_key: &str,
+    ) -> Result<InvoiceResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let invoice = self.lock_invoice(invoice_id, merchant_id, &mut tx).await?;
+        precondition.check(invoice.updated_at)?;
+        
+        if !matches!(invoice.status, InvoiceStatus::Open | InvoiceStatus::Uncollectible) {
+            return Err(DefiantError::Conflict(format!(
+                "Only open or uncollectible invoices can be voided (invoice is {:?})",
+                invoice.status
+            )));
+        }
+        
+        if invoice.customer_balance_applied > 0 {
+            let ending_balance = sqlx::query_scalar!(
+                r#"
+                UPDATE customers SET balance = COALESCE(balance, 0) + $1
+                WHERE id = $2
+                RETURNING balance AS "balance!"
+                "#,
+                invoice.customer_balance_applied,
+                invoice.customer_id,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            
+            sqlx::query!(
+                r#"
+                INSERT INTO customer_balance_transactions (
+                    merchant_id, customer_id, invoice_id, type, amount, currency, ending_balance, description
+                )
+                VALUES ($1, $2, $3, 'invoice_voided', $4, $5, $6, $7)
+                "#,
+                merchant_id,
+                invoice.customer_id,
+                invoice.id,
+                invoice.customer_balance_applied,
+                invoice.currency,
+                ending_balance,
+                format!("Credit returned from voided invoice {}", invoice.number.as_deref().unwrap_or("")),
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        let voided = sqlx::query_as!(
+            Invoice,
+            r#"
+            UPDATE invoices
+            SET status = $1, voided_at = $2, amount_remaining = 0, customer_balance_applied = 0
+            WHERE id = $3
+            RETURNING *
+            "#,
+            InvoiceStatus::Void as InvoiceStatus,
+            Utc::now(),
+            invoice.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Invoice voided: {}", voided.id);
+        
+        self.emit(merchant_id, INVOICE_VOIDED, &voided).await;
+        
+        let line_items = self.line_items(pool, voided.id).await?;
+        Ok(InvoiceResponse::from_parts(voided, line_items))
+    }
+    
+    /// Writes off an open invoice. The invoice keeps its outstanding amount for
+    /// audit but drops out of receivables and revenue reporting.
+    pub async fn mark_uncollectible(
+        &self,
+        invoice_id: Uuid,
+        precondition: &Precondition,
+        api_key: &str,
+    ) -> Result<InvoiceResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let invoice = self.lock_invoice(invoice_id, merchant_id, &mut tx).await?;
+        precondition.check(invoice.updated_at)?;
+        
+        if !matches!(invoice.status, InvoiceStatus::Open) {
+            return Err(DefiantError::Conflict(format!(
+                "Only open invoices can be marked uncollectible (invoice is {:?})",
+                invoice.status
+            )));
+        }
+        
+        let updated = sqlx::query_as!(
+            Invoice,
+            r#"
+            UPDATE invoices
+            SET status = $1, marked_uncollectible_at = $2
+            WHERE id = $3
+            RETURNING *
+            "#,
+            InvoiceStatus::Uncollectible as InvoiceStatus,
+            Utc::now(),
+            invoice.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Invoice marked uncollectible: {}", updated.id);
+        
+        self.emit(merchant_id, INVOICE_MARKED_UNCOLLECTIBLE, &updated).await;
+        
+        let line_items = self.line_items(pool, updated.id).await?;
+        Ok(InvoiceResponse::from_parts(updated, line_items))
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, invoice: &Invoice) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(invoice))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+    
+    async fn lock_invoice(
+        &self,
+        invoice_id: Uuid,
+        merchant_id: Uuid,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Invoice, DefiantError> {
+        sqlx::query_as!(
+            Invoice,
+            r#"
+            SELECT * FROM invoices
+            WHERE id = $1 AND merchant_id = $2
+            FOR UPDATE
+            "#,
+            invoice_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Invoice not found".into()))
+    }
+    
+    async fn line_items(&self, pool: &PgPool, invoice_id: Uuid) -> Result<Vec<InvoiceLineItem>, DefiantError> {
+        let items = sqlx::query_as!(
+            InvoiceLineItem,
+            r#"
+            SELECT * FROM invoice_line_items
+            WHERE invoice_id = $1
+            ORDER BY created_at, id
+            "#,
+            invoice_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(items)
+    }
+}
+
+/// A line of an invoice generated from another object, priced in the invoice currency.
+#[derive(Debug, Clone)]
+pub struct InvoiceDraftLine {
+    pub description: String,
+    pub quantity: i32,
+    pub unit_amount: i64,
+}
+
+/// Creates an already-finalized (open) invoice inside the caller's transaction,
+/// applying any available customer credit. Used when other objects, such as
+/// accepted quotes, convert into invoices. The invoice takes the customer's mode.
+pub(crate) async fn insert_open_invoice(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    customer_id: Uuid,
+    currency: &str,
+    description: Option<String>,
+    metadata: Option<serde_json::Value>,
+    lines: &[InvoiceDraftLine],
+) -> Result<(Invoice, Vec<InvoiceLineItem>), DefiantError> {
+    let mut amount_due: i64 = 0;
+    for line in lines {
+        let line_total = line.unit_amount
+            .checked_mul(line.quantity as i64)
+            .and_then(|total| total.checked_add(amount_due))
+            .ok_or_else(|| DefiantError::ValidationError("Invoice amount is out of range".into()))?;
+        amount_due = line_total;
+    }
+    
+    let customer = sqlx::query!(
+        r#"
+        SELECT balance, currency, livemode FROM customers
+        WHERE id = $1 AND merchant_id = $2
+        FOR UPDATE
+        "#,
+        customer_id,
+        merchant_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| DefiantError::NotFound("Customer not found".into()))?;
+    
+    // Credit only applies in the currency it is held in
+    let balance = customer.balance.unwrap_or(0);
+    let same_currency = customer.currency
+        .as_deref()
+        .map(|c| c.eq_ignore_ascii_case(currency))
+        .unwrap_or(true);
+    let applied = if balance > 0 && same_currency { balance.min(amount_due) } else { 0 };
+    let remaining = amount_due - applied;
+    let status = if remaining == 0 { InvoiceStatus::Paid } else { InvoiceStatus::Open };
+    let now = Utc::now();
+    
+    let invoice = sqlx::query_as!(
+        Invoice,
+        r#"
+        INSERT INTO invoices (
+            merchant_id, customer_id, status, amount_due, amount_paid, amount_remaining,
+            currency, description, metadata, customer_balance_applied, paid_at, livemode
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $5, $10, $11)
+        RETURNING *
+        "#,
+        merchant_id,
+        customer_id,
+        status as InvoiceStatus,
+        amount_due,
+        applied,
+        remaining,
+        currency,
+        description,
+        metadata,
+        (remaining == 0).then_some(now),
+        customer.livemode,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    if applied > 0 {
+        let ending_balance = sqlx::query_scalar!(
+            r#"
+            UPDATE customers SET balance = balance - $1
+            WHERE id = $2
+            RETURNING balance AS "balance!"
+            "#,
+            applied,
+            customer_id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO customer_balance_transactions (
+                merchant_id, customer_id, invoice_id, type, amount, currency, ending_balance
+            )
+            VALUES ($1, $2, $3, 'applied_to_invoice', $4, $5, $6)
+            "#,
+            merchant_id,
+            customer_id,
+            invoice.id,
+            -applied,
+            currency,
+            ending_balance,
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+    
+    let mut items = Vec::with_capacity(lines.len());
+    for line in lines {
+        let item = sqlx::query_as!(
+            InvoiceLineItem,
+            r#"
+            INSERT INTO invoice_line_items (
+                invoice_id, description, quantity, unit_amount, currency, amount
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            invoice.id,
+            line.description,
+            line.quantity,
+            line.unit_amount,
+            currency,
+            line.unit_amount * line.quantity as i64,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        items.push(item);
+    }
+    
+    Ok((invoice, items))
+}
+
+/// Returns the single currency shared by every line item, or a validation
+/// error naming the conflicting currencies.
+fn resolve_line_currency(request: &CreateInvoiceRequest, invoice_currency: &str) -> Result<String, DefiantError> {
+    let mut currencies = request.line_items
+        .iter()
+        .map(|line| line.currency.as_deref().unwrap_or(invoice_currency).to_uppercase());
+    
+    let first = currencies.next().unwrap_or_else(|| invoice_currency.to_string());
+    
+    if let Some(other) = currencies.find(|c| *c != first) {
+        return Err(DefiantError::ValidationError(format!(
+            "line_items: all line items must use the same currency (found {} and {})",
+            first, other
+        )));
+    }
+    
+    Ok(first)
+}
+
+/// Applies a payment to an open invoice inside the caller's transaction, marking it
+/// paid once nothing remains due. Returns `None` when the invoice is no longer open,
+/// e.g. voided while a crypto payment was confirming.
+pub(crate) async fn apply_payment(
+    tx: &mut Transaction<'_, Postgres>,
+    invoice_id: Uuid,
+    amount: i64,
+) -> Result<Option<Invoice>, DefiantError> {
+    let invoice = sqlx::query_as!(
+        Invoice,
+        r#"
+        UPDATE invoices
+        SET amount_paid = amount_paid + LEAST($2, amount_remaining),
+            amount_remaining = amount_remaining - LEAST($2, amount_remaining),
+            status = CASE WHEN amount_remaining <= $2 THEN 'paid'::invoice_status ELSE status END,
+            paid_at = CASE WHEN amount_remaining <= $2 THEN NOW() ELSE paid_at END
+        WHERE id = $1 AND status = 'open'
+        RETURNING *
+        "#,
+        invoice_id,
+        amount,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    Ok(invoice)
+}
@@ -0,0 +1,993 @@
+use std::sync::Arc;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use tracing::error;
+
+use super::connect_service;
+use super::pricing;
+use super::reserve_service;
+use crate::{models::{Payment, Payout, Topup, BalanceConversion, ReserveHold, Transfer, TransferReversal, CryptoRefund, LedgerAccountCode, LedgerDirection, JournalEntry, JournalLine, JournalEntryWithLines, JournalEntryListQuery, JournalEntryListResponse, LedgerAccountBalance, LedgerBalancesResponse, AvailableBalance, BalanceResponse, BalanceTransaction, BalanceTransactionType, BalanceTransactionStatus, BalanceTransactionSource, BalanceTransactionListQuery, BalanceTransactionListResponse}, errors::DefiantError, db::Database, config::{Config, ProcessingFees}};
+
+/// Days card funds stay pending when `settlement_delay_days` is unset.
+const DEFAULT_SETTLEMENT_DELAY_DAYS: u32 = 2;
+
+/// A journal entry to post: `amount` debited to one account and credited to another.
+pub struct Booking<'a> {
+    pub merchant_id: Uuid,
+    pub kind: &'a str,
+    pub currency: &'a str,
+    /// Minor units
+    pub amount: i64,
+    pub debit: LedgerAccountCode,
+    pub credit: LedgerAccountCode,
+    pub description: Option<&'a str>,
+    pub metadata: serde_json::Value,
+    /// When the funds stop counting as pending; at once when absent
+    pub available_on: Option<DateTime<Utc>>,
+}
+
+/// Writes a balanced entry inside the caller's transaction, so it commits or rolls
+/// back with the state change it records. Nothing is posted for a zero amount.
+pub async fn post(
+    tx: &mut Transaction<'_, Postgres>,
+    booking: Booking<'_>,
+) -> Result<Option<JournalEntry>, DefiantError> {
+    if booking.amount == 0 {
+        return Ok(None);
+    }
+    if booking.amount < 0 {
+        error!("Refusing to post a negative {} entry for merchant {}", booking.kind, booking.merchant_id);
+        return Err(DefiantError::InternalError);
+    }
+    
+    let currency = booking.currency.to_uppercase();
+    let debit_account = open_account(tx, booking.merchant_id, booking.debit, &currency).await?;
+    let credit_account = open_account(tx, booking.merchant_id, booking.credit, &currency).await?;
+    
+    let entry = sqlx::query_as!(
+        JournalEntry,
+        r#"
+        INSERT INTO journal_entries (merchant_id, kind, currency, description, metadata, available_on)
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, NOW()))
+        RETURNING *
+        "#,
+        booking.merchant_id,
+        booking.kind,
+        currency,
+        booking.description,
+        booking.metadata,
+        booking.available_on,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    // Balance is checked again by the database when the transaction commits
+    sqlx::query!(
+        r#"
+        INSERT INTO journal_lines (journal_entry_id, merchant_id, account_id, direction, amount)
+        VALUES ($1, $2, $3, 'debit', $5), ($1, $2, $4, 'credit', $5)
+        "#,
+        entry.id,
+        booking.merchant_id,
+        debit_account,
+        credit_account,
+        booking.amount,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    Ok(Some(entry))
+}
+
+async fn open_account(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    code: LedgerAccountCode,
+    currency: &str,
+) -> Result<Uuid, DefiantError> {
+    let account_id = sqlx::query_scalar!(
+        r#"
+        INSERT INTO ledger_accounts (merchant_id, code, currency, normal_balance)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (merchant_id, code, currency) DO UPDATE SET code = EXCLUDED.code
+        RETURNING id
+        "#,
+        merchant_id,
+        code.as_str(),
+        currency,
+        code.normal_balance() as LedgerDirection,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(account_id)
+}
+
+/// A succeeded card payment reaches the merchant's balance less the processing fee,
+/// which is booked as its own entry. Both stay pending for the settlement delay, as
+/// do the transfer to a connected account when the payment was routed to one and the
+/// share held back under the merchant's rolling reserve.
+///
+/// Ledger accounts are not split by mode, so test payments book nothing; otherwise
+/// they would reach the balance live payouts spend.
+pub async fn record_card_payment(
+    tx: &mut Transaction<'_, Postgres>,
+    directory: &PgPool,
+    payment: &Payment,
+    config: &Config,
+) -> Result<(), DefiantError> {
+    if !payment.livemode {
+        return Ok(());
+    }
+    let delay_days = config.settlement_delay_days.unwrap_or(DEFAULT_SETTLEMENT_DELAY_DAYS);
+    let available_on = Some(Utc::now() + Duration::days(delay_days as i64));
+    let fee = pricing::fee_for_payment(directory, payment, config.processing_fees).await?;
+    
+    post(tx, Booking {
+        merchant_id: payment.merchant_id,
+        kind: "payment",
+        currency: &payment.currency,
+        amount: payment.amount,
+        debit: LedgerAccountCode::ProcessorFunds,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: payment.description.as_deref(),
+        metadata: serde_json::json!({ "payment_id": payment.id }),
+        available_on,
+    })
+    .await?;
+    
+    post(tx, Booking {
+        merchant_id: payment.merchant_id,
+        kind: "fee",
+        currency: &payment.currency,
+        amount: fee.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::FeeRevenue,
+        description: Some("Processing fee"),
+        metadata: serde_json::json!({ "payment_id": payment.id, "pricing_rule_id": fee.pricing_rule_id }),
+        available_on,
+    })
+    .await?;
+    
+    if let Some(destination) = payment.transfer_destination {
+        connect_service::transfer_payment_funds(tx, payment, destination, available_on).await?;
+    }
+    
+    reserve_service::hold_payment_funds(tx, payment, available_on).await?;
+    
+    Ok(())
+}
+
+/// Card refunds come out of the merchant's balance; the processing fee is kept. Test
+/// payments were never booked, so neither are their refunds.
+pub async fn record_card_refund(
+    tx: &mut Transaction<'_, Postgres>,
+    payment: &Payment,
+    amount: i64,
+    reason: Option<&str>,
+) -> Result<(), DefiantError> {
+    if !payment.livemode {
+        return Ok(());
+    }
+    post(tx, Booking {
+        merchant_id: payment.merchant_id,
+        kind: "refund",
+        currency: &payment.currency,
+        amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::ProcessorFunds,
+        description: reason,
+        metadata: serde_json::json!({ "payment_id": payment.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+pub async fn record_payout(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: payout.merchant_id,
+        kind: "payout",
+        currency: &payout.currency,
+        amount: payout.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::ProcessorFunds,
+        description: payout.description.as_deref(),
+        metadata: serde_json::json!({ "payout_id": payout.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    post(tx, Booking {
+        merchant_id: payout.merchant_id,
+        kind: "payout_fee",
+        currency: &payout.currency,
+        amount: payout.fee,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::FeeRevenue,
+        description: Some("Instant payout fee"),
+        metadata: serde_json::json!({ "payout_id": payout.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    claim_for_payout(tx, payout).await?;
+    
+    Ok(())
+}
+
+/// Links the payout to every available movement of its currency not yet paid out, so
+/// it can be reconciled against them. When the payout pays out the whole available
+/// balance, as scheduled payouts do, their net adds up to its amount.
+async fn claim_for_payout(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    let payout_kinds: Vec<String> = BalanceTransactionType::Payout.kinds().iter().map(|kind| kind.to_string()).collect();
+    
+    sqlx::query!(
+        r#"
+        INSERT INTO payout_journal_entries (journal_entry_id, payout_id, merchant_id)
+        SELECT DISTINCT e.id, $1::UUID, e.merchant_id
+        FROM journal_entries e
+        JOIN journal_lines l ON l.journal_entry_id = e.id
+        JOIN ledger_accounts a ON a.id = l.account_id AND a.code = $3
+        WHERE e.merchant_id = $2 AND e.currency = $4
+        AND e.available_on <= NOW()
+        AND e.kind <> ALL($5)
+        AND NOT EXISTS (SELECT 1 FROM payout_journal_entries p WHERE p.journal_entry_id = e.id)
+        "#,
+        payout.id,
+        payout.merchant_id,
+        LedgerAccountCode::MerchantBalance.as_str(),
+        payout.currency,
+        &payout_kinds,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    Ok(())
+}
+
+/// Hands the movements a payout claimed back to the next payout. The payout and its
+/// reversal cancel out, so neither is claimed by a later payout.
+async fn release_from_payout(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    sqlx::query!(
+        r#"DELETE FROM payout_journal_entries WHERE payout_id = $1"#,
+        payout.id,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    Ok(())
+}
+
+/// Reverses `record_payout`, fee included.
+pub async fn record_payout_failure(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: payout.merchant_id,
+        kind: "payout_failure",
+        currency: &payout.currency,
+        amount: payout.amount,
+        debit: LedgerAccountCode::ProcessorFunds,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: payout.failure_message.as_deref(),
+        metadata: serde_json::json!({ "payout_id": payout.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    refund_payout_fee(tx, payout).await?;
+    release_from_payout(tx, payout).await?;
+    
+    Ok(())
+}
+
+async fn refund_payout_fee(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: payout.merchant_id,
+        kind: "payout_fee_refund",
+        currency: &payout.currency,
+        amount: payout.fee,
+        debit: LedgerAccountCode::FeeRevenue,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: Some("Instant payout fee refund"),
+        metadata: serde_json::json!({ "payout_id": payout.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Reverses `record_payout`, fee included, for a payout stopped before it was sent.
+pub async fn record_payout_cancellation(tx: &mut Transaction<'_, Postgres>, payout: &Payout) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: payout.merchant_id,
+        kind: "payout_cancellation",
+        currency: &payout.currency,
+        amount: payout.amount,
+        debit: LedgerAccountCode::ProcessorFunds,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: payout.description.as_deref(),
+        metadata: serde_json::json!({ "payout_id": payout.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    refund_payout_fee(tx, payout).await?;
+    release_from_payout(tx, payout).await?;
+    
+    Ok(())
+}
+
+/// Moves funds from the platform's balance to the connected account's. Each side is
+/// its own entry on its own merchant's ledger.
+pub async fn record_transfer(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer: &Transfer,
+    available_on: Option<DateTime<Utc>>,
+) -> Result<(), DefiantError> {
+    let metadata = serde_json::json!({ "transfer_id": transfer.id, "payment_id": transfer.source_payment_id });
+    
+    post(tx, Booking {
+        merchant_id: transfer.merchant_id,
+        kind: "transfer",
+        currency: &transfer.currency,
+        amount: transfer.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::ConnectedTransfers,
+        description: transfer.description.as_deref(),
+        metadata: metadata.clone(),
+        available_on,
+    })
+    .await?;
+    
+    post(tx, Booking {
+        merchant_id: transfer.destination,
+        kind: "transfer",
+        currency: &transfer.currency,
+        amount: transfer.amount,
+        debit: LedgerAccountCode::ConnectedTransfers,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: transfer.description.as_deref(),
+        metadata,
+        available_on,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Returns part of a transfer from the connected account's balance to the platform's.
+pub async fn record_transfer_reversal(
+    tx: &mut Transaction<'_, Postgres>,
+    transfer: &Transfer,
+    reversal: &TransferReversal,
+) -> Result<(), DefiantError> {
+    let metadata = serde_json::json!({ "transfer_id": transfer.id, "transfer_reversal_id": reversal.id });
+    
+    post(tx, Booking {
+        merchant_id: transfer.destination,
+        kind: "transfer_reversal",
+        currency: &transfer.currency,
+        amount: reversal.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::ConnectedTransfers,
+        description: reversal.description.as_deref(),
+        metadata: metadata.clone(),
+        available_on: None,
+    })
+    .await?;
+    
+    post(tx, Booking {
+        merchant_id: transfer.merchant_id,
+        kind: "transfer_reversal",
+        currency: &transfer.currency,
+        amount: reversal.amount,
+        debit: LedgerAccountCode::ConnectedTransfers,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: reversal.description.as_deref(),
+        metadata,
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Sells `from_amount` of one currency from the merchant's balance and buys
+/// `to_amount` of another; each leg is an entry in its own currency.
+pub async fn record_conversion(
+    tx: &mut Transaction<'_, Postgres>,
+    conversion: &BalanceConversion,
+) -> Result<(), DefiantError> {
+    let metadata = serde_json::json!({ "balance_conversion_id": conversion.id, "rate": conversion.rate });
+    let description = format!("Converted to {}", conversion.to_currency);
+    
+    post(tx, Booking {
+        merchant_id: conversion.merchant_id,
+        kind: "conversion",
+        currency: &conversion.from_currency,
+        amount: conversion.from_amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::FxConversion,
+        description: Some(&description),
+        metadata: metadata.clone(),
+        available_on: None,
+    })
+    .await?;
+    
+    let description = format!("Converted from {}", conversion.from_currency);
+    post(tx, Booking {
+        merchant_id: conversion.merchant_id,
+        kind: "conversion",
+        currency: &conversion.to_currency,
+        amount: conversion.to_amount,
+        debit: LedgerAccountCode::FxConversion,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: Some(&description),
+        metadata,
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Moves funds from the balance into the reserve. The hold becomes available with the
+/// payment it was taken from, so it never counts towards the available balance.
+pub async fn record_reserve_hold(
+    tx: &mut Transaction<'_, Postgres>,
+    hold: &ReserveHold,
+    available_on: Option<DateTime<Utc>>,
+) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: hold.merchant_id,
+        kind: "reserve_hold",
+        currency: &hold.currency,
+        amount: hold.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::Reserve,
+        description: Some("Rolling reserve"),
+        metadata: serde_json::json!({ "payment_id": hold.payment_id, "reserve_hold_id": hold.id }),
+        available_on,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Reverses `record_reserve_hold` once the hold period is over.
+pub async fn record_reserve_release(tx: &mut Transaction<'_, Postgres>, hold: &ReserveHold) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: hold.merchant_id,
+        kind: "reserve_release",
+        currency: &hold.currency,
+        amount: hold.amount,
+        debit: LedgerAccountCode::Reserve,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: Some("Rolling reserve release"),
+        metadata: serde_json::json!({ "payment_id": hold.payment_id, "reserve_hold_id": hold.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+pub async fn record_topup(tx: &mut Transaction<'_, Postgres>, topup: &Topup) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: topup.merchant_id,
+        kind: "topup",
+        currency: &topup.currency,
+        amount: topup.amount,
+        debit: LedgerAccountCode::ProcessorFunds,
+        credit: LedgerAccountCode::MerchantBalance,
+        description: topup.description.as_deref(),
+        metadata: serde_json::json!({ "topup_id": topup.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Crypto settles on chain to the merchant, so it is booked apart from the balance
+/// card payments and payouts move through. Its fee does come out of that balance, so
+/// test payments book nothing, as for cards.
+pub async fn record_crypto_payment(
+    tx: &mut Transaction<'_, Postgres>,
+    directory: &PgPool,
+    payment: &Payment,
+    conversion_id: Option<Uuid>,
+) -> Result<(), DefiantError> {
+    if !payment.livemode {
+        return Ok(());
+    }
+    post(tx, Booking {
+        merchant_id: payment.merchant_id,
+        kind: "crypto_payment",
+        currency: &payment.currency,
+        amount: payment.amount,
+        debit: LedgerAccountCode::CryptoWallet,
+        credit: LedgerAccountCode::CryptoSales,
+        description: payment.description.as_deref(),
+        metadata: serde_json::json!({ "payment_id": payment.id, "crypto_conversion_id": conversion_id }),
+        available_on: None,
+    })
+    .await?;
+    
+    // Only plans with a crypto rule charge a fee; it comes out of the merchant balance
+    let fee = pricing::fee_for_payment(directory, payment, ProcessingFees::default()).await?;
+    post(tx, Booking {
+        merchant_id: payment.merchant_id,
+        kind: "fee",
+        currency: &payment.currency,
+        amount: fee.amount,
+        debit: LedgerAccountCode::MerchantBalance,
+        credit: LedgerAccountCode::FeeRevenue,
+        description: Some("Processing fee"),
+        metadata: serde_json::json!({ "payment_id": payment.id, "pricing_rule_id": fee.pricing_rule_id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+pub async fn record_crypto_refund(
+    tx: &mut Transaction<'_, Postgres>,
+    refund: &CryptoRefund,
+) -> Result<(), DefiantError> {
+    post(tx, Booking {
+        merchant_id: refund.merchant_id,
+        kind: "crypto_refund",
+        currency: &refund.currency,
+        amount: refund.amount,
+        debit: LedgerAccountCode::CryptoSales,
+        credit: LedgerAccountCode::CryptoWallet,
+        description: refund.reason.as_deref(),
+        metadata: serde_json::json!({ "payment_id": refund.payment_id, "crypto_refund_id": refund.id }),
+        available_on: None,
+    })
+    .await?;
+    
+    Ok(())
+}
+
+/// Funds in the merchant balance account that are past their settlement delay, per
+/// currency.
+pub async fn available_balances(pool: &PgPool, merchant_id: Uuid) -> Result<Vec<AvailableBalance>, DefiantError> {
+    available_balances_at(pool, merchant_id, Utc::now()).await
+}
+
+/// Funds in the merchant balance account that are, or will be, available at `at`, from
+/// the entries booked so far.
+pub async fn available_balances_at(
+    pool: &PgPool,
+    merchant_id: Uuid,
+    at: DateTime<Utc>,
+) -> Result<Vec<AvailableBalance>, DefiantError> {
+    let available = sqlx::query_as!(
+        AvailableBalance,
+        r#"
+        SELECT a.currency, COALESCE(SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS "amount!"
+        FROM journal_lines l
+        JOIN ledger_accounts a ON a.id = l.account_id
+        JOIN journal_entries e ON e.id = l.journal_entry_id
+        WHERE a.merchant_id = $1 AND a.code = $2 AND e.available_on <= $3
+        GROUP BY a.currency
+        ORDER BY a.currency
+        "#,
+        merchant_id,
+        LedgerAccountCode::MerchantBalance.as_str(),
+        at,
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(available)
+}
+
+/// Available and pending funds in the merchant balance account, and funds held in
+/// reserve, per currency.
+pub async fn balance(pool: &PgPool, merchant_id: Uuid) -> Result<BalanceResponse, DefiantError> {
+    let pending = sqlx::query_as!(
+        AvailableBalance,
+        r#"
+        SELECT a.currency, COALESCE(SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS "amount!"
+        FROM journal_lines l
+        JOIN ledger_accounts a ON a.id = l.account_id
+        JOIN journal_entries e ON e.id = l.journal_entry_id
+        WHERE a.merchant_id = $1 AND a.code = $2 AND e.available_on > NOW()
+        GROUP BY a.currency
+        ORDER BY a.currency
+        "#,
+        merchant_id,
+        LedgerAccountCode::MerchantBalance.as_str(),
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    let reserved = sqlx::query_as!(
+        AvailableBalance,
+        r#"
+        SELECT a.currency, COALESCE(SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS "amount!"
+        FROM journal_lines l
+        JOIN ledger_accounts a ON a.id = l.account_id
+        WHERE a.merchant_id = $1 AND a.code = $2
+        GROUP BY a.currency
+        HAVING SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END) <> 0
+        ORDER BY a.currency
+        "#,
+        merchant_id,
+        LedgerAccountCode::Reserve.as_str(),
+    )
+    .fetch_all(pool)
+    .await?;
+    
+    Ok(BalanceResponse {
+        available: available_balances(pool, merchant_id).await?,
+        pending,
+        reserved,
+    })
+}
+
+/// Available funds in one currency, with the merchant's balance account for it locked
+/// until the transaction ends so concurrent payouts cannot spend them twice.
+pub async fn lock_available(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    currency: &str,
+) -> Result<i64, DefiantError> {
+    let account_id = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM ledger_accounts
+        WHERE merchant_id = $1 AND code = $2 AND currency = $3
+        FOR UPDATE
+        "#,
+        merchant_id,
+        LedgerAccountCode::MerchantBalance.as_str(),
+        currency.to_uppercase(),
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    let account_id = match account_id {
+        Some(account_id) => account_id,
+        None => return Ok(0),
+    };
+    
+    let available = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS "amount!"
+        FROM journal_lines l
+        JOIN journal_entries e ON e.id = l.journal_entry_id
+        WHERE l.account_id = $1 AND e.available_on <= NOW()
+        "#,
+        account_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(available)
+}
+
+/// The part of the available balance in `currency` that instant payouts may spend:
+/// funds card payments made available that no payout has claimed yet. Call with the
+/// balance account locked by `lock_available`.
+pub async fn instant_available(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    currency: &str,
+) -> Result<i64, DefiantError> {
+    let card_kinds: Vec<String> = [BalanceTransactionType::Charge, BalanceTransactionType::Refund, BalanceTransactionType::Fee, BalanceTransactionType::Reserve]
+        .iter()
+        .flat_map(|kind| kind.kinds())
+        .map(|kind| kind.to_string())
+        .collect();
+    
+    let unclaimed = sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END), 0)::BIGINT AS "amount!"
+        FROM journal_lines l
+        JOIN ledger_accounts a ON a.id = l.account_id
+        JOIN journal_entries e ON e.id = l.journal_entry_id
+        WHERE a.merchant_id = $1 AND a.code = $2 AND a.currency = $3
+        AND e.available_on <= NOW()
+        AND e.kind = ANY($4)
+        AND NOT EXISTS (SELECT 1 FROM payout_journal_entries p WHERE p.journal_entry_id = e.id)
+        "#,
+        merchant_id,
+        LedgerAccountCode::MerchantBalance.as_str(),
+        currency.to_uppercase(),
+        &card_kinds,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(unclaimed)
+}
+
+#[derive(sqlx::FromRow)]
+struct BalanceEntryRow {
+    id: Uuid,
+    kind: String,
+    currency: String,
+    description: Option<String>,
+    metadata: serde_json::Value,
+    available_on: DateTime<Utc>,
+    created_at: Option<DateTime<Utc>>,
+    amount: i64,
+}
+
+impl BalanceEntryRow {
+    fn into_transaction(self, now: DateTime<Utc>) -> BalanceTransaction {
+        let source = ["transfer", "payment", "payout", "topup", "balance_conversion"].iter().find_map(|object| {
+            self.metadata[format!("{}_id", object)]
+                .as_str()
+                .and_then(|id| id.parse::<Uuid>().ok())
+                .map(|id| BalanceTransactionSource { object: object.to_string(), id })
+        });
+        let status = if self.available_on <= now {
+            BalanceTransactionStatus::Available
+        } else {
+            BalanceTransactionStatus::Pending
+        };
+        
+        BalanceTransaction {
+            id: self.id,
+            transaction_type: BalanceTransactionType::from_kind(&self.kind),
+            amount: self.amount,
+            currency: self.currency,
+            status,
+            available_on: self.available_on,
+            description: self.description,
+            source,
+            created_at: self.created_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct LineRow {
+    journal_entry_id: Uuid,
+    account: String,
+    direction: LedgerDirection,
+    amount: i64,
+}
+
+/// Read access to a merchant's ledger.
+pub struct LedgerService {
+    db: Arc<Database>,
+}
+
+impl LedgerService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn balances(&self, api_key: &str) -> Result<LedgerBalancesResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let data = sqlx::query_as!(
+            LedgerAccountBalance,
+            r#"
+            WITH totals AS (
+                SELECT
+                    a.code,
+                    a.currency,
+                    a.normal_balance,
+                    COALESCE(SUM(l.amount) FILTER (WHERE l.direction = 'debit'), 0)::BIGINT AS debits,
+                    COALESCE(SUM(l.amount) FILTER (WHERE l.direction = 'credit'), 0)::BIGINT AS credits
+                FROM ledger_accounts a
+                LEFT JOIN journal_lines l ON l.account_id = a.id
+                WHERE a.merchant_id = $1
+                GROUP BY a.id
+            )
+            SELECT
+                code AS "account!",
+                currency AS "currency!",
+                normal_balance AS "normal_balance!",
+                debits AS "debits!",
+                credits AS "credits!",
+                CASE WHEN normal_balance = 'debit' THEN debits - credits ELSE credits - debits END AS "balance!"
+            FROM totals
+            ORDER BY currency, code
+            "#,
+            merchant_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(LedgerBalancesResponse { data })
+    }
+    
+    /// Available and pending funds per currency, from the merchant balance account.
+    pub async fn balance(&self, api_key: &str) -> Result<BalanceResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        balance(pool, merchant_id).await
+    }
+    
+    /// Every entry that moved the merchant balance account, newest first.
+    pub async fn list_balance_transactions(
+        &self,
+        query: BalanceTransactionListQuery,
+        api_key: &str,
+    ) -> Result<BalanceTransactionListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        let kinds = query.transaction_type
+            .map(|transaction_type| transaction_type.kinds().iter().map(|kind| kind.to_string()).collect::<Vec<_>>());
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut rows = sqlx::query_as!(
+            BalanceEntryRow,
+            r#"
+            SELECT
+                e.id, e.kind, e.currency, e.description, e.metadata, e.available_on, e.created_at,
+                SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END)::BIGINT AS "amount!"
+            FROM journal_entries e
+            JOIN journal_lines l ON l.journal_entry_id = e.id
+            JOIN ledger_accounts a ON a.id = l.account_id AND a.code = $2
+            WHERE e.merchant_id = $1
+            AND ($3::TEXT[] IS NULL OR e.kind = ANY($3))
+            AND ($4::TEXT IS NULL OR e.currency = UPPER($4))
+            GROUP BY e.id
+            ORDER BY e.created_at DESC, e.id
+            LIMIT $5
+            "#,
+            merchant_id,
+            LedgerAccountCode::MerchantBalance.as_str(),
+            kinds.as_deref(),
+            query.currency,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        
+        let now = Utc::now();
+        Ok(BalanceTransactionListResponse {
+            data: rows.into_iter().map(|row| row.into_transaction(now)).collect(),
+            has_more,
+            url: "/api/v1/balance_transactions".into(),
+        })
+    }
+    
+    /// The movements a payout paid out, oldest first.
+    pub async fn list_payout_transactions(
+        &self,
+        payout_id: Uuid,
+        query: BalanceTransactionListQuery,
+        api_key: &str,
+    ) -> Result<BalanceTransactionListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        let kinds = query.transaction_type
+            .map(|transaction_type| transaction_type.kinds().iter().map(|kind| kind.to_string()).collect::<Vec<_>>());
+        
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM payouts WHERE id = $1 AND merchant_id = $2) AS "exists!""#,
+            payout_id,
+            merchant_id,
+        )
+        .fetch_one(pool)
+        .await?;
+        if !exists {
+            return Err(DefiantError::NotFound("Payout not found".into()));
+        }
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut rows = sqlx::query_as!(
+            BalanceEntryRow,
+            r#"
+            SELECT
+                e.id, e.kind, e.currency, e.description, e.metadata, e.available_on, e.created_at,
+                SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END)::BIGINT AS "amount!"
+            FROM payout_journal_entries p
+            JOIN journal_entries e ON e.id = p.journal_entry_id
+            JOIN journal_lines l ON l.journal_entry_id = e.id
+            JOIN ledger_accounts a ON a.id = l.account_id AND a.code = $3
+            WHERE p.payout_id = $1 AND p.merchant_id = $2
+            AND ($4::TEXT[] IS NULL OR e.kind = ANY($4))
+            GROUP BY e.id
+            ORDER BY e.created_at, e.id
+            LIMIT $5
+            "#,
+            payout_id,
+            merchant_id,
+            LedgerAccountCode::MerchantBalance.as_str(),
+            kinds.as_deref(),
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        
+        let now = Utc::now();
+        Ok(BalanceTransactionListResponse {
+            data: rows.into_iter().map(|row| row.into_transaction(now)).collect(),
+            has_more,
+            url: format!("/api/v1/payouts/{}/transactions", payout_id),
+        })
+    }
+    
+    pub async fn list_entries(
+        &self,
+        query: JournalEntryListQuery,
+        api_key: &str,
+    ) -> Result<JournalEntryListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut entries = sqlx::query_as!(
+            JournalEntry,
+            r#"
+            SELECT * FROM journal_entries
+            WHERE merchant_id = $1
+            AND ($2::TEXT IS NULL OR kind = $2)
+            AND ($3::TEXT IS NULL OR metadata->>'payment_id' = $3)
+            ORDER BY created_at DESC, id
+            LIMIT $4
+            "#,
+            merchant_id,
+            query.kind,
+            query.payment_id.map(|id| id.to_string()),
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = entries.len() as i64 > limit;
+        entries.truncate(limit as usize);
+        
+        let entry_ids: Vec<Uuid> = entries.iter().map(|entry| entry.id).collect();
+        let lines = sqlx::query_as!(
+            LineRow,
+            r#"
+            SELECT l.journal_entry_id, a.code AS account, l.direction, l.amount
+            FROM journal_lines l
+            JOIN ledger_accounts a ON a.id = l.account_id
+            WHERE l.journal_entry_id = ANY($1)
+            ORDER BY l.direction, a.code
+            "#,
+            &entry_ids,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let data = entries
+            .into_iter()
+            .map(|entry| {
+                let lines = lines
+                    .iter()
+                    .filter(|line| line.journal_entry_id == entry.id)
+                    .map(|line| JournalLine {
+                        account: line.account.clone(),
+                        direction: line.direction,
+                        amount: line.amount,
+                    })
+                    .collect();
+                JournalEntryWithLines { entry, lines }
+            })
+            .collect();
+        
+        Ok(JournalEntryListResponse {
+            data,
+            has_more,
+            url: "/api/v1/ledger/entries".into(),
+        })
+    }
+}
@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use tracing::{info, error};
+
+use crate::{models::{Payment, PaymentStatus, PAYMENT_CANCELED}, errors::DefiantError, db::Database, config::Partitioning};
+use super::webhook_service::WebhookService;
+
+/// Card networks release uncaptured authorizations after about a week, so a payment
+/// still awaiting capture by then can no longer be captured.
+const AUTHORIZATION_VALIDITY_DAYS: i32 = 7;
+/// Spent and expired sessions, account tokens and OAuth codes are kept this long for
+/// investigating misuse, then purged.
+const EXPIRED_CREDENTIAL_RETENTION_DAYS: i32 = 30;
+
+/// Housekeeping run by the scheduler: expiring stale authorizations, purging
+/// short-lived credentials once they are of no further use, and keeping the monthly
+/// partitions of payments and events in step with the calendar.
+pub struct MaintenanceService {
+    db: Arc<Database>,
+}
+
+impl MaintenanceService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Cancels payments whose authorization has lapsed uncaptured.
+    pub async fn expire_authorizations(&self) -> Result<(), DefiantError> {
+        for pool in self.db.pools() {
+            let expired = sqlx::query_as!(
+                Payment,
+                r#"
+                UPDATE payments SET status = $1, updated_at = NOW()
+                WHERE status = 'requires_capture'
+                AND created_at < NOW() - make_interval(days => $2)
+                RETURNING *
+                "#,
+                PaymentStatus::Canceled as PaymentStatus,
+                AUTHORIZATION_VALIDITY_DAYS,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            for payment in &expired {
+                info!("Authorization for payment {} expired uncaptured", payment.id);
+                if let Err(e) = WebhookService::new(self.db.clone())
+                    .enqueue_event(payment.merchant_id, PAYMENT_CANCELED, serde_json::json!(payment))
+                    .await
+                {
+                    error!("Failed to enqueue {} event: {}", PAYMENT_CANCELED, e);
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    pub async fn purge_expired(&self) -> Result<(), DefiantError> {
+        for pool in self.db.pools() {
+            self.purge_card_tokens(pool).await?;
+        }
+        
+        let sessions = sqlx::query!(
+            "DELETE FROM auth_sessions WHERE expires_at < NOW() - make_interval(days => $1)",
+            EXPIRED_CREDENTIAL_RETENTION_DAYS,
+        )
+        .execute(&self.db.pool)
+        .await?
+        .rows_affected();
+        
+        let account_tokens = sqlx::query!(
+            "DELETE FROM account_tokens WHERE expires_at < NOW() - make_interval(days => $1)",
+            EXPIRED_CREDENTIAL_RETENTION_DAYS,
+        )
+        .execute(&self.db.pool)
+        .await?
+        .rows_affected();
+        
+        // Tokens issued from a code keep working after it is purged; they only lose the link
+        let authorization_codes = sqlx::query!(
+            "DELETE FROM oauth_authorization_codes WHERE expires_at < NOW() - make_interval(days => $1)",
+            EXPIRED_CREDENTIAL_RETENTION_DAYS,
+        )
+        .execute(&self.db.pool)
+        .await?
+        .rows_affected();
+        
+        info!(
+            "Purged {} sessions, {} account tokens and {} authorization codes",
+            sessions, account_tokens, authorization_codes
+        );
+        
+        Ok(())
+    }
+    
+    /// Creates the coming months' partitions of payments and events, and moves those
+    /// past their retention into the archive schema. Both steps are idempotent, so a
+    /// missed run is caught up by the next and a repeated one changes nothing.
+    pub async fn maintain_partitions(&self, settings: &Partitioning) -> Result<(), DefiantError> {
+        let tables = [
+            ("payments", settings.payments_retention_months),
+            ("events", settings.events_retention_months),
+        ];
+        
+        for pool in self.db.pools() {
+            for (table, retention_months) in tables {
+                let created = sqlx::query_scalar!(
+                    r#"SELECT create_monthly_partitions($1::TEXT::REGCLASS, $2) AS "created!""#,
+                    table,
+                    settings.months_ahead as i32,
+                )
+                .fetch_one(pool)
+                .await?;
+                if created > 0 {
+                    info!("Created {} monthly partitions of {}", created, table);
+                }
+                
+                let Some(retention_months) = retention_months else {
+                    continue;
+                };
+                // Whole months only: a partition goes once its last day is past retention
+                let archived = sqlx::query_scalar!(
+                    r#"
+                    SELECT archived AS "archived!"
+                    FROM archive_partitions_before(
+                        $1::TEXT::REGCLASS,
+                        date_trunc('month', NOW(), 'UTC') - make_interval(months => $2)
+                    ) AS archived
+                    "#,
+                    table,
+                    retention_months as i32,
+                )
+                .fetch_all(pool)
+                .await?;
+                for partition in archived {
+                    info!("Archived partition {} of {}", partition, table);
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Card tokens are single-use and hold card data, so they go as soon as they expire.
+    async fn purge_card_tokens(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let purged = sqlx::query!("DELETE FROM card_tokens WHERE expires_at < NOW()")
+            .execute(pool)
+            .await?
+            .rows_affected();
+        
+        info!("Purged {} expired card tokens", purged);
+        Ok(())
+    }
+}
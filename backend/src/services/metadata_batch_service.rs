@@ -0,0 +1,426 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, error};
+
+use crate::{models::{MetadataBatchUpdate, MetadataBatchObject, MetadataBatchStatus, MetadataBatchFilter, CreateMetadataBatchUpdateRequest}, errors::DefiantError, db::Database};
+use crate::shutdown::Shutdown;
+
+const MAX_FILTER_IDS: usize = 10_000;
+const MAX_PATCH_KEYS: usize = 50;
+const BATCH_SIZE: i64 = 500;
+const WORKER_POLL_INTERVAL_SECS: u64 = 5;
+/// A running job whose lease lapses is assumed dead and resumed from its cursor.
+const LEASE_SECS: i64 = 120;
+
+/// Metadata patches applied to many payments or customers at once, for backfills
+/// too large to make one update call per object.
+pub struct MetadataBatchService {
+    db: Arc<Database>,
+}
+
+impl MetadataBatchService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Queues the patch; the worker applies it in the background and progress is
+    /// reported through `get_batch_update`.
+    pub async fn request_batch_update(
+        &self,
+        object: MetadataBatchObject,
+        request: CreateMetadataBatchUpdateRequest,
+        api_key: &str,
+    ) -> Result<MetadataBatchUpdate, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        if request.filter.is_empty() {
+            return Err(DefiantError::ValidationError(
+                "filter: at least one of ids, created_gte, created_lte or metadata is required".into(),
+            ));
+        }
+        if request.filter.ids.as_ref().map_or(false, |ids| ids.is_empty() || ids.len() > MAX_FILTER_IDS) {
+            return Err(DefiantError::ValidationError(format!("filter.ids: between 1 and {} ids", MAX_FILTER_IDS)));
+        }
+        if let (Some(gte), Some(lte)) = (request.filter.created_gte, request.filter.created_lte) {
+            if gte > lte {
+                return Err(DefiantError::ValidationError("filter: created_gte must not be after created_lte".into()));
+            }
+        }
+        if request.metadata.is_empty() || request.metadata.len() > MAX_PATCH_KEYS {
+            return Err(DefiantError::ValidationError(format!("metadata: between 1 and {} keys", MAX_PATCH_KEYS)));
+        }
+        
+        let batch = sqlx::query_as!(
+            MetadataBatchUpdate,
+            r#"
+            INSERT INTO metadata_batch_updates (merchant_id, object, filter, patch)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            merchant_id,
+            object as MetadataBatchObject,
+            serde_json::json!(request.filter),
+            serde_json::Value::Object(request.metadata),
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Metadata batch update {} requested for {:?} objects of merchant {}", batch.id, object, merchant_id);
+        Ok(batch)
+    }
+    
+    pub async fn get_batch_update(
+        &self,
+        object: MetadataBatchObject,
+        batch_id: Uuid,
+        api_key: &str,
+    ) -> Result<MetadataBatchUpdate, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let batch = sqlx::query_as!(
+            MetadataBatchUpdate,
+            r#"SELECT * FROM metadata_batch_updates WHERE id = $1 AND merchant_id = $2 AND object = $3"#,
+            batch_id,
+            merchant_id,
+            object as MetadataBatchObject,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Metadata batch update not found".into()))?;
+        
+        Ok(batch)
+    }
+}
+
+/// Runs queued batch updates in the background, one job at a time per cluster.
+pub fn spawn_batch_update_worker(db: Arc<Database>, shutdown: &Shutdown) {
+    for pool in db.pools() {
+        let worker = BatchUpdateWorker { pool: pool.clone() };
+        
+        shutdown.spawn(|mut shutdown| async move {
+            let mut interval = tokio::time::interval(StdDuration::from_secs(WORKER_POLL_INTERVAL_SECS));
+            
+            while shutdown.tick(&mut interval).await {
+                loop {
+                    match worker.claim_next().await {
+                        Ok(Some(batch)) => worker.run(batch).await,
+                        Ok(None) => break,
+                        Err(e) => {
+                            error!("Failed to claim metadata batch update: {}", e);
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+    }
+}
+
+struct BatchUpdateWorker {
+    pool: PgPool,
+}
+
+/// Filter columns as bound query parameters.
+struct FilterParams {
+    ids: Option<Vec<Uuid>>,
+    created_gte: Option<DateTime<Utc>>,
+    created_lte: Option<DateTime<Utc>>,
+    metadata: Option<serde_json::Value>,
+}
+
+/// Patch split into keys to merge in and keys to remove.
+struct PatchParams {
+    set: serde_json::Value,
+    remove: Vec<String>,
+}
+
+impl BatchUpdateWorker {
+    async fn claim_next(&self) -> Result<Option<MetadataBatchUpdate>, DefiantError> {
+        let batch = sqlx::query_as!(
+            MetadataBatchUpdate,
+            r#"
+            UPDATE metadata_batch_updates
+            SET status = $1, started_at = COALESCE(started_at, NOW()), locked_until = $2
+            WHERE id = (
+                SELECT id FROM metadata_batch_updates
+                WHERE status = 'pending' OR (status = 'running' AND locked_until < NOW())
+                ORDER BY created_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            MetadataBatchStatus::Running as MetadataBatchStatus,
+            Utc::now() + Duration::seconds(LEASE_SECS),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(batch)
+    }
+    
+    async fn run(&self, batch: MetadataBatchUpdate) {
+        info!("Running metadata batch update {}", batch.id);
+        
+        if let Err(e) = self.process(&batch).await {
+            error!("Metadata batch update {} failed: {}", batch.id, e);
+            if let Err(e) = sqlx::query!(
+                r#"UPDATE metadata_batch_updates SET status = $1, error = $2, locked_until = NULL WHERE id = $3"#,
+                MetadataBatchStatus::Failed as MetadataBatchStatus,
+                e.to_string(),
+                batch.id,
+            )
+            .execute(&self.pool)
+            .await
+            {
+                error!("Failed to record metadata batch update {} failure: {}", batch.id, e);
+            }
+        }
+    }
+    
+    async fn process(&self, batch: &MetadataBatchUpdate) -> Result<(), DefiantError> {
+        let filter: MetadataBatchFilter = serde_json::from_value(batch.filter.clone())
+            .map_err(|_| DefiantError::InternalError)?;
+        let filter = FilterParams {
+            ids: filter.ids,
+            created_gte: filter.created_gte,
+            created_lte: filter.created_lte,
+            metadata: filter.metadata.map(serde_json::Value::Object),
+        };
+        let patch = split_patch(&batch.patch);
+        
+        // Counted once, on the first run, so resuming does not reset progress
+        if batch.cursor_created_at.is_none() {
+            let matched = self.count_matching(batch, &filter).await?;
+            sqlx::query!(
+                r#"UPDATE metadata_batch_updates SET matched_objects = $1 WHERE id = $2"#,
+                matched as i32,
+                batch.id,
+            )
+            .execute(&self.pool)
+            .await?;
+        }
+        
+        let mut cursor = batch.cursor_created_at.zip(batch.cursor_object_id);
+        
+        loop {
+            match self.apply_batch(batch, &filter, &patch, cursor).await? {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        
+        sqlx::query!(
+            r#"UPDATE metadata_batch_updates SET status = $1, completed_at = NOW(), locked_until = NULL WHERE id = $2"#,
+            MetadataBatchStatus::Completed as MetadataBatchStatus,
+            batch.id,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        info!("Metadata batch update {} completed", batch.id);
+        Ok(())
+    }
+    
+    async fn count_matching(&self, batch: &MetadataBatchUpdate, filter: &FilterParams) -> Result<i64, DefiantError> {
+        let count = match batch.object {
+            MetadataBatchObject::Payment => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "count!" FROM payments
+                WHERE merchant_id = $1
+                AND ($2::UUID[] IS NULL OR id = ANY($2))
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+                AND ($5::JSONB IS NULL OR metadata @> $5)
+                "#,
+                batch.merchant_id,
+                filter.ids.as_deref(),
+                filter.created_gte,
+                filter.created_lte,
+                filter.metadata,
+            )
+            .fetch_one(&self.pool)
+            .await?,
+            MetadataBatchObject::Customer => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "count!" FROM customers
+                WHERE merchant_id = $1
+                AND ($2::UUID[] IS NULL OR id = ANY($2))
+                AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+                AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+                AND ($5::JSONB IS NULL OR metadata @> $5)
+                "#,
+                batch.merchant_id,
+                filter.ids.as_deref(),
+                filter.created_gte,
+                filter.created_lte,
+                filter.metadata,
+            )
+            .fetch_one(&self.pool)
+            .await?,
+        };
+        
+        Ok(count)
+    }
+    
+    /// Patches the next batch after `cursor` and advances the stored cursor in the same
+    /// transaction. Returns the new cursor, or `None` once every match has been patched.
+    async fn apply_batch(
+        &self,
+        batch: &MetadataBatchUpdate,
+        filter: &FilterParams,
+        patch: &PatchParams,
+        cursor: Option<(DateTime<Utc>, Uuid)>,
+    ) -> Result<Option<(DateTime<Utc>, Uuid)>, DefiantError> {
+        let mut tx = self.pool.begin().await?;
+        
+        let rows = match batch.object {
+            MetadataBatchObject::Payment => patch_payments(&mut tx, batch.merchant_id, filter, patch, cursor).await?,
+            MetadataBatchObject::Customer => patch_customers(&mut tx, batch.merchant_id, filter, patch, cursor).await?,
+        };
+        
+        let next = match rows.last() {
+            Some(last) => *last,
+            None => {
+                tx.rollback().await?;
+                return Ok(None);
+            }
+        };
+        
+        sqlx::query!(
+            r#"
+            UPDATE metadata_batch_updates
+            SET updated_objects = updated_objects + $1, cursor_created_at = $2, cursor_object_id = $3, locked_until = $4
+            WHERE id = $5
+            "#,
+            rows.len() as i32,
+            next.0,
+            next.1,
+            Utc::now() + Duration::seconds(LEASE_SECS),
+            batch.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        Ok(Some(next))
+    }
+}
+
+/// Returns the `(created_at, id)` of each patched payment in walk order.
+async fn patch_payments(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    filter: &FilterParams,
+    patch: &PatchParams,
+    cursor: Option<(DateTime<Utc>, Uuid)>,
+) -> Result<Vec<(DateTime<Utc>, Uuid)>, DefiantError> {
+    let (cursor_created_at, cursor_id) = cursor.unzip();
+    
+    let rows = sqlx::query!(
+        r#"
+        WITH batch AS (
+            SELECT id FROM payments
+            WHERE merchant_id = $1
+            AND ($2::UUID[] IS NULL OR id = ANY($2))
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+            AND ($5::JSONB IS NULL OR metadata @> $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR (created_at, id) > ($6, $7))
+            ORDER BY created_at, id
+            LIMIT $8
+            FOR UPDATE
+        )
+        UPDATE payments p
+        SET metadata = (COALESCE(p.metadata, '{}'::jsonb) || $9) - $10::TEXT[], updated_at = NOW()
+        FROM batch WHERE p.id = batch.id
+        RETURNING p.id, p.created_at AS "created_at!"
+        "#,
+        merchant_id,
+        filter.ids.as_deref(),
+        filter.created_gte,
+        filter.created_lte,
+        filter.metadata,
+        cursor_created_at,
+        cursor_id,
+        BATCH_SIZE,
+        patch.set,
+        &patch.remove,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    
+    let mut rows: Vec<_> = rows.into_iter().map(|row| (row.created_at, row.id)).collect();
+    rows.sort();
+    Ok(rows)
+}
+
+async fn patch_customers(
+    tx: &mut Transaction<'_, Postgres>,
+    merchant_id: Uuid,
+    filter: &FilterParams,
+    patch: &PatchParams,
+    cursor: Option<(DateTime<Utc>, Uuid)>,
+) -> Result<Vec<(DateTime<Utc>, Uuid)>, DefiantError> {
+    let (cursor_created_at, cursor_id) = cursor.unzip();
+    
+    let rows = sqlx::query!(
+        r#"
+        WITH batch AS (
+            SELECT id FROM customers
+            WHERE merchant_id = $1
+            AND ($2::UUID[] IS NULL OR id = ANY($2))
+            AND ($3::TIMESTAMPTZ IS NULL OR created_at >= $3)
+            AND ($4::TIMESTAMPTZ IS NULL OR created_at <= $4)
+            AND ($5::JSONB IS NULL OR metadata @> $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR (created_at, id) > ($6, $7))
+            ORDER BY created_at, id
+            LIMIT $8
+            FOR UPDATE
+        )
+        UPDATE customers c
+        SET metadata = (COALESCE(c.metadata, '{}'::jsonb) || $9) - $10::TEXT[], updated_at = NOW()
+        FROM batch WHERE c.id = batch.id
+        RETURNING c.id, c.created_at AS "created_at!"
+        "#,
+        merchant_id,
+        filter.ids.as_deref(),
+        filter.created_gte,
+        filter.created_lte,
+        filter.metadata,
+        cursor_created_at,
+        cursor_id,
+        BATCH_SIZE,
+        patch.set,
+        &patch.remove,
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+    
+    let mut rows: Vec<_> = rows.into_iter().map(|row| (row.created_at, row.id)).collect();
+    rows.sort();
+    Ok(rows)
+}
+
+fn split_patch(patch: &serde_json::Value) -> PatchParams {
+    let mut set = serde_json::Map::new();
+    let mut remove = Vec::new();
+    
+    if let Some(entries) = patch.as_object() {
+        for (key, value) in entries {
+            if value.is_null() {
+                remove.push(key.clone());
+            } else {
+                set.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    
+    PatchParams { set: serde_json::Value::Object(set), remove }
+}
@@ -0,0 +1,335 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use chrono::Utc;
+use rand::{distributions::Alphanumeric, Rng};
+use ring::{digest, hmac};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, warn};
+
+use crate::{models::{MfaEnrollment, MfaSetupResponse, MfaStatus, MfaPolicy, UpdateMfaPolicyRequest, NewSecurityEvent, SecurityEventType}, errors::DefiantError, db::Database, config::Config};
+use super::webhook_service::SecretCipher;
+use super::security_event_service::SecurityEventService;
+
+const ISSUER: &str = "Defiant";
+const SECRET_BYTES: usize = 20;
+const STEP_SECONDS: i64 = 30;
+/// Steps either side of the current one still accepted, for clock drift
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const BACKUP_CODE_COUNT: usize = 10;
+
+/// Outcome of the second-factor check at sign-in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SignIn {
+    /// The user passed MFA, or does not need it
+    Allowed,
+    /// The merchant requires MFA and the user has not enrolled yet
+    EnrollmentRequired,
+}
+
+/// TOTP second factor (RFC 6238, SHA-1, six digits) for dashboard sign-in.
+pub struct MfaService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl MfaService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Starts enrollment with a fresh secret and backup codes, replacing any earlier
+    /// enrollment that was never verified.
+    pub async fn setup(&self, subject: &str, merchant_id: Option<Uuid>) -> Result<MfaSetupResponse, DefiantError> {
+        let secret: [u8; SECRET_BYTES] = rand::random();
+        let backup_codes: Vec<String> = (0..BACKUP_CODE_COUNT).map(|_| generate_backup_code()).collect();
+        let backup_code_hashes: Vec<String> = backup_codes.iter().map(|code| hash_backup_code(code)).collect();
+        
+        let replaced = sqlx::query!(
+            r#"
+            INSERT INTO mfa_enrollments (subject, merchant_id, encrypted_secret, backup_code_hashes)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (subject) DO UPDATE
+            SET encrypted_secret = EXCLUDED.encrypted_secret,
+                backup_code_hashes = EXCLUDED.backup_code_hashes,
+                last_used_step = NULL
+            WHERE mfa_enrollments.enabled_at IS NULL
+            "#,
+            subject,
+            merchant_id,
+            self.cipher()?.seal(&hex::encode(secret))?,
+            &backup_code_hashes,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        if replaced.rows_affected() == 0 {
+            return Err(DefiantError::Conflict("MFA is already enabled".into()));
+        }
+        
+        let secret = base32(&secret);
+        let provisioning_uri = format!(
+            "otpauth://totp/{issuer}:{subject}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits=6&period={STEP_SECONDS}",
+            issuer = ISSUER,
+        );
+        
+        Ok(MfaSetupResponse { secret, provisioning_uri, backup_codes })
+    }
+    
+    /// Enables MFA once the authenticator app produces a valid code.
+    pub async fn verify_enrollment(
+        &self,
+        subject: &str,
+        code: &str,
+        client_ip: Option<IpAddr>,
+    ) -> Result<MfaStatus, DefiantError> {
+        let mut tx = self.db.pool.begin().await?;
+        let enrollment = self.lock_enrollment(&mut tx, subject).await?
+            .ok_or_else(|| DefiantError::NotFound("No MFA enrollment in progress".into()))?;
+        
+        if enrollment.enabled_at.is_some() {
+            return Err(DefiantError::Conflict("MFA is already enabled".into()));
+        }
+        if !self.accept_totp(&mut tx, &enrollment, code).await? {
+            return Err(DefiantError::ValidationError("Invalid MFA code".into()));
+        }
+        
+        let enabled = sqlx::query_as!(
+            MfaEnrollment,
+            r#"UPDATE mfa_enrollments SET enabled_at = NOW() WHERE subject = $1 RETURNING *"#,
+            subject,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        
+        info!("MFA enabled for {}", subject);
+        if let Some(merchant_id) = enabled.merchant_id {
+            self.record(NewSecurityEvent {
+                subject: Some(subject.to_string()),
+                ip_address: client_ip.map(|ip| ip.to_string()),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::MfaEnabled)
+            })
+            .await;
+        }
+        Ok(MfaStatus {
+            enabled: true,
+            enabled_at: enabled.enabled_at,
+            backup_codes_remaining: enabled.backup_code_hashes.len(),
+        })
+    }
+    
+    /// Called before a session starts. Users with MFA enabled must pass a current
+    /// code or an unused backup code; users of a merchant that requires MFA who have
+    /// not enrolled may only start a session to enroll.
+    pub(crate) async fn check_sign_in(
+        &self,
+        subject: &str,
+        merchant_id: Option<Uuid>,
+        code: Option<&str>,
+        client_ip: Option<IpAddr>,
+    ) -> Result<SignIn, DefiantError> {
+        let mut tx = self.db.pool.begin().await?;
+        let enrollment = self.lock_enrollment(&mut tx, subject).await?
+            .filter(|enrollment| enrollment.enabled_at.is_some());
+        
+        let Some(enrollment) = enrollment else {
+            if let Some(merchant_id) = merchant_id {
+                if self.policy(merchant_id).await?.mfa_required {
+                    return Ok(SignIn::EnrollmentRequired);
+                }
+            }
+            return Ok(SignIn::Allowed);
+        };
+        
+        let code = code.ok_or_else(|| DefiantError::AuthenticationError("MFA code required".into()))?;
+        if self.accept_totp(&mut tx, &enrollment, code).await? {
+            tx.commit().await?;
+            return Ok(SignIn::Allowed);
+        }
+        if !self.accept_backup_code(&mut tx, &enrollment, code).await? {
+            warn!("Rejected MFA code for {}", subject);
+            return Err(DefiantError::AuthenticationError("Invalid MFA code".into()));
+        }
+        tx.commit().await?;
+        
+        if let Some(merchant_id) = enrollment.merchant_id {
+            self.record(NewSecurityEvent {
+                subject: Some(subject.to_string()),
+                ip_address: client_ip.map(|ip| ip.to_string()),
+                details: serde_json::json!({ "backup_codes_remaining": enrollment.backup_code_hashes.len() - 1 }),
+                ..NewSecurityEvent::new(merchant_id, SecurityEventType::MfaBackupCodeUsed)
+            })
+            .await;
+        }
+        Ok(SignIn::Allowed)
+    }
+    
+    pub async fn policy(&self, merchant_id: Uuid) -> Result<MfaPolicy, DefiantError> {
+        let mfa_required = sqlx::query_scalar!(
+            r#"SELECT mfa_required FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        Ok(MfaPolicy { merchant_id, mfa_required })
+    }
+    
+    pub async fn update_policy(
+        &self,
+        merchant_id: Uuid,
+        request: UpdateMfaPolicyRequest,
+    ) -> Result<MfaPolicy, DefiantError> {
+        let mfa_required = sqlx::query_scalar!(
+            r#"UPDATE merchants SET mfa_required = $2 WHERE id = $1 RETURNING mfa_required"#,
+            merchant_id,
+            request.mfa_required,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        info!("MFA {} for merchant {}", if mfa_required { "required" } else { "optional" }, merchant_id);
+        self.record(NewSecurityEvent {
+            details: serde_json::json!({ "mfa_required": mfa_required }),
+            ..NewSecurityEvent::new(merchant_id, SecurityEventType::MfaPolicyUpdated)
+        })
+        .await;
+        Ok(MfaPolicy { merchant_id, mfa_required })
+    }
+    
+    async fn record(&self, event: NewSecurityEvent) {
+        SecurityEventService::new(self.db.clone()).record(event).await;
+    }
+    
+    async fn lock_enrollment(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        subject: &str,
+    ) -> Result<Option<MfaEnrollment>, DefiantError> {
+        let enrollment = sqlx::query_as!(
+            MfaEnrollment,
+            r#"SELECT * FROM mfa_enrollments WHERE subject = $1 FOR UPDATE"#,
+            subject,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        Ok(enrollment)
+    }
+    
+    /// Accepts a code for a step near now that is later than the last one used.
+    async fn accept_totp(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        enrollment: &MfaEnrollment,
+        code: &str,
+    ) -> Result<bool, DefiantError> {
+        if code.len() != 6 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(false);
+        }
+        
+        let secret = hex::decode(self.cipher()?.open(&enrollment.encrypted_secret)?)
+            .map_err(|_| DefiantError::InternalError)?;
+        let current = Utc::now().timestamp() / STEP_SECONDS;
+        let step = (current - ALLOWED_DRIFT_STEPS..=current + ALLOWED_DRIFT_STEPS)
+            .filter(|step| enrollment.last_used_step.map(|last| *step > last).unwrap_or(true))
+            .find(|step| format!("{:06}", totp(&secret, *step as u64)) == code);
+        
+        let Some(step) = step else {
+            return Ok(false);
+        };
+        
+        sqlx::query!(
+            r#"UPDATE mfa_enrollments SET last_used_step = $2 WHERE subject = $1"#,
+            enrollment.subject,
+            step,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        Ok(true)
+    }
+    
+    async fn accept_backup_code(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        enrollment: &MfaEnrollment,
+        code: &str,
+    ) -> Result<bool, DefiantError> {
+        let hash = hash_backup_code(code);
+        if !enrollment.backup_code_hashes.contains(&hash) {
+            return Ok(false);
+        }
+        
+        sqlx::query!(
+            r#"UPDATE mfa_enrollments SET backup_code_hashes = array_remove(backup_code_hashes, $2) WHERE subject = $1"#,
+            enrollment.subject,
+            hash,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        info!("Backup code used for {}; {} left", enrollment.subject, enrollment.backup_code_hashes.len() - 1);
+        Ok(true)
+    }
+    
+    fn cipher(&self) -> Result<SecretCipher, DefiantError> {
+        SecretCipher::from_hex(&self.config.mfa_encryption_key)
+    }
+}
+
+/// HOTP value (RFC 4226) for one time step.
+fn totp(secret: &[u8], step: u64) -> u32 {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret);
+    let tag = hmac::sign(&key, &step.to_be_bytes());
+    let digest = tag.as_ref();
+    
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+    binary % 1_000_000
+}
+
+/// RFC 4648 base32 without padding, as authenticator apps expect.
+fn base32(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+    
+    let mut encoded = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for byte in bytes {
+        buffer = (buffer << 8) | *byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            encoded.push(ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+        buffer &= (1 << bits) - 1;
+    }
+    if bits > 0 {
+        encoded.push(ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+    encoded
+}
+
+fn generate_backup_code() -> String {
+    let code: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(10)
+        .map(|c| char::from(c).to_ascii_lowercase())
+        .collect();
+    
+    format!("{}-{}", &code[..5], &code[5..])
+}
+
+fn hash_backup_code(code: &str) -> String {
+    let normalized = code.trim().to_ascii_lowercase();
+    hex::encode(digest::digest(&digest::SHA256, normalized.as_bytes()).as_ref())
+}
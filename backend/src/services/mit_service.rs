@@ -0,0 +1,142 @@
+use std::sync::Arc;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{CardDetails, CreatePaymentRequest, MitAgreement, MitAgreementType, Payment}, errors::DefiantError, db::Database, config::Config};
+use super::sca_service::card_fingerprint;
+
+/// Merchant-initiated transactions: agreements captured during a customer-initiated
+/// payment, and the checks every later off-session charge must pass against them.
+pub struct MitService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl MitService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn get_agreement(&self, agreement_id: Uuid, api_key: &str) -> Result<MitAgreement, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query_as!(
+            MitAgreement,
+            r#"
+            SELECT * FROM mit_agreements
+            WHERE id = $1 AND merchant_id = $2
+            "#,
+            agreement_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("MIT agreement not found".into()))
+    }
+    
+    /// Withdraws consent; later off-session charges under the agreement are refused.
+    pub async fn revoke_agreement(&self, agreement_id: Uuid, api_key: &str) -> Result<MitAgreement, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let agreement = sqlx::query_as!(
+            MitAgreement,
+            r#"
+            UPDATE mit_agreements
+            SET active = false, revoked_at = COALESCE(revoked_at, NOW())
+            WHERE id = $1 AND merchant_id = $2
+            RETURNING *
+            "#,
+            agreement_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("MIT agreement not found".into()))?;
+        
+        info!("MIT agreement {} revoked", agreement.id);
+        Ok(agreement)
+    }
+    
+    /// Finds the agreement an off-session charge relies on and checks it covers this
+    /// customer and card. Charges without one are rejected outright.
+    pub async fn authorize_off_session(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        request: &CreatePaymentRequest,
+    ) -> Result<MitAgreement, DefiantError> {
+        let agreement_id = request.mit_agreement_id.ok_or_else(|| {
+            DefiantError::PaymentError("Off-session charges require a merchant-initiated transaction agreement".into())
+        })?;
+        
+        let agreement = sqlx::query_as!(
+            MitAgreement,
+            r#"
+            SELECT * FROM mit_agreements
+            WHERE id = $1 AND merchant_id = $2
+            FOR SHARE
+            "#,
+            agreement_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("MIT agreement not found".into()))?;
+        
+        if !agreement.active {
+            return Err(DefiantError::PaymentError("MIT agreement has been revoked".into()));
+        }
+        
+        if request.customer_id != Some(agreement.customer_id) {
+            return Err(DefiantError::ValidationError("MIT agreement belongs to a different customer".into()));
+        }
+        
+        if let Some(card) = request.source.as_ref().and_then(|source| source.card.as_ref()) {
+            if card_fingerprint(&self.config.card_fingerprint_key, &card.number) != agreement.card_fingerprint {
+                return Err(DefiantError::ValidationError("MIT agreement was made for a different card".into()));
+            }
+        }
+        
+        Ok(agreement)
+    }
+    
+    /// Records the customer's consent given in `payment`, which must have been authorized.
+    pub async fn create_agreement(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        payment: &Payment,
+        customer_id: Uuid,
+        agreement_type: MitAgreementType,
+        card: &CardDetails,
+    ) -> Result<MitAgreement, DefiantError> {
+        let network_transaction_id = payment.network_transaction_id.as_deref().ok_or_else(|| {
+            DefiantError::PaymentError("An MIT agreement needs an authorized initial payment".into())
+        })?;
+        
+        let agreement = sqlx::query_as!(
+            MitAgreement,
+            r#"
+            INSERT INTO mit_agreements (
+                merchant_id, customer_id, agreement_type, card_fingerprint,
+                initial_payment_id, network_transaction_id
+            )
+            SELECT merchant_id, $2, $3, $4, id, $5
+            FROM payments WHERE id = $1
+            RETURNING *
+            "#,
+            payment.id,
+            customer_id,
+            agreement_type as MitAgreementType,
+            card_fingerprint(&self.config.card_fingerprint_key, &card.number),
+            network_transaction_id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        info!("MIT agreement {} created from payment {}", agreement.id, payment.id);
+        Ok(agreement)
+    }
+}
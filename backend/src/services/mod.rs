@@ -3,6 +3,57 @@ pub mod customer_service;
 pub mod webhook_service;
 pub mod subscription_service;
 pub mod invoice_service;
+pub mod hosted_invoice_service;
 pub mod email_service;
+pub mod communication_service;
 pub mod crypto_service;
-pub mod fraud_detection;
\ No newline at end of file
+pub mod crypto_watcher;
+pub mod crypto_rate_service;
+pub mod crypto_conversion_service;
+pub mod crypto_refund_service;
+pub mod crypto_payout_service;
+pub mod crypto_signer;
+pub mod crypto_fee_service;
+pub mod fx_service;
+pub mod checkout_service;
+pub mod report_service;
+pub mod receipt_service;
+pub mod quote_service;
+pub mod payout_service;
+pub mod sca_service;
+pub mod event_service;
+pub mod event_renderer;
+pub mod mit_service;
+pub mod consent_service;
+pub mod terminal_service;
+pub mod settlement_service;
+pub mod stripe_service;
+pub mod account_service;
+pub mod metadata_batch_service;
+pub mod dependency_service;
+pub mod security_service;
+pub mod security_event_service;
+pub mod statement_descriptor_service;
+pub mod fraud_detection;
+pub mod geoip_service;
+pub mod fraud_feedback_service;
+pub mod fraud_settings_service;
+pub mod ledger;
+pub mod connect_service;
+pub mod pricing;
+pub mod reserve_service;
+pub mod token_service;
+pub mod auth_service;
+pub mod mfa_service;
+pub mod team_service;
+pub mod oauth_service;
+pub mod verification_service;
+pub mod audit_service;
+pub mod maintenance_service;
+pub mod pagination;
+pub mod pii_service;
+pub mod object_storage;
+pub mod archive_service;
+pub mod search;
+pub mod event_stream;
+pub mod event_router;
@@ -0,0 +1,505 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::digest;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{models::{OAuthClient, CreateOAuthClientRequest, OAuthClientCreated, OAuthClientListQuery, OAuthClientListResponse, AuthorizationRequest, ConsentDetails, AuthorizationRedirect, OAuthTokenRequest, OAuthTokenResponse, OAuthToken, OAuthTokenLookup, IntrospectionResponse}, errors::DefiantError, db::{Database, AuthenticatedKey, hash_api_key}, api::v1::permissions::is_known_scope};
+
+pub(crate) const ACCESS_TOKEN_PREFIX: &str = "oat_";
+const REFRESH_TOKEN_PREFIX: &str = "ort_";
+const CODE_TTL_MINUTES: i64 = 10;
+const ACCESS_TOKEN_TTL_SECONDS: i64 = 3600;
+/// Resources an app can never be granted: it must not mint keys, manage the team,
+/// register apps of its own or read the security log
+const RESTRICTED_RESOURCES: &[&str] = &["api_keys", "team", "oauth_clients", "security", "audit_logs"];
+
+/// Whether a bearer token was issued by `/api/oauth/token`.
+pub(crate) fn is_oauth_token(token: &str) -> bool {
+    token.starts_with(ACCESS_TOKEN_PREFIX)
+}
+
+/// OAuth2 authorization-code provider (RFC 6749). Access tokens resolve like API
+/// keys holding the scopes the merchant approved.
+pub struct OAuthService {
+    db: Arc<Database>,
+}
+
+impl OAuthService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn register_client(
+        &self,
+        request: CreateOAuthClientRequest,
+        api_key: &str,
+    ) -> Result<OAuthClientCreated, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        for uri in &request.redirect_uris {
+            validate_redirect_uri(uri)?;
+        }
+        for scope in &request.scopes {
+            validate_scope(scope)?;
+        }
+        
+        let client_secret = generate_token("ocs_", 48);
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            INSERT INTO oauth_clients (client_id, client_secret_hash, merchant_id, name, redirect_uris, scopes)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *
+            "#,
+            generate_token("oc_", 24),
+            hash_api_key(&client_secret),
+            merchant_id,
+            request.name,
+            &request.redirect_uris,
+            &request.scopes,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("OAuth client {} registered by merchant {}", client.client_id, merchant_id);
+        Ok(OAuthClientCreated { client, client_secret })
+    }
+    
+    pub async fn list_clients(
+        &self,
+        query: OAuthClientListQuery,
+        api_key: &str,
+    ) -> Result<OAuthClientListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            OAuthClient,
+            r#"
+            SELECT * FROM oauth_clients
+            WHERE merchant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(OAuthClientListResponse {
+            data,
+            has_more,
+            url: "/api/v1/oauth/clients".into(),
+        })
+    }
+    
+    /// Checks an authorization request and describes it for the consent screen.
+    /// Errors here are shown to the merchant rather than sent to the app, since the
+    /// redirect URI may not be the app's.
+    pub async fn consent(
+        &self,
+        request: &AuthorizationRequest,
+        access_token: &str,
+    ) -> Result<ConsentDetails, DefiantError> {
+        let approver = self.approver(access_token).await?;
+        self.consent_for(request, &approver).await
+    }
+    
+    /// Records the merchant's decision and returns where to send them: back to the
+    /// app with a code, or with `error=access_denied`.
+    pub async fn decide(
+        &self,
+        request: &AuthorizationRequest,
+        approved: bool,
+        access_token: &str,
+    ) -> Result<AuthorizationRedirect, DefiantError> {
+        let approver = self.approver(access_token).await?;
+        let consent = self.consent_for(request, &approver).await?;
+        let state = request.state.as_deref();
+        
+        if !approved {
+            info!("Authorization of OAuth client {} denied", consent.client_id);
+            return Ok(redirect(&consent.redirect_uri, &RedirectParams { code: None, error: Some("access_denied"), state }));
+        }
+        
+        let code = generate_token("oac_", 40);
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_authorization_codes (
+                code_hash, client_id, merchant_id, scopes, redirect_uri, livemode,
+                code_challenge, approved_by, expires_at
+            )
+            SELECT $1, id, $3, $4, $5, $6, $7, $8, $9 FROM oauth_clients WHERE client_id = $2
+            "#,
+            hash_api_key(&code),
+            consent.client_id,
+            approver.merchant_id,
+            &consent.scopes,
+            consent.redirect_uri,
+            approver.livemode,
+            request.code_challenge,
+            approver.id.to_string(),
+            Utc::now() + Duration::minutes(CODE_TTL_MINUTES),
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        info!("Merchant {} authorized OAuth client {}", approver.merchant_id, consent.client_id);
+        Ok(redirect(&consent.redirect_uri, &RedirectParams { code: Some(&code), error: None, state }))
+    }
+    
+    /// The token endpoint, for the `authorization_code` and `refresh_token` grants.
+    pub async fn exchange(&self, request: OAuthTokenRequest) -> Result<OAuthTokenResponse, DefiantError> {
+        let client = self.authenticate_client(&request.client_id, &request.client_secret).await?;
+        
+        match request.grant_type.as_str() {
+            "authorization_code" => self.redeem_code(&client, request).await,
+            "refresh_token" => self.refresh(&client, request).await,
+            _ => Err(DefiantError::OAuthError("unsupported_grant_type", "Use authorization_code or refresh_token".into())),
+        }
+    }
+    
+    pub async fn introspect(&self, lookup: OAuthTokenLookup) -> Result<IntrospectionResponse, DefiantError> {
+        let client = self.authenticate_client(&lookup.client_id, &lookup.client_secret).await?;
+        let hash = hash_api_key(&lookup.token);
+        
+        let token = sqlx::query_as!(
+            OAuthToken,
+            r#"
+            SELECT t.* FROM oauth_tokens t
+            JOIN merchants m ON m.id = t.merchant_id
+            WHERE (t.access_token_hash = $1 OR t.refresh_token_hash = $1)
+            AND t.client_id = $2 AND t.revoked_at IS NULL AND m.active = true
+            "#,
+            hash,
+            client.id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?;
+        
+        let Some(token) = token else {
+            return Ok(IntrospectionResponse::default());
+        };
+        let is_access_token = token.access_token_hash == hash;
+        if is_access_token && token.access_expires_at <= Utc::now() {
+            return Ok(IntrospectionResponse::default());
+        }
+        
+        Ok(IntrospectionResponse {
+            active: true,
+            scope: Some(token.scopes.join(" ")),
+            client_id: Some(client.client_id),
+            token_type: Some(if is_access_token { "access_token" } else { "refresh_token" }.into()),
+            exp: is_access_token.then(|| token.access_expires_at.timestamp()),
+            iat: Some(token.created_at.timestamp()),
+            sub: Some(token.merchant_id.to_string()),
+            livemode: Some(token.livemode),
+        })
+    }
+    
+    /// Ends the grant behind an access or refresh token. Unknown tokens are not an
+    /// error (RFC 7009 section 2.2).
+    pub async fn revoke(&self, lookup: OAuthTokenLookup) -> Result<(), DefiantError> {
+        let client = self.authenticate_client(&lookup.client_id, &lookup.client_secret).await?;
+        
+        let revoked = sqlx::query_scalar!(
+            r#"
+            UPDATE oauth_tokens SET revoked_at = NOW()
+            WHERE (access_token_hash = $1 OR refresh_token_hash = $1)
+            AND client_id = $2 AND revoked_at IS NULL
+            RETURNING id
+            "#,
+            hash_api_key(&lookup.token),
+            client.id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?;
+        
+        if let Some(token_id) = revoked {
+            info!("OAuth grant {} revoked by client {}", token_id, client.client_id);
+        }
+        Ok(())
+    }
+    
+    async fn redeem_code(
+        &self,
+        client: &OAuthClient,
+        request: OAuthTokenRequest,
+    ) -> Result<OAuthTokenResponse, DefiantError> {
+        let invalid = || DefiantError::OAuthError("invalid_grant", "Authorization code is invalid, expired or already used".into());
+        let code = request.code.as_deref()
+            .ok_or_else(|| DefiantError::OAuthError("invalid_request", "code is required".into()))?;
+        
+        let mut tx = self.db.pool.begin().await?;
+        let authorization = sqlx::query!(
+            r#"
+            SELECT id, client_id, merchant_id, scopes, redirect_uri, livemode, code_challenge, expires_at, used_at
+            FROM oauth_authorization_codes
+            WHERE code_hash = $1
+            FOR UPDATE
+            "#,
+            hash_api_key(code),
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .filter(|authorization| authorization.client_id == client.id)
+        .ok_or_else(invalid)?;
+        
+        if authorization.used_at.is_some() {
+            // A code presented twice has leaked; end the grant it produced
+            sqlx::query!(
+                r#"UPDATE oauth_tokens SET revoked_at = NOW() WHERE authorization_code_id = $1 AND revoked_at IS NULL"#,
+                authorization.id,
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+            warn!("Authorization code {} reused; its grant was revoked", authorization.id);
+            return Err(invalid());
+        }
+        if authorization.expires_at <= Utc::now() {
+            return Err(invalid());
+        }
+        if request.redirect_uri.as_deref().is_some_and(|uri| uri != authorization.redirect_uri) {
+            return Err(DefiantError::OAuthError("invalid_grant", "redirect_uri does not match the authorization".into()));
+        }
+        if let Some(challenge) = &authorization.code_challenge {
+            let verified = request.code_verifier.as_deref()
+                .is_some_and(|verifier| pkce_challenge(verifier) == *challenge);
+            if !verified {
+                return Err(DefiantError::OAuthError("invalid_grant", "code_verifier does not match the code_challenge".into()));
+            }
+        }
+        
+        sqlx::query!(
+            r#"UPDATE oauth_authorization_codes SET used_at = NOW() WHERE id = $1"#,
+            authorization.id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        let access_token = generate_token(ACCESS_TOKEN_PREFIX, 48);
+        let refresh_token = generate_token(REFRESH_TOKEN_PREFIX, 48);
+        let token = sqlx::query_as!(
+            OAuthToken,
+            r#"
+            INSERT INTO oauth_tokens (
+                client_id, merchant_id, authorization_code_id, scopes, livemode,
+                access_token_hash, access_expires_at, refresh_token_hash
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+            client.id,
+            authorization.merchant_id,
+            authorization.id,
+            &authorization.scopes,
+            authorization.livemode,
+            hash_api_key(&access_token),
+            Utc::now() + Duration::seconds(ACCESS_TOKEN_TTL_SECONDS),
+            hash_api_key(&refresh_token),
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        
+        info!("OAuth grant {} issued to client {} for merchant {}", token.id, client.client_id, token.merchant_id);
+        Ok(token_response(&token, access_token, refresh_token))
+    }
+    
+    /// Replaces both tokens of the grant. The grant keeps its scopes.
+    async fn refresh(
+        &self,
+        client: &OAuthClient,
+        request: OAuthTokenRequest,
+    ) -> Result<OAuthTokenResponse, DefiantError> {
+        let presented = request.refresh_token.as_deref()
+            .ok_or_else(|| DefiantError::OAuthError("invalid_request", "refresh_token is required".into()))?;
+        let access_token = generate_token(ACCESS_TOKEN_PREFIX, 48);
+        let refresh_token = generate_token(REFRESH_TOKEN_PREFIX, 48);
+        
+        let token = sqlx::query_as!(
+            OAuthToken,
+            r#"
+            UPDATE oauth_tokens
+            SET access_token_hash = $3, access_expires_at = $4, refresh_token_hash = $5
+            WHERE refresh_token_hash = $1 AND client_id = $2 AND revoked_at IS NULL
+            RETURNING *
+            "#,
+            hash_api_key(presented),
+            client.id,
+            hash_api_key(&access_token),
+            Utc::now() + Duration::seconds(ACCESS_TOKEN_TTL_SECONDS),
+            hash_api_key(&refresh_token),
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::OAuthError("invalid_grant", "Refresh token is invalid or revoked".into()))?;
+        
+        Ok(token_response(&token, access_token, refresh_token))
+    }
+    
+    /// Only a signed-in dashboard user can authorize an app; an API key cannot
+    /// consent on the merchant's behalf.
+    async fn approver(&self, access_token: &str) -> Result<AuthenticatedKey, DefiantError> {
+        let approver = self.db.authenticate_key(access_token).await?;
+        if approver.team_role.is_none() {
+            return Err(DefiantError::AuthorizationError("Apps are authorized from a dashboard session".into()));
+        }
+        Ok(approver)
+    }
+    
+    async fn consent_for(
+        &self,
+        request: &AuthorizationRequest,
+        approver: &AuthenticatedKey,
+    ) -> Result<ConsentDetails, DefiantError> {
+        let (client, redirect_uri, scopes) = self.resolve(request).await?;
+        if let Some(scope) = scopes.iter().find(|scope| !approver.grants(scope)) {
+            return Err(DefiantError::AuthorizationError(format!(
+                "You cannot grant the '{}' scope you do not hold yourself", scope
+            )));
+        }
+        
+        Ok(ConsentDetails {
+            client_id: client.client_id,
+            client_name: client.name,
+            redirect_uri,
+            scopes,
+            livemode: approver.livemode,
+        })
+    }
+    
+    /// The client, redirect URI and scopes an authorization request resolves to.
+    async fn resolve(&self, request: &AuthorizationRequest) -> Result<(OAuthClient, String, Vec<String>), DefiantError> {
+        if request.response_type != "code" {
+            return Err(DefiantError::OAuthError("unsupported_response_type", "Only response_type=code is supported".into()));
+        }
+        if request.code_challenge.is_some() && request.code_challenge_method.as_deref() != Some("S256") {
+            return Err(DefiantError::OAuthError("invalid_request", "code_challenge_method must be S256".into()));
+        }
+        
+        let client = sqlx::query_as!(
+            OAuthClient,
+            r#"SELECT * FROM oauth_clients WHERE client_id = $1 AND active = true"#,
+            request.client_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::OAuthError("invalid_client", "Unknown client_id".into()))?;
+        
+        let redirect_uri = match &request.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(uri) => uri.clone(),
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            _ => return Err(DefiantError::OAuthError("invalid_request", "redirect_uri is not registered for this client".into())),
+        };
+        
+        let scopes: Vec<String> = match &request.scope {
+            Some(scope) => scope.split_whitespace().map(str::to_owned).collect(),
+            None => client.scopes.clone(),
+        };
+        if scopes.is_empty() {
+            return Err(DefiantError::OAuthError("invalid_scope", "At least one scope is required".into()));
+        }
+        if let Some(scope) = scopes.iter().find(|scope| !client.scopes.contains(scope)) {
+            return Err(DefiantError::OAuthError("invalid_scope", format!("The client is not registered for '{}'", scope)));
+        }
+        
+        Ok((client, redirect_uri, scopes))
+    }
+    
+    async fn authenticate_client(&self, client_id: &str, client_secret: &str) -> Result<OAuthClient, DefiantError> {
+        sqlx::query_as!(
+            OAuthClient,
+            r#"
+            SELECT * FROM oauth_clients
+            WHERE client_id = $1 AND client_secret_hash = $2 AND active = true
+            "#,
+            client_id,
+            hash_api_key(client_secret),
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::OAuthError("invalid_client", "Client authentication failed".into()))
+    }
+}
+
+#[derive(Serialize)]
+struct RedirectParams<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    state: Option<&'a str>,
+}
+
+fn redirect(redirect_uri: &str, params: &RedirectParams) -> AuthorizationRedirect {
+    let separator = if redirect_uri.contains('?') { '&' } else { '?' };
+    let query = serde_qs::to_string(params).unwrap_or_default();
+    
+    AuthorizationRedirect { redirect_to: format!("{}{}{}", redirect_uri, separator, query) }
+}
+
+fn token_response(token: &OAuthToken, access_token: String, refresh_token: String) -> OAuthTokenResponse {
+    OAuthTokenResponse {
+        access_token,
+        token_type: "Bearer".into(),
+        expires_in: (token.access_expires_at - Utc::now()).num_seconds().max(0),
+        refresh_token,
+        scope: token.scopes.join(" "),
+    }
+}
+
+/// Redirect URIs must be HTTPS, except on localhost, and carry no fragment.
+fn validate_redirect_uri(uri: &str) -> Result<(), DefiantError> {
+    let local = ["http://localhost", "http://127.0.0.1"].iter().any(|prefix| {
+        uri.strip_prefix(prefix).is_some_and(|rest| rest.is_empty() || rest.starts_with([':', '/']))
+    });
+    if !(uri.starts_with("https://") || local) || uri.contains('#') {
+        return Err(DefiantError::ValidationError(format!("Invalid redirect URI '{}'", uri)));
+    }
+    Ok(())
+}
+
+fn validate_scope(scope: &str) -> Result<(), DefiantError> {
+    if !is_known_scope(scope) {
+        return Err(DefiantError::ValidationError(format!("Unknown scope '{}'", scope)));
+    }
+    let resource = scope.split(':').next().unwrap_or_default();
+    if RESTRICTED_RESOURCES.contains(&resource) {
+        return Err(DefiantError::ValidationError(format!("Apps cannot be granted '{}'", scope)));
+    }
+    Ok(())
+}
+
+/// RFC 7636 S256: unpadded base64url of the verifier's SHA-256.
+fn pkce_challenge(verifier: &str) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    
+    let hash = digest::digest(&digest::SHA256, verifier.as_bytes());
+    let mut encoded = String::with_capacity(43);
+    for chunk in hash.as_ref().chunks(3) {
+        let buffer = chunk.iter().fold(0u32, |buffer, byte| (buffer << 8) | *byte as u32) << (8 * (3 - chunk.len()));
+        for i in 0..=chunk.len() {
+            encoded.push(ALPHABET[((buffer >> (18 - 6 * i)) & 0x3f) as usize] as char);
+        }
+    }
+    encoded
+}
+
+fn generate_token(prefix: &str, length: usize) -> String {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect();
+    
+    format!("{}{}", prefix, token)
+}
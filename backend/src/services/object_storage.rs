@@ -0,0 +1,87 @@
+use std::time::Duration;
+use chrono::Utc;
+use ring::{digest, hmac};
+use tracing::error;
+
+use crate::{config::ArchiveBucket, errors::DefiantError};
+
+const UPLOAD_TIMEOUT_SECS: u64 = 120;
+
+/// Uploads objects to an S3-compatible bucket, signing requests with AWS Signature
+/// Version 4.
+pub struct ObjectStorage {
+    bucket: ArchiveBucket,
+    client: reqwest::Client,
+}
+
+impl ObjectStorage {
+    pub fn new(bucket: ArchiveBucket) -> Result<Self, DefiantError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(UPLOAD_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        Ok(Self { bucket, client })
+    }
+    
+    /// Puts `body` at `key`, which must hold only URL-safe characters.
+    pub async fn put(&self, key: &str, content_type: &str, body: Vec<u8>) -> Result<(), DefiantError> {
+        let url = reqwest::Url::parse(&format!("{}/{}/{}", self.bucket.endpoint.trim_end_matches('/'), self.bucket.name, key))
+            .map_err(|_| DefiantError::BadRequest("Invalid archive bucket endpoint".into()))?;
+        let host = match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(DefiantError::BadRequest("Invalid archive bucket endpoint".into())),
+        };
+        
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let payload_hash = hex::encode(digest::digest(&digest::SHA256, &body));
+        let scope = format!("{}/{}/s3/aws4_request", date, self.bucket.region);
+        
+        let canonical_request = format!(
+            "PUT\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            url.path(), host, payload_hash, amz_date, payload_hash,
+        );
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            hex::encode(digest::digest(&digest::SHA256, canonical_request.as_bytes())),
+        );
+        
+        let signing_key = [date.as_str(), self.bucket.region.as_str(), "s3", "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.bucket.secret_access_key).into_bytes(), |key, part| {
+                hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes()).as_ref().to_vec()
+            });
+        let signature = hex::encode(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &signing_key), string_to_sign.as_bytes()));
+        
+        let response = self.client
+            .put(url)
+            .header("Content-Type", content_type)
+            .header("x-amz-content-sha256", payload_hash)
+            .header("x-amz-date", amz_date)
+            .header(
+                "Authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+                    self.bucket.access_key_id, scope, signature,
+                ),
+            )
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| {
+                error!("Failed to upload {} to bucket {}: {}", key, self.bucket.name, e);
+                DefiantError::InternalError
+            })?;
+        
+        if !response.status().is_success() {
+            error!("Bucket {} refused {}: {}", self.bucket.name, key, response.status());
+            return Err(DefiantError::InternalError);
+        }
+        Ok(())
+    }
+}
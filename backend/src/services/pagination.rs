@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::errors::DefiantError;
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+/// A position in a list ordered newest first by `(created_at, id)`. The id breaks ties
+/// between rows created in the same microsecond, so every row has exactly one place
+/// and pages neither skip nor repeat rows as new ones arrive.
+///
+/// Clients see cursors as opaque strings and hand back the `next_cursor` of one page to
+/// fetch the next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self { created_at, id }
+    }
+    
+    pub fn encode(&self) -> String {
+        let mut bytes = self.created_at.timestamp_micros().to_be_bytes().to_vec();
+        bytes.extend_from_slice(self.id.as_bytes());
+        hex::encode(bytes)
+    }
+    
+    pub fn decode(cursor: &str) -> Result<Self, DefiantError> {
+        let invalid = || DefiantError::ValidationError("Invalid cursor".into());
+        let bytes = hex::decode(cursor).map_err(|_| invalid())?;
+        if bytes.len() != 24 {
+            return Err(invalid());
+        }
+        
+        let (micros, id) = bytes.split_at(8);
+        let created_at = DateTime::<Utc>::from_timestamp_micros(i64::from_be_bytes(micros.try_into().unwrap()))
+            .ok_or_else(invalid)?;
+        let id = Uuid::from_slice(id).map_err(|_| invalid())?;
+        
+        Ok(Self { created_at, id })
+    }
+}
+
+/// The requested page size, defaulted and clamped.
+pub fn limit(requested: Option<i64>) -> i64 {
+    requested.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Decodes the client's cursor into the `(created_at, id)` a query continues after,
+/// both unset for the first page. Queries filter with
+/// `($n::TIMESTAMPTZ IS NULL OR (created_at, id) < ($n, $m))` and order by
+/// `created_at DESC, id DESC`.
+pub fn after(cursor: Option<&str>) -> Result<(Option<DateTime<Utc>>, Option<Uuid>), DefiantError> {
+    Ok(cursor
+        .map(Cursor::decode)
+        .transpose()?
+        .map(|cursor| (cursor.created_at, cursor.id))
+        .unzip())
+}
+
+/// One page of a list fetched with `limit + 1` rows: the extra row only tells
+/// whether another page exists.
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub has_more: bool,
+    /// Where the next page starts; unset on the last page
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(mut rows: Vec<T>, limit: i64, cursor: impl Fn(&T) -> Cursor) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        
+        let next_cursor = if has_more {
+            rows.last().map(|row| cursor(row).encode())
+        } else {
+            None
+        };
+        
+        Self { data: rows, has_more, next_cursor }
+    }
+    
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            data: self.data.into_iter().map(f).collect(),
+            has_more: self.has_more,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
@@ -1,38 +1,136 @@
 use std::sync::Arc;
 use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use redis::aio::ConnectionManager;
+use ring::digest;
 use tracing::{info, warn, error};
 
-use crate::{models::{CreatePaymentRequest, PaymentResponse, PaymentStatus, PaymentMethod}, errors::DefiantError, db::Database};
+use crate::{models::{Payment, Invoice, CardBrand, CardDetails, CardEntryMode, CreatePaymentRequest, PaymentResponse, PaymentStatusResponse, PaymentStatus, PaymentMethod, ShippingDetails, PaymentLineItem, NextAction, ScaStatus, ScaExemption, PAYMENT_CREATED, PAYMENT_SUCCEEDED, PAYMENT_FAILED, PAYMENT_REQUIRES_ACTION, PAYMENT_REVIEW_OPENED, CryptoQuote, GeoSignals, PaymentReview, ReviewListQuery, ReviewListResponse, PaymentListQuery, PaymentSearchQuery, PaymentsListResponse, ConfirmPaymentRequest, current_webhook_api_version}, errors::DefiantError, db::{Database, AuthenticatedKey}, config::Config, plugins::{self, PaymentAttempt}};
+use super::webhook_service::WebhookService;
+use super::event_renderer;
+use super::event_router;
+use super::sca_service::ScaService;
+use super::mit_service::MitService;
+use super::consent_service::ConsentService;
+use super::terminal_service::TerminalService;
+use super::sca_service::card_fingerprint;
+use super::geoip_service::GeoIpService;
+use super::fraud_settings_service::FraudSettingsService;
+use super::fx_service;
+use super::ledger;
+use super::connect_service::ConnectService;
+use super::fraud_detection::{assess_risk, blocklisted, check_velocity, risk_scorer, RiskAssessment, RiskContext, VelocityOutcome, VelocitySubject};
+use super::crypto_service::CryptoService;
+use super::crypto_conversion_service::{CryptoConversionService, DEFAULT_PAYMENT_EXPIRY_MINUTES};
+use super::crypto_rate_service::{CryptoRateService, QUOTE_LOCK_MINUTES};
+use super::token_service::TokenService;
+use super::pagination::{self, Cursor, Page};
+use super::search::SearchFilter;
+use super::archive_service;
+
+/// What fraud checks concluded about a payment they let through.
+struct FraudChecks {
+    review_reason: Option<String>,
+    /// The amount reached the merchant's 3DS threshold
+    require_three_ds: bool,
+    risk: RiskAssessment,
+    geo: Option<GeoSignals>,
+    card_fingerprint: Option<String>,
+    client_ip: Option<String>,
+}
+
+impl FraudChecks {
+    fn geo_signals(&self) -> Option<serde_json::Value> {
+        self.geo.as_ref().map(|geo| serde_json::json!(geo))
+    }
+}
 
 pub struct PaymentService {
     db: Arc<Database>,
     redis: Arc<ConnectionManager>,
+    config: Arc<Config>,
 }
 
 impl PaymentService {
-    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
-        Self { db, redis }
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>, config: Arc<Config>) -> Self {
+        Self { db, redis, config }
     }
     
     pub async fn create_payment(
         &self,
-        request: CreatePaymentRequest,
+        mut request: CreatePaymentRequest,
         api_key: &str,
     ) -> Result<PaymentResponse, DefiantError> {
+        let (key, merchant) = self.authenticate(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant.id).await?;
+        
+        let attempt = PaymentAttempt { merchant_id: merchant.id, request: &request, invoice_id: None };
+        plugins::hooks().pre_payment(&attempt).await?;
+        
+        if let Some(transfer_data) = &request.transfer_data {
+            if !matches!(request.payment_method, PaymentMethod::Card | PaymentMethod::CardPresent) {
+                return Err(DefiantError::ValidationError("transfer_data is only supported for card payments".into()));
+            }
+            self.db.require_scope(api_key, "connect:write").await?;
+            ConnectService::new(self.db.clone())
+                .validate_transfer_data(merchant.id, request.amount, transfer_data)
+                .await?;
+        }
+        
         // Start transaction
-        let mut tx = self.db.pool.begin().await?;
+        let mut tx = pool.begin().await?;
+        
+        // A card tokenized client-side is exchanged for its details in this transaction,
+        // so the token stays usable if the payment is not created
+        if let Some(source) = request.source.as_mut().filter(|source| source.card.is_none() && source.token.starts_with("tok_")) {
+            let card = TokenService::new(self.db.clone(), self.config.clone())
+                .redeem(&mut tx, merchant.id, key.livemode, &source.token)
+                .await?;
+            source.card = Some(card);
+        }
         
-        // Validate API key and get merchant
-        let merchant = self.validate_api_key(api_key, &mut tx).await?;
+        // Without a source the customer's client supplies the card later, through `confirm`
+        let awaits_confirmation = matches!(request.payment_method, PaymentMethod::Card)
+            && request.source.is_none()
+            && !request.off_session;
         
         // Check fraud
-        self.check_fraud(&request, &merchant.id, &mut tx).await?;
+        let fraud = self.check_fraud(&request, &merchant.id, &mut tx).await?;
+        
+        // Saving a card needs the customer present, a card, and evidence of their consent
+        let card_on_file = match request.setup_future_usage {
+            Some(agreement_type) => {
+                self.db.require_scope(api_key, "mit_agreements:write").await?;
+                if request.off_session {
+                    return Err(DefiantError::ValidationError("setup_future_usage requires the customer to be on-session".into()));
+                }
+                let customer_id = request.customer_id.ok_or_else(|| {
+                    DefiantError::ValidationError("setup_future_usage requires a customer_id".into())
+                })?;
+                let card = request.source.as_ref().and_then(|source| source.card.as_ref()).ok_or_else(|| {
+                    DefiantError::ValidationError("setup_future_usage requires card details".into())
+                })?;
+                let consent = request.consent.as_ref().ok_or_else(|| {
+                    DefiantError::ValidationError("setup_future_usage requires the customer's consent details".into())
+                })?;
+                Some((agreement_type, customer_id, card, consent))
+            }
+            None => None,
+        };
+        
+        // Off-session charges are merchant-initiated and must reference an agreement
+        let mit = MitService::new(self.db.clone(), self.config.clone());
+        let agreement = if request.off_session {
+            Some(mit.authorize_off_session(&mut tx, merchant.id, &request).await?)
+        } else {
+            None
+        };
         
         // Create payment record
         let payment_id = Uuid::new_v4();
+        let initial_status = if awaits_confirmation { PaymentStatus::RequiresConfirmation } else { PaymentStatus::Pending };
+        let client_secret = format!("pi_{}_secret_{}", payment_id, Uuid::new_v4().simple());
         let now = Utc::now();
         
         let payment = sqlx::query_as!(
@@ -41,20 +139,41 @@ impl PaymentService {
             INSERT INTO payments (
                 id, amount, currency, status, payment_method,
                 merchant_id, customer_id, description, metadata,
-                created_at, updated_at
+                mit_agreement_id, original_network_transaction_id,
+                livemode, client_secret_hash, review_reason, risk_score, risk_reasons,
+                geo_signals, card_fingerprint, client_ip, transfer_destination, transfer_amount,
+                card_brand, card_country, created_at, updated_at
+            )
+            VALUES (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11,
+                $12,
+                $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             RETURNING *
             "#,
             payment_id,
             request.amount,
             request.currency.to_uppercase(),
-            PaymentStatus::Pending as PaymentStatus,
+            initial_status as PaymentStatus,
             request.payment_method as PaymentMethod,
             merchant.id,
             request.customer_id,
             request.description,
             request.metadata,
+            agreement.as_ref().map(|a| a.id),
+            agreement.as_ref().map(|a| a.network_transaction_id.clone()),
+            key.livemode,
+            hash_client_secret(&client_secret),
+            fraud.review_reason.as_deref(),
+            fraud.risk.score,
+            &fraud.risk.reasons,
+            fraud.geo_signals(),
+            fraud.card_fingerprint.as_deref(),
+            fraud.client_ip.as_deref(),
+            request.transfer_data.as_ref().map(|transfer| transfer.destination),
+            request.transfer_data.as_ref().and_then(|transfer| transfer.amount),
+            card_brand(&request),
+            card_country(&request),
             now,
             now,
         )
@@ -63,18 +182,33 @@ impl PaymentService {
         
         // Process payment based on method
         let processed_payment = match request.payment_method {
-            PaymentMethod::Card => self.process_card_payment(payment, &request, &mut tx).await?,
-            PaymentMethod::Crypto => self.process_crypto_payment(payment, &mut tx).await?,
+            PaymentMethod::Card if awaits_confirmation => payment,
+            PaymentMethod::Card => {
+                let card = request.source.as_ref().and_then(|source| source.card.as_ref());
+                self.process_card_payment(merchant.id, payment, card, request.off_session, fraud.require_three_ds, &mut tx).await?
+            }
+            PaymentMethod::Crypto => self.process_crypto_payment(merchant.id, payment, &request, &mut tx).await?,
+            PaymentMethod::CardPresent => self.process_card_present_payment(merchant.id, payment, &request, &mut tx).await?,
             _ => payment,
         };
         
+        // Consent to future charges only counts once the customer's own payment went through
+        if let (Some((agreement_type, customer_id, card, consent)), PaymentStatus::Succeeded) = (card_on_file, &processed_payment.status) {
+            let payment_method_id = self.save_card(&mut tx, merchant.id, customer_id, card).await?;
+            let agreement = mit.create_agreement(&mut tx, &processed_payment, customer_id, agreement_type, card).await?;
+            ConsentService::new(self.db.clone())
+                .record(&mut tx, merchant.id, customer_id, payment_method_id, Some(agreement.id), Some(processed_payment.id), consent)
+                .await?;
+        }
+        
         // Commit transaction
         tx.commit().await?;
         
-        // Emit event
-        self.emit_payment_event(&processed_payment, "payment.created").await;
+        // Emit events
+        self.emit_created(merchant.id, &processed_payment).await;
         
         // Convert to response
+        let next_action = next_action(&processed_payment);
         Ok(PaymentResponse {
             id: processed_payment.id,
             amount: processed_payment.amount,
@@ -84,18 +218,140 @@ impl PaymentService {
             customer_id: processed_payment.customer_id,
             description: processed_payment.description,
             metadata: processed_payment.metadata,
+            shipping_amount: processed_payment.shipping_amount,
+            shipping: ShippingDetails::from_value(processed_payment.shipping.as_ref()),
+            is_donation: processed_payment.is_donation,
+            sca_status: processed_payment.sca_status,
+            sca_exemption: processed_payment.sca_exemption,
+            mit_agreement_id: processed_payment.mit_agreement_id,
+            livemode: processed_payment.livemode,
+            review_reason: processed_payment.review_reason,
+            risk_score: processed_payment.risk_score,
+            line_items: Vec::new(),
             created_at: processed_payment.created_at,
-            client_secret: Some(format!("pi_{}_secret_{}", processed_payment.id, Uuid::new_v4())),
-            next_action: None,
+            updated_at: processed_payment.updated_at,
+            version: processed_payment.version,
+            client_secret: Some(client_secret),
+            next_action,
         })
     }
     
+    /// Outcome of a payment for the customer's client, authenticated by the client
+    /// secret returned at creation rather than an API key.
+    pub async fn get_payment_status(
+        &self,
+        payment_id: Uuid,
+        client_secret: &str,
+    ) -> Result<PaymentStatusResponse, DefiantError> {
+        let not_found = || DefiantError::NotFound("Payment not found".into());
+        let pool = self.db.pool_for_row("payments", payment_id).await?.ok_or_else(not_found)?;
+        
+        // A wrong secret looks exactly like a missing payment
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"SELECT * FROM payments WHERE id = $1 AND client_secret_hash = $2"#,
+            payment_id,
+            hash_client_secret(client_secret),
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(not_found)?;
+        
+        Ok(status_response(payment))
+    }
+    
+    /// Charges a payment created without card details, using a card the customer's
+    /// client tokenized. Meant to be called with a publishable key and the client secret.
+    pub async fn confirm_payment(
+        &self,
+        payment_id: Uuid,
+        request: ConfirmPaymentRequest,
+        api_key: &str,
+    ) -> Result<PaymentStatusResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.pool_for_merchant(key.merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        // A wrong secret looks exactly like a missing payment
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            SELECT * FROM payments
+            WHERE id = $1 AND merchant_id = $2 AND livemode = $3 AND client_secret_hash = $4
+            FOR UPDATE
+            "#,
+            payment_id,
+            key.merchant_id,
+            key.livemode,
+            hash_client_secret(&request.client_secret),
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment not found".into()))?;
+        
+        if !matches!(payment.status, PaymentStatus::RequiresConfirmation) {
+            return Err(DefiantError::Conflict("Payment is not awaiting confirmation".into()));
+        }
+        
+        let card = TokenService::new(self.db.clone(), self.config.clone())
+            .redeem(&mut tx, key.merchant_id, key.livemode, &request.token)
+            .await?;
+        
+        // The card was unknown when the payment went through fraud checks
+        let fingerprint = card_fingerprint(&self.config.card_fingerprint_key, &card.number);
+        let subject = VelocitySubject {
+            merchant_id: key.merchant_id,
+            customer_id: payment.customer_id,
+            card_fingerprint: Some(&fingerprint),
+            client_ip: payment.client_ip.as_deref(),
+        };
+        if let Some(entry) = blocklisted(&mut tx, &subject).await? {
+            warn!("Payment {} declined at confirmation: {}", payment.id, entry);
+            return Err(DefiantError::PaymentError(format!("Payment declined by fraud checks: {}", entry)));
+        }
+        
+        let fraud_settings = FraudSettingsService::new(self.db.clone(), self.redis.clone());
+        let settings = fraud_settings.settings(key.merchant_id).await?;
+        let require_three_ds = match settings.three_ds_amount_threshold {
+            Some(threshold) => fraud_settings
+                .amount_in_settings_currency(&settings, payment.amount, &payment.currency)
+                .await
+                .map(|amount| amount >= threshold)
+                .unwrap_or(true),
+            None => false,
+        };
+        
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            UPDATE payments SET card_brand = $2, card_country = $3, card_fingerprint = $4, updated_at = NOW()
+            WHERE id = $1
+            RETURNING *
+            "#,
+            payment.id,
+            CardBrand::from_number(&card.number).as_str(),
+            card.issuer_country.as_ref().map(|country| country.to_uppercase()),
+            fingerprint,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let payment = self.process_card_payment(key.merchant_id, payment, Some(&card), false, require_three_ds, &mut tx).await?;
+        tx.commit().await?;
+        
+        info!("Payment {} confirmed", payment.id);
+        self.emit_outcome(key.merchant_id, &payment).await;
+        
+        Ok(status_response(payment))
+    }
+    
     pub async fn get_payment(
         &self,
         payment_id: Uuid,
         api_key: &str,
     ) -> Result<PaymentResponse, DefiantError> {
         let merchant = self.get_merchant_by_api_key(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant.id).await?;
         
         let payment = sqlx::query_as!(
             Payment,
@@ -106,118 +362,490 @@ impl PaymentService {
             payment_id,
             merchant.id,
         )
-        .fetch_optional(&self.db.pool)
-        .await?
-        .ok_or_else(|| DefiantError::NotFound("Payment not found".into()))?;
+        .fetch_optional(pool)
+        .await?;
+        let Some(payment) = payment else {
+            return Err(archive_service::not_found(pool, "payment", payment_id, merchant.id, "Payment not found").await);
+        };
+        
+        self.payment_to_response(pool, payment).await
+    }
+    
+    pub async fn list_payments(
+        &self,
+        query: PaymentListQuery,
+        api_key: &str,
+    ) -> Result<PaymentsListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        
+        // Fetch one extra row to learn whether another page exists
+        let payments = sqlx::query_as!(
+            Payment,
+            r#"
+            SELECT * FROM payments
+            WHERE merchant_id = $1 AND livemode = $2
+            AND ($3::UUID IS NULL OR customer_id = $3)
+            AND ($4::payment_status IS NULL OR status = $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            key.merchant_id,
+            key.livemode,
+            query.customer,
+            query.status as Option<PaymentStatus>,
+            after_created,
+            after_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let page = Page::new(payments, limit, |payment| Cursor::new(payment.created_at, payment.id));
+        let mut data = Vec::with_capacity(page.data.len());
+        for payment in page.data {
+            data.push(self.payment_to_response(pool, payment).await?);
+        }
+        
+        Ok(PaymentsListResponse {
+            data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/payments".into(),
+        })
+    }
+    
+    /// Payments matching a search, newest first. Text is matched against the
+    /// description index and metadata by containment, so neither scans the table.
+    pub async fn search_payments(
+        &self,
+        query: PaymentSearchQuery,
+        api_key: &str,
+    ) -> Result<PaymentsListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let filter = SearchFilter::parse(&query.query)?;
+        let limit = pagination::limit(query.limit);
+        let (after_created, after_id) = pagination::after(query.cursor.as_deref())?;
+        
+        // The text expression must match idx_payments_description_search to use it
+        let payments = sqlx::query_as!(
+            Payment,
+            r#"
+            SELECT * FROM payments
+            WHERE merchant_id = $1 AND livemode = $2
+            AND ($3::TEXT IS NULL
+                OR to_tsvector('simple', COALESCE(description, '')) @@ websearch_to_tsquery('simple', $3))
+            AND ($4::JSONB IS NULL OR metadata @> $4)
+            AND ($5::TIMESTAMPTZ IS NULL OR (created_at, id) < ($5, $6::UUID))
+            ORDER BY created_at DESC, id DESC
+            LIMIT $7
+            "#,
+            key.merchant_id,
+            key.livemode,
+            filter.text,
+            filter.metadata,
+            after_created,
+            after_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let page = Page::new(payments, limit, |payment| Cursor::new(payment.created_at, payment.id));
+        let mut data = Vec::with_capacity(page.data.len());
+        for payment in page.data {
+            data.push(self.payment_to_response(pool, payment).await?);
+        }
         
-        self.payment_to_response(payment).await
+        Ok(PaymentsListResponse {
+            data,
+            has_more: page.has_more,
+            next_cursor: page.next_cursor,
+            url: "/api/v1/payments/search".into(),
+        })
+    }
+    
+    /// Payments fraud checks let through flagged for review, newest first.
+    pub async fn list_reviews(
+        &self,
+        query: ReviewListQuery,
+        api_key: &str,
+    ) -> Result<ReviewListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.read_for_merchant(key.merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut payments = sqlx::query_as!(
+            Payment,
+            r#"
+            SELECT * FROM payments
+            WHERE merchant_id = $1 AND livemode = $4
+            AND review_reason IS NOT NULL
+            AND ($2::UUID IS NULL OR customer_id = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            key.merchant_id,
+            query.customer_id,
+            limit + 1,
+            key.livemode,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = payments.len() as i64 > limit;
+        payments.truncate(limit as usize);
+        
+        Ok(ReviewListResponse {
+            data: payments.into_iter().map(PaymentReview::from_payment).collect(),
+            has_more,
+            url: "/api/v1/reviews".into(),
+        })
+    }
+    
+    /// Charges what remains due on an invoice from its hosted payment page, inside the
+    /// caller's transaction. No API key is involved, so the payment takes the mode of
+    /// the key that sent the invoice.
+    pub(crate) async fn charge_invoice(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        invoice: &Invoice,
+        request: &CreatePaymentRequest,
+    ) -> Result<Payment, DefiantError> {
+        let attempt = PaymentAttempt { merchant_id: invoice.merchant_id, request, invoice_id: Some(invoice.id) };
+        plugins::hooks().pre_payment(&attempt).await?;
+        
+        let fraud = self.check_fraud(request, &invoice.merchant_id, tx).await?;
+        
+        let payment = sqlx::query_as!(
+            Payment,
+            r#"
+            INSERT INTO payments (
+                amount, currency, status, payment_method, merchant_id, customer_id,
+                description, metadata, invoice_id, livemode, review_reason, risk_score, risk_reasons,
+                geo_signals, card_fingerprint, client_ip, card_brand, card_country
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            RETURNING *
+            "#,
+            request.amount,
+            request.currency,
+            PaymentStatus::Pending as PaymentStatus,
+            request.payment_method as PaymentMethod,
+            invoice.merchant_id,
+            invoice.customer_id,
+            request.description,
+            request.metadata,
+            invoice.id,
+            invoice.livemode,
+            fraud.review_reason.as_deref(),
+            fraud.risk.score,
+            &fraud.risk.reasons,
+            fraud.geo_signals(),
+            fraud.card_fingerprint.as_deref(),
+            fraud.client_ip.as_deref(),
+            card_brand(request),
+            card_country(request),
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        match request.payment_method {
+            PaymentMethod::Card => {
+                let card = request.source.as_ref().and_then(|source| source.card.as_ref());
+                self.process_card_payment(invoice.merchant_id, payment, card, request.off_session, fraud.require_three_ds, tx).await
+            }
+            PaymentMethod::Crypto => self.process_crypto_payment(invoice.merchant_id, payment, request, tx).await,
+            _ => Err(DefiantError::ValidationError("Invoices can only be paid online by card or crypto".into())),
+        }
+    }
+    
+    /// Announces a new payment, then its outcome when processing already reached one.
+    pub(crate) async fn emit_created(&self, merchant_id: Uuid, payment: &Payment) {
+        self.emit_payment_event(merchant_id, payment, PAYMENT_CREATED).await;
+        self.emit_outcome(merchant_id, payment).await;
+    }
+    
+    async fn emit_outcome(&self, merchant_id: Uuid, payment: &Payment) {
+        let outcome = match payment.status {
+            PaymentStatus::Succeeded => Some(PAYMENT_SUCCEEDED),
+            PaymentStatus::Failed => Some(PAYMENT_FAILED),
+            PaymentStatus::RequiresAction => Some(PAYMENT_REQUIRES_ACTION),
+            _ => None,
+        };
+        if let Some(event_type) = outcome {
+            self.emit_payment_event(merchant_id, payment, event_type).await;
+        }
+        if payment.review_reason.is_some() {
+            self.emit_payment_event(merchant_id, payment, PAYMENT_REVIEW_OPENED).await;
+        }
     }
     
     async fn process_card_payment(
         &self,
+        merchant_id: Uuid,
         payment: Payment,
-        request: &CreatePaymentRequest,
+        card: Option<&CardDetails>,
+        off_session: bool,
+        require_three_ds: bool,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<Payment, DefiantError> {
         // Simulate payment processing
         info!("Processing card payment: {}", payment.id);
         
-        // In real implementation, integrate with payment processor
-        // For now, simulate success
-        let status = if rand::random::<f32>() > 0.1 {
-            PaymentStatus::Succeeded
+        let sca = ScaService::new(self.db.clone(), self.config.clone());
+        let decision = sca.evaluate(tx, &payment, card, off_session, require_three_ds).await?;
+        
+        let (status, sca_status) = if decision.requires_challenge() {
+            // The cardholder completes 3DS before we authorize, which also restarts
+            // the low-value allowance for the card
+            if let Some(fingerprint) = &decision.card_fingerprint {
+                sca.reset_low_value_counter(tx, merchant_id, fingerprint).await?;
+            }
+            (PaymentStatus::RequiresAction, ScaStatus::ChallengeRequired)
         } else {
-            PaymentStatus::Failed
+            // In real implementation, integrate with payment processor
+            // For now, simulate the issuer response
+            let roll = rand::random::<f32>();
+            if roll > 0.1 {
+                sca.record_exemption_outcome(tx, merchant_id, payment.id, &decision, None).await?;
+                (PaymentStatus::Succeeded, decision.status)
+            } else if decision.exemption.is_some() && roll > 0.05 {
+                // Soft decline: the issuer refused the exemption, fall back to a challenge
+                sca.record_exemption_outcome(tx, merchant_id, payment.id, &decision, Some("authentication_required")).await?;
+                (PaymentStatus::RequiresAction, ScaStatus::ExemptionDeclined)
+            } else {
+                (PaymentStatus::Failed, decision.status)
+            }
         };
         
+        // The scheme reference later MITs quote back to the network
+        let network_transaction_id = matches!(status, PaymentStatus::Succeeded)
+            .then(|| format!("{:015}", rand::random::<u64>() % 1_000_000_000_000_000));
+        
         let updated_payment = sqlx::query_as!(
             Payment,
             r#"
             UPDATE payments 
-            SET status = $1, updated_at = $2
-            WHERE id = $3
+            SET status = $1, sca_status = $2, sca_exemption = $3, network_transaction_id = $4, updated_at = $5
+            WHERE id = $6
             RETURNING *
             "#,
             status as PaymentStatus,
+            sca_status as ScaStatus,
+            decision.exemption as Option<ScaExemption>,
+            network_transaction_id,
             Utc::now(),
             payment.id,
         )
         .fetch_one(&mut **tx)
         .await?;
         
+        if matches!(updated_payment.status, PaymentStatus::Succeeded) {
+            ledger::record_card_payment(tx, &self.db.pool, &updated_payment, &self.config).await?;
+        }
+        
         Ok(updated_payment)
     }
     
-    async fn process_crypto_payment(
+    /// Authorizes a terminal payment by forwarding the reader's encrypted payload to
+    /// the processor. Only the key reference is kept on the payment.
+    async fn process_card_present_payment(
         &self,
+        merchant_id: Uuid,
         payment: Payment,
+        request: &CreatePaymentRequest,
         tx: &mut Transaction<'_, Postgres>,
     ) -> Result<Payment, DefiantError> {
-        // Generate crypto address for payment
-        let crypto_address = self.generate_crypto_address(&payment).await?;
+        let data = request.card_present.as_ref().ok_or_else(|| {
+            DefiantError::ValidationError("card_present payments require encrypted reader data".into())
+        })?;
+        
+        info!("Processing card-present payment: {} ({:?})", payment.id, data.entry_mode);
+        
+        let terminal = TerminalService::new(self.db.clone(), self.config.clone());
+        let key = terminal.resolve_key(tx, merchant_id, data).await?;
+        let authorization = terminal.authorize(&payment, &key, data).await?;
+        
+        let status = if authorization.approved {
+            PaymentStatus::Succeeded
+        } else {
+            PaymentStatus::Failed
+        };
         
-        // Update payment with crypto details
         let updated_payment = sqlx::query_as!(
             Payment,
             r#"
-            UPDATE payments 
-            SET metadata = jsonb_set(
-                COALESCE(metadata, '{}'::jsonb),
-                '{crypto_address}',
-                $1::jsonb
-            ),
-            updated_at = $2
-            WHERE id = $3
+            UPDATE payments
+            SET status = $1, entry_mode = $2, terminal_key_set_id = $3, terminal_ksn = $4,
+                network_transaction_id = $5, failure_code = $6, updated_at = $7
+            WHERE id = $8
             RETURNING *
             "#,
-            serde_json::json!(crypto_address),
+            status as PaymentStatus,
+            data.entry_mode as CardEntryMode,
+            key.key_set_id,
+            data.ksn,
+            authorization.network_transaction_id,
+            authorization.decline_code,
             Utc::now(),
             payment.id,
         )
         .fetch_one(&mut **tx)
         .await?;
         
+        if matches!(updated_payment.status, PaymentStatus::Succeeded) {
+            ledger::record_card_payment(tx, &self.db.pool, &updated_payment, &self.config).await?;
+        }
+        
         Ok(updated_payment)
     }
     
-    async fn generate_crypto_address(&self, payment: &Payment) -> Result<String, DefiantError> {
-        // Generate unique crypto address for this payment
-        let address = format!("0x{}{}", 
-            hex::encode(payment.id.as_bytes()),
-            hex::encode(&payment.created_at.timestamp().to_be_bytes())
-        );
-        
-        Ok(address)
-    }
-    
-    async fn validate_api_key(
+    /// Stores the card as a reusable payment method. Only the last four digits and
+    /// expiry are kept alongside the fingerprint used to recognise the card again.
+    async fn save_card(
         &self,
-        api_key: &str,
         tx: &mut Transaction<'_, Postgres>,
-    ) -> Result<Merchant, DefiantError> {
-        let merchant = sqlx::query_as!(
-            Merchant,
+        merchant_id: Uuid,
+        customer_id: Uuid,
+        card: &CardDetails,
+    ) -> Result<Uuid, DefiantError> {
+        let details = serde_json::json!({
+            "last4": &card.number[card.number.len().saturating_sub(4)..],
+            "exp_month": card.exp_month,
+            "exp_year": card.exp_year,
+            "fingerprint": card_fingerprint(&self.config.card_fingerprint_key, &card.number),
+        });
+        
+        let payment_method_id = sqlx::query_scalar!(
             r#"
-            SELECT m.* FROM merchants m
-            JOIN api_keys ak ON m.id = ak.merchant_id
-            WHERE ak.key = $1 AND ak.active = true
-            AND m.active = true
+            INSERT INTO payment_methods (merchant_id, customer_id, type, details)
+            VALUES ($1, $2, 'card', $3)
+            RETURNING id
             "#,
-            api_key,
+            merchant_id,
+            customer_id,
+            details,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        Ok(payment_method_id)
+    }
+    
+    async fn process_crypto_payment(
+        &self,
+        merchant_id: Uuid,
+        payment: Payment,
+        request: &CreatePaymentRequest,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Payment, DefiantError> {
+        let chain = request.crypto_chain
+            .ok_or_else(|| DefiantError::ValidationError("crypto_chain is required for crypto payments".into()))?;
+        // A merchant-quoted amount is locked for as long as one we convert ourselves
+        let quote = match request.crypto_amount {
+            Some(amount) if amount.is_sign_positive() && !amount.is_zero() && amount.fract().is_zero() => CryptoQuote {
+                amount,
+                currency: payment.currency.clone(),
+                rate: None,
+                expires_at: Utc::now() + Duration::minutes(QUOTE_LOCK_MINUTES),
+            },
+            Some(_) => {
+                return Err(DefiantError::ValidationError("crypto_amount must be a positive whole number of base units".into()));
+            }
+            None => CryptoRateService::new(self.config.clone())
+                .quote(chain, request.crypto_token.as_deref(), payment.amount, &payment.currency)
+                .await?,
+        };
+        
+        // Stablecoin settlement collects through the provider's deposit address; otherwise
+        // the customer pays the next receiving address from the merchant's own wallet
+        let conversions = CryptoConversionService::new(self.db.clone(), self.config.clone());
+        let conversion = conversions
+            .conversion_for(tx, merchant_id, chain, request.crypto_token.as_deref())
+            .await?;
+        let crypto_address = match &conversion {
+            Some(preference) => conversions
+                .open_conversion(tx, payment.id, chain, request.crypto_token.as_deref(), &quote, preference)
+                .await?,
+            None => CryptoService::new(self.db.clone())
+                .assign_address(tx, merchant_id, payment.id, chain, request.crypto_token.as_deref(), &quote)
+                .await?,
+        };
+        
+        let expiry_minutes = sqlx::query_scalar!(
+            r#"SELECT payment_expiry_minutes FROM crypto_settlement_preferences WHERE merchant_id = $1"#,
+            merchant_id,
         )
         .fetch_optional(&mut **tx)
         .await?
-        .ok_or_else(|| DefiantError::AuthenticationError("Invalid API key".into()))?;
+        .unwrap_or(DEFAULT_PAYMENT_EXPIRY_MINUTES);
         
-        Ok(merchant)
+        let mut details = serde_json::json!({
+            "crypto_chain": crypto_address.chain,
+            "crypto_address": crypto_address.address,
+            "crypto_amount": quote.amount.to_string(),
+            "crypto_quote_expires_at": quote.expires_at,
+        });
+        if let Some(preference) = &conversion {
+            details["crypto_settlement_asset"] = serde_json::json!(preference.settlement_asset);
+        }
+        if let Some(reference) = &crypto_address.reference {
+            details["crypto_reference"] = serde_json::json!(reference);
+        }
+        if let Some(token) = &crypto_address.token_contract {
+            details["crypto_token"] = serde_json::json!(token);
+        }
+        
+        // Update payment with crypto details
+        let updated_payment = sqlx::query_as!(
+            Payment,
+            r#"
+            UPDATE payments 
+            SET metadata = COALESCE(metadata, '{}'::jsonb) || $1::jsonb,
+            expires_at = $2,
+            updated_at = $3
+            WHERE id = $4
+            RETURNING *
+            "#,
+            details,
+            Utc::now() + Duration::minutes(expiry_minutes as i64),
+            Utc::now(),
+            payment.id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        Ok(updated_payment)
+    }
+    
+    /// The caller, whether an API key, OAuth token or dashboard session, and its merchant.
+    async fn authenticate(&self, api_key: &str) -> Result<(AuthenticatedKey, Merchant), DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant = sqlx::query_as!(
+            Merchant,
+            r#"SELECT * FROM merchants WHERE id = $1"#,
+            key.merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        Ok((key, merchant))
     }
     
+    /// Declines the payment, or scores it and says whether it should go ahead flagged
+    /// for review.
     async fn check_fraud(
         &self,
         request: &CreatePaymentRequest,
         merchant_id: &Uuid,
         tx: &mut Transaction<'_, Postgres>,
-    ) -> Result<(), DefiantError> {
+    ) -> Result<FraudChecks, DefiantError> {
         // Simple fraud check
         // In production, use machine learning
         if request.amount > 1_000_000_00 { // $10,000
@@ -237,20 +865,154 @@ impl PaymentService {
             }
         }
         
-        Ok(())
+        // Attempts in quick succession from one card, customer or address
+        let card_fingerprint = request.source.as_ref()
+            .and_then(|source| source.card.as_ref())
+            .map(|card| card_fingerprint(&self.config.card_fingerprint_key, &card.number));
+        let client_ip = request.client_ip.as_deref()
+            .or(request.consent.as_ref().map(|consent| consent.ip_address.as_str()));
+        let subject = VelocitySubject {
+            merchant_id: *merchant_id,
+            customer_id: request.customer_id,
+            card_fingerprint: card_fingerprint.as_deref(),
+            client_ip,
+        };
+        
+        if let Some(entry) = blocklisted(tx, &subject).await? {
+            warn!("Payment for merchant {} declined: {}", merchant_id, entry);
+            return Err(DefiantError::PaymentError(format!("Payment declined by fraud checks: {}", entry)));
+        }
+        
+        let fraud_settings = FraudSettingsService::new(self.db.clone(), self.redis.clone());
+        let settings = fraud_settings.settings(*merchant_id).await?;
+        
+        let velocity = if settings.velocity_checks_enabled {
+            check_velocity(&self.redis, &self.config.velocity_limits, &subject).await
+        } else {
+            VelocityOutcome::Allow
+        };
+        let velocity_review = match velocity {
+            VelocityOutcome::Allow => None,
+            VelocityOutcome::Review(reason) => {
+                info!("Payment for merchant {} flagged for review: {}", merchant_id, reason);
+                Some(reason)
+            }
+            VelocityOutcome::Block(reason) => {
+                warn!("Payment for merchant {} declined: {}", merchant_id, reason);
+                return Err(DefiantError::PaymentError(format!("Payment declined by fraud checks: {}", reason)));
+            }
+        };
+        
+        let billing_country = request.source.as_ref()
+            .and_then(|source| source.billing_details.as_ref())
+            .and_then(|billing| billing.address.as_ref())
+            .map(|address| address.country.as_str());
+        let card_country = request.source.as_ref()
+            .and_then(|source| source.card.as_ref())
+            .and_then(|card| card.issuer_country.as_deref());
+        let geo = match client_ip {
+            Some(ip) => {
+                GeoIpService::new(self.redis.clone(), self.config.clone())
+                    .signals(ip, billing_country, card_country)
+                    .await
+            }
+            None => None,
+        };
+        
+        let ip_country = geo.as_ref().and_then(|geo| geo.ip_country.as_deref());
+        let blocked_country = [ip_country, billing_country, card_country]
+            .into_iter()
+            .flatten()
+            .find(|country| settings.blocked_countries.iter().any(|blocked| blocked.eq_ignore_ascii_case(country)));
+        if let Some(country) = blocked_country {
+            warn!("Payment for merchant {} declined: country {} is blocked", merchant_id, country);
+            return Err(DefiantError::PaymentError(format!(
+                "Payment declined by fraud checks: country_blocked: {}",
+                country.to_uppercase(),
+            )));
+        }
+        
+        // Without a rate to compare at, large limits are treated as reached
+        let amount = match (settings.max_amount_without_review, settings.three_ds_amount_threshold) {
+            (None, None) => None,
+            _ => fraud_settings.amount_in_settings_currency(&settings, request.amount, &request.currency).await,
+        };
+        let amount_review = settings.max_amount_without_review
+            .filter(|limit| amount.map(|amount| amount > *limit).unwrap_or(true))
+            .map(|limit| format!(
+                "amount_review: {} over {} without review",
+                fx_service::format_amount(request.amount, &request.currency),
+                fx_service::format_amount(limit, &settings.currency),
+            ));
+        if let Some(reason) = &amount_review {
+            info!("Payment for merchant {} flagged for review: {}", merchant_id, reason);
+        }
+        let require_three_ds = settings.three_ds_amount_threshold
+            .map(|threshold| amount.map(|amount| amount >= threshold).unwrap_or(true))
+            .unwrap_or(false);
+        
+        let context = RiskContext {
+            merchant_id: *merchant_id,
+            amount: request.amount,
+            currency: &request.currency,
+            payment_method: &request.payment_method,
+            customer_id: request.customer_id,
+            card_fingerprint: subject.card_fingerprint,
+            client_ip,
+            off_session: request.off_session,
+            velocity_review: velocity_review.as_deref(),
+            geo: geo.as_ref(),
+        };
+        let risk = assess_risk(risk_scorer(&self.config).as_ref(), &context).await;
+        
+        // Scores short of blocking are challenged with 3DS instead, when the card is authorized
+        let risk_settings = ScaService::new(self.db.clone(), self.config.clone())
+            .risk_settings(tx, *merchant_id)
+            .await?;
+        if let Some(block_score) = risk_settings.block_score.filter(|block_score| risk.score >= *block_score) {
+            warn!("Payment for merchant {} declined at risk score {}", merchant_id, risk.score);
+            return Err(DefiantError::PaymentError(format!(
+                "Payment declined by fraud checks: risk score {} (limit {})",
+                risk.score, block_score,
+            )));
+        }
+        
+        Ok(FraudChecks {
+            review_reason: velocity_review.or(amount_review),
+            require_three_ds,
+            risk,
+            geo,
+            card_fingerprint: card_fingerprint.clone(),
+            client_ip: client_ip.map(str::to_string),
+        })
     }
     
-    async fn emit_payment_event(&self, payment: &Payment, event_type: &str) {
-        // Publish event to Redis for WebSocket clients
-        let event = serde_json::json!({
-            "type": event_type,
-            "data": payment,
-            "created_at": Utc::now(),
-        });
+    async fn emit_payment_event(&self, merchant_id: Uuid, payment: &Payment, event_type: &str) {
+        // Queue signed deliveries to the merchant's webhook endpoints
+        let event = match WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(payment))
+            .await
+        {
+            Ok(event) => event,
+            Err(e) => {
+                error!("Failed to enqueue webhook event: {}", e);
+                return;
+            }
+        };
         
+        // Publish event to Redis for WebSocket clients, on the merchant's own channel so
+        // a connection bound to one merchant never sees another's payments. Only events
+        // already in the log go out, so a client resuming from an event's id misses none.
+        let rendered = match event_renderer::render(&event, current_webhook_api_version()) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                error!("Failed to render event {}: {}", event.id, e);
+                return;
+            }
+        };
         if let Err(e) = redis::cmd("PUBLISH")
-            .arg("payments")
-            .arg(event.to_string())
+            .arg(event_router::merchant_channel(merchant_id))
+            .arg(serde_json::json!(rendered).to_string())
             .query_async::<_, ()>(&mut self.redis.clone())
             .await
         {
@@ -258,7 +1020,16 @@ impl PaymentService {
         }
     }
     
-    async fn payment_to_response(&self, payment: Payment) -> Result<PaymentResponse, DefiantError> {
+    async fn payment_to_response(&self, pool: &PgPool, payment: Payment) -> Result<PaymentResponse, DefiantError> {
+        let next_action = next_action(&payment);
+        let line_items = sqlx::query_as!(
+            PaymentLineItem,
+            r#"SELECT * FROM payment_line_items WHERE payment_id = $1 ORDER BY created_at, id"#,
+            payment.id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
         Ok(PaymentResponse {
             id: payment.id,
             amount: payment.amount,
@@ -268,13 +1039,67 @@ impl PaymentService {
             customer_id: payment.customer_id,
             description: payment.description,
             metadata: payment.metadata,
+            shipping_amount: payment.shipping_amount,
+            shipping: ShippingDetails::from_value(payment.shipping.as_ref()),
+            is_donation: payment.is_donation,
+            sca_status: payment.sca_status,
+            sca_exemption: payment.sca_exemption,
+            mit_agreement_id: payment.mit_agreement_id,
+            livemode: payment.livemode,
+            line_items,
             created_at: payment.created_at,
+            updated_at: payment.updated_at,
+            version: payment.version,
             client_secret: None, // Only for initial creation
-            next_action: None,
+            next_action,
         })
     }
 }
 
+pub(crate) fn hash_client_secret(client_secret: &str) -> String {
+    hex::encode(digest::digest(&digest::SHA256, client_secret.as_bytes()).as_ref())
+}
+
+/// What the customer's client may see of a payment.
+fn status_response(payment: Payment) -> PaymentStatusResponse {
+    let next_action = next_action(&payment);
+    PaymentStatusResponse {
+        id: payment.id,
+        status: payment.status,
+        amount: payment.amount,
+        currency: payment.currency,
+        livemode: payment.livemode,
+        review_reason: payment.review_reason,
+        risk_score: payment.risk_score,
+        failure_code: payment.failure_code,
+        failure_message: payment.failure_message,
+        next_action,
+    }
+}
+
+/// Payments waiting on the cardholder point at the hosted 3DS page.
+fn next_action(payment: &Payment) -> Option<NextAction> {
+    match (&payment.status, payment.sca_status) {
+        (PaymentStatus::RequiresAction, ScaStatus::ChallengeRequired | ScaStatus::ExemptionDeclined) => {
+            Some(NextAction::ThreeDSecure { url: format!("/hosted/3ds/{}", payment.id) })
+        }
+        _ => None,
+    }
+}
+
+fn card_brand(request: &CreatePaymentRequest) -> Option<&'static str> {
+    request.source.as_ref()
+        .and_then(|source| source.card.as_ref())
+        .map(|card| CardBrand::from_number(&card.number).as_str())
+}
+
+fn card_country(request: &CreatePaymentRequest) -> Option<String> {
+    request.source.as_ref()
+        .and_then(|source| source.card.as_ref())
+        .and_then(|card| card.issuer_country.as_ref())
+        .map(|country| country.to_uppercase())
+}
+
 // Internal types
 struct Merchant {
     id: Uuid,
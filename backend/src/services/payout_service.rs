@@ -0,0 +1,816 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{DateTime, Duration, Utc};
+use tracing::{info, error};
+
+use crate::{models::{Payout, PayoutStatus, PayoutMethod, KycStatus, PayoutInterval, PayoutSchedule, CreatePayoutRequest, PayoutListQuery, PayoutListResponse, UpdatePayoutScheduleRequest, BalanceConversion, BalanceConversionListQuery, BalanceConversionListResponse, AvailableBalance, UpcomingPayouts, ConversionPreview, HeldBackBalance, HeldBackReason, Topup, TopupStatus, BalanceAvailableData, LedgerAccountCode, ConfigurationChangeData, PAYOUT_CREATED, PAYOUT_PAID, PAYOUT_FAILED, PAYOUT_CANCELED, PAYOUT_SCHEDULE_UPDATED, BALANCE_AVAILABLE, TOPUP_SUCCEEDED}, errors::DefiantError, db::Database, config::Config, plugins::{self, PayoutAttempt}};
+use crate::shutdown::Shutdown;
+use super::webhook_service::WebhookService;
+use super::ledger;
+use super::fx_service::{self, FxService};
+
+const AVAILABILITY_SCAN_INTERVAL_SECS: u64 = 60;
+/// When an instant payout is expected to reach the merchant's bank account
+const INSTANT_PAYOUT_ARRIVAL_MINUTES: i64 = 30;
+/// Instant payouts are refused for this long after one of the merchant's payouts fails
+const INSTANT_PAYOUT_FAILURE_LOOKBACK_DAYS: i64 = 30;
+
+/// Money movement in and out of a merchant's balance.
+///
+/// Every state change is booked to the ledger and recorded in `balance_transactions`
+/// in the same transaction, and announced through the webhook pipeline so finance
+/// tooling can follow it.
+pub struct PayoutService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl PayoutService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Pays out part of the available balance in `currency`, refusing more than is
+    /// available. Instant payouts are sent at once and charged their fee on top of
+    /// `amount`; both must fit in the funds card payments made available.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_payout(
+        &self,
+        merchant_id: Uuid,
+        amount: i64,
+        currency: &str,
+        description: Option<String>,
+        arrival_date: Option<DateTime<Utc>>,
+        automatic: bool,
+        method: PayoutMethod,
+    ) -> Result<Payout, DefiantError> {
+        plugins::hooks()
+            .pre_payout(&PayoutAttempt::Balance { merchant_id, amount, currency })
+            .await?;
+        
+        let instant = method == PayoutMethod::Instant;
+        if instant {
+            self.check_instant_eligibility(merchant_id).await?;
+        }
+        let fee = if instant { self.config.instant_payout_fees.fee_for(amount) } else { 0 };
+        let total = amount
+            .checked_add(fee)
+            .ok_or_else(|| DefiantError::ValidationError("Payout amount is out of range".into()))?;
+        let (status, arrival_date) = if instant {
+            (PayoutStatus::InTransit, Some(Utc::now() + Duration::minutes(INSTANT_PAYOUT_ARRIVAL_MINUTES)))
+        } else {
+            (PayoutStatus::Pending, arrival_date)
+        };
+        
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let available = ledger::lock_available(&mut tx, merchant_id, currency).await?;
+        if total > available {
+            return Err(DefiantError::Conflict(format!(
+                "Payout exceeds the available balance of {}",
+                fx_service::format_amount(available, currency),
+            )));
+        }
+        if instant {
+            let eligible = ledger::instant_available(&mut tx, merchant_id, currency).await?;
+            if total > eligible {
+                return Err(DefiantError::Conflict(format!(
+                    "Instant payouts can only spend funds from card payments; {} is eligible, including the {} fee",
+                    fx_service::format_amount(eligible.max(0), currency),
+                    fx_service::format_amount(fee, currency),
+                )));
+            }
+        }
+        
+        let payout = sqlx::query_as!(
+            Payout,
+            r#"
+            INSERT INTO payouts (merchant_id, amount, currency, description, arrival_date, automatic, method, fee, status)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            merchant_id,
+            amount,
+            currency.to_uppercase(),
+            description,
+            arrival_date,
+            automatic,
+            method as PayoutMethod,
+            fee,
+            status as PayoutStatus,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        // Funds leave the balance as soon as the payout is created
+        ledger::record_payout(&mut tx, &payout).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO balance_transactions (merchant_id, amount, currency, fee, net, type, description, metadata, available_on)
+            VALUES ($1, $2, $3, $4, $2 - $4, 'payout', $5, $6, NOW())
+            "#,
+            merchant_id,
+            -payout.amount,
+            payout.currency,
+            payout.fee,
+            payout.description,
+            serde_json::json!({ "payout_id": payout.id }),
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Payout {} created for merchant {}", payout.id, merchant_id);
+        self.emit(merchant_id, PAYOUT_CREATED, serde_json::json!(payout)).await;
+        
+        Ok(payout)
+    }
+    
+    pub async fn create_manual_payout(
+        &self,
+        request: CreatePayoutRequest,
+        api_key: &str,
+    ) -> Result<Payout, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        fx_service::ensure_supported_currency(&request.currency)?;
+        
+        self.create_payout(merchant_id, request.amount, &request.currency, request.description, None, false, request.method).await
+    }
+    
+    /// Instant payouts are for merchants in good standing: active, verified, and
+    /// without a recently failed payout.
+    async fn check_instant_eligibility(&self, merchant_id: Uuid) -> Result<(), DefiantError> {
+        let merchant = sqlx::query!(
+            r#"SELECT active, kyc_status AS "kyc_status: KycStatus" FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        if merchant.active != Some(true) {
+            return Err(DefiantError::AuthorizationError("Instant payouts are not available for inactive accounts".into()));
+        }
+        if merchant.kyc_status != KycStatus::Verified {
+            return Err(DefiantError::AuthorizationError("Instant payouts require a verified account".into()));
+        }
+        
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let recently_failed = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM payouts
+                WHERE merchant_id = $1 AND status = 'failed'
+                AND failed_at > NOW() - make_interval(days => $2::INT)
+            ) AS "exists!"
+            "#,
+            merchant_id,
+            INSTANT_PAYOUT_FAILURE_LOOKBACK_DAYS as i32,
+        )
+        .fetch_one(pool)
+        .await?;
+        if recently_failed {
+            return Err(DefiantError::AuthorizationError(format!(
+                "Instant payouts are unavailable for {} days after a payout fails",
+                INSTANT_PAYOUT_FAILURE_LOOKBACK_DAYS,
+            )));
+        }
+        
+        Ok(())
+    }
+    
+    pub async fn list_payouts(
+        &self,
+        query: PayoutListQuery,
+        api_key: &str,
+    ) -> Result<PayoutListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            Payout,
+            r#"
+            SELECT * FROM payouts
+            WHERE merchant_id = $1
+            AND ($2::payout_status IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            merchant_id,
+            query.status as Option<PayoutStatus>,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(PayoutListResponse {
+            data,
+            has_more,
+            url: "/api/v1/payouts".into(),
+        })
+    }
+    
+    pub async fn get_payout(&self, payout_id: Uuid, api_key: &str) -> Result<Payout, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        sqlx::query_as!(
+            Payout,
+            r#"SELECT * FROM payouts WHERE id = $1 AND merchant_id = $2"#,
+            payout_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payout not found".into()))
+    }
+    
+    /// Stops a payout that has not been sent yet and returns its funds to the balance.
+    pub async fn cancel_payout(&self, payout_id: Uuid, api_key: &str) -> Result<Payout, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let payout = sqlx::query_as!(
+            Payout,
+            r#"SELECT * FROM payouts WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            payout_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payout not found".into()))?;
+        
+        if !matches!(payout.status, PayoutStatus::Pending) {
+            return Err(DefiantError::Conflict("Only payouts that have not been sent can be canceled".into()));
+        }
+        
+        let payout = sqlx::query_as!(
+            Payout,
+            r#"
+            UPDATE payouts SET status = $1, canceled_at = NOW()
+            WHERE id = $2
+            RETURNING *
+            "#,
+            PayoutStatus::Canceled as PayoutStatus,
+            payout.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        ledger::record_payout_cancellation(&mut tx, &payout).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO balance_transactions (merchant_id, amount, currency, fee, net, type, description, metadata, available_on)
+            VALUES ($1, $2, $3, $4, $2 - $4, 'payout_cancel', $5, $6, NOW())
+            "#,
+            payout.merchant_id,
+            payout.amount,
+            payout.currency,
+            -payout.fee,
+            payout.description,
+            serde_json::json!({ "payout_id": payout.id }),
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Payout {} canceled by merchant {}", payout.id, merchant_id);
+        self.emit(merchant_id, PAYOUT_CANCELED, serde_json::json!(payout)).await;
+        
+        Ok(payout)
+    }
+    
+    pub async fn get_payout_schedule(&self, api_key: &str) -> Result<PayoutSchedule, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let schedule = sqlx::query_as!(
+            PayoutSchedule,
+            r#"SELECT * FROM payout_schedules WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(schedule.unwrap_or(PayoutSchedule {
+            merchant_id,
+            interval: PayoutInterval::Manual,
+            weekly_anchor: None,
+            settlement_currency: None,
+            cutoff_hour: 0,
+            timezone: "UTC".into(),
+            minimum_payout_amount: 0,
+            last_run_on: None,
+            created_at: None,
+            updated_at: None,
+        }))
+    }
+    
+    pub async fn update_payout_schedule(
+        &self,
+        request: UpdatePayoutScheduleRequest,
+        api_key: &str,
+    ) -> Result<PayoutSchedule, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let weekly_anchor = match request.interval {
+            PayoutInterval::Weekly => Some(request.weekly_anchor.ok_or_else(|| {
+                DefiantError::ValidationError("weekly_anchor is required for weekly payouts".into())
+            })?),
+            _ => None,
+        };
+        if let Some(currency) = &request.settlement_currency {
+            fx_service::ensure_supported_currency(currency)?;
+        }
+        let timezone = request.timezone.unwrap_or_else(|| "UTC".into());
+        let known_timezone = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM pg_timezone_names WHERE name = $1) AS "exists!""#,
+            timezone,
+        )
+        .fetch_one(pool)
+        .await?;
+        if !known_timezone {
+            return Err(DefiantError::ValidationError(format!("Unknown timezone: {}", timezone)));
+        }
+        
+        let schedule = sqlx::query_as!(
+            PayoutSchedule,
+            r#"
+            INSERT INTO payout_schedules (merchant_id, interval, weekly_anchor, settlement_currency, cutoff_hour, timezone, minimum_payout_amount)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (merchant_id) DO UPDATE
+            SET interval = EXCLUDED.interval,
+                weekly_anchor = EXCLUDED.weekly_anchor,
+                settlement_currency = EXCLUDED.settlement_currency,
+                cutoff_hour = EXCLUDED.cutoff_hour,
+                timezone = EXCLUDED.timezone,
+                minimum_payout_amount = EXCLUDED.minimum_payout_amount
+            RETURNING *
+            "#,
+            merchant_id,
+            request.interval as PayoutInterval,
+            weekly_anchor,
+            request.settlement_currency.map(|currency| currency.to_uppercase()),
+            request.cutoff_hour.unwrap_or(0),
+            timezone,
+            request.minimum_payout_amount.unwrap_or(0),
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Merchant {} now paid out {:?}", merchant_id, schedule.interval);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                PAYOUT_SCHEDULE_UPDATED,
+                ConfigurationChangeData::new(
+                    "payout_schedule",
+                    merchant_id,
+                    vec![
+                        "interval".into(),
+                        "weekly_anchor".into(),
+                        "settlement_currency".into(),
+                        "cutoff_hour".into(),
+                        "timezone".into(),
+                        "minimum_payout_amount".into(),
+                    ],
+                ),
+                api_key,
+            )
+            .await;
+        
+        Ok(schedule)
+    }
+    
+    /// Pays out the balance, per currency, that was available at the cutoff of every
+    /// merchant whose schedule is due: the scheduled day has reached its cutoff hour in
+    /// the merchant's timezone. A schedule runs at most once a day; a merchant whose
+    /// payout fails is retried on its next scheduled day, as are balances below the
+    /// minimum.
+    ///
+    /// With a settlement currency, the other currencies are converted into it and only
+    /// it is paid out; a currency without a rate for the day stays in the balance.
+    pub async fn run_scheduled_payouts(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let schedules = sqlx::query!(
+            r#"
+            UPDATE payout_schedules SET last_run_on = (NOW() AT TIME ZONE timezone)::date
+            WHERE interval <> 'manual'
+            AND (last_run_on IS NULL OR last_run_on < (NOW() AT TIME ZONE timezone)::date)
+            AND EXTRACT(HOUR FROM NOW() AT TIME ZONE timezone) >= cutoff_hour
+            AND (interval = 'daily' OR weekly_anchor = EXTRACT(ISODOW FROM NOW() AT TIME ZONE timezone))
+            RETURNING merchant_id, settlement_currency, minimum_payout_amount,
+                ((NOW() AT TIME ZONE timezone)::date + make_time(cutoff_hour, 0, 0)) AT TIME ZONE timezone AS "cutoff!"
+            "#,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        for schedule in schedules {
+            let merchant_id = schedule.merchant_id;
+            let mut balances = ledger::available_balances_at(pool, merchant_id, schedule.cutoff).await?;
+            
+            if let Some(settlement_currency) = &schedule.settlement_currency {
+                let mut settled = 0;
+                for balance in balances.iter().filter(|balance| balance.amount > 0) {
+                    if balance.currency == *settlement_currency {
+                        settled += balance.amount;
+                        continue;
+                    }
+                    match self.convert_available(pool, merchant_id, &balance.currency, settlement_currency, balance.amount).await {
+                        Ok(Some(conversion)) => settled += conversion.to_amount,
+                        Ok(None) => {}
+                        Err(e) => error!(
+                            "Converting the {} balance of merchant {} to {} failed: {}",
+                            balance.currency, merchant_id, settlement_currency, e,
+                        ),
+                    }
+                }
+                balances = vec![AvailableBalance { currency: settlement_currency.clone(), amount: settled }];
+            }
+            
+            let due = balances
+                .into_iter()
+                .filter(|balance| balance.amount > 0 && balance.amount >= schedule.minimum_payout_amount);
+            for balance in due {
+                let created = self
+                    .create_payout(merchant_id, balance.amount, &balance.currency, None, None, true, PayoutMethod::Standard)
+                    .await;
+                if let Err(e) = created {
+                    error!(
+                        "Scheduled payout of {} for merchant {} failed: {}",
+                        fx_service::format_amount(balance.amount, &balance.currency), merchant_id, e,
+                    );
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Converts up to `up_to` of the available balance in `from` into `to` at the day's
+    /// rate.
+    async fn convert_available(
+        &self,
+        pool: &PgPool,
+        merchant_id: Uuid,
+        from: &str,
+        to: &str,
+        up_to: i64,
+    ) -> Result<Option<BalanceConversion>, DefiantError> {
+        let rate = FxService::new(self.db.clone())
+            .rate_on(from, to, Utc::now().date_naive())
+            .await?
+            .ok_or_else(|| DefiantError::ValidationError(format!("No exchange rate available for {} to {}", from, to)))?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let from_amount = ledger::lock_available(&mut tx, merchant_id, from).await?.min(up_to);
+        if from_amount <= 0 {
+            return Ok(None);
+        }
+        let to_amount = fx_service::convert_amount(from_amount, from, to, rate.rate)?;
+        
+        let conversion = sqlx::query_as!(
+            BalanceConversion,
+            r#"
+            INSERT INTO balance_conversions (merchant_id, from_currency, from_amount, to_currency, to_amount, rate, rate_date, rate_source)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+            "#,
+            merchant_id,
+            rate.from,
+            from_amount,
+            rate.to,
+            to_amount,
+            rate.rate,
+            rate.rate_date,
+            rate.source,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        ledger::record_conversion(&mut tx, &conversion).await?;
+        
+        tx.commit().await?;
+        
+        info!(
+            "Converted {} to {} for merchant {}",
+            fx_service::format_amount(from_amount, from), fx_service::format_amount(to_amount, to), merchant_id,
+        );
+        
+        Ok(Some(conversion))
+    }
+    
+    /// A dry run of the merchant's next scheduled payout run. Nothing is converted or
+    /// paid out.
+    pub async fn upcoming_payouts(&self, api_key: &str) -> Result<UpcomingPayouts, DefiantError> {
+        let schedule = self.get_payout_schedule(api_key).await?;
+        let merchant_id = schedule.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let mut upcoming = UpcomingPayouts {
+            interval: schedule.interval,
+            cutoff: None,
+            conversions: Vec::new(),
+            payouts: Vec::new(),
+            held_back: Vec::new(),
+        };
+        if schedule.interval == PayoutInterval::Manual {
+            return Ok(upcoming);
+        }
+        
+        // The first scheduled day, from today in the merchant's timezone, not yet run
+        let cutoff = sqlx::query_scalar!(
+            r#"
+            SELECT (day::date + make_time(s.cutoff_hour, 0, 0)) AT TIME ZONE s.timezone AS "cutoff!"
+            FROM payout_schedules s,
+                generate_series((NOW() AT TIME ZONE s.timezone)::date, (NOW() AT TIME ZONE s.timezone)::date + 7, INTERVAL '1 day') AS day
+            WHERE s.merchant_id = $1
+            AND (s.last_run_on IS NULL OR day::date > s.last_run_on)
+            AND (s.interval = 'daily' OR s.weekly_anchor = EXTRACT(ISODOW FROM day))
+            ORDER BY day
+            LIMIT 1
+            "#,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        let cutoff = match cutoff {
+            Some(cutoff) => cutoff,
+            None => return Ok(upcoming),
+        };
+        upcoming.cutoff = Some(cutoff);
+        
+        let mut balances: Vec<AvailableBalance> = ledger::available_balances_at(pool, merchant_id, cutoff)
+            .await?
+            .into_iter()
+            .filter(|balance| balance.amount > 0)
+            .collect();
+        
+        if let Some(settlement_currency) = &schedule.settlement_currency {
+            let fx = FxService::new(self.db.clone());
+            let today = Utc::now().date_naive();
+            let mut settled = 0;
+            for balance in balances {
+                if balance.currency == *settlement_currency {
+                    settled += balance.amount;
+                    continue;
+                }
+                match fx.rate_on(&balance.currency, settlement_currency, today).await? {
+                    Some(rate) => {
+                        let to_amount = fx_service::convert_amount(balance.amount, &balance.currency, settlement_currency, rate.rate)?;
+                        settled += to_amount;
+                        upcoming.conversions.push(ConversionPreview {
+                            from_currency: balance.currency,
+                            from_amount: balance.amount,
+                            to_currency: settlement_currency.clone(),
+                            to_amount,
+                            rate: rate.rate,
+                        });
+                    }
+                    None => upcoming.held_back.push(HeldBackBalance {
+                        currency: balance.currency,
+                        amount: balance.amount,
+                        reason: HeldBackReason::NoExchangeRate,
+                    }),
+                }
+            }
+            balances = vec![AvailableBalance { currency: settlement_currency.clone(), amount: settled }];
+        }
+        
+        for balance in balances.into_iter().filter(|balance| balance.amount > 0) {
+            if balance.amount >= schedule.minimum_payout_amount {
+                upcoming.payouts.push(balance);
+            } else {
+                upcoming.held_back.push(HeldBackBalance {
+                    currency: balance.currency,
+                    amount: balance.amount,
+                    reason: HeldBackReason::BelowMinimum,
+                });
+            }
+        }
+        
+        Ok(upcoming)
+    }
+    
+    /// Conversions between the merchant's balances, newest first.
+    pub async fn list_balance_conversions(
+        &self,
+        query: BalanceConversionListQuery,
+        api_key: &str,
+    ) -> Result<BalanceConversionListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            BalanceConversion,
+            r#"
+            SELECT * FROM balance_conversions
+            WHERE merchant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(BalanceConversionListResponse {
+            data,
+            has_more,
+            url: "/api/v1/balance/conversions".into(),
+        })
+    }
+    
+    pub async fn mark_payout_paid(&self, payout_id: Uuid) -> Result<Payout, DefiantError> {
+        let pool = self.db.pool_for_row("payouts", payout_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Payout not found".into()))?;
+        let payout = sqlx::query_as!(
+            Payout,
+            r#"
+            UPDATE payouts
+            SET status = $1, paid_at = NOW()
+            WHERE id = $2 AND status IN ('pending', 'in_transit')
+            RETURNING *
+            "#,
+            PayoutStatus::Paid as PayoutStatus,
+            payout_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Payout is not pending or in transit".into()))?;
+        
+        self.emit(payout.merchant_id, PAYOUT_PAID, serde_json::json!(payout)).await;
+        
+        Ok(payout)
+    }
+    
+    /// Fails a payout and returns its funds to the merchant's balance.
+    pub async fn mark_payout_failed(
+        &self,
+        payout_id: Uuid,
+        failure_code: &str,
+        failure_message: &str,
+    ) -> Result<Payout, DefiantError> {
+        let pool = self.db.pool_for_row("payouts", payout_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Payout not found".into()))?;
+        let mut tx = pool.begin().await?;
+        
+        let payout = sqlx::query_as!(
+            Payout,
+            r#"
+            UPDATE payouts
+            SET status = $1, failure_code = $2, failure_message = $3, failed_at = NOW()
+            WHERE id = $4 AND status IN ('pending', 'in_transit')
+            RETURNING *
+            "#,
+            PayoutStatus::Failed as PayoutStatus,
+            failure_code,
+            failure_message,
+            payout_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Payout is not pending or in transit".into()))?;
+        
+        ledger::record_payout_failure(&mut tx, &payout).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO balance_transactions (merchant_id, amount, currency, fee, net, type, description, metadata, available_on)
+            VALUES ($1, $2, $3, $4, $2 - $4, 'payout_failure', $5, $6, NOW())
+            "#,
+            payout.merchant_id,
+            payout.amount,
+            payout.currency,
+            -payout.fee,
+            failure_message,
+            serde_json::json!({ "payout_id": payout.id }),
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        self.emit(payout.merchant_id, PAYOUT_FAILED, serde_json::json!(payout)).await;
+        
+        Ok(payout)
+    }
+    
+    /// Credits a completed top-up to the merchant's balance.
+    pub async fn mark_topup_succeeded(&self, topup_id: Uuid) -> Result<Topup, DefiantError> {
+        let pool = self.db.pool_for_row("topups", topup_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Top-up not found".into()))?;
+        let mut tx = pool.begin().await?;
+        
+        let topup = sqlx::query_as!(
+            Topup,
+            r#"
+            UPDATE topups
+            SET status = $1, succeeded_at = NOW()
+            WHERE id = $2 AND status = 'pending'
+            RETURNING *
+            "#,
+            TopupStatus::Succeeded as TopupStatus,
+            topup_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Top-up is not pending".into()))?;
+        
+        ledger::record_topup(&mut tx, &topup).await?;
+        sqlx::query!(
+            r#"
+            INSERT INTO balance_transactions (merchant_id, amount, currency, net, type, description, metadata, available_on)
+            VALUES ($1, $2, $3, $2, 'topup', $4, $5, NOW())
+            "#,
+            topup.merchant_id,
+            topup.amount,
+            topup.currency,
+            topup.description,
+            serde_json::json!({ "topup_id": topup.id }),
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        self.emit(topup.merchant_id, TOPUP_SUCCEEDED, serde_json::json!(topup)).await;
+        
+        Ok(topup)
+    }
+    
+    /// Emits `balance.available` for every merchant with ledger funds that cleared since
+    /// the last scan.
+    pub async fn announce_available_funds(&self) -> Result<(), DefiantError> {
+        for pool in self.db.pools() {
+            let merchant_ids = sqlx::query_scalar!(
+                r#"
+                WITH cleared AS (
+                    SELECT e.merchant_id, MAX(e.available_on) AS through
+                    FROM journal_entries e
+                    JOIN journal_lines l ON l.journal_entry_id = e.id
+                    JOIN ledger_accounts a ON a.id = l.account_id AND a.code = $1
+                    LEFT JOIN balance_announcements b ON b.merchant_id = e.merchant_id
+                    WHERE e.available_on <= NOW()
+                    AND (b.announced_through IS NULL OR e.available_on > b.announced_through)
+                    GROUP BY e.merchant_id
+                )
+                INSERT INTO balance_announcements (merchant_id, announced_through)
+                SELECT merchant_id, through FROM cleared
+                ON CONFLICT (merchant_id) DO UPDATE SET announced_through = EXCLUDED.announced_through
+                RETURNING merchant_id
+                "#,
+                LedgerAccountCode::MerchantBalance.as_str(),
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            for merchant_id in merchant_ids {
+                let available = ledger::available_balances(pool, merchant_id).await?;
+                self.emit(merchant_id, BALANCE_AVAILABLE, serde_json::json!(BalanceAvailableData { available })).await;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, data: serde_json::Value) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, data)
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+/// Periodically announces newly available funds.
+pub fn spawn_availability_watcher(db: Arc<Database>, config: Arc<Config>, shutdown: &Shutdown) {
+    shutdown.spawn(|mut shutdown| async move {
+        let service = PayoutService::new(db, config);
+        let mut interval = tokio::time::interval(StdDuration::from_secs(AVAILABILITY_SCAN_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            if let Err(e) = service.announce_available_funds().await {
+                error!("Failed to announce available balances: {}", e);
+            }
+        }
+    });
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::{info, warn};
+
+use crate::{models::{Pii, SealedPii}, errors::DefiantError, db::Database, config::Config, pii};
+
+const REENCRYPTION_BATCH_SIZE: i64 = 500;
+
+/// Moves stored PII onto the current keys: data keys onto the active master key, and
+/// values onto the active data key, bound to the column and row they are stored in.
+/// Values sealed before they were bound, and plaintext left from before encryption,
+/// are sealed the same way, and customers missing an email hash get one.
+///
+/// Re-sealing a customer is an update like any other, so it moves their `version`
+/// and `updated_at`.
+pub struct PiiService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl PiiService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn reencrypt(&self) -> Result<(), DefiantError> {
+        pii::rewrap_data_keys(&self.db, &self.config).await?;
+        
+        for pool in self.db.pools() {
+            self.reencrypt_customers(pool).await?;
+            self.reencrypt_bank_accounts(pool).await?;
+        }
+        
+        Ok(())
+    }
+    
+    async fn reencrypt_customers(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let keyring = pii::keyring()?;
+        let prefix = keyring.active_prefix();
+        let mut after = Uuid::nil();
+        let mut resealed = 0;
+        
+        loop {
+            let batch = sqlx::query!(
+                r#"
+                SELECT id, email AS "email: SealedPii", phone AS "phone: SealedPii"
+                FROM customers
+                WHERE id > $1
+                AND (email NOT LIKE $2 || '%' OR phone NOT LIKE $2 || '%' OR email_hash IS NULL)
+                ORDER BY id
+                LIMIT $3
+                "#,
+                after,
+                prefix,
+                REENCRYPTION_BATCH_SIZE,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            let Some(last) = batch.last() else {
+                break;
+            };
+            after = last.id;
+            
+            for row in batch {
+                let email = reseal(&row.email, &pii::context("customers.email", row.id))?;
+                let phone = row.phone.as_ref()
+                    .map(|phone| reseal(phone, &pii::context("customers.phone", row.id)))
+                    .transpose()?;
+                // Skipped if the customer changed since it was read; the next run picks it up
+                let updated = sqlx::query!(
+                    r#"
+                    UPDATE customers SET email = $2, phone = $3, email_hash = $4
+                    WHERE id = $1 AND email = $5 AND phone IS NOT DISTINCT FROM $6
+                    "#,
+                    row.id,
+                    email.sealed(),
+                    phone.as_ref().map(Pii::sealed),
+                    keyring.blind_index(email.as_str()),
+                    row.email.sealed(),
+                    row.phone.as_ref().map(SealedPii::sealed),
+                )
+                .execute(pool)
+                .await;
+                
+                match updated {
+                    Ok(_) => resealed += 1,
+                    // Two customers whose emails differ only in case share a hash
+                    Err(e) => warn!("Failed to re-encrypt customer {}: {}", row.id, e),
+                }
+            }
+        }
+        
+        if resealed > 0 {
+            info!("Re-encrypted {} customers", resealed);
+        }
+        Ok(())
+    }
+    
+    async fn reencrypt_bank_accounts(&self, pool: &PgPool) -> Result<(), DefiantError> {
+        let prefix = pii::keyring()?.active_prefix();
+        let mut after = Uuid::nil();
+        let mut resealed = 0;
+        
+        loop {
+            let batch = sqlx::query!(
+                r#"
+                SELECT id, account_holder_name AS "account_holder_name: SealedPii", routing_number AS "routing_number: SealedPii"
+                FROM merchant_bank_accounts
+                WHERE id > $1
+                AND (account_holder_name NOT LIKE $2 || '%' OR routing_number NOT LIKE $2 || '%')
+                ORDER BY id
+                LIMIT $3
+                "#,
+                after,
+                prefix,
+                REENCRYPTION_BATCH_SIZE,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            let Some(last) = batch.last() else {
+                break;
+            };
+            after = last.id;
+            
+            for row in batch {
+                let holder = reseal(&row.account_holder_name, &pii::context("merchant_bank_accounts.account_holder_name", row.id))?;
+                let routing_number = row.routing_number.as_ref()
+                    .map(|routing_number| reseal(routing_number, &pii::context("merchant_bank_accounts.routing_number", row.id)))
+                    .transpose()?;
+                sqlx::query!(
+                    r#"
+                    UPDATE merchant_bank_accounts SET account_holder_name = $2, routing_number = $3
+                    WHERE id = $1 AND account_holder_name = $4 AND routing_number IS NOT DISTINCT FROM $5
+                    "#,
+                    row.id,
+                    holder.sealed(),
+                    routing_number.as_ref().map(Pii::sealed),
+                    row.account_holder_name.sealed(),
+                    row.routing_number.as_ref().map(SealedPii::sealed),
+                )
+                .execute(pool)
+                .await?;
+                resealed += 1;
+            }
+        }
+        
+        if resealed > 0 {
+            info!("Re-encrypted {} bank accounts", resealed);
+        }
+        Ok(())
+    }
+}
+
+/// Opens a stored value however it was sealed and seals it again for `context`.
+fn reseal(stored: &SealedPii, context: &str) -> Result<Pii, DefiantError> {
+    Pii::seal(context, stored.open(context)?.into_string())
+}
@@ -0,0 +1,237 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{Payment, PaymentMethod, DataRegion, CardRegion, PricingRule, PricingPlan, PricingPlanWithRules, UpdatePricingPlanRequest, AssignPricingPlanRequest, EffectivePricing, FeeSchedule}, errors::DefiantError, db::Database, config::{Config, ProcessingFees}};
+use super::sca_service::SCA_COUNTRIES;
+
+/// The processing fee charged on a payment, and the rule that priced it.
+#[derive(Debug, Clone, Copy)]
+pub struct AppliedFee {
+    /// Minor units, never more than the payment
+    pub amount: i64,
+    /// Absent when no rule of the merchant's plan matched and `fallback` applied
+    pub pricing_rule_id: Option<Uuid>,
+}
+
+/// Payment attributes pricing rules match on.
+struct PricedPayment<'a> {
+    payment_method: &'a PaymentMethod,
+    card_brand: Option<&'a str>,
+    card_region: Option<CardRegion>,
+}
+
+/// Prices a payment by the merchant's plan. `fallback` applies when no rule matches.
+///
+/// Plans and their rules are only written to the directory database, so they are read
+/// from `directory` rather than the regional cluster booking the fee.
+pub async fn fee_for_payment(
+    directory: &PgPool,
+    payment: &Payment,
+    fallback: ProcessingFees,
+) -> Result<AppliedFee, DefiantError> {
+    let rules = sqlx::query_as!(
+        PricingRule,
+        r#"
+        SELECT r.* FROM pricing_rules r
+        JOIN merchants m ON m.pricing_plan = r.plan_code
+        WHERE m.id = $1
+        "#,
+        payment.merchant_id,
+    )
+    .fetch_all(directory)
+    .await?;
+    
+    let region = sqlx::query_scalar!(
+        r#"SELECT data_region AS "data_region: DataRegion" FROM merchants WHERE id = $1"#,
+        payment.merchant_id,
+    )
+    .fetch_one(directory)
+    .await?;
+    
+    let priced = PricedPayment {
+        payment_method: &payment.payment_method,
+        card_brand: payment.card_brand.as_deref(),
+        card_region: payment.card_country.as_deref().map(|country| card_region(region, country)),
+    };
+    
+    let rule = rules
+        .iter()
+        .filter(|rule| matches(rule, &priced))
+        .max_by_key(|rule| rank(rule));
+    
+    Ok(match rule {
+        Some(rule) => AppliedFee {
+            amount: fee(rule.percent_bps as u32, rule.fixed, payment.amount),
+            pricing_rule_id: Some(rule.id),
+        },
+        None => AppliedFee {
+            amount: fallback.fee_for(payment.amount),
+            pricing_rule_id: None,
+        },
+    })
+}
+
+fn fee(percent_bps: u32, fixed: i64, amount: i64) -> i64 {
+    ProcessingFees { percent_bps, fixed }.fee_for(amount)
+}
+
+/// Cards issued where the merchant's data lives count as domestic.
+fn card_region(region: DataRegion, country: &str) -> CardRegion {
+    let domestic = match region {
+        DataRegion::Us => country.eq_ignore_ascii_case("US"),
+        DataRegion::Eu => SCA_COUNTRIES.iter().any(|c| c.eq_ignore_ascii_case(country)),
+    };
+    if domestic {
+        CardRegion::Domestic
+    } else {
+        CardRegion::International
+    }
+}
+
+/// Whether every criterion the rule sets matches the payment.
+fn matches(rule: &PricingRule, payment: &PricedPayment<'_>) -> bool {
+    rule.payment_method.as_ref().map_or(true, |method| method == payment.payment_method)
+        && rule.card_region.map_or(true, |region| payment.card_region == Some(region))
+        && rule.card_brand.as_deref().map_or(true, |brand| payment.card_brand == Some(brand))
+}
+
+/// How many criteria the rule sets, with the card brand, then the region, breaking
+/// ties. The highest ranked matching rule prices the payment.
+fn rank(rule: &PricingRule) -> (u8, bool, bool) {
+    let criteria = rule.payment_method.is_some() as u8 + rule.card_region.is_some() as u8 + rule.card_brand.is_some() as u8;
+    (criteria, rule.card_brand.is_some(), rule.card_region.is_some())
+}
+
+/// Admin management of pricing plans and the plan each merchant is on.
+pub struct PricingService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl PricingService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn effective_pricing(&self, merchant_id: Uuid) -> Result<EffectivePricing, DefiantError> {
+        let plan = sqlx::query_scalar!(
+            r#"SELECT pricing_plan FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        let mut rules = self.rules(&plan).await?;
+        rules.sort_by_key(|rule| std::cmp::Reverse(rank(rule)));
+        
+        Ok(EffectivePricing {
+            merchant_id,
+            plan,
+            rules,
+            default_card_fee: FeeSchedule {
+                percent_bps: self.config.processing_fees.percent_bps as i64,
+                fixed: self.config.processing_fees.fixed,
+            },
+        })
+    }
+    
+    /// Creates the plan or replaces its rules. Applies to fees booked from now on.
+    pub async fn update_plan(
+        &self,
+        code: &str,
+        request: UpdatePricingPlanRequest,
+    ) -> Result<PricingPlanWithRules, DefiantError> {
+        let mut tx = self.db.pool.begin().await?;
+        
+        let plan = sqlx::query_as!(
+            PricingPlan,
+            r#"
+            INSERT INTO pricing_plans (code, name)
+            VALUES ($1, $2)
+            ON CONFLICT (code) DO UPDATE SET name = EXCLUDED.name
+            RETURNING *
+            "#,
+            code,
+            request.name,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        sqlx::query!(r#"DELETE FROM pricing_rules WHERE plan_code = $1"#, plan.code)
+            .execute(&mut *tx)
+            .await?;
+        
+        let mut rules = Vec::with_capacity(request.rules.len());
+        for rule in request.rules {
+            let rule = sqlx::query_as!(
+                PricingRule,
+                r#"
+                INSERT INTO pricing_rules (plan_code, payment_method, card_brand, card_region, percent_bps, fixed)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                RETURNING *
+                "#,
+                plan.code,
+                rule.payment_method as Option<PaymentMethod>,
+                rule.card_brand.map(|brand| brand.as_str()),
+                rule.card_region as Option<CardRegion>,
+                rule.percent_bps,
+                rule.fixed,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            rules.push(rule);
+        }
+        
+        tx.commit().await?;
+        
+        info!("Pricing plan {} now has {} rules", plan.code, rules.len());
+        
+        Ok(PricingPlanWithRules { plan, rules })
+    }
+    
+    pub async fn assign_plan(
+        &self,
+        merchant_id: Uuid,
+        request: AssignPricingPlanRequest,
+    ) -> Result<EffectivePricing, DefiantError> {
+        let plan_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM pricing_plans WHERE code = $1) AS "exists!""#,
+            request.plan,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        if !plan_exists {
+            return Err(DefiantError::ValidationError(format!("Unknown pricing plan: {}", request.plan)));
+        }
+        
+        let updated = sqlx::query!(
+            r#"UPDATE merchants SET pricing_plan = $1 WHERE id = $2"#,
+            request.plan,
+            merchant_id,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(DefiantError::NotFound("Merchant not found".into()));
+        }
+        
+        info!("Merchant {} moved to pricing plan {}", merchant_id, request.plan);
+        
+        self.effective_pricing(merchant_id).await
+    }
+    
+    async fn rules(&self, plan: &str) -> Result<Vec<PricingRule>, DefiantError> {
+        let rules = sqlx::query_as!(
+            PricingRule,
+            r#"SELECT * FROM pricing_rules WHERE plan_code = $1 ORDER BY created_at"#,
+            plan,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        Ok(rules)
+    }
+}
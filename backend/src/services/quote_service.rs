@@ -0,0 +1,401 @@
+use std::sync::Arc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use redis::aio::ConnectionManager;
+use tracing::info;
+
+use crate::{models::{Quote, QuoteLineItem, QuoteStatus, CreateQuoteRequest, QuoteResponse, Plan, SubscriptionStatus}, errors::DefiantError, db::Database};
+use super::fx_service;
+use super::invoice_service::{self, InvoiceDraftLine};
+
+const DEFAULT_EXPIRY_DAYS: i64 = 30;
+
+pub struct QuoteService {
+    db: Arc<Database>,
+    redis: Arc<ConnectionManager>,
+}
+
+impl QuoteService {
+    pub fn new(db: Arc<Database>, redis: Arc<ConnectionManager>) -> Self {
+        Self { db, redis }
+    }
+    
+    pub async fn create_quote(
+        &self,
+        request: CreateQuoteRequest,
+        api_key: &str,
+    ) -> Result<QuoteResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let currency = request.currency.to_uppercase();
+        fx_service::ensure_supported_currency(&currency)?;
+        
+        match (request.plan_id.is_some(), request.line_items.is_empty()) {
+            (true, false) => {
+                return Err(DefiantError::ValidationError("Provide either plan_id or line_items, not both".into()))
+            }
+            (false, true) => {
+                return Err(DefiantError::ValidationError("line_items: a quote needs at least one line item".into()))
+            }
+            _ => {}
+        }
+        
+        let mut tx = pool.begin().await?;
+        
+        let customer_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1 AND merchant_id = $2 AND livemode = $3)"#,
+            request.customer_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(false);
+        
+        if !customer_exists {
+            return Err(DefiantError::NotFound("Customer not found".into()));
+        }
+        
+        let amount_total = match request.plan_id {
+            Some(plan_id) => {
+                let plan = self.load_plan(plan_id, merchant_id, &mut tx).await?;
+                if !plan.currency.eq_ignore_ascii_case(&currency) {
+                    return Err(DefiantError::ValidationError(format!(
+                        "currency: plan is priced in {}, not {}",
+                        plan.currency, currency
+                    )));
+                }
+                plan.amount
+            }
+            None => request.line_items
+                .iter()
+                .try_fold(0i64, |total, line| {
+                    line.unit_amount
+                        .checked_mul(line.quantity as i64)
+                        .and_then(|amount| total.checked_add(amount))
+                })
+                .ok_or_else(|| DefiantError::ValidationError("Quote amount is out of range".into()))?,
+        };
+        
+        let expires_at = Utc::now() + Duration::days(request.expires_in_days.unwrap_or(DEFAULT_EXPIRY_DAYS));
+        
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"
+            INSERT INTO quotes (
+                merchant_id, customer_id, status, currency, amount_total,
+                description, plan_id, expires_at, metadata, livemode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.customer_id,
+            QuoteStatus::Draft as QuoteStatus,
+            currency,
+            amount_total,
+            request.description,
+            request.plan_id,
+            expires_at,
+            request.metadata,
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        let mut line_items = Vec::with_capacity(request.line_items.len());
+        for line in &request.line_items {
+            let item = sqlx::query_as!(
+                QuoteLineItem,
+                r#"
+                INSERT INTO quote_line_items (quote_id, description, quantity, unit_amount, amount)
+                VALUES ($1, $2, $3, $4, $5)
+                RETURNING *
+                "#,
+                quote.id,
+                line.description,
+                line.quantity,
+                line.unit_amount,
+                line.unit_amount * line.quantity as i64,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            line_items.push(item);
+        }
+        
+        tx.commit().await?;
+        
+        info!("Quote created: {}", quote.id);
+        Ok(to_response(quote, line_items))
+    }
+    
+    pub async fn get_quote(&self, quote_id: Uuid, api_key: &str) -> Result<QuoteResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"SELECT * FROM quotes WHERE id = $1 AND merchant_id = $2"#,
+            quote_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Quote not found".into()))?;
+        
+        let line_items = self.line_items(pool, quote.id).await?;
+        Ok(to_response(quote, line_items))
+    }
+    
+    /// Moves a draft to `open` and issues the token for the customer's acceptance link.
+    pub async fn finalize_quote(&self, quote_id: Uuid, api_key: &str) -> Result<QuoteResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(48)
+            .map(char::from)
+            .collect();
+        
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"
+            UPDATE quotes
+            SET status = $1, acceptance_token = $2, finalized_at = $3
+            WHERE id = $4 AND merchant_id = $5 AND status = 'draft'
+            RETURNING *
+            "#,
+            QuoteStatus::Open as QuoteStatus,
+            token,
+            Utc::now(),
+            quote_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Only draft quotes can be finalized".into()))?;
+        
+        info!("Quote finalized: {}", quote.id);
+        
+        let line_items = self.line_items(pool, quote.id).await?;
+        Ok(to_response(quote, line_items))
+    }
+    
+    pub async fn cancel_quote(&self, quote_id: Uuid, api_key: &str) -> Result<QuoteResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"
+            UPDATE quotes
+            SET status = $1, canceled_at = $2, acceptance_token = NULL
+            WHERE id = $3 AND merchant_id = $4 AND status IN ('draft', 'open')
+            RETURNING *
+            "#,
+            QuoteStatus::Canceled as QuoteStatus,
+            Utc::now(),
+            quote_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Only draft or open quotes can be canceled".into()))?;
+        
+        let line_items = self.line_items(pool, quote.id).await?;
+        Ok(to_response(quote, line_items))
+    }
+    
+    pub async fn get_hosted_quote(&self, quote_id: Uuid, token: &str) -> Result<QuoteResponse, DefiantError> {
+        let pool = self.db.pool_for_row("quotes", quote_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Quote not found".into()))?;
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"SELECT * FROM quotes WHERE id = $1 AND acceptance_token = $2"#,
+            quote_id,
+            token,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Quote not found".into()))?;
+        
+        let line_items = self.line_items(pool, quote.id).await?;
+        Ok(to_response(quote, line_items))
+    }
+    
+    /// Accepts an open quote from its hosted link and converts it, in one
+    /// transaction, into either a finalized invoice or a subscription.
+    pub async fn accept_quote(&self, quote_id: Uuid, token: &str) -> Result<QuoteResponse, DefiantError> {
+        let pool = self.db.pool_for_row("quotes", quote_id).await?
+            .ok_or_else(|| DefiantError::NotFound("Quote not found".into()))?;
+        let mut tx = pool.begin().await?;
+        
+        let quote = sqlx::query_as!(
+            Quote,
+            r#"SELECT * FROM quotes WHERE id = $1 AND acceptance_token = $2 FOR UPDATE"#,
+            quote_id,
+            token,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Quote not found".into()))?;
+        
+        if !matches!(quote.status, QuoteStatus::Open) {
+            return Err(DefiantError::Conflict("Quote is not open for acceptance".into()));
+        }
+        
+        let now = Utc::now();
+        if quote.expires_at.map(|expires_at| expires_at <= now).unwrap_or(false) {
+            return Err(DefiantError::Conflict("Quote has expired".into()));
+        }
+        
+        let line_items = sqlx::query_as!(
+            QuoteLineItem,
+            r#"SELECT * FROM quote_line_items WHERE quote_id = $1 ORDER BY created_at, id"#,
+            quote.id,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let accepted = match quote.plan_id {
+            Some(plan_id) => {
+                let plan = self.load_plan(plan_id, quote.merchant_id, &mut tx).await?;
+                let (status, trial_end) = if plan.trial_period_days > 0 {
+                    (SubscriptionStatus::Trialing, Some(now + Duration::days(plan.trial_period_days as i64)))
+                } else {
+                    (SubscriptionStatus::Active, None)
+                };
+                let period_end = trial_end.unwrap_or_else(|| plan.period_end(now));
+                
+                let subscription_id = sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO subscriptions (
+                        merchant_id, customer_id, status, plan_id,
+                        current_period_start, current_period_end, trial_start, trial_end, metadata, livemode
+                    )
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                    RETURNING id
+                    "#,
+                    quote.merchant_id,
+                    quote.customer_id,
+                    status as SubscriptionStatus,
+                    plan.id,
+                    now,
+                    period_end,
+                    trial_end.map(|_| now),
+                    trial_end,
+                    serde_json::json!({ "quote_id": quote.id }),
+                    quote.livemode,
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                
+                sqlx::query_as!(
+                    Quote,
+                    r#"
+                    UPDATE quotes
+                    SET status = $1, accepted_at = $2, subscription_id = $3
+                    WHERE id = $4
+                    RETURNING *
+                    "#,
+                    QuoteStatus::Accepted as QuoteStatus,
+                    now,
+                    subscription_id,
+                    quote.id,
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+            None => {
+                let lines: Vec<InvoiceDraftLine> = line_items
+                    .iter()
+                    .map(|item| InvoiceDraftLine {
+                        description: item.description.clone(),
+                        quantity: item.quantity,
+                        unit_amount: item.unit_amount,
+                    })
+                    .collect();
+                
+                let (invoice, _) = invoice_service::insert_open_invoice(
+                    &mut tx,
+                    quote.merchant_id,
+                    quote.customer_id,
+                    &quote.currency,
+                    quote.description.clone(),
+                    Some(serde_json::json!({ "quote_id": quote.id })),
+                    &lines,
+                )
+                .await?;
+                
+                sqlx::query_as!(
+                    Quote,
+                    r#"
+                    UPDATE quotes
+                    SET status = $1, accepted_at = $2, invoice_id = $3
+                    WHERE id = $4
+                    RETURNING *
+                    "#,
+                    QuoteStatus::Invoiced as QuoteStatus,
+                    now,
+                    invoice.id,
+                    quote.id,
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+        
+        tx.commit().await?;
+        
+        info!("Quote {} accepted", accepted.id);
+        Ok(to_response(accepted, line_items))
+    }
+    
+    async fn load_plan(
+        &self,
+        plan_id: Uuid,
+        merchant_id: Uuid,
+        tx: &mut Transaction<'_, Postgres>,
+    ) -> Result<Plan, DefiantError> {
+        let plan = sqlx::query_as!(
+            Plan,
+            r#"SELECT * FROM plans WHERE id = $1 AND merchant_id = $2"#,
+            plan_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Plan not found".into()))?;
+        
+        if !plan.active {
+            return Err(DefiantError::Conflict("Plan is no longer active".into()));
+        }
+        
+        Ok(plan)
+    }
+    
+    async fn line_items(&self, pool: &PgPool, quote_id: Uuid) -> Result<Vec<QuoteLineItem>, DefiantError> {
+        let items = sqlx::query_as!(
+            QuoteLineItem,
+            r#"SELECT * FROM quote_line_items WHERE quote_id = $1 ORDER BY created_at, id"#,
+            quote_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(items)
+    }
+}
+
+fn to_response(quote: Quote, line_items: Vec<QuoteLineItem>) -> QuoteResponse {
+    let hosted_url = match (&quote.status, &quote.acceptance_token) {
+        (QuoteStatus::Open, Some(token)) => Some(format!("/api/v1/hosted/quotes/{}?token={}", quote.id, token)),
+        _ => None,
+    };
+    
+    QuoteResponse { quote, line_items, hosted_url }
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{Receipt, ShippingDetails, CommunicationKind, PaymentLineItem, Pii, SealedPii}, errors::DefiantError, db::Database, config::Config, pii};
+use super::email_service::{EmailService, CustomerEmail};
+
+pub struct ReceiptService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl ReceiptService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn build_receipt(&self, payment_id: Uuid, merchant_id: Uuid) -> Result<(Receipt, Uuid), DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let row = sqlx::query!(
+            r#"
+            SELECT p.id, p.amount, p.currency, p.shipping_amount, p.shipping,
+                p.description, p.created_at AS "created_at!", p.customer_id,
+                c.email AS "customer_email?: SealedPii", m.name AS merchant_name
+            FROM payments p
+            JOIN merchants m ON m.id = p.merchant_id
+            LEFT JOIN customers c ON c.id = p.customer_id
+            WHERE p.id = $1 AND p.merchant_id = $2
+            "#,
+            payment_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Payment not found".into()))?;
+        
+        let customer_id = row.customer_id
+            .ok_or_else(|| DefiantError::BadRequest("Payment has no customer to send a receipt to".into()))?;
+        let customer_email = row.customer_email
+            .map(|email| email.open(&pii::context("customers.email", customer_id)))
+            .transpose()?
+            .map(Pii::into_string)
+            .ok_or_else(|| DefiantError::BadRequest("Customer has no email address".into()))?;
+        
+        let line_items = sqlx::query_as!(
+            PaymentLineItem,
+            r#"SELECT * FROM payment_line_items WHERE payment_id = $1 ORDER BY created_at, id"#,
+            payment_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let receipt = Receipt {
+            payment_id: row.id,
+            merchant_name: row.merchant_name,
+            customer_email,
+            currency: row.currency,
+            line_items,
+            subtotal: row.amount - row.shipping_amount,
+            shipping: ShippingDetails::from_value(row.shipping.as_ref()),
+            total: row.amount,
+            description: row.description,
+            created_at: row.created_at,
+        };
+        
+        Ok((receipt, customer_id))
+    }
+    
+    pub async fn send_receipt(&self, payment_id: Uuid, api_key: &str) -> Result<Receipt, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        self.deliver(payment_id, merchant_id).await
+    }
+    
+    /// Emails the receipt for a payment the platform completed itself, such as one
+    /// paying an invoice from its hosted page.
+    pub async fn deliver(&self, payment_id: Uuid, merchant_id: Uuid) -> Result<Receipt, DefiantError> {
+        let (receipt, customer_id) = self.build_receipt(payment_id, merchant_id).await?;
+        
+        EmailService::new(self.db.clone(), self.config.clone())
+            .send_to_customer(CustomerEmail {
+                merchant_id,
+                customer_id,
+                kind: CommunicationKind::Receipt,
+                to: receipt.customer_email.clone(),
+                subject: receipt.subject(),
+                html_body: receipt.render_html(),
+                related_object_type: Some("payment".into()),
+                related_object_id: Some(payment_id),
+            })
+            .await?;
+        
+        info!("Receipt sent for payment {}", payment_id);
+        Ok(receipt)
+    }
+}
@@ -0,0 +1,270 @@
+use std::sync::Arc;
+use chrono::{NaiveDate, Utc};
+use uuid::Uuid;
+
+use crate::{models::{ArAgingReport, ArAgingRow, ArAgingTotals, PaymentExportRow, PayoutReconciliationRow, RevenueSummary, LedgerAccountCode, Pii, SealedPii}, errors::DefiantError, db::Database, pii};
+
+pub struct ReportService {
+    db: Arc<Database>,
+}
+
+impl ReportService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Buckets open invoice balances by days past due (or since issue when no
+    /// due date is set). Invoices not yet due count as 0–30.
+    pub async fn ar_aging(
+        &self,
+        as_of: Option<NaiveDate>,
+        api_key: &str,
+    ) -> Result<ArAgingReport, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        let as_of = as_of.unwrap_or_else(|| Utc::now().date_naive());
+        
+        // The customer-level and currency-level groupings come back in one
+        // pass; rows with a NULL customer are the per-currency totals.
+        let rows = sqlx::query!(
+            r#"
+            WITH aged AS (
+                SELECT
+                    i.customer_id,
+                    i.currency,
+                    i.amount_remaining,
+                    GREATEST($2::date - COALESCE(i.due_date, i.created_at)::date, 0) AS age
+                FROM invoices i
+                WHERE i.merchant_id = $1
+                AND i.status = 'open'
+                AND i.amount_remaining > 0
+                AND i.created_at::date <= $2::date
+            )
+            SELECT
+                a.customer_id,
+                c.email AS "customer_email?: SealedPii",
+                c.name AS customer_name,
+                a.currency AS "currency!",
+                COALESCE(SUM(a.amount_remaining) FILTER (WHERE a.age <= 30), 0)::BIGINT AS "days_0_30!",
+                COALESCE(SUM(a.amount_remaining) FILTER (WHERE a.age BETWEEN 31 AND 60), 0)::BIGINT AS "days_31_60!",
+                COALESCE(SUM(a.amount_remaining) FILTER (WHERE a.age BETWEEN 61 AND 90), 0)::BIGINT AS "days_61_90!",
+                COALESCE(SUM(a.amount_remaining) FILTER (WHERE a.age > 90), 0)::BIGINT AS "days_over_90!",
+                COALESCE(SUM(a.amount_remaining), 0)::BIGINT AS "total!"
+            FROM aged a
+            JOIN customers c ON c.id = a.customer_id
+            GROUP BY GROUPING SETS ((a.currency, a.customer_id, c.email, c.name), (a.currency))
+            ORDER BY a.currency, "total!" DESC
+            "#,
+            merchant_id,
+            as_of,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let mut customers = Vec::new();
+        let mut totals = Vec::new();
+        
+        for row in rows {
+            match row.customer_id {
+                Some(customer_id) => customers.push(ArAgingRow {
+                    customer_id,
+                    customer_email: row.customer_email
+                        .map(|email| email.open(&pii::context("customers.email", customer_id)))
+                        .transpose()?
+                        .map(Pii::into_string)
+                        .unwrap_or_default(),
+                    customer_name: row.customer_name,
+                    currency: row.currency,
+                    days_0_30: row.days_0_30,
+                    days_31_60: row.days_31_60,
+                    days_61_90: row.days_61_90,
+                    days_over_90: row.days_over_90,
+                    total: row.total,
+                }),
+                None => totals.push(ArAgingTotals {
+                    currency: row.currency,
+                    days_0_30: row.days_0_30,
+                    days_31_60: row.days_31_60,
+                    days_61_90: row.days_61_90,
+                    days_over_90: row.days_over_90,
+                    total: row.total,
+                }),
+            }
+        }
+        
+        Ok(ArAgingReport { as_of, customers, totals })
+    }
+    
+    /// Payments created in `[from, to]` (inclusive dates), newest first.
+    pub async fn payments_export(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        api_key: &str,
+    ) -> Result<Vec<PaymentExportRow>, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        let rows = sqlx::query!(
+            r#"
+            SELECT
+                p.id,
+                p.created_at AS "created_at!",
+                p.status::text AS "status!",
+                p.payment_method::text AS "payment_method!",
+                p.currency,
+                p.amount,
+                p.shipping_amount,
+                c.id AS "customer_id?",
+                c.email AS "customer_email?: SealedPii",
+                p.description,
+                p.shipping->>'display_name' AS shipping_option,
+                p.shipping->'address'->>'country' AS shipping_country,
+                p.is_donation,
+                (
+                    SELECT string_agg(li.quantity || ' x ' || li.name, '; ' ORDER BY li.created_at)
+                    FROM payment_line_items li
+                    WHERE li.payment_id = p.id
+                ) AS line_items,
+                p.risk_score,
+                NULLIF(array_to_string(p.risk_reasons, '; '), '') AS risk_reasons
+            FROM payments p
+            LEFT JOIN customers c ON c.id = p.customer_id
+            WHERE p.merchant_id = $1
+            AND ($2::date IS NULL OR p.created_at::date >= $2)
+            AND ($3::date IS NULL OR p.created_at::date <= $3)
+            ORDER BY p.created_at DESC
+            "#,
+            merchant_id,
+            from,
+            to,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        rows.into_iter()
+            .map(|row| -> Result<PaymentExportRow, DefiantError> {
+                let customer_email = match (row.customer_id, row.customer_email) {
+                    (Some(customer_id), Some(email)) => Some(email.open(&pii::context("customers.email", customer_id))?),
+                    _ => None,
+                };
+                Ok(PaymentExportRow {
+                    id: row.id,
+                    created_at: row.created_at,
+                    status: row.status,
+                    payment_method: row.payment_method,
+                    currency: row.currency,
+                    amount: row.amount,
+                    shipping_amount: row.shipping_amount,
+                    customer_email,
+                    description: row.description,
+                    shipping_option: row.shipping_option,
+                    shipping_country: row.shipping_country,
+                    is_donation: row.is_donation,
+                    line_items: row.line_items,
+                    risk_score: row.risk_score,
+                    risk_reasons: row.risk_reasons,
+                })
+            })
+            .collect()
+    }
+    
+    /// Breaks down each payout created in the range into the sources it paid out, so
+    /// a bank deposit can be matched to the charges, refunds and fees behind it. A
+    /// charge's processing fee is reported on the charge's row.
+    pub async fn payout_reconciliation(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        payout_id: Option<Uuid>,
+        api_key: &str,
+    ) -> Result<Vec<PayoutReconciliationRow>, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        let rows = sqlx::query_as!(
+            PayoutReconciliationRow,
+            r#"
+            SELECT
+                p.id AS payout_id,
+                p.created_at AS "payout_created_at!",
+                p.arrival_date,
+                p.amount AS payout_amount,
+                p.currency,
+                CASE e.kind
+                    WHEN 'payment' THEN 'charge'
+                    WHEN 'fee' THEN 'charge'
+                    WHEN 'refund' THEN 'refund'
+                    WHEN 'transfer' THEN 'transfer'
+                    WHEN 'transfer_reversal' THEN 'transfer'
+                    WHEN 'conversion' THEN 'conversion'
+                    WHEN 'reserve_hold' THEN 'reserve'
+                    WHEN 'reserve_release' THEN 'reserve'
+                    ELSE 'adjustment'
+                END AS "transaction_type!",
+                COALESCE(e.metadata->>'transfer_id', e.metadata->>'payment_id', e.metadata->>'balance_conversion_id', e.metadata->>'topup_id')::UUID AS source_id,
+                SUM(CASE WHEN e.kind = 'fee' THEN 0 WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END)::BIGINT AS "gross!",
+                SUM(CASE WHEN e.kind <> 'fee' THEN 0 WHEN l.direction = 'debit' THEN l.amount ELSE -l.amount END)::BIGINT AS "fee!",
+                SUM(CASE WHEN l.direction = 'credit' THEN l.amount ELSE -l.amount END)::BIGINT AS "net!",
+                MAX(e.available_on) AS "available_on!"
+            FROM payouts p
+            JOIN payout_journal_entries pe ON pe.payout_id = p.id
+            JOIN journal_entries e ON e.id = pe.journal_entry_id
+            JOIN journal_lines l ON l.journal_entry_id = e.id
+            JOIN ledger_accounts a ON a.id = l.account_id AND a.code = $2
+            WHERE p.merchant_id = $1
+            AND ($3::UUID IS NULL OR p.id = $3)
+            AND ($4::date IS NULL OR p.created_at::date >= $4)
+            AND ($5::date IS NULL OR p.created_at::date <= $5)
+            GROUP BY p.id, 6, 7
+            ORDER BY p.created_at DESC, p.id, MIN(e.created_at)
+            "#,
+            merchant_id,
+            LedgerAccountCode::MerchantBalance.as_str(),
+            payout_id,
+            from,
+            to,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(rows)
+    }
+    
+    pub async fn revenue(
+        &self,
+        from: Option<NaiveDate>,
+        to: Option<NaiveDate>,
+        api_key: &str,
+    ) -> Result<Vec<RevenueSummary>, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        let summaries = sqlx::query_as!(
+            RevenueSummary,
+            r#"
+            SELECT
+                i.currency AS "currency!",
+                COUNT(*) FILTER (WHERE i.status IN ('open', 'paid')) AS "invoice_count!",
+                COALESCE(SUM(i.amount_due) FILTER (WHERE i.status IN ('open', 'paid')), 0)::BIGINT AS "revenue!",
+                COALESCE(SUM(i.amount_paid) FILTER (WHERE i.status IN ('open', 'paid')), 0)::BIGINT AS "collected!",
+                COUNT(*) FILTER (WHERE i.status = 'uncollectible') AS "uncollectible_count!",
+                COALESCE(SUM(i.amount_remaining) FILTER (WHERE i.status = 'uncollectible'), 0)::BIGINT AS "written_off!"
+            FROM invoices i
+            WHERE i.merchant_id = $1
+            AND i.status IN ('open', 'paid', 'uncollectible')
+            AND ($2::date IS NULL OR i.created_at::date >= $2)
+            AND ($3::date IS NULL OR i.created_at::date <= $3)
+            GROUP BY i.currency
+            ORDER BY i.currency
+            "#,
+            merchant_id,
+            from,
+            to,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(summaries)
+    }
+}
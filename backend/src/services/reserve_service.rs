@@ -0,0 +1,197 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::{models::{Payment, RollingReserve, UpdateRollingReserveRequest, ReserveHold}, errors::DefiantError, db::Database};
+use crate::shutdown::Shutdown;
+use super::ledger;
+
+const RESERVE_RELEASE_INTERVAL_SECS: u64 = 3600;
+const RESERVE_RELEASE_BATCH_SIZE: i64 = 100;
+
+/// Holds back the merchant's rolling reserve share of a card payment, inside the
+/// transaction that books the payment. `available_on` is when the payment's funds
+/// become available; the hold is released `hold_days` after that.
+pub async fn hold_payment_funds(
+    tx: &mut Transaction<'_, Postgres>,
+    payment: &Payment,
+    available_on: Option<DateTime<Utc>>,
+) -> Result<(), DefiantError> {
+    let reserve = sqlx::query_as!(
+        RollingReserve,
+        r#"SELECT * FROM rolling_reserves WHERE merchant_id = $1"#,
+        payment.merchant_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    let reserve = match reserve {
+        Some(reserve) => reserve,
+        None => return Ok(()),
+    };
+    
+    let amount = payment.amount * reserve.percent_bps as i64 / 10_000;
+    if amount == 0 {
+        return Ok(());
+    }
+    let release_on = available_on.unwrap_or_else(Utc::now) + Duration::days(reserve.hold_days as i64);
+    
+    let hold = sqlx::query_as!(
+        ReserveHold,
+        r#"
+        INSERT INTO reserve_holds (merchant_id, payment_id, currency, amount, release_on)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (payment_id) DO NOTHING
+        RETURNING *
+        "#,
+        payment.merchant_id,
+        payment.id,
+        payment.currency.to_uppercase(),
+        amount,
+        release_on,
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+    
+    if let Some(hold) = hold {
+        ledger::record_reserve_hold(tx, &hold, available_on).await?;
+    }
+    
+    Ok(())
+}
+
+/// Rolling reserve configuration, set by admins per merchant.
+pub struct ReserveService {
+    db: Arc<Database>,
+}
+
+impl ReserveService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn get_reserve(&self, merchant_id: Uuid) -> Result<Option<RollingReserve>, DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let reserve = sqlx::query_as!(
+            RollingReserve,
+            r#"SELECT * FROM rolling_reserves WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?;
+        
+        Ok(reserve)
+    }
+    
+    /// Applies to payments booked from now on; funds already held keep their release
+    /// date.
+    pub async fn update_reserve(
+        &self,
+        merchant_id: Uuid,
+        request: UpdateRollingReserveRequest,
+    ) -> Result<RollingReserve, DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let reserve = sqlx::query_as!(
+            RollingReserve,
+            r#"
+            INSERT INTO rolling_reserves (merchant_id, percent_bps, hold_days)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (merchant_id) DO UPDATE
+            SET percent_bps = EXCLUDED.percent_bps,
+                hold_days = EXCLUDED.hold_days
+            RETURNING *
+            "#,
+            merchant_id,
+            request.percent_bps,
+            request.hold_days,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Merchant {} now holds {} bps for {} days", merchant_id, reserve.percent_bps, reserve.hold_days);
+        
+        Ok(reserve)
+    }
+    
+    /// Stops holding back new payments. Funds already held are released on schedule.
+    pub async fn remove_reserve(&self, merchant_id: Uuid) -> Result<(), DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query!(
+            r#"DELETE FROM rolling_reserves WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .execute(pool)
+        .await?;
+        
+        info!("Rolling reserve removed for merchant {}", merchant_id);
+        
+        Ok(())
+    }
+    
+    /// Returns every hold whose period is over to its merchant's balance. Each hold is
+    /// released in its own transaction; holds another worker is releasing are skipped.
+    pub async fn release_due_holds(&self, pool: &PgPool) -> Result<usize, DefiantError> {
+        let mut released = 0;
+        
+        loop {
+            let mut tx = pool.begin().await?;
+            
+            let holds = sqlx::query_as!(
+                ReserveHold,
+                r#"
+                UPDATE reserve_holds SET released_at = NOW()
+                WHERE id IN (
+                    SELECT id FROM reserve_holds
+                    WHERE released_at IS NULL AND release_on <= NOW()
+                    ORDER BY release_on
+                    LIMIT $1
+                    FOR UPDATE SKIP LOCKED
+                )
+                RETURNING *
+                "#,
+                RESERVE_RELEASE_BATCH_SIZE,
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            
+            for hold in &holds {
+                ledger::record_reserve_release(&mut tx, hold).await?;
+            }
+            
+            tx.commit().await?;
+            
+            released += holds.len();
+            if (holds.len() as i64) < RESERVE_RELEASE_BATCH_SIZE {
+                break;
+            }
+        }
+        
+        if released > 0 {
+            info!("Released {} rolling reserve holds", released);
+        }
+        
+        Ok(released)
+    }
+}
+
+/// Periodically releases reserve holds whose period is over.
+pub fn spawn_reserve_releaser(db: Arc<Database>, shutdown: &Shutdown) {
+    shutdown.spawn(|mut shutdown| async move {
+        let service = ReserveService::new(db.clone());
+        let mut interval = tokio::time::interval(StdDuration::from_secs(RESERVE_RELEASE_INTERVAL_SECS));
+        
+        while shutdown.tick(&mut interval).await {
+            for pool in db.pools() {
+                if let Err(e) = service.release_due_holds(pool).await {
+                    error!("Failed to release reserve holds: {}", e);
+                }
+            }
+        }
+    });
+}
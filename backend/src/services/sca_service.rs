@@ -0,0 +1,377 @@
+use std::sync::Arc;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use chrono::Utc;
+use ring::hmac;
+use tracing::{info, warn};
+
+use crate::{models::{CardDetails, Payment, ScaDecision, ScaExemption, ScaStatus, RiskSettings, UpdateRiskSettingsRequest, ConfigurationChangeData, RISK_SETTINGS_UPDATED}, errors::DefiantError, db::Database, config::Config};
+use super::fx_service::{self, FxService};
+use super::webhook_service::WebhookService;
+
+/// EEA member states plus the UK; SCA applies when the card is issued in one of these.
+pub(crate) const SCA_COUNTRIES: &[&str] = &[
+    "AT", "BE", "BG", "HR", "CY", "CZ", "DK", "EE", "FI", "FR", "DE", "GR", "HU", "IE",
+    "IT", "LV", "LT", "LU", "MT", "NL", "PL", "PT", "RO", "SK", "SI", "ES", "SE",
+    "IS", "LI", "NO", "GB",
+];
+
+/// Low-value exemption: under EUR 30, at most 5 in a row or EUR 100 cumulative per card.
+const LOW_VALUE_LIMIT_EUR: i64 = 30_00;
+const LOW_VALUE_MAX_COUNT: i32 = 5;
+const LOW_VALUE_MAX_CUMULATIVE_EUR: i64 = 100_00;
+
+/// TRA thresholds (RTS art. 18): fraud rate per 100,000 of volume -> max amount in euro cents.
+const TRA_THRESHOLDS: &[(i64, i64)] = &[(10, 500_00), (60, 250_00), (130, 100_00)];
+const TRA_LOOKBACK_DAYS: i32 = 90;
+
+/// Risk score from which card payments are challenged, for merchants who never chose one.
+const DEFAULT_CHALLENGE_SCORE: i16 = 50;
+
+pub struct ScaService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl ScaService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Decides whether an authorization can skip 3DS, preferring the exemption that
+    /// is least likely to be refused: merchant-initiated, then TRA, then low-value.
+    ///
+    /// A payment whose risk score reaches the merchant's `challenge_score` is sent
+    /// through 3DS even outside SCA scope: it loses the TRA exemption and keeps the
+    /// low-value one only if the merchant allows it. So is one the fraud checks
+    /// `require_three_ds` for, having reached the merchant's 3DS amount threshold.
+    pub async fn evaluate(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        payment: &Payment,
+        card: Option<&CardDetails>,
+        off_session: bool,
+        require_three_ds: bool,
+    ) -> Result<ScaDecision, DefiantError> {
+        let card = match card {
+            Some(card) => card,
+            None => return Ok(ScaDecision::not_required()),
+        };
+        let settings = match payment.risk_score {
+            Some(_) => Some(self.risk_settings(tx, payment.merchant_id).await?),
+            None => None,
+        };
+        let risky = require_three_ds || match (payment.risk_score, &settings) {
+            (Some(score), Some(settings)) => score >= settings.challenge_score,
+            _ => false,
+        };
+        if !risky && !in_sca_scope(card) {
+            return Ok(ScaDecision::not_required());
+        }
+        
+        let fingerprint = card_fingerprint(&self.config.card_fingerprint_key, &card.number);
+        let amount_eur = match self.to_eur(payment.amount, &payment.currency).await {
+            Ok(amount_eur) => amount_eur,
+            Err(e) => {
+                // Without a rate there is no way to prove the amount is under a threshold
+                warn!("No EUR rate for SCA evaluation ({}), requiring challenge", e);
+                return Ok(challenge(0, fingerprint));
+            }
+        };
+        
+        let low_value_allowed = !risky || settings.as_ref().map(|s| s.low_value_exemption).unwrap_or(true);
+        let exemption = if off_session {
+            // Nobody is present to complete a challenge, however risky
+            Some(ScaExemption::MerchantInitiated)
+        } else if !risky && amount_eur <= self.tra_threshold(tx, payment.merchant_id).await? {
+            Some(ScaExemption::TransactionRiskAnalysis)
+        } else if low_value_allowed && self.low_value_available(tx, payment.merchant_id, &fingerprint, amount_eur).await? {
+            Some(ScaExemption::LowValue)
+        } else {
+            None
+        };
+        
+        Ok(match exemption {
+            Some(exemption) => ScaDecision {
+                status: ScaStatus::Exempted,
+                exemption: Some(exemption),
+                amount_eur,
+                card_fingerprint: Some(fingerprint),
+            },
+            None => {
+                if require_three_ds {
+                    info!("Payment {} reached the 3DS amount threshold, requesting 3DS", payment.id);
+                } else if risky {
+                    info!("Payment {} scored {:?}, requesting 3DS", payment.id, payment.risk_score);
+                }
+                challenge(amount_eur, fingerprint)
+            }
+        })
+    }
+    
+    pub async fn get_risk_settings(&self, api_key: &str) -> Result<RiskSettings, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        let settings = self.risk_settings(&mut tx, merchant_id).await?;
+        tx.commit().await?;
+        
+        Ok(settings)
+    }
+    
+    pub async fn update_risk_settings(
+        &self,
+        request: UpdateRiskSettingsRequest,
+        api_key: &str,
+    ) -> Result<RiskSettings, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        if !(0..=100).contains(&request.challenge_score) {
+            return Err(DefiantError::ValidationError("challenge_score must be between 0 and 100".into()));
+        }
+        if let Some(block_score) = request.block_score {
+            if block_score <= request.challenge_score || block_score > 100 {
+                return Err(DefiantError::ValidationError(
+                    "block_score must be above challenge_score and at most 100".into(),
+                ));
+            }
+        }
+        
+        let settings = sqlx::query_as!(
+            RiskSettings,
+            r#"
+            INSERT INTO risk_settings (merchant_id, challenge_score, block_score, low_value_exemption)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (merchant_id) DO UPDATE
+            SET challenge_score = EXCLUDED.challenge_score,
+                block_score = EXCLUDED.block_score,
+                low_value_exemption = EXCLUDED.low_value_exemption
+            RETURNING *
+            "#,
+            merchant_id,
+            request.challenge_score,
+            request.block_score,
+            request.low_value_exemption.unwrap_or(true),
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!(
+            "Merchant {} now challenges at risk score {} and blocks at {:?}",
+            merchant_id, settings.challenge_score, settings.block_score,
+        );
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                RISK_SETTINGS_UPDATED,
+                ConfigurationChangeData::new(
+                    "risk_settings",
+                    merchant_id,
+                    vec!["challenge_score".into(), "block_score".into(), "low_value_exemption".into()],
+                ),
+                api_key,
+            )
+            .await;
+        
+        Ok(settings)
+    }
+    
+    /// The merchant's settings, or the defaults when it has never changed them.
+    pub async fn risk_settings(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+    ) -> Result<RiskSettings, DefiantError> {
+        let settings = sqlx::query_as!(
+            RiskSettings,
+            r#"SELECT * FROM risk_settings WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        Ok(settings.unwrap_or(RiskSettings {
+            merchant_id,
+            challenge_score: DEFAULT_CHALLENGE_SCORE,
+            block_score: None,
+            low_value_exemption: true,
+            created_at: None,
+            updated_at: None,
+        }))
+    }
+    
+    /// Records how the issuer treated an exemption request and keeps the
+    /// low-value counters in step with it.
+    pub async fn record_exemption_outcome(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        payment_id: Uuid,
+        decision: &ScaDecision,
+        decline_code: Option<&str>,
+    ) -> Result<(), DefiantError> {
+        let exemption = match decision.exemption {
+            Some(exemption) => exemption,
+            None => return Ok(()),
+        };
+        let accepted = decline_code.is_none();
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO sca_exemption_attempts (merchant_id, payment_id, exemption, amount_eur, accepted, decline_code)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            merchant_id,
+            payment_id,
+            exemption as ScaExemption,
+            decision.amount_eur,
+            accepted,
+            decline_code,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        if let (ScaExemption::LowValue, true, Some(fingerprint)) = (exemption, accepted, &decision.card_fingerprint) {
+            sqlx::query!(
+                r#"
+                INSERT INTO sca_low_value_counters (merchant_id, card_fingerprint, transaction_count, cumulative_amount_eur)
+                VALUES ($1, $2, 1, $3)
+                ON CONFLICT (merchant_id, card_fingerprint) DO UPDATE
+                SET transaction_count = sca_low_value_counters.transaction_count + 1,
+                    cumulative_amount_eur = sca_low_value_counters.cumulative_amount_eur + EXCLUDED.cumulative_amount_eur
+                "#,
+                merchant_id,
+                fingerprint,
+                decision.amount_eur,
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+        
+        if !accepted {
+            info!("Issuer declined {:?} exemption for payment {}", exemption, payment_id);
+        }
+        
+        Ok(())
+    }
+    
+    /// A completed challenge restarts the card's low-value allowance.
+    pub async fn reset_low_value_counter(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        card_fingerprint: &str,
+    ) -> Result<(), DefiantError> {
+        sqlx::query!(
+            r#"DELETE FROM sca_low_value_counters WHERE merchant_id = $1 AND card_fingerprint = $2"#,
+            merchant_id,
+            card_fingerprint,
+        )
+        .execute(&mut **tx)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn to_eur(&self, amount: i64, currency: &str) -> Result<i64, DefiantError> {
+        if currency.eq_ignore_ascii_case("EUR") {
+            return Ok(amount);
+        }
+        
+        let (rate, _) = FxService::new(self.db.clone())
+            .get_rate(currency, "EUR", Utc::now().date_naive())
+            .await?;
+        fx_service::convert_amount(amount, currency, "EUR", rate)
+    }
+    
+    /// Highest amount the merchant may exempt under TRA given its recent fraud rate, or 0.
+    async fn tra_threshold(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+    ) -> Result<i64, DefiantError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                COALESCE(SUM(amount) FILTER (WHERE status = 'disputed'), 0)::BIGINT AS "fraud!",
+                COALESCE(SUM(amount) FILTER (
+                    WHERE status IN ('succeeded', 'disputed', 'refunded', 'partially_refunded')
+                ), 0)::BIGINT AS "volume!"
+            FROM payments
+            WHERE merchant_id = $1 AND created_at >= NOW() - make_interval(days => $2)
+            "#,
+            merchant_id,
+            TRA_LOOKBACK_DAYS,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        if row.volume == 0 {
+            return Ok(0);
+        }
+        
+        let rate_per_100k = (row.fraud as i128 * 100_000 / row.volume as i128) as i64;
+        Ok(TRA_THRESHOLDS
+            .iter()
+            .find(|(max_rate, _)| rate_per_100k <= *max_rate)
+            .map(|(_, threshold)| *threshold)
+            .unwrap_or(0))
+    }
+    
+    async fn low_value_available(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        card_fingerprint: &str,
+        amount_eur: i64,
+    ) -> Result<bool, DefiantError> {
+        if amount_eur > LOW_VALUE_LIMIT_EUR {
+            return Ok(false);
+        }
+        
+        let counter = sqlx::query!(
+            r#"
+            SELECT transaction_count, cumulative_amount_eur
+            FROM sca_low_value_counters
+            WHERE merchant_id = $1 AND card_fingerprint = $2
+            FOR UPDATE
+            "#,
+            merchant_id,
+            card_fingerprint,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        Ok(match counter {
+            Some(counter) => {
+                counter.transaction_count < LOW_VALUE_MAX_COUNT
+                    && counter.cumulative_amount_eur + amount_eur <= LOW_VALUE_MAX_CUMULATIVE_EUR
+            }
+            None => true,
+        })
+    }
+}
+
+fn in_sca_scope(card: &CardDetails) -> bool {
+    card.issuer_country
+        .as_deref()
+        .map(|country| SCA_COUNTRIES.iter().any(|c| c.eq_ignore_ascii_case(country)))
+        .unwrap_or(false)
+}
+
+/// Stable card identifier so counters never hold a PAN. Keyed, because the PAN
+/// space is small enough to brute-force a plain hash.
+pub fn card_fingerprint(key: &str, number: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key.as_bytes());
+    hex::encode(hmac::sign(&key, number.as_bytes()).as_ref())
+}
+
+fn challenge(amount_eur: i64, card_fingerprint: String) -> ScaDecision {
+    ScaDecision {
+        status: ScaStatus::ChallengeRequired,
+        exemption: None,
+        amount_eur,
+        card_fingerprint: Some(card_fingerprint),
+    }
+}
@@ -0,0 +1,108 @@
+use serde_json::{Map, Value};
+
+use crate::errors::DefiantError;
+
+const MAX_QUERY_LENGTH: usize = 1000;
+
+/// A parsed search query, ready to bind into a search statement.
+///
+/// Queries are whitespace-separated clauses. `metadata[key]:value` matches objects
+/// whose metadata has that value under that key; every other clause is searched for
+/// in the object's text, with the syntax of `websearch_to_tsquery`: quoted phrases,
+/// `or` and `-excluded` words. Keys and values may be quoted to hold spaces, as in
+/// `metadata['order ref']:'A 12'`. All clauses must match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilter {
+    /// Matched against the object's text index; unset when the query has only
+    /// metadata clauses
+    pub text: Option<String>,
+    /// Matched by containment against the object's metadata; unset when the query
+    /// has no metadata clauses
+    pub metadata: Option<Value>,
+}
+
+impl SearchFilter {
+    pub fn parse(query: &str) -> Result<Self, DefiantError> {
+        if query.len() > MAX_QUERY_LENGTH {
+            return Err(DefiantError::ValidationError(format!(
+                "Search queries are limited to {} characters",
+                MAX_QUERY_LENGTH,
+            )));
+        }
+        
+        let mut text = Vec::new();
+        let mut metadata = Map::new();
+        for clause in clauses(query)? {
+            match clause.strip_prefix("metadata[") {
+                Some(rest) => {
+                    let (key, value) = rest.split_once("]:").ok_or_else(|| {
+                        DefiantError::ValidationError(format!(
+                            "Invalid clause '{}'; metadata is searched as metadata[key]:value",
+                            clause,
+                        ))
+                    })?;
+                    let (key, value) = (unquote(key), unquote(value));
+                    if key.is_empty() {
+                        return Err(DefiantError::ValidationError("Metadata keys cannot be empty".into()));
+                    }
+                    metadata.insert(key.to_string(), Value::String(value.to_string()));
+                }
+                None => text.push(clause),
+            }
+        }
+        
+        if text.is_empty() && metadata.is_empty() {
+            return Err(DefiantError::ValidationError("Search query is empty".into()));
+        }
+        
+        Ok(Self {
+            text: (!text.is_empty()).then(|| text.join(" ")),
+            metadata: (!metadata.is_empty()).then(|| Value::Object(metadata)),
+        })
+    }
+}
+
+/// Splits on whitespace outside quotes, keeping the quotes for `unquote` or the text
+/// search to interpret.
+fn clauses(query: &str) -> Result<Vec<&str>, DefiantError> {
+    let mut clauses = Vec::new();
+    let mut start = None;
+    let mut quote = None;
+    
+    for (i, c) in query.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                start.get_or_insert(i);
+            }
+            (None, c) if c.is_whitespace() => {
+                if let Some(start) = start.take() {
+                    clauses.push(&query[start..i]);
+                }
+            }
+            (None, _) => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    
+    if quote.is_some() {
+        return Err(DefiantError::ValidationError("Search query has an unclosed quote".into()));
+    }
+    if let Some(start) = start {
+        clauses.push(&query[start..]);
+    }
+    
+    Ok(clauses)
+}
+
+fn unquote(s: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = s.strip_prefix(quote).and_then(|s| s.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    s
+}
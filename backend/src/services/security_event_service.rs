@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use tracing::{warn, error};
+
+use crate::{models::{SecurityEvent, SecurityEventType, NewSecurityEvent, SecurityEventListQuery, SecurityEventListResponse, SECURITY_EVENT_SUSPICIOUS}, errors::DefiantError, db::Database};
+use super::webhook_service::WebhookService;
+
+/// Failed sign-ins of one account within this window, this one included, that make
+/// the burst suspicious.
+const FAILED_LOGIN_WINDOW_MINUTES: i64 = 15;
+const FAILED_LOGIN_THRESHOLD: i64 = 5;
+/// A burst against the same account or key notifies endpoints at most this often.
+const NOTIFICATION_COOLDOWN_MINUTES: i64 = 60;
+
+/// The merchant's security log. Kept in the directory database, like the sessions
+/// and keys it describes.
+pub struct SecurityEventService {
+    db: Arc<Database>,
+}
+
+impl SecurityEventService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Appends to the log, notifying `security_event.suspicious` subscribers when the
+    /// event is suspicious. Whatever the event describes has already happened, so a
+    /// failure here is logged rather than returned.
+    pub(crate) async fn record(&self, event: NewSecurityEvent) {
+        let event_type = event.event_type;
+        let merchant_id = event.merchant_id;
+        if let Err(e) = self.try_record(event).await {
+            error!("Failed to record {:?} security event for merchant {}: {}", event_type, merchant_id, e);
+        }
+    }
+    
+    pub async fn list(
+        &self,
+        query: SecurityEventListQuery,
+        api_key: &str,
+    ) -> Result<SecurityEventListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            SecurityEvent,
+            r#"
+            SELECT id, merchant_id, event_type AS "event_type: SecurityEventType", subject, api_key_id,
+                ip_address, details, suspicious, created_at
+            FROM security_events
+            WHERE merchant_id = $1
+            AND ($2::security_event_type IS NULL OR event_type = $2)
+            AND ($3::TEXT IS NULL OR subject = $3)
+            AND ($4::UUID IS NULL OR api_key_id = $4)
+            AND ($5::BOOLEAN IS NULL OR suspicious = $5)
+            AND ($6::TIMESTAMPTZ IS NULL OR created_at >= $6)
+            AND ($7::TIMESTAMPTZ IS NULL OR created_at <= $7)
+            ORDER BY created_at DESC
+            LIMIT $8
+            "#,
+            merchant_id,
+            query.event_type as Option<SecurityEventType>,
+            query.subject,
+            query.api_key_id,
+            query.suspicious,
+            query.created_gte,
+            query.created_lte,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(SecurityEventListResponse {
+            data,
+            has_more,
+            url: "/api/v1/security/events".into(),
+        })
+    }
+    
+    async fn try_record(&self, event: NewSecurityEvent) -> Result<(), DefiantError> {
+        let suspicious = self.is_suspicious(&event).await?;
+        let notify = suspicious && !self.recently_notified(&event).await?;
+        
+        let recorded = sqlx::query_as!(
+            SecurityEvent,
+            r#"
+            INSERT INTO security_events (merchant_id, event_type, subject, api_key_id, ip_address, details, suspicious)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING id, merchant_id, event_type AS "event_type: SecurityEventType", subject, api_key_id,
+                ip_address, details, suspicious, created_at
+            "#,
+            event.merchant_id,
+            event.event_type as SecurityEventType,
+            event.subject,
+            event.api_key_id,
+            event.ip_address,
+            event.details,
+            suspicious,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        if notify {
+            warn!("Suspicious {:?} security event {} for merchant {}", recorded.event_type, recorded.id, recorded.merchant_id);
+            WebhookService::new(self.db.clone())
+                .enqueue_event(recorded.merchant_id, SECURITY_EVENT_SUSPICIOUS, serde_json::json!(recorded))
+                .await?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Refused requests always are; failed sign-ins once they come in a burst.
+    async fn is_suspicious(&self, event: &NewSecurityEvent) -> Result<bool, DefiantError> {
+        match (event.event_type, &event.subject) {
+            (SecurityEventType::IpNotAllowed, _) => Ok(true),
+            (SecurityEventType::LoginFailed, Some(subject)) => {
+                let earlier = sqlx::query_scalar!(
+                    r#"
+                    SELECT COUNT(*) AS "count!" FROM security_events
+                    WHERE subject = $1 AND event_type = 'login_failed'
+                    AND created_at > NOW() - make_interval(mins => $2)
+                    "#,
+                    subject,
+                    FAILED_LOGIN_WINDOW_MINUTES as i32,
+                )
+                .fetch_one(&self.db.pool)
+                .await?;
+                
+                Ok(earlier + 1 >= FAILED_LOGIN_THRESHOLD)
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    async fn recently_notified(&self, event: &NewSecurityEvent) -> Result<bool, DefiantError> {
+        let notified = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM security_events
+                WHERE merchant_id = $1 AND event_type = $2 AND suspicious = true
+                AND subject IS NOT DISTINCT FROM $3 AND api_key_id IS NOT DISTINCT FROM $4
+                AND created_at > NOW() - make_interval(mins => $5)
+            ) AS "notified!"
+            "#,
+            event.merchant_id,
+            event.event_type as SecurityEventType,
+            event.subject,
+            event.api_key_id,
+            NOTIFICATION_COOLDOWN_MINUTES as i32,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        Ok(notified)
+    }
+}
@@ -0,0 +1,788 @@
+use std::net::IpAddr;
+use std::sync::Arc;
+use actix_web::{web::Bytes, HttpRequest};
+use chrono::Utc;
+use ring::{hmac, signature::{UnparsedPublicKey, ED25519}};
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{TrustedSource, TrustedSourceKind, CreateTrustedSourceRequest, SecuritySettings, RateLimitTier, RateLimitPolicy, UpdateRateLimitTierRequest, ApiKeyIpViolation, ApiKeyIpViolationListQuery, ApiKeyIpViolationListResponse, RequestSigningSecret, ConfigurationChangeData, NewSecurityEvent, SecurityEventType, TRUSTED_SOURCE_CREATED, TRUSTED_SOURCE_DELETED, REQUEST_SIGNING_UPDATED}, errors::DefiantError, db::{Database, AuthenticatedKey, hash_api_key}, config::Config};
+use super::webhook_service::{WebhookService, SecretCipher};
+use super::account_service::is_secret_key;
+use super::security_event_service::SecurityEventService;
+
+pub const SERVICE_IDENTITY_HEADER: &str = "Defiant-Service-Identity";
+pub const SERVICE_SIGNATURE_HEADER: &str = "Defiant-Service-Signature";
+pub const REQUEST_SIGNATURE_HEADER: &str = "Defiant-Request-Signature";
+/// Signed requests older (or further in the future) than this are not trusted.
+const SIGNATURE_TOLERANCE_SECS: u64 = 300;
+const MAX_TRUSTED_SOURCES: i64 = 50;
+/// Tier changes clear the cached copy; the token cache only has to expire revoked keys.
+const TIER_CACHE_SECS: u64 = 300;
+const TOKEN_CACHE_SECS: u64 = 60;
+
+/// `Defiant-Request-Signature: t=<unix seconds>,n=<nonce>,v1=<hex>`, required on
+/// secret-key requests of merchants with request signing on.
+struct RequestSignature {
+    timestamp: i64,
+    nonce: String,
+    signature: Vec<u8>,
+}
+
+impl RequestSignature {
+    fn parse(header: &str) -> Result<Self, DefiantError> {
+        let invalid = || DefiantError::AuthenticationError("Malformed request signature".into());
+        
+        let mut timestamp = None;
+        let mut nonce = None;
+        let mut signature = None;
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                Some(("n", value)) if is_valid_nonce(value) => nonce = Some(value.to_string()),
+                Some(("v1", value)) => signature = hex::decode(value).ok(),
+                _ => {}
+            }
+        }
+        
+        Ok(Self {
+            timestamp: timestamp.ok_or_else(invalid)?,
+            nonce: nonce.ok_or_else(invalid)?,
+            signature: signature.ok_or_else(invalid)?,
+        })
+    }
+}
+
+/// What a request signature covers, read before the handler takes the body. The auth
+/// middleware leaves one on secret-key requests to the paths it does not
+/// authenticate, for the handler's own check.
+#[derive(Debug, Clone)]
+pub struct SignedRequest {
+    pub method: String,
+    pub path_and_query: String,
+    pub header: Option<String>,
+    pub body: Bytes,
+}
+
+impl SignedRequest {
+    pub fn capture(req: &HttpRequest, body: Bytes) -> Self {
+        Self {
+            method: req.method().as_str().to_string(),
+            path_and_query: req.uri().path_and_query().map(|p| p.as_str()).unwrap_or_else(|| req.path()).to_string(),
+            header: req.headers().get(REQUEST_SIGNATURE_HEADER).and_then(|h| h.to_str().ok()).map(str::to_owned),
+            body,
+        }
+    }
+}
+
+/// Whether a signed timestamp is recent enough to trust. Timestamps are the sender's,
+/// so the difference is taken without overflowing.
+fn within_tolerance(timestamp: i64) -> bool {
+    Utc::now().timestamp().abs_diff(timestamp) <= SIGNATURE_TOLERANCE_SECS
+}
+
+/// 16 to 64 URL-safe characters, e.g. a UUID or random hex.
+fn is_valid_nonce(nonce: &str) -> bool {
+    (16..=64).contains(&nonce.len())
+        && nonce.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// A request's claim to come from a registered service identity, from the
+/// `Defiant-Service-Identity` and `Defiant-Service-Signature: t=<unix seconds>,v1=<hex>`
+/// headers. The Ed25519 signature covers `"<t>.<METHOD>.<path and query>"`.
+#[derive(Debug, Clone)]
+pub struct ServiceSignature {
+    pub source_id: Uuid,
+    pub timestamp: i64,
+    pub signature: Vec<u8>,
+}
+
+impl ServiceSignature {
+    pub fn parse(identity: &str, header: &str) -> Result<Self, DefiantError> {
+        let invalid = || DefiantError::AuthenticationError("Malformed service signature".into());
+        
+        let source_id = Uuid::parse_str(identity.trim()).map_err(|_| invalid())?;
+        let mut timestamp = None;
+        let mut signature = None;
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+                Some(("v1", value)) => signature = hex::decode(value).ok(),
+                _ => {}
+            }
+        }
+        
+        Ok(Self {
+            source_id,
+            timestamp: timestamp.ok_or_else(invalid)?,
+            signature: signature.ok_or_else(invalid)?,
+        })
+    }
+    
+    fn verify(&self, public_key_hex: &str, method: &str, path_and_query: &str) -> bool {
+        if !within_tolerance(self.timestamp) {
+            return false;
+        }
+        let Ok(public_key) = hex::decode(public_key_hex) else {
+            return false;
+        };
+        
+        let message = format!("{}.{}.{}", self.timestamp, method, path_and_query);
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(message.as_bytes(), &self.signature)
+            .is_ok()
+    }
+}
+
+/// Security settings: which merchant backends are trusted, and what that buys them in
+/// the rate limiter, plus key IP allowlists and request signing.
+///
+/// Trusted sources live in the directory database alongside API keys, because the
+/// limiter classifies a request before it knows which region serves the merchant.
+pub struct SecurityService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl SecurityService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn settings(&self, api_key: &str) -> Result<SecuritySettings, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        
+        let trusted_sources = sqlx::query_as!(
+            TrustedSource,
+            r#"
+            SELECT * FROM merchant_trusted_sources
+            WHERE merchant_id = $1
+            ORDER BY created_at
+            "#,
+            merchant_id,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let merchant = sqlx::query!(
+            r#"
+            SELECT request_signing_secret IS NOT NULL AS "request_signing_enabled!",
+                rate_limit_tier AS "rate_limit_tier: RateLimitTier"
+            FROM merchants WHERE id = $1
+            "#,
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        let policy = self.policy_for(merchant_id, merchant.rate_limit_tier);
+        
+        Ok(SecuritySettings {
+            rate_limit_tier: policy.tier,
+            rate_limit_requests: policy.requests_per_period,
+            trusted_rate_limit_requests: policy.trusted_requests_per_period,
+            rate_limit_period_secs: policy.period_secs,
+            trusted_sources,
+            request_signing_enabled: merchant.request_signing_enabled,
+        })
+    }
+    
+    pub async fn rate_limit_policy(&self, merchant_id: Uuid) -> Result<RateLimitPolicy, DefiantError> {
+        let tier = sqlx::query_scalar!(
+            r#"SELECT rate_limit_tier AS "rate_limit_tier: RateLimitTier" FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        Ok(self.policy_for(merchant_id, tier))
+    }
+    
+    /// Moves a merchant to another tier. Its buckets keep their tokens and refill at
+    /// the new rate from the next request.
+    pub async fn update_rate_limit_tier(
+        &self,
+        redis: &redis::Client,
+        merchant_id: Uuid,
+        request: UpdateRateLimitTierRequest,
+    ) -> Result<RateLimitPolicy, DefiantError> {
+        let tier = sqlx::query_scalar!(
+            r#"
+            UPDATE merchants SET rate_limit_tier = $2 WHERE id = $1
+            RETURNING rate_limit_tier AS "rate_limit_tier: RateLimitTier"
+            "#,
+            merchant_id,
+            request.tier as RateLimitTier,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Merchant not found".into()))?;
+        
+        let cleared = match redis.get_async_connection().await {
+            Ok(mut conn) => redis::cmd("DEL").arg(tier_cache_key(merchant_id)).query_async::<_, ()>(&mut conn).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = cleared {
+            // The limiter keeps the old quotas until the cached copy expires
+            warn!("Failed to clear cached rate limit tier for merchant {}: {}", merchant_id, e);
+        }
+        
+        info!("Merchant {} moved to the {:?} rate limit tier", merchant_id, tier);
+        Ok(self.policy_for(merchant_id, tier))
+    }
+    
+    /// The key, session or app grant a bearer token belongs to, and its merchant's
+    /// quotas; `None` when the token does not authenticate. The limiter asks on every
+    /// request, so both answers are cached in Redis.
+    pub(crate) async fn rate_limit_holder(
+        &self,
+        redis: &redis::Client,
+        token: &str,
+    ) -> Result<Option<(Uuid, RateLimitPolicy)>, DefiantError> {
+        let mut conn = redis.get_async_connection().await
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let token_key = format!("rate_limit_token:{}", hash_api_key(token));
+        let cached: Option<String> = match redis::cmd("GET").arg(&token_key).query_async(&mut conn).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!("Failed to read cached rate limit holder: {}", e);
+                None
+            }
+        };
+        let holder = match cached {
+            // Tokens that did not authenticate are cached as an empty value
+            Some(cached) => cached.split_once(':').and_then(|(key_id, merchant_id)| {
+                Some((Uuid::parse_str(key_id).ok()?, Uuid::parse_str(merchant_id).ok()?))
+            }),
+            None => {
+                let holder = match self.db.authenticate_key(token).await {
+                    Ok(key) => Some((key.id, key.merchant_id)),
+                    Err(DefiantError::AuthenticationError(_)) => None,
+                    Err(e) => return Err(e),
+                };
+                let value = holder.map(|(key_id, merchant_id)| format!("{}:{}", key_id, merchant_id)).unwrap_or_default();
+                if let Err(e) = redis::cmd("SET").arg(&token_key).arg(value).arg("EX").arg(TOKEN_CACHE_SECS)
+                    .query_async::<_, ()>(&mut conn)
+                    .await
+                {
+                    warn!("Failed to cache rate limit holder: {}", e);
+                }
+                holder
+            }
+        };
+        let Some((key_id, merchant_id)) = holder else {
+            return Ok(None);
+        };
+        
+        let cached: Option<String> = match redis::cmd("GET").arg(tier_cache_key(merchant_id)).query_async(&mut conn).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!("Failed to read cached rate limit tier: {}", e);
+                None
+            }
+        };
+        let tier = match cached.and_then(|cached| serde_json::from_str::<RateLimitTier>(&cached).ok()) {
+            Some(tier) => tier,
+            None => {
+                let tier = self.rate_limit_policy(merchant_id).await?.tier;
+                if let Ok(cached) = serde_json::to_string(&tier) {
+                    if let Err(e) = redis::cmd("SET").arg(tier_cache_key(merchant_id)).arg(cached).arg("EX").arg(TIER_CACHE_SECS)
+                        .query_async::<_, ()>(&mut conn)
+                        .await
+                    {
+                        warn!("Failed to cache rate limit tier: {}", e);
+                    }
+                }
+                tier
+            }
+        };
+        
+        Ok(Some((key_id, self.policy_for(merchant_id, tier))))
+    }
+    
+    fn policy_for(&self, merchant_id: Uuid, tier: RateLimitTier) -> RateLimitPolicy {
+        RateLimitPolicy {
+            merchant_id,
+            tier,
+            requests_per_period: self.config.rate_limit_requests.saturating_mul(tier.multiplier()),
+            trusted_requests_per_period: self.config.trusted_rate_limit_requests.saturating_mul(tier.multiplier()),
+            period_secs: self.config.rate_limit_period,
+        }
+    }
+    
+    /// Turns request signing on, or rotates the secret when it already is. Requests
+    /// signed with the previous secret stop being accepted at once.
+    pub async fn enable_request_signing(&self, api_key: &str) -> Result<RequestSigningSecret, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let secret = format!("rss_{}", hex::encode(rand::random::<[u8; 32]>()));
+        
+        let enabled_at = sqlx::query_scalar!(
+            r#"
+            UPDATE merchants
+            SET request_signing_secret = $2, request_signing_enabled_at = NOW()
+            WHERE id = $1
+            RETURNING request_signing_enabled_at AS "enabled_at!"
+            "#,
+            merchant_id,
+            self.signing_cipher()?.seal(&secret)?,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("Request signing secret issued for merchant {}", merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                REQUEST_SIGNING_UPDATED,
+                ConfigurationChangeData::new("request_signing", merchant_id, vec!["secret".into()]),
+                api_key,
+            )
+            .await;
+        
+        Ok(RequestSigningSecret { secret, enabled_at })
+    }
+    
+    pub async fn disable_request_signing(&self, api_key: &str) -> Result<(), DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        
+        sqlx::query!(
+            r#"UPDATE merchants SET request_signing_secret = NULL, request_signing_enabled_at = NULL WHERE id = $1"#,
+            merchant_id,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        info!("Request signing turned off for merchant {}", merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                REQUEST_SIGNING_UPDATED,
+                ConfigurationChangeData::new("request_signing", merchant_id, vec!["enabled".into()]),
+                api_key,
+            )
+            .await;
+        
+        Ok(())
+    }
+    
+    /// Requires a valid signature on `token`'s request when it is a secret key of a
+    /// merchant with request signing on. A request whose body was not captured cannot
+    /// be checked, so it is refused.
+    pub(crate) async fn enforce_request_signing(
+        &self,
+        redis: &redis::Client,
+        token: &str,
+        merchant_id: Uuid,
+        request: Option<&SignedRequest>,
+    ) -> Result<(), DefiantError> {
+        if !is_secret_key(token) {
+            return Ok(());
+        }
+        let Some(sealed) = self.request_signing_secret(merchant_id).await? else {
+            return Ok(());
+        };
+        let request = request.ok_or_else(|| {
+            DefiantError::AuthenticationError(format!("This account requires a {} header", REQUEST_SIGNATURE_HEADER))
+        })?;
+        
+        self.verify_request_signature(
+            redis,
+            merchant_id,
+            &sealed,
+            request.header.as_deref(),
+            &request.method,
+            &request.path_and_query,
+            &request.body,
+        )
+        .await
+    }
+    
+    /// Checks the `Defiant-Request-Signature` of a secret-key request from a merchant
+    /// with signing on. Each nonce is remembered in Redis for as long as its timestamp
+    /// would be accepted, so a captured request cannot be replayed.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) async fn verify_request_signature(
+        &self,
+        redis: &redis::Client,
+        merchant_id: Uuid,
+        sealed_secret: &str,
+        header: Option<&str>,
+        method: &str,
+        path_and_query: &str,
+        body: &[u8],
+    ) -> Result<(), DefiantError> {
+        let header = header.ok_or_else(|| {
+            DefiantError::AuthenticationError(format!("This account requires a {} header", REQUEST_SIGNATURE_HEADER))
+        })?;
+        let signature = RequestSignature::parse(header)?;
+        
+        if !within_tolerance(signature.timestamp) {
+            return Err(DefiantError::AuthenticationError("Request signature timestamp is too old or too far in the future".into()));
+        }
+        
+        let secret = self.signing_cipher()?.open(sealed_secret)?;
+        let mut message = format!("{}.{}.{}.{}.", signature.timestamp, signature.nonce, method, path_and_query).into_bytes();
+        message.extend_from_slice(body);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        if hmac::verify(&key, &message, &signature.signature).is_err() {
+            warn!("Invalid request signature for merchant {}", merchant_id);
+            return Err(DefiantError::AuthenticationError("Invalid request signature".into()));
+        }
+        
+        let mut conn = redis.get_async_connection().await
+            .map_err(|_| DefiantError::InternalError)?;
+        let first_use: Option<String> = redis::cmd("SET")
+            .arg(format!("request_nonce:{}:{}", merchant_id, signature.nonce))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(2 * SIGNATURE_TOLERANCE_SECS)
+            .query_async(&mut conn)
+            .await
+            .map_err(|_| DefiantError::InternalError)?;
+        if first_use.is_none() {
+            warn!("Replayed request nonce for merchant {}", merchant_id);
+            return Err(DefiantError::AuthenticationError("Request nonce has already been used".into()));
+        }
+        
+        Ok(())
+    }
+    
+    /// The merchant's sealed signing secret; `None` while request signing is off.
+    pub(crate) async fn request_signing_secret(&self, merchant_id: Uuid) -> Result<Option<String>, DefiantError> {
+        let sealed = sqlx::query_scalar!(
+            r#"SELECT request_signing_secret FROM merchants WHERE id = $1"#,
+            merchant_id,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .flatten();
+        
+        Ok(sealed)
+    }
+    
+    fn signing_cipher(&self) -> Result<SecretCipher, DefiantError> {
+        SecretCipher::from_hex(&self.config.request_signing_encryption_key)
+    }
+    
+    pub async fn create_trusted_source(
+        &self,
+        request: CreateTrustedSourceRequest,
+        api_key: &str,
+    ) -> Result<TrustedSource, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        
+        let (ip_range, public_key) = match (request.kind, request.ip_range, request.public_key) {
+            (TrustedSourceKind::IpRange, Some(range), None) => (Some(normalize_ip_range("ip_range", &range)?), None),
+            (TrustedSourceKind::ServiceIdentity, None, Some(key)) => (None, Some(normalize_public_key(&key)?)),
+            (TrustedSourceKind::IpRange, _, _) => {
+                return Err(DefiantError::ValidationError("ip_range sources take an ip_range only".into()));
+            }
+            (TrustedSourceKind::ServiceIdentity, _, _) => {
+                return Err(DefiantError::ValidationError("service_identity sources take a public_key only".into()));
+            }
+        };
+        
+        let existing = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM merchant_trusted_sources WHERE merchant_id = $1"#,
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        if existing >= MAX_TRUSTED_SOURCES {
+            return Err(DefiantError::Conflict(format!("At most {} trusted sources can be registered", MAX_TRUSTED_SOURCES)));
+        }
+        
+        let source = sqlx::query_as!(
+            TrustedSource,
+            r#"
+            INSERT INTO merchant_trusted_sources (merchant_id, kind, name, ip_range, public_key)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.kind as TrustedSourceKind,
+            request.name,
+            ip_range,
+            public_key,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        info!("Trusted source {} registered for merchant {}", source.id, merchant_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                TRUSTED_SOURCE_CREATED,
+                ConfigurationChangeData::new("trusted_source", source.id, Vec::new()),
+                api_key,
+            )
+            .await;
+        
+        Ok(source)
+    }
+    
+    pub async fn delete_trusted_source(&self, source_id: Uuid, api_key: &str) -> Result<(), DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        
+        let deleted = sqlx::query!(
+            r#"DELETE FROM merchant_trusted_sources WHERE id = $1 AND merchant_id = $2"#,
+            source_id,
+            merchant_id,
+        )
+        .execute(&self.db.pool)
+        .await?;
+        
+        if deleted.rows_affected() == 0 {
+            return Err(DefiantError::NotFound("Trusted source not found".into()));
+        }
+        
+        info!("Trusted source {} removed", source_id);
+        WebhookService::new(self.db.clone())
+            .emit_configuration_change(
+                merchant_id,
+                TRUSTED_SOURCE_DELETED,
+                ConfigurationChangeData::new("trusted_source", source_id, Vec::new()),
+                api_key,
+            )
+            .await;
+        
+        Ok(())
+    }
+    
+    /// Refuses a key used from outside its IP allowlist, recording the attempt in the
+    /// violation and security logs. Requests whose address is unknown are refused too.
+    pub(crate) async fn enforce_ip_allowlist(
+        &self,
+        key: &AuthenticatedKey,
+        client_ip: Option<IpAddr>,
+        method: &str,
+        path: &str,
+    ) -> Result<(), DefiantError> {
+        let Some(allowed_ips) = &key.allowed_ips else {
+            return Ok(());
+        };
+        if client_ip.is_some_and(|ip| allowlist_contains(allowed_ips, ip)) {
+            return Ok(());
+        }
+        
+        let ip_address = client_ip.map(|ip| ip.to_string());
+        warn!("API key {} used from {} outside its allowlist", key.id, ip_address.as_deref().unwrap_or("an unknown address"));
+        // A session carries its key's allowlist but not the key, so its refusals go to
+        // the security log alone
+        let session_id = key.team_role.map(|_| key.id);
+        if session_id.is_none() {
+            let recorded = sqlx::query!(
+                r#"
+                INSERT INTO api_key_ip_violations (merchant_id, api_key_id, ip_address, method, path)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+                key.merchant_id,
+                key.id,
+                ip_address,
+                method,
+                path,
+            )
+            .execute(&self.db.pool)
+            .await;
+            if let Err(e) = recorded {
+                error!("Failed to record IP allowlist violation for API key {}: {}", key.id, e);
+            }
+        }
+        SecurityEventService::new(self.db.clone())
+            .record(NewSecurityEvent {
+                api_key_id: session_id.is_none().then_some(key.id),
+                ip_address: ip_address.clone(),
+                details: serde_json::json!({ "method": method, "path": path, "session_id": session_id }),
+                ..NewSecurityEvent::new(key.merchant_id, SecurityEventType::IpNotAllowed)
+            })
+            .await;
+        
+        Err(DefiantError::IpNotAllowed(format!(
+            "This API key cannot be used from {}",
+            ip_address.as_deref().unwrap_or("an unknown address"),
+        )))
+    }
+    
+    /// The security log of requests refused by key allowlists, newest first.
+    pub async fn list_ip_violations(
+        &self,
+        query: ApiKeyIpViolationListQuery,
+        api_key: &str,
+    ) -> Result<ApiKeyIpViolationListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            ApiKeyIpViolation,
+            r#"
+            SELECT * FROM api_key_ip_violations
+            WHERE merchant_id = $1
+            AND ($2::UUID IS NULL OR api_key_id = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            merchant_id,
+            query.api_key_id,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(ApiKeyIpViolationListResponse {
+            data,
+            has_more,
+            url: "/api/v1/account/security/ip_violations".into(),
+        })
+    }
+    
+    /// The merchant whose trusted source sent this request, if it came from one.
+    ///
+    /// A request qualifies by coming from a registered IP range or by carrying a valid
+    /// signature from a registered service identity, in both cases alongside one of that
+    /// merchant's API keys. A signature that fails to verify rejects the request rather
+    /// than quietly demoting it, so a misconfigured backend notices.
+    pub async fn trusted_merchant(
+        &self,
+        api_key: &str,
+        client_ip: Option<IpAddr>,
+        signature: Option<&ServiceSignature>,
+        method: &str,
+        path_and_query: &str,
+    ) -> Result<Option<Uuid>, DefiantError> {
+        let sources = sqlx::query_as!(
+            TrustedSource,
+            r#"
+            SELECT ts.* FROM merchant_trusted_sources ts
+            JOIN api_keys ak ON ak.merchant_id = ts.merchant_id
+            WHERE ak.key_hash = $1 AND ak.active = true
+            AND (ak.expires_at IS NULL OR ak.expires_at > NOW())
+            AND (
+                (ts.kind = 'ip_range' AND $2::INET <<= ts.ip_range::INET)
+                OR ts.id = $3
+            )
+            "#,
+            hash_api_key(api_key),
+            client_ip.map(|ip| ip.to_string()) as Option<String>,
+            signature.map(|s| s.source_id),
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        if let Some(signature) = signature {
+            let verified = sources.iter().any(|source| {
+                source.id == signature.source_id
+                    && source.public_key.as_deref().is_some_and(|key| signature.verify(key, method, path_and_query))
+            });
+            if !verified {
+                return Err(DefiantError::AuthenticationError("Invalid service signature".into()));
+            }
+        }
+        
+        Ok(sources.first().map(|source| source.merchant_id))
+    }
+}
+
+/// Canonical `address/prefix`, rejecting ranges with host bits set so what is stored is
+/// exactly what matches.
+fn normalize_ip_range(field: &str, range: &str) -> Result<String, DefiantError> {
+    let invalid = || DefiantError::ValidationError(format!("{}: '{}' is not a CIDR range", field, range));
+    
+    let (address, prefix) = range.trim().split_once('/').ok_or_else(invalid)?;
+    let address: IpAddr = address.parse().map_err(|_| invalid())?;
+    let prefix: u32 = prefix.parse().map_err(|_| invalid())?;
+    
+    let host_bits = match address {
+        IpAddr::V4(v4) => {
+            if prefix > 32 {
+                return Err(invalid());
+            }
+            u128::from(u32::from(v4)) & (u32::MAX.checked_shr(prefix).unwrap_or(0) as u128)
+        }
+        IpAddr::V6(v6) => {
+            if prefix > 128 {
+                return Err(invalid());
+            }
+            u128::from(v6) & u128::MAX.checked_shr(prefix).unwrap_or(0)
+        }
+    };
+    if host_bits != 0 {
+        return Err(DefiantError::ValidationError(format!("{}: '{}' has host bits set", field, range)));
+    }
+    
+    Ok(format!("{}/{}", address, prefix))
+}
+
+/// Canonical, deduplicated ranges for an API key's `allowed_ips`.
+pub(crate) fn normalize_allowlist(ranges: &[String]) -> Result<Vec<String>, DefiantError> {
+    let mut normalized = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        let range = normalize_ip_range("allowed_ips", range)?;
+        if !normalized.contains(&range) {
+            normalized.push(range);
+        }
+    }
+    Ok(normalized)
+}
+
+/// Whether `ip` falls inside one of the canonical ranges. IPv4 addresses mapped into
+/// IPv6 match IPv4 ranges.
+fn allowlist_contains(ranges: &[String], ip: IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    ranges.iter().any(|range| {
+        let Some((network, prefix)) = range.split_once('/') else {
+            return false;
+        };
+        let (Ok(network), Ok(prefix)) = (network.parse::<IpAddr>(), prefix.parse::<u32>()) else {
+            return false;
+        };
+        match (network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    })
+}
+
+fn normalize_public_key(key: &str) -> Result<String, DefiantError> {
+    match hex::decode(key.trim()) {
+        Ok(bytes) if bytes.len() == 32 => Ok(hex::encode(bytes)),
+        _ => Err(DefiantError::ValidationError("public_key must be a hex-encoded 32-byte Ed25519 key".into())),
+    }
+}
+
+fn tier_cache_key(merchant_id: Uuid) -> String {
+    format!("rate_limit_tier:{}", merchant_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn extreme_timestamps_are_outside_tolerance() {
+        assert!(within_tolerance(Utc::now().timestamp()));
+        assert!(!within_tolerance(i64::MIN));
+        assert!(!within_tolerance(i64::MAX));
+    }
+    
+    #[test]
+    fn service_signature_with_extreme_timestamp_is_invalid() {
+        for timestamp in [i64::MIN, i64::MAX] {
+            let signature = ServiceSignature { source_id: Uuid::new_v4(), timestamp, signature: vec![0; 64] };
+            assert!(!signature.verify(&"00".repeat(32), "GET", "/api/v1/payments"));
+        }
+    }
+}
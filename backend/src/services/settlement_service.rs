@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use uuid::Uuid;
+use chrono::{Duration, NaiveDate};
+use tracing::{info, warn, error};
+
+use crate::{models::{SettlementBatch, SettlementBatchItem, SettlementBatchResponse, SettlementBatchStatus, SettlementItemStatus, SettlementRecord, AcknowledgmentLine, settlement_file, parse_acknowledgment}, errors::DefiantError, db::Database};
+
+
+/// End-of-day settlement files for merchants on net-settlement acquirers.
+pub struct SettlementService {
+    db: Arc<Database>,
+}
+
+impl SettlementService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    /// Writes the batches for `business_date` for every batch-settled merchant.
+    /// Safe to run repeatedly: payments are batched once and batches are unique per day.
+    pub async fn generate_batches(&self, business_date: NaiveDate) -> Result<(), DefiantError> {
+        let merchants = sqlx::query!(
+            r#"
+            SELECT id, acquirer_merchant_id
+            FROM merchants
+            WHERE batch_settlement_enabled = true AND active = true
+            "#,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        for merchant in merchants {
+            let acquirer_merchant_id = match merchant.acquirer_merchant_id {
+                Some(mid) => mid,
+                None => {
+                    warn!("Merchant {} is batch-settled but has no acquirer merchant id", merchant.id);
+                    continue;
+                }
+            };
+            
+            if let Err(e) = self.generate_for_merchant(merchant.id, &acquirer_merchant_id, business_date).await {
+                error!("Failed to generate settlement batch for merchant {}: {}", merchant.id, e);
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Batches every captured, not yet batched payment up to the end of `business_date`,
+    /// one batch per currency. Payments rejected from an earlier batch are picked up again.
+    pub async fn generate_for_merchant(
+        &self,
+        merchant_id: Uuid,
+        acquirer_merchant_id: &str,
+        business_date: NaiveDate,
+    ) -> Result<Vec<SettlementBatch>, DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        let cutoff = (business_date + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap().and_utc();
+        
+        let payments = sqlx::query!(
+            r#"
+            SELECT id, amount, currency, network_transaction_id, updated_at
+            FROM payments
+            WHERE merchant_id = $1 AND status = 'succeeded'
+            AND settlement_batch_id IS NULL AND created_at < $2
+            ORDER BY created_at, id
+            FOR UPDATE SKIP LOCKED
+            "#,
+            merchant_id,
+            cutoff,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        let mut by_currency: BTreeMap<String, Vec<SettlementRecord>> = BTreeMap::new();
+        for payment in payments {
+            let records = by_currency.entry(payment.currency).or_default();
+            records.push(SettlementRecord {
+                sequence_number: records.len() as i32 + 1,
+                payment_id: payment.id,
+                amount: payment.amount,
+                network_transaction_id: payment.network_transaction_id,
+                captured_at: payment.updated_at,
+            });
+        }
+        
+        let mut batches = Vec::new();
+        for (currency, records) in by_currency {
+            let batch_id = Uuid::new_v4();
+            let contents = settlement_file(batch_id, acquirer_merchant_id, business_date, &currency, &records);
+            let total: i64 = records.iter().map(|r| r.amount).sum();
+            
+            // A batch for this day and currency already went out; leftovers wait for tomorrow's
+            let batch = sqlx::query_as!(
+                SettlementBatch,
+                r#"
+                INSERT INTO settlement_batches (
+                    id, merchant_id, business_date, currency, file_name, file_contents,
+                    transaction_count, total_amount
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (merchant_id, business_date, currency) DO NOTHING
+                RETURNING *
+                "#,
+                batch_id,
+                merchant_id,
+                business_date,
+                currency,
+                format!("{}_{}_{}.csv", acquirer_merchant_id, business_date.format("%Y%m%d"), currency),
+                contents,
+                records.len() as i32,
+                total,
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+            
+            let batch = match batch {
+                Some(batch) => batch,
+                None => continue,
+            };
+            
+            let payment_ids: Vec<Uuid> = records.iter().map(|r| r.payment_id).collect();
+            let sequence_numbers: Vec<i32> = records.iter().map(|r| r.sequence_number).collect();
+            let amounts: Vec<i64> = records.iter().map(|r| r.amount).collect();
+            
+            sqlx::query!(
+                r#"
+                INSERT INTO settlement_batch_items (batch_id, payment_id, sequence_number, amount)
+                SELECT $1, * FROM unnest($2::UUID[], $3::INTEGER[], $4::BIGINT[])
+                "#,
+                batch.id,
+                &payment_ids,
+                &sequence_numbers,
+                &amounts,
+            )
+            .execute(&mut *tx)
+            .await?;
+            
+            sqlx::query!(
+                r#"UPDATE payments SET settlement_batch_id = $1 WHERE id = ANY($2)"#,
+                batch.id,
+                &payment_ids,
+            )
+            .execute(&mut *tx)
+            .await?;
+            
+            info!(
+                "Settlement batch {} generated for merchant {}: {} {} across {} transactions",
+                batch.id, merchant_id, batch.total_amount, batch.currency, batch.transaction_count
+            );
+            batches.push(batch);
+        }
+        
+        tx.commit().await?;
+        
+        Ok(batches)
+    }
+    
+    pub async fn get_batch(&self, batch_id: Uuid, api_key: &str) -> Result<SettlementBatchResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let batch = sqlx::query_as!(
+            SettlementBatch,
+            r#"SELECT * FROM settlement_batches WHERE id = $1 AND merchant_id = $2"#,
+            batch_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Settlement batch not found".into()))?;
+        
+        let items = sqlx::query_as!(
+            SettlementBatchItem,
+            r#"SELECT * FROM settlement_batch_items WHERE batch_id = $1 ORDER BY sequence_number"#,
+            batch.id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(SettlementBatchResponse { batch, items })
+    }
+    
+    /// Applies the acquirer's acknowledgment file. Rejected transactions are released
+    /// from the batch so the next run resubmits them.
+    pub async fn ingest_acknowledgment(
+        &self,
+        batch_id: Uuid,
+        contents: &str,
+        api_key: &str,
+    ) -> Result<SettlementBatchResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let lines = parse_acknowledgment(contents)
+            .map_err(|e| DefiantError::ValidationError(format!("Invalid acknowledgment file: {}", e)))?;
+        
+        let disposition = match lines.first() {
+            Some(AcknowledgmentLine::Batch { batch_id: acked, disposition }) if *acked == batch_id => disposition.clone(),
+            Some(AcknowledgmentLine::Batch { .. }) => {
+                return Err(DefiantError::ValidationError("Acknowledgment is for a different batch".into()));
+            }
+            _ => return Err(DefiantError::ValidationError("Acknowledgment must start with an A record".into())),
+        };
+        
+        let mut rejections: Vec<(i32, String)> = lines
+            .iter()
+            .filter_map(|line| match line {
+                AcknowledgmentLine::Rejected { sequence_number, reject_code } => Some((*sequence_number, reject_code.clone())),
+                _ => None,
+            })
+            .collect();
+        
+        let mut tx = pool.begin().await?;
+        
+        let batch = sqlx::query_as!(
+            SettlementBatch,
+            r#"SELECT * FROM settlement_batches WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+            batch_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Settlement batch not found".into()))?;
+        
+        if !matches!(batch.status, SettlementBatchStatus::Generated) {
+            return Err(DefiantError::Conflict("Settlement batch has already been acknowledged".into()));
+        }
+        
+        match disposition.as_str() {
+            "ACCEPTED" | "PARTIAL" => {}
+            "REJECTED" => {
+                // A whole-file rejection covers every line, with any per-line codes given
+                let coded: Vec<i32> = rejections.iter().map(|(seq, _)| *seq).collect();
+                rejections.extend(
+                    (1..=batch.transaction_count)
+                        .filter(|seq| !coded.contains(seq))
+                        .map(|seq| (seq, "BATCH_REJECTED".to_string())),
+                );
+            }
+            other => return Err(DefiantError::ValidationError(format!("Unknown batch disposition: {}", other))),
+        }
+        
+        let rejected_sequences: Vec<i32> = rejections.iter().map(|(seq, _)| *seq).collect();
+        let reject_codes: Vec<String> = rejections.into_iter().map(|(_, code)| code).collect();
+        
+        let rejected_payments = sqlx::query_scalar!(
+            r#"
+            UPDATE settlement_batch_items i
+            SET status = $2, reject_code = r.reject_code
+            FROM unnest($3::INTEGER[], $4::TEXT[]) AS r(sequence_number, reject_code)
+            WHERE i.batch_id = $1 AND i.sequence_number = r.sequence_number
+            RETURNING i.payment_id AS "payment_id!"
+            "#,
+            batch.id,
+            SettlementItemStatus::Rejected as SettlementItemStatus,
+            &rejected_sequences,
+            &reject_codes,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+        
+        if rejected_payments.len() != rejected_sequences.len() {
+            return Err(DefiantError::ValidationError("Acknowledgment rejects a sequence number not in the batch".into()));
+        }
+        
+        sqlx::query!(
+            r#"UPDATE settlement_batch_items SET status = $2 WHERE batch_id = $1 AND status = 'pending'"#,
+            batch.id,
+            SettlementItemStatus::Accepted as SettlementItemStatus,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"UPDATE payments SET settlement_batch_id = NULL WHERE id = ANY($1)"#,
+            &rejected_payments,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        let rejected_count = rejected_payments.len() as i32;
+        let accepted_count = batch.transaction_count - rejected_count;
+        let status = if rejected_count == 0 {
+            SettlementBatchStatus::Accepted
+        } else if accepted_count == 0 {
+            SettlementBatchStatus::Rejected
+        } else {
+            SettlementBatchStatus::PartiallyAccepted
+        };
+        
+        sqlx::query!(
+            r#"
+            UPDATE settlement_batches
+            SET status = $2, accepted_count = $3, rejected_count = $4, acknowledged_at = NOW()
+            WHERE id = $1
+            "#,
+            batch.id,
+            status as SettlementBatchStatus,
+            accepted_count,
+            rejected_count,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Settlement batch {} acknowledged: {} accepted, {} rejected", batch.id, accepted_count, rejected_count);
+        
+        self.get_batch(batch.id, api_key).await
+    }
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use crate::{models::{CardNetwork, CharacterSubstitution, PreviewStatementDescriptorRequest, StatementDescriptorPreview, StatementRendering}, errors::DefiantError, db::{Database, hash_api_key}};
+
+/// Longest descriptor, suffix included, that every network prints in full.
+const MAX_DESCRIPTOR_LENGTH: usize = 22;
+const MIN_DESCRIPTOR_LENGTH: usize = 5;
+/// Bounds on the descriptor when a suffix is appended to it.
+const MIN_PREFIX_LENGTH: usize = 2;
+const MAX_PREFIX_LENGTH: usize = 10;
+const SUFFIX_SEPARATOR: &str = "* ";
+/// Rejected outright by the networks.
+const FORBIDDEN_CHARACTERS: &[char] = &['<', '>', '\\', '\'', '"', '*'];
+/// Punctuation statements print as is; anything else outside letters and digits
+/// becomes a space.
+const PRINTABLE_PUNCTUATION: &[char] = &[' ', '.', ',', '-', '&', '/', '#', '+', ':'];
+/// Statement line length and whether the network prints it in capitals.
+const NETWORKS: &[(CardNetwork, usize, bool)] = &[
+    (CardNetwork::Visa, 25, true),
+    (CardNetwork::Mastercard, 22, true),
+    (CardNetwork::Amex, 20, false),
+    (CardNetwork::Discover, 22, true),
+];
+
+/// Previews how a statement descriptor reads on cardholder statements, so merchants can
+/// pick one customers recognize before "unrecognized charge" disputes arrive.
+///
+/// Renderings approximate the major networks' statement lines: their length limits,
+/// capitalization, and the ASCII character set, into which accented Latin letters are
+/// transliterated and other characters dropped. Previews are a test-mode tool.
+pub struct StatementDescriptorService {
+    db: Arc<Database>,
+}
+
+impl StatementDescriptorService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn preview(
+        &self,
+        request: PreviewStatementDescriptorRequest,
+        api_key: &str,
+    ) -> Result<StatementDescriptorPreview, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        
+        let merchant = sqlx::query!(
+            r#"
+            SELECT ak.livemode, m.name, m.website FROM api_keys ak
+            JOIN merchants m ON m.id = ak.merchant_id
+            WHERE ak.key_hash = $1 AND m.id = $2
+            "#,
+            hash_api_key(api_key),
+            merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await?;
+        
+        if merchant.livemode {
+            return Err(DefiantError::AuthorizationError(
+                "Statement descriptor previews are only available with test mode keys".into(),
+            ));
+        }
+        
+        let prefix = request.descriptor.unwrap_or_else(|| merchant.name.clone());
+        let prefix = prefix.trim();
+        let suffix = request.suffix.as_deref().map(str::trim);
+        let descriptor = match suffix {
+            Some(suffix) => format!("{}{}{}", prefix, SUFFIX_SEPARATOR, suffix),
+            None => prefix.to_string(),
+        };
+        
+        let mut errors = validate(prefix, suffix, &descriptor);
+        let mut warnings = Vec::new();
+        
+        let renderings: Vec<StatementRendering> = NETWORKS
+            .iter()
+            .map(|&(network, max_length, uppercase)| render(&descriptor, network, max_length, uppercase))
+            .collect();
+        
+        if renderings.iter().any(|rendering| rendering.text.trim().is_empty()) {
+            errors.push("Nothing in the descriptor can be printed on a statement".into());
+        }
+        for rendering in renderings.iter().filter(|rendering| rendering.truncated) {
+            warnings.push(format!("{:?} statements cut the descriptor to {} characters", rendering.network, rendering.max_length));
+        }
+        if renderings.iter().any(|rendering| !rendering.substitutions.is_empty()) {
+            warnings.push("Some characters are replaced or dropped on statements".into());
+        }
+        if !recognizable(&descriptor, &merchant.name, merchant.website.as_deref()) {
+            warnings.push("The descriptor mentions neither the business name nor its website, so customers may not recognize the charge".into());
+        }
+        
+        Ok(StatementDescriptorPreview {
+            descriptor,
+            valid: errors.is_empty(),
+            errors,
+            warnings,
+            renderings,
+        })
+    }
+}
+
+fn validate(prefix: &str, suffix: Option<&str>, descriptor: &str) -> Vec<String> {
+    let mut errors = Vec::new();
+    let length = descriptor.chars().count();
+    let prefix_length = prefix.chars().count();
+    
+    match suffix {
+        Some(_) if !(MIN_PREFIX_LENGTH..=MAX_PREFIX_LENGTH).contains(&prefix_length) => {
+            errors.push(format!(
+                "A descriptor with a suffix must be {} to {} characters",
+                MIN_PREFIX_LENGTH, MAX_PREFIX_LENGTH,
+            ));
+        }
+        None if length < MIN_DESCRIPTOR_LENGTH => {
+            errors.push(format!("The descriptor must be at least {} characters", MIN_DESCRIPTOR_LENGTH));
+        }
+        _ => {}
+    }
+    if length > MAX_DESCRIPTOR_LENGTH {
+        errors.push(format!("The descriptor must be at most {} characters, suffix included", MAX_DESCRIPTOR_LENGTH));
+    }
+    if !prefix.chars().any(|c| c.is_alphabetic()) {
+        errors.push("The descriptor must contain at least one letter".into());
+    }
+    
+    let forbidden: String = prefix
+        .chars()
+        .chain(suffix.unwrap_or_default().chars())
+        .filter(|c| FORBIDDEN_CHARACTERS.contains(c))
+        .collect();
+    if !forbidden.is_empty() {
+        errors.push(format!("The descriptor cannot contain {}", forbidden));
+    }
+    
+    errors
+}
+
+fn render(descriptor: &str, network: CardNetwork, max_length: usize, uppercase: bool) -> StatementRendering {
+    let mut substitutions: Vec<CharacterSubstitution> = Vec::new();
+    let mut text = String::new();
+    
+    for c in descriptor.chars() {
+        let printed = match c {
+            _ if c.is_ascii_alphanumeric() || PRINTABLE_PUNCTUATION.contains(&c) => c.to_string(),
+            // Our own suffix separator, which the forbidden-character rule leaves to us
+            '*' => c.to_string(),
+            _ => {
+                let replacement = transliterate(c).unwrap_or(if c.is_ascii() { " " } else { "" });
+                let replacement = if c.is_uppercase() { replacement.to_uppercase() } else { replacement.to_string() };
+                if !substitutions.iter().any(|s| s.original == c.to_string()) {
+                    substitutions.push(CharacterSubstitution { original: c.to_string(), replacement: replacement.clone() });
+                }
+                replacement
+            }
+        };
+        text.push_str(&printed);
+    }
+    
+    // Statements do not print runs of spaces
+    let mut text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if uppercase {
+        text = text.to_uppercase();
+    }
+    
+    let truncated = text.chars().count() > max_length;
+    if truncated {
+        text = text.chars().take(max_length).collect::<String>().trim_end().to_string();
+    }
+    
+    StatementRendering {
+        network,
+        text,
+        max_length,
+        truncated,
+        substitutions,
+    }
+}
+
+/// ASCII spelling of the accented Latin letters statements cannot print.
+fn transliterate(c: char) -> Option<&'static str> {
+    let ascii = match c.to_lowercase().next().unwrap_or(c) {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ą' => "a",
+        'ç' | 'ć' | 'č' => "c",
+        'ď' | 'đ' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+        'ğ' => "g",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'ı' => "i",
+        'ł' => "l",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'ř' => "r",
+        'ś' | 'š' | 'ş' => "s",
+        'ť' | 'ţ' => "t",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'ý' | 'ÿ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'þ' => "th",
+        _ => return None,
+    };
+    Some(ascii)
+}
+
+/// Whether the descriptor carries the business name or website, compared on letters
+/// and digits alone.
+fn recognizable(descriptor: &str, name: &str, website: Option<&str>) -> bool {
+    let normalize = |value: &str| -> String {
+        value.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+    };
+    let descriptor = normalize(descriptor);
+    
+    let domain = website.map(|website| {
+        let host = website.trim_start_matches("https://").trim_start_matches("http://").trim_start_matches("www.");
+        host.split(['.', '/']).next().unwrap_or_default().to_string()
+    });
+    
+    name.split_whitespace()
+        .map(normalize)
+        .chain(domain.as_deref().map(normalize))
+        .filter(|word| word.len() >= 3)
+        .any(|word| descriptor.contains(&word))
+}
@@ -0,0 +1,309 @@
+use std::sync::Arc;
+use sqlx::PgPool;
+use uuid::Uuid;
+use ring::hmac;
+use serde::Deserialize;
+use tracing::{info, warn, error};
+
+use crate::{models::{Payment, PaymentStatus, PAYMENT_SUCCEEDED, PAYMENT_FAILED, PAYMENT_REFUNDED, PAYMENT_DISPUTED}, errors::DefiantError, db::Database, config::Config, plugins::{self, RefundNotice}};
+use super::webhook_service::WebhookService;
+use super::ledger;
+
+pub const STRIPE_SIGNATURE_HEADER: &str = "Stripe-Signature";
+/// Signed timestamps older (or newer) than this are rejected as replays.
+const SIGNATURE_TOLERANCE_SECS: u64 = 300;
+/// Charges we create carry our payment id under this metadata key.
+const PAYMENT_ID_METADATA_KEY: &str = "defiant_payment_id";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StripeEvent {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: String,
+    pub data: StripeEventData,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StripeEventData {
+    pub object: serde_json::Value,
+}
+
+/// Checks a `Stripe-Signature` header (`t=<ts>,v1=<hex>[,v1=<hex>...]`) against the raw body.
+///
+/// Any `v1` entry may match, since Stripe signs with every active secret while one rolls.
+pub fn verify_signature(secret: &str, header: &str, payload: &[u8], now: i64) -> Result<(), DefiantError> {
+    let mut timestamp = None;
+    let mut signatures = Vec::new();
+    
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signatures.push(value),
+            _ => {}
+        }
+    }
+    
+    let timestamp = timestamp.ok_or_else(|| DefiantError::WebhookError("Missing signature timestamp".into()))?;
+    if now.abs_diff(timestamp) > SIGNATURE_TOLERANCE_SECS {
+        return Err(DefiantError::WebhookError("Signature timestamp outside tolerance".into()));
+    }
+    
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let mut signed = format!("{}.", timestamp).into_bytes();
+    signed.extend_from_slice(payload);
+    
+    let valid = signatures.iter().any(|signature| {
+        hex::decode(signature)
+            .map(|tag| hmac::verify(&key, &signed, &tag).is_ok())
+            .unwrap_or(false)
+    });
+    
+    if !valid {
+        return Err(DefiantError::WebhookError("No matching signature".into()));
+    }
+    
+    Ok(())
+}
+
+/// Applies Stripe events to the payments they concern and re-emits them as our own events.
+pub struct StripeService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl StripeService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn handle_event(&self, event: StripeEvent) -> Result<(), DefiantError> {
+        // Stripe redelivers until it sees a 2xx; the first delivery to land wins
+        let first_delivery = sqlx::query!(
+            r#"
+            INSERT INTO stripe_events (id, type) VALUES ($1, $2)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+            event.id,
+            event.event_type,
+        )
+        .execute(&self.db.pool)
+        .await?
+        .rows_affected() == 1;
+        
+        if !first_delivery {
+            info!("Stripe event {} already processed", event.id);
+            return Ok(());
+        }
+        
+        let result = self.apply(&event).await;
+        if result.is_err() {
+            // Let Stripe's retry reprocess it
+            if let Err(e) = sqlx::query!(r#"DELETE FROM stripe_events WHERE id = $1"#, event.id)
+                .execute(&self.db.pool)
+                .await
+            {
+                error!("Failed to release Stripe event {}: {}", event.id, e);
+            }
+        }
+        
+        result
+    }
+    
+    async fn apply(&self, event: &StripeEvent) -> Result<(), DefiantError> {
+        let object = &event.data.object;
+        
+        match event.event_type.as_str() {
+            "charge.succeeded" => {
+                let (pool, merchant_id, payment_id) = match self.payment_for_charge(object).await? {
+                    Some(found) => found,
+                    None => return Ok(()),
+                };
+                let mut tx = pool.begin().await?;
+                let payment = sqlx::query_as!(
+                    Payment,
+                    r#"
+                    UPDATE payments
+                    SET status = $1, stripe_charge_id = $2, updated_at = NOW()
+                    WHERE id = $3 AND status IN ('pending', 'processing', 'requires_action', 'requires_confirmation')
+                    RETURNING *
+                    "#,
+                    PaymentStatus::Succeeded as PaymentStatus,
+                    object["id"].as_str(),
+                    payment_id,
+                )
+                .fetch_optional(&mut *tx)
+                .await?;
+                
+                if let Some(payment) = &payment {
+                    ledger::record_card_payment(&mut tx, &self.db.pool, payment, &self.config).await?;
+                }
+                tx.commit().await?;
+                
+                if let Some(payment) = payment {
+                    self.emit(merchant_id, &payment, PAYMENT_SUCCEEDED).await;
+                }
+            }
+            "charge.failed" => {
+                let (pool, merchant_id, payment_id) = match self.payment_for_charge(object).await? {
+                    Some(found) => found,
+                    None => return Ok(()),
+                };
+                let payment = sqlx::query_as!(
+                    Payment,
+                    r#"
+                    UPDATE payments
+                    SET status = $1, stripe_charge_id = $2, failure_code = $3, failure_message = $4, updated_at = NOW()
+                    WHERE id = $5 AND status IN ('pending', 'processing', 'requires_action', 'requires_confirmation')
+                    RETURNING *
+                    "#,
+                    PaymentStatus::Failed as PaymentStatus,
+                    object["id"].as_str(),
+                    object["failure_code"].as_str(),
+                    object["failure_message"].as_str(),
+                    payment_id,
+                )
+                .fetch_optional(pool)
+                .await?;
+                
+                if let Some(payment) = payment {
+                    self.emit(merchant_id, &payment, PAYMENT_FAILED).await;
+                }
+            }
+            "charge.refunded" => {
+                let (pool, merchant_id, payment_id) = match self.payment_for_charge(object).await? {
+                    Some(found) => found,
+                    None => return Ok(()),
+                };
+                let amount_refunded = object["amount_refunded"].as_i64().unwrap_or(0);
+                let mut tx = pool.begin().await?;
+                let previously_refunded = sqlx::query_scalar!(
+                    r#"SELECT refunded_amount FROM payments WHERE id = $1 FOR UPDATE"#,
+                    payment_id,
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                
+                // Redelivered or out-of-order events refund nothing new, and must not
+                // lower the total a later event already recorded
+                let amount = amount_refunded - previously_refunded;
+                if amount <= 0 {
+                    return Ok(());
+                }
+                let payment = sqlx::query_as!(
+                    Payment,
+                    r#"
+                    UPDATE payments
+                    SET refunded_amount = $1,
+                        status = CASE WHEN $1 >= amount THEN 'refunded'::payment_status ELSE 'partially_refunded'::payment_status END,
+                        updated_at = NOW()
+                    WHERE id = $2
+                    RETURNING *
+                    "#,
+                    amount_refunded,
+                    payment_id,
+                )
+                .fetch_one(&mut *tx)
+                .await?;
+                ledger::record_card_refund(&mut tx, &payment, amount, None).await?;
+                tx.commit().await?;
+                
+                self.emit(merchant_id, &payment, PAYMENT_REFUNDED).await;
+                plugins::hooks()
+                    .post_refund(&RefundNotice { merchant_id, payment: &payment, amount, reason: None })
+                    .await;
+            }
+            "charge.dispute.created" => {
+                let charge_id = match object["charge"].as_str() {
+                    Some(charge_id) => charge_id,
+                    None => return Ok(()),
+                };
+                
+                for pool in self.db.pools() {
+                    let merchant_id = sqlx::query_scalar!(
+                        r#"SELECT merchant_id AS "merchant_id!" FROM payments WHERE stripe_charge_id = $1"#,
+                        charge_id,
+                    )
+                    .fetch_optional(pool)
+                    .await?;
+                    
+                    let merchant_id = match merchant_id {
+                        Some(merchant_id) => merchant_id,
+                        None => continue,
+                    };
+                    
+                    let payment = sqlx::query_as!(
+                        Payment,
+                        r#"
+                        UPDATE payments SET status = $1, updated_at = NOW()
+                        WHERE stripe_charge_id = $2
+                        RETURNING *
+                        "#,
+                        PaymentStatus::Disputed as PaymentStatus,
+                        charge_id,
+                    )
+                    .fetch_one(pool)
+                    .await?;
+                    
+                    self.emit(merchant_id, &payment, PAYMENT_DISPUTED).await;
+                    return Ok(());
+                }
+                
+                warn!("Stripe dispute for unknown charge {}", charge_id);
+            }
+            other => info!("Ignoring Stripe event type {}", other),
+        }
+        
+        Ok(())
+    }
+    
+    /// Locates the payment a charge object belongs to via its metadata.
+    async fn payment_for_charge(&self, charge: &serde_json::Value) -> Result<Option<(&PgPool, Uuid, Uuid)>, DefiantError> {
+        let payment_id = match charge["metadata"][PAYMENT_ID_METADATA_KEY].as_str().and_then(|id| id.parse::<Uuid>().ok()) {
+            Some(payment_id) => payment_id,
+            None => {
+                warn!("Stripe charge {} has no {} metadata", charge["id"], PAYMENT_ID_METADATA_KEY);
+                return Ok(None);
+            }
+        };
+        
+        let pool = match self.db.pool_for_row("payments", payment_id).await? {
+            Some(pool) => pool,
+            None => {
+                warn!("Stripe charge {} references unknown payment {}", charge["id"], payment_id);
+                return Ok(None);
+            }
+        };
+        
+        let merchant_id = sqlx::query_scalar!(
+            r#"SELECT merchant_id AS "merchant_id!" FROM payments WHERE id = $1"#,
+            payment_id,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        Ok(Some((pool, merchant_id, payment_id)))
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, payment: &Payment, event_type: &str) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(payment))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn extreme_timestamps_are_rejected() {
+        let now = chrono::Utc::now().timestamp();
+        for timestamp in [i64::MIN, i64::MAX] {
+            let header = format!("t={},v1={}", timestamp, "00".repeat(32));
+            assert!(verify_signature("whsec_test", &header, b"{}", now).is_err());
+        }
+    }
+}
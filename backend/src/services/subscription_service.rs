@@ -0,0 +1,461 @@
+use std::sync::Arc;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+use chrono::{Duration, Utc};
+use tracing::{info, warn, error};
+
+use crate::{api::v1::conditional::check_version, models::{Subscription, SubscriptionStatus, SubscriptionResponse, SubscriptionListResponse, SubscriptionChangeTiming, CreateSubscriptionRequest, UpdateSubscriptionRequest, CancelSubscriptionRequest, SubscriptionListQuery, Plan, SUBSCRIPTION_CREATED, SUBSCRIPTION_UPDATED, SUBSCRIPTION_RENEWED, SUBSCRIPTION_CANCELED}, errors::DefiantError, db::Database};
+use super::invoice_service::{self, InvoiceDraftLine};
+use super::webhook_service::WebhookService;
+
+const RENEWAL_BATCH_SIZE: i64 = 100;
+
+/// Subscriptions and their renewals.
+///
+/// Changes are either immediate or held on the subscription until its current period
+/// ends: a scheduled plan switch is kept in `pending_plan_id` and a scheduled
+/// cancellation in `cancel_at_period_end`, both shown as `pending_update`. The renewal
+/// scheduler applies them when the period ends, then either ends the subscription or
+/// starts the next period and issues its invoice.
+pub struct SubscriptionService {
+    db: Arc<Database>,
+}
+
+impl SubscriptionService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn create_subscription(
+        &self,
+        request: CreateSubscriptionRequest,
+        api_key: &str,
+    ) -> Result<SubscriptionResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let customer_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS(SELECT 1 FROM customers WHERE id = $1 AND merchant_id = $2 AND livemode = $3)"#,
+            request.customer_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?
+        .unwrap_or(false);
+        
+        if !customer_exists {
+            return Err(DefiantError::NotFound("Customer not found".into()));
+        }
+        
+        let plan = load_plan(&mut tx, request.plan_id, merchant_id).await?;
+        if !plan.active {
+            return Err(DefiantError::Conflict("Plan is no longer active".into()));
+        }
+        
+        let now = Utc::now();
+        let trial_days = request.trial_period_days.unwrap_or(plan.trial_period_days);
+        let (status, trial_end) = if trial_days > 0 {
+            (SubscriptionStatus::Trialing, Some(now + Duration::days(trial_days as i64)))
+        } else {
+            (SubscriptionStatus::Active, None)
+        };
+        let period_end = trial_end.unwrap_or_else(|| plan.period_end(now));
+        
+        let subscription = sqlx::query_as!(
+            Subscription,
+            r#"
+            INSERT INTO subscriptions (
+                merchant_id, customer_id, status, plan_id,
+                current_period_start, current_period_end, trial_start, trial_end, metadata, livemode
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.customer_id,
+            status as SubscriptionStatus,
+            plan.id,
+            now,
+            period_end,
+            trial_end.map(|_| now),
+            trial_end,
+            request.metadata,
+            key.livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        // A trial is free; otherwise the first period is billed up front like every renewal
+        if trial_end.is_none() {
+            issue_period_invoice(&mut tx, &subscription, &plan).await?;
+        }
+        
+        tx.commit().await?;
+        
+        info!("Subscription {} created on plan {}", subscription.id, plan.id);
+        let response = SubscriptionResponse::from(subscription);
+        self.emit(merchant_id, SUBSCRIPTION_CREATED, &response).await;
+        
+        Ok(response)
+    }
+    
+    pub async fn get_subscription(
+        &self,
+        subscription_id: Uuid,
+        api_key: &str,
+    ) -> Result<SubscriptionResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        
+        let subscription = sqlx::query_as!(
+            Subscription,
+            r#"SELECT * FROM subscriptions WHERE id = $1 AND merchant_id = $2"#,
+            subscription_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Subscription not found".into()))?;
+        
+        Ok(subscription.into())
+    }
+    
+    pub async fn list_subscriptions(
+        &self,
+        query: SubscriptionListQuery,
+        api_key: &str,
+    ) -> Result<SubscriptionListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.read_for_merchant(merchant_id).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            Subscription,
+            r#"
+            SELECT * FROM subscriptions
+            WHERE merchant_id = $1 AND livemode = $5
+            AND ($2::UUID IS NULL OR customer_id = $2)
+            AND ($3::subscription_status IS NULL OR status = $3)
+            ORDER BY created_at DESC
+            LIMIT $4
+            "#,
+            merchant_id,
+            query.customer_id,
+            query.status as Option<SubscriptionStatus>,
+            limit + 1,
+            key.livemode,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(SubscriptionListResponse {
+            data: data.into_iter().map(SubscriptionResponse::from).collect(),
+            has_more,
+            url: "/api/v1/subscriptions".into(),
+        })
+    }
+    
+    /// Switches plan now, or schedules the switch for the next renewal, and sets or
+    /// withdraws an end-of-period cancellation. Immediate switches are not prorated.
+    /// Asking for the current plan withdraws a scheduled switch.
+    pub async fn update_subscription(
+        &self,
+        subscription_id: Uuid,
+        request: UpdateSubscriptionRequest,
+        api_key: &str,
+    ) -> Result<SubscriptionResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let subscription = lock_live_subscription(&mut tx, subscription_id, merchant_id).await?;
+        check_version(request.version, subscription.version)?;
+        
+        let (plan_id, pending_plan_id) = match request.plan_id {
+            None => (subscription.plan_id, subscription.pending_plan_id),
+            Some(plan_id) if plan_id == subscription.plan_id => (plan_id, None),
+            Some(plan_id) => {
+                let plan = load_plan(&mut tx, plan_id, merchant_id).await?;
+                if !plan.active {
+                    return Err(DefiantError::Conflict("Plan is no longer active".into()));
+                }
+                match request.change_timing {
+                    SubscriptionChangeTiming::Immediately => (plan.id, None),
+                    SubscriptionChangeTiming::PeriodEnd => (subscription.plan_id, Some(plan.id)),
+                }
+            }
+        };
+        let cancel_at_period_end = request.cancel_at_period_end.unwrap_or(subscription.cancel_at_period_end);
+        
+        let updated = sqlx::query_as!(
+            Subscription,
+            r#"
+            UPDATE subscriptions
+            SET plan_id = $1, pending_plan_id = $2, cancel_at_period_end = $3,
+                metadata = COALESCE($4, metadata)
+            WHERE id = $5
+            RETURNING *
+            "#,
+            plan_id,
+            pending_plan_id,
+            cancel_at_period_end,
+            request.metadata,
+            subscription.id,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!(
+            "Subscription {} updated: plan {}, pending plan {:?}, cancel at period end {}",
+            updated.id, updated.plan_id, updated.pending_plan_id, updated.cancel_at_period_end,
+        );
+        let response = SubscriptionResponse::from(updated);
+        self.emit(merchant_id, SUBSCRIPTION_UPDATED, &response).await;
+        
+        Ok(response)
+    }
+    
+    /// Ends the subscription now, or at the end of the period already billed.
+    pub async fn cancel_subscription(
+        &self,
+        subscription_id: Uuid,
+        request: CancelSubscriptionRequest,
+        api_key: &str,
+    ) -> Result<SubscriptionResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let subscription = lock_live_subscription(&mut tx, subscription_id, merchant_id).await?;
+        check_version(request.version, subscription.version)?;
+        
+        let (canceled, event_type) = if request.at_period_end {
+            let scheduled = sqlx::query_as!(
+                Subscription,
+                r#"
+                UPDATE subscriptions SET cancel_at_period_end = true
+                WHERE id = $1
+                RETURNING *
+                "#,
+                subscription.id,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            (scheduled, SUBSCRIPTION_UPDATED)
+        } else {
+            (end_subscription(&mut tx, subscription.id, Utc::now()).await?, SUBSCRIPTION_CANCELED)
+        };
+        
+        tx.commit().await?;
+        
+        info!("Subscription {} canceled (at period end: {})", canceled.id, request.at_period_end);
+        let response = SubscriptionResponse::from(canceled);
+        self.emit(merchant_id, event_type, &response).await;
+        
+        Ok(response)
+    }
+    
+    /// Renews or ends every subscription whose current period is over.
+    pub async fn renew_due(&self) -> Result<(), DefiantError> {
+        for pool in self.db.pools() {
+            let due = sqlx::query_scalar!(
+                r#"
+                SELECT id FROM subscriptions
+                WHERE status IN ('active', 'trialing', 'past_due') AND current_period_end <= NOW()
+                ORDER BY current_period_end
+                LIMIT $1
+                "#,
+                RENEWAL_BATCH_SIZE,
+            )
+            .fetch_all(pool)
+            .await?;
+            
+            for subscription_id in due {
+                if let Err(e) = self.renew(pool, subscription_id).await {
+                    warn!("Failed to renew subscription {}: {}", subscription_id, e);
+                }
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn renew(&self, pool: &PgPool, subscription_id: Uuid) -> Result<(), DefiantError> {
+        let mut tx = pool.begin().await?;
+        
+        // Another scheduler instance may have renewed it since it was listed
+        let subscription = sqlx::query_as!(
+            Subscription,
+            r#"
+            SELECT * FROM subscriptions
+            WHERE id = $1 AND status IN ('active', 'trialing', 'past_due') AND current_period_end <= NOW()
+            FOR UPDATE SKIP LOCKED
+            "#,
+            subscription_id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        let subscription = match subscription {
+            Some(subscription) => subscription,
+            None => return Ok(()),
+        };
+        
+        let (renewed, event_type) = if subscription.cancel_at_period_end {
+            (end_subscription(&mut tx, subscription.id, subscription.current_period_end).await?, SUBSCRIPTION_CANCELED)
+        } else {
+            // A scheduled switch applies regardless of the plan having been retired since
+            let plan_id = subscription.pending_plan_id.unwrap_or(subscription.plan_id);
+            let plan = load_plan(&mut tx, plan_id, subscription.merchant_id).await?;
+            let period_start = subscription.current_period_end;
+            
+            let renewed = sqlx::query_as!(
+                Subscription,
+                r#"
+                UPDATE subscriptions
+                SET plan_id = $1, pending_plan_id = NULL,
+                    current_period_start = $2, current_period_end = $3,
+                    status = CASE WHEN status = 'trialing' THEN 'active'::subscription_status ELSE status END
+                WHERE id = $4
+                RETURNING *
+                "#,
+                plan.id,
+                period_start,
+                plan.period_end(period_start),
+                subscription.id,
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            
+            issue_period_invoice(&mut tx, &renewed, &plan).await?;
+            (renewed, SUBSCRIPTION_RENEWED)
+        };
+        
+        tx.commit().await?;
+        
+        info!("Subscription {} reached the end of its period: {}", renewed.id, event_type);
+        let merchant_id = renewed.merchant_id;
+        self.emit(merchant_id, event_type, &SubscriptionResponse::from(renewed)).await;
+        
+        Ok(())
+    }
+    
+    async fn emit(&self, merchant_id: Uuid, event_type: &str, subscription: &SubscriptionResponse) {
+        if let Err(e) = WebhookService::new(self.db.clone())
+            .enqueue_event(merchant_id, event_type, serde_json::json!(subscription))
+            .await
+        {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+}
+
+async fn load_plan(
+    tx: &mut Transaction<'_, Postgres>,
+    plan_id: Uuid,
+    merchant_id: Uuid,
+) -> Result<Plan, DefiantError> {
+    sqlx::query_as!(
+        Plan,
+        r#"SELECT * FROM plans WHERE id = $1 AND merchant_id = $2"#,
+        plan_id,
+        merchant_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| DefiantError::NotFound("Plan not found".into()))
+}
+
+async fn lock_live_subscription(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription_id: Uuid,
+    merchant_id: Uuid,
+) -> Result<Subscription, DefiantError> {
+    let subscription = sqlx::query_as!(
+        Subscription,
+        r#"SELECT * FROM subscriptions WHERE id = $1 AND merchant_id = $2 FOR UPDATE"#,
+        subscription_id,
+        merchant_id,
+    )
+    .fetch_optional(&mut **tx)
+    .await?
+    .ok_or_else(|| DefiantError::NotFound("Subscription not found".into()))?;
+    
+    if matches!(subscription.status, SubscriptionStatus::Canceled | SubscriptionStatus::IncompleteExpired) {
+        return Err(DefiantError::Conflict("Subscription has already ended".into()));
+    }
+    
+    Ok(subscription)
+}
+
+async fn end_subscription(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription_id: Uuid,
+    canceled_at: chrono::DateTime<Utc>,
+) -> Result<Subscription, DefiantError> {
+    let subscription = sqlx::query_as!(
+        Subscription,
+        r#"
+        UPDATE subscriptions
+        SET status = $1, canceled_at = $2, cancel_at_period_end = false, pending_plan_id = NULL
+        WHERE id = $3
+        RETURNING *
+        "#,
+        SubscriptionStatus::Canceled as SubscriptionStatus,
+        canceled_at,
+        subscription_id,
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    
+    Ok(subscription)
+}
+
+/// Bills the subscription's current period at the plan's price.
+async fn issue_period_invoice(
+    tx: &mut Transaction<'_, Postgres>,
+    subscription: &Subscription,
+    plan: &Plan,
+) -> Result<(), DefiantError> {
+    let lines = [InvoiceDraftLine {
+        description: plan.name.clone(),
+        quantity: 1,
+        unit_amount: plan.amount,
+    }];
+    
+    let (invoice, _) = invoice_service::insert_open_invoice(
+        tx,
+        subscription.merchant_id,
+        subscription.customer_id,
+        &plan.currency,
+        plan.description.clone(),
+        Some(serde_json::json!({ "subscription_id": subscription.id })),
+        &lines,
+    )
+    .await?;
+    
+    sqlx::query!(
+        r#"
+        UPDATE invoices SET subscription_id = $1, period_start = $2, period_end = $3
+        WHERE id = $4
+        "#,
+        subscription.id,
+        subscription.current_period_start,
+        subscription.current_period_end,
+        invoice.id,
+    )
+    .execute(&mut **tx)
+    .await?;
+    
+    Ok(())
+}
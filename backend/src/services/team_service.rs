@@ -0,0 +1,378 @@
+use std::sync::Arc;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier, password_hash::{rand_core::OsRng, SaltString}};
+use chrono::{Duration, Utc};
+use sqlx::{Postgres, Transaction};
+use rand::{distributions::Alphanumeric, Rng};
+use uuid::Uuid;
+use tracing::{info, error};
+
+use crate::{models::{TeamMember, TeamRole, InviteTeamMemberRequest, UpdateTeamMemberRequest, TeamMemberListQuery, TeamMemberListResponse, AcceptInvitationRequest, TokenPair}, errors::DefiantError, db::{Database, AuthenticatedKey, hash_api_key}, config::Config};
+use super::auth_service::AuthService;
+use super::email_service::EmailService;
+
+const INVITATION_VALIDITY_DAYS: i64 = 7;
+
+/// Dashboard team membership. Members act with the scopes of their role, checked by
+/// `permission!` like an API key's.
+pub struct TeamService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl TeamService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Emails an invitation link. The member can sign in once they accept it and
+    /// choose a password.
+    pub async fn invite(
+        &self,
+        request: InviteTeamMemberRequest,
+        api_key: &str,
+    ) -> Result<TeamMember, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        require_manage(&caller, request.role)?;
+        
+        let token = generate_invite_token();
+        let member = sqlx::query_as!(
+            TeamMember,
+            r#"
+            INSERT INTO team_members (merchant_id, email, role, invite_token_hash, invite_expires_at, invited_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (merchant_id, email) DO NOTHING
+            RETURNING id, merchant_id, email, role AS "role: TeamRole", password_hash, invite_token_hash,
+                invite_expires_at, invited_by, accepted_at, email_verified_at, created_at, updated_at
+            "#,
+            caller.merchant_id,
+            request.email.to_lowercase(),
+            request.role as TeamRole,
+            hash_api_key(&token),
+            Utc::now() + Duration::days(INVITATION_VALIDITY_DAYS),
+            caller.id.to_string(),
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict(format!("{} is already on the team", request.email)))?;
+        
+        let url = format!(
+            "{}/invitations/accept?token={}",
+            self.config.dashboard_base_url.trim_end_matches('/'),
+            token,
+        );
+        let body = format!(
+            "<p>You have been invited to join a Defiant account as {:?}.</p>\
+             <p><a href=\"{}\">Accept the invitation</a> within {} days to set your password.</p>",
+            member.role, url, INVITATION_VALIDITY_DAYS
+        );
+        let email_service = EmailService::new(self.db.clone(), self.config.clone());
+        if let Err(e) = email_service.send(&member.email, "You have been invited to Defiant", body).await {
+            error!("Failed to send invitation for team member {}: {}", member.id, e);
+        }
+        
+        info!("Team member {} invited to merchant {}", member.id, caller.merchant_id);
+        Ok(member)
+    }
+    
+    pub async fn list(
+        &self,
+        query: TeamMemberListQuery,
+        api_key: &str,
+    ) -> Result<TeamMemberListResponse, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let limit = query.limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            TeamMember,
+            r#"
+            SELECT id, merchant_id, email, role AS "role: TeamRole", password_hash, invite_token_hash,
+                invite_expires_at, invited_by, accepted_at, email_verified_at, created_at, updated_at
+            FROM team_members
+            WHERE merchant_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+            merchant_id,
+            limit + 1,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(TeamMemberListResponse {
+            data,
+            has_more,
+            url: "/api/v1/team/members".into(),
+        })
+    }
+    
+    /// Changes a member's role. Their sessions are revoked so the new role applies
+    /// from their next sign-in.
+    pub async fn update_role(
+        &self,
+        member_id: Uuid,
+        request: UpdateTeamMemberRequest,
+        api_key: &str,
+    ) -> Result<TeamMember, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        let mut tx = self.db.pool.begin().await?;
+        
+        let current = self.lock_member(&mut tx, caller.merchant_id, member_id).await?;
+        require_manage(&caller, current.role)?;
+        require_manage(&caller, request.role)?;
+        if current.role == TeamRole::Owner && request.role != TeamRole::Owner {
+            self.require_other_owner(&mut tx, caller.merchant_id, member_id).await?;
+        }
+        
+        let member = sqlx::query_as!(
+            TeamMember,
+            r#"
+            UPDATE team_members SET role = $2 WHERE id = $1
+            RETURNING id, merchant_id, email, role AS "role: TeamRole", password_hash, invite_token_hash,
+                invite_expires_at, invited_by, accepted_at, email_verified_at, created_at, updated_at
+            "#,
+            member_id,
+            request.role as TeamRole,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        
+        AuthService::new(self.db.clone(), self.config.clone())
+            .revoke_subject(&member.id.to_string())
+            .await?;
+        
+        info!("Team member {} is now {:?}", member.id, member.role);
+        Ok(member)
+    }
+    
+    /// Removes a member, or withdraws a pending invitation, and ends their sessions.
+    pub async fn remove(&self, member_id: Uuid, api_key: &str) -> Result<TeamMember, DefiantError> {
+        let caller = self.db.authenticate_key(api_key).await?;
+        let mut tx = self.db.pool.begin().await?;
+        
+        let member = self.lock_member(&mut tx, caller.merchant_id, member_id).await?;
+        require_manage(&caller, member.role)?;
+        if member.role == TeamRole::Owner {
+            self.require_other_owner(&mut tx, caller.merchant_id, member_id).await?;
+        }
+        
+        sqlx::query!(r#"DELETE FROM team_members WHERE id = $1"#, member_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        
+        AuthService::new(self.db.clone(), self.config.clone())
+            .revoke_subject(&member.id.to_string())
+            .await?;
+        
+        info!("Team member {} removed from merchant {}", member.id, caller.merchant_id);
+        Ok(member)
+    }
+    
+    /// Sets the member's password and signs them in, under the merchant's MFA policy
+    /// like any other sign-in.
+    pub async fn accept_invitation(&self, request: AcceptInvitationRequest) -> Result<TokenPair, DefiantError> {
+        let password_hash = hash_password(request.password).await?;
+        
+        let member = sqlx::query_as!(
+            TeamMember,
+            r#"
+            UPDATE team_members
+            SET password_hash = $2, accepted_at = NOW(), email_verified_at = NOW(),
+                invite_token_hash = NULL, invite_expires_at = NULL
+            WHERE invite_token_hash = $1 AND invite_expires_at > NOW()
+            RETURNING id, merchant_id, email, role AS "role: TeamRole", password_hash, invite_token_hash,
+                invite_expires_at, invited_by, accepted_at, email_verified_at, created_at, updated_at
+            "#,
+            hash_api_key(&request.token),
+            password_hash,
+        )
+        .fetch_optional(&self.db.pool)
+        .await?
+        .ok_or_else(|| DefiantError::ValidationError("Invitation is invalid or has expired".into()))?;
+        
+        info!("Team member {} accepted their invitation", member.id);
+        AuthService::new(self.db.clone(), self.config.clone())
+            .start_member_session(&member, None, false, None, "invitation")
+            .await
+    }
+    
+    /// The accepted member signing in with this email, within `merchant_id` when the
+    /// email is on several teams.
+    pub(crate) async fn find_for_sign_in(
+        &self,
+        email: &str,
+        merchant_id: Option<Uuid>,
+    ) -> Result<Option<TeamMember>, DefiantError> {
+        let mut members = sqlx::query_as!(
+            TeamMember,
+            r#"
+            SELECT t.id, t.merchant_id, t.email, t.role AS "role: TeamRole", t.password_hash, t.invite_token_hash,
+                t.invite_expires_at, t.invited_by, t.accepted_at, t.email_verified_at, t.created_at, t.updated_at
+            FROM team_members t
+            JOIN merchants m ON m.id = t.merchant_id
+            WHERE t.email = $1 AND t.accepted_at IS NOT NULL AND m.active = true
+            AND ($2::UUID IS NULL OR t.merchant_id = $2)
+            "#,
+            email.to_lowercase(),
+            merchant_id,
+        )
+        .fetch_all(&self.db.pool)
+        .await?;
+        
+        if members.len() > 1 {
+            return Err(DefiantError::ValidationError("This email is on several teams; pass merchant_id".into()));
+        }
+        Ok(members.pop())
+    }
+    
+    async fn lock_member(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<TeamMember, DefiantError> {
+        sqlx::query_as!(
+            TeamMember,
+            r#"
+            SELECT id, merchant_id, email, role AS "role: TeamRole", password_hash, invite_token_hash,
+                invite_expires_at, invited_by, accepted_at, email_verified_at, created_at, updated_at
+            FROM team_members
+            WHERE id = $1 AND merchant_id = $2
+            FOR UPDATE
+            "#,
+            member_id,
+            merchant_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Team member not found".into()))
+    }
+    
+    /// A team cannot lose its last accepted owner.
+    async fn require_other_owner(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<(), DefiantError> {
+        let others = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM team_members
+            WHERE merchant_id = $1 AND id <> $2 AND role = 'owner' AND accepted_at IS NOT NULL
+            "#,
+            merchant_id,
+            member_id,
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+        
+        if others == 0 {
+            return Err(DefiantError::Conflict("The team must keep at least one owner".into()));
+        }
+        Ok(())
+    }
+}
+
+/// Only owners, and full-access API keys, can grant, change or remove the owner role.
+/// Restricted keys and OAuth tokens carry a scopes list, so holding `team:write` is
+/// not enough.
+fn require_manage(caller: &AuthenticatedKey, role: TeamRole) -> Result<(), DefiantError> {
+    let full_access_key = caller.team_role.is_none()
+        && caller.permissions.as_ref().and_then(|permissions| permissions.get("scopes")).is_none();
+    let caller_is_owner = caller.team_role == Some(TeamRole::Owner) || full_access_key;
+    if role == TeamRole::Owner && !caller_is_owner {
+        return Err(DefiantError::AuthorizationError("Only owners can manage owners".into()));
+    }
+    Ok(())
+}
+
+fn generate_invite_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
+
+/// Argon2id, off the async runtime since hashing is deliberately slow.
+pub(crate) async fn hash_password(password: String) -> Result<String, DefiantError> {
+    tokio::task::spawn_blocking(move || {
+        Argon2::default()
+            .hash_password(password.as_bytes(), &SaltString::generate(&mut OsRng))
+            .map(|hash| hash.to_string())
+    })
+    .await
+    .map_err(|_| DefiantError::InternalError)?
+    .map_err(|_| DefiantError::InternalError)
+}
+
+pub(crate) async fn verify_password(password: String, password_hash: String) -> Result<bool, DefiantError> {
+    tokio::task::spawn_blocking(move || {
+        PasswordHash::new(&password_hash)
+            .map(|parsed| Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok())
+            .unwrap_or(false)
+    })
+    .await
+    .map_err(|_| DefiantError::InternalError)
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    
+    use super::*;
+    use crate::test_support;
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn restricted_key_cannot_invite_an_owner(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        let restricted = test_support::restricted_key(&pool, merchant_id, &["team:write"]).await;
+        let team = TeamService::new(Arc::new(Database::from_pool(pool.clone())), Arc::new(test_support::config()));
+        
+        let request = InviteTeamMemberRequest { email: "new-owner@example.com".into(), role: TeamRole::Owner };
+        let invited = team.invite(request, &restricted).await;
+        assert!(matches!(invited, Err(DefiantError::AuthorizationError(_))));
+        
+        let members = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM team_members WHERE merchant_id = $1"#, merchant_id)
+            .fetch_one(&pool)
+            .await
+            .expect("members counted");
+        assert_eq!(members, 0);
+    }
+    
+    #[sqlx::test(migrations = "src/migrations")]
+    async fn accepting_under_required_mfa_only_allows_enrollment(pool: PgPool) {
+        let merchant_id = test_support::merchant(&pool).await;
+        sqlx::query!(r#"UPDATE merchants SET mfa_required = true WHERE id = $1"#, merchant_id)
+            .execute(&pool)
+            .await
+            .expect("MFA required");
+        sqlx::query!(
+            r#"
+            INSERT INTO team_members (merchant_id, email, role, invite_token_hash, invite_expires_at)
+            VALUES ($1, 'member@example.com', 'developer', $2, NOW() + INTERVAL '1 day')
+            "#,
+            merchant_id,
+            hash_api_key("invite-token"),
+        )
+        .execute(&pool)
+        .await
+        .expect("member invited");
+        let team = TeamService::new(Arc::new(Database::from_pool(pool.clone())), Arc::new(test_support::config()));
+        
+        let request = AcceptInvitationRequest { token: "invite-token".into(), password: "a long enough password".into() };
+        team.accept_invitation(request).await.expect("invitation accepted");
+        
+        let session = sqlx::query!(r#"SELECT role, team_role::TEXT AS team_role FROM auth_sessions WHERE merchant_id = $1"#, merchant_id)
+            .fetch_one(&pool)
+            .await
+            .expect("session started");
+        assert_eq!(session.role, crate::services::auth_service::MFA_ENROLLMENT_ROLE);
+        assert_eq!(session.team_role, None);
+    }
+}
@@ -0,0 +1,198 @@
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{models::{CardEncryptionScheme, EncryptedCardPresentData, Payment, RegisterTerminalKeyRequest, TerminalEncryptionKey, TerminalKeyStatus}, errors::DefiantError, db::Database, config::Config};
+
+const PROCESSOR_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_KEY_GRACE_DAYS: i64 = 7;
+
+/// What the processor tells us about a card-present authorization.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProcessorAuthorization {
+    pub approved: bool,
+    pub network_transaction_id: Option<String>,
+    pub decline_code: Option<String>,
+    /// EMV issuer script / ARPC for the reader to complete the chip transaction, hex
+    pub emv_response: Option<String>,
+}
+
+/// Card-present payments from EMV readers.
+///
+/// Card data is encrypted on the reader under a DUKPT key the processor holds.
+/// Defiant checks the key metadata and forwards the ciphertext untouched; it is
+/// never decrypted, logged, or stored here.
+pub struct TerminalService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl TerminalService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Registers a newly injected key set. Any active key of the same scheme starts
+    /// retiring, so readers not yet re-keyed keep working through the grace period.
+    pub async fn register_key(
+        &self,
+        request: RegisterTerminalKeyRequest,
+        api_key: &str,
+    ) -> Result<TerminalEncryptionKey, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let retire_after = Utc::now() + Duration::days(request.grace_period_days.unwrap_or(DEFAULT_KEY_GRACE_DAYS));
+        
+        sqlx::query!(
+            r#"
+            UPDATE terminal_encryption_keys
+            SET status = $1, retire_after = $2
+            WHERE merchant_id = $3 AND scheme = $4 AND status = 'active'
+            "#,
+            TerminalKeyStatus::Retiring as TerminalKeyStatus,
+            retire_after,
+            merchant_id,
+            request.scheme as CardEncryptionScheme,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        let key = sqlx::query_as!(
+            TerminalEncryptionKey,
+            r#"
+            INSERT INTO terminal_encryption_keys (merchant_id, key_set_id, scheme)
+            VALUES ($1, $2, $3)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.key_set_id,
+            request.scheme as CardEncryptionScheme,
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(db) if db.is_unique_violation() => {
+                DefiantError::Conflict("Key set is already registered".into())
+            }
+            e => e.into(),
+        })?;
+        
+        tx.commit().await?;
+        
+        info!("Terminal key set {} registered for merchant {}", key.key_set_id, merchant_id);
+        Ok(key)
+    }
+    
+    pub async fn list_keys(&self, api_key: &str) -> Result<Vec<TerminalEncryptionKey>, DefiantError> {
+        let merchant_id = self.db.authenticate_merchant(api_key).await?;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let keys = sqlx::query_as!(
+            TerminalEncryptionKey,
+            r#"
+            SELECT * FROM terminal_encryption_keys
+            WHERE merchant_id = $1
+            ORDER BY activated_at DESC
+            "#,
+            merchant_id,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        Ok(keys)
+    }
+    
+    /// Checks the payload was encrypted under a key set that is still accepted,
+    /// retiring keys whose grace period has run out along the way.
+    pub async fn resolve_key(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        data: &EncryptedCardPresentData,
+    ) -> Result<TerminalEncryptionKey, DefiantError> {
+        let key = sqlx::query_as!(
+            TerminalEncryptionKey,
+            r#"
+            UPDATE terminal_encryption_keys
+            SET status = CASE
+                    WHEN status = 'retiring' AND retire_after <= NOW() THEN 'retired'::terminal_key_status
+                    ELSE status
+                END,
+                retired_at = CASE
+                    WHEN status = 'retiring' AND retire_after <= NOW() THEN NOW()
+                    ELSE retired_at
+                END
+            WHERE merchant_id = $1 AND key_set_id = $2
+            RETURNING *
+            "#,
+            merchant_id,
+            data.key_set_id,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(|| DefiantError::ValidationError("Unknown terminal key set".into()))?;
+        
+        if matches!(key.status, TerminalKeyStatus::Retired) {
+            warn!("Card-present payload under retired key set {} rejected", key.key_set_id);
+            return Err(DefiantError::PaymentError("Reader is using a retired encryption key; re-key the reader".into()));
+        }
+        
+        Ok(key)
+    }
+    
+    /// Hands the encrypted payload to the processor, which decrypts and authorizes it.
+    pub async fn authorize(
+        &self,
+        payment: &Payment,
+        key: &TerminalEncryptionKey,
+        data: &EncryptedCardPresentData,
+    ) -> Result<ProcessorAuthorization, DefiantError> {
+        let url = self.config.card_present_processor_url.as_deref().ok_or_else(|| {
+            DefiantError::PaymentError("Card-present processing is not configured".into())
+        })?;
+        
+        let client = reqwest::Client::builder()
+            .timeout(StdDuration::from_secs(PROCESSOR_TIMEOUT_SECS))
+            .build()
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let response = client
+            .post(url)
+            .json(&serde_json::json!({
+                "reference": payment.id,
+                "amount": payment.amount,
+                "currency": payment.currency,
+                "entry_mode": data.entry_mode,
+                "encryption": {
+                    "scheme": key.scheme,
+                    "key_set_id": key.key_set_id,
+                    "ksn": data.ksn,
+                },
+                "encrypted_track2": data.encrypted_track2,
+                "encrypted_pin_block": data.encrypted_pin_block,
+                "pin_block_format": data.pin_block_format,
+                "emv_data": data.emv_data,
+            }))
+            .send()
+            .await
+            .map_err(|e| DefiantError::PaymentError(format!("Card-present processor unreachable: {}", e)))?;
+        
+        if !response.status().is_success() {
+            return Err(DefiantError::PaymentError(format!(
+                "Card-present processor responded with HTTP {}",
+                response.status().as_u16()
+            )));
+        }
+        
+        response
+            .json::<ProcessorAuthorization>()
+            .await
+            .map_err(|e| DefiantError::PaymentError(format!("Invalid card-present processor response: {}", e)))
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::info;
+
+use crate::{models::{CardBrand, CardDetails, CardToken, CardTokenResponse, CreateCardTokenRequest}, errors::DefiantError, db::Database, config::Config};
+use super::webhook_service::SecretCipher;
+
+/// A token not redeemed by a payment within this long stops working.
+const TOKEN_TTL_MINUTES: i64 = 15;
+
+/// Client-side card tokenization, so card numbers go from the customer's browser or
+/// app to us without passing through the merchant's servers.
+pub struct TokenService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl TokenService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    pub async fn create_card_token(
+        &self,
+        request: CreateCardTokenRequest,
+        api_key: &str,
+    ) -> Result<CardTokenResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let pool = self.db.pool_for_merchant(key.merchant_id).await?;
+        let card = request.card;
+        
+        let sealed = serde_json::to_string(&card).map_err(|_| DefiantError::InternalError)?;
+        let token = sqlx::query_as!(
+            CardToken,
+            r#"
+            INSERT INTO card_tokens (
+                merchant_id, livemode, encrypted_card, brand, last4,
+                exp_month, exp_year, issuer_country, expires_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            RETURNING *
+            "#,
+            key.merchant_id,
+            key.livemode,
+            self.cipher()?.seal(&sealed)?,
+            CardBrand::from_number(&card.number).as_str(),
+            &card.number[card.number.len().saturating_sub(4)..],
+            card.exp_month as i16,
+            card.exp_year as i16,
+            card.issuer_country.as_ref().map(|country| country.to_uppercase()),
+            Utc::now() + Duration::minutes(TOKEN_TTL_MINUTES),
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Card token {} created for merchant {}", token.id, key.merchant_id);
+        Ok(token.into())
+    }
+    
+    /// Marks the token used and returns the card it holds, inside the transaction
+    /// that charges it, so a payment that fails to be created leaves the token usable.
+    pub(crate) async fn redeem(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        merchant_id: Uuid,
+        livemode: bool,
+        token: &str,
+    ) -> Result<CardDetails, DefiantError> {
+        let invalid = || DefiantError::ValidationError("Card token is invalid, expired or already used".into());
+        let token_id = token
+            .strip_prefix("tok_")
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or_else(invalid)?;
+        
+        let sealed = sqlx::query_scalar!(
+            r#"
+            UPDATE card_tokens SET used_at = NOW()
+            WHERE id = $1 AND merchant_id = $2 AND livemode = $3
+            AND used_at IS NULL AND expires_at > NOW()
+            RETURNING encrypted_card
+            "#,
+            token_id,
+            merchant_id,
+            livemode,
+        )
+        .fetch_optional(&mut **tx)
+        .await?
+        .ok_or_else(invalid)?;
+        
+        let card = self.cipher()?.open(&sealed)?;
+        serde_json::from_str(&card).map_err(|_| DefiantError::InternalError)
+    }
+    
+    fn cipher(&self) -> Result<SecretCipher, DefiantError> {
+        SecretCipher::from_hex(&self.config.card_token_encryption_key)
+    }
+}
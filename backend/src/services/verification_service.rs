@@ -0,0 +1,308 @@
+use std::sync::Arc;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+use tracing::{info, warn, error};
+
+use crate::{models::{AccountToken, AccountTokenPurpose, ForgotPasswordRequest, ResetPasswordRequest, VerifyEmailRequest, EmailVerificationStatus}, errors::DefiantError, db::{Database, hash_api_key}, config::Config};
+use super::auth_service::AuthService;
+use super::email_service::EmailService;
+use super::team_service::{TeamService, hash_password};
+
+const PASSWORD_RESET_TTL_MINUTES: i64 = 60;
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 24;
+/// Tokens of one purpose an account can be sent per hour
+const MAX_TOKENS_PER_HOUR: i64 = 5;
+
+/// Whose email a verification is for.
+#[derive(Debug, Clone, Copy)]
+pub enum EmailOwner {
+    TeamMember(Uuid),
+    Merchant(Uuid),
+}
+
+impl EmailOwner {
+    fn subject(&self) -> String {
+        match self {
+            EmailOwner::TeamMember(id) => format!("team_member:{}", id),
+            EmailOwner::Merchant(id) => format!("merchant:{}", id),
+        }
+    }
+    
+    fn from_subject(subject: &str) -> Option<Self> {
+        let (kind, id) = subject.split_once(':')?;
+        let id = Uuid::parse_str(id).ok()?;
+        match kind {
+            "team_member" => Some(EmailOwner::TeamMember(id)),
+            "merchant" => Some(EmailOwner::Merchant(id)),
+            _ => None,
+        }
+    }
+}
+
+/// Password resets and email verification: single-use, expiring tokens emailed to
+/// the address being proven.
+pub struct VerificationService {
+    db: Arc<Database>,
+    config: Arc<Config>,
+}
+
+impl VerificationService {
+    pub fn new(db: Arc<Database>, config: Arc<Config>) -> Self {
+        Self { db, config }
+    }
+    
+    /// Emails a reset link to a team member. Unknown emails, and members over the
+    /// hourly limit, get the same answer and no email, so the endpoint cannot be used
+    /// to learn who is on a team.
+    pub async fn forgot_password(&self, request: ForgotPasswordRequest) -> Result<(), DefiantError> {
+        let member = TeamService::new(self.db.clone(), self.config.clone())
+            .find_for_sign_in(&request.email, request.merchant_id)
+            .await?;
+        let Some(member) = member else {
+            return Ok(());
+        };
+        
+        let owner = EmailOwner::TeamMember(member.id);
+        let Some(token) = self.issue(AccountTokenPurpose::PasswordReset, owner, &member.email, Duration::minutes(PASSWORD_RESET_TTL_MINUTES)).await? else {
+            warn!("Password reset limit reached for team member {}", member.id);
+            return Ok(());
+        };
+        
+        let url = format!("{}/password/reset?token={}", self.config.dashboard_base_url.trim_end_matches('/'), token);
+        let body = format!(
+            "<p>Someone asked to reset the password of your Defiant account.</p>\
+             <p><a href=\"{}\">Choose a new password</a> within {} minutes. If this was not you, ignore this email.</p>",
+            url, PASSWORD_RESET_TTL_MINUTES
+        );
+        if let Err(e) = self.email().send(&member.email, "Reset your Defiant password", body).await {
+            error!("Failed to send password reset for team member {}: {}", member.id, e);
+        }
+        
+        info!("Password reset sent to team member {}", member.id);
+        Ok(())
+    }
+    
+    /// Sets the new password and signs the member out everywhere.
+    pub async fn reset_password(&self, request: ResetPasswordRequest) -> Result<(), DefiantError> {
+        let invalid = || DefiantError::ValidationError("Reset link is invalid or has expired".into());
+        let password_hash = hash_password(request.password).await?;
+        let mut tx = self.db.pool.begin().await?;
+        
+        let token = self.consume(&mut tx, AccountTokenPurpose::PasswordReset, &request.token).await?
+            .ok_or_else(invalid)?;
+        let Some(EmailOwner::TeamMember(member_id)) = EmailOwner::from_subject(&token.subject) else {
+            return Err(invalid());
+        };
+        
+        let updated = sqlx::query!(
+            r#"
+            UPDATE team_members SET password_hash = $3
+            WHERE id = $1 AND email = $2 AND accepted_at IS NOT NULL
+            "#,
+            member_id,
+            token.email,
+            password_hash,
+        )
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(invalid());
+        }
+        tx.commit().await?;
+        
+        AuthService::new(self.db.clone(), self.config.clone())
+            .revoke_subject(&member_id.to_string())
+            .await?;
+        
+        info!("Password reset for team member {}", member_id);
+        Ok(())
+    }
+    
+    /// Emails a verification link to the owner's current address.
+    pub async fn send_email_verification(&self, owner: EmailOwner) -> Result<EmailVerificationStatus, DefiantError> {
+        let status = self.email_status(owner).await?;
+        if status.verified_at.is_some() {
+            return Err(DefiantError::Conflict("Email is already verified".into()));
+        }
+        
+        let token = self.issue(AccountTokenPurpose::EmailVerification, owner, &status.email, Duration::hours(EMAIL_VERIFICATION_TTL_HOURS)).await?
+            .ok_or(DefiantError::RateLimitError)?;
+        
+        let url = format!("{}/email/verify?token={}", self.config.dashboard_base_url.trim_end_matches('/'), token);
+        let body = format!(
+            "<p>Confirm this is the email address of your Defiant account.</p>\
+             <p><a href=\"{}\">Verify your email</a> within {} hours.</p>",
+            url, EMAIL_VERIFICATION_TTL_HOURS
+        );
+        self.email().send(&status.email, "Verify your email for Defiant", body).await?;
+        
+        info!("Email verification sent for {}", owner.subject());
+        Ok(status)
+    }
+    
+    pub async fn verify_email(&self, request: VerifyEmailRequest) -> Result<EmailVerificationStatus, DefiantError> {
+        let invalid = || DefiantError::ValidationError("Verification link is invalid or has expired".into());
+        let mut tx = self.db.pool.begin().await?;
+        
+        let token = self.consume(&mut tx, AccountTokenPurpose::EmailVerification, &request.token).await?
+            .ok_or_else(invalid)?;
+        let owner = EmailOwner::from_subject(&token.subject).ok_or_else(invalid)?;
+        
+        // The address must still be the one the link was sent to
+        let verified = match owner {
+            EmailOwner::TeamMember(id) => sqlx::query_as!(
+                EmailVerificationStatus,
+                r#"
+                UPDATE team_members SET email_verified_at = NOW()
+                WHERE id = $1 AND email = $2
+                RETURNING email, email_verified_at AS verified_at
+                "#,
+                id,
+                token.email,
+            )
+            .fetch_optional(&mut *tx)
+            .await?,
+            EmailOwner::Merchant(id) => sqlx::query_as!(
+                EmailVerificationStatus,
+                r#"
+                UPDATE merchants SET email_verified_at = NOW()
+                WHERE id = $1 AND email = $2
+                RETURNING email, email_verified_at AS verified_at
+                "#,
+                id,
+                token.email,
+            )
+            .fetch_optional(&mut *tx)
+            .await?,
+        }
+        .ok_or_else(invalid)?;
+        tx.commit().await?;
+        
+        info!("Email verified for {}", token.subject);
+        Ok(verified)
+    }
+    
+    async fn email_status(&self, owner: EmailOwner) -> Result<EmailVerificationStatus, DefiantError> {
+        let status = match owner {
+            EmailOwner::TeamMember(id) => sqlx::query_as!(
+                EmailVerificationStatus,
+                r#"SELECT email, email_verified_at AS verified_at FROM team_members WHERE id = $1"#,
+                id,
+            )
+            .fetch_optional(&self.db.pool)
+            .await?,
+            EmailOwner::Merchant(id) => sqlx::query_as!(
+                EmailVerificationStatus,
+                r#"SELECT email, email_verified_at AS verified_at FROM merchants WHERE id = $1"#,
+                id,
+            )
+            .fetch_optional(&self.db.pool)
+            .await?,
+        };
+        
+        status.ok_or_else(|| DefiantError::NotFound("Account not found".into()))
+    }
+    
+    /// A new token, replacing any unused one of the same purpose, or `None` once the
+    /// account has been sent `MAX_TOKENS_PER_HOUR` in the last hour.
+    async fn issue(
+        &self,
+        purpose: AccountTokenPurpose,
+        owner: EmailOwner,
+        email: &str,
+        ttl: Duration,
+    ) -> Result<Option<String>, DefiantError> {
+        let subject = owner.subject();
+        let mut tx = self.db.pool.begin().await?;
+        
+        // Locking the account serializes issuing, so concurrent requests cannot exceed the limit
+        match owner {
+            EmailOwner::TeamMember(id) => sqlx::query_scalar!(r#"SELECT id FROM team_members WHERE id = $1 FOR UPDATE"#, id)
+                .fetch_one(&mut *tx)
+                .await?,
+            EmailOwner::Merchant(id) => sqlx::query_scalar!(r#"SELECT id FROM merchants WHERE id = $1 FOR UPDATE"#, id)
+                .fetch_one(&mut *tx)
+                .await?,
+        };
+        
+        let recent = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "count!" FROM account_tokens
+            WHERE subject = $1 AND purpose = $2 AND created_at > NOW() - INTERVAL '1 hour'
+            "#,
+            subject,
+            purpose as AccountTokenPurpose,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        if recent >= MAX_TOKENS_PER_HOUR {
+            return Ok(None);
+        }
+        
+        sqlx::query!(
+            r#"
+            UPDATE account_tokens SET expires_at = NOW()
+            WHERE subject = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+            "#,
+            subject,
+            purpose as AccountTokenPurpose,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        let token = generate_token();
+        sqlx::query!(
+            r#"
+            INSERT INTO account_tokens (purpose, subject, email, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+            purpose as AccountTokenPurpose,
+            subject,
+            email,
+            hash_api_key(&token),
+            Utc::now() + ttl,
+        )
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        
+        Ok(Some(token))
+    }
+    
+    async fn consume(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        purpose: AccountTokenPurpose,
+        token: &str,
+    ) -> Result<Option<AccountToken>, DefiantError> {
+        let token = sqlx::query_as!(
+            AccountToken,
+            r#"
+            UPDATE account_tokens SET used_at = NOW()
+            WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > NOW()
+            RETURNING id, purpose AS "purpose: AccountTokenPurpose", subject, email, token_hash,
+                expires_at, used_at, created_at
+            "#,
+            hash_api_key(token),
+            purpose as AccountTokenPurpose,
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+        
+        Ok(token)
+    }
+    
+    fn email(&self) -> EmailService {
+        EmailService::new(self.db.clone(), self.config.clone())
+    }
+}
+
+fn generate_token() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
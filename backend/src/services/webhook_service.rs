@@ -0,0 +1,1194 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+use sqlx::PgPool;
+use uuid::Uuid;
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::{aead, hmac, rand::{SecureRandom, SystemRandom}};
+use tracing::{info, warn, error};
+
+use crate::{models::{Webhook, WebhookDelivery, WebhookDeliveryStatus, WebhookDeliveryAttempt, WebhookDeliveryLog, WebhookDeliveryListResponse, CreateWebhookRequest, UpdateWebhookRequest, WebhookResponse, WebhookListResponse, Event, ConfigurationChangeData, WEBHOOK_API_VERSIONS, WEBHOOK_ENDPOINT_CREATED, WEBHOOK_ENDPOINT_UPDATED, WEBHOOK_ENDPOINT_SECRET_ROTATED, WEBHOOK_ENDPOINT_DELETED, current_webhook_api_version, is_valid_event_filter, html_escape}, errors::DefiantError, db::{Database, hash_api_key}, config::Config};
+use crate::shutdown::Shutdown;
+use super::{email_service::EmailService, event_renderer, dependency_service::{self, Dependent}};
+
+pub const SIGNATURE_HEADER: &str = "Defiant-Signature";
+pub const VERSION_HEADER: &str = "Defiant-Version";
+
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+const IDLE_POLL_INTERVAL_MS: u64 = 1000;
+/// How long a claimed delivery stays leased before another worker may pick it up again.
+const CLAIM_LEASE_SECS: i64 = 300;
+/// First retry comes a minute after the initial failure, doubling from there.
+const RETRY_BASE_DELAY_SECS: i64 = 60;
+const RETRY_MAX_DELAY_SECS: i64 = 12 * 60 * 60;
+/// Deliveries still failing this long after the event are dead-lettered.
+const RETRY_WINDOW_DAYS: i64 = 3;
+/// How long the old secret keeps signing after a rotation.
+const SECRET_ROTATION_OVERLAP_HOURS: i64 = 24;
+/// Response bodies are kept only up to this many characters in the attempt log.
+const RESPONSE_BODY_LOG_LIMIT: usize = 2048;
+/// An endpoint is disabled once it has failed this many times in a row...
+const AUTO_DISABLE_MIN_FAILURES: i32 = 20;
+/// ...without a single success for this long.
+const AUTO_DISABLE_AFTER_DAYS: i64 = 3;
+
+/// Seals endpoint signing secrets at rest with AES-256-GCM.
+///
+/// Stored values are hex-encoded `nonce || ciphertext || tag`.
+#[derive(Clone)]
+pub struct SecretCipher {
+    key: Arc<aead::LessSafeKey>,
+    rng: SystemRandom,
+}
+
+impl SecretCipher {
+    pub fn from_hex(key_hex: &str) -> Result<Self, DefiantError> {
+        let bytes = hex::decode(key_hex)
+            .map_err(|_| DefiantError::BadRequest("Webhook encryption key must be hex-encoded".into()))?;
+        let unbound = aead::UnboundKey::new(&aead::AES_256_GCM, &bytes)
+            .map_err(|_| DefiantError::BadRequest("Webhook encryption key must be 32 bytes".into()))?;
+        
+        Ok(Self {
+            key: Arc::new(aead::LessSafeKey::new(unbound)),
+            rng: SystemRandom::new(),
+        })
+    }
+    
+    pub fn seal(&self, plaintext: &str) -> Result<String, DefiantError> {
+        let mut nonce_bytes = [0u8; aead::NONCE_LEN];
+        self.rng.fill(&mut nonce_bytes).map_err(|_| DefiantError::InternalError)?;
+        
+        let mut in_out = plaintext.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(aead::Nonce::assume_unique_for_key(nonce_bytes), aead::Aad::empty(), &mut in_out)
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        let mut sealed = nonce_bytes.to_vec();
+        sealed.extend_from_slice(&in_out);
+        Ok(hex::encode(sealed))
+    }
+    
+    pub fn open(&self, sealed_hex: &str) -> Result<String, DefiantError> {
+        let sealed = hex::decode(sealed_hex).map_err(|_| DefiantError::InternalError)?;
+        if sealed.len() < aead::NONCE_LEN {
+            return Err(DefiantError::InternalError);
+        }
+        
+        let (nonce_bytes, ciphertext) = sealed.split_at(aead::NONCE_LEN);
+        let nonce = aead::Nonce::try_assume_unique_for_key(nonce_bytes).map_err(|_| DefiantError::InternalError)?;
+        let mut in_out = ciphertext.to_vec();
+        let plaintext = self.key
+            .open_in_place(nonce, aead::Aad::empty(), &mut in_out)
+            .map_err(|_| DefiantError::InternalError)?;
+        
+        String::from_utf8(plaintext.to_vec()).map_err(|_| DefiantError::InternalError)
+    }
+}
+
+/// Builds the `Defiant-Signature` header value: `t=<unix seconds>,v1=<hex HMAC-SHA256>`.
+///
+/// The MAC covers `"<t>.<body>"` so receivers can reject replays outside their tolerance window.
+pub fn sign_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    sign_payload_with_secrets(&[secret], timestamp, payload)
+}
+
+/// Like `sign_payload`, with one `v1=` entry per secret. Receivers accept the payload
+/// if any entry matches, which is what lets a secret rotate without dropped events.
+pub fn sign_payload_with_secrets(secrets: &[&str], timestamp: i64, payload: &str) -> String {
+    let signed = format!("{}.{}", timestamp, payload);
+    let mut header = format!("t={}", timestamp);
+    
+    for secret in secrets {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+        let tag = hmac::sign(&key, signed.as_bytes());
+        header.push_str(",v1=");
+        header.push_str(&hex::encode(tag.as_ref()));
+    }
+    
+    header
+}
+
+/// Delay before the next attempt once `attempts` have failed: 1m, 2m, 4m, ... capped at 12h.
+fn retry_delay(attempts: i32) -> Duration {
+    let exponent = (attempts.max(1) - 1).min(20) as u32;
+    let delay = RETRY_BASE_DELAY_SECS.saturating_mul(2i64.pow(exponent));
+    
+    Duration::seconds(delay.min(RETRY_MAX_DELAY_SECS))
+}
+
+fn ensure_valid_events(events: &[String]) -> Result<(), DefiantError> {
+    match events.iter().find(|e| !is_valid_event_filter(e)) {
+        Some(invalid) => Err(DefiantError::ValidationError(format!("events: '{}' is not a valid event type or wildcard", invalid))),
+        None => Ok(()),
+    }
+}
+
+fn ensure_supported_version(version: &str) -> Result<(), DefiantError> {
+    if WEBHOOK_API_VERSIONS.contains(&version) {
+        Ok(())
+    } else {
+        Err(DefiantError::ValidationError(format!(
+            "api_version: '{}' is not supported (expected one of {})",
+            version,
+            WEBHOOK_API_VERSIONS.join(", ")
+        )))
+    }
+}
+
+/// Endpoints must be HTTPS URLs on a named host or a public address. Hosts are
+/// resolved separately, by `ensure_public_endpoint` and as each delivery connects.
+fn parse_endpoint_url(url: &str) -> Result<reqwest::Url, DefiantError> {
+    let parsed = reqwest::Url::parse(url)
+        .map_err(|_| DefiantError::ValidationError("url: Webhook URL must be a valid URL".into()))?;
+    if parsed.scheme() != "https" {
+        return Err(DefiantError::ValidationError("url: Webhook URLs must use https".into()));
+    }
+    let host = parsed.host_str()
+        .ok_or_else(|| DefiantError::ValidationError("url: Webhook URL must have a host".into()))?;
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        if !is_public_ip(ip) {
+            return Err(DefiantError::ValidationError("url: Webhook URLs cannot point to a private or reserved address".into()));
+        }
+    }
+    
+    Ok(parsed)
+}
+
+/// Refuses an endpoint URL unless every address its host resolves to is public, so
+/// webhooks cannot be aimed at the network the workers run in.
+async fn ensure_public_endpoint(url: &str) -> Result<(), DefiantError> {
+    let parsed = parse_endpoint_url(url)?;
+    let host = parsed.host_str().unwrap_or_default();
+    if host.starts_with('[') || host.parse::<IpAddr>().is_ok() {
+        return Ok(());
+    }
+    
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, parsed.port_or_known_default().unwrap_or(443)))
+        .await
+        .map_err(|_| DefiantError::ValidationError(format!("url: '{}' could not be resolved", host)))?
+        .collect();
+    if addrs.is_empty() || addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(DefiantError::ValidationError("url: Webhook URLs cannot point to a private or reserved address".into()));
+    }
+    
+    Ok(())
+}
+
+/// False for loopback, private, shared, link-local (cloud metadata included), unique
+/// local, multicast, broadcast and unspecified addresses.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // 100.64.0.0/10, carrier-grade NAT
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public_ip(IpAddr::V4(mapped)),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_unspecified()
+                    || ip.is_loopback()
+                    || ip.is_multicast()
+                    // fc00::/7, unique local
+                    || (first & 0xfe00) == 0xfc00
+                    // fe80::/10, link-local
+                    || (first & 0xffc0) == 0xfe80)
+            }
+        },
+    }
+}
+
+/// Resolves endpoint hosts for the delivery client, refusing any with an address that
+/// is not public. Checking as the connection is made means a host that passed at
+/// registration cannot be rebound to an internal address later.
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(resolve_public(name))
+    }
+}
+
+async fn resolve_public(name: reqwest::dns::Name) -> Result<reqwest::dns::Addrs, Box<dyn std::error::Error + Send + Sync>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?.collect();
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("{} resolves to a private or reserved address", name.as_str()).into());
+    }
+    
+    Ok(Box::new(addrs.into_iter()))
+}
+
+fn generate_secret() -> String {
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+    
+    format!("whsec_{}", token)
+}
+
+pub struct WebhookService {
+    db: Arc<Database>,
+}
+
+impl WebhookService {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+    
+    pub async fn create_endpoint(
+        &self,
+        request: CreateWebhookRequest,
+        cipher: &SecretCipher,
+        api_key: &str,
+    ) -> Result<WebhookResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        ensure_valid_events(&request.events)?;
+        ensure_public_endpoint(&request.url).await?;
+        let api_version = request.api_version.unwrap_or_else(|| current_webhook_api_version().to_string());
+        ensure_supported_version(&api_version)?;
+        
+        let secret = generate_secret();
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            INSERT INTO webhooks (merchant_id, url, description, encrypted_secret, events, api_version, livemode)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING *
+            "#,
+            merchant_id,
+            request.url,
+            request.description,
+            cipher.seal(&secret)?,
+            &request.events,
+            api_version,
+            key.livemode,
+        )
+        .fetch_one(pool)
+        .await?;
+        
+        info!("Webhook endpoint {} registered for merchant {}", webhook.id, merchant_id);
+        self.emit_configuration_change(
+            merchant_id,
+            WEBHOOK_ENDPOINT_CREATED,
+            ConfigurationChangeData::new("webhook_endpoint", webhook.id, Vec::new()),
+            api_key,
+        )
+        .await;
+        
+        Ok(WebhookResponse { webhook, secret: Some(secret) })
+    }
+    
+    pub async fn get_endpoint(&self, webhook_id: Uuid, api_key: &str) -> Result<Webhook, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        sqlx::query_as!(
+            Webhook,
+            r#"SELECT * FROM webhooks WHERE id = $1 AND merchant_id = $2 AND livemode = $3"#,
+            webhook_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Webhook endpoint not found".into()))
+    }
+    
+    pub async fn list_endpoints(
+        &self,
+        limit: Option<i64>,
+        active: Option<bool>,
+        api_key: &str,
+    ) -> Result<WebhookListResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let limit = limit.unwrap_or(20).clamp(1, 100);
+        
+        // Fetch one extra row to learn whether another page exists
+        let mut data = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT * FROM webhooks
+            WHERE merchant_id = $1 AND livemode = $4 AND ($2::BOOLEAN IS NULL OR active = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            merchant_id,
+            active,
+            limit + 1,
+            key.livemode,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = data.len() as i64 > limit;
+        data.truncate(limit as usize);
+        
+        Ok(WebhookListResponse {
+            data,
+            has_more,
+            url: "/api/v1/webhooks".into(),
+        })
+    }
+    
+    pub async fn update_endpoint(
+        &self,
+        webhook_id: Uuid,
+        request: UpdateWebhookRequest,
+        api_key: &str,
+    ) -> Result<Webhook, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        if let Some(events) = &request.events {
+            ensure_valid_events(events)?;
+        }
+        if let Some(url) = &request.url {
+            ensure_public_endpoint(url).await?;
+        }
+        if let Some(version) = &request.api_version {
+            ensure_supported_version(version)?;
+        }
+        
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET url = COALESCE($3, url),
+                description = COALESCE($4, description),
+                events = COALESCE($5, events),
+                api_version = COALESCE($6, api_version),
+                active = COALESCE($7, active),
+                consecutive_failures = CASE WHEN $7 THEN 0 ELSE consecutive_failures END,
+                failing_since = CASE WHEN $7 THEN NULL ELSE failing_since END,
+                disabled_at = CASE
+                    WHEN $7 THEN NULL
+                    WHEN NOT $7 THEN COALESCE(disabled_at, NOW())
+                    ELSE disabled_at
+                END,
+                disabled_reason = CASE
+                    WHEN $7 THEN NULL
+                    WHEN NOT $7 THEN 'Disabled by merchant'
+                    ELSE disabled_reason
+                END
+            WHERE id = $1 AND merchant_id = $2 AND livemode = $8
+            RETURNING *
+            "#,
+            webhook_id,
+            merchant_id,
+            request.url,
+            request.description,
+            request.events.as_deref(),
+            request.api_version,
+            request.active,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Webhook endpoint not found".into()))?;
+        
+        let changed_fields = [
+            ("url", request.url.is_some()),
+            ("description", request.description.is_some()),
+            ("events", request.events.is_some()),
+            ("api_version", request.api_version.is_some()),
+            ("active", request.active.is_some()),
+        ]
+        .into_iter()
+        .filter(|(_, set)| *set)
+        .map(|(field, _)| field.to_string())
+        .collect();
+        
+        info!("Webhook endpoint {} updated", webhook.id);
+        self.emit_configuration_change(
+            merchant_id,
+            WEBHOOK_ENDPOINT_UPDATED,
+            ConfigurationChangeData::new("webhook_endpoint", webhook.id, changed_fields),
+            api_key,
+        )
+        .await;
+        Ok(webhook)
+    }
+    
+    /// Issues a new signing secret. The old one keeps signing for
+    /// `SECRET_ROTATION_OVERLAP_HOURS`; rotating again inside that window drops the
+    /// oldest secret immediately.
+    pub async fn rotate_secret(
+        &self,
+        webhook_id: Uuid,
+        cipher: &SecretCipher,
+        api_key: &str,
+    ) -> Result<WebhookResponse, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let secret = generate_secret();
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET previous_encrypted_secret = encrypted_secret,
+                previous_secret_expires_at = $3,
+                encrypted_secret = $4
+            WHERE id = $1 AND merchant_id = $2 AND livemode = $5
+            RETURNING *
+            "#,
+            webhook_id,
+            merchant_id,
+            Utc::now() + Duration::hours(SECRET_ROTATION_OVERLAP_HOURS),
+            cipher.seal(&secret)?,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Webhook endpoint not found".into()))?;
+        
+        info!("Signing secret rotated for webhook endpoint {}", webhook.id);
+        self.emit_configuration_change(
+            merchant_id,
+            WEBHOOK_ENDPOINT_SECRET_ROTATED,
+            ConfigurationChangeData::new("webhook_endpoint", webhook.id, vec!["secret".into()]),
+            api_key,
+        )
+        .await;
+        
+        Ok(WebhookResponse { webhook, secret: Some(secret) })
+    }
+    
+    /// Deletes an endpoint along with its historical deliveries. Undelivered events and
+    /// running replays block the delete unless `force` is set.
+    pub async fn delete_endpoint(&self, webhook_id: Uuid, force: bool, api_key: &str) -> Result<(), DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let mut tx = pool.begin().await?;
+        
+        let exists = sqlx::query_scalar!(
+            r#"SELECT id FROM webhooks WHERE id = $1 AND merchant_id = $2 AND livemode = $3 FOR UPDATE"#,
+            webhook_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+        
+        if exists.is_none() {
+            return Err(DefiantError::NotFound("Webhook endpoint not found".into()));
+        }
+        
+        dependency_service::ensure_removable(&mut tx, merchant_id, Dependent::WebhookEndpoint(webhook_id), force).await?;
+        
+        sqlx::query!(
+            r#"DELETE FROM webhooks WHERE id = $1 AND merchant_id = $2"#,
+            webhook_id,
+            merchant_id,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        info!("Webhook endpoint {} deleted", webhook_id);
+        self.emit_configuration_change(
+            merchant_id,
+            WEBHOOK_ENDPOINT_DELETED,
+            ConfigurationChangeData::new("webhook_endpoint", webhook_id, Vec::new()),
+            api_key,
+        )
+        .await;
+        Ok(())
+    }
+    
+    /// Deliveries to an endpoint, newest first, each with its full attempt history.
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        limit: Option<i64>,
+        status: Option<WebhookDeliveryStatus>,
+        api_key: &str,
+    ) -> Result<WebhookDeliveryListResponse, DefiantError> {
+        let webhook = self.get_endpoint(webhook_id, api_key).await?;
+        let pool = self.db.pool_for_merchant(webhook.merchant_id).await?;
+        let limit = limit.unwrap_or(20).clamp(1, 100);
+        
+        let mut deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT * FROM webhook_deliveries
+            WHERE webhook_id = $1 AND ($2::webhook_delivery_status IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3
+            "#,
+            webhook.id,
+            status as Option<WebhookDeliveryStatus>,
+            limit + 1,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let has_more = deliveries.len() as i64 > limit;
+        deliveries.truncate(limit as usize);
+        
+        let delivery_ids: Vec<Uuid> = deliveries.iter().map(|d| d.id).collect();
+        let event_ids: Vec<Uuid> = deliveries.iter().map(|d| d.event_id).collect();
+        
+        let attempts = sqlx::query_as!(
+            WebhookDeliveryAttempt,
+            r#"
+            SELECT * FROM webhook_delivery_attempts
+            WHERE delivery_id = ANY($1)
+            ORDER BY attempt_number, created_at
+            "#,
+            &delivery_ids,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let event_types = sqlx::query!(
+            r#"SELECT id, type AS event_type FROM events WHERE id = ANY($1)"#,
+            &event_ids,
+        )
+        .fetch_all(pool)
+        .await?;
+        
+        let data = deliveries
+            .into_iter()
+            .map(|delivery| {
+                let event_type = event_types
+                    .iter()
+                    .find(|e| e.id == delivery.event_id)
+                    .map(|e| e.event_type.clone())
+                    .unwrap_or_default();
+                let attempt_log = attempts
+                    .iter()
+                    .filter(|a| a.delivery_id == delivery.id)
+                    .cloned()
+                    .collect();
+                WebhookDeliveryLog { delivery, event_type, attempt_log }
+            })
+            .collect();
+        
+        Ok(WebhookDeliveryListResponse {
+            data,
+            has_more,
+            url: format!("/api/v1/webhooks/{}/deliveries", webhook.id),
+        })
+    }
+    
+    /// Queues a delivery for an immediate attempt, whatever state it ended in.
+    ///
+    /// A manual retry gets a single attempt: it is outside the original retry window,
+    /// so a failure dead-letters it again rather than restarting the backoff schedule.
+    pub async fn retry_delivery(&self, delivery_id: Uuid, api_key: &str) -> Result<WebhookDelivery, DefiantError> {
+        let key = self.db.authenticate_key(api_key).await?;
+        let merchant_id = key.merchant_id;
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        
+        let webhook_active = sqlx::query_scalar!(
+            r#"
+            SELECT w.active
+            FROM webhook_deliveries d
+            JOIN webhooks w ON w.id = d.webhook_id
+            WHERE d.id = $1 AND d.merchant_id = $2 AND w.livemode = $3
+            "#,
+            delivery_id,
+            merchant_id,
+            key.livemode,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::NotFound("Webhook delivery not found".into()))?;
+        
+        if !webhook_active {
+            return Err(DefiantError::Conflict("Webhook endpoint is disabled; re-enable it before retrying".into()));
+        }
+        
+        let delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, next_attempt_at = NOW()
+            WHERE id = $2 AND merchant_id = $3 AND status <> 'in_progress'
+            RETURNING *
+            "#,
+            WebhookDeliveryStatus::Pending as WebhookDeliveryStatus,
+            delivery_id,
+            merchant_id,
+        )
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| DefiantError::Conflict("Webhook delivery is already being attempted".into()))?;
+        
+        info!("Webhook delivery {} queued for manual retry", delivery.id);
+        Ok(delivery)
+    }
+    
+    /// Records a change to account configuration, attributed to the key that made it, so
+    /// merchants can spot changes they did not make. The change is already committed by
+    /// the time this runs, so a failure here is logged rather than returned.
+    pub async fn emit_configuration_change(
+        &self,
+        merchant_id: Uuid,
+        event_type: &str,
+        mut change: ConfigurationChangeData,
+        api_key: &str,
+    ) {
+        let mut livemode = true;
+        match sqlx::query!(r#"SELECT id, livemode FROM api_keys WHERE key_hash = $1"#, hash_api_key(api_key))
+            .fetch_optional(&self.db.pool)
+            .await
+        {
+            Ok(actor) => {
+                change.actor_api_key_id = actor.as_ref().map(|actor| actor.id);
+                livemode = actor.map_or(true, |actor| actor.livemode);
+            }
+            Err(e) => warn!("Could not attribute {} to an API key: {}", event_type, e),
+        }
+        
+        if let Err(e) = self.insert_event(merchant_id, event_type, serde_json::json!(change), livemode).await {
+            error!("Failed to enqueue {} event: {}", event_type, e);
+        }
+    }
+    
+    /// Records an event and queues a delivery for every active endpoint subscribed to it.
+    /// The event takes the mode of the object it carries; objects without one, such as
+    /// payouts, only exist in live mode.
+    pub async fn enqueue_event(
+        &self,
+        merchant_id: Uuid,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> Result<Event, DefiantError> {
+        let livemode = data.get("livemode").and_then(|livemode| livemode.as_bool()).unwrap_or(true);
+        self.insert_event(merchant_id, event_type, data, livemode).await
+    }
+    
+    /// Only endpoints of the event's mode receive it.
+    async fn insert_event(
+        &self,
+        merchant_id: Uuid,
+        event_type: &str,
+        data: serde_json::Value,
+        livemode: bool,
+    ) -> Result<Event, DefiantError> {
+        let pool = self.db.pool_for_merchant(merchant_id).await?;
+        let mut tx = pool.begin().await?;
+        
+        let event = sqlx::query_as!(
+            Event,
+            r#"
+            INSERT INTO events (merchant_id, type, data, api_version, livemode)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, merchant_id, type AS event_type, data, api_version, livemode, created_at
+            "#,
+            merchant_id,
+            event_type,
+            data,
+            current_webhook_api_version(),
+            livemode,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (merchant_id, webhook_id, event_id)
+            SELECT merchant_id, id, $2
+            FROM webhooks
+            WHERE merchant_id = $1 AND active = true AND livemode = $4
+            AND EXISTS (
+                SELECT 1 FROM unnest(events) AS filter
+                WHERE filter = '*'
+                OR filter = $3
+                -- `invoice.*` matches `invoice.paid`, `invoice.payment_failed`, ...
+                OR (filter LIKE '%.*' AND $3 LIKE left(filter, -1) || '%')
+            )
+            "#,
+            merchant_id,
+            event.id,
+            event_type,
+            livemode,
+        )
+        .execute(&mut *tx)
+        .await?;
+        
+        tx.commit().await?;
+        
+        Ok(event)
+    }
+}
+
+/// Reads at most `RESPONSE_BODY_LOG_LIMIT` characters of a response so a chatty
+/// endpoint cannot bloat the attempt log.
+async fn read_truncated(mut response: reqwest::Response) -> String {
+    let mut body = Vec::new();
+    while let Ok(Some(chunk)) = response.chunk().await {
+        body.extend_from_slice(&chunk);
+        if body.len() >= RESPONSE_BODY_LOG_LIMIT * 4 {
+            break;
+        }
+    }
+    String::from_utf8_lossy(&body).chars().take(RESPONSE_BODY_LOG_LIMIT).collect()
+}
+
+/// What merchants are shown of a failed request. The underlying error stays in the
+/// logs, since its detail (addresses tried, resolver and TLS messages) would describe
+/// the network the workers run in.
+fn describe_delivery_error(e: &reqwest::Error) -> &'static str {
+    if e.is_timeout() {
+        "Endpoint did not respond in time"
+    } else if e.is_connect() {
+        "Could not connect to the endpoint"
+    } else {
+        "Request to the endpoint failed"
+    }
+}
+
+/// Starts `workers` delivery loops that drain the queue concurrently.
+///
+/// Claims use `FOR UPDATE SKIP LOCKED`, so workers in this or any other process
+/// never pick up the same delivery twice.
+pub fn spawn_delivery_workers(db: Arc<Database>, config: Arc<Config>, cipher: SecretCipher, workers: usize, shutdown: &Shutdown) {
+    // Redirects are not followed, as they could lead anywhere
+    let client = reqwest::Client::builder()
+        .timeout(StdDuration::from_secs(DELIVERY_TIMEOUT_SECS))
+        .redirect(reqwest::redirect::Policy::none())
+        .dns_resolver(Arc::new(PublicResolver))
+        .build()
+        .expect("Failed to build webhook HTTP client");
+    
+    // Each cluster has its own queue; regional deliveries never leave their region's workers
+    for pool in db.pools() {
+        for worker_id in 0..workers.max(1) {
+            let worker = DeliveryWorker {
+                db: db.clone(),
+                pool: pool.clone(),
+                config: config.clone(),
+                cipher: cipher.clone(),
+                client: client.clone(),
+            };
+            
+            shutdown.spawn(|shutdown| async move {
+                info!("Webhook delivery worker {} started", worker_id);
+                worker.run(shutdown).await;
+            });
+        }
+    }
+}
+
+struct DeliveryWorker {
+    db: Arc<Database>,
+    pool: PgPool,
+    config: Arc<Config>,
+    cipher: SecretCipher,
+    client: reqwest::Client,
+}
+
+impl DeliveryWorker {
+    /// Finishes the delivery in hand on shutdown but claims no more; the rest stay
+    /// queued for the next process.
+    async fn run(&self, mut shutdown: Shutdown) {
+        while !shutdown.is_requested() {
+            let idle = match self.claim_next().await {
+                Ok(Some(delivery)) => {
+                    if let Err(e) = self.deliver(delivery).await {
+                        error!("Webhook delivery failed to complete: {}", e);
+                    }
+                    false
+                }
+                Ok(None) => true,
+                Err(e) => {
+                    error!("Failed to claim webhook delivery: {}", e);
+                    true
+                }
+            };
+            
+            if idle && !shutdown.sleep(StdDuration::from_millis(IDLE_POLL_INTERVAL_MS)).await {
+                break;
+            }
+        }
+    }
+    
+    /// Leases the oldest due delivery. A lease that expires (e.g. the worker crashed
+    /// mid-request) makes the delivery due again.
+    async fn claim_next(&self) -> Result<Option<WebhookDelivery>, DefiantError> {
+        let delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, attempts = attempts + 1, next_attempt_at = $2
+            WHERE id = (
+                SELECT id FROM webhook_deliveries
+                WHERE status IN ('pending', 'in_progress', 'failed') AND next_attempt_at <= NOW()
+                ORDER BY next_attempt_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+            "#,
+            WebhookDeliveryStatus::InProgress as WebhookDeliveryStatus,
+            Utc::now() + Duration::seconds(CLAIM_LEASE_SECS),
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(delivery)
+    }
+    
+    async fn deliver(&self, delivery: WebhookDelivery) -> Result<(), DefiantError> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"SELECT * FROM webhooks WHERE id = $1"#,
+            delivery.webhook_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        
+        // Anything still queued for a disabled endpoint goes straight to the dead-letter state
+        if !webhook.active {
+            return self.dead_letter(&delivery, None, "Endpoint disabled").await;
+        }
+        
+        // Failing before the request counts as a failed attempt, so the delivery is
+        // retried on schedule rather than on every poll
+        let payload = match self.render_payload(&delivery, &webhook).await {
+            Ok(payload) => payload,
+            Err(e) => {
+                error!("Failed to render event {} for webhook delivery {}: {}", delivery.event_id, delivery.id, e);
+                return self.mark_failed(&delivery, None, "Event payload could not be rendered").await;
+            }
+        };
+        // No retry would open the secret, so the delivery is dead-lettered at once
+        let secrets = match self.signing_secrets(&webhook) {
+            Ok(secrets) => secrets,
+            Err(e) => {
+                error!("Failed to open the signing secret of webhook {}: {}", webhook.id, e);
+                return self.dead_letter(&delivery, None, "Endpoint signing secret could not be read").await;
+            }
+        };
+        let secrets: Vec<&str> = secrets.iter().map(String::as_str).collect();
+        let signature = sign_payload_with_secrets(&secrets, Utc::now().timestamp(), &payload);
+        
+        // Endpoints registered before URLs were checked fail like unreachable ones, so
+        // they are disabled in time and the merchant told
+        if let Err(DefiantError::ValidationError(reason)) = parse_endpoint_url(&webhook.url) {
+            let reason = reason.trim_start_matches("url: ");
+            warn!("Webhook {} has a disallowed URL: {}", webhook.id, reason);
+            self.record_attempt(&delivery, &payload, None, None, 0, Some(reason)).await?;
+            self.mark_failed(&delivery, None, reason).await?;
+            return self.record_endpoint_failure(&webhook).await;
+        }
+        
+        let started = Instant::now();
+        let result = self.client
+            .post(&webhook.url)
+            .header("Content-Type", "application/json")
+            .header(SIGNATURE_HEADER, signature)
+            .header(VERSION_HEADER, webhook.api_version.as_str())
+            .body(payload.clone())
+            .send()
+            .await;
+        
+        match result {
+            Ok(response) => {
+                let status = response.status();
+                let body = read_truncated(response).await;
+                let latency_ms = started.elapsed().as_millis() as i32;
+                self.record_attempt(&delivery, &payload, Some(status.as_u16() as i32), Some(&body), latency_ms, None).await?;
+                
+                if status.is_success() {
+                    self.mark_succeeded(&delivery, status.as_u16() as i32).await?;
+                    sqlx::query!(
+                        r#"
+                        UPDATE webhooks
+                        SET last_triggered_at = NOW(), consecutive_failures = 0, failing_since = NULL
+                        WHERE id = $1
+                        "#,
+                        webhook.id,
+                    )
+                    .execute(&self.pool)
+                    .await?;
+                } else {
+                    let status = status.as_u16() as i32;
+                    warn!("Webhook {} responded {} for event {}", webhook.id, status, delivery.event_id);
+                    self.mark_failed(&delivery, Some(status), &format!("Endpoint responded with HTTP {}", status)).await?;
+                    self.record_endpoint_failure(&webhook).await?;
+                }
+            }
+            Err(e) => {
+                let latency_ms = started.elapsed().as_millis() as i32;
+                warn!("Webhook {} unreachable for event {}: {}", webhook.id, delivery.event_id, e);
+                let error = describe_delivery_error(&e);
+                self.record_attempt(&delivery, &payload, None, None, latency_ms, Some(error)).await?;
+                self.mark_failed(&delivery, None, error).await?;
+                self.record_endpoint_failure(&webhook).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// The event as the endpoint's pinned API version renders it.
+    async fn render_payload(&self, delivery: &WebhookDelivery, webhook: &Webhook) -> Result<String, DefiantError> {
+        let event = sqlx::query_as!(
+            Event,
+            r#"SELECT id, merchant_id, type AS event_type, data, api_version, livemode, created_at FROM events WHERE id = $1"#,
+            delivery.event_id,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        
+        let rendered = event_renderer::render(&event, &webhook.api_version)?;
+        serde_json::to_string(&rendered).map_err(|_| DefiantError::InternalError)
+    }
+    
+    /// The current secret, then the previous one while its rotation overlap lasts.
+    fn signing_secrets(&self, webhook: &Webhook) -> Result<Vec<String>, DefiantError> {
+        let mut secrets = vec![self.cipher.open(&webhook.encrypted_secret)?];
+        if let (Some(sealed), Some(expires_at)) = (&webhook.previous_encrypted_secret, webhook.previous_secret_expires_at) {
+            if expires_at > Utc::now() {
+                secrets.push(self.cipher.open(sealed)?);
+            }
+        }
+        Ok(secrets)
+    }
+    
+    async fn record_attempt(
+        &self,
+        delivery: &WebhookDelivery,
+        request_body: &str,
+        response_status: Option<i32>,
+        response_body: Option<&str>,
+        latency_ms: i32,
+        error: Option<&str>,
+    ) -> Result<(), DefiantError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_delivery_attempts (
+                merchant_id, delivery_id, attempt_number, request_body,
+                response_status, response_body, latency_ms, error
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            delivery.merchant_id,
+            delivery.id,
+            delivery.attempts,
+            request_body,
+            response_status,
+            response_body,
+            latency_ms,
+            error,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn mark_succeeded(&self, delivery: &WebhookDelivery, response_status: i32) -> Result<(), DefiantError> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, last_response_status = $2, last_error = NULL, delivered_at = NOW()
+            WHERE id = $3
+            "#,
+            WebhookDeliveryStatus::Succeeded as WebhookDeliveryStatus,
+            response_status,
+            delivery.id,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Schedules the next attempt, or dead-letters the delivery once the retry window is spent.
+    async fn mark_failed(
+        &self,
+        delivery: &WebhookDelivery,
+        response_status: Option<i32>,
+        error: &str,
+    ) -> Result<(), DefiantError> {
+        let next_attempt_at = Utc::now() + retry_delay(delivery.attempts);
+        if next_attempt_at > delivery.created_at + Duration::days(RETRY_WINDOW_DAYS) {
+            warn!("Webhook delivery {} dead-lettered after {} attempts", delivery.id, delivery.attempts);
+            return self.dead_letter(delivery, response_status, error).await;
+        }
+        
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, last_response_status = $2, last_error = $3, next_attempt_at = $4
+            WHERE id = $5
+            "#,
+            WebhookDeliveryStatus::Failed as WebhookDeliveryStatus,
+            response_status,
+            error,
+            next_attempt_at,
+            delivery.id,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    async fn dead_letter(
+        &self,
+        delivery: &WebhookDelivery,
+        response_status: Option<i32>,
+        error: &str,
+    ) -> Result<(), DefiantError> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $1, last_response_status = COALESCE($2, last_response_status), last_error = $3
+            WHERE id = $4
+            "#,
+            WebhookDeliveryStatus::DeadLettered as WebhookDeliveryStatus,
+            response_status,
+            error,
+            delivery.id,
+        )
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(())
+    }
+    
+    /// Tracks the endpoint's failure streak and disables it once failures are sustained.
+    async fn record_endpoint_failure(&self, webhook: &Webhook) -> Result<(), DefiantError> {
+        let disable_before = Utc::now() - Duration::days(AUTO_DISABLE_AFTER_DAYS);
+        let reason = format!(
+            "Disabled after {}+ consecutive failed deliveries over {} days",
+            AUTO_DISABLE_MIN_FAILURES, AUTO_DISABLE_AFTER_DAYS
+        );
+        
+        // Only the update that flips `active` returns a row, so the merchant is notified once
+        let disabled = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET consecutive_failures = consecutive_failures + 1,
+                failing_since = COALESCE(failing_since, NOW()),
+                active = CASE
+                    WHEN active AND consecutive_failures + 1 >= $2 AND failing_since <= $3 THEN false
+                    ELSE active
+                END,
+                disabled_at = CASE
+                    WHEN active AND consecutive_failures + 1 >= $2 AND failing_since <= $3 THEN NOW()
+                    ELSE disabled_at
+                END,
+                disabled_reason = CASE
+                    WHEN active AND consecutive_failures + 1 >= $2 AND failing_since <= $3 THEN $4
+                    ELSE disabled_reason
+                END
+            WHERE id = $1
+            RETURNING *
+            "#,
+            webhook.id,
+            AUTO_DISABLE_MIN_FAILURES,
+            disable_before,
+            reason,
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        
+        if webhook.active && !disabled.active {
+            warn!("Webhook endpoint {} auto-disabled", disabled.id);
+            self.notify_disabled(&disabled).await;
+        }
+        
+        Ok(())
+    }
+    
+    async fn notify_disabled(&self, webhook: &Webhook) {
+        let merchant_email = match sqlx::query_scalar!(
+            r#"SELECT email FROM merchants WHERE id = $1"#,
+            webhook.merchant_id,
+        )
+        .fetch_one(&self.db.pool)
+        .await
+        {
+            Ok(email) => email,
+            Err(e) => {
+                error!("Failed to look up merchant for disabled webhook {}: {}", webhook.id, e);
+                return;
+            }
+        };
+        
+        let email_service = EmailService::new(self.db.clone(), self.config.clone());
+        let body = disabled_notice(&webhook.url);
+        if let Err(e) = email_service.send(&merchant_email, "Your Defiant webhook endpoint was disabled", body).await {
+            error!("Failed to send webhook disabled notice for {}: {}", webhook.id, e);
+        }
+    }
+}
+
+/// The URL is the merchant's own input, so it is escaped like any other in an email.
+fn disabled_notice(url: &str) -> String {
+    format!(
+        "<p>Your webhook endpoint <strong>{}</strong> has been disabled because deliveries to it \
+         have failed continuously for more than {} days.</p>\
+         <p>Events that could not be delivered have been kept. Fix the endpoint and re-enable it \
+         to resume deliveries.</p>",
+        html_escape(url), AUTO_DISABLE_AFTER_DAYS
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn endpoint_urls_must_be_https_and_public() {
+        assert!(parse_endpoint_url("https://hooks.example.com/defiant").is_ok());
+        assert!(parse_endpoint_url("http://hooks.example.com/defiant").is_err());
+        for url in [
+            "https://127.0.0.1/",
+            "https://10.0.0.8/",
+            "https://172.16.4.1/",
+            "https://192.168.1.1/",
+            "https://169.254.169.254/latest/meta-data/",
+            "https://0.0.0.0/",
+            "https://[::1]/",
+            "https://[fd00::1]/",
+            "https://[fe80::1]/",
+            "https://[::ffff:169.254.169.254]/",
+        ] {
+            assert!(parse_endpoint_url(url).is_err(), "{} should be refused", url);
+        }
+    }
+    
+    #[tokio::test]
+    async fn hosts_resolving_to_loopback_are_refused() {
+        assert!(ensure_public_endpoint("https://localhost/hooks").await.is_err());
+    }
+    
+    #[test]
+    fn disabled_notice_escapes_the_endpoint_url() {
+        let body = disabled_notice("https://hooks.example.com/?q=<script>alert(1)</script>&x=\"y\"");
+        assert!(body.contains("https://hooks.example.com/?q=&lt;script&gt;alert(1)&lt;/script&gt;&amp;x=&quot;y&quot;"));
+        assert!(!body.contains("<script>"));
+    }
+}
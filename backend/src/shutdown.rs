@@ -0,0 +1,92 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, Interval};
+use tracing::{info, warn};
+
+/// Lets background workers finish what they are doing when the process stops.
+///
+/// Workers are started with `spawn` and wait between rounds with `tick` or `sleep`,
+/// which return `false` once `drain` has been called so the worker can return instead
+/// of starting more work. Anything a worker had leased when it is cut off at the
+/// deadline is picked up again, by this process after a restart or by another one.
+#[derive(Clone)]
+pub struct Shutdown {
+    requested: watch::Receiver<bool>,
+    sender: Arc<watch::Sender<bool>>,
+    tasks: Arc<Mutex<Vec<JoinHandle<()>>>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        let (sender, requested) = watch::channel(false);
+        Self {
+            requested,
+            sender: Arc::new(sender),
+            tasks: Arc::default(),
+        }
+    }
+    
+    /// Starts a worker that `drain` waits for, handing it its own copy of the signal.
+    pub fn spawn<F, Fut>(&self, worker: F)
+    where
+        F: FnOnce(Shutdown) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(worker(self.clone()));
+        self.tasks.lock().expect("worker list poisoned").push(handle);
+    }
+    
+    pub fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+    
+    /// Waits for the interval's next tick. `false` means stop.
+    pub async fn tick(&mut self, interval: &mut Interval) -> bool {
+        let stopped = tokio::select! {
+            _ = interval.tick() => false,
+            _ = self.requested.wait_for(|requested| *requested) => true,
+        };
+        !stopped && !self.is_requested()
+    }
+    
+    /// Sleeps for `duration`, waking early when shutdown is requested. `false` means stop.
+    pub async fn sleep(&mut self, duration: Duration) -> bool {
+        let stopped = tokio::select! {
+            _ = tokio::time::sleep(duration) => false,
+            _ = self.requested.wait_for(|requested| *requested) => true,
+        };
+        !stopped && !self.is_requested()
+    }
+    
+    /// Asks every worker to stop and waits up to `deadline` for them to return, then
+    /// aborts the rest.
+    pub async fn drain(&self, deadline: Duration) {
+        self.sender.send_replace(true);
+        let workers = std::mem::take(&mut *self.tasks.lock().expect("worker list poisoned"));
+        info!("Waiting up to {}s for {} background workers to finish", deadline.as_secs(), workers.len());
+        
+        let deadline = Instant::now() + deadline;
+        let mut aborted = 0;
+        for mut worker in workers {
+            if tokio::time::timeout_at(deadline, &mut worker).await.is_err() {
+                worker.abort();
+                aborted += 1;
+            }
+        }
+        
+        if aborted > 0 {
+            warn!("{} background workers were still running at the shutdown deadline and were aborted", aborted);
+        } else {
+            info!("Background workers finished");
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use actix_web::{dev::{ServiceRequest, ServiceResponse}, body::MessageBody, Error, HttpMessage};
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpan, RootSpanBuilder};
+use tracing_subscriber::EnvFilter;
+use uuid::Uuid;
+
+use crate::config::{Config, Environment, LogFormat};
+
+/// Installs the global subscriber: `log_level` as the default, `log_filters` per
+/// module, written as JSON lines unless configured otherwise.
+pub fn init(config: &Config) {
+    let mut filter = EnvFilter::new(&config.log_level);
+    for (module, level) in &config.log_filters {
+        match format!("{}={}", module, level).parse() {
+            Ok(directive) => filter = filter.add_directive(directive),
+            Err(e) => eprintln!("Ignoring log filter {}={}: {}", module, level, e),
+        }
+    }
+    
+    let format = config.log_format.unwrap_or(match config.environment {
+        Environment::Development => LogFormat::Text,
+        _ => LogFormat::Json,
+    });
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        // Every enclosing span's fields, so lines logged while serving a request carry
+        // its request_id, route and merchant_id
+        LogFormat::Json => builder.json().with_current_span(false).with_span_list(true).init(),
+        LogFormat::Text => builder.init(),
+    }
+}
+
+/// The span each request is served in: tracing-actix-web's fields (request_id,
+/// http.route, http.method, status and so on) plus the merchant, which is filled in
+/// once authentication resolves the caller.
+pub struct RequestSpan;
+
+impl RootSpanBuilder for RequestSpan {
+    fn on_request_start(request: &ServiceRequest) -> Span {
+        tracing_actix_web::root_span!(request, merchant_id = tracing::field::Empty)
+    }
+    
+    fn on_request_end<B: MessageBody>(span: Span, outcome: &Result<ServiceResponse<B>, Error>) {
+        DefaultRootSpanBuilder::on_request_end(span, outcome);
+    }
+}
+
+/// Adds the caller's merchant to the request's span.
+pub fn record_merchant(req: &impl HttpMessage, merchant_id: Uuid) {
+    if let Some(span) = req.extensions().get::<RootSpan>() {
+        span.record("merchant_id", tracing::field::display(merchant_id));
+    }
+}
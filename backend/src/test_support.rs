@@ -0,0 +1,151 @@
+//! Setup shared by tests that drive the API through the auth middleware against a
+//! database from `#[sqlx::test]`. Redis is only reached by tests that need it, at
+//! `REDIS_URL` or a local default.
+
+use std::sync::Arc;
+use actix_web::{body::MessageBody, dev::{ServiceFactory, ServiceRequest, ServiceResponse}, web, App};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{api, config::Config, custom_middleware::auth::Authentication, db::{Database, hash_api_key}, services::{event_router::EventRouter, webhook_service::SecretCipher}, AppState};
+
+const TEST_KEY: &str = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f";
+
+pub fn config() -> Config {
+    serde_json::from_value(serde_json::json!({
+        "host": "127.0.0.1",
+        "port": 8080,
+        "database_url": "postgres://localhost/defiant_test",
+        "redis_url": std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".into()),
+        "jwt_secret": "test-jwt-secret",
+        "jwt_expiration": 900,
+        "jwt_refresh_expiration": 86400,
+        "cors_origin": "*",
+        "workers": 1,
+        "log_level": "warn",
+        "environment": "Development",
+        "webhook_encryption_key": TEST_KEY,
+        "mfa_encryption_key": TEST_KEY,
+        "request_signing_encryption_key": TEST_KEY,
+        "webhook_workers": 0,
+        "card_fingerprint_key": "test-fingerprint-key",
+        "card_token_encryption_key": TEST_KEY,
+        "pii_master_keys": { "test": TEST_KEY },
+        "pii_master_key_id": "test",
+        "pii_blind_index_key": "test-blind-index-key",
+        "invoice_link_key": "test-invoice-link-key",
+        "hosted_base_url": "https://pay.example.com",
+        "dashboard_base_url": "https://dashboard.example.com",
+        "bitcoin_confirmations": 1,
+        "ethereum_confirmations": 1,
+        "smtp_host": "localhost",
+        "smtp_port": 25,
+        "smtp_username": "",
+        "smtp_password": "",
+        "from_email": "noreply@example.com",
+        "rate_limit_requests": 1000,
+        "trusted_rate_limit_requests": 1000,
+        "rate_limit_period": 60,
+        "trust_proxy_headers": true,
+    }))
+    .expect("test config is complete")
+}
+
+pub fn state(pool: PgPool) -> web::Data<AppState> {
+    let config = config();
+    let redis = Arc::new(redis::Client::open(config.redis_url.clone()).expect("valid Redis URL"));
+    web::Data::new(AppState {
+        db: Arc::new(Database::from_pool(pool)),
+        config: Arc::new(config),
+        redis: redis.clone(),
+        events: EventRouter::start(redis),
+    })
+}
+
+/// The API as `main` serves it, behind the auth middleware.
+pub fn app(
+    state: web::Data<AppState>,
+) -> App<impl ServiceFactory<ServiceRequest, Config = (), Response = ServiceResponse<impl MessageBody>, Error = actix_web::Error, InitError = ()>> {
+    App::new()
+        .app_data(state)
+        .wrap(Authentication)
+        .configure(api::configure)
+}
+
+pub async fn merchant(pool: &PgPool) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO merchants (id, name, email) VALUES ($1, $2, $3)"#,
+        id,
+        "Test merchant",
+        format!("{}@example.com", id),
+    )
+    .execute(pool)
+    .await
+    .expect("merchant inserted");
+    id
+}
+
+/// A full-access test-mode secret key, limited to `allowed_ips` when given.
+pub async fn secret_key(pool: &PgPool, merchant_id: Uuid, allowed_ips: Option<Vec<String>>) -> String {
+    let key = format!("sk_test_{}", Uuid::new_v4().simple());
+    sqlx::query!(
+        r#"
+        INSERT INTO api_keys (merchant_id, key_hash, key_prefix, name, livemode, allowed_ips)
+        VALUES ($1, $2, $3, $4, false, $5)
+        "#,
+        merchant_id,
+        hash_api_key(&key),
+        &key[..12],
+        "Test key",
+        allowed_ips.as_deref(),
+    )
+    .execute(pool)
+    .await
+    .expect("API key inserted");
+    key
+}
+
+/// A test-mode secret key holding only `scopes`.
+pub async fn restricted_key(pool: &PgPool, merchant_id: Uuid, scopes: &[&str]) -> String {
+    let key = format!("sk_test_{}", Uuid::new_v4().simple());
+    sqlx::query!(
+        r#"
+        INSERT INTO api_keys (merchant_id, key_hash, key_prefix, name, permissions, livemode)
+        VALUES ($1, $2, $3, $4, $5, false)
+        "#,
+        merchant_id,
+        hash_api_key(&key),
+        &key[..12],
+        "Restricted test key",
+        serde_json::json!({ "scopes": scopes }),
+    )
+    .execute(pool)
+    .await
+    .expect("API key inserted");
+    key
+}
+
+/// The id of a key made by `secret_key` or `restricted_key`.
+pub async fn key_id(pool: &PgPool, key: &str) -> Uuid {
+    sqlx::query_scalar!("SELECT id FROM api_keys WHERE key_hash = $1", hash_api_key(key))
+        .fetch_one(pool)
+        .await
+        .expect("API key exists")
+}
+
+/// Turns request signing on for the merchant, returning the secret requests must be
+/// signed with.
+pub async fn enable_request_signing(pool: &PgPool, merchant_id: Uuid) -> String {
+    let secret = format!("rss_{}", Uuid::new_v4().simple());
+    let sealed = SecretCipher::from_hex(TEST_KEY).and_then(|cipher| cipher.seal(&secret)).expect("secret sealed");
+    sqlx::query!(
+        r#"UPDATE merchants SET request_signing_secret = $2, request_signing_enabled_at = NOW() WHERE id = $1"#,
+        merchant_id,
+        sealed,
+    )
+    .execute(pool)
+    .await
+    .expect("request signing enabled");
+    secret
+}
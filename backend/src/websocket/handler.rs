@@ -0,0 +1,153 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use actix::{Actor, ActorContext, AsyncContext, StreamHandler};
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use uuid::Uuid;
+
+use crate::{models::{RenderedEvent, StreamClientMessage, StreamServerMessage, StreamNack, WebSocketQuery}, errors::DefiantError, db::AuthenticatedKey, api::v1::{events::stream_key, permissions::require_scope}, services::{event_service::EventService, event_router::MerchantEvents, event_stream::{Liveness, TopicSubscriptions, Transport}}, AppState};
+
+/// Upgrades to a WebSocket streaming the events of the key's merchant and mode on the
+/// connection's topics, one `{"event": {...}}` message each. The key is checked by the
+/// auth middleware on the handshake; browsers, which cannot set headers on it, pass
+/// it as `?token=`.
+///
+/// Clients change topics with `{"subscribe": [...]}` and `{"unsubscribe": [...]}`,
+/// each answered with an `ack` of the topics now held or a `nack` saying why nothing
+/// changed. The server pings every heartbeat interval and closes connections that
+/// send nothing, pongs included, for the idle timeout.
+///
+/// A client reconnecting with `last_event_id` is first sent the events it missed on
+/// its starting topics. Live events are subscribed to before those are read, so
+/// events published in between are sent once, from the replay.
+#[get("")]
+pub async fn websocket_route(
+    req: HttpRequest,
+    stream: web::Payload,
+    query: web::Query<WebSocketQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, DefiantError> {
+    require_scope(&req, &state, "events:read").await?;
+    let key = stream_key(&req, &state).await?;
+    
+    let mut subscriptions = TopicSubscriptions::default();
+    if let Some(topics) = &query.topics {
+        let topics: Vec<String> = topics.split(',').map(|topic| topic.trim().to_string()).collect();
+        subscriptions.subscribe(&topics, &key)?;
+    }
+    
+    let live = state.events.subscribe(key.merchant_id).await;
+    let replayed = match query.last_event_id {
+        Some(last_event_id) => {
+            EventService::new(state.db.clone())
+                .events_since(&key, last_event_id, &subscriptions.topics())
+                .await?
+        }
+        None => Vec::new(),
+    };
+    
+    let liveness = Liveness::open(Transport::WebSocket, &state.config.streaming);
+    let socket = EventSocket {
+        key,
+        subscriptions,
+        liveness,
+        replayed_ids: replayed.iter().map(|event| event.id).collect(),
+        replayed,
+        live: Some(live),
+    };
+    
+    ws::start(socket, &req, stream).map_err(|_| DefiantError::BadRequest("A WebSocket upgrade is required".into()))
+}
+
+/// One connection's actor. Live events arrive as a stream of their own next to the
+/// client's messages.
+struct EventSocket {
+    key: AuthenticatedKey,
+    subscriptions: TopicSubscriptions,
+    liveness: Liveness,
+    /// Sent when the connection starts, then skipped if they also arrive live
+    replayed: Vec<RenderedEvent>,
+    replayed_ids: HashSet<Uuid>,
+    live: Option<MerchantEvents>,
+}
+
+struct Live(Arc<RenderedEvent>);
+
+impl EventSocket {
+    fn send(&self, message: &StreamServerMessage, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.text(serde_json::json!(message).to_string());
+    }
+}
+
+impl Actor for EventSocket {
+    type Context = ws::WebsocketContext<Self>;
+    
+    fn started(&mut self, ctx: &mut Self::Context) {
+        for event in std::mem::take(&mut self.replayed) {
+            self.send(&StreamServerMessage::Event(event), ctx);
+        }
+        
+        ctx.run_interval(self.liveness.heartbeat_interval, |socket, ctx| {
+            if socket.liveness.idle() {
+                socket.liveness.reap();
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+        if let Some(live) = self.live.take() {
+            ctx.add_stream(futures_util::stream::unfold(live, |mut live| async move {
+                live.recv().await.map(|event| (Live(event), live))
+            }));
+        }
+    }
+}
+
+impl StreamHandler<Live> for EventSocket {
+    fn handle(&mut self, Live(event): Live, ctx: &mut Self::Context) {
+        if event.livemode != self.key.livemode
+            || !self.subscriptions.matches(&event.event_type)
+            || self.replayed_ids.contains(&event.id)
+        {
+            return;
+        }
+        self.send(&StreamServerMessage::Event((*event).clone()), ctx);
+    }
+    
+    /// Events may have been missed, so the client is told to reconnect.
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        ctx.close(Some(ws::CloseReason {
+            code: ws::CloseCode::Again,
+            description: Some("Live events were interrupted; reconnect to resume".into()),
+        }));
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EventSocket {
+    fn handle(&mut self, message: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        if message.is_ok() {
+            self.liveness.seen();
+        }
+        match message {
+            Ok(ws::Message::Text(text)) => {
+                let reply = match serde_json::from_str::<StreamClientMessage>(&text) {
+                    Ok(message) => self.subscriptions.apply(message, &self.key),
+                    Err(_) => StreamServerMessage::Nack(StreamNack {
+                        topic: None,
+                        code: "invalid_message",
+                        message: "Expected {\"subscribe\": [...]} or {\"unsubscribe\": [...]}".into(),
+                    }),
+                };
+                self.send(&reply, ctx);
+            }
+            Ok(ws::Message::Ping(payload)) => ctx.pong(&payload),
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(_) => {}
+            Err(_) => ctx.stop(),
+        }
+    }
+}
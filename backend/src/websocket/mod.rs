@@ -0,0 +1,4 @@
+//! The WebSocket transport for live events, served at `/ws`. Live events reach each
+//! connection through the `EventRouter`, as they do Server-Sent Events streams.
+
+pub mod handler;